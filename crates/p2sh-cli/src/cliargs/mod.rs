@@ -0,0 +1,510 @@
+use clap::{Parser, Subcommand};
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::builtins::bpf::BpfExpr;
+use crate::builtins::capture::TstampType;
+use crate::builtins::csv::OutputFormat;
+use crate::builtins::pcap::{CorruptPolicy, TsCorrection};
+use crate::builtins::pcapfiles::ReadOrder;
+use crate::parser::rules::parse_duration;
+use p2sh_dissect::ports::{parse_port_mapping, AppProto};
+
+pub mod tests;
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about,
+    long_about,
+    args_conflicts_with_subcommands = true
+)]
+struct Args {
+    #[command(subcommand)]
+    subcmd: Option<SubCmd>,
+    /// Program via command line argument
+    #[arg(short, long)]
+    command: Option<String>,
+    /// Script to run
+    script: Option<String>,
+    /// Script arguments
+    args: Vec<String>,
+    /// Skip pcap output in filter mode; defaults to false
+    #[arg(short, long, default_value_t = false)]
+    skip_pcap: bool,
+    /// Treat the use of deprecated builtins as errors; defaults to false
+    #[arg(long, default_value_t = false)]
+    deny_deprecated: bool,
+    /// Action on finding a corrupt packet record in filter mode
+    #[arg(long, value_enum, default_value_t = CorruptPolicy::Error)]
+    on_corrupt: CorruptPolicy,
+    /// Override the snaplen of the input pcap in filter mode
+    #[arg(long)]
+    snaplen: Option<u32>,
+    /// Largest packet record read from the input pcap in filter mode;
+    /// larger records are handled as set by '--on-corrupt' without being
+    /// read in full; defaults to 262144
+    #[arg(long, value_name = "BYTES")]
+    max_packet_size: Option<u32>,
+    /// Read the input pcap from a file instead of stdin in filter mode;
+    /// may be repeated to read several files one after the other. A
+    /// directory or a pattern such as 'captures/*.pcap' reads the files
+    /// in it or matching it
+    #[arg(short, long, value_name = "FILE", conflicts_with = "interface")]
+    read: Vec<String>,
+    /// Order in which the files in a directory or matching a pattern
+    /// given to '--read' are read
+    #[arg(long, value_enum, default_value_t = ReadOrder::Name)]
+    read_order: ReadOrder,
+    /// Count the packets in 'NP' from 1 again at the start of each file
+    /// read; defaults to false
+    #[arg(long, default_value_t = false)]
+    reset_np: bool,
+    /// Capture packets live from a network interface instead of stdin
+    #[arg(short, long)]
+    interface: Option<String>,
+    /// Stop the live capture after this long, e.g. 300 or 5m; the 'end'
+    /// filter is run before exiting
+    #[arg(long, value_name = "SECONDS", value_parser = parse_interval, requires = "interface")]
+    duration: Option<Duration>,
+    /// Clock that stamps the packets of a live capture
+    #[arg(long, value_enum, default_value_t = TstampType::Host, requires = "interface")]
+    time_stamp_type: TstampType,
+    /// Add this offset to the timestamps of the packets read before the
+    /// script sees them, e.g. -1.5ms or 2s
+    #[arg(long, value_name = "DURATION", value_parser = parse_offset, allow_hyphen_values = true)]
+    ts_offset: Option<i64>,
+    /// Correct the timestamps of a clock that runs slow by this many parts
+    /// per million, or fast if negative, from the first packet on
+    #[arg(long, value_name = "PPM", allow_hyphen_values = true)]
+    ts_skew: Option<f64>,
+    /// Compile the script again when its file changes or on SIGHUP during
+    /// a live capture, keeping the values of the globals; defaults to false
+    #[arg(long, default_value_t = false, requires = "interface")]
+    reload: bool,
+    /// Serve the counters of the filters in the Prometheus format over
+    /// HTTP at /metrics on this port of the loopback interface, or at this
+    /// address such as 0.0.0.0:9100, in filter mode
+    #[arg(long, value_name = "[HOST:]PORT", value_parser = parse_metrics_addr)]
+    metrics_port: Option<SocketAddr>,
+    /// Skip the packets that do not match a pcap-filter expression in
+    /// filter mode, e.g. "tcp port 443"
+    #[arg(long, value_parser = BpfExpr::compile)]
+    bpf: Option<BpfExpr>,
+    /// Resync on the next plausible packet record after a corrupt one
+    #[arg(long, default_value_t = false)]
+    repair: bool,
+    /// Only allow ASCII characters in identifiers; defaults to false
+    #[arg(long, default_value_t = false)]
+    ascii_ident: bool,
+    /// Replay a transcript of a REPL session and check the outputs
+    #[arg(long, value_name = "TRANSCRIPT")]
+    replay_session: Option<String>,
+    /// Decode the udp or tcp payload on a port as a protocol, e.g.
+    /// 5353=dns; may be repeated
+    #[arg(long, value_name = "PORT=PROTO", value_parser = parse_port_mapping)]
+    decode_as: Vec<(u16, Option<AppProto>)>,
+    /// Detect the protocol of the udp or tcp payload on ports that are
+    /// not mapped to one; defaults to false
+    #[arg(long, default_value_t = false)]
+    heuristics: bool,
+    /// Report the time spent by each dissector parsing the layers of
+    /// packets on exit; defaults to false
+    #[arg(long, default_value_t = false)]
+    profile_dissect: bool,
+    /// Return addresses as strings and payloads as arrays of bytes from
+    /// the packet properties as older versions did; defaults to false
+    #[arg(long, default_value_t = false)]
+    legacy_props: bool,
+    /// Save the globals to the checkpoint file at this interval in filter
+    /// mode, e.g. 5m
+    #[arg(long, value_name = "INTERVAL", value_parser = parse_interval)]
+    checkpoint: Option<Duration>,
+    /// Checkpoint file to save the globals to and resume from
+    #[arg(long, value_name = "FILE", default_value = "p2sh.checkpoint")]
+    checkpoint_file: String,
+    /// Restore the globals from the checkpoint file before running the
+    /// filters; defaults to false
+    #[arg(long, default_value_t = false)]
+    resume: bool,
+    /// Always compile the script instead of loading the bytecode saved by
+    /// an earlier run from ~/.cache/p2sh; defaults to false
+    #[arg(long, default_value_t = false)]
+    no_cache: bool,
+    /// Compile the script to a file of bytecode to run using '--exec'
+    /// instead of running it
+    #[arg(long, value_name = "FILE", conflicts_with = "exec")]
+    compile: Option<String>,
+    /// Run the bytecode of a script compiled using '--compile'; the
+    /// arguments that follow are passed to the script
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["command", "reload"])]
+    exec: Option<String>,
+    /// Print the filter statements of the script without running it;
+    /// defaults to false
+    #[arg(long, default_value_t = false)]
+    list_filters: bool,
+    /// Run the script in the debugger, which pauses at the first line
+    /// and at breakpoints; defaults to false
+    #[arg(long, default_value_t = false)]
+    debug: bool,
+    /// Print the number of packets processed, corrupt and dropped by a
+    /// live capture to stderr at the end; defaults to false
+    #[arg(long, default_value_t = false)]
+    stats: bool,
+    /// Print the bytecode of the script with the source line of each
+    /// instruction without running it; defaults to false
+    #[arg(long, default_value_t = false)]
+    disasm: bool,
+    /// Log every call to a builtin function with its arguments and its
+    /// result to stderr; defaults to false
+    #[arg(long, default_value_t = false)]
+    trace_builtins: bool,
+    /// Format of the output of 'puts'; csv prints the arguments as a row
+    /// of comma separated values
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output_format: OutputFormat,
+    /// Look up the names of addresses for 'resolve' in /etc/hosts only,
+    /// without querying the name servers; defaults to false
+    #[arg(long, default_value_t = false)]
+    no_dns: bool,
+    /// Make the output reproducible: maps and sets are printed in order,
+    /// 'rand' is seeded and 'time' returns 0; defaults to false
+    #[arg(long, default_value_t = false)]
+    deterministic: bool,
+    /// Seed 'rand' with this number; implies '--deterministic'
+    #[arg(long, value_name = "N")]
+    seed: Option<u64>,
+    /// Report the number of live objects of each kind and the largest
+    /// arrays, maps and sets with the line defining their global on exit;
+    /// defaults to false
+    #[arg(long, default_value_t = false)]
+    debug_objects: bool,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum SubCmd {
+    /// List the property paths supported by the packet objects
+    Fields {
+        /// Packet object to list the properties of, e.g. tcp
+        proto: Option<String>,
+    },
+    /// Compare two pcap files packet by packet
+    Diff {
+        /// First pcap file
+        first: String,
+        /// Second pcap file
+        second: String,
+        /// Compare only the packet contents, not the timestamps
+        #[arg(long, default_value_t = false)]
+        ignore_time: bool,
+        /// Maximum difference between timestamps of equal packets, e.g. 10us
+        #[arg(long, value_parser = parse_tolerance, default_value = "0s")]
+        tolerance: u64,
+        /// Number of packets to look ahead for a match after a mismatch
+        #[arg(long, default_value_t = 1000)]
+        window: usize,
+    },
+    /// Run two scripts over a capture and list the packets matched by
+    /// only one of them
+    Ab {
+        /// First script, e.g. the rules before a change
+        first: String,
+        /// Second script, e.g. the rules after a change
+        second: String,
+        /// Pcap file to run the scripts over
+        capture: String,
+    },
+    /// Run the test scripts named *_test.p2
+    Test {
+        /// Test scripts or directories to search for them; defaults to the
+        /// current directory
+        paths: Vec<String>,
+    },
+}
+
+// Parse a duration such as '10us' into nanoseconds
+fn parse_tolerance(s: &str) -> Result<u64, String> {
+    match parse_duration(s) {
+        Some(secs) if secs >= 0.0 => Ok((secs * 1e9).round() as u64),
+        _ => Err(format!("invalid duration '{}'", s)),
+    }
+}
+
+// Parse an offset in nanoseconds such as '-1.5ms', or a number of seconds
+fn parse_offset(s: &str) -> Result<i64, String> {
+    let (sign, abs) = match s.strip_prefix('-') {
+        Some(abs) => (-1.0, abs),
+        None => (1.0, s),
+    };
+    match abs.parse::<f64>().ok().or_else(|| parse_duration(abs)) {
+        Some(secs) if secs.is_finite() => Ok((sign * secs * 1e9).round() as i64),
+        _ => Err(format!("invalid offset '{}'", s)),
+    }
+}
+
+// Parse the address to serve the metrics at, such as '[::]:9100', or a port
+// alone on the loopback interface so that they are not exposed by default
+fn parse_metrics_addr(s: &str) -> Result<SocketAddr, String> {
+    if let Ok(port) = s.parse::<u16>() {
+        return Ok(SocketAddr::from((Ipv4Addr::LOCALHOST, port)));
+    }
+    s.to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| format!("invalid address '{}'", s))
+}
+
+// Parse a non-zero interval such as '5m', or a number of seconds
+fn parse_interval(s: &str) -> Result<Duration, String> {
+    match s.parse::<f64>().ok().or_else(|| parse_duration(s)) {
+        Some(secs) if secs > 0.0 => Ok(Duration::from_secs_f64(secs)),
+        _ => Err(format!("invalid interval '{}'", s)),
+    }
+}
+
+pub struct CliArgs {
+    subcmd: Option<SubCmd>,
+    args: Vec<String>,
+    cmd: Option<String>,
+    skip_pcap: bool,
+    deny_deprecated: bool,
+    on_corrupt: CorruptPolicy,
+    snaplen: Option<u32>,
+    max_packet_size: Option<u32>,
+    read: Vec<String>,
+    read_order: ReadOrder,
+    reset_np: bool,
+    interface: Option<String>,
+    duration: Option<Duration>,
+    time_stamp_type: TstampType,
+    ts_offset: Option<i64>,
+    ts_skew: Option<f64>,
+    reload: bool,
+    metrics_addr: Option<SocketAddr>,
+    bpf: Option<BpfExpr>,
+    repair: bool,
+    ascii_ident: bool,
+    replay_session: Option<String>,
+    decode_as: Vec<(u16, Option<AppProto>)>,
+    heuristics: bool,
+    profile_dissect: bool,
+    legacy_props: bool,
+    checkpoint: Option<Duration>,
+    checkpoint_file: String,
+    resume: bool,
+    no_cache: bool,
+    compile: Option<String>,
+    exec: Option<String>,
+    list_filters: bool,
+    debug: bool,
+    stats: bool,
+    disasm: bool,
+    trace_builtins: bool,
+    output_format: OutputFormat,
+    no_dns: bool,
+    deterministic: bool,
+    seed: Option<u64>,
+    debug_objects: bool,
+}
+
+impl CliArgs {
+    pub fn new() -> Self {
+        let cliargs = Args::parse();
+        // Create args with script as first element. The compiled script
+        // run using '--exec' comes before them.
+        let mut args = Vec::new();
+        if let Some(exec) = cliargs.exec.clone() {
+            args.push(exec);
+        }
+        if let Some(script) = cliargs.script.clone() {
+            args.push(script);
+        }
+        // Extend args with script arguments
+        args.extend_from_slice(cliargs.args.as_slice());
+
+        Self {
+            subcmd: cliargs.subcmd,
+            args,
+            cmd: cliargs.command,
+            skip_pcap: cliargs.skip_pcap,
+            deny_deprecated: cliargs.deny_deprecated,
+            on_corrupt: cliargs.on_corrupt,
+            snaplen: cliargs.snaplen,
+            max_packet_size: cliargs.max_packet_size,
+            read: cliargs.read,
+            read_order: cliargs.read_order,
+            reset_np: cliargs.reset_np,
+            interface: cliargs.interface,
+            duration: cliargs.duration,
+            time_stamp_type: cliargs.time_stamp_type,
+            ts_offset: cliargs.ts_offset,
+            ts_skew: cliargs.ts_skew,
+            reload: cliargs.reload,
+            metrics_addr: cliargs.metrics_port,
+            bpf: cliargs.bpf,
+            repair: cliargs.repair,
+            ascii_ident: cliargs.ascii_ident,
+            replay_session: cliargs.replay_session,
+            decode_as: cliargs.decode_as,
+            heuristics: cliargs.heuristics,
+            profile_dissect: cliargs.profile_dissect,
+            legacy_props: cliargs.legacy_props,
+            checkpoint: cliargs.checkpoint,
+            checkpoint_file: cliargs.checkpoint_file,
+            resume: cliargs.resume,
+            no_cache: cliargs.no_cache,
+            compile: cliargs.compile,
+            exec: cliargs.exec,
+            list_filters: cliargs.list_filters,
+            debug: cliargs.debug,
+            stats: cliargs.stats,
+            disasm: cliargs.disasm,
+            trace_builtins: cliargs.trace_builtins,
+            output_format: cliargs.output_format,
+            no_dns: cliargs.no_dns,
+            deterministic: cliargs.deterministic,
+            seed: cliargs.seed,
+            debug_objects: cliargs.debug_objects,
+        }
+    }
+    pub fn get_subcmd(&self) -> Option<SubCmd> {
+        self.subcmd.clone()
+    }
+    /// Set when running the test scripts
+    pub fn test_mode(&self) -> bool {
+        matches!(self.subcmd, Some(SubCmd::Test { .. }))
+    }
+    pub fn get_args(&self) -> &[String] {
+        self.args.as_slice()
+    }
+    pub fn get_cmd(&self) -> Option<String> {
+        self.cmd.clone()
+    }
+    pub fn skip_pcap(&self) -> bool {
+        self.skip_pcap
+    }
+    pub fn deny_deprecated(&self) -> bool {
+        self.deny_deprecated
+    }
+    pub fn on_corrupt(&self) -> CorruptPolicy {
+        self.on_corrupt
+    }
+    pub fn snaplen(&self) -> Option<u32> {
+        self.snaplen
+    }
+    pub fn max_packet_size(&self) -> Option<u32> {
+        self.max_packet_size
+    }
+    pub fn read(&self) -> &[String] {
+        self.read.as_slice()
+    }
+    pub fn read_order(&self) -> ReadOrder {
+        self.read_order
+    }
+    pub fn reset_np(&self) -> bool {
+        self.reset_np
+    }
+    pub fn interface(&self) -> Option<String> {
+        self.interface.clone()
+    }
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+    pub fn time_stamp_type(&self) -> TstampType {
+        self.time_stamp_type
+    }
+    /// The correction of the timestamps if an offset or a skew is given
+    pub fn ts_correction(&self) -> Option<TsCorrection> {
+        if self.ts_offset.is_none() && self.ts_skew.is_none() {
+            return None;
+        }
+        let offset = self.ts_offset.unwrap_or(0);
+        Some(TsCorrection::new(offset, self.ts_skew.unwrap_or(0.0)))
+    }
+    pub fn reload(&self) -> bool {
+        self.reload
+    }
+    pub fn metrics_addr(&self) -> Option<SocketAddr> {
+        self.metrics_addr
+    }
+    pub fn bpf(&self) -> Option<&BpfExpr> {
+        self.bpf.as_ref()
+    }
+    pub fn repair(&self) -> bool {
+        self.repair
+    }
+    pub fn ascii_ident(&self) -> bool {
+        self.ascii_ident
+    }
+    pub fn replay_session(&self) -> Option<String> {
+        self.replay_session.clone()
+    }
+    pub fn decode_as(&self) -> &[(u16, Option<AppProto>)] {
+        self.decode_as.as_slice()
+    }
+    pub fn heuristics(&self) -> bool {
+        self.heuristics
+    }
+    pub fn profile_dissect(&self) -> bool {
+        self.profile_dissect
+    }
+    pub fn legacy_props(&self) -> bool {
+        self.legacy_props
+    }
+    pub fn checkpoint(&self) -> Option<Duration> {
+        self.checkpoint
+    }
+    pub fn checkpoint_file(&self) -> &str {
+        &self.checkpoint_file
+    }
+    pub fn resume(&self) -> bool {
+        self.resume
+    }
+    pub fn no_cache(&self) -> bool {
+        self.no_cache
+    }
+    pub fn compile(&self) -> Option<String> {
+        self.compile.clone()
+    }
+    pub fn exec(&self) -> Option<String> {
+        self.exec.clone()
+    }
+    pub fn list_filters(&self) -> bool {
+        self.list_filters
+    }
+    pub fn debug(&self) -> bool {
+        self.debug
+    }
+    pub fn stats(&self) -> bool {
+        self.stats
+    }
+    pub fn disasm(&self) -> bool {
+        self.disasm
+    }
+    pub fn trace_builtins(&self) -> bool {
+        self.trace_builtins
+    }
+
+    pub fn output_format(&self) -> OutputFormat {
+        self.output_format
+    }
+
+    pub fn no_dns(&self) -> bool {
+        self.no_dns
+    }
+
+    /// The seed of 'rand' in the deterministic mode, which is 0 unless
+    /// one is given
+    pub fn seed(&self) -> Option<u64> {
+        match self.seed {
+            Some(seed) => Some(seed),
+            None if self.deterministic => Some(0),
+            None => None,
+        }
+    }
+
+    pub fn debug_objects(&self) -> bool {
+        self.debug_objects
+    }
+}
@@ -0,0 +1,28 @@
+#![allow(unused_imports)]
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use super::*;
+
+#[test]
+fn test_parse_metrics_addr() {
+    // A port alone is served on the loopback interface only
+    assert_eq!(
+        parse_metrics_addr("9100"),
+        Ok(SocketAddr::from((Ipv4Addr::LOCALHOST, 9100)))
+    );
+    assert_eq!(
+        parse_metrics_addr("0.0.0.0:9100"),
+        Ok(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 9100)))
+    );
+    assert_eq!(
+        parse_metrics_addr("[::1]:9100"),
+        Ok(SocketAddr::from((Ipv6Addr::LOCALHOST, 9100)))
+    );
+    assert!(parse_metrics_addr("localhost:9100")
+        .unwrap()
+        .ip()
+        .is_loopback());
+    for s in ["", "70000", "9100:", "0.0.0.0", "no such host:9100"] {
+        assert!(parse_metrics_addr(s).is_err(), "{}", s);
+    }
+}
@@ -0,0 +1,1347 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use builtins::capture::{Capture, CaptureStats};
+use builtins::functions::BUILTINFNS;
+use builtins::pcap::Pcap;
+use builtins::pcap::PcapPacket;
+use builtins::pcapdiff::{summarize_file, DiffOptions, MatchReport, PacketSummary};
+use builtins::pcapfiles::expand_paths;
+use builtins::variables::BuiltinVarType;
+use cliargs::CliArgs;
+use cliargs::SubCmd;
+use compiler::*;
+use object::file::FileHandle;
+use object::func::CompiledFunction;
+use object::Object;
+use parser::ast::Program;
+use parser::*;
+use repl::prompt::{self, PROMPT_DEBUG, PROMPT_MAIN};
+use repl::replay::{parse_transcript, replay};
+use repl::session::{Output, Session};
+use scanner::*;
+use vm::checkpoint::Checkpoint;
+use vm::debugger::Debugger;
+use vm::error::{RTError, EXIT_BROKEN_PIPE};
+use vm::interpreter::GLOBALS_SIZE;
+use vm::interpreter::{RegisteredFilter, VM};
+use vm::metrics::{self, Metrics};
+use vm::reload::ScriptWatcher;
+
+use p2sh_core::{builtins, code, compiler, object, parser, scanner, vm};
+
+mod cliargs;
+mod repl;
+
+const HISTORY_LINES: usize = 8;
+// Snaplen of a live capture unless specified
+const DEFAULT_SNAPLEN: u32 = 65535;
+const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
+const PKG_DESC: &str = env!("CARGO_PKG_DESCRIPTION");
+
+fn main() {
+    let cliargs = CliArgs::new();
+    let args = cliargs.get_args().to_vec();
+    let command = cliargs.get_cmd();
+
+    if let Some(SubCmd::Fields { proto }) = cliargs.get_subcmd() {
+        match code::prop::describe_fields(proto.as_deref()) {
+            Ok(fields) => print!("{}", fields),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Some(SubCmd::Diff {
+        first,
+        second,
+        ignore_time,
+        tolerance,
+        window,
+    }) = cliargs.get_subcmd()
+    {
+        let options = DiffOptions {
+            ignore_time,
+            tolerance_ns: tolerance,
+            window,
+        };
+        process::exit(run_diff(&first, &second, &options));
+    }
+    if let Some(SubCmd::Ab {
+        first,
+        second,
+        capture,
+    }) = cliargs.get_subcmd()
+    {
+        process::exit(run_ab(&first, &second, &capture, &cliargs));
+    }
+    if let Some(SubCmd::Test { paths }) = cliargs.get_subcmd() {
+        process::exit(run_tests(paths, &cliargs));
+    }
+    if let Some(path) = cliargs.replay_session() {
+        process::exit(run_replay(&path, args, &cliargs));
+    }
+    if let Some(out) = cliargs.compile() {
+        process::exit(run_compile(&out, command, &args, &cliargs));
+    }
+    if let Some(path) = cliargs.exec() {
+        run_exec(&path, args, &cliargs);
+        return;
+    }
+    if let Some(cmd) = command {
+        run_buf(cmd, args, true, &cliargs);
+        return;
+    }
+    if args.is_empty() {
+        run_prompt(args, &cliargs);
+    } else {
+        run_file(&args[0].clone(), args, &cliargs);
+    }
+}
+
+/// Compare two pcap files and print the packets that differ.
+/// Returns 0 if the files are equal, 1 if they differ and 2 on error.
+fn run_diff(first: &str, second: &str, options: &DiffOptions) -> i32 {
+    let summarize = |path: &str| {
+        summarize_file(path).map_err(|e| eprintln!("Failed to read pcap '{}': {}", path, e))
+    };
+    let (Ok(a), Ok(b)) = (summarize(first), summarize(second)) else {
+        return 2;
+    };
+    let report = options.diff(&a, &b);
+    print!("{}", report);
+    if report.is_equal() {
+        0
+    } else {
+        1
+    }
+}
+
+/// Run two scripts over the same capture in one pass and print the
+/// packets matched by the filters of only one of them. Returns 0 if the
+/// scripts matched the same packets, 1 if they did not and 2 on error.
+fn run_ab(first: &str, second: &str, capture: &str, cliargs: &CliArgs) -> i32 {
+    let (Some(mut a), Some(mut b)) = (
+        AbScript::load(first, cliargs),
+        AbScript::load(second, cliargs),
+    ) else {
+        return 2;
+    };
+    let pcap_in = match fs::File::open(capture).and_then(|file| {
+        let reader = Rc::new(FileHandle::new_reader(io::BufReader::new(file)));
+        Pcap::from_file(reader)
+    }) {
+        Ok(pcap) => configure_input(pcap, cliargs),
+        Err(err) => {
+            eprintln!("Failed to open '{}': {}", capture, err);
+            return 2;
+        }
+    };
+    let mut report = MatchReport::default();
+    let mut start_ts = None;
+    let mut failed = false;
+    // Stop at the end of the capture or when a script calls exit
+    loop {
+        let pkt = match pcap_in.next_packet() {
+            Ok(pkt) => pkt,
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => {
+                eprintln!("{}", err);
+                failed = true;
+                break;
+            }
+        };
+        let ts = pkt.get_timestamp_ns(pcap_in.is_nanosec());
+        let time = (ts - *start_ts.get_or_insert(ts)) as f64 / 1e9;
+        let np = report.packets as i64 + 1;
+        let summary = PacketSummary::new(&pkt, pcap_in.is_nanosec());
+        // Each script gets a copy of the packet as it may change it
+        let matched = (
+            a.matches(Rc::new(pkt.duplicate()), np, time),
+            b.matches(pkt, np, time),
+        );
+        match matched {
+            (Ok(x), Ok(y)) => report.add(summary, x, y),
+            (x, y) => {
+                failed = x.is_err_and(|exit| !exit) || y.is_err_and(|exit| !exit);
+                break;
+            }
+        }
+    }
+    print!("{}", report);
+    if failed {
+        2
+    } else if report.is_equal() {
+        0
+    } else {
+        1
+    }
+}
+
+/// A script compared by 'ab' with the filters run on each packet
+struct AbScript {
+    vm: VM,
+    filters: Vec<Rc<CompiledFunction>>,
+    registered: Vec<RegisteredFilter>,
+}
+
+impl AbScript {
+    /// Compile a script and run the statements outside the filters
+    fn load(path: &str, cliargs: &CliArgs) -> Option<Self> {
+        let Ok(buf) = fs::read_to_string(path) else {
+            eprintln!("Failed to read file {}", path);
+            return None;
+        };
+        let bytecode = compile_script(&buf, !cliargs.no_cache(), cliargs)?;
+        let filters = bytecode.filters.clone();
+        let data = Rc::new(Object::Null);
+        let globals = vec![data; GLOBALS_SIZE];
+        let mut vm = VM::new_with_global_store(bytecode, globals);
+        vm.init_builtin_vars(vec![path.to_string()]);
+        configure_vm(&mut vm, cliargs);
+        if let Err(err) = vm.run() {
+            report_error(err);
+            return None;
+        }
+        let registered = vm.take_registered_filters();
+        Some(Self {
+            vm,
+            filters,
+            registered,
+        })
+    }
+
+    /// Run the filters on a packet and return true if any of them matched
+    /// it. On an error, which is reported, return whether the script
+    /// called exit.
+    fn matches(&mut self, pkt: Rc<PcapPacket>, np: i64, time: f64) -> Result<bool, bool> {
+        let vm = &mut self.vm;
+        vm.set_curr_pkt(pkt);
+        vm.update_builtin_var(BuiltinVarType::NP, Rc::new(Object::Integer(np)));
+        vm.update_builtin_var(BuiltinVarType::Time, Rc::new(Object::Float(time)));
+        let mut matched = false;
+        for (i, filter) in self.filters.iter().enumerate() {
+            set_filter_vars(vm, Some(i + 1), filter.line);
+            match run_filter(vm, filter) {
+                Ok(m) => matched |= m,
+                Err(err) => return Err(report_filter_error(err, filter).is_some()),
+            }
+        }
+        for (i, filter) in self.registered.iter().enumerate() {
+            let index = self.filters.len() + i + 1;
+            set_filter_vars(vm, Some(index), filter.pattern.func.line);
+            match vm.run_registered_filter(filter) {
+                Ok(m) => matched |= m,
+                Err(err) => return Err(report_error(err).is_some()),
+            }
+        }
+        Ok(matched)
+    }
+}
+
+/// Run the test scripts found in the given paths and report the result
+/// of each. A test passes if it runs without errors and does not exit
+/// with a non-zero exit code. Returns 0 if all the tests passed, else 1.
+fn run_tests(paths: Vec<String>, cliargs: &CliArgs) -> i32 {
+    let paths = if paths.is_empty() {
+        vec![String::from(".")]
+    } else {
+        paths
+    };
+    let mut scripts = Vec::new();
+    for path in &paths {
+        if let Err(err) = find_tests(Path::new(path), &mut scripts) {
+            eprintln!("Failed to read '{}': {}", path, err);
+            return 1;
+        }
+    }
+    let mut failed = 0;
+    for script in &scripts {
+        let name = script.display().to_string();
+        let passed = match fs::read_to_string(script) {
+            Ok(buf) => {
+                let status = run_script(&buf, vec![name.clone()], false, cliargs);
+                !status.failed && status.exit.unwrap_or(0) == 0
+            }
+            Err(err) => {
+                eprintln!("Failed to read file {}: {}", name, err);
+                false
+            }
+        };
+        if passed {
+            println!("test {} ... ok", name);
+        } else {
+            println!("test {} ... FAILED", name);
+            failed += 1;
+        }
+    }
+    let result = if failed == 0 { "ok" } else { "FAILED" };
+    println!(
+        "\ntest result: {}. {} passed; {} failed",
+        result,
+        scripts.len() - failed,
+        failed
+    );
+    if failed == 0 {
+        0
+    } else {
+        1
+    }
+}
+
+/// Find the test scripts named *_test.p2 in a directory and its
+/// subdirectories. A file is taken as a test script regardless of its name.
+fn find_tests(path: &Path, scripts: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        scripts.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            find_tests(&entry, scripts)?;
+        } else if entry.to_string_lossy().ends_with("_test.p2") {
+            scripts.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Function to run the REPL
+pub fn run_prompt(args: Vec<String>, cliargs: &CliArgs) {
+    println!("{} v{}", PKG_DESC, PKG_VERSION);
+    println!("Type quit to quit REPL");
+
+    let mut cmds = vec![
+        "quit".to_string(),
+        ":set maxprint".to_string(),
+        ":disasm".to_string(),
+    ];
+    cmds.extend(BUILTINFNS.iter().map(|sym| sym.name.to_string()));
+    for n in BuiltinVarType::range() {
+        let name: &str = BuiltinVarType::from(n).into();
+        cmds.push(name.to_string());
+    }
+    // Complete packet property paths
+    cmds.extend(code::prop::property_paths());
+
+    let mut session = Session::new(args, cliargs.deny_deprecated(), cliargs.ascii_ident());
+    let mut prompt = prompt::Prompt::new(HISTORY_LINES, cmds.as_slice());
+    loop {
+        if let Ok(line) = prompt.show() {
+            if line == "quit" {
+                break;
+            }
+            for output in session.eval(&line) {
+                match output {
+                    Output::Print(text) => print!("{}", text),
+                    Output::Value(value) => println!("{}", value),
+                    Output::Error(err) => eprintln!("{}", err),
+                    Output::Exit(code) => {
+                        process::exit(session.finish(Some(code)).unwrap_or(code));
+                    }
+                }
+            }
+        }
+    }
+    println!("\nExiting...");
+    if let Some(code) = session.finish(None) {
+        process::exit(code);
+    }
+}
+
+/// Replay a transcript of a REPL session and report the inputs whose
+/// output differs from the one recorded. Returns 0 if all the outputs
+/// match, else 1.
+fn run_replay(path: &str, args: Vec<String>, cliargs: &CliArgs) -> i32 {
+    let entries = match fs::read_to_string(path) {
+        Ok(text) => parse_transcript(&text),
+        Err(err) => Err(err.to_string()),
+    };
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("Failed to read transcript {}: {}", path, err);
+            return 1;
+        }
+    };
+    let mut session = Session::new(args, cliargs.deny_deprecated(), cliargs.ascii_ident());
+    let (count, exit, mismatches) = replay(&mut session, &entries);
+    session.finish(exit);
+    for mismatch in &mismatches {
+        let entry = &mismatch.entry;
+        println!("{}:{}: {} {}", path, entry.line, PROMPT_MAIN, entry.input);
+        for line in &entry.expected {
+            println!("- {}", line);
+        }
+        for line in &mismatch.actual {
+            println!("+ {}", line);
+        }
+    }
+    println!(
+        "replayed {} of {} inputs: {} mismatches",
+        count,
+        entries.len(),
+        mismatches.len()
+    );
+    if mismatches.is_empty() && count == entries.len() {
+        0
+    } else {
+        1
+    }
+}
+
+/// Function to run a script file
+/// # Arguments
+/// * `path` - Path to the script file
+/// * `args` - Arguments to the script
+/// * `cliargs` - Command line options
+pub fn run_file(path: &str, args: Vec<String>, cliargs: &CliArgs) {
+    let buf = fs::read_to_string(path);
+    if buf.is_err() {
+        eprintln!("Failed to read file {}", path);
+        return;
+    }
+    let buf = buf.unwrap();
+    run_buf(buf, args, false, cliargs);
+}
+
+/// Function to run a script stored in a buffer
+/// # Arguments
+/// * `buf` - Buffer containing the script
+/// * `args` - Arguments to the script
+/// * `cmd_mode` - Flag to indicate command mode
+/// * `cliargs` - Command line options
+pub fn run_buf(buf: String, args: Vec<String>, cmd_mode: bool, cliargs: &CliArgs) {
+    if let Some(code) = run_script(&buf, args, cmd_mode, cliargs).exit {
+        process::exit(code);
+    }
+}
+
+/// Compile the script given using '-c' or in a file, and save its
+/// bytecode to 'out' to be run using '--exec'. Returns 0 on success,
+/// else 1.
+fn run_compile(out: &str, command: Option<String>, args: &[String], cliargs: &CliArgs) -> i32 {
+    let source = match (command, args.first()) {
+        (Some(cmd), _) => cmd,
+        (None, Some(path)) => match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("Failed to read file {}: {}", path, err);
+                return 1;
+            }
+        },
+        (None, None) => {
+            eprintln!("No script to compile");
+            return 1;
+        }
+    };
+    let Some(bytecode) = compile_script(&source, false, cliargs) else {
+        return 1;
+    };
+    if let Err(err) = cache::save(Path::new(out), &bytecode) {
+        eprintln!("Failed to write '{}': {}", out, err);
+        return 1;
+    }
+    0
+}
+
+/// Run the bytecode of a script saved using '--compile'
+fn run_exec(path: &str, args: Vec<String>, cliargs: &CliArgs) {
+    let bytecode = match cache::load(Path::new(path)) {
+        Ok(bytecode) => bytecode,
+        Err(err) => {
+            eprintln!("Failed to load '{}': {}", path, err);
+            process::exit(1);
+        }
+    };
+    // Checkpoints are taken of the globals of the same bytecode
+    let source = bytecode.disassemble();
+    if let Some(code) = run_bytecode(bytecode, &source, args, false, cliargs).exit {
+        process::exit(code);
+    }
+}
+
+/// Outcome of running a script
+#[derive(Debug, Default)]
+struct Status {
+    /// Exit code requested by the script
+    exit: Option<i32>,
+    /// Set if the script failed with an error
+    failed: bool,
+}
+
+/// Run a script and return its status. The functions registered using
+/// 'atexit' are run before returning.
+fn run_script(buf: &str, args: Vec<String>, cmd_mode: bool, cliargs: &CliArgs) -> Status {
+    if buf.trim().is_empty() {
+        return Status::default();
+    }
+    match compile_script(buf, !cmd_mode && !cliargs.no_cache(), cliargs) {
+        Some(bytecode) => run_bytecode(bytecode, buf, args, cmd_mode, cliargs),
+        None => Status {
+            exit: None,
+            failed: true,
+        },
+    }
+}
+
+/// Run the bytecode of a script and return its status. 'source' is the
+/// script, which identifies the globals saved to checkpoints.
+fn run_bytecode(
+    bytecode: Bytecode,
+    source: &str,
+    args: Vec<String>,
+    cmd_mode: bool,
+    cliargs: &CliArgs,
+) -> Status {
+    let data = Rc::new(Object::Null);
+    let globals = vec![data; GLOBALS_SIZE];
+    let mut status = Status::default();
+    if cliargs.list_filters() {
+        print!("{}", bytecode.list_filters());
+        return status;
+    }
+    if cliargs.disasm() {
+        print!("{}", bytecode.disassemble_with_lines());
+        return status;
+    }
+    let script = ScriptFilters::new(&bytecode);
+    // Paths declared using 'testdata' are relative to the script
+    let script_dir = match args.first() {
+        Some(script) if !cmd_mode => Path::new(script).parent().map(Path::to_path_buf),
+        _ => None,
+    };
+    let watcher = match args.first() {
+        Some(script) if !cmd_mode && cliargs.reload() => {
+            Some(ScriptWatcher::new(Path::new(script)))
+        }
+        _ => None,
+    };
+
+    // The source of a compiled file is not known to show its lines
+    let debugger = cliargs.debug().then(|| {
+        let source = Some(source).filter(|_| cliargs.exec().is_none());
+        new_debugger(bytecode.globals.clone(), source)
+    });
+
+    // Run the bytecode that excludes the filter statements
+    let mut vm = VM::new_with_global_store(bytecode, globals);
+    vm.init_builtin_vars(args);
+    configure_vm(&mut vm, cliargs);
+    if let Some(debugger) = debugger {
+        vm.set_debugger(debugger);
+    }
+    let err = vm.run();
+    if let Err(err) = err {
+        match report_error(err) {
+            Some(code) => {
+                status.exit = exit_code(&mut vm, Some(code));
+                report_dissect_profile(&vm);
+                report_objects(&vm, cliargs);
+                return status;
+            }
+            None => status.failed = true,
+        }
+    }
+
+    // Filters may also be registered while running the script
+    let filter_mode = !script.filters.is_empty()
+        || script.end.is_some()
+        || !script.intervals.is_empty()
+        || vm.has_registered_filters();
+
+    if cmd_mode && !filter_mode {
+        // Get the object at the top of the VM's stack
+        let stack_elem = vm.last_popped();
+        // print last popped element if it is not null
+        if !matches!(stack_elem.as_ref(), Object::Null) {
+            println!("{}", stack_elem);
+        }
+    }
+
+    // Run all the filter statements
+    if filter_mode {
+        vm.update_builtin_var(BuiltinVarType::NP, Rc::new(Object::Integer(0)));
+        vm.update_builtin_var(BuiltinVarType::NT, Rc::new(Object::Integer(0)));
+        let testdata = vm.testdata().map(|path| match &script_dir {
+            Some(dir) => dir.join(path),
+            None => PathBuf::from(path),
+        });
+        let path = Path::new(cliargs.checkpoint_file());
+        let mut checkpoint = Checkpoint::new(path, cliargs.checkpoint(), source);
+        if cliargs.resume() {
+            if let Err(err) = checkpoint.resume(&mut vm.globals) {
+                eprintln!("Failed to resume from '{}': {}", path.display(), err);
+                status.failed = true;
+                return status;
+            }
+        }
+        let filter_status =
+            run_filters(&mut vm, script, testdata, &mut checkpoint, watcher, cliargs);
+        status.exit = filter_status.exit;
+        status.failed |= filter_status.failed;
+    }
+    status.exit = exit_code(&mut vm, status.exit);
+    report_dissect_profile(&vm);
+    report_objects(&vm, cliargs);
+    status
+}
+
+/// A debugger reading its commands at a prompt of its own
+fn new_debugger(globals: Vec<String>, source: Option<&str>) -> Debugger {
+    let cmds: Vec<String> = [
+        "break",
+        "delete",
+        "breakpoints",
+        "step",
+        "continue",
+        "print",
+        "globals",
+        "locals",
+        "stack",
+        "where",
+        "list",
+        "quit",
+        "help",
+    ]
+    .iter()
+    .map(|cmd| cmd.to_string())
+    .chain(globals.iter().cloned())
+    .collect();
+    let mut prompt = prompt::Prompt::new(HISTORY_LINES, &cmds).with_prompt(PROMPT_DEBUG);
+    Debugger::new(globals, source, Box::new(move || prompt.show().ok()))
+}
+
+/// Apply the command line options that change how a script runs
+fn configure_vm(vm: &mut VM, cliargs: &CliArgs) {
+    for (port, proto) in cliargs.decode_as() {
+        vm.decode_as(*port, *proto);
+    }
+    vm.set_heuristics(cliargs.heuristics());
+    vm.set_legacy_props(cliargs.legacy_props());
+    vm.set_trace_builtins(cliargs.trace_builtins());
+    vm.set_output_format(cliargs.output_format());
+    vm.set_no_dns(cliargs.no_dns());
+    if let Some(seed) = cliargs.seed() {
+        vm.set_seed(seed);
+    }
+    if cliargs.profile_dissect() {
+        vm.enable_dissect_profile();
+    }
+}
+
+/// Display the time spent by each dissector if profiling is enabled
+fn report_dissect_profile(vm: &VM) {
+    if let Some(profile) = vm.dissect_profile() {
+        eprintln!("{}", profile);
+    }
+}
+
+/// Display the live objects held by the globals if asked to
+fn report_objects(vm: &VM, cliargs: &CliArgs) {
+    if cliargs.debug_objects() {
+        eprintln!("{}", vm.object_report());
+    }
+}
+
+/// Display a runtime error unless it is a request to exit the program,
+/// in which case return the exit code
+fn report_error(err: RTError) -> Option<i32> {
+    if err.exit.is_none() {
+        eprintln!("{}", err);
+    }
+    err.exit
+}
+
+/// Display a runtime error in a filter statement followed by the filter
+/// unless it is a request to exit the program
+fn report_filter_error(err: RTError, filter: &CompiledFunction) -> Option<i32> {
+    if err.exit.is_none() {
+        eprintln!("{}\n  in {}", err, filter);
+    }
+    err.exit
+}
+
+/// Run the functions registered using 'atexit' and return the exit code
+/// requested either earlier or by those functions
+fn exit_code(vm: &mut VM, exit: Option<i32>) -> Option<i32> {
+    vm.run_exit_handlers().or(exit)
+}
+
+/// The filter statements of a script along with the names of its
+/// globals, which are replaced when the script is reloaded
+struct ScriptFilters {
+    filters: Vec<Rc<CompiledFunction>>,
+    end: Option<Rc<CompiledFunction>>,
+    // Filter statements run at intervals of seconds
+    intervals: Vec<(f64, Rc<CompiledFunction>)>,
+    globals: Vec<String>,
+}
+
+impl ScriptFilters {
+    fn new(bytecode: &Bytecode) -> Self {
+        Self {
+            filters: bytecode.filters.clone(),
+            end: bytecode.filter_end.clone(),
+            intervals: bytecode.filter_intervals.clone(),
+            globals: bytecode.globals.clone(),
+        }
+    }
+
+    /// Lines of the filters run on each packet including the ones
+    /// registered using 'register_filter'
+    fn lines(&self, registered: &[RegisteredFilter]) -> Vec<usize> {
+        let filters = self.filters.iter().map(|f| f.line);
+        filters
+            .chain(registered.iter().map(|f| f.pattern.func.line))
+            .collect()
+    }
+
+    /// The interval filters, the first interval of which is the one that
+    /// the time 'ts' falls in
+    fn interval_filters(&self, start: i64, ts: i64) -> Vec<IntervalFilter> {
+        self.intervals
+            .iter()
+            .map(|(secs, filter)| {
+                let mut interval = IntervalFilter::new(*secs, filter.clone());
+                interval.count = (ts - start) / interval.period;
+                interval
+            })
+            .collect()
+    }
+}
+
+/// Run the filter statements on the input pcap stream and
+/// write the output pcap stream to stdout. Return the exit code
+/// if a filter called exit.
+/// # Arguments
+/// * `vm` - VM instance
+/// * `script` - Filter statements of the script
+/// * `testdata` - Pcap file to read instead of the input stream
+/// * `checkpoint` - Periodic checkpoints of the globals
+/// * `watcher` - Script file to reload when it changes, if any
+/// * `cliargs` - Command line options
+fn run_filters(
+    vm: &mut VM,
+    mut script: ScriptFilters,
+    testdata: Option<PathBuf>,
+    checkpoint: &mut Checkpoint,
+    mut watcher: Option<ScriptWatcher>,
+    cliargs: &CliArgs,
+) -> Status {
+    let mut status = Status::default();
+    let mut inputs = match open_input(testdata, cliargs) {
+        Ok(inputs) => inputs,
+        Err(err) => {
+            eprintln!("{}", err);
+            status.failed = true;
+            return status;
+        }
+    };
+    let (mut pcap_in, path) = match inputs.next(cliargs) {
+        Ok(Some(input)) => input,
+        Ok(None) => return status,
+        Err(err) => {
+            eprintln!("{}", err);
+            status.failed = true;
+            return status;
+        }
+    };
+    set_filename(vm, path);
+    // The output stream has the same format as the input stream
+    let magic = pcap_in.get_magic_number_raw();
+    let format = pcap_in.format();
+    let pcap_out = if cliargs.skip_pcap() || cliargs.test_mode() {
+        None
+    } else {
+        let out = match Pcap::new_with_format(Rc::new(FileHandle::Stdout), magic, format) {
+            Ok(pcap) => pcap,
+            Err(err) => return report_write_error(err),
+        };
+        Some(out)
+    };
+
+    // Read packet stream from the inputs and write to stdout in a loop
+    let mut count = 1;
+    // Packets read before the current input if 'NP' restarts with each one
+    let mut np_base = 0;
+    // Corrupt records found in the inputs read before the current one
+    let mut corrupt_before = 0;
+    let mut start_ts = None;
+    let bpf = cliargs.bpf();
+    let linktype = pcap_in.get_linktype_raw();
+    let mut registered = vm.take_registered_filters();
+    let mut intervals = script.interval_filters(0, 0);
+    let metrics = match cliargs.metrics_addr() {
+        Some(addr) => {
+            let metrics = Arc::new(Metrics::default());
+            if let Err(err) = metrics::serve(metrics.clone(), addr) {
+                eprintln!("Failed to serve metrics on {}: {}", addr, err);
+                status.failed = true;
+                return status;
+            }
+            metrics.set_filters(&script.lines(&registered));
+            Some(metrics)
+        }
+        None => None,
+    };
+    // The drop counts of a live capture are read at most once a second
+    let mut drops_read = Instant::now();
+    if let Some(stats) = pcap_in.capture_stats() {
+        set_drop_vars(vm, stats);
+    }
+    'out: loop {
+        let result = pcap_in.next_packet();
+        let corrupt = (corrupt_before + pcap_in.get_corrupt_count()) as i64;
+        vm.update_builtin_var(BuiltinVarType::NC, Rc::new(Object::Integer(corrupt)));
+        if let Some(metrics) = &metrics {
+            metrics.set_corrupt(corrupt as u64);
+        }
+        if drops_read.elapsed() >= Duration::from_secs(1) {
+            drops_read = Instant::now();
+            if let Some(stats) = pcap_in.capture_stats() {
+                set_drop_vars(vm, stats);
+                if let Some(metrics) = &metrics {
+                    metrics.set_dropped(stats.drop);
+                }
+            }
+        }
+        match result {
+            // Skip the packets that do not match the pre-filter
+            Ok(pkt) if !bpf.is_none_or(|bpf| bpf.matches(&pkt, linktype)) => {}
+            Ok(pkt) => {
+                let ts = pkt.get_timestamp_ns(pcap_in.is_nanosec());
+                let start = *start_ts.get_or_insert(ts);
+                // Close the intervals that ended before this packet
+                if let Err((err, filter)) = run_intervals(vm, &mut intervals, start, Some(ts)) {
+                    status.exit = report_filter_error(err, &filter);
+                    if status.exit.is_some() {
+                        return status;
+                    }
+                    status.failed = true;
+                    break 'out;
+                }
+                // Swap in the filters of the script if it was changed
+                let changed = watcher.as_mut().is_some_and(|w| w.changed());
+                if let Some(w) = watcher.as_ref().filter(|_| changed) {
+                    let reloaded = reload_script(
+                        vm,
+                        w.path(),
+                        &mut script,
+                        &mut registered,
+                        checkpoint,
+                        cliargs,
+                    );
+                    match reloaded {
+                        Ok(true) => {
+                            intervals = script.interval_filters(start, ts);
+                            if let Some(metrics) = &metrics {
+                                metrics.set_filters(&script.lines(&registered));
+                            }
+                        }
+                        Ok(false) => {}
+                        Err(code) => {
+                            status.exit = Some(code);
+                            return status;
+                        }
+                    }
+                }
+                vm.set_curr_pkt(pkt.clone());
+                let np = Object::Integer(count - np_base);
+                vm.update_builtin_var(BuiltinVarType::NP, Rc::new(np));
+                if let Some(metrics) = &metrics {
+                    metrics.add_packet();
+                }
+                // Time relative to the first packet in the capture
+                let time = (ts - start) as f64 / 1e9;
+                vm.update_builtin_var(BuiltinVarType::Time, Rc::new(Object::Float(time)));
+                // Run filter statements on the packet followed by the
+                // filters registered using 'register_filter'
+                let filters = &script.filters;
+                let num_filters = filters.len();
+                for i in 0..num_filters + registered.len() {
+                    let result = if i < num_filters {
+                        set_filter_vars(vm, Some(i + 1), filters[i].line);
+                        run_filter(vm, &filters[i])
+                    } else {
+                        let filter = &registered[i - num_filters];
+                        set_filter_vars(vm, Some(i + 1), filter.pattern.func.line);
+                        vm.run_registered_filter(filter)
+                    };
+                    // If the result of the filter is true, then write the packet to stdout
+                    // The result is true when the action is not specified and the pattern
+                    // evaluates to true.
+                    if let Some(metrics) = &metrics {
+                        match result {
+                            Ok(true) => metrics.add_match(i),
+                            Ok(false) => {}
+                            Err(_) => metrics.add_error(),
+                        }
+                    }
+                    match result {
+                        Ok(true) => {
+                            if let Some(out) = &pcap_out {
+                                if let Err(err) = out.write_all(pkt.clone()) {
+                                    status = report_write_error(err);
+                                    break 'out;
+                                }
+                            }
+                        }
+                        Ok(false) => {}
+                        Err(err) if i < num_filters => {
+                            status.exit = report_filter_error(err, &filters[i]);
+                            if status.exit.is_some() {
+                                return status;
+                            }
+                            status.failed = true;
+                            break 'out;
+                        }
+                        Err(err) => {
+                            status.exit = report_error(err);
+                            if status.exit.is_some() {
+                                return status;
+                            }
+                            status.failed = true;
+                            break 'out;
+                        }
+                    }
+                }
+                count += 1;
+                if let Err(err) = checkpoint.tick(&vm.globals) {
+                    let path = checkpoint.path().display();
+                    eprintln!("Failed to save checkpoint '{}': {}", path, err);
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                // Continue with the next input if any
+                let (next, path) = match inputs.next(cliargs) {
+                    Ok(Some(next)) => next,
+                    Ok(None) => break,
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        status.failed = true;
+                        break;
+                    }
+                };
+                corrupt_before += pcap_in.get_corrupt_count();
+                // The skew is corrected from the first packet of all inputs
+                if let Some(correction) = pcap_in.ts_correction() {
+                    next.set_ts_correction(correction);
+                }
+                pcap_in = next;
+                set_filename(vm, path);
+                if cliargs.reset_np() {
+                    np_base = count - 1;
+                }
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                status.failed = true;
+                break;
+            }
+        }
+    }
+    // Flush the packets written so far unless the reader is gone
+    if let (Some(out), None) = (&pcap_out, status.exit) {
+        if let Err(err) = out.flush() {
+            status = report_write_error(err);
+        }
+    }
+    // Reset built-in variables for packets
+    vm.update_builtin_var(BuiltinVarType::PL, Rc::new(Object::Null));
+    vm.update_builtin_var(BuiltinVarType::WL, Rc::new(Object::Null));
+    let capture_stats = pcap_in.capture_stats();
+    if let Some(stats) = capture_stats {
+        set_drop_vars(vm, stats);
+    }
+    if cliargs.stats() {
+        let corrupt = corrupt_before + pcap_in.get_corrupt_count();
+        report_stats(count - 1, corrupt, capture_stats);
+    }
+    // Close the last intervals which are cut short by the end of the input
+    if let Some(start) = start_ts {
+        if let Err((err, filter)) = run_intervals(vm, &mut intervals, start, None) {
+            match report_filter_error(err, &filter) {
+                Some(code) => status.exit = Some(code),
+                None => status.failed = true,
+            }
+            return status;
+        }
+    }
+    // Call the end filter
+    if let Some(filter) = script.end {
+        set_filter_vars(vm, None, filter.line);
+        if let Err(err) = vm.push_filter_frame(&filter) {
+            eprintln!("{}", err);
+            status.failed = true;
+            return status;
+        }
+        if let Err(err) = vm.run() {
+            match report_filter_error(err, &filter) {
+                Some(code) => status.exit = Some(code),
+                None => status.failed = true,
+            }
+            return status;
+        }
+        // There is nothing to write to stdout for the end filter
+        // as there is always an action specified for the end filter
+        if let Err(err) = vm.pop_filter_frame() {
+            report_filter_error(err, &filter);
+            status.failed = true;
+        }
+    }
+    status
+}
+
+/// Set the variables holding the number of packets dropped by a live capture
+fn set_drop_vars(vm: &VM, stats: CaptureStats) {
+    let drop = Rc::new(Object::Integer(stats.drop as i64));
+    vm.update_builtin_var(BuiltinVarType::PsDrop, drop);
+    let ifdrop = Rc::new(Object::Integer(stats.ifdrop as i64));
+    vm.update_builtin_var(BuiltinVarType::PsIfDrop, ifdrop);
+}
+
+/// Display the number of packets processed and those lost on the way
+fn report_stats(packets: i64, corrupt: usize, capture: Option<CaptureStats>) {
+    eprintln!("{} packets processed", packets);
+    eprintln!("{} corrupt records", corrupt);
+    if let Some(stats) = capture {
+        eprintln!("{} packets dropped by kernel", stats.drop);
+        eprintln!("{} packets dropped by interface", stats.ifdrop);
+    }
+}
+
+/// Compile the script at 'path' again and run it in place of the current
+/// one, keeping the values of the globals with the same names. The current
+/// script keeps running if the new one fails. Returns true if the script
+/// was replaced, or the exit code if the new script called exit.
+fn reload_script(
+    vm: &mut VM,
+    path: &Path,
+    script: &mut ScriptFilters,
+    registered: &mut Vec<RegisteredFilter>,
+    checkpoint: &mut Checkpoint,
+    cliargs: &CliArgs,
+) -> Result<bool, i32> {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("Failed to reload '{}': {}", path.display(), err);
+            return Ok(false);
+        }
+    };
+    let Some(bytecode) = compile_script(&source, !cliargs.no_cache(), cliargs) else {
+        eprintln!(
+            "Failed to reload '{}'; the script is not changed",
+            path.display()
+        );
+        return Ok(false);
+    };
+    let new_script = ScriptFilters::new(&bytecode);
+    match vm.reload(bytecode, &script.globals) {
+        Ok(kept) => {
+            *script = new_script;
+            *registered = vm.take_registered_filters();
+            checkpoint.set_source(&source);
+            eprintln!("Reloaded '{}' keeping {} globals", path.display(), kept);
+            Ok(true)
+        }
+        Err(err) => match report_error(err) {
+            Some(code) => Err(code),
+            None => {
+                eprintln!(
+                    "Failed to reload '{}'; the script is not changed",
+                    path.display()
+                );
+                Ok(false)
+            }
+        },
+    }
+}
+
+/// A filter statement whose action is run at regular intervals of the
+/// packet timestamps starting from the first packet
+struct IntervalFilter {
+    period: i64, // Length of the interval in nanoseconds
+    count: i64,  // Number of intervals closed so far
+    filter: Rc<CompiledFunction>,
+}
+
+impl IntervalFilter {
+    fn new(secs: f64, filter: Rc<CompiledFunction>) -> Self {
+        Self {
+            period: ((secs * 1e9).round() as i64).max(1),
+            count: 0,
+            filter,
+        }
+    }
+
+    /// Timestamp at which the current interval ends
+    fn end(&self, start: i64) -> i64 {
+        start + (self.count + 1) * self.period
+    }
+
+    fn run(&self, vm: &mut VM) -> Result<(), (RTError, Rc<CompiledFunction>)> {
+        set_filter_vars(vm, None, self.filter.line);
+        vm.push_filter_frame(&self.filter)
+            .and_then(|_| vm.run())
+            .and_then(|_| vm.pop_filter_frame())
+            .map(|_| ())
+            .map_err(|err| (err, self.filter.clone()))
+    }
+}
+
+/// Run the actions of the interval filters whose intervals ended at or
+/// before the timestamp 'ts' in the order in which they ended. Intervals
+/// without packets are also closed. 'time' is set to the end of each
+/// interval relative to the first packet. If there is no timestamp, which
+/// is at the end of the input, the current intervals are closed.
+fn run_intervals(
+    vm: &mut VM,
+    intervals: &mut [IntervalFilter],
+    start: i64,
+    ts: Option<i64>,
+) -> Result<(), (RTError, Rc<CompiledFunction>)> {
+    let Some(ts) = ts else {
+        for interval in intervals.iter_mut() {
+            interval.run(vm)?;
+            interval.count += 1;
+        }
+        return Ok(());
+    };
+    while let Some(interval) = intervals
+        .iter_mut()
+        .filter(|i| i.end(start) <= ts)
+        .min_by_key(|i| i.end(start))
+    {
+        let time = (interval.end(start) - start) as f64 / 1e9;
+        vm.update_builtin_var(BuiltinVarType::Time, Rc::new(Object::Float(time)));
+        interval.run(vm)?;
+        interval.count += 1;
+    }
+    Ok(())
+}
+
+/// Open the input pcap streams from the pcap file declared using
+/// 'testdata', or from the files given using '--read', which are read one
+/// after the other, or from stdin or, if an interface is given, from a live
+/// capture on the interface.
+fn open_input(testdata: Option<PathBuf>, cliargs: &CliArgs) -> io::Result<Inputs> {
+    if let Some(path) = testdata {
+        let file = fs::File::open(&path).map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                format!("Failed to open testdata '{}': {}", path.display(), err),
+            )
+        })?;
+        let pcap = Pcap::from_file(Rc::new(FileHandle::new_reader(io::BufReader::new(file))))?;
+        return Ok(Inputs::new(pcap, Some(path.display().to_string())));
+    }
+    if cliargs.test_mode() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "Tests with filters must declare a pcap file using 'testdata'",
+        ));
+    }
+    if let Some(interface) = cliargs.interface() {
+        let snaplen = cliargs.snaplen().unwrap_or(DEFAULT_SNAPLEN);
+        let capture = Capture::open(&interface, snaplen)?;
+        if let Some(duration) = cliargs.duration() {
+            capture.set_duration(duration);
+        }
+        capture.set_tstamp_type(cliargs.time_stamp_type())?;
+        let pcap_in = Pcap::from_capture(capture, snaplen);
+        if let Some(correction) = cliargs.ts_correction() {
+            pcap_in.set_ts_correction(correction);
+        }
+        return Ok(Inputs::new(pcap_in, None));
+    }
+    if cliargs.read().is_empty() {
+        let pcap_in = Pcap::from_file(Rc::new(FileHandle::Stdin))?;
+        return Ok(Inputs::new(configure_input(pcap_in, cliargs), None));
+    }
+    let paths = expand_paths(cliargs.read(), cliargs.read_order())?;
+    Ok(Inputs {
+        opened: None,
+        paths: paths.into_iter(),
+        first: None,
+    })
+}
+
+/// The input pcap streams read one after the other in filter mode with
+/// the paths of their files. The files are opened one at a time as the
+/// previous one is done so that a directory of any number of rotated
+/// captures can be read.
+struct Inputs {
+    opened: Option<(Pcap, Option<String>)>,
+    paths: std::vec::IntoIter<PathBuf>,
+    // The link type, the timestamp precision and the path of the first file
+    first: Option<(u32, bool, String)>,
+}
+
+impl Inputs {
+    fn new(pcap_in: Pcap, path: Option<String>) -> Self {
+        Self {
+            opened: Some((pcap_in, path)),
+            paths: Vec::new().into_iter(),
+            first: None,
+        }
+    }
+
+    /// Open the next input stream if there is one
+    fn next(&mut self, cliargs: &CliArgs) -> io::Result<Option<(Pcap, Option<String>)>> {
+        if let Some(input) = self.opened.take() {
+            return Ok(Some(input));
+        }
+        let Some(path) = self.paths.next() else {
+            return Ok(None);
+        };
+        let name = path.display().to_string();
+        let open_err = |err: io::Error| {
+            io::Error::new(err.kind(), format!("Failed to open '{}': {}", name, err))
+        };
+        let file = fs::File::open(&path).map_err(open_err)?;
+        let reader = Rc::new(FileHandle::new_reader(io::BufReader::new(file)));
+        let pcap_in = configure_input(Pcap::from_file(reader).map_err(open_err)?, cliargs);
+        // The packets are written to a single output stream
+        let linktype = pcap_in.get_linktype_raw();
+        let nanosec = pcap_in.is_nanosec();
+        match &self.first {
+            Some((first_linktype, first_nanosec, first))
+                if (*first_linktype, *first_nanosec) != (linktype, nanosec) =>
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "'{}' differs from '{}' in the link type or timestamp precision",
+                        name, first
+                    ),
+                ));
+            }
+            Some(_) => {}
+            None => self.first = Some((linktype, nanosec, name.clone())),
+        }
+        Ok(Some((pcap_in, Some(name))))
+    }
+}
+
+/// Apply the options for reading a pcap file to an input stream
+fn configure_input(pcap_in: Pcap, cliargs: &CliArgs) -> Pcap {
+    pcap_in.set_on_corrupt(cliargs.on_corrupt());
+    pcap_in.set_repair(cliargs.repair());
+    if let Some(snaplen) = cliargs.snaplen() {
+        pcap_in.set_snaplen_raw(snaplen);
+    }
+    if let Some(size) = cliargs.max_packet_size() {
+        pcap_in.set_max_packet_size(size);
+    }
+    if let Some(correction) = cliargs.ts_correction() {
+        pcap_in.set_ts_correction(correction);
+    }
+    pcap_in
+}
+
+/// Set the built-in variable holding the path of the pcap file being read
+fn set_filename(vm: &VM, path: Option<String>) {
+    let path = match path {
+        Some(path) => Object::Str(path),
+        None => Object::Null,
+    };
+    vm.update_builtin_var(BuiltinVarType::Filename, Rc::new(path));
+}
+
+/// Set the built-in variables identifying the filter statement being run.
+/// The 'end' filter does not have an index.
+fn set_filter_vars(vm: &VM, index: Option<usize>, line: usize) {
+    let index = match index {
+        Some(i) => Object::Integer(i as i64),
+        None => Object::Null,
+    };
+    vm.update_builtin_var(BuiltinVarType::FI, Rc::new(index));
+    vm.update_builtin_var(BuiltinVarType::FL, Rc::new(Object::Integer(line as i64)));
+}
+
+/// Run a filter statement on the current packet. Return true if the
+/// packet is to be written to the output pcap stream.
+fn run_filter(vm: &mut VM, filter: &Rc<CompiledFunction>) -> Result<bool, RTError> {
+    vm.push_filter_frame(filter)?;
+    vm.run()?;
+    vm.pop_filter_frame()
+}
+
+/// Display an error writing the output pcap stream. A broken pipe is not
+/// reported since the reader, e.g. 'head', may close the pipe early.
+/// Return the exit code in that case.
+fn report_write_error(err: io::Error) -> Status {
+    if err.kind() == io::ErrorKind::BrokenPipe {
+        return Status {
+            exit: Some(EXIT_BROKEN_PIPE),
+            failed: false,
+        };
+    }
+    eprintln!("{}", err);
+    Status {
+        exit: None,
+        failed: true,
+    }
+}
+
+/// Compile a script, displaying the errors if any. If 'use_cache' is set,
+/// the bytecode is loaded from the cache of compiled scripts if present,
+/// else it is saved there after compiling.
+fn compile_script(source: &str, use_cache: bool, cliargs: &CliArgs) -> Option<Bytecode> {
+    let options = [cliargs.deny_deprecated(), cliargs.ascii_ident()];
+    let path = cache::cache_dir()
+        .filter(|_| use_cache)
+        .map(|dir| cache::cache_path(&dir, source, &options));
+    if let Some(bytecode) = path.as_ref().and_then(|path| cache::load(path).ok()) {
+        return Some(bytecode);
+    }
+
+    let program = parse_program(source, cliargs)?;
+    let mut compiler = Compiler::new();
+    compiler.set_deny_deprecated(cliargs.deny_deprecated());
+    if let Err(e) = compiler.compile(program) {
+        eprintln!("{}", e);
+        return None;
+    }
+    let bytecode = compiler.bytecode();
+    // Failing to cache the script only makes the next run slower
+    if let Some(path) = path.filter(|_| compiler.cacheable()) {
+        let _ = cache::save(&path, &bytecode);
+    }
+    Some(bytecode)
+}
+
+fn parse_program(source: &str, cliargs: &CliArgs) -> Option<Program> {
+    let mut scanner = Scanner::new(source);
+    scanner.set_ascii_ident(cliargs.ascii_ident());
+    let mut parser = Parser::new(scanner);
+    let program = parser.parse_program();
+    if print_parse_errors(&parser) {
+        None
+    } else {
+        Some(program)
+    }
+}
+
+fn print_parse_errors(parser: &parser::Parser) -> bool {
+    if parser.print_errors() {
+        eprintln!("{} parse errors", parser.parse_errors().len());
+        true
+    } else {
+        false
+    }
+}
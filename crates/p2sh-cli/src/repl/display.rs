@@ -0,0 +1,111 @@
+use std::fmt::Write;
+
+use crate::object::Object;
+
+/// Default number of elements of an array or a map echoed in the REPL
+pub const DEFAULT_MAX_PRINT: usize = 100;
+
+/// Options that control how the REPL echoes the values of expressions
+pub struct DisplayOptions {
+    /// Maximum number of elements of an array or a map to display.
+    /// Zero means no limit.
+    pub maxprint: usize,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            maxprint: DEFAULT_MAX_PRINT,
+        }
+    }
+}
+
+impl DisplayOptions {
+    /// Set an option given the arguments to the ':set' command
+    /// e.g. 'maxprint 10'
+    pub fn set(&mut self, args: &str) -> Result<(), String> {
+        let mut parts = args.split_whitespace();
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some("maxprint"), Some(value), None) => {
+                self.maxprint = value
+                    .parse()
+                    .map_err(|_| format!("invalid value for maxprint '{}'", value))?;
+                Ok(())
+            }
+            (Some("maxprint"), None, None) => Err(String::from("usage: :set maxprint <count>")),
+            (Some(name), _, _) => Err(format!("unknown option '{}'", name)),
+            (None, _, _) => {
+                println!("maxprint {}", self.maxprint);
+                Ok(())
+            }
+        }
+    }
+
+    /// Format an object displaying at most 'maxprint' elements of
+    /// arrays, maps and sets, including the nested ones
+    pub fn format(&self, obj: &Object) -> String {
+        let mut out = String::new();
+        self.format_obj(&mut out, obj);
+        out
+    }
+
+    fn format_obj(&self, out: &mut String, obj: &Object) {
+        match obj {
+            Object::Arr(arr) => {
+                let elements = arr.elements.borrow();
+                out.push('[');
+                for (i, elem) in elements.iter().take(self.limit()).enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    self.format_obj(out, elem);
+                }
+                self.write_remaining(out, elements.len());
+                out.push(']');
+            }
+            Object::Map(map) => {
+                let pairs = map.pairs.borrow();
+                out.push_str("map {");
+                for (i, (key, val)) in pairs.iter().take(self.limit()).enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    self.format_obj(out, key);
+                    out.push_str(": ");
+                    self.format_obj(out, val);
+                }
+                self.write_remaining(out, pairs.len());
+                out.push('}');
+            }
+            Object::Set(set) => {
+                let elements = set.elements.borrow();
+                out.push_str("set {");
+                for (i, elem) in elements.iter().take(self.limit()).enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    self.format_obj(out, elem);
+                }
+                self.write_remaining(out, elements.len());
+                out.push('}');
+            }
+            _ => {
+                let _ = write!(out, "{}", obj);
+            }
+        }
+    }
+
+    fn limit(&self) -> usize {
+        if self.maxprint == 0 {
+            usize::MAX
+        } else {
+            self.maxprint
+        }
+    }
+
+    fn write_remaining(&self, out: &mut String, len: usize) {
+        if len > self.limit() {
+            let _ = write!(out, ", ... {} more", len - self.limit());
+        }
+    }
+}
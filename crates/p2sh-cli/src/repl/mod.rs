@@ -0,0 +1,6 @@
+pub mod completion;
+pub mod display;
+pub mod prompt;
+pub mod replay;
+pub mod session;
+pub mod tests;
@@ -3,10 +3,12 @@ use dialoguer::{theme::ColorfulTheme, BasicHistory, Input};
 
 pub const PROMPT_MAIN: &str = ">>";
 pub const PROMPT_CONTINUE: &str = ">";
+pub const PROMPT_DEBUG: &str = "(debug)";
 
 pub struct Prompt {
     history: BasicHistory,
     commands: Commands,
+    prompt: &'static str,
 }
 
 impl Prompt {
@@ -14,16 +16,23 @@ impl Prompt {
         Prompt {
             history: BasicHistory::new().max_entries(entries).no_duplicates(true),
             commands: Commands::new(commands),
+            prompt: PROMPT_MAIN,
         }
     }
 
+    /// Show 'prompt' in place of the main prompt
+    pub fn with_prompt(mut self, prompt: &'static str) -> Self {
+        self.prompt = prompt;
+        self
+    }
+
     pub fn show(&mut self) -> Result<String, dialoguer::Error> {
         let mut input_lines = Vec::new();
 
         loop {
             // Select the prompt for displaying
             let prompt_str = if input_lines.is_empty() {
-                PROMPT_MAIN
+                self.prompt
             } else {
                 PROMPT_CONTINUE
             };
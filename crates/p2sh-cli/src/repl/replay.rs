@@ -0,0 +1,124 @@
+use super::prompt::{PROMPT_CONTINUE, PROMPT_MAIN};
+use super::session::{Output, Session};
+
+/// A line of input in a REPL transcript followed by its expected output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Line number of the input in the transcript
+    pub line: usize,
+    pub input: String,
+    pub expected: Vec<String>,
+}
+
+/// An input for which the output differs from the one in the transcript
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub entry: Entry,
+    pub actual: Vec<String>,
+}
+
+/// Parse a transcript of a REPL session. Each input starts with the main
+/// prompt followed by a space. An input ending with a backslash continues
+/// on the next line, which starts with the continuation prompt. All the
+/// other lines are the output expected from the input before them.
+pub fn parse_transcript(text: &str) -> Result<Vec<Entry>, String> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut continued = false;
+    for (i, line) in text.lines().enumerate() {
+        if continued {
+            let Some(rest) = strip_prompt(line, PROMPT_CONTINUE) else {
+                return Err(format!(
+                    "line {}: expected the continuation of the input",
+                    i + 1
+                ));
+            };
+            continued = rest.trim_end().ends_with('\\');
+            if let Some(entry) = entries.last_mut() {
+                entry.input.push('\n');
+                entry.input.push_str(rest.trim_end().trim_end_matches('\\'));
+            }
+        } else if let Some(input) = strip_prompt(line, PROMPT_MAIN) {
+            continued = input.trim_end().ends_with('\\');
+            entries.push(Entry {
+                line: i + 1,
+                input: input.trim_end().trim_end_matches('\\').to_string(),
+                expected: Vec::new(),
+            });
+        } else if let Some(entry) = entries.last_mut() {
+            entry.expected.push(line.to_string());
+        } else if !line.trim().is_empty() {
+            return Err(format!(
+                "line {}: expected an input starting with '{}'",
+                i + 1,
+                PROMPT_MAIN
+            ));
+        }
+    }
+    if continued {
+        return Err(String::from("unexpected end of the transcript"));
+    }
+    // Blank lines separating the inputs are not a part of the output
+    for entry in &mut entries {
+        while entry.expected.last().is_some_and(|l| l.trim().is_empty()) {
+            entry.expected.pop();
+        }
+    }
+    Ok(entries)
+}
+
+fn strip_prompt<'a>(line: &'a str, prompt: &str) -> Option<&'a str> {
+    let rest = line.strip_prefix(prompt)?;
+    if rest.is_empty() {
+        Some(rest)
+    } else {
+        rest.strip_prefix(' ')
+    }
+}
+
+/// Lines of the output of an input as displayed in the REPL. The text
+/// printed without a newline at the end runs into the line that follows.
+pub fn output_lines(output: &[Output]) -> Vec<String> {
+    let mut text = String::new();
+    for out in output {
+        match out {
+            Output::Print(s) => text.push_str(s),
+            Output::Value(s) | Output::Error(s) => {
+                text.push_str(s);
+                text.push('\n');
+            }
+            Output::Exit(code) => text.push_str(&format!("exit({})\n", code)),
+        }
+    }
+    text.lines().map(String::from).collect()
+}
+
+/// Feed the inputs of a transcript to a session in order and compare the
+/// outputs, including the text printed by the builtins, with the expected
+/// ones. The replay stops at 'quit' or when the session requests to exit.
+/// Returns the number of inputs evaluated, the exit code requested if any,
+/// and the mismatches.
+pub fn replay(session: &mut Session, entries: &[Entry]) -> (usize, Option<i32>, Vec<Mismatch>) {
+    session.set_capture(true);
+    let mut mismatches = Vec::new();
+    for (count, entry) in entries.iter().enumerate() {
+        if entry.input == "quit" {
+            return (count, None, mismatches);
+        }
+        let output = session.eval(&entry.input);
+        let actual = output_lines(&output);
+        if actual != entry.expected {
+            mismatches.push(Mismatch {
+                entry: entry.clone(),
+                actual,
+            });
+        }
+        let exit = output.iter().find_map(|out| match out {
+            Output::Exit(code) => Some(*code),
+            _ => None,
+        });
+        if exit.is_some() {
+            return (count + 1, exit, mismatches);
+        }
+    }
+    (entries.len(), None, mismatches)
+}
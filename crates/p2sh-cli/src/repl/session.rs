@@ -0,0 +1,194 @@
+use std::rc::Rc;
+
+use super::display::DisplayOptions;
+use crate::builtins::functions::BUILTINFNS;
+use crate::builtins::print::{capture_stdout, take_captured};
+use crate::builtins::variables::BuiltinVarType;
+use crate::compiler::symtab::SymbolTable;
+use crate::compiler::Compiler;
+use crate::object::func::Closure;
+use crate::object::Object;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::vm::interpreter::{GLOBALS_SIZE, VM};
+use p2sh_dissect::ports::PortMap;
+
+/// Output of evaluating a line in the REPL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Output {
+    /// Text printed to stdout by the builtins if captured
+    Print(String),
+    /// Value of the expression on the line
+    Value(String),
+    /// Parse, compile or runtime error
+    Error(String),
+    /// Request to exit the program with the exit code
+    Exit(i32),
+}
+
+/// State of a REPL session that is carried over from one line to the
+/// next: the symbols defined, the constants, the globals, the
+/// functions registered using 'atexit' and the ports mapped using
+/// 'decode_as'.
+pub struct Session {
+    symtab: SymbolTable,
+    constants: Vec<Rc<Object>>,
+    globals: Vec<Rc<Object>>,
+    exit_handlers: Vec<Rc<Closure>>,
+    port_map: PortMap,
+    args: Vec<String>,
+    deny_deprecated: bool,
+    ascii_ident: bool,
+    capture: bool,
+    pub options: DisplayOptions,
+}
+
+impl Session {
+    pub fn new(args: Vec<String>, deny_deprecated: bool, ascii_ident: bool) -> Self {
+        let mut symtab = SymbolTable::default();
+        for (i, sym) in BUILTINFNS.iter().enumerate() {
+            // Define the built-in function via an index into the 'BUILTINS' array
+            symtab.define_builtin_fn(i, sym.name);
+        }
+        // Define the built-in variables
+        for n in BuiltinVarType::range() {
+            let name: &str = BuiltinVarType::from(n).into();
+            symtab.define_builtin_var(n, name);
+        }
+        let data = Rc::new(Object::Null);
+        Self {
+            symtab,
+            constants: vec![],
+            globals: vec![data; GLOBALS_SIZE],
+            exit_handlers: Vec::new(),
+            port_map: PortMap::default(),
+            args,
+            deny_deprecated,
+            ascii_ident,
+            capture: false,
+            options: DisplayOptions::default(),
+        }
+    }
+
+    /// Make the text printed to stdout by the builtins, such as 'println',
+    /// a part of the output of the lines evaluated from now on
+    pub fn set_capture(&mut self, capture: bool) {
+        self.capture = capture;
+    }
+
+    /// Evaluate a line, which may be a ':set' or a ':disasm' command, and
+    /// return its output. The value of an expression is not part of the
+    /// output if it is null.
+    pub fn eval(&mut self, line: &str) -> Vec<Output> {
+        if let Some(args) = line.trim().strip_prefix(":set") {
+            return match self.options.set(args) {
+                Ok(()) => vec![],
+                Err(e) => vec![Output::Error(e)],
+            };
+        }
+        // ':disasm' shows the bytecode the rest of the line compiles to
+        // without running it
+        let (line, disasm) = match line.trim().strip_prefix(":disasm") {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() {
+            return vec![];
+        }
+        let mut scanner = Scanner::new(line);
+        scanner.set_ascii_ident(self.ascii_ident);
+        let mut parser = Parser::new(scanner);
+        let program = parser.parse_program();
+        let errors = parser.parse_errors();
+        if !errors.is_empty() {
+            let mut output: Vec<_> = errors.iter().cloned().map(Output::Error).collect();
+            output.push(Output::Error(format!("{} parse errors", errors.len())));
+            return output;
+        }
+
+        // Snapshot of the state to roll back to if the line fails
+        let snapshot = self.symtab.clone();
+        let num_constants = self.constants.len();
+
+        let symtab = std::mem::take(&mut self.symtab);
+        let constants = std::mem::take(&mut self.constants);
+        let mut compiler = Compiler::new_with_state(symtab, constants);
+        compiler.set_deny_deprecated(self.deny_deprecated);
+        let bytecode = compiler.compile(program).map(|_| compiler.bytecode());
+        self.symtab = compiler.symtab;
+        self.constants = compiler.constants;
+        let bytecode = match bytecode {
+            Ok(bytecode) => bytecode,
+            Err(e) => {
+                self.rollback(snapshot, num_constants);
+                return vec![Output::Error(e.to_string())];
+            }
+        };
+        if disasm {
+            let listing = bytecode.disassemble_from(num_constants);
+            self.rollback(snapshot, num_constants);
+            return vec![Output::Value(listing.trim_end().to_string())];
+        }
+
+        let globals = std::mem::take(&mut self.globals);
+        let mut vm = VM::new_with_global_store(bytecode, globals);
+        vm.set_exit_handlers(std::mem::take(&mut self.exit_handlers));
+        vm.set_port_map(self.port_map.clone());
+        vm.init_builtin_vars(self.args.clone());
+        capture_stdout(self.capture);
+        let result = vm.run();
+        let printed = take_captured();
+        capture_stdout(false);
+        self.exit_handlers = vm.take_exit_handlers();
+        self.port_map = vm.port_map().clone();
+        let mut output = match result {
+            Err(err) => match err.exit {
+                Some(code) => vec![Output::Exit(code)],
+                None => vec![Output::Error(err.to_string())],
+            },
+            Ok(()) => {
+                // Echo the object at the top of the VM's stack unless it is null
+                let stack_elem = vm.last_popped();
+                match stack_elem.as_ref() {
+                    Object::Null => vec![],
+                    obj => vec![Output::Value(self.options.format(obj))],
+                }
+            }
+        };
+        self.globals = vm.globals;
+        if let [Output::Error(_)] = output[..] {
+            self.rollback(snapshot, num_constants);
+        }
+        // The text printed comes before the value of the line
+        if !printed.is_empty() {
+            output.insert(0, Output::Print(printed));
+        }
+        output
+    }
+
+    /// Undo the symbols defined and the constants added by a line that
+    /// failed to compile or run, so that a name defined by the line does
+    /// not resolve to a global that was never assigned. The globals of the
+    /// symbols undone are cleared.
+    fn rollback(&mut self, snapshot: SymbolTable, num_constants: usize) {
+        let start = snapshot.get_num_definitions();
+        // The compiler may have stopped within the scope of a function
+        let end = self.symtab.global_table().get_num_definitions();
+        let end = end.min(self.globals.len());
+        if start < end {
+            let data = Rc::new(Object::Null);
+            self.globals[start..end].fill(data);
+        }
+        self.symtab = snapshot;
+        self.constants.truncate(num_constants);
+    }
+
+    /// End the session running the functions registered using 'atexit'.
+    /// Returns the exit code requested either earlier or by those functions.
+    pub fn finish(self, exit: Option<i32>) -> Option<i32> {
+        let bytecode = Compiler::new_with_state(self.symtab, self.constants).bytecode();
+        let mut vm = VM::new_with_global_store(bytecode, self.globals);
+        vm.set_exit_handlers(self.exit_handlers);
+        vm.run_exit_handlers().or(exit)
+    }
+}
@@ -0,0 +1,183 @@
+#![allow(unused_imports)]
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::display::*;
+use super::replay::*;
+use super::session::*;
+use crate::object::array::Array;
+use crate::object::hmap::HMap;
+use crate::object::Object;
+
+#[cfg(test)]
+fn make_array(n: i64) -> Rc<Object> {
+    let elements = (0..n).map(|i| Rc::new(Object::Integer(i))).collect();
+    Rc::new(Object::Arr(Rc::new(Array::new(elements))))
+}
+
+#[test]
+fn test_display_truncation() {
+    let mut options = DisplayOptions::default();
+    options.set("maxprint 3").unwrap();
+    assert_eq!(options.maxprint, 3);
+    assert_eq!(options.format(&make_array(3)), "[0, 1, 2]");
+    assert_eq!(options.format(&make_array(10)), "[0, 1, 2, ... 7 more]");
+    assert_eq!(options.format(&make_array(0)), "[]");
+    assert_eq!(options.format(&Object::Integer(5)), "5");
+
+    // Nested arrays are truncated as well
+    let nested = Object::Arr(Rc::new(Array::new(vec![make_array(5), make_array(1)])));
+    assert_eq!(options.format(&nested), "[[0, 1, 2, ... 2 more], [0]]");
+
+    #[allow(clippy::mutable_key_type)]
+    let mut pairs = HashMap::new();
+    for i in 0..5 {
+        pairs.insert(Rc::new(Object::Integer(i)), Rc::new(Object::Integer(i)));
+    }
+    let map = Object::Map(Rc::new(HMap::new(pairs)));
+    let out = options.format(&map);
+    assert!(out.starts_with("map {"), "{}", out);
+    assert!(out.ends_with(", ... 2 more}"), "{}", out);
+
+    // Zero removes the limit
+    options.set(" maxprint 0").unwrap();
+    assert_eq!(options.format(&make_array(5)), make_array(5).to_string());
+
+    assert!(options.set("maxprint x").is_err());
+    assert!(options.set("maxprint").is_err());
+    assert!(options.set("color on").is_err());
+}
+
+#[test]
+fn test_session_state() {
+    let mut session = Session::new(vec![], false, false);
+    assert_eq!(
+        session.eval("let a = 2;"),
+        vec![Output::Value("2".to_string())]
+    );
+    assert_eq!(session.eval(""), vec![]);
+    session.eval("let f = fn(x) { x * a };");
+    assert_eq!(session.eval("f(3)"), vec![Output::Value("6".to_string())]);
+    assert_eq!(session.eval(":set maxprint 2"), vec![]);
+    assert_eq!(
+        session.eval("[1, 2, 3]"),
+        vec![Output::Value("[1, 2, ... 1 more]".to_string())]
+    );
+    assert!(matches!(session.eval("b")[..], [Output::Error(_)]));
+    assert!(matches!(
+        session.eval("let = 1")[..],
+        [Output::Error(_), Output::Error(_)]
+    ));
+    assert_eq!(session.eval("exit(3)"), vec![Output::Exit(3)]);
+    assert_eq!(session.finish(None), None);
+}
+
+#[test]
+fn test_parse_transcript() {
+    let text = ">> let a = 1;\n>> a + 1\n2\n\n>> fn(x) {\\\n> x }\n<closure>\n";
+    let entries = parse_transcript(text).unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].input, "let a = 1;");
+    assert!(entries[0].expected.is_empty());
+    assert_eq!(entries[1].expected, vec!["2".to_string()]);
+    assert_eq!(entries[2].line, 5);
+    assert_eq!(entries[2].input, "fn(x) {\nx }");
+
+    assert_eq!(
+        parse_transcript("1\n>> 1").unwrap_err(),
+        "line 1: expected an input starting with '>>'"
+    );
+    assert_eq!(
+        parse_transcript(">> 1 \\\n2").unwrap_err(),
+        "line 2: expected the continuation of the input"
+    );
+    assert_eq!(
+        parse_transcript(">> 1 \\").unwrap_err(),
+        "unexpected end of the transcript"
+    );
+}
+
+#[test]
+fn test_replay_transcript() {
+    let text = ">> let a = 2;\n2\n>> a * 3\n6\n>> a + 1\n4\n>> exit(2)\nexit(2)\n>> a\n";
+    let entries = parse_transcript(text).unwrap();
+    let mut session = Session::new(vec![], false, false);
+    let (count, exit, mismatches) = replay(&mut session, &entries);
+    assert_eq!(count, 4);
+    assert_eq!(exit, Some(2));
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].entry.line, 5);
+    assert_eq!(mismatches[0].actual, vec!["3".to_string()]);
+
+    // The text printed is a part of the output
+    let text = ">> println(\"hi\")\nhi\n3\n>> let a = 4; println(\"{}\", a); puts(a + 1);\n4\n5\n\
+                >> print(\"x\"); a\nx4\n>> println(\"no\")\nyes\n3\n";
+    let entries = parse_transcript(text).unwrap();
+    let mut session = Session::new(vec![], false, false);
+    let (count, exit, mismatches) = replay(&mut session, &entries);
+    assert_eq!((count, exit), (4, None));
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].entry.line, 9);
+    assert_eq!(
+        mismatches[0].actual,
+        vec!["no".to_string(), "3".to_string()]
+    );
+
+    let entries = parse_transcript(">> 1\n1\n>> quit\n>> 2\n").unwrap();
+    let mut session = Session::new(vec![], false, false);
+    assert_eq!(replay(&mut session, &entries), (1, None, vec![]));
+}
+
+#[test]
+fn test_session_rollback() {
+    let mut session = Session::new(vec![], false, false);
+    session.eval("let a = 1;");
+
+    // Symbols defined by a line that fails to compile are undone
+    assert!(matches!(
+        session.eval("let x = undefined_fn();")[..],
+        [Output::Error(_)]
+    ));
+    assert!(matches!(session.eval("x")[..], [Output::Error(_)]));
+    assert!(matches!(
+        session.eval("let f = fn() { let y = 1; z };")[..],
+        [Output::Error(_)]
+    ));
+    assert!(matches!(session.eval("f")[..], [Output::Error(_)]));
+
+    // Symbols defined by a line that fails at runtime are undone as well
+    assert!(matches!(
+        session.eval("let b = 2; let c = 1 / 0;")[..],
+        [Output::Error(_)]
+    ));
+    assert!(matches!(session.eval("b")[..], [Output::Error(_)]));
+    assert!(matches!(session.eval("c")[..], [Output::Error(_)]));
+
+    // The symbols defined earlier are intact
+    assert_eq!(session.eval("a"), vec![Output::Value("1".to_string())]);
+    session.eval("let d = [a, 2];");
+    assert_eq!(session.eval("d"), vec![Output::Value("[1, 2]".to_string())]);
+}
+
+#[test]
+fn test_session_disasm() {
+    let mut session = Session::new(vec![], false, false);
+    session.eval("let x = 5;");
+    let expected = "\
+== main ==
+0000 OpGetGlobal 0            ; x
+0003 OpJumpIfFalse 12         ; L0
+0006 OpConstant 1             ; \"yes\"
+0009 OpJump 13                ; L1
+L0:
+0012 OpNull
+L1:
+0013 OpPop";
+    assert_eq!(
+        session.eval(":disasm if x { \"yes\" }"),
+        vec![Output::Value(expected.to_string())]
+    );
+    // The line is not run and what it defines is not kept
+    assert_eq!(session.eval(":disasm let y = exit(1);").len(), 1);
+    assert!(matches!(session.eval("y")[..], [Output::Error(_)]));
+}
@@ -0,0 +1,167 @@
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+
+const P2SH: &str = env!("CARGO_BIN_EXE_p2sh");
+
+#[test]
+fn test_print_to_closed_pipe() {
+    let mut child = Command::new(P2SH)
+        .args(["-c", "let i = 0; loop { println(\"{}\", i); i = i + 1; }"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Close the pipe after reading the first line as 'head -1' would
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    assert_eq!(line, "0\n");
+    drop(stdout);
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(141));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn test_fields() {
+    let fields = run_p2sh(&["fields".as_ref(), "udp".as_ref()]);
+    assert!(fields.lines().all(|line| line.starts_with("udp.")));
+    assert!(!fields.is_empty());
+
+    // An object that is not known is an error
+    let output = Command::new(P2SH)
+        .args(["fields", "bogus"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(stderr.starts_with("unknown object 'bogus'"), "{}", stderr);
+}
+
+// Write a file to the temporary directory, named after the test
+fn write_temp(name: &str, contents: &[u8]) -> PathBuf {
+    let path = env::temp_dir().join(format!("p2sh-cli-{}-{}", process::id(), name));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+// Write a little endian pcap file of ethernet packets of the given lengths
+fn write_pcap(name: &str, lens: &[u32]) -> PathBuf {
+    let mut bytes = Vec::new();
+    for field in [0xA1B2C3D4u32, 4 << 16 | 2, 0, 0, 65535, 1] {
+        bytes.extend_from_slice(&field.to_le_bytes());
+    }
+    for (i, len) in lens.iter().enumerate() {
+        for field in [i as u32, 0, *len, *len] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes.extend(vec![0; *len as usize]);
+    }
+    write_temp(name, &bytes)
+}
+
+// Run p2sh and return its standard output, checking that it succeeded
+fn run_p2sh(args: &[&OsStr]) -> String {
+    let output = Command::new(P2SH).args(args).output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{:?} failed: {}", args, stderr);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+const FILTER_SCRIPT: &str = r#"
+@ PL < 35 { println("{} {} {}", FI, FL, PL) }
+@ PL > 25 {
+    println("{} {} {}", FI, FL, PL)
+}
+@ end { println("{} {} {}", FI, FL, NP) }
+"#;
+
+const FILTER_OUTPUT: &str = "\
+1 2 20
+1 2 30
+2 3 30
+2 3 40
+null 6 3
+";
+
+#[test]
+fn test_filter_vars_across_files() {
+    let script = write_temp("filter-vars.p2", FILTER_SCRIPT.as_bytes());
+    let first = write_pcap("filter-vars-1.pcap", &[20, 30]);
+    let second = write_pcap("filter-vars-2.pcap", &[40]);
+    let stdout = run_p2sh(&[
+        "-s".as_ref(),
+        "-r".as_ref(),
+        first.as_ref(),
+        "-r".as_ref(),
+        second.as_ref(),
+        script.as_ref(),
+    ]);
+    assert_eq!(stdout, FILTER_OUTPUT);
+    for path in [script, first, second] {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[test]
+fn test_compile_and_exec() {
+    let script = write_temp("exec.p2", FILTER_SCRIPT.as_bytes());
+    let compiled = env::temp_dir().join(format!("p2sh-cli-{}-exec.p2c", process::id()));
+    let pcap = write_pcap("exec.pcap", &[20, 30, 40]);
+    run_p2sh(&["--compile".as_ref(), compiled.as_ref(), script.as_ref()]);
+    let run = |path: &Path| {
+        let args = [
+            "-s".as_ref(),
+            "-r".as_ref(),
+            pcap.as_os_str(),
+            "--exec".as_ref(),
+            path.as_os_str(),
+        ];
+        Command::new(P2SH).args(args).output().unwrap()
+    };
+    // The compiled script prints what the script does
+    let expected = run_p2sh(&["-s".as_ref(), "-r".as_ref(), pcap.as_ref(), script.as_ref()]);
+    assert_eq!(expected, FILTER_OUTPUT);
+    let output = run(&compiled);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+
+    // A file compiled by another version of p2sh, or by an older format of
+    // the bytecode, or that is not compiled at all, is not run
+    let bytecode = fs::read(&compiled).unwrap();
+    let version = env!("CARGO_PKG_VERSION").as_bytes();
+    let at = bytecode
+        .windows(version.len())
+        .position(|w| w == version)
+        .unwrap();
+    let mut other_version = bytecode.clone();
+    other_version[at] = b'9';
+    let mut other_format = bytecode.clone();
+    other_format[4] = other_format[4].wrapping_add(1);
+    let rejected = [
+        ("other-version.p2c", other_version, "compiled by p2sh 9"),
+        ("other-format.p2c", other_format, "not a compiled script"),
+        (
+            "script.p2c",
+            FILTER_SCRIPT.as_bytes().to_vec(),
+            "not a compiled script",
+        ),
+    ];
+    for (name, contents, expected) in rejected {
+        let path = write_temp(name, &contents);
+        let output = run(&path);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(1), "{}", name);
+        assert!(output.stdout.is_empty(), "{}", name);
+        assert!(stderr.contains(expected), "{}: {}", name, stderr);
+        let _ = fs::remove_file(path);
+    }
+    for path in [script, compiled, pcap] {
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,480 @@
+use std::net::IpAddr;
+
+use super::pcap::PcapPacket;
+use p2sh_dissect::ethernet::EtherTypes;
+use p2sh_dissect::ipv4::Protocols;
+
+// Link types of the packets the pre-filter can decode
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+// IPv6 extension headers that are skipped to find the upper layer protocol
+const IPV6_EXT_HEADERS: [u8; 4] = [0, 43, 44, 60];
+const IPV6_FRAGMENT: u8 = 44;
+const IPPROTO_ICMPV6: u8 = 58;
+
+/// Protocols that can be used as qualifiers in a pre-filter expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BpfProto {
+    Ip,
+    Ip6,
+    Arp,
+    Vlan,
+    Tcp,
+    Udp,
+    Icmp,
+    Icmp6,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BpfDir {
+    Src,
+    Dst,
+    Any,
+}
+
+/// A pre-filter expression in a subset of the pcap-filter syntax
+#[derive(Debug, Clone, PartialEq)]
+pub enum BpfExpr {
+    And(Box<BpfExpr>, Box<BpfExpr>),
+    Or(Box<BpfExpr>, Box<BpfExpr>),
+    Not(Box<BpfExpr>),
+    Proto(BpfProto),
+    Net(BpfDir, IpAddr, u8),
+    PortRange(BpfDir, u16, u16),
+    // Packet length compared to a value using an operator
+    Len(String, u32),
+}
+
+// The parts of a packet that the expressions refer to
+#[derive(Default)]
+struct Decoded {
+    ethertype: Option<u16>,
+    vlan: bool,
+    addrs: Option<(IpAddr, IpAddr)>,
+    proto: Option<u8>,
+    ports: Option<(u16, u16)>,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn ip_addr(data: &[u8], offset: usize, len: usize) -> Option<IpAddr> {
+    let bytes = data.get(offset..offset + len)?;
+    match len {
+        4 => <[u8; 4]>::try_from(bytes).ok().map(IpAddr::from),
+        _ => <[u8; 16]>::try_from(bytes).ok().map(IpAddr::from),
+    }
+}
+
+impl Decoded {
+    fn new(data: &[u8], linktype: u32) -> Self {
+        let mut decoded = Decoded::default();
+        let offset = match linktype {
+            LINKTYPE_ETHERNET => {
+                // Skip the VLAN tags if any
+                let mut offset = 12;
+                let mut ethertype = read_u16(data, offset);
+                while ethertype == Some(EtherTypes::Vlan.0) || ethertype == Some(EtherTypes::QinQ.0)
+                {
+                    decoded.vlan = true;
+                    offset += 4;
+                    ethertype = read_u16(data, offset);
+                }
+                decoded.ethertype = ethertype;
+                offset + 2
+            }
+            LINKTYPE_RAW => {
+                decoded.ethertype = match data.first().map(|b| b >> 4) {
+                    Some(4) => Some(EtherTypes::Ipv4.0),
+                    Some(6) => Some(EtherTypes::Ipv6.0),
+                    _ => None,
+                };
+                0
+            }
+            _ => return decoded,
+        };
+        match decoded.ethertype {
+            Some(t) if t == EtherTypes::Ipv4.0 => decoded.decode_ipv4(data, offset),
+            Some(t) if t == EtherTypes::Ipv6.0 => decoded.decode_ipv6(data, offset),
+            _ => {}
+        }
+        decoded
+    }
+
+    fn decode_ipv4(&mut self, data: &[u8], offset: usize) {
+        let Some(&ver_ihl) = data.get(offset) else {
+            return;
+        };
+        let (Some(src), Some(dst)) = (ip_addr(data, offset + 12, 4), ip_addr(data, offset + 16, 4))
+        else {
+            return;
+        };
+        self.addrs = Some((src, dst));
+        self.proto = data.get(offset + 9).copied();
+        // Only the first fragment has the ports
+        let frag_offset = read_u16(data, offset + 6).unwrap_or(0) & 0x1fff;
+        if frag_offset == 0 {
+            self.decode_ports(data, offset + (ver_ihl & 0x0f) as usize * 4);
+        }
+    }
+
+    fn decode_ipv6(&mut self, data: &[u8], offset: usize) {
+        let (Some(src), Some(dst)) = (
+            ip_addr(data, offset + 8, 16),
+            ip_addr(data, offset + 24, 16),
+        ) else {
+            return;
+        };
+        self.addrs = Some((src, dst));
+        let mut next = data.get(offset + 6).copied();
+        let mut offset = offset + 40;
+        while let Some(header) = next.filter(|h| IPV6_EXT_HEADERS.contains(h)) {
+            if header == IPV6_FRAGMENT && read_u16(data, offset + 2).unwrap_or(0) & 0xfff8 != 0 {
+                // Not the first fragment
+                self.proto = data.get(offset).copied();
+                return;
+            }
+            next = data.get(offset).copied();
+            offset += match (header, data.get(offset + 1)) {
+                (IPV6_FRAGMENT, _) => 8,
+                (_, Some(len)) => (*len as usize + 1) * 8,
+                (_, None) => return,
+            };
+        }
+        self.proto = next;
+        self.decode_ports(data, offset);
+    }
+
+    fn decode_ports(&mut self, data: &[u8], offset: usize) {
+        let has_ports = [Protocols::Tcp.0, Protocols::Udp.0, Protocols::Sctp.0];
+        if self.proto.is_some_and(|p| has_ports.contains(&p)) {
+            if let (Some(src), Some(dst)) = (read_u16(data, offset), read_u16(data, offset + 2)) {
+                self.ports = Some((src, dst));
+            }
+        }
+    }
+}
+
+impl BpfExpr {
+    /// Compile a pre-filter expression
+    pub fn compile(source: &str) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        if tokens.is_empty() {
+            return Err(String::from("empty expression"));
+        }
+        let mut parser = BpfParser {
+            tokens,
+            pos: 0,
+            last: None,
+        };
+        let expr = parser.parse_or()?;
+        match parser.peek() {
+            Some(token) => Err(format!("unexpected '{}'", token)),
+            None => Ok(expr),
+        }
+    }
+
+    /// Check if a packet with the given link type matches the expression
+    pub fn matches(&self, pkt: &PcapPacket, linktype: u32) -> bool {
+        let decoded = Decoded::new(&pkt.rawdata.borrow(), linktype);
+        self.eval(&decoded, pkt.get_wirelen_raw() as usize)
+    }
+
+    fn eval(&self, pkt: &Decoded, wirelen: usize) -> bool {
+        match self {
+            BpfExpr::And(a, b) => a.eval(pkt, wirelen) && b.eval(pkt, wirelen),
+            BpfExpr::Or(a, b) => a.eval(pkt, wirelen) || b.eval(pkt, wirelen),
+            BpfExpr::Not(a) => !a.eval(pkt, wirelen),
+            BpfExpr::Proto(proto) => match proto {
+                BpfProto::Ip => pkt.ethertype == Some(EtherTypes::Ipv4.0),
+                BpfProto::Ip6 => pkt.ethertype == Some(EtherTypes::Ipv6.0),
+                BpfProto::Arp => pkt.ethertype == Some(EtherTypes::Arp.0),
+                BpfProto::Vlan => pkt.vlan,
+                BpfProto::Tcp => pkt.proto == Some(Protocols::Tcp.0),
+                BpfProto::Udp => pkt.proto == Some(Protocols::Udp.0),
+                BpfProto::Icmp => {
+                    pkt.ethertype == Some(EtherTypes::Ipv4.0)
+                        && pkt.proto == Some(Protocols::Icmp.0)
+                }
+                BpfProto::Icmp6 => pkt.proto == Some(IPPROTO_ICMPV6),
+            },
+            BpfExpr::Net(dir, net, prefix) => pkt.addrs.is_some_and(|(src, dst)| {
+                let in_net = |addr: IpAddr| in_network(addr, *net, *prefix);
+                match dir {
+                    BpfDir::Src => in_net(src),
+                    BpfDir::Dst => in_net(dst),
+                    BpfDir::Any => in_net(src) || in_net(dst),
+                }
+            }),
+            BpfExpr::PortRange(dir, lo, hi) => pkt.ports.is_some_and(|(src, dst)| {
+                let in_range = |port: u16| (*lo..=*hi).contains(&port);
+                match dir {
+                    BpfDir::Src => in_range(src),
+                    BpfDir::Dst => in_range(dst),
+                    BpfDir::Any => in_range(src) || in_range(dst),
+                }
+            }),
+            BpfExpr::Len(op, n) => {
+                let len = wirelen as u32;
+                match op.as_str() {
+                    "<" => len < *n,
+                    "<=" => len <= *n,
+                    ">" => len > *n,
+                    ">=" => len >= *n,
+                    "!=" => len != *n,
+                    _ => len == *n,
+                }
+            }
+        }
+    }
+}
+
+// Check if an address is in a network given its prefix length
+fn in_network(addr: IpAddr, net: IpAddr, prefix: u8) -> bool {
+    let (addr, net, bits) = match (addr, net) {
+        (IpAddr::V4(a), IpAddr::V4(n)) => (u32::from(a) as u128, u32::from(n) as u128, 32),
+        (IpAddr::V6(a), IpAddr::V6(n)) => (u128::from(a), u128::from(n), 128),
+        _ => return false,
+    };
+    let shift = bits - prefix as u32;
+    shift >= bits || addr >> shift == net >> shift
+}
+
+fn tokenize(source: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' | ')' => tokens.push(c.to_string()),
+            '!' | '<' | '>' | '=' => {
+                if chars.next_if_eq(&'=').is_some() {
+                    tokens.push(format!("{}=", c));
+                } else {
+                    tokens.push(c.to_string());
+                }
+            }
+            '&' | '|' => {
+                if chars.next_if_eq(&c).is_none() {
+                    return Err(format!("unexpected '{}'", c));
+                }
+                tokens.push(format!("{}{}", c, c));
+            }
+            c if c.is_ascii_alphanumeric() || ":./-_".contains(c) => {
+                let mut word = c.to_string();
+                while let Some(c) =
+                    chars.next_if(|c| c.is_ascii_alphanumeric() || ":./-_".contains(*c))
+                {
+                    word.push(c);
+                }
+                tokens.push(word);
+            }
+            _ => return Err(format!("unexpected '{}'", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Clone, Copy)]
+enum BpfKind {
+    Host,
+    Net,
+    Port,
+    PortRange,
+}
+
+// The qualifiers of a primitive that are applied to the identifiers that
+// follow without qualifiers, as in 'port 80 or 443'.
+#[derive(Clone, Copy)]
+struct Qualifiers {
+    proto: Option<BpfProto>,
+    dir: BpfDir,
+    kind: BpfKind,
+}
+
+struct BpfParser {
+    tokens: Vec<String>,
+    pos: usize,
+    last: Option<Qualifiers>,
+}
+
+impl BpfParser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|t| t.as_str())
+    }
+
+    fn next(&mut self) -> Result<String, String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token.ok_or_else(|| String::from("unexpected end of expression"))
+    }
+
+    fn accept(&mut self, tokens: &[&str]) -> bool {
+        if self.peek().is_some_and(|t| tokens.contains(&t)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<BpfExpr, String> {
+        let mut expr = self.parse_and()?;
+        while self.accept(&["or", "||"]) {
+            expr = BpfExpr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<BpfExpr, String> {
+        let mut expr = self.parse_not()?;
+        while self.accept(&["and", "&&"]) {
+            expr = BpfExpr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<BpfExpr, String> {
+        if self.accept(&["not", "!"]) {
+            return Ok(BpfExpr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primitive()
+    }
+
+    fn parse_primitive(&mut self) -> Result<BpfExpr, String> {
+        let token = self.next()?;
+        match token.as_str() {
+            "(" => {
+                let expr = self.parse_or()?;
+                if self.next()? != ")" {
+                    return Err(String::from("expected ')'"));
+                }
+                Ok(expr)
+            }
+            "len" => {
+                let op = self.next()?;
+                if !["<", "<=", ">", ">=", "=", "==", "!="].contains(&op.as_str()) {
+                    return Err(format!(
+                        "expected a comparison operator after 'len', got '{}'",
+                        op
+                    ));
+                }
+                Ok(BpfExpr::Len(op, self.parse_number()?))
+            }
+            "less" => Ok(BpfExpr::Len(String::from("<="), self.parse_number()?)),
+            "greater" => Ok(BpfExpr::Len(String::from(">="), self.parse_number()?)),
+            _ => {
+                self.pos -= 1;
+                self.parse_qualified()
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<u32, String> {
+        let token = self.next()?;
+        token
+            .parse()
+            .map_err(|_| format!("expected a number, got '{}'", token))
+    }
+
+    // [proto] [src|dst] [host|net|port|portrange] [id]
+    fn parse_qualified(&mut self) -> Result<BpfExpr, String> {
+        let proto = self.peek().and_then(parse_proto);
+        if proto.is_some() {
+            self.pos += 1;
+        }
+        let dir = match self.peek() {
+            Some("src") => BpfDir::Src,
+            Some("dst") => BpfDir::Dst,
+            _ => BpfDir::Any,
+        };
+        if dir != BpfDir::Any {
+            self.pos += 1;
+        }
+        let kind = match self.peek() {
+            Some("host") => Some(BpfKind::Host),
+            Some("net") => Some(BpfKind::Net),
+            Some("port") => Some(BpfKind::Port),
+            Some("portrange") => Some(BpfKind::PortRange),
+            _ => None,
+        };
+        if kind.is_some() {
+            self.pos += 1;
+        }
+        let qualifiers = match (proto, dir, kind, self.last) {
+            // A protocol by itself
+            (Some(proto), BpfDir::Any, None, _) => return Ok(BpfExpr::Proto(proto)),
+            // An identifier without qualifiers uses the previous ones
+            (None, BpfDir::Any, None, Some(last)) => last,
+            _ => Qualifiers {
+                proto,
+                dir,
+                kind: kind.unwrap_or(BpfKind::Host),
+            },
+        };
+        let id = self.next()?;
+        let expr = self.parse_id(&qualifiers, &id)?;
+        self.last = Some(qualifiers);
+        Ok(match qualifiers.proto.map(BpfExpr::Proto) {
+            Some(proto) => BpfExpr::And(Box::new(proto), Box::new(expr)),
+            None => expr,
+        })
+    }
+
+    fn parse_id(&self, qualifiers: &Qualifiers, id: &str) -> Result<BpfExpr, String> {
+        let dir = qualifiers.dir;
+        match qualifiers.kind {
+            BpfKind::Host => {
+                let addr: IpAddr = id
+                    .parse()
+                    .map_err(|_| format!("invalid host address '{}'", id))?;
+                let prefix = if addr.is_ipv4() { 32 } else { 128 };
+                Ok(BpfExpr::Net(dir, addr, prefix))
+            }
+            BpfKind::Net => {
+                let (addr, prefix) = id.split_once('/').unwrap_or((id, ""));
+                let addr: IpAddr = addr
+                    .parse()
+                    .map_err(|_| format!("invalid network '{}'", id))?;
+                let max = if addr.is_ipv4() { 32 } else { 128 };
+                let prefix = match prefix {
+                    "" => max,
+                    p => p
+                        .parse()
+                        .ok()
+                        .filter(|p| *p <= max)
+                        .ok_or_else(|| format!("invalid network '{}'", id))?,
+                };
+                Ok(BpfExpr::Net(dir, addr, prefix))
+            }
+            BpfKind::Port => {
+                let port = id.parse().map_err(|_| format!("invalid port '{}'", id))?;
+                Ok(BpfExpr::PortRange(dir, port, port))
+            }
+            BpfKind::PortRange => {
+                let range = id.split_once('-').and_then(|(lo, hi)| {
+                    let (lo, hi) = (lo.parse::<u16>().ok()?, hi.parse::<u16>().ok()?);
+                    (lo <= hi).then_some((lo, hi))
+                });
+                let (lo, hi) = range.ok_or_else(|| format!("invalid port range '{}'", id))?;
+                Ok(BpfExpr::PortRange(dir, lo, hi))
+            }
+        }
+    }
+}
+
+fn parse_proto(token: &str) -> Option<BpfProto> {
+    match token {
+        "ip" => Some(BpfProto::Ip),
+        "ip6" => Some(BpfProto::Ip6),
+        "arp" => Some(BpfProto::Arp),
+        "vlan" => Some(BpfProto::Vlan),
+        "tcp" => Some(BpfProto::Tcp),
+        "udp" => Some(BpfProto::Udp),
+        "icmp" => Some(BpfProto::Icmp),
+        "icmp6" => Some(BpfProto::Icmp6),
+        _ => None,
+    }
+}
@@ -0,0 +1,59 @@
+//! Encoding of objects as rows of comma separated values.
+//!
+//! Fields are quoted as described in RFC 4180: a field that contains a
+//! comma, a double quote or a line break is enclosed in double quotes and
+//! the double quotes in it are doubled. Strings and characters are written
+//! as is, null as an empty field, and other objects as their textual form.
+
+use std::rc::Rc;
+
+use crate::object::Object;
+
+/// Format of the output of 'puts'
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum OutputFormat {
+    /// The textual form of the arguments one after the other
+    #[default]
+    Text,
+    /// The arguments as the fields of a row of comma separated values
+    Csv,
+}
+
+/// Encode the objects as a row of comma separated values terminated
+/// by a newline.
+pub fn to_csv_row(fields: &[Rc<Object>]) -> String {
+    let mut row = fields
+        .iter()
+        .map(|f| encode_field(f))
+        .collect::<Vec<_>>()
+        .join(",");
+    row.push('\n');
+    row
+}
+
+/// Encode the arguments of 'puts' as a row. A single array argument
+/// supplies the fields of the row.
+pub fn args_to_csv_row(args: &[Rc<Object>]) -> String {
+    match args {
+        [arg] => match arg.as_ref() {
+            Object::Arr(arr) => to_csv_row(&arr.elements.borrow()),
+            _ => to_csv_row(args),
+        },
+        _ => to_csv_row(args),
+    }
+}
+
+fn encode_field(obj: &Object) -> String {
+    let text = match obj {
+        Object::Null => return String::new(),
+        Object::Str(s) => s.clone(),
+        Object::Char(c) => c.to_string(),
+        o => o.to_string(),
+    };
+    if text.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
@@ -0,0 +1,2699 @@
+use rand::Rng;
+use std::cmp::Ordering;
+use std::fs;
+use std::io;
+use std::io::{BufRead, Read, Write};
+#[cfg(feature = "pcap")]
+use std::mem;
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::thread;
+use std::time;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::csv;
+use super::json;
+#[cfg(feature = "pcap")]
+use super::pcap::{Pcap, PcapFormat, PCAP_MAGIC_US};
+use super::print::{format_buf, sprintf_buf, write_stdout};
+use super::regex::Regex;
+use super::search;
+#[cfg(feature = "pcap")]
+use crate::code::prop::match_props;
+use crate::object::array::Array;
+use crate::object::error::ErrorObj;
+use crate::object::file::{borrow_open, FileHandle};
+use crate::object::func::BuiltinAlias;
+use crate::object::func::BuiltinFunction;
+use crate::object::hmap::HMap;
+use crate::object::hset::HSet;
+use crate::object::timestamp::{Duration, Timestamp};
+use crate::object::Object;
+#[cfg(feature = "pcap")]
+use p2sh_dissect::addr::{Addr, Cidr};
+#[cfg(feature = "pcap")]
+use p2sh_dissect::ports::parse_app_proto;
+
+pub const BUILTINFNS: &[BuiltinFunction] = &[
+    BuiltinFunction::new("len", builtin_len),
+    BuiltinFunction::new("puts", builtin_puts),
+    BuiltinFunction::new("first", builtin_first),
+    BuiltinFunction::new("last", builtin_last),
+    BuiltinFunction::new("rest", builtin_rest),
+    BuiltinFunction::new("push", builtin_push),
+    BuiltinFunction::new("pop", builtin_pop),
+    BuiltinFunction::new("get", builtin_get),
+    BuiltinFunction::new("contains", builtin_contains),
+    BuiltinFunction::new("insert", builtin_insert),
+    BuiltinFunction::new("str", builtin_str),
+    BuiltinFunction::new("int", builtin_int),
+    BuiltinFunction::new("float", builtin_float),
+    BuiltinFunction::new("char", builtin_char),
+    BuiltinFunction::new("byte", builtin_byte),
+    BuiltinFunction::new("time", builtin_time),
+    BuiltinFunction::new("exit", builtin_exit),
+    BuiltinFunction::new("flush", builtin_flush),
+    BuiltinFunction::new("format", builtin_format),
+    BuiltinFunction::new("print", builtin_print),
+    BuiltinFunction::new("println", builtin_println),
+    BuiltinFunction::new("eprint", builtin_eprint),
+    BuiltinFunction::new("eprintln", builtin_eprintln),
+    BuiltinFunction::new("round", builtin_round),
+    BuiltinFunction::new("sleep", builtin_sleep),
+    BuiltinFunction::new("tolower", builtin_tolower),
+    BuiltinFunction::new("toupper", builtin_toupper),
+    BuiltinFunction::new("open", builtin_open),
+    BuiltinFunction::new("read", builtin_read),
+    BuiltinFunction::new("write", builtin_write),
+    BuiltinFunction::new("read_to_string", builtin_read_to_string),
+    BuiltinFunction::new("decode_utf8", decode_utf8),
+    BuiltinFunction::new("encode_utf8", encode_utf8),
+    BuiltinFunction::new("read_line", builtin_read_line),
+    BuiltinFunction::new("input", builtin_input),
+    BuiltinFunction::new("get_errno", builtin_get_errno),
+    BuiltinFunction::new("strerror", builtin_strerror),
+    BuiltinFunction::new("is_err", builtin_is_err),
+    BuiltinFunction::new("sort", builtin_sort),
+    BuiltinFunction::new("chars", builtin_chars),
+    BuiltinFunction::new("join", builtin_join),
+    BuiltinFunction::new("rand", builtin_rand),
+    #[cfg(feature = "pcap")]
+    BuiltinFunction::new("pcap_open", builtin_pcap_open),
+    #[cfg(feature = "pcap")]
+    BuiltinFunction::new("pcap_stream", builtin_pcap_stream),
+    #[cfg(feature = "pcap")]
+    BuiltinFunction::new("pcap_read_next", builtin_pcap_read_next),
+    #[cfg(feature = "pcap")]
+    BuiltinFunction::new("pcap_read_all", builtin_pcap_read_all),
+    #[cfg(feature = "pcap")]
+    BuiltinFunction::new("pcap_write", builtin_pcap_write),
+    BuiltinFunction::new("globals", builtin_globals),
+    BuiltinFunction::new("locals", builtin_locals),
+    BuiltinFunction::new("ntohs", builtin_ntohs),
+    BuiltinFunction::new("ntohl", builtin_ntohl),
+    BuiltinFunction::new("htons", builtin_htons),
+    BuiltinFunction::new("htonl", builtin_htonl),
+    BuiltinFunction::new("atexit", builtin_atexit),
+    BuiltinFunction::new("register_filter", builtin_register_filter),
+    BuiltinFunction::new("set", builtin_set),
+    BuiltinFunction::new("remove", builtin_remove),
+    BuiltinFunction::new("union", builtin_union),
+    BuiltinFunction::new("intersection", builtin_intersection),
+    BuiltinFunction::new("mapdefault", builtin_mapdefault),
+    BuiltinFunction::new("sum", builtin_sum),
+    BuiltinFunction::new("min", builtin_min),
+    BuiltinFunction::new("max", builtin_max),
+    BuiltinFunction::new("avg", builtin_avg),
+    BuiltinFunction::new("group_by", builtin_group_by),
+    BuiltinFunction::new("equals", builtin_equals),
+    BuiltinFunction::new("diff", builtin_diff),
+    BuiltinFunction::new("assert", builtin_assert),
+    BuiltinFunction::new("testdata", builtin_testdata),
+    #[cfg(feature = "pcap")]
+    BuiltinFunction::new("decode_as", builtin_decode_as),
+    BuiltinFunction::new("find", builtin_find),
+    BuiltinFunction::new("bytes", builtin_bytes),
+    #[cfg(feature = "pcap")]
+    BuiltinFunction::new("props", builtin_props),
+    #[cfg(feature = "pcap")]
+    BuiltinFunction::new("to_map", builtin_to_map),
+    BuiltinFunction::new("to_json", builtin_to_json),
+    #[cfg(feature = "pcap")]
+    BuiltinFunction::new("flow", builtin_flow),
+    #[cfg(feature = "pcap")]
+    BuiltinFunction::new("flows", builtin_flows),
+    #[cfg(feature = "pcap")]
+    BuiltinFunction::new("flow_timeout", builtin_flow_timeout),
+    BuiltinFunction::new("split", builtin_split),
+    BuiltinFunction::new("trim", builtin_trim),
+    BuiltinFunction::new("starts_with", builtin_starts_with),
+    BuiltinFunction::new("ends_with", builtin_ends_with),
+    BuiltinFunction::new("replace", builtin_replace),
+    BuiltinFunction::new("regex", builtin_regex),
+    BuiltinFunction::new("matches", builtin_matches),
+    BuiltinFunction::new("captures", builtin_captures),
+    BuiltinFunction::new("hexdump", builtin_hexdump),
+    BuiltinFunction::new("hex", builtin_hex),
+    BuiltinFunction::new("slice", builtin_slice),
+    BuiltinFunction::new("close", builtin_close),
+    #[cfg(feature = "pcap")]
+    BuiltinFunction::new("decode", builtin_decode),
+    BuiltinFunction::new("csv_write", builtin_csv_write),
+    #[cfg(feature = "pcap")]
+    BuiltinFunction::new("set_comment", builtin_set_comment),
+    BuiltinFunction::new("resolve", builtin_resolve),
+    BuiltinFunction::new("service_name", builtin_service_name),
+    #[cfg(feature = "pcap")]
+    BuiltinFunction::new("ip", builtin_ip),
+    #[cfg(feature = "pcap")]
+    BuiltinFunction::new("cidr", builtin_cidr),
+    BuiltinFunction::new("ratelimit", builtin_ratelimit),
+    BuiltinFunction::new("timestamp", builtin_timestamp),
+    BuiltinFunction::new("strftime", builtin_strftime),
+    BuiltinFunction::new("duration", builtin_duration),
+    BuiltinFunction::new("rate_limit", builtin_rate_limit),
+    BuiltinFunction::new("err_msg", builtin_err_msg),
+    BuiltinFunction::new("sprintf", builtin_sprintf),
+    BuiltinFunction::new("printf", builtin_printf),
+];
+
+// Old names of the builtin functions that were renamed. Scripts using an
+// old name continue to work, but the compiler emits a deprecation warning
+// (or an error with '--deny-deprecated'). Add an entry here when renaming
+// a builtin, e.g. BuiltinAlias::new("old_name", "new_name").
+pub const BUILTIN_ALIASES: &[BuiltinAlias] = &[BuiltinAlias::new("is_error", "is_err")];
+
+fn builtin_len(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    let len = match args[0].as_ref() {
+        Object::Map(m) => m.len(),
+        Object::Set(s) => s.len(),
+        obj => obj
+            .seq_len()
+            .ok_or_else(|| String::from("unsupported argument"))?,
+    };
+    Ok(Rc::new(Object::Integer(len as i64)))
+}
+
+fn builtin_puts(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    let mut buf = String::new();
+    for obj in args {
+        match obj.as_ref() {
+            // Avoid quotes around string
+            Object::Str(t) => buf.push_str(t),
+            o => buf.push_str(&o.to_string()),
+        }
+    }
+    buf.push('\n');
+    match write_stdout(&buf) {
+        // puts returns Null
+        Ok(()) => Ok(Rc::new(Object::Null)),
+        Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+    }
+}
+
+fn builtin_first(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    if args[0].seq_name().is_none() {
+        return Err(String::from("unsupported argument"));
+    }
+    Ok(args[0].seq_get(0).unwrap_or_else(|| Rc::new(Object::Null)))
+}
+
+fn builtin_last(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    let len = args[0]
+        .seq_len()
+        .ok_or_else(|| String::from("unsupported argument"))?;
+    let last = len.checked_sub(1).and_then(|idx| args[0].seq_get(idx));
+    Ok(last.unwrap_or_else(|| Rc::new(Object::Null)))
+}
+
+fn builtin_rest(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    match args[0].seq_len() {
+        None => Err(String::from("unsupported argument")),
+        Some(0) => Ok(Rc::new(Object::Null)),
+        Some(_) => Ok(args[0]
+            .seq_slice(1, usize::MAX)
+            .unwrap_or_else(|| Rc::new(Object::Null))),
+    }
+}
+
+// Insert a value to the end of an array
+fn builtin_push(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+
+    match args[0].as_ref() {
+        Object::Arr(arr) => {
+            arr.push(args[1].clone());
+            Ok(Rc::new(Object::Null))
+        }
+        obj => Err(unmodifiable(obj)),
+    }
+}
+
+// Error for modifying a sequence other than an array
+fn unmodifiable(obj: &Object) -> String {
+    match obj.seq_name() {
+        Some(name) => format!("{} cannot be modified", name),
+        None => String::from("unsupported argument"),
+    }
+}
+
+// Remove value from the end of an array
+fn builtin_pop(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+
+    match args[0].as_ref() {
+        Object::Arr(arr) => {
+            let obj = arr.elements.borrow_mut().pop();
+            match obj {
+                Some(obj) => Ok(obj),
+                None => Ok(Rc::new(Object::Null)),
+            }
+        }
+        obj => Err(unmodifiable(obj)),
+    }
+}
+
+// Get an array item by index or a map value by key
+// Return Null if index is out of bounds or if the key doesn't exist
+fn builtin_get(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+
+    match (args[0].as_ref(), args[1].as_ref()) {
+        (Object::Map(map), _) => Ok(map.get(&args[1])),
+        (seq, Object::Integer(index)) if seq.seq_name().is_some() => {
+            let elem = usize::try_from(*index).ok().and_then(|i| seq.seq_get(i));
+            Ok(elem.unwrap_or_else(|| Rc::new(Object::Null)))
+        }
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+fn builtin_contains(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+
+    let contains = contains(&args[0], &args[1])?;
+    Ok(Rc::new(Object::Bool(contains)))
+}
+
+/// Check if a map has a key, a set, an array or a network has an element,
+/// or a string has a substring. This is the 'in' operator.
+pub fn contains(container: &Object, elem: &Rc<Object>) -> Result<bool, String> {
+    match container {
+        Object::Map(map) => Ok(map.contains(elem)),
+        Object::Set(set) => Ok(set.contains(elem)),
+        Object::Arr(arr) => Ok(arr.elements.borrow().iter().any(|e| e == elem)),
+        Object::Str(s) => {
+            let sub = string_arg(elem)?;
+            Ok(s.contains(sub.as_str()))
+        }
+        #[cfg(feature = "pcap")]
+        Object::Cidr(cidr) => match elem.as_ref() {
+            Object::Addr(addr) => Ok(cidr.contains(addr)),
+            Object::Cidr(other) => Ok(cidr.contains_cidr(other)),
+            Object::Str(s) => match Addr::parse(s) {
+                Some(addr) => Ok(cidr.contains(&addr)),
+                None => Err(format!("invalid ip address '{}'", s)),
+            },
+            _ => Err(String::from("element should be an ip address or a network")),
+        },
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+// Insert a key-value pair into a map. If the key already exists,
+// the old value is returned, otherwise Null is returned. For sets,
+// insert an element and return true if it was not already present.
+fn builtin_insert(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if let Some(Object::Set(set)) = args.first().map(|a| a.as_ref()) {
+        if args.len() != 2 {
+            return Err(format!("takes two arguments. got={}", args.len()));
+        }
+        let elem = args[1].clone();
+        if !elem.is_a_valid_key() {
+            return Err(format!("not a valid set element: {}", elem));
+        }
+        return Ok(Rc::new(Object::Bool(set.insert(elem))));
+    }
+    if args.len() != 3 {
+        return Err(format!("takes three arguments. got={}", args.len()));
+    }
+
+    match args[0].as_ref() {
+        Object::Map(map) => {
+            let key = args[1].clone();
+            if !key.is_a_valid_key() {
+                return Err(format!("not a valid key: {}", key));
+            }
+            let val = args[2].clone();
+            let old = map.insert(key, val);
+            Ok(old)
+        }
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+fn builtin_str(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+
+    let obj = args[0].as_ref();
+    match obj {
+        Object::Str(_) => Ok(Rc::clone(&args[0])),
+        Object::Null
+        | Object::Integer(_)
+        | Object::Bool(_)
+        | Object::Arr(_)
+        | Object::Err(_)
+        | Object::Map(_)
+        | Object::Set(_)
+        | Object::Bytes(_)
+        | Object::Timestamp(_)
+        | Object::Duration(_) => Ok(Rc::new(Object::Str(obj.to_string()))),
+        #[cfg(feature = "pcap")]
+        Object::Addr(_) | Object::Cidr(_) => Ok(Rc::new(Object::Str(obj.to_string()))),
+        Object::Char(c) => Ok(Rc::new(Object::Str(c.to_string()))),
+        Object::Byte(b) => Ok(Rc::new(Object::Str(b.to_string()))),
+        Object::Regex(re) => Ok(Rc::new(Object::Str(re.pattern().to_string()))),
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+fn builtin_int(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+
+    let obj = args[0].as_ref();
+    match obj {
+        Object::Str(s) => {
+            if let Ok(num) = s.parse::<i64>() {
+                Ok(Rc::new(Object::Integer(num)))
+            } else {
+                let msg = format!("invalid integer '{}'", s);
+                Ok(Rc::new(Object::Err(ErrorObj::Value(msg))))
+            }
+        }
+        Object::Integer(_) => Ok(Rc::clone(&args[0])),
+        Object::Float(n) => Ok(Rc::new(Object::Integer(*n as i64))),
+        Object::Timestamp(ts) => Ok(Rc::new(Object::Integer(ts.secs()))),
+        Object::Duration(d) => Ok(Rc::new(Object::Integer(d.secs()))),
+        Object::Char(b) => Ok(Rc::new(Object::Integer(*b as i64))),
+        Object::Byte(b) => Ok(Rc::new(Object::Integer(*b as i64))),
+        Object::Bool(b) => {
+            if *b {
+                Ok(Rc::new(Object::Integer(1)))
+            } else {
+                Ok(Rc::new(Object::Integer(0)))
+            }
+        }
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+fn builtin_float(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+
+    let obj = args[0].as_ref();
+    match obj {
+        Object::Str(s) => {
+            if let Ok(num) = s.parse::<f64>() {
+                Ok(Rc::new(Object::Float(num)))
+            } else {
+                let msg = format!("invalid float '{}'", s);
+                Ok(Rc::new(Object::Err(ErrorObj::Value(msg))))
+            }
+        }
+        Object::Float(_) => Ok(Rc::clone(&args[0])),
+        Object::Integer(n) => Ok(Rc::new(Object::Float(*n as f64))),
+        Object::Timestamp(ts) => Ok(Rc::new(Object::Float(ts.as_secs_f64()))),
+        Object::Duration(d) => Ok(Rc::new(Object::Float(d.as_secs_f64()))),
+        Object::Char(b) => Ok(Rc::new(Object::Float(*b as i64 as f64))),
+        Object::Byte(b) => Ok(Rc::new(Object::Float(*b as f64))),
+        Object::Bool(b) => {
+            if *b {
+                Ok(Rc::new(Object::Float(1.)))
+            } else {
+                Ok(Rc::new(Object::Float(0.)))
+            }
+        }
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+fn builtin_char(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+
+    let obj = args[0].as_ref();
+    match obj {
+        Object::Char(_) => Ok(Rc::clone(&args[0])),
+        Object::Byte(b) => {
+            if let Some(c) = std::char::from_u32(*b as u32) {
+                Ok(Rc::new(Object::Char(c)))
+            } else {
+                Ok(invalid_code(obj))
+            }
+        }
+        Object::Integer(s) => {
+            if let Some(c) = std::char::from_u32(*s as u32) {
+                Ok(Rc::new(Object::Char(c)))
+            } else {
+                Ok(invalid_code(obj))
+            }
+        }
+        Object::Float(n) => {
+            if let Some(c) = std::char::from_u32(*n as u32) {
+                Ok(Rc::new(Object::Char(c)))
+            } else {
+                Ok(invalid_code(obj))
+            }
+        }
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+fn builtin_byte(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    let obj = args[0].as_ref();
+    match obj {
+        Object::Byte(_) => Ok(Rc::clone(&args[0])),
+        Object::Char(c) => Ok(Rc::new(Object::Byte(*c as u8))),
+        Object::Bool(b) => {
+            if *b {
+                Ok(Rc::new(Object::Byte(1)))
+            } else {
+                Ok(Rc::new(Object::Byte(0)))
+            }
+        }
+        Object::Integer(s) => {
+            if let Some(b) = std::char::from_u32(*s as u32) {
+                Ok(Rc::new(Object::Byte(b as u8)))
+            } else {
+                Ok(invalid_code(obj))
+            }
+        }
+        Object::Float(n) => {
+            if let Some(b) = std::char::from_u32(*n as u32) {
+                Ok(Rc::new(Object::Byte(b as u8)))
+            } else {
+                Ok(invalid_code(obj))
+            }
+        }
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+// The error for a number that is not the code of a character
+fn invalid_code(obj: &Object) -> Rc<Object> {
+    let msg = format!("invalid character code {}", obj);
+    Rc::new(Object::Err(ErrorObj::Value(msg)))
+}
+
+fn builtin_time(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if !args.is_empty() {
+        return Err(format!("takes no argument(s). got={}", args.len()));
+    }
+    let current_time = SystemTime::now();
+    let duration = current_time
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards");
+    let seconds = duration.as_secs() as i64;
+    Ok(Rc::new(Object::Integer(seconds)))
+}
+
+// The VM terminates the program when this returns an exit code, after
+// running the functions registered using 'atexit'.
+fn builtin_exit(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::Integer(code) => Ok(Rc::new(Object::Integer(*code))),
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+fn builtin_flush(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::File(f) => match f.as_ref() {
+            FileHandle::Reader(_) => {
+                return Err("cannot flush a reader".to_string());
+            }
+            FileHandle::Writer(writer) => {
+                let mut writer = borrow_open(writer).map_err(|e| e.to_string())?;
+                writer.flush().expect("Failed to flush file");
+            }
+            FileHandle::Stdin => {
+                return Err("cannot flush stdin".to_string());
+            }
+            FileHandle::Stdout => {
+                io::stdout().flush().expect("Failed to flush stdout");
+            }
+            FileHandle::Stderr => {
+                io::stderr().flush().expect("Failed to flush stderr");
+            }
+        },
+        #[cfg(feature = "pcap")]
+        Object::Pcap(pcap) => pcap.flush().expect("Failed to flush file"),
+        _ => return Err(String::from("argument should be a file handle")),
+    }
+    Ok(Rc::new(Object::Null))
+}
+
+pub fn builtin_format(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() {
+        return Err(String::from("takes atleast one argument. got none"));
+    }
+    let collector = format_buf(args)?;
+    // Join the collected formatted output
+    let buf: String = collector.0.into_iter().collect();
+    // Return buf
+    Ok(Rc::new(Object::Str(buf)))
+}
+
+pub fn builtin_sprintf(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() {
+        return Err(String::from("takes atleast one argument. got none"));
+    }
+    let collector = sprintf_buf(args)?;
+    Ok(Rc::new(Object::Str(collector.0.concat())))
+}
+
+fn builtin_printf(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() {
+        return Err(String::from("takes atleast one argument. got none"));
+    }
+    let collector = sprintf_buf(args)?;
+    Ok(print_stdout(&collector.0, false))
+}
+
+fn builtin_print(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() {
+        return Err(String::from("takes atleast one argument. got none"));
+    }
+    let collector = format_buf(args)?;
+    Ok(print_stdout(&collector.0, false))
+}
+
+fn builtin_println(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() {
+        return Err(String::from("takes atleast one argument. got none"));
+    }
+    let collector = format_buf(args)?;
+    Ok(print_stdout(&collector.0, true))
+}
+
+// Print the collected formatted output to stdout followed by a newline if
+// 'newline' is set. Returns the number of bytes printed, or an error object
+// if stdout could not be written to, such as when the reader of a pipe has
+// gone away.
+fn print_stdout(parts: &[String], newline: bool) -> Rc<Object> {
+    let mut buf = parts.concat();
+    if newline {
+        buf.push('\n');
+    }
+    match write_stdout(&buf) {
+        Ok(()) => Rc::new(Object::Integer(buf.len() as i64)),
+        Err(e) => Rc::new(Object::Err(ErrorObj::IO(e))),
+    }
+}
+
+fn builtin_eprint(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() {
+        return Err(String::from("takes atleast one argument. got none"));
+    }
+    let mut len = 0;
+    let collector = format_buf(args)?;
+    // Print the collected formatted output
+    for s in &collector.0 {
+        eprint!("{}", s);
+        len += s.len() as i64;
+    }
+    Ok(Rc::new(Object::Integer(len)))
+}
+
+fn builtin_eprintln(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() {
+        return Err(String::from("takes atleast one argument. got none"));
+    }
+    let mut len = 0;
+    let collector = format_buf(args)?;
+    // Print the collected formatted output
+    for s in &collector.0 {
+        eprint!("{}", s);
+        len += s.len() as i64;
+    }
+    // Newline at the end
+    eprintln!();
+    len += 1;
+    Ok(Rc::new(Object::Integer(len)))
+}
+
+fn builtin_round(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+
+    match args[0].as_ref() {
+        Object::Float(f) => {
+            if let Object::Integer(n) = args[1].as_ref() {
+                let multiplier = 10i64.pow(*n as u32);
+                let rounded = (f * multiplier as f64).round() / multiplier as f64;
+                Ok(Rc::new(Object::Float(rounded)))
+            } else {
+                Err(String::from("second argument should be an integer"))
+            }
+        }
+        _ => Err(String::from("first argument should be a float")),
+    }
+}
+
+fn builtin_sleep(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+
+    match args[0].as_ref() {
+        Object::Integer(n) => {
+            thread::sleep(time::Duration::from_secs(*n as u64));
+            Ok(Rc::new(Object::Null))
+        }
+        _ => Err(String::from("argument should be an integer")),
+    }
+}
+
+fn builtin_tolower(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+
+    match args[0].as_ref() {
+        Object::Char(c) => {
+            let c = c.to_ascii_lowercase();
+            Ok(Rc::new(Object::Char(c)))
+        }
+        Object::Byte(b) => {
+            let b = b.to_ascii_lowercase();
+            Ok(Rc::new(Object::Byte(b)))
+        }
+        Object::Str(s) => {
+            let s = s.to_ascii_lowercase();
+            Ok(Rc::new(Object::Str(s)))
+        }
+        _ => Err(String::from("argument should be an integer")),
+    }
+}
+
+fn builtin_toupper(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+
+    match args[0].as_ref() {
+        Object::Char(c) => {
+            let c = c.to_ascii_uppercase();
+            Ok(Rc::new(Object::Char(c)))
+        }
+        Object::Byte(b) => {
+            let b = b.to_ascii_uppercase();
+            Ok(Rc::new(Object::Byte(b)))
+        }
+        Object::Str(s) => {
+            let s = s.to_ascii_uppercase();
+            Ok(Rc::new(Object::Str(s)))
+        }
+        _ => Err(String::from("argument should be an integer")),
+    }
+}
+
+/// Opens a file handle
+/// # Arguments
+/// * `args` - A vector of Rc<Object> containing the path to the file (Object::Str) and an optional
+///   second argument specifying the mode (Object::Str).
+/// # Returns
+/// Returns a Result containing a file handle wrapped in an Object::File,
+/// or an error object if the operation fails. An I/O error will result in the last
+/// error being set which can be retrieved using get_errno().
+fn builtin_open(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(format!("takes one or two arguments. got={}", args.len()));
+    }
+
+    let path = if let Object::Str(s) = args[0].as_ref() {
+        s
+    } else {
+        return Err(String::from("argument should be a string"));
+    };
+
+    let mode = if args.len() == 2 {
+        if let Object::Str(s) = args[1].as_ref() {
+            s
+        } else {
+            return Err(String::from("second argument should be a string"));
+        }
+    } else {
+        "r"
+    };
+
+    match mode {
+        "r" => {
+            // opens a file for reading, returns error if the file does not exist
+            let file = fs::File::open(path);
+            match file {
+                Ok(file) => {
+                    let reader = io::BufReader::new(file);
+                    let handle = FileHandle::new_reader(reader);
+                    Ok(Rc::new(Object::File(Rc::new(handle))))
+                }
+                Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+            }
+        }
+        "a" => {
+            // open a file for appending, create the file if it does not exist
+            let file = fs::OpenOptions::new().append(true).create(true).open(path);
+            match file {
+                Ok(file) => {
+                    let writer = io::BufWriter::new(file);
+                    let handle = FileHandle::new_writer(writer);
+                    Ok(Rc::new(Object::File(Rc::new(handle))))
+                }
+                Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+            }
+        }
+        "w" => {
+            // open a file for writing, create the file if it does not exist,
+            // truncate the file if it exists. return null if the operation fails.
+            let file = fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(path);
+            match file {
+                Ok(file) => {
+                    let writer = io::BufWriter::new(file);
+                    let handle = FileHandle::new_writer(writer);
+                    Ok(Rc::new(Object::File(Rc::new(handle))))
+                }
+                Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+            }
+        }
+        "x" => {
+            // create the specified file, returns error if the file exist
+            // or if the operation fails.
+            let file = fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path);
+            match file {
+                Ok(file) => {
+                    let writer = io::BufWriter::new(file);
+                    let handle = FileHandle::new_writer(writer);
+                    Ok(Rc::new(Object::File(Rc::new(handle))))
+                }
+                Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+            }
+        }
+        _ => Err(String::from("invalid file open mode")),
+    }
+}
+
+/// Helper to read bytes from a file handle into an array of Object::Byte variants.
+/// # Arguments
+/// * `reader` - A reference to a Read trait object.
+/// * `args` - A vector of Rc<Object> containing the file handle and an optional
+///   second argument specifying the number of bytes to read (Object::Integer).
+/// # Returns
+/// Returns an array of Object::Byte variants wrapped in an Object::Arr,
+/// or an error object if the operation fails. An I/O error will result in the last
+/// error being set which can be retrieved using get_errno().
+fn read_from_file<R: Read>(reader: &mut R, num_bytes_to_read: usize) -> Rc<Object> {
+    let mut total_bytes_read = 0;
+    let mut buffer = [0; 4096];
+    let mut result_bytes = Vec::new();
+
+    while total_bytes_read < num_bytes_to_read {
+        let bytes_remaining = num_bytes_to_read - total_bytes_read;
+        let read_len = buffer.len().min(bytes_remaining);
+        let buf_slice = &mut buffer[..read_len];
+        match reader.read(buf_slice) {
+            Ok(bytes_read) => {
+                if bytes_read == 0 {
+                    break; // EOF
+                }
+                // Copy bytes off
+                for byte in buf_slice.iter().take(bytes_read) {
+                    result_bytes.push(Rc::new(Object::Byte(*byte)));
+                }
+                // Got fewer bytes than requested, so we're done
+                if bytes_read < read_len {
+                    break;
+                }
+                total_bytes_read += bytes_read;
+            }
+            Err(e) => {
+                // This should set last error which can be retrieved using get_errno()
+                return Rc::new(Object::Err(ErrorObj::IO(e)));
+            }
+        }
+    }
+
+    Rc::new(Object::Arr(Rc::new(Array::new(result_bytes))))
+}
+
+/// Reads bytes from a file handle into an array of Object::Byte variants.
+/// # Arguments
+/// * `args` - A vector of Rc<Object> containing the file handle and an optional
+///   second argument specifying the number of bytes to read (Object::Integer).
+/// # Returns
+/// Returns a Result containing an array of Object::Byte variants wrapped in an Object::Arr,
+/// or an error object if the operation fails. An I/O error will result in the last
+/// error being set which can be retrieved using get_errno().
+fn builtin_read(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(format!("takes one or two arguments. got={}", args.len()));
+    }
+
+    match args[0].as_ref() {
+        Object::File(f) => match f.as_ref() {
+            FileHandle::Reader(reader) => {
+                let mut file = borrow_open(reader).map_err(|e| e.to_string())?;
+                let num_bytes_to_read = if args.len() == 2 {
+                    match args[1].as_ref() {
+                        Object::Integer(num) => *num as usize,
+                        _ => return Err(String::from("second argument should be an integer")),
+                    }
+                } else {
+                    usize::MAX
+                };
+                Ok(read_from_file(&mut *file, num_bytes_to_read))
+            }
+            FileHandle::Writer(_) => Err(String::from("cannot read from a writer")),
+            FileHandle::Stdin => {
+                let num_bytes_to_read = if args.len() == 2 {
+                    match args[1].as_ref() {
+                        Object::Integer(num) => *num as usize,
+                        _ => return Err(String::from("second argument should be an integer")),
+                    }
+                } else {
+                    usize::MAX
+                };
+
+                Ok(read_from_file(&mut io::stdin(), num_bytes_to_read))
+            }
+            FileHandle::Stdout => Err(String::from("cannot read from stdout")),
+            FileHandle::Stderr => Err(String::from("cannot read from stderr")),
+        },
+        _ => Err(String::from("first argument should be a file handle")),
+    }
+}
+
+/// Reads bytes from a file handle and return it as a string
+/// # Arguments
+/// * `args` - A vector of Rc<Object> containing the file handle
+/// # Returns
+/// Returns a Result containing a string wrapped in an Object::Str,
+/// or an error object if the operation fails. An I/O error will result in the last
+/// error being set which can be retrieved using get_errno().
+fn builtin_read_to_string(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+
+    if let Object::File(f) = args[0].as_ref() {
+        match f.as_ref() {
+            FileHandle::Reader(reader) => {
+                let mut file = borrow_open(reader).map_err(|e| e.to_string())?;
+                // Read all data
+                let mut result_bytes = Vec::new();
+                match file.read_to_end(&mut result_bytes) {
+                    Ok(_) => {}
+                    Err(e) => {
+                        return Ok(Rc::new(Object::Err(ErrorObj::IO(e))));
+                    }
+                }
+                match String::from_utf8(result_bytes) {
+                    Ok(s) => Ok(Rc::new(Object::Str(s))),
+                    Err(e) => Ok(Rc::new(Object::Err(ErrorObj::Utf8(e)))),
+                }
+            }
+            FileHandle::Writer(_) => Err(String::from("cannot read from a writer")),
+            _ => Err(String::from("invalid file handle")),
+        }
+    } else {
+        Err(String::from("first argument should be a file handle"))
+    }
+}
+
+/// Decodes a UTF-8 encoded byte array into a string
+/// # Arguments
+/// * `args` - A vector of Rc<Object> containing an array of Object::Byte variants.
+/// # Returns
+/// Returns a Result containing a string wrapped in an Object::Str,
+/// or an error message if the operation fails.
+fn decode_utf8(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+
+    if let Object::Arr(_) | Object::Bytes(_) = args[0].as_ref() {
+        let bytes = byte_array(&args[0])?;
+        match String::from_utf8(bytes) {
+            Ok(s) => Ok(Rc::new(Object::Str(s))),
+            Err(e) => Ok(Rc::new(Object::Err(ErrorObj::Utf8(e)))),
+        }
+    } else {
+        Err(String::from("argument should be an array of bytes"))
+    }
+}
+
+/// Encodes a string into a UTF-8 encoded byte array
+/// # Arguments
+/// * `args` - A vector of Rc<Object> containing a string wrapped in an Object::Str.
+/// # Returns
+/// Returns a Result containing an array of Object::Byte variants wrapped in an Object::Arr,
+/// or an error message if the operation fails.
+fn encode_utf8(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+
+    if let Object::Str(s) = args[0].as_ref() {
+        let mut bytes = Vec::new();
+        for b in s.as_bytes() {
+            bytes.push(Rc::new(Object::Byte(*b)));
+        }
+        Ok(Rc::new(Object::Arr(Rc::new(Array::new(bytes)))))
+    } else {
+        Err(String::from("argument should be a string"))
+    }
+}
+
+/// Writes a byte or an array of bytes to a file handle, or a packet to a
+/// pcap file handle
+/// # Arguments
+/// * `args` - A vector of Rc<Object> containing the file handle and a byte or an
+///   array of bytes (Object::Byte or Object::Arr).
+/// # Returns
+/// Returns a Result containing the number of bytes written wrapped in an Object::Integer,
+/// or an error message if the operation fails.
+fn builtin_write(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+
+    match args[0].as_ref() {
+        #[cfg(feature = "pcap")]
+        Object::Pcap(_) => builtin_pcap_write(args),
+        Object::File(f) => {
+            match f.as_ref() {
+                FileHandle::Reader(_) => Err(String::from("cannot write to a reader")),
+                FileHandle::Writer(writer) => {
+                    let mut file = borrow_open(writer).map_err(|e| e.to_string())?;
+                    match args[1].as_ref() {
+                        Object::Byte(b) => {
+                            let buf = [*b];
+                            match file.write(&buf) {
+                                // Return number of bytes written
+                                Ok(n) => Ok(Rc::new(Object::Integer(n as i64))),
+                                Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+                            }
+                        }
+                        Object::Arr(arr) => {
+                            let mut buf = Vec::new();
+                            for obj in arr.elements.borrow().iter() {
+                                if let Object::Byte(b) = obj.as_ref() {
+                                    buf.push(*b);
+                                } else {
+                                    return Err(String::from("array should contain only bytes"));
+                                }
+                            }
+                            match file.write(&buf) {
+                                Ok(n) => Ok(Rc::new(Object::Integer(n as i64))),
+                                Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+                            }
+                        }
+                        Object::Str(s) => {
+                            let bytes = s.as_bytes();
+                            match file.write(bytes) {
+                                Ok(n) => Ok(Rc::new(Object::Integer(n as i64))),
+                                Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+                            }
+                        }
+                        Object::Bytes(bytes) => match file.write(bytes) {
+                            Ok(n) => Ok(Rc::new(Object::Integer(n as i64))),
+                            Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+                        },
+                        #[cfg(feature = "pcap")]
+                        Object::Packet(s) => {
+                            let bytes: Vec<u8> = s.as_ref().into();
+                            match file.write(&bytes) {
+                                Ok(n) => Ok(Rc::new(Object::Integer(n as i64))),
+                                Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+                            }
+                        }
+                        _ => Err(String::from(
+                            "second argument should be a packet, byte, arr, bytes or string",
+                        )),
+                    }
+                }
+                FileHandle::Stdin => Err("cannot write to stdin".to_string()),
+                FileHandle::Stdout => match args[1].as_ref() {
+                    Object::Byte(b) => {
+                        print!("{}", *b as char);
+                        Ok(Rc::new(Object::Integer(1)))
+                    }
+                    Object::Arr(arr) => {
+                        for obj in arr.elements.borrow().iter() {
+                            if let Object::Byte(b) = obj.as_ref() {
+                                print!("{}", *b as char);
+                            } else {
+                                return Err(String::from("array should contain only bytes"));
+                            }
+                        }
+                        Ok(Rc::new(Object::Integer(arr.elements.borrow().len() as i64)))
+                    }
+                    Object::Str(s) => {
+                        print!("{}", s);
+                        Ok(Rc::new(Object::Integer(s.len() as i64)))
+                    }
+                    Object::Bytes(bytes) => match io::stdout().write_all(bytes) {
+                        Ok(_) => Ok(Rc::new(Object::Integer(bytes.len() as i64))),
+                        Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+                    },
+                    #[cfg(feature = "pcap")]
+                    Object::Packet(s) => {
+                        let bytes: Vec<u8> = s.as_ref().into();
+                        match io::stdout().write_all(&bytes) {
+                            Ok(_) => Ok(Rc::new(Object::Integer(bytes.len() as i64))),
+                            Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+                        }
+                    }
+                    _ => Err(String::from(
+                        "second argument should be a packet, byte, arr, bytes or string",
+                    )),
+                },
+                FileHandle::Stderr => match args[1].as_ref() {
+                    Object::Byte(b) => {
+                        eprint!("{}", *b as char);
+                        Ok(Rc::new(Object::Integer(1)))
+                    }
+                    Object::Arr(arr) => {
+                        for obj in arr.elements.borrow().iter() {
+                            if let Object::Byte(b) = obj.as_ref() {
+                                eprint!("{}", *b as char);
+                            } else {
+                                return Err(String::from("array should contain only bytes"));
+                            }
+                        }
+                        Ok(Rc::new(Object::Integer(arr.elements.borrow().len() as i64)))
+                    }
+                    Object::Str(s) => {
+                        eprint!("{}", s);
+                        Ok(Rc::new(Object::Integer(s.len() as i64)))
+                    }
+                    Object::Bytes(bytes) => match io::stderr().write_all(bytes) {
+                        Ok(_) => Ok(Rc::new(Object::Integer(bytes.len() as i64))),
+                        Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+                    },
+                    #[cfg(feature = "pcap")]
+                    Object::Packet(s) => {
+                        let bytes: Vec<u8> = s.as_ref().into();
+                        match io::stderr().write_all(&bytes) {
+                            Ok(_) => Ok(Rc::new(Object::Integer(bytes.len() as i64))),
+                            Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+                        }
+                    }
+                    _ => Err(String::from(
+                        "second argument should be a packet, byte, arr, bytes or string",
+                    )),
+                },
+            }
+        }
+        _ => Err(String::from("first argument should be a file handle")),
+    }
+}
+
+/// Reads a line from stdin or a file handle
+/// # Arguments
+/// * `args` - A vector of Rc<Object> containing the file handle
+/// # Returns
+/// Returns a Result containing a string wrapped in an Object::Str,
+/// or an error message if the operation fails.
+fn builtin_read_line(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+
+    let mut line = String::new();
+    match args[0].as_ref() {
+        Object::File(f) => match f.as_ref() {
+            FileHandle::Reader(reader) => {
+                let mut file = borrow_open(reader).map_err(|e| e.to_string())?;
+                match file.read_line(&mut line) {
+                    Ok(_) => Ok(Rc::new(Object::Str(line))),
+                    Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+                }
+            }
+            FileHandle::Writer(_) => Err(String::from("cannot read from a writer")),
+            FileHandle::Stdin => match io::stdin().read_line(&mut line) {
+                Ok(_) => Ok(Rc::new(Object::Str(line))),
+                Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+            },
+            FileHandle::Stdout => Err(String::from("cannot read from stdout")),
+            FileHandle::Stderr => Err(String::from("cannot read from stderr")),
+        },
+        _ => Err(String::from("argument should be a file handle")),
+    }
+}
+
+/// Closes a file handle, flushing the data written to it
+/// # Arguments
+/// * `args` - A vector of Rc<Object> containing the file handle
+/// # Returns
+/// Returns a Result containing a null, or an error object if the data
+/// could not be flushed. Closing a file that is already closed has no effect.
+fn builtin_close(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::File(f) => match f.as_ref() {
+            FileHandle::Reader(_) | FileHandle::Writer(_) => match f.close() {
+                Ok(()) => Ok(Rc::new(Object::Null)),
+                Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+            },
+            handle => Err(format!("cannot close {}", handle)),
+        },
+        _ => Err(String::from("argument should be a file handle")),
+    }
+}
+
+/// Reads a line from stdin
+/// # Arguments
+/// * `args` - A vector of Rc<Object> containing the prompt (Object::Str).
+/// # Returns
+/// Returns a Result containing a string wrapped in an Object::Str,
+/// or an error message if the operation fails.
+fn builtin_input(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() > 1 {
+        return Err(format!("takes one or no arguments. got={}", args.len()));
+    }
+    // display the prompt only if args has atleast one element
+    if args.len() == 1 {
+        if let Object::Str(s) = args[0].as_ref() {
+            print!("{}", s);
+            io::stdout().flush().expect("Failed to flush stdout");
+        } else {
+            return Err(String::from("argument should be a string"));
+        }
+    }
+    let mut line = String::new();
+    match io::stdin().read_line(&mut line) {
+        Ok(_) => Ok(Rc::new(Object::Str(line.trim().to_string()))),
+        Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+    }
+}
+
+/// Get last os error code
+/// # Returns
+/// Returns a Result containing an integer wrapped in an Object::Integer,
+/// or a null value if there is no error.
+fn builtin_get_errno(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if !args.is_empty() {
+        return Err(format!("takes no arguments. got={}", args.len()));
+    }
+    if let Some(err_code) = io::Error::last_os_error().raw_os_error() {
+        Ok(Rc::new(Object::Integer(err_code as i64)))
+    } else {
+        Ok(Rc::new(Object::Null))
+    }
+}
+
+/// Convert an os error code to a string
+fn builtin_strerror(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    let obj = args[0].as_ref();
+    match obj {
+        Object::Integer(n) => {
+            let s = io::Error::from_raw_os_error(*n as i32).to_string();
+            Ok(Rc::new(Object::Str(s)))
+        }
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+/// Check if an object is an error
+fn builtin_is_err(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    let obj = args[0].as_ref();
+    match obj {
+        Object::Err(_) => Ok(Rc::new(Object::Bool(true))),
+        _ => Ok(Rc::new(Object::Bool(false))),
+    }
+}
+
+/// Get the message of an error object, or null if the object is not an error
+fn builtin_err_msg(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::Err(e) => Ok(Rc::new(Object::Str(e.message()))),
+        _ => Ok(Rc::new(Object::Null)),
+    }
+}
+
+/// Sort the elements of an array
+fn builtin_sort(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    let obj = args[0].as_ref();
+    match obj {
+        Object::Arr(arr) => {
+            arr.elements.borrow_mut().sort();
+            Ok(Rc::clone(&args[0]))
+        }
+        _ => Ok(Rc::new(Object::Null)),
+    }
+}
+
+/// Convert string to array of chars
+fn builtin_chars(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    let obj = args[0].as_ref();
+    match obj {
+        Object::Str(s) => Ok(Rc::new(Object::Arr(Rc::new(Array::new(
+            s.chars().map(|c| Rc::new(Object::Char(c))).collect(),
+        ))))),
+        _ => Ok(Rc::new(Object::Null)),
+    }
+}
+
+// Join an array of chars or strings into a string, optionally delimited by
+// a character or a string
+fn builtin_join(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(format!("takes one or two arguments. got={}", args.len()));
+    }
+    let obj = args[0].as_ref();
+    match obj {
+        Object::Arr(arr) => {
+            let mut delim = String::new();
+            if args.len() == 2 {
+                if let Object::Str(s) = args[1].as_ref() {
+                    delim = s.clone();
+                } else if let Object::Char(c) = args[1].as_ref() {
+                    delim.push(*c);
+                } else {
+                    return Err(String::from("second argument should be a string or a char"));
+                }
+            }
+            let mut s = String::new();
+            let mut first = true;
+            for obj in arr.elements.borrow().iter() {
+                if !first {
+                    s.push_str(&delim);
+                }
+                match obj.as_ref() {
+                    Object::Char(c) => s.push(*c),
+                    Object::Str(part) => s.push_str(part),
+                    _ => return Err(String::from("array should contain only chars or strings")),
+                }
+                first = false;
+            }
+            Ok(Rc::new(Object::Str(s)))
+        }
+        _ => Ok(Rc::new(Object::Null)),
+    }
+}
+
+// A string or a char argument of the string builtins as a string
+fn string_arg(obj: &Object) -> Result<String, String> {
+    match obj {
+        Object::Str(s) => Ok(s.clone()),
+        Object::Char(c) => Ok(c.to_string()),
+        _ => Err(String::from("argument should be a string or a char")),
+    }
+}
+
+fn str_array<'a>(parts: impl Iterator<Item = &'a str>) -> Rc<Object> {
+    let parts = parts.map(|p| Rc::new(Object::Str(p.to_string()))).collect();
+    Rc::new(Object::Arr(Rc::new(Array::new(parts))))
+}
+
+// Split a string into an array of strings at every occurrence of a
+// separator, or at runs of whitespace if no separator is given
+fn builtin_split(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(format!("takes one or two arguments. got={}", args.len()));
+    }
+    let Object::Str(s) = args[0].as_ref() else {
+        return Err(String::from("first argument should be a string"));
+    };
+    match args.get(1) {
+        None => Ok(str_array(s.split_whitespace())),
+        Some(sep) => {
+            let sep = string_arg(sep)?;
+            if sep.is_empty() {
+                return Err(String::from("separator should not be empty"));
+            }
+            Ok(str_array(s.split(sep.as_str())))
+        }
+    }
+}
+
+// Remove the leading and trailing whitespace of a string
+fn builtin_trim(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::Str(s) => Ok(Rc::new(Object::Str(s.trim().to_string()))),
+        _ => Err(String::from("argument should be a string")),
+    }
+}
+
+fn builtin_starts_with(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    let Object::Str(s) = args[0].as_ref() else {
+        return Err(String::from("first argument should be a string"));
+    };
+    let prefix = string_arg(&args[1])?;
+    Ok(Rc::new(Object::Bool(s.starts_with(prefix.as_str()))))
+}
+
+fn builtin_ends_with(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    let Object::Str(s) = args[0].as_ref() else {
+        return Err(String::from("first argument should be a string"));
+    };
+    let suffix = string_arg(&args[1])?;
+    Ok(Rc::new(Object::Bool(s.ends_with(suffix.as_str()))))
+}
+
+// Replace every occurrence of a string in a string with another
+fn builtin_replace(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 3 {
+        return Err(format!("takes three arguments. got={}", args.len()));
+    }
+    let Object::Str(s) = args[0].as_ref() else {
+        return Err(String::from("first argument should be a string"));
+    };
+    let from = string_arg(&args[1])?;
+    if from.is_empty() {
+        return Err(String::from("string to replace should not be empty"));
+    }
+    let to = string_arg(&args[2])?;
+    Ok(Rc::new(Object::Str(s.replace(from.as_str(), &to))))
+}
+
+/// Compile a regular expression pattern
+pub fn compile_regex(pattern: &str) -> Result<Rc<Regex>, String> {
+    Regex::new(pattern)
+        .map(Rc::new)
+        .map_err(|e| format!("invalid regex '{}': {}", pattern, e))
+}
+
+// The regex given as a regex object or as a string holding the pattern
+fn regex_arg(obj: &Object) -> Result<Rc<Regex>, String> {
+    match obj {
+        Object::Regex(re) => Ok(re.clone()),
+        Object::Str(pattern) => compile_regex(pattern),
+        _ => Err(String::from("pattern should be a regex or a string")),
+    }
+}
+
+/// Check if a string or a payload matches a regex given as a regex object
+/// or a string holding the pattern. This is the '~' operator.
+pub fn regex_matches(subject: &Object, re: &Object) -> Result<bool, String> {
+    let re = regex_arg(re)?;
+    match subject {
+        Object::Str(s) => Ok(re.is_match_str(s)),
+        obj => Ok(re.is_match_bytes(&byte_array(obj)?)),
+    }
+}
+
+fn builtin_regex(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::Str(pattern) => Ok(Rc::new(Object::Regex(compile_regex(pattern)?))),
+        Object::Regex(_) => Ok(args[0].clone()),
+        _ => Err(String::from("argument should be a string")),
+    }
+}
+
+fn builtin_matches(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    Ok(Rc::new(Object::Bool(regex_matches(&args[0], &args[1])?)))
+}
+
+// Get the text of the leftmost match of a regex in a string or a payload,
+// followed by that of each group, which is null if the group did not take
+// part in the match. Returns null if there is no match.
+fn builtin_captures(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    let re = regex_arg(&args[1])?;
+    let groups: Option<Vec<Rc<Object>>> = match args[0].as_ref() {
+        Object::Str(s) => re.captures_str(s).map(|spans| {
+            spans
+                .iter()
+                .map(|span| match span {
+                    Some((start, end)) => Rc::new(Object::Str(s[*start..*end].to_string())),
+                    None => Rc::new(Object::Null),
+                })
+                .collect()
+        }),
+        obj => {
+            let bytes = byte_array(obj)?;
+            re.captures_bytes(&bytes).map(|spans| {
+                spans
+                    .iter()
+                    .map(|span| match span {
+                        Some((start, end)) => Rc::new(Object::Bytes(bytes[*start..*end].to_vec())),
+                        None => Rc::new(Object::Null),
+                    })
+                    .collect()
+            })
+        }
+    };
+    match groups {
+        Some(groups) => Ok(Rc::new(Object::Arr(Rc::new(Array::new(groups))))),
+        None => Ok(Rc::new(Object::Null)),
+    }
+}
+
+/// Generate a random number. Optionally take a max value
+fn builtin_rand(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    random(&mut rand::thread_rng(), &args)
+}
+
+/// Generate a random number for 'rand' using the given generator, which
+/// is a seeded one in the deterministic mode
+pub fn random<R: Rng>(rng: &mut R, args: &[Rc<Object>]) -> Result<Rc<Object>, String> {
+    if args.len() > 1 {
+        return Err(format!("takes one or no arguments. got={}", args.len()));
+    }
+    let max = if args.is_empty() {
+        Rc::new(Object::Integer(i64::MAX))
+    } else {
+        args[0].clone()
+    };
+    match max.as_ref() {
+        Object::Integer(n) => {
+            let r = rng.gen_range(0..=*n);
+            Ok(Rc::new(Object::Integer(r)))
+        }
+        Object::Float(n) => {
+            let r = rng.gen_range(0.0..=*n);
+            Ok(Rc::new(Object::Float(r)))
+        }
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+/// Opens a pcap file
+/// # Arguments
+/// * `args` - A vector of Rc<Object> containing the path to the file (Object::Str) and an optional
+///   second argument specifying the mode (Object::Str) or the link type (Object::Integer).
+/// # Returns
+/// Returns a Result containing a pcap file handle wrapped in an Object::Pcap,
+/// or an error object if the operation fails. An I/O error will result in the last
+/// error being set which can be retrieved using get_errno().
+/// Apart from opening the file, read the pcap header and validate
+/// the magic number and the endianness. Return error if the validation fails.
+/// A file opened with a link type is created for writing packets of that
+/// link type.
+#[cfg(feature = "pcap")]
+fn builtin_pcap_open(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    let (args, linktype) = match args.get(1).map(|a| a.as_ref()) {
+        Some(Object::Integer(n)) => {
+            let linktype = u32::try_from(*n).map_err(|_| String::from("invalid link type"))?;
+            let mode = Rc::new(Object::Str(String::from("w")));
+            (vec![args[0].clone(), mode], Some(linktype))
+        }
+        _ => (args, None),
+    };
+    let obj = builtin_open(args.clone())?;
+    let mode = if args.len() == 2 {
+        if let Object::Str(s) = args[1].as_ref() {
+            s
+        } else {
+            return Err(String::from("invalid mode"));
+        }
+    } else {
+        "r"
+    };
+    // Files with the extension '.pcapng' are written in the pcapng format
+    let format = match args[0].as_ref() {
+        Object::Str(path) if path.ends_with(".pcapng") => PcapFormat::PcapNg,
+        _ => PcapFormat::Pcap,
+    };
+    let res = match obj.as_ref() {
+        Object::File(f) => match mode {
+            "r" => Ok(Pcap::from_file(f.clone())),
+            "a" => Err(String::from("append mode not supported for pcap files")),
+            "w" | "x" => match linktype {
+                Some(linktype) => Ok(Pcap::new_with_linktype(
+                    f.clone(),
+                    PCAP_MAGIC_US,
+                    format,
+                    linktype,
+                )),
+                None => Ok(Pcap::new_with_format(f.clone(), PCAP_MAGIC_US, format)),
+            },
+            _ => Err(String::from("invalid file open mode")),
+        },
+        // Failed to open the file
+        Object::Err(_) => return Ok(obj),
+        _ => Err(String::from("unsupported argument")),
+    }?;
+    match res {
+        Ok(pcap) => Ok(Rc::new(Object::Pcap(Rc::new(pcap)))),
+        Err(e) => {
+            // Failed to open pcap file
+            Ok(Rc::new(Object::Err(ErrorObj::IO(e))))
+        }
+    }
+}
+
+/// Read the next packet from a pcap file
+/// # Arguments
+/// * `args` - A vector of Rc<Object> containing the pcap file handle
+/// # Returns
+/// Returns a Result containing a packet object wrapped in an Object::Packet,
+/// or an error if the operation fails. An I/O error will result in the last
+/// error being set which can be retrieved using get_errno().
+#[cfg(feature = "pcap")]
+fn builtin_pcap_read_next(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+
+    match args[0].as_ref() {
+        Object::Pcap(f) => {
+            match f.next_packet() {
+                Ok(packet) => {
+                    // Return the packet object
+                    Ok(Rc::new(Object::Packet(packet)))
+                }
+                Err(e) => {
+                    if e.kind() == io::ErrorKind::UnexpectedEof {
+                        // Return Object::Null for EOF error
+                        return Ok(Rc::new(Object::Null));
+                    }
+                    // For other IO errors, return the error
+                    Ok(Rc::new(Object::Err(ErrorObj::IO(e))))
+                }
+            }
+        }
+        _ => Err(String::from("first argument should be a file handle")),
+    }
+}
+
+/// Read all or a specified number of packets from a pcap file
+/// # Arguments
+/// * `args` - A vector of Rc<Object> containing the pcap file handle and an optional
+///   second argument specifying the number of packets to read (Object::Integer).
+/// # Returns
+/// Returns a Result containing an array of packet objects wrapped in an Object::Arr,
+/// or an error if the operation fails. An I/O error will result in the last
+/// error being set which can be retrieved using get_errno().
+/// If the number of packets to read is not specified, read all packets.
+/// If the number of packets to read is specified, read that many packets.
+#[cfg(feature = "pcap")]
+fn builtin_pcap_read_all(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(format!("takes one or two arguments. got={}", args.len()));
+    }
+
+    match args[0].as_ref() {
+        Object::Pcap(f) => {
+            let num_packets_to_read = if args.len() == 2 {
+                match args[1].as_ref() {
+                    Object::Integer(num) => *num as usize,
+                    _ => return Err(String::from("second argument should be an integer")),
+                }
+            } else {
+                usize::MAX
+            };
+            // Use next_packet() in a loop to read all packets
+            let mut packets = Vec::new();
+            for _ in 0..num_packets_to_read {
+                match f.next_packet() {
+                    Ok(packet) => {
+                        packets.push(Rc::new(Object::Packet(packet)));
+                    }
+                    Err(e) => {
+                        if e.kind() == io::ErrorKind::UnexpectedEof {
+                            break;
+                        }
+                        // For other IO errors, return the error
+                        return Ok(Rc::new(Object::Err(ErrorObj::IO(e))));
+                    }
+                }
+            }
+            // Return the array of packets
+            Ok(Rc::new(Object::Arr(Rc::new(Array::new(packets)))))
+        }
+        _ => Err(String::from("first argument should be a file handle")),
+    }
+}
+
+/// Opens a pcap stream
+/// # Arguments
+/// * `args` - A vector of Rc<Object> containing the path to the file (Object::Str) and an optional
+///   second argument specifying the mode (Object::Str).
+/// # Returns
+/// Returns a Result containing a pcap file handle wrapped in an Object::Pcap,
+/// or an error object if the operation fails. An I/O error will result in the last
+/// error being set which can be retrieved using get_errno().
+/// Apart from opening the file, read the pcap header and validate
+/// the magic number and the endianness. Return error if the validation fails.
+#[cfg(feature = "pcap")]
+fn builtin_pcap_stream(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() > 1 {
+        return Err(format!("takes one or no arguments. got={}", args.len()));
+    }
+    let result = match args[0].as_ref() {
+        Object::File(f) => match f.as_ref() {
+            FileHandle::Stdin => Pcap::from_file(f.clone()),
+            FileHandle::Stdout => Pcap::new(f.clone()),
+            _ => Err(String::from("invalid file handle"))?,
+        },
+        _ => Err(String::from("unsupported argument"))?,
+    };
+    match result {
+        Ok(pcap) => {
+            // Return the pcap object
+            Ok(Rc::new(Object::Pcap(Rc::new(pcap))))
+        }
+        Err(e) => {
+            // Failed to open pcap file
+            Ok(Rc::new(Object::Err(ErrorObj::IO(e))))
+        }
+    }
+}
+
+/// Write a packet to the pcap file
+/// # Arguments
+/// * `args` - A vector of Rc<Object> containing the pcap file handle
+/// # Returns
+/// Returns a Result containing a object wrapped in an Object::Integer,
+/// that represents the number of bytes written, or an error if the
+/// operation fails. An I/O error will result in the last error being set
+/// which can be retrieved using get_errno().
+#[cfg(feature = "pcap")]
+fn builtin_pcap_write(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::Pcap(f) => {
+            let packet = match args[1].as_ref() {
+                Object::Packet(p) => p,
+                _ => return Err(String::from("second argument should be a packet")),
+            };
+            match f.write_all(packet.clone()) {
+                Ok(n) => Ok(Rc::new(Object::Integer(n as i64))),
+                Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+            }
+        }
+        _ => Err(String::from("first argument should be a file handle")),
+    }
+}
+
+// Calls to 'globals()' and 'locals()' are compiled into map literals by the
+// compiler. These are only invoked when called indirectly, for instance,
+// via a variable that refers to the builtin.
+fn builtin_globals(_args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    Err(String::from("must be called directly"))
+}
+
+fn builtin_locals(_args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    Err(String::from("must be called directly"))
+}
+
+// Read an integer of 'size' bytes in network byte order from an array
+// of bytes starting at an optional offset
+fn read_network_order(args: &[Rc<Object>], size: usize) -> Result<i64, String> {
+    let bytes = byte_array(&args[0])?;
+    let offset = match args.get(1).map(|a| a.as_ref()) {
+        None => 0,
+        Some(Object::Integer(n)) if *n >= 0 => *n as usize,
+        Some(_) => return Err(String::from("second argument should be an offset")),
+    };
+    if offset + size > bytes.len() {
+        return Err(format!(
+            "array too short to read {} bytes at offset {}",
+            size, offset
+        ));
+    }
+    let value = bytes[offset..offset + size]
+        .iter()
+        .fold(0, |value, byte| (value << 8) | *byte as i64);
+    Ok(value)
+}
+
+// The bytes of a bytes object or of an array of bytes
+fn byte_array(obj: &Object) -> Result<Vec<u8>, String> {
+    match obj {
+        Object::Bytes(bytes) => Ok(bytes.clone()),
+        Object::Arr(arr) => arr
+            .elements
+            .borrow()
+            .iter()
+            .map(|obj| match obj.as_ref() {
+                Object::Byte(b) => Ok(*b),
+                _ => Err(String::from("array should contain only bytes")),
+            })
+            .collect(),
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+/// Convert a 16 bit integer from network to host byte order
+/// # Arguments
+/// * `args` - A vector of Rc<Object> containing an integer (Object::Integer)
+///   or bytes (Object::Bytes or Object::Arr) and an optional offset into it.
+/// # Returns
+/// Returns a Result containing the converted integer. If the argument is an
+/// array, two bytes at the offset are read in network byte order.
+// Validate the function to be run at exit. The VM registers the
+// function returned from here.
+fn builtin_atexit(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::Clos(c) if c.func.num_params == 0 => Ok(args[0].clone()),
+        Object::Clos(_) => Err(String::from("function must not take any arguments")),
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+// Validate the pattern and the action functions of a filter. The VM
+// registers the filter using the array returned from here.
+fn builtin_register_filter(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    for (i, arg) in args.iter().enumerate() {
+        match arg.as_ref() {
+            Object::Clos(c) if c.func.num_params == 0 => {}
+            Object::Clos(_) => return Err(String::from("function must not take any arguments")),
+            // The action is optional
+            Object::Null if i == 1 => {}
+            _ => return Err(String::from("unsupported argument")),
+        }
+    }
+    Ok(Rc::new(Object::Arr(Rc::new(Array::new(args)))))
+}
+
+// Create a set that is empty or that contains the elements of an array
+// or of another set
+fn builtin_set(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() > 1 {
+        return Err(format!("takes zero or one argument. got={}", args.len()));
+    }
+    let set = HSet::default();
+    match args.first().map(|a| a.as_ref()) {
+        None => {}
+        Some(Object::Arr(arr)) => {
+            for elem in arr.elements.borrow().iter() {
+                if !elem.is_a_valid_key() {
+                    return Err(format!("not a valid set element: {}", elem));
+                }
+                set.insert(elem.clone());
+            }
+        }
+        Some(Object::Set(other)) => {
+            set.elements.replace(other.elements.borrow().clone());
+        }
+        _ => return Err(String::from("unsupported argument")),
+    }
+    Ok(Rc::new(Object::Set(Rc::new(set))))
+}
+
+// Remove a key from a map or an element from a set. For maps, the removed
+// value is returned, or Null if the key was not present. For sets, it
+// returns true if the element was present.
+fn builtin_remove(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::Map(map) => match map.pairs.borrow_mut().remove(&args[1]) {
+            Some(val) => Ok(val),
+            None => Ok(Rc::new(Object::Null)),
+        },
+        Object::Set(set) => Ok(Rc::new(Object::Bool(set.remove(&args[1])))),
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+fn builtin_union(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    match (args[0].as_ref(), args[1].as_ref()) {
+        (Object::Set(a), Object::Set(b)) => Ok(Rc::new(Object::Set(Rc::new(a.union(b))))),
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+fn builtin_intersection(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    match (args[0].as_ref(), args[1].as_ref()) {
+        (Object::Set(a), Object::Set(b)) => Ok(Rc::new(Object::Set(Rc::new(a.intersection(b))))),
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+// Set the value that is inserted into a map when a key that is not
+// present is indexed. A null default removes it. Returns the map.
+fn builtin_mapdefault(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::Map(map) => {
+            let default = if args[1].is_null() {
+                None
+            } else {
+                Some(args[1].deep_copy())
+            };
+            map.set_default(default);
+            Ok(args[0].clone())
+        }
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+// Get the elements of an array or a set, or the values of a map
+// for the aggregate builtins
+fn aggregate_elements(obj: &Object) -> Result<Vec<Rc<Object>>, String> {
+    match obj {
+        Object::Arr(arr) => Ok(arr.elements.borrow().clone()),
+        // Copied as an element such as the maximum may be returned
+        Object::Set(set) => Ok(set
+            .sorted_elements()
+            .iter()
+            .map(|e| e.deep_copy())
+            .collect()),
+        Object::Map(map) => Ok(map.sorted_pairs().into_iter().map(|(_, v)| v).collect()),
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+// Add up numbers. The result is an integer when all the elements are
+// integers or bytes, and a float otherwise.
+fn sum_elements(elements: &[Rc<Object>]) -> Result<Rc<Object>, String> {
+    let mut int_sum: i64 = 0;
+    let mut float_sum: Option<f64> = None;
+    for elem in elements {
+        match elem.as_ref() {
+            Object::Integer(n) => {
+                int_sum = int_sum
+                    .checked_add(*n)
+                    .ok_or_else(|| String::from("integer overflow"))?;
+            }
+            Object::Byte(b) => {
+                int_sum = int_sum
+                    .checked_add(*b as i64)
+                    .ok_or_else(|| String::from("integer overflow"))?;
+            }
+            Object::Float(f) => *float_sum.get_or_insert(0.) += f,
+            _ => return Err(format!("not a number: {}", elem)),
+        }
+    }
+    match float_sum {
+        Some(f) => Ok(Rc::new(Object::Float(f + int_sum as f64))),
+        None => Ok(Rc::new(Object::Integer(int_sum))),
+    }
+}
+
+fn builtin_sum(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    sum_elements(&aggregate_elements(&args[0])?)
+}
+
+// Return the average of numbers as a float or null if there are none
+fn builtin_avg(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    let elements = aggregate_elements(&args[0])?;
+    if elements.is_empty() {
+        return Ok(Rc::new(Object::Null));
+    }
+    let avg = match sum_elements(&elements)?.as_ref() {
+        Object::Integer(n) => *n as f64 / elements.len() as f64,
+        Object::Float(f) => f / elements.len() as f64,
+        _ => unreachable!(),
+    };
+    Ok(Rc::new(Object::Float(avg)))
+}
+
+// Find the smallest or the largest element, either of an array, a set or
+// the values of a map, or of two or more arguments. Numbers of different
+// types are compared by their values. Returns null if there are no elements.
+fn min_max_elements(args: Vec<Rc<Object>>, ordering: Ordering) -> Result<Rc<Object>, String> {
+    let elements = match args.len() {
+        0 => return Err(String::from("takes one or more arguments. got=0")),
+        1 => aggregate_elements(&args[0])?,
+        _ => args,
+    };
+    let mut result: Option<Rc<Object>> = None;
+    for elem in elements {
+        result = match result {
+            None => Some(elem),
+            Some(curr) => match elem.partial_cmp(&curr) {
+                Some(ord) if ord == ordering => Some(elem),
+                Some(_) => Some(curr),
+                None => {
+                    return Err(format!("cannot compare {} and {}", elem, curr));
+                }
+            },
+        };
+    }
+    Ok(result.unwrap_or_else(|| Rc::new(Object::Null)))
+}
+
+fn builtin_min(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    min_max_elements(args, Ordering::Less)
+}
+
+fn builtin_max(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    min_max_elements(args, Ordering::Greater)
+}
+
+// Validate the array and the key function. The VM groups the elements
+// using the array returned from here since it needs to call the function.
+fn builtin_group_by(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    match (args[0].as_ref(), args[1].as_ref()) {
+        (Object::Arr(_), Object::Clos(c)) if c.func.num_params == 1 => {}
+        (Object::Arr(_), Object::Clos(_)) => {
+            return Err(String::from("function must take one argument"))
+        }
+        _ => return Err(String::from("unsupported argument")),
+    }
+    Ok(Rc::new(Object::Arr(Rc::new(Array::new(args)))))
+}
+
+// Compare two objects recursively and collect the paths at which they
+// differ along with the values on either side. Missing array elements and
+// map keys are represented by null. Packets and protocol objects are
+// compared by their bytes.
+fn diff_objects(
+    path: &str,
+    a: &Rc<Object>,
+    b: &Rc<Object>,
+    out: &mut Vec<(String, Rc<Object>, Rc<Object>)>,
+) {
+    match (a.as_ref(), b.as_ref()) {
+        (Object::Arr(x), Object::Arr(y)) => {
+            let x = x.elements.borrow();
+            let y = y.elements.borrow();
+            for i in 0..x.len().max(y.len()) {
+                let path = format!("{}[{}]", path, i);
+                diff_entry(path, x.get(i), y.get(i), out);
+            }
+        }
+        (Object::Map(x), Object::Map(y)) => {
+            let x = x.pairs.borrow();
+            let y = y.pairs.borrow();
+            // Sort the keys so that the differences are reported in a
+            // predictable order
+            let mut keys: Vec<&Rc<Object>> = x
+                .keys()
+                .chain(y.keys().filter(|k| !x.contains_key(*k)))
+                .collect();
+            keys.sort_by(|a, b| a.total_cmp(b));
+            for key in keys {
+                let path = format!("{}[{}]", path, key);
+                diff_entry(path, x.get(key), y.get(key), out);
+            }
+        }
+        #[cfg(feature = "pcap")]
+        (x, y) if is_protocol_object(x) && mem::discriminant(x) == mem::discriminant(y) => {
+            let x: Vec<u8> = a.as_ref().into();
+            let y: Vec<u8> = b.as_ref().into();
+            if x != y {
+                out.push((path.to_string(), a.clone(), b.clone()));
+            }
+        }
+        _ => {
+            if a != b {
+                out.push((path.to_string(), a.clone(), b.clone()));
+            }
+        }
+    }
+}
+
+// Compare the elements or values at the same index or key of two arrays
+// or maps, either of which may be missing
+fn diff_entry(
+    path: String,
+    a: Option<&Rc<Object>>,
+    b: Option<&Rc<Object>>,
+    out: &mut Vec<(String, Rc<Object>, Rc<Object>)>,
+) {
+    let null = || Rc::new(Object::Null);
+    match (a, b) {
+        (Some(a), Some(b)) => diff_objects(&path, a, b, out),
+        (Some(a), None) => out.push((path, a.clone(), null())),
+        (None, Some(b)) => out.push((path, null(), b.clone())),
+        (None, None) => {}
+    }
+}
+
+#[cfg(feature = "pcap")]
+fn is_protocol_object(obj: &Object) -> bool {
+    matches!(
+        obj,
+        Object::Packet(_)
+            | Object::Eth(_)
+            | Object::Vlan(_)
+            | Object::Ipv4(_)
+            | Object::Ipv6(_)
+            | Object::Udp(_)
+            | Object::Tcp(_)
+            | Object::Dns(_)
+            | Object::Tls(_)
+            | Object::Tunnel(_)
+    )
+}
+
+// Deep comparison of two objects
+pub fn builtin_equals(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    let mut diffs = Vec::new();
+    diff_objects("", &args[0], &args[1], &mut diffs);
+    Ok(Rc::new(Object::Bool(diffs.is_empty())))
+}
+
+// Return a map of the paths at which two objects differ to an array of
+// the two differing values. An empty map is returned if they are equal.
+fn builtin_diff(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    let mut diffs = Vec::new();
+    diff_objects("", &args[0], &args[1], &mut diffs);
+    let result = HMap::default();
+    for (path, a, b) in diffs {
+        let values = Rc::new(Object::Arr(Rc::new(Array::new(vec![a, b]))));
+        result.insert(Rc::new(Object::Str(path)), values);
+    }
+    Ok(Rc::new(Object::Map(Rc::new(result))))
+}
+
+fn builtin_ntohs(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(format!("takes one or two arguments. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::Integer(n) if args.len() == 1 => {
+            Ok(Rc::new(Object::Integer(u16::from_be(*n as u16) as i64)))
+        }
+        _ => Ok(Rc::new(Object::Integer(read_network_order(&args, 2)?))),
+    }
+}
+
+/// Convert a 32 bit integer from network to host byte order
+/// # Arguments
+/// * `args` - A vector of Rc<Object> containing an integer (Object::Integer)
+///   or bytes (Object::Bytes or Object::Arr) and an optional offset into it.
+/// # Returns
+/// Returns a Result containing the converted integer. If the argument is an
+/// array, four bytes at the offset are read in network byte order.
+fn builtin_ntohl(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(format!("takes one or two arguments. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::Integer(n) if args.len() == 1 => {
+            Ok(Rc::new(Object::Integer(u32::from_be(*n as u32) as i64)))
+        }
+        _ => Ok(Rc::new(Object::Integer(read_network_order(&args, 4)?))),
+    }
+}
+
+// Convert a 16 bit integer from host to network byte order
+fn builtin_htons(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::Integer(n) => Ok(Rc::new(Object::Integer((*n as u16).to_be() as i64))),
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+// Convert a 32 bit integer from host to network byte order
+fn builtin_htonl(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::Integer(n) => Ok(Rc::new(Object::Integer((*n as u32).to_be() as i64))),
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+fn builtin_assert(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(format!("takes one or two arguments. got={}", args.len()));
+    }
+    if !args[0].is_falsey() {
+        return Ok(Rc::new(Object::Null));
+    }
+    match args.get(1).map(|msg| msg.as_ref()) {
+        Some(Object::Str(msg)) => Err(format!("assertion failed: {}", msg)),
+        Some(msg) => Err(format!("assertion failed: {}", msg)),
+        None => Err(String::from("assertion failed")),
+    }
+}
+
+// The VM records the path returned from here as the pcap file to read
+// the packets from in filter mode.
+fn builtin_testdata(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::Str(_) => Ok(args[0].clone()),
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+// Decode the payload on a udp or tcp port as a protocol. The mapping is
+// added to the VM, which intercepts the array returned here.
+#[cfg(feature = "pcap")]
+fn builtin_decode_as(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    match (args[0].as_ref(), args[1].as_ref()) {
+        (Object::Integer(port), Object::Str(proto)) => {
+            if u16::try_from(*port).is_err() {
+                return Err(format!("invalid port {}", port));
+            }
+            parse_app_proto(proto)?;
+            Ok(Rc::new(Object::Arr(Rc::new(Array::new(args)))))
+        }
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+// Bytes searched by 'find'
+fn search_bytes(obj: &Object) -> Option<Vec<u8>> {
+    match obj {
+        Object::Str(s) => Some(s.as_bytes().to_vec()),
+        Object::Char(c) => Some(c.to_string().into_bytes()),
+        Object::Byte(b) => Some(vec![*b]),
+        Object::Arr(arr) => Some(arr.as_ref().into()),
+        Object::Bytes(bytes) => Some(bytes.clone()),
+        _ => None,
+    }
+}
+
+// Find the first occurrence of a string or an array of bytes in a
+// string or a payload, optionally starting at an offset. Returns the
+// offset of the match, or null if not found.
+fn builtin_find(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(format!("takes two or three arguments. got={}", args.len()));
+    }
+    let haystack = search_bytes(&args[0]).ok_or("unsupported argument")?;
+    let needle = search_bytes(&args[1]).ok_or("unsupported argument")?;
+    let start = match args.get(2).map(|a| a.as_ref()) {
+        None => 0,
+        Some(Object::Integer(start)) if *start >= 0 => *start as usize,
+        Some(_) => return Err(String::from("start must be a non-negative integer")),
+    };
+    let found = haystack
+        .get(start..)
+        .and_then(|hay| search::find(hay, &needle))
+        .map(|pos| Object::Integer((start + pos) as i64));
+    Ok(Rc::new(found.unwrap_or(Object::Null)))
+}
+
+// Convert a string or an array of bytes into a bytes object, the type of
+// the payloads of packets
+fn builtin_bytes(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    Ok(Rc::new(Object::Bytes(inspect_bytes(&args[0])?)))
+}
+
+// Bytes of a string, an array of bytes, a payload, a packet or one of its
+// layers. A packet yields the frame without the capture record header and
+// a layer yields its header followed by the data it carries.
+fn inspect_bytes(obj: &Object) -> Result<Vec<u8>, String> {
+    match obj {
+        Object::Str(s) => Ok(s.as_bytes().to_vec()),
+        #[cfg(feature = "pcap")]
+        Object::Packet(pkt) => Ok(pkt.serialize().1),
+        #[cfg(feature = "pcap")]
+        Object::Eth(_)
+        | Object::Vlan(_)
+        | Object::Ipv4(_)
+        | Object::Ipv6(_)
+        | Object::Udp(_)
+        | Object::Tcp(_)
+        | Object::Dns(_)
+        | Object::Tls(_)
+        | Object::Tunnel(_) => Ok(obj.into()),
+        obj => byte_array(obj),
+    }
+}
+
+// Number of bytes shown on each line of a hex dump
+const HEXDUMP_WIDTH: usize = 16;
+
+// Dump bytes in the format of Wireshark, with the offset of each line
+// followed by the bytes in hex and then as ASCII characters
+fn builtin_hexdump(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    let data = inspect_bytes(&args[0])?;
+    let offset_width = if data.len() > 0x10000 { 8 } else { 4 };
+    let mut lines = Vec::new();
+    for (idx, chunk) in data.chunks(HEXDUMP_WIDTH).enumerate() {
+        let mut line = format!("{:0w$x} ", idx * HEXDUMP_WIDTH, w = offset_width);
+        for i in 0..HEXDUMP_WIDTH {
+            // An extra space separates the two halves of the line
+            if i == HEXDUMP_WIDTH / 2 {
+                line.push(' ');
+            }
+            match chunk.get(i) {
+                Some(b) => line.push_str(&format!(" {:02x}", b)),
+                None => line.push_str("   "),
+            }
+        }
+        line.push_str("   ");
+        line.extend(chunk.iter().map(|b| {
+            if b.is_ascii_graphic() || *b == b' ' {
+                *b as char
+            } else {
+                '.'
+            }
+        }));
+        lines.push(line);
+    }
+    Ok(Rc::new(Object::Str(lines.join("\n"))))
+}
+
+// Format an integer in hex, padded with zeros to an optional width
+fn builtin_hex(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(format!("takes one or two arguments. got={}", args.len()));
+    }
+    let num = match args[0].as_ref() {
+        Object::Integer(num) => *num as u64,
+        Object::Byte(b) => *b as u64,
+        _ => return Err(String::from("first argument should be an integer")),
+    };
+    let width = match args.get(1).map(|a| a.as_ref()) {
+        None => 0,
+        Some(Object::Integer(w)) if (0..=64).contains(w) => *w as usize,
+        Some(_) => return Err(String::from("width must be an integer from 0 to 64")),
+    };
+    Ok(Rc::new(Object::Str(format!("{:0w$x}", num, w = width))))
+}
+
+// Take 'len' elements starting at 'start', or the rest if 'len' is not
+// given. Strings yield strings of characters, arrays yield arrays and
+// everything else yields bytes. The range is clipped to the end.
+fn builtin_slice(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(format!("takes two or three arguments. got={}", args.len()));
+    }
+    let start = match args[1].as_ref() {
+        Object::Integer(start) if *start >= 0 => *start as usize,
+        _ => return Err(String::from("start must be a non-negative integer")),
+    };
+    let len = match args.get(2).map(|a| a.as_ref()) {
+        None => usize::MAX,
+        Some(Object::Integer(len)) if *len >= 0 => *len as usize,
+        Some(_) => return Err(String::from("length must be a non-negative integer")),
+    };
+    if let Some(slice) = args[0].seq_slice(start, len) {
+        return Ok(slice);
+    }
+    // The layers of a packet are sliced as bytes
+    let data = inspect_bytes(&args[0])?;
+    let data = data.into_iter().skip(start).take(len).collect();
+    Ok(Rc::new(Object::Bytes(data)))
+}
+
+// Collect the properties of a packet that match a pattern such as 'tcp.*'
+// into a map. The properties are read by the VM since decoding the layers
+// of the packet depends on its state.
+#[cfg(feature = "pcap")]
+fn builtin_props(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    let Object::Str(pattern) = args[1].as_ref() else {
+        return Err(String::from("second argument should be a pattern string"));
+    };
+    if match_props(pattern).is_empty() {
+        return Err(format!("no property matches '{}'", pattern));
+    }
+    if !is_packet_object(&args[0]) {
+        return Err(String::from("unsupported argument"));
+    }
+    Ok(Rc::new(Object::Arr(Rc::new(Array::new(args)))))
+}
+
+// Convert a packet into a map of its properties in which the layers
+// within it are nested maps. The layers are decoded by the VM.
+#[cfg(feature = "pcap")]
+fn builtin_to_map(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    if !is_packet_object(&args[0]) {
+        return Err(String::from("unsupported argument"));
+    }
+    Ok(Rc::new(Object::Arr(Rc::new(Array::new(args)))))
+}
+
+// Decode a packet into a map of its layers, or a JSON string of the map
+// if the format is "json". The layers are decoded by the VM.
+#[cfg(feature = "pcap")]
+fn builtin_decode(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(format!("takes one or two arguments. got={}", args.len()));
+    }
+    if !is_packet_object(&args[0]) {
+        return Err(String::from("unsupported argument"));
+    }
+    match args.get(1).map(|a| a.as_ref()) {
+        None => {}
+        Some(Object::Str(f)) if f == "map" || f == "json" => {}
+        Some(_) => return Err(String::from("format must be \"map\" or \"json\"")),
+    }
+    Ok(Rc::new(Object::Arr(Rc::new(Array::new(args)))))
+}
+
+// Write an array as a row of comma separated values to a file handle
+// and return the number of bytes written
+fn builtin_csv_write(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    match (args[0].as_ref(), args[1].as_ref()) {
+        (Object::File(_), Object::Arr(arr)) => {
+            let row = csv::to_csv_row(&arr.elements.borrow());
+            builtin_write(vec![args[0].clone(), Rc::new(Object::Str(row))])
+        }
+        (Object::File(_), _) => Err(String::from("second argument should be an array")),
+        _ => Err(String::from("first argument should be a file handle")),
+    }
+}
+
+// Attach a comment to a packet, or remove it given null. The comment is
+// written as a packet comment option when the packet is written to a
+// pcapng file.
+#[cfg(feature = "pcap")]
+fn builtin_set_comment(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    match (args[0].as_ref(), args[1].as_ref()) {
+        (Object::Packet(pkt), Object::Str(comment)) => pkt.set_comment(Some(comment.clone())),
+        (Object::Packet(pkt), Object::Null) => pkt.set_comment(None),
+        (Object::Packet(_), _) => return Err(String::from("comment should be a string or null")),
+        _ => return Err(String::from("first argument should be a packet")),
+    }
+    Ok(Rc::new(Object::Null))
+}
+
+// Look up the name of the host with an ip address. The name is resolved
+// and cached by the VM, which intercepts the address returned here.
+fn builtin_resolve(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    let addr = match args[0].as_ref() {
+        #[cfg(feature = "pcap")]
+        Object::Addr(addr @ (Addr::Ipv4(_) | Addr::Ipv6(_))) => addr.to_string(),
+        Object::Str(s) => s.clone(),
+        _ => return Err(String::from("argument should be an ip address")),
+    };
+    if addr.parse::<IpAddr>().is_err() {
+        return Err(format!("invalid ip address '{}'", addr));
+    }
+    let addr = Rc::new(Object::Str(addr));
+    Ok(Rc::new(Object::Arr(Rc::new(Array::new(vec![addr])))))
+}
+
+// Look up the name of the service on a port of a transport protocol. The
+// name is looked up by the VM, which intercepts the array returned here.
+fn builtin_service_name(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    match (args[0].as_ref(), args[1].as_ref()) {
+        (Object::Integer(port), Object::Str(_)) => {
+            if u16::try_from(*port).is_err() {
+                return Err(format!("invalid port {}", port));
+            }
+            Ok(Rc::new(Object::Arr(Rc::new(Array::new(args)))))
+        }
+        _ => Err(String::from("unsupported argument")),
+    }
+}
+
+// Convert a string to an ipv4 or ipv6 address
+#[cfg(feature = "pcap")]
+fn builtin_ip(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::Addr(Addr::Ipv4(_) | Addr::Ipv6(_)) => Ok(args[0].clone()),
+        Object::Str(s) => match Addr::parse(s) {
+            Some(addr @ (Addr::Ipv4(_) | Addr::Ipv6(_))) => Ok(Rc::new(Object::Addr(addr))),
+            _ => {
+                let msg = format!("invalid ip address '{}'", s);
+                Ok(Rc::new(Object::Err(ErrorObj::Value(msg))))
+            }
+        },
+        _ => Err(String::from("argument should be a string")),
+    }
+}
+
+// Convert a string such as "10.0.0.0/8" to a network
+#[cfg(feature = "pcap")]
+fn builtin_cidr(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    match args[0].as_ref() {
+        Object::Cidr(_) => Ok(args[0].clone()),
+        Object::Str(s) => match Cidr::parse(s) {
+            Ok(cidr) => Ok(Rc::new(Object::Cidr(cidr))),
+            Err(msg) => Ok(Rc::new(Object::Err(ErrorObj::Value(msg)))),
+        },
+        _ => Err(String::from("argument should be a string")),
+    }
+}
+
+// Check if an action keyed by a value may run given the maximum number of
+// times it may run over a period. The VM keeps the buckets of tokens.
+fn builtin_ratelimit(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    check_rate(&args)?;
+    Ok(Rc::new(Object::Arr(Rc::new(Array::new(args)))))
+}
+
+// Check if an action keyed by a value may run given the maximum number of
+// times it may run in each window of time. The VM counts the calls.
+fn builtin_rate_limit(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    check_rate(&args)?;
+    Ok(Rc::new(Object::Arr(Rc::new(Array::new(args)))))
+}
+
+// Validate the key, the maximum and the period of 'ratelimit' and
+// 'rate_limit'
+fn check_rate(args: &[Rc<Object>]) -> Result<(), String> {
+    if args.len() != 3 {
+        return Err(format!("takes three arguments. got={}", args.len()));
+    }
+    if !args[0].is_a_valid_key() {
+        return Err(format!("not a valid key: {}", args[0]));
+    }
+    match args[1].as_ref() {
+        Object::Integer(n) if *n > 0 => {}
+        _ => return Err(String::from("maximum must be a positive integer")),
+    }
+    match args[2].as_secs() {
+        Some(secs) if secs > 0.0 && secs.is_finite() => Ok(()),
+        _ => Err(String::from("period must be a positive number of seconds")),
+    }
+}
+
+// A timestamp from a number of seconds since the epoch, or from the
+// seconds and the nanoseconds as integers
+fn builtin_timestamp(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    let ts = match args.iter().map(|a| a.as_ref()).collect::<Vec<_>>()[..] {
+        [Object::Timestamp(_)] => return Ok(args[0].clone()),
+        [secs @ (Object::Integer(_) | Object::Float(_))] => to_timestamp(secs),
+        [Object::Integer(secs), Object::Integer(nanos)] => Timestamp::new(*secs, *nanos),
+        [_] | [_, _] => return Err(String::from("unsupported argument")),
+        _ => return Err(format!("takes one or two arguments. got={}", args.len())),
+    };
+    match ts {
+        Some(ts) => Ok(Rc::new(Object::Timestamp(ts))),
+        None => Err(String::from("timestamp out of range")),
+    }
+}
+
+// Format a timestamp, or a number of seconds since the epoch, in UTC
+fn builtin_strftime(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 2 {
+        return Err(format!("takes two arguments. got={}", args.len()));
+    }
+    let ts = match args[0].as_ref() {
+        Object::Timestamp(ts) => *ts,
+        secs @ (Object::Integer(_) | Object::Float(_)) => {
+            to_timestamp(secs).ok_or("timestamp out of range")?
+        }
+        _ => return Err(String::from("first argument should be a timestamp")),
+    };
+    let Object::Str(format) = args[1].as_ref() else {
+        return Err(String::from("format should be a string"));
+    };
+    Ok(Rc::new(Object::Str(ts.strftime(format)?)))
+}
+
+// A duration from a number of milliseconds
+fn builtin_duration(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    let d = match args[0].as_ref() {
+        Object::Duration(_) => return Ok(args[0].clone()),
+        Object::Integer(ms) => ms.checked_mul(1_000_000).map(Duration::from_nanos),
+        Object::Float(ms) => Duration::from_millis_f64(*ms),
+        _ => return Err(String::from("argument should be a number of milliseconds")),
+    };
+    match d {
+        Some(d) => Ok(Rc::new(Object::Duration(d))),
+        None => Err(String::from("duration out of range")),
+    }
+}
+
+// A timestamp from a number of seconds since the epoch
+fn to_timestamp(secs: &Object) -> Option<Timestamp> {
+    match secs {
+        Object::Integer(secs) => Timestamp::new(*secs, 0),
+        Object::Float(secs) => Timestamp::from_secs_f64(*secs),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "pcap")]
+fn is_packet_object(obj: &Object) -> bool {
+    matches!(
+        obj,
+        Object::Pcap(_)
+            | Object::Packet(_)
+            | Object::Eth(_)
+            | Object::Vlan(_)
+            | Object::Ipv4(_)
+            | Object::Ipv6(_)
+            | Object::Udp(_)
+            | Object::Tcp(_)
+            | Object::Dns(_)
+            | Object::Tls(_)
+            | Object::Tunnel(_)
+    )
+}
+
+// Encode an object, such as a map returned by 'to_map', as a JSON string
+fn builtin_to_json(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.len() != 1 {
+        return Err(format!("takes one argument. got={}", args.len()));
+    }
+    Ok(Rc::new(Object::Str(json::to_json(&args[0])?)))
+}
+
+// The record of the flow of the current packet is looked up by the VM
+#[cfg(feature = "pcap")]
+fn builtin_flow(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if !args.is_empty() {
+        return Err(format!("takes no arguments. got={}", args.len()));
+    }
+    Ok(Rc::new(Object::Null))
+}
+
+// The records of the active flows are collected by the VM
+#[cfg(feature = "pcap")]
+fn builtin_flows(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if !args.is_empty() {
+        return Err(format!("takes no arguments. got={}", args.len()));
+    }
+    Ok(Rc::new(Object::Null))
+}
+
+// Validate the idle timeout of the flows in seconds and the optional
+// function that is passed the record of each expired flow. The VM sets
+// them using the array returned from here.
+#[cfg(feature = "pcap")]
+fn builtin_flow_timeout(args: Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(format!("takes one or two arguments. got={}", args.len()));
+    }
+    match args[0].as_secs() {
+        Some(secs) if secs > 0.0 && secs.is_finite() => {}
+        _ => return Err(String::from("timeout must be a positive number of seconds")),
+    }
+    match args.get(1).map(|a| a.as_ref()) {
+        None => {}
+        Some(Object::Clos(c)) if c.func.num_params == 1 => {}
+        Some(Object::Clos(_)) => return Err(String::from("function must take one argument")),
+        Some(_) => return Err(String::from("unsupported argument")),
+    }
+    Ok(Rc::new(Object::Arr(Rc::new(Array::new(args)))))
+}
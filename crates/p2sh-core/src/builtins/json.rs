@@ -0,0 +1,109 @@
+//! Encoding of objects as JSON.
+//!
+//! Strings, characters, addresses, networks and timestamps are encoded as
+//! strings, bytes as hex strings, and arrays and sets as arrays. Maps are
+//! encoded as objects with their keys sorted so that the output is stable;
+//! keys that are not strings are encoded as their textual form. Non-finite
+//! floats, which have no JSON representation, are encoded as null.
+
+use std::fmt::Write;
+
+use crate::object::Object;
+
+/// Encode an object as JSON. Returns an error for objects such as
+/// functions and files that have no JSON representation.
+pub fn to_json(obj: &Object) -> Result<String, String> {
+    let mut out = String::new();
+    encode(obj, &mut out)?;
+    Ok(out)
+}
+
+fn encode(obj: &Object, out: &mut String) -> Result<(), String> {
+    match obj {
+        Object::Null => out.push_str("null"),
+        Object::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Object::Integer(n) => out.push_str(&n.to_string()),
+        Object::Byte(b) => out.push_str(&b.to_string()),
+        Object::Float(f) if f.is_finite() => out.push_str(&f.to_string()),
+        Object::Float(_) => out.push_str("null"),
+        Object::Str(s) => encode_str(s, out),
+        Object::Char(c) => encode_str(&c.to_string(), out),
+        #[cfg(feature = "pcap")]
+        Object::Addr(addr) => encode_str(&addr.to_string(), out),
+        #[cfg(feature = "pcap")]
+        Object::Cidr(cidr) => encode_str(&cidr.to_string(), out),
+        Object::Timestamp(ts) => encode_str(&ts.to_string(), out),
+        // Durations are encoded as a number of seconds
+        Object::Duration(d) => out.push_str(&d.as_secs_f64().to_string()),
+        Object::Bytes(bytes) => {
+            let hex = bytes.iter().fold(String::new(), |mut s, b| {
+                let _ = write!(s, "{:02x}", b);
+                s
+            });
+            encode_str(&hex, out);
+        }
+        Object::Arr(arr) => {
+            let elements = arr.elements.borrow();
+            let encoded = elements
+                .iter()
+                .map(|e| to_json(e))
+                .collect::<Result<Vec<_>, _>>()?;
+            out.push('[');
+            out.push_str(&encoded.join(","));
+            out.push(']');
+        }
+        Object::Set(set) => {
+            let mut encoded = set
+                .elements
+                .borrow()
+                .iter()
+                .map(|e| to_json(e))
+                .collect::<Result<Vec<_>, _>>()?;
+            encoded.sort();
+            out.push('[');
+            out.push_str(&encoded.join(","));
+            out.push(']');
+        }
+        Object::Map(map) => {
+            let mut members = Vec::new();
+            for (key, value) in map.pairs.borrow().iter() {
+                let key = match key.as_ref() {
+                    Object::Str(s) => s.clone(),
+                    key => key.to_string(),
+                };
+                members.push((key, to_json(value)?));
+            }
+            members.sort();
+            out.push('{');
+            for (i, (key, value)) in members.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                encode_str(key, out);
+                out.push(':');
+                out.push_str(value);
+            }
+            out.push('}');
+        }
+        _ => return Err(format!("cannot encode {} as json", obj)),
+    }
+    Ok(())
+}
+
+fn encode_str(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
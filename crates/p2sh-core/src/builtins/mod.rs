@@ -0,0 +1,19 @@
+#[cfg(feature = "pcap")]
+pub use p2sh_dissect::{capture, pcapng};
+
+#[cfg(feature = "pcap")]
+pub mod bpf;
+pub mod csv;
+pub mod functions;
+pub mod json;
+#[cfg(feature = "pcap")]
+pub mod pcap;
+#[cfg(feature = "pcap")]
+pub mod pcapdiff;
+#[cfg(feature = "pcap")]
+pub mod pcapfiles;
+pub mod print;
+pub mod regex;
+pub mod search;
+pub mod tests;
+pub mod variables;
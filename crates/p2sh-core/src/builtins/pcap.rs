@@ -0,0 +1,1112 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::convert::From;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::rc::Rc;
+
+use super::capture::{Capture, CaptureStats};
+use super::pcapng::{self, PcapNgBlock, PcapNgInterface};
+use super::pcapng::{PCAPNG_EPB, PCAPNG_IDB, PCAPNG_SHB, PCAPNG_SPB};
+use crate::object::file::{borrow_open, FileHandle};
+use crate::object::timestamp::Timestamp;
+use crate::object::Object;
+pub use p2sh_dissect::record::{read_u16, read_u32, PcapPacketHeader};
+
+pub const PCAP_MAGIC_US: u32 = 0xA1B2C3D4;
+const PCAP_MAGIC_NS: u32 = 0xA1B23C4D;
+// Records larger than this are considered garbage as no sane capture
+// would have a packet this large. Same as MAXIMUM_SNAPLEN in libpcap.
+// It is the default maximum packet size.
+const PCAP_MAX_SNAPLEN: u32 = 262144;
+// Size of the chunks in which the data of a skipped record is read
+const PCAP_DISCARD_CHUNK: usize = 65536;
+// Maximum gap in seconds between the timestamps of consecutive packets
+// for a packet record header to be considered plausible while resyncing.
+const PCAP_RESYNC_MAX_TS_GAP: u32 = 3600;
+
+/// Action to take when a packet record with inconsistent
+/// lengths is found while reading a pcap stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum CorruptPolicy {
+    /// Stop reading with an error
+    #[default]
+    Error,
+    /// Display a warning and use the record as is
+    Warn,
+    /// Truncate the record to a valid length
+    Clamp,
+    /// Skip the record
+    Skip,
+}
+
+/// Correction of the timestamps of the packets read, such as those of a
+/// clock that is off by an offset or that runs fast or slow
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TsCorrection {
+    /// Nanoseconds added to each timestamp
+    pub offset: i64,
+    /// Parts per million added to the time elapsed since the first
+    /// packet; positive for a clock that runs slow
+    pub skew_ppm: f64,
+    origin: Option<i64>, // time of the first packet in nanoseconds
+}
+
+impl TsCorrection {
+    pub fn new(offset: i64, skew_ppm: f64) -> Self {
+        Self {
+            offset,
+            skew_ppm,
+            origin: None,
+        }
+    }
+
+    /// Correct the timestamp of a packet, which is in nanoseconds if
+    /// 'nanosec' is set, else in microseconds
+    pub fn apply(&mut self, header: &mut PcapPacketHeader, nanosec: bool) {
+        let unit = if nanosec { 1 } else { 1000 };
+        let ts = header.ts_sec as i64 * 1_000_000_000 + header.ts_usec as i64 * unit;
+        let origin = *self.origin.get_or_insert(ts);
+        let skew = ((ts - origin) as f64 * self.skew_ppm / 1e6).round() as i64;
+        let max = (u32::MAX as i64 + 1) * 1_000_000_000 - 1;
+        let ts = (ts + self.offset + skew).clamp(0, max);
+        header.ts_sec = (ts / 1_000_000_000) as u32;
+        header.ts_usec = ((ts % 1_000_000_000) / unit) as u32;
+    }
+}
+
+/// File format of a pcap stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PcapFormat {
+    /// The classic libpcap format
+    #[default]
+    Pcap,
+    /// The pcap next generation format
+    PcapNg,
+}
+
+#[derive(Debug, PartialEq)]
+enum PcapTsFormat {
+    MicroSeconds,
+    NanoSeconds,
+}
+
+#[derive(Debug)]
+pub struct PcapGlobalHeader {
+    magic_number: u32,
+    version_major: u16,
+    version_minor: u16,
+    thiszone: i32,
+    sigfigs: u32,
+    snaplen: u32,
+    linktype: u32,
+    // Byte order of the headers in the pcap stream being read.
+    // Pcap streams are always written in little endian order.
+    big_endian: bool,
+}
+
+impl Default for PcapGlobalHeader {
+    fn default() -> Self {
+        Self {
+            magic_number: PCAP_MAGIC_US,
+            version_major: 2,
+            version_minor: 4,
+            thiszone: 0,
+            sigfigs: 0,
+            snaplen: 65535,
+            linktype: 1,
+            big_endian: false,
+        }
+    }
+}
+
+impl From<&PcapGlobalHeader> for Vec<u8> {
+    fn from(header: &PcapGlobalHeader) -> Self {
+        let mut bytes = Vec::new();
+
+        // Convert each field to its byte representation and append to the bytes Vec
+        bytes.extend_from_slice(&header.magic_number.to_le_bytes());
+        bytes.extend_from_slice(&header.version_major.to_le_bytes());
+        bytes.extend_from_slice(&header.version_minor.to_le_bytes());
+        bytes.extend_from_slice(&header.thiszone.to_le_bytes());
+        bytes.extend_from_slice(&header.sigfigs.to_le_bytes());
+        bytes.extend_from_slice(&header.snaplen.to_le_bytes());
+        bytes.extend_from_slice(&header.linktype.to_le_bytes());
+
+        bytes
+    }
+}
+
+impl PcapGlobalHeader {
+    pub fn new(magic_number: u32) -> Self {
+        Self {
+            magic_number,
+            ..Default::default()
+        }
+    }
+    pub fn from_bytes(data: &[u8]) -> io::Result<Self> {
+        if data.len() < 24 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid pcap global header size: {:X}", data.len()),
+            ));
+        }
+
+        // The byte order of the pcap stream is determined by the magic number.
+        // The magic number read in the wrong byte order appears swapped.
+        let magic_number = read_u32(data, false);
+        let big_endian = match magic_number {
+            PCAP_MAGIC_US | PCAP_MAGIC_NS => false,
+            m if m.swap_bytes() == PCAP_MAGIC_US || m.swap_bytes() == PCAP_MAGIC_NS => true,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid pcap magic number {:X}", magic_number,),
+                ));
+            }
+        };
+        let magic_number = read_u32(data, big_endian);
+        let version_major = read_u16(&data[4..], big_endian);
+        let version_minor = read_u16(&data[6..], big_endian);
+        let thiszone = read_u32(&data[8..], big_endian) as i32;
+        let sigfigs = read_u32(&data[12..], big_endian);
+        let snaplen = read_u32(&data[16..], big_endian);
+        let linktype = read_u32(&data[20..], big_endian);
+
+        Ok(Self {
+            magic_number,
+            version_major,
+            version_minor,
+            thiszone,
+            sigfigs,
+            snaplen,
+            linktype,
+            big_endian,
+        })
+    }
+    pub fn snaplen(&self) -> u32 {
+        self.snaplen
+    }
+}
+
+#[derive(Debug)]
+pub struct PcapPacket {
+    header: RefCell<PcapPacketHeader>,
+    pub inner: RefCell<Option<Rc<Object>>>,
+    pub rawdata: RefCell<Rc<Vec<u8>>>,
+    truncated: Cell<bool>, // a layer could not be parsed as the capture is short
+    comment: RefCell<Option<String>>, // written as an option in pcapng
+    nanosec: Cell<bool>,   // the sub-second part of the timestamp is in nanoseconds
+}
+
+impl fmt::Display for PcapPacket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.header.borrow().clone())?;
+        if let Some(inner) = self.inner.borrow().clone() {
+            write!(f, " {}", inner)
+        } else {
+            write!(f, " [len: {}]", self.rawdata.borrow().len())
+        }
+    }
+}
+
+impl From<&PcapPacket> for Vec<u8> {
+    fn from(pkt: &PcapPacket) -> Self {
+        let (header, data) = pkt.serialize();
+        let mut bytes: Vec<u8> = (&header).into();
+        bytes.extend_from_slice(&data);
+        bytes
+    }
+}
+
+impl PcapPacket {
+    /// Create a packet from its header and the data following it
+    pub fn new(header: PcapPacketHeader, data: Vec<u8>) -> Self {
+        Self {
+            header: RefCell::new(header),
+            rawdata: RefCell::new(Rc::new(data)),
+            inner: RefCell::new(None),
+            truncated: Cell::new(false),
+            comment: RefCell::new(None),
+            nanosec: Cell::new(false),
+        }
+    }
+
+    /// A copy of the packet that can be changed without changing this one
+    pub fn duplicate(&self) -> Self {
+        let (header, data) = self.serialize();
+        let pkt = Self::new(header, data);
+        pkt.set_comment(self.comment());
+        pkt.set_nanosec(self.nanosec.get());
+        pkt
+    }
+
+    /// Comment attached to the packet by a script or read from a pcapng
+    pub fn comment(&self) -> Option<String> {
+        self.comment.borrow().clone()
+    }
+
+    pub fn set_comment(&self, comment: Option<String>) {
+        *self.comment.borrow_mut() = comment;
+    }
+
+    /// Set if a layer could not be parsed since the capture is shorter
+    /// than the packet on the wire
+    pub fn is_truncated(&self) -> bool {
+        self.truncated.get()
+    }
+
+    /// Flag the packet as truncated if the capture is shorter than the
+    /// packet on the wire. Returns true if the flag was newly set.
+    pub fn set_truncated(&self) -> bool {
+        let header = self.header.borrow();
+        if header.caplen >= header.wirelen || self.truncated.get() {
+            return false;
+        }
+        self.truncated.set(true);
+        true
+    }
+
+    /// The packet data following the packet header
+    pub fn payload(&self) -> Vec<u8> {
+        match self.inner.borrow().as_ref() {
+            Some(inner) => inner.as_ref().into(),
+            None => self.rawdata.borrow().to_vec(),
+        }
+    }
+    /// The header and the data of the packet as written to a pcap. The
+    /// lengths in the header follow the data if rewriting the packet
+    /// changed its size.
+    pub fn serialize(&self) -> (PcapPacketHeader, Vec<u8>) {
+        let data = self.payload();
+        let mut header = self.header.borrow().clone();
+        let delta = data.len() as i64 - header.caplen as i64;
+        header.caplen = data.len() as u32;
+        header.wirelen = (header.wirelen as i64 + delta).max(data.len() as i64) as u32;
+        (header, data)
+    }
+    /// Timestamp of the packet in nanoseconds. The sub-second part of the
+    /// timestamp is in nanoseconds if 'nanosec' is set, else microseconds.
+    pub fn get_timestamp_ns(&self, nanosec: bool) -> i64 {
+        let header = self.header.borrow();
+        let subsec = header.ts_usec as i64;
+        let subsec = if nanosec { subsec } else { subsec * 1000 };
+        header.ts_sec as i64 * 1_000_000_000 + subsec
+    }
+    /// Set if the sub-second part of the timestamp is in nanoseconds
+    /// rather than microseconds
+    pub fn set_nanosec(&self, nanosec: bool) {
+        self.nanosec.set(nanosec);
+    }
+    /// Timestamp of the packet in the precision of the stream it was read
+    /// from
+    pub fn timestamp(&self) -> Timestamp {
+        Timestamp::from_nanos(self.get_timestamp_ns(self.nanosec.get()))
+    }
+    pub fn get_ts_sec(&self) -> Rc<Object> {
+        Rc::new(Object::Integer(self.header.borrow().ts_sec as i64))
+    }
+    pub fn get_ts_usec(&self) -> Rc<Object> {
+        Rc::new(Object::Integer(self.header.borrow().ts_usec as i64))
+    }
+    pub fn get_caplen(&self) -> Rc<Object> {
+        Rc::new(Object::Integer(self.header.borrow().caplen as i64))
+    }
+    pub fn get_wirelen_raw(&self) -> u32 {
+        self.header.borrow().wirelen
+    }
+    pub fn get_wirelen(&self) -> Rc<Object> {
+        Rc::new(Object::Integer(self.header.borrow().wirelen as i64))
+    }
+    pub fn set_ts_sec(&self, obj: Rc<Object>) -> Result<(), String> {
+        match obj.as_ref() {
+            Object::Integer(n) => {
+                self.header.borrow_mut().ts_sec = *n as u32;
+            }
+            _ => {
+                return Err("Invalid value for packet property sec".to_string());
+            }
+        };
+        Ok(())
+    }
+    pub fn set_ts_usec(&self, obj: Rc<Object>) -> Result<(), String> {
+        match obj.as_ref() {
+            Object::Integer(n) => {
+                self.header.borrow_mut().ts_usec = *n as u32;
+            }
+            _ => {
+                return Err("Invalid value for packet property usec".to_string());
+            }
+        };
+        Ok(())
+    }
+    pub fn set_caplen(&self, obj: Rc<Object>) -> Result<(), String> {
+        match obj.as_ref() {
+            Object::Integer(n) => {
+                self.header.borrow_mut().caplen = *n as u32;
+            }
+            _ => {
+                return Err("Invalid value for packet property caplen".to_string());
+            }
+        };
+        Ok(())
+    }
+    pub fn set_wirelen(&self, obj: Rc<Object>) -> Result<(), String> {
+        match obj.as_ref() {
+            Object::Integer(n) => {
+                self.header.borrow_mut().wirelen = *n as u32;
+            }
+            _ => {
+                return Err("Invalid value for packet property wirelen".to_string());
+            }
+        };
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Pcap {
+    pub file: Rc<FileHandle>,
+    pub header: RefCell<PcapGlobalHeader>,
+    format: PcapFormat,
+    ts_format: PcapTsFormat,
+    interfaces: RefCell<Vec<PcapNgInterface>>, // pcapng interfaces in the section
+    capture: Option<Capture>,                  // live capture read instead of the file
+    snaplen_set: Cell<bool>,                   // snaplen overridden by the user
+    on_corrupt: Cell<CorruptPolicy>,
+    max_packet: Cell<u32>,                     // largest packet record read
+    repair: Cell<bool>,                        // resync on corrupt records
+    pending: RefCell<VecDeque<u8>>,            // bytes read ahead while resyncing
+    last_ts_sec: Cell<Option<u32>>,            // timestamp of the last good packet
+    records: Cell<usize>,                      // number of packet records read so far
+    corrupt: Cell<usize>,                      // number of corrupt packet records found so far
+    ts_correction: Cell<Option<TsCorrection>>, // applied to the timestamps read
+}
+
+impl fmt::Display for Pcap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.capture {
+            Some(capture) => write!(f, "{}", capture),
+            None => write!(f, "{}", self.file),
+        }
+    }
+}
+
+impl Pcap {
+    pub fn get_magic_number(&self) -> Rc<Object> {
+        Rc::new(Object::Integer(self.header.borrow().magic_number as i64))
+    }
+    pub fn get_magic_number_raw(&self) -> u32 {
+        self.header.borrow().magic_number
+    }
+    pub fn get_version_major(&self) -> Rc<Object> {
+        Rc::new(Object::Integer(self.header.borrow().version_major as i64))
+    }
+    pub fn get_version_minor(&self) -> Rc<Object> {
+        Rc::new(Object::Integer(self.header.borrow().version_minor as i64))
+    }
+    pub fn get_thiszone(&self) -> Rc<Object> {
+        Rc::new(Object::Integer(self.header.borrow().thiszone as i64))
+    }
+    pub fn get_sigfigs(&self) -> Rc<Object> {
+        Rc::new(Object::Integer(self.header.borrow().sigfigs as i64))
+    }
+    pub fn get_snaplen(&self) -> Rc<Object> {
+        Rc::new(Object::Integer(self.header.borrow().snaplen as i64))
+    }
+    pub fn get_linktype_raw(&self) -> u32 {
+        self.header.borrow().linktype
+    }
+    pub fn get_linktype(&self) -> Rc<Object> {
+        Rc::new(Object::Integer(self.header.borrow().linktype as i64))
+    }
+    pub fn set_magic_number(&self, obj: Rc<Object>) -> Result<(), String> {
+        match obj.as_ref() {
+            Object::Integer(n) => {
+                self.header.borrow_mut().magic_number = *n as u32;
+            }
+            _ => {
+                return Err("Invalid value for pcap property magic_number".to_string());
+            }
+        };
+        Ok(())
+    }
+    pub fn set_version_major(&self, obj: Rc<Object>) -> Result<(), String> {
+        match obj.as_ref() {
+            Object::Integer(n) => {
+                self.header.borrow_mut().version_major = *n as u16;
+            }
+            _ => {
+                return Err("Invalid value for pcap property version_major".to_string());
+            }
+        };
+        Ok(())
+    }
+    pub fn set_version_minor(&self, obj: Rc<Object>) -> Result<(), String> {
+        match obj.as_ref() {
+            Object::Integer(n) => {
+                self.header.borrow_mut().version_minor = *n as u16;
+            }
+            _ => {
+                return Err("Invalid value for pcap property version_minor".to_string());
+            }
+        };
+        Ok(())
+    }
+    pub fn set_thiszone(&self, obj: Rc<Object>) -> Result<(), String> {
+        match obj.as_ref() {
+            Object::Integer(n) => {
+                self.header.borrow_mut().thiszone = *n as i32;
+            }
+            _ => {
+                return Err("Invalid value for pcap property thiszone".to_string());
+            }
+        };
+        Ok(())
+    }
+    pub fn set_sigfigs(&self, obj: Rc<Object>) -> Result<(), String> {
+        match obj.as_ref() {
+            Object::Integer(n) => {
+                self.header.borrow_mut().sigfigs = *n as u32;
+            }
+            _ => {
+                return Err("Invalid value for pcap property sigfigs".to_string());
+            }
+        };
+        Ok(())
+    }
+    pub fn set_snaplen(&self, obj: Rc<Object>) -> Result<(), String> {
+        match obj.as_ref() {
+            Object::Integer(n) => {
+                self.header.borrow_mut().snaplen = *n as u32;
+            }
+            _ => {
+                return Err("Invalid value for pcap property snaplen".to_string());
+            }
+        };
+        Ok(())
+    }
+    pub fn set_linktype(&self, obj: Rc<Object>) -> Result<(), String> {
+        match obj.as_ref() {
+            Object::Integer(n) => {
+                self.header.borrow_mut().linktype = *n as u32;
+            }
+            _ => {
+                return Err("Invalid value for pcap property linktype".to_string());
+            }
+        };
+        Ok(())
+    }
+
+    fn with_header(
+        file: Rc<FileHandle>,
+        header: PcapGlobalHeader,
+        format: PcapFormat,
+        ts_format: PcapTsFormat,
+    ) -> Self {
+        Self {
+            file,
+            header: RefCell::new(header),
+            format,
+            ts_format,
+            interfaces: RefCell::new(Vec::new()),
+            capture: None,
+            snaplen_set: Cell::new(false),
+            on_corrupt: Cell::new(CorruptPolicy::default()),
+            max_packet: Cell::new(PCAP_MAX_SNAPLEN),
+            repair: Cell::new(false),
+            pending: RefCell::new(VecDeque::new()),
+            last_ts_sec: Cell::new(None),
+            records: Cell::new(0),
+            corrupt: Cell::new(0),
+            ts_correction: Cell::new(None),
+        }
+    }
+
+    /// Read the pcap global header from a pcap file. The pcapng format is
+    /// detected by the block type of the Section Header Block at the start.
+    pub fn from_file(file: Rc<FileHandle>) -> io::Result<Self> {
+        let mut pcap = Self::with_header(
+            file,
+            PcapGlobalHeader::default(),
+            PcapFormat::Pcap,
+            PcapTsFormat::MicroSeconds,
+        );
+        let mut global_header_data = [0u8; 24]; // Size of pcap global header
+        pcap.read_exact(&mut global_header_data[..4])?;
+        if read_u32(&global_header_data, false) == PCAPNG_SHB {
+            pcap.pending.borrow_mut().extend(&global_header_data[..4]);
+            pcap.read_pcapng_header()?;
+            return Ok(pcap);
+        }
+        pcap.read_exact(&mut global_header_data[4..])?;
+        let global_header = PcapGlobalHeader::from_bytes(&global_header_data)?;
+        if global_header.magic_number == PCAP_MAGIC_NS {
+            pcap.ts_format = PcapTsFormat::NanoSeconds;
+        }
+        pcap.header = RefCell::new(global_header);
+        Ok(pcap)
+    }
+
+    // Read the blocks at the start of a pcapng stream up to the first
+    // Interface Description Block. The global header is made up from the
+    // first interface so that the pcap properties are available as for a
+    // classic pcap stream. Timestamps are converted to nanoseconds.
+    fn read_pcapng_header(&mut self) -> io::Result<()> {
+        self.format = PcapFormat::PcapNg;
+        self.ts_format = PcapTsFormat::NanoSeconds;
+        *self.header.borrow_mut() = PcapGlobalHeader {
+            snaplen: 0,
+            ..PcapGlobalHeader::new(PCAP_MAGIC_NS)
+        };
+        while self.interfaces.borrow().is_empty() {
+            let block = self.next_pcapng_block()?;
+            if block.block_type == PCAPNG_EPB || block.block_type == PCAPNG_SPB {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "pcapng packet block found before interface description",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the packets from a live capture instead of a pcap file. The
+    /// timestamps are in nanoseconds and the link type is ethernet.
+    pub fn from_capture(capture: Capture, snaplen: u32) -> Self {
+        let mut header = PcapGlobalHeader::new(PCAP_MAGIC_NS);
+        header.snaplen = snaplen;
+        let mut pcap = Self::with_header(
+            Rc::new(FileHandle::Stdin),
+            header,
+            PcapFormat::Pcap,
+            PcapTsFormat::NanoSeconds,
+        );
+        pcap.capture = Some(capture);
+        pcap
+    }
+
+    /// Write global header to a newly created pcap file
+    pub fn new_with_magic(file: Rc<FileHandle>, magic: u32) -> io::Result<Self> {
+        Self::new_with_format(file, magic, PcapFormat::Pcap)
+    }
+
+    /// Write the headers of the given format to a newly created pcap file.
+    /// For pcapng, a section header and a single interface are written.
+    /// The timestamps are in nanoseconds if the magic number says so.
+    pub fn new_with_format(
+        file: Rc<FileHandle>,
+        magic: u32,
+        format: PcapFormat,
+    ) -> io::Result<Self> {
+        Self::new_with_header(file, PcapGlobalHeader::new(magic), format)
+    }
+
+    /// Write the headers of the given format and link type to a newly
+    /// created pcap file
+    pub fn new_with_linktype(
+        file: Rc<FileHandle>,
+        magic: u32,
+        format: PcapFormat,
+        linktype: u32,
+    ) -> io::Result<Self> {
+        let mut global_header = PcapGlobalHeader::new(magic);
+        global_header.linktype = linktype;
+        Self::new_with_header(file, global_header, format)
+    }
+
+    fn new_with_header(
+        file: Rc<FileHandle>,
+        global_header: PcapGlobalHeader,
+        format: PcapFormat,
+    ) -> io::Result<Self> {
+        let magic = global_header.magic_number;
+        let ts_format = if magic == PCAP_MAGIC_NS {
+            PcapTsFormat::NanoSeconds
+        } else {
+            PcapTsFormat::MicroSeconds
+        };
+        let bytes: Vec<u8> = match format {
+            PcapFormat::Pcap => (&global_header).into(),
+            PcapFormat::PcapNg => pcapng::section_header(
+                global_header.linktype,
+                global_header.snaplen,
+                ts_format == PcapTsFormat::NanoSeconds,
+            ),
+        };
+        match file.as_ref() {
+            FileHandle::Writer(writer) => {
+                borrow_open(writer)?.write_all(&bytes)?;
+            }
+            FileHandle::Stdout => {
+                io::stdout().write_all(&bytes)?;
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Invalid file handle",
+                ))
+            }
+        }
+        Ok(Self::with_header(file, global_header, format, ts_format))
+    }
+
+    pub fn new(file: Rc<FileHandle>) -> io::Result<Self> {
+        Self::new_with_magic(file, PCAP_MAGIC_US)
+    }
+
+    /// Set the action to take on finding a corrupt packet record
+    pub fn set_on_corrupt(&self, policy: CorruptPolicy) {
+        self.on_corrupt.set(policy);
+    }
+
+    /// Override the snaplen used to validate the packet records
+    pub fn set_snaplen_raw(&self, snaplen: u32) {
+        self.header.borrow_mut().snaplen = snaplen;
+        self.snaplen_set.set(true);
+    }
+
+    /// Set the size of the largest packet record read. Larger records are
+    /// handled by the corrupt record policy without reading them in full.
+    pub fn set_max_packet_size(&self, size: u32) {
+        self.max_packet.set(size);
+    }
+
+    /// Resync on plausible packet record headers after a corrupt record
+    pub fn set_repair(&self, repair: bool) {
+        self.repair.set(repair);
+    }
+
+    pub fn is_nanosec(&self) -> bool {
+        self.ts_format == PcapTsFormat::NanoSeconds
+    }
+    pub fn format(&self) -> PcapFormat {
+        self.format
+    }
+    /// Number of corrupt packet records found so far
+    pub fn get_corrupt_count(&self) -> usize {
+        self.corrupt.get()
+    }
+
+    // Read from the bytes read ahead while resyncing before reading the file
+    fn read_exact(&self, buf: &mut [u8]) -> io::Result<()> {
+        let mut pending = self.pending.borrow_mut();
+        let n = pending.len().min(buf.len());
+        for (dst, src) in buf.iter_mut().zip(pending.drain(..n)) {
+            *dst = src;
+        }
+        let buf = &mut buf[n..];
+        match self.file.as_ref() {
+            FileHandle::Reader(reader) => borrow_open(reader)?.read_exact(buf),
+            FileHandle::Stdin => io::stdin().read_exact(buf),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid file handle",
+            )),
+        }
+    }
+
+    // Read and drop the given number of bytes without holding them all
+    fn discard(&self, mut len: usize) -> io::Result<()> {
+        let mut chunk = vec![0u8; len.min(PCAP_DISCARD_CHUNK)];
+        while len > 0 {
+            let n = len.min(chunk.len());
+            self.read_exact(&mut chunk[..n])?;
+            len -= n;
+        }
+        Ok(())
+    }
+
+    // Read bytes to the window until it has 'len' bytes.
+    // Returns false if the end of the stream is reached.
+    fn fill_window(&self, window: &mut VecDeque<u8>, len: usize) -> io::Result<bool> {
+        let mut byte = [0u8; 1];
+        while window.len() < len {
+            match self.read_exact(&mut byte) {
+                Ok(()) => window.push_back(byte[0]),
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+
+    // Check the lengths in a packet record header for consistency
+    fn check_record(&self, header: &PcapPacketHeader) -> Option<String> {
+        let snaplen = self.header.borrow().snaplen;
+        if header.caplen > snaplen {
+            Some(format!(
+                "Invalid caplen value {} exceeds snaplen {}",
+                header.caplen, snaplen
+            ))
+        } else if header.caplen > header.wirelen {
+            Some(format!(
+                "Invalid caplen value {} exceeds wirelen {}",
+                header.caplen, header.wirelen
+            ))
+        } else {
+            None
+        }
+    }
+
+    // Check if a packet record header found while resyncing looks genuine.
+    // The lengths should be consistent, the sub-second part of the timestamp
+    // should be within range and the timestamp should not go back in time or
+    // jump too far ahead from the previous timestamp.
+    fn is_plausible(&self, header: &PcapPacketHeader, prev_ts_sec: Option<u32>) -> bool {
+        let max_subsec = if self.ts_format == PcapTsFormat::MicroSeconds {
+            1_000_000
+        } else {
+            1_000_000_000
+        };
+        if header.caplen == 0
+            || header.wirelen > PCAP_MAX_SNAPLEN
+            || header.ts_usec >= max_subsec
+            || self.check_record(header).is_some()
+        {
+            return false;
+        }
+        match prev_ts_sec {
+            Some(prev) => header.ts_sec >= prev && header.ts_sec - prev <= PCAP_RESYNC_MAX_TS_GAP,
+            None => true,
+        }
+    }
+
+    // Scan forward one byte at a time from the start of a corrupt record
+    // for a plausible packet record header. A candidate header is accepted
+    // only if the header of the record that follows is plausible as well,
+    // or if the record ends exactly at the end of the stream. The bytes read
+    // ahead are kept in 'pending' to be read again as the next record.
+    fn resync(&self, mut window: VecDeque<u8>) -> io::Result<usize> {
+        let big_endian = self.header.borrow().big_endian;
+        let mut skipped = 0;
+        loop {
+            window.pop_front();
+            skipped += 1;
+            if !self.fill_window(&mut window, 16)? {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "No packet record found while resyncing",
+                ));
+            }
+            let header = PcapPacketHeader::from_bytes(&window.make_contiguous()[..16], big_endian)?;
+            if !self.is_plausible(&header, self.last_ts_sec.get()) {
+                continue;
+            }
+            let next = 16 + header.caplen as usize;
+            if self.fill_window(&mut window, next + 16)? {
+                let next_header = PcapPacketHeader::from_bytes(
+                    &window.make_contiguous()[next..next + 16],
+                    big_endian,
+                )?;
+                if !self.is_plausible(&next_header, Some(header.ts_sec)) {
+                    continue;
+                }
+            } else if window.len() != next {
+                continue;
+            }
+            *self.pending.borrow_mut() = window;
+            return Ok(skipped);
+        }
+    }
+
+    /// Correct the timestamps of the packets read from now on
+    pub fn set_ts_correction(&self, correction: TsCorrection) {
+        self.ts_correction.set(Some(correction));
+    }
+
+    pub fn ts_correction(&self) -> Option<TsCorrection> {
+        self.ts_correction.get()
+    }
+
+    /// Packets dropped by the kernel and by the interface if the packets
+    /// are read from a live capture
+    pub fn capture_stats(&self) -> Option<CaptureStats> {
+        self.capture.as_ref().and_then(|c| c.stats().ok())
+    }
+
+    /// Read next packet from a pcap file
+    pub fn next_packet(&self) -> io::Result<Rc<PcapPacket>> {
+        if let Some(capture) = &self.capture {
+            let (header, data) = capture.next_packet()?;
+            self.records.set(self.records.get() + 1);
+            return Ok(self.make_packet(header, data));
+        }
+        match self.format {
+            PcapFormat::Pcap => self.next_pcap_packet(),
+            PcapFormat::PcapNg => self.next_pcapng_packet(),
+        }
+    }
+
+    fn next_pcap_packet(&self) -> io::Result<Rc<PcapPacket>> {
+        loop {
+            let mut packet_header_data = [0u8; 16]; // Size of pcap packet header
+            self.read_exact(&mut packet_header_data)?;
+            let big_endian = self.header.borrow().big_endian;
+            let mut packet_header = PcapPacketHeader::from_bytes(&packet_header_data, big_endian)?;
+            self.records.set(self.records.get() + 1);
+
+            // A record this large is either corrupt or cannot be read safely
+            if packet_header.caplen > self.max_packet.get() {
+                if self.repair.get() {
+                    self.corrupt.set(self.corrupt.get() + 1);
+                    let msg = format!(
+                        "Invalid caplen value {} in packet record {}",
+                        packet_header.caplen,
+                        self.records.get()
+                    );
+                    self.repair_record(&msg, packet_header_data.to_vec())?;
+                    continue;
+                }
+                let caplen = packet_header.caplen as usize;
+                let keep = match self.handle_oversized(&packet_header)? {
+                    Some(keep) => keep,
+                    None => {
+                        self.read_corrupt(|| self.discard(caplen))?;
+                        continue;
+                    }
+                };
+                let mut packet_data = vec![0u8; keep];
+                self.read_corrupt(|| self.read_exact(&mut packet_data))?;
+                self.read_corrupt(|| self.discard(caplen - keep))?;
+                packet_header.caplen = keep as u32;
+                return Ok(self.make_packet(packet_header, packet_data));
+            }
+
+            // Check the lengths before reading the payload so that a bad
+            // caplen does not consume the records that follow it
+            if let Some(msg) = self.check_record(&packet_header) {
+                self.corrupt.set(self.corrupt.get() + 1);
+                if self.on_corrupt.get() == CorruptPolicy::Error && self.repair.get() {
+                    self.repair_record(&msg, packet_header_data.to_vec())?;
+                    continue;
+                }
+                let caplen = packet_header.caplen as usize;
+                if !self.handle_corrupt(msg, &mut packet_header)? {
+                    self.read_corrupt(|| self.discard(caplen))?;
+                    continue;
+                }
+                let keep = packet_header.caplen as usize;
+                let mut packet_data = vec![0u8; keep];
+                self.read_corrupt(|| self.read_exact(&mut packet_data))?;
+                self.read_corrupt(|| self.discard(caplen - keep))?;
+                return Ok(self.make_packet(packet_header, packet_data));
+            }
+
+            // Read the payload data based on the caplen from the packet header
+            let mut packet_data = vec![0u8; packet_header.caplen as usize];
+            self.read_exact(&mut packet_data)?;
+            return Ok(self.make_packet(packet_header, packet_data));
+        }
+    }
+
+    // Read the data of a corrupt record. The stream ending within such a
+    // record is an error rather than the end of the packets, since the
+    // length that was read past the end is likely the damaged part.
+    fn read_corrupt<F>(&self, read: F) -> io::Result<()>
+    where
+        F: FnOnce() -> io::Result<()>,
+    {
+        read().map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Packet record {} is truncated at the end of the stream",
+                    self.records.get()
+                ),
+            ),
+            _ => e,
+        })
+    }
+
+    // Read the next block from a pcapng stream. The interfaces are reset
+    // at the start of a new section and updated on finding an interface.
+    fn next_pcapng_block(&self) -> io::Result<PcapNgBlock> {
+        let mut block_type = [0u8; 4];
+        self.read_exact(&mut block_type)?;
+        let mut big_endian = self.header.borrow().big_endian;
+        let block_type = read_u32(&block_type, big_endian);
+        let block = pcapng::read_block(block_type, &mut big_endian, |buf| self.read_exact(buf))?;
+        self.header.borrow_mut().big_endian = big_endian;
+        match block_type {
+            PCAPNG_SHB => self.interfaces.borrow_mut().clear(),
+            PCAPNG_IDB => {
+                let interface = pcapng::parse_interface(&block.body, big_endian)?;
+                self.add_interface(interface);
+            }
+            _ => {}
+        }
+        Ok(block)
+    }
+
+    // The link type is taken from the first interface. Packets are checked
+    // against the largest snaplen of the interfaces unless overridden.
+    fn add_interface(&self, interface: PcapNgInterface) {
+        let mut header = self.header.borrow_mut();
+        if self.interfaces.borrow().is_empty() {
+            header.linktype = interface.linktype as u32;
+        }
+        if !self.snaplen_set.get() {
+            let snaplen = match interface.snaplen {
+                0 => PCAP_MAX_SNAPLEN,
+                n => n,
+            };
+            header.snaplen = header.snaplen.max(snaplen);
+        }
+        self.interfaces.borrow_mut().push(interface);
+    }
+
+    // Read the next packet from a pcapng stream skipping the blocks that
+    // do not contain packets. Resyncing is not supported for pcapng.
+    fn next_pcapng_packet(&self) -> io::Result<Rc<PcapPacket>> {
+        loop {
+            let block = self.next_pcapng_block()?;
+            let big_endian = self.header.borrow().big_endian;
+            let interfaces = self.interfaces.borrow();
+            let comment = match block.block_type {
+                PCAPNG_EPB => pcapng::packet_comment(&block.body, big_endian),
+                _ => None,
+            };
+            let (mut packet_header, mut packet_data) = match block.block_type {
+                PCAPNG_EPB => pcapng::parse_enhanced_packet(&block.body, big_endian, &interfaces)?,
+                PCAPNG_SPB => pcapng::parse_simple_packet(&block.body, big_endian, &interfaces)?,
+                _ => continue,
+            };
+            drop(interfaces);
+            self.records.set(self.records.get() + 1);
+            if packet_header.caplen > self.max_packet.get() {
+                match self.handle_oversized(&packet_header)? {
+                    Some(keep) => {
+                        packet_data.truncate(keep);
+                        packet_header.caplen = keep as u32;
+                    }
+                    None => continue,
+                }
+            } else if let Some(msg) = self.check_record(&packet_header) {
+                self.corrupt.set(self.corrupt.get() + 1);
+                if !self.handle_corrupt(msg, &mut packet_header)? {
+                    continue;
+                }
+                packet_data.truncate(packet_header.caplen as usize);
+            }
+            let packet = self.make_packet(packet_header, packet_data);
+            packet.set_comment(comment);
+            return Ok(packet);
+        }
+    }
+
+    // Apply the corrupt record policy to a packet record header with
+    // inconsistent lengths. The caplen is clamped in place if the policy
+    // says so. Returns false if the record is to be skipped.
+    fn handle_corrupt(
+        &self,
+        msg: String,
+        packet_header: &mut PcapPacketHeader,
+    ) -> io::Result<bool> {
+        match self.on_corrupt.get() {
+            CorruptPolicy::Error => Err(io::Error::new(io::ErrorKind::InvalidData, msg)),
+            CorruptPolicy::Warn => {
+                eprintln!("warning: packet record {}: {}", self.records.get(), msg);
+                Ok(true)
+            }
+            CorruptPolicy::Clamp => {
+                let snaplen = self.header.borrow().snaplen;
+                let caplen = packet_header.caplen.min(snaplen);
+                packet_header.caplen = caplen;
+                packet_header.wirelen = packet_header.wirelen.max(caplen);
+                Ok(true)
+            }
+            CorruptPolicy::Skip => Ok(false),
+        }
+    }
+
+    // Apply the corrupt record policy to a packet record larger than the
+    // maximum packet size. Such a record cannot be used as is, so it is
+    // skipped with a warning or truncated to the maximum packet size.
+    // Returns the number of bytes of the packet data to keep, or None if
+    // the record is to be skipped.
+    fn handle_oversized(&self, packet_header: &PcapPacketHeader) -> io::Result<Option<usize>> {
+        self.corrupt.set(self.corrupt.get() + 1);
+        let max_packet = self.max_packet.get();
+        let msg = format!(
+            "caplen {} exceeds the maximum packet size {}",
+            packet_header.caplen, max_packet
+        );
+        match self.on_corrupt.get() {
+            CorruptPolicy::Error => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Packet record {}: {}", self.records.get(), msg),
+            )),
+            CorruptPolicy::Warn => {
+                eprintln!(
+                    "warning: packet record {}: {}; skipped",
+                    self.records.get(),
+                    msg
+                );
+                Ok(None)
+            }
+            CorruptPolicy::Clamp => Ok(Some(max_packet as usize)),
+            CorruptPolicy::Skip => Ok(None),
+        }
+    }
+
+    fn make_packet(
+        &self,
+        mut packet_header: PcapPacketHeader,
+        packet_data: Vec<u8>,
+    ) -> Rc<PcapPacket> {
+        self.last_ts_sec.set(Some(packet_header.ts_sec));
+        if let Some(mut correction) = self.ts_correction.get() {
+            correction.apply(&mut packet_header, self.is_nanosec());
+            self.ts_correction.set(Some(correction));
+        }
+        // Do not parse the inner packet yet. Parse it only when referred to.
+        let packet = PcapPacket::new(packet_header, packet_data);
+        packet.set_nanosec(self.is_nanosec());
+        Rc::new(packet)
+    }
+
+    // Resync after a corrupt record given the bytes read from its start
+    fn repair_record(&self, msg: &str, record: Vec<u8>) -> io::Result<()> {
+        let skipped = self.resync(VecDeque::from(record))?;
+        eprintln!(
+            "warning: packet record {}: {}; skipped {} bytes to resync",
+            self.records.get(),
+            msg,
+            skipped
+        );
+        Ok(())
+    }
+
+    /// Function to write a packet to a pcap file
+    pub fn write_all(&self, pkt: Rc<PcapPacket>) -> io::Result<usize> {
+        let bytes: Vec<u8> = match self.format {
+            PcapFormat::Pcap => pkt.as_ref().into(),
+            PcapFormat::PcapNg => {
+                let (header, data) = pkt.serialize();
+                let comment = pkt.comment();
+                pcapng::enhanced_packet(&header, &data, self.is_nanosec(), comment.as_deref())
+            }
+        };
+
+        match self.file.as_ref() {
+            FileHandle::Writer(writer) => borrow_open(writer)?.write_all(&bytes),
+            FileHandle::Stdout => io::stdout().write_all(&bytes),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid file handle",
+            )),
+        }?;
+        Ok(bytes.len())
+    }
+
+    /// Function to flush the packets written to a pcap file
+    pub fn flush(&self) -> io::Result<()> {
+        match self.file.as_ref() {
+            FileHandle::Writer(writer) => borrow_open(writer)?.flush(),
+            FileHandle::Stdout => io::stdout().flush(),
+            _ => Ok(()),
+        }
+    }
+}
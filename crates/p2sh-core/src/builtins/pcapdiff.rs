@@ -0,0 +1,239 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::rc::Rc;
+
+use super::pcap::{Pcap, PcapPacket};
+use crate::object::file::FileHandle;
+
+/// Options to compare two pcap streams
+#[derive(Debug, Clone)]
+pub struct DiffOptions {
+    /// Compare only the packet contents
+    pub ignore_time: bool,
+    /// Maximum difference in nanoseconds for timestamps to be equal
+    pub tolerance_ns: u64,
+    /// Number of packets to look ahead for a match after a mismatch
+    pub window: usize,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            ignore_time: false,
+            tolerance_ns: 0,
+            window: 1000,
+        }
+    }
+}
+
+/// A packet summarized by its timestamp, length and a digest of the bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketSummary {
+    pub ts_ns: i64,
+    pub caplen: usize,
+    pub digest: u64,
+}
+
+impl fmt::Display for PacketSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ts: {}.{:09}, len: {}",
+            self.ts_ns.div_euclid(1_000_000_000),
+            self.ts_ns.rem_euclid(1_000_000_000),
+            self.caplen
+        )
+    }
+}
+
+/// A packet that is only present in one of the streams. The index is the
+/// 1-based position of the packet in its stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffEntry {
+    Deleted(usize, PacketSummary),
+    Inserted(usize, PacketSummary),
+}
+
+impl fmt::Display for DiffEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiffEntry::Deleted(idx, pkt) => write!(f, "- {:<8} {}", idx, pkt),
+            DiffEntry::Inserted(idx, pkt) => write!(f, "+ {:<8} {}", idx, pkt),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DiffReport {
+    pub packets: (usize, usize),
+    pub equal: usize,
+    pub entries: Vec<DiffEntry>,
+}
+
+impl DiffReport {
+    pub fn is_equal(&self) -> bool {
+        self.entries.is_empty()
+    }
+    fn deleted(&mut self, packets: &[PacketSummary], start: usize, count: usize) {
+        let deleted = packets[start..start + count].iter().enumerate();
+        self.entries
+            .extend(deleted.map(|(k, p)| DiffEntry::Deleted(start + k + 1, *p)));
+    }
+    fn inserted(&mut self, packets: &[PacketSummary], start: usize, count: usize) {
+        let inserted = packets[start..start + count].iter().enumerate();
+        self.entries
+            .extend(inserted.map(|(k, p)| DiffEntry::Inserted(start + k + 1, *p)));
+    }
+}
+
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        let deleted = self
+            .entries
+            .iter()
+            .filter(|e| matches!(e, DiffEntry::Deleted(..)))
+            .count();
+        writeln!(
+            f,
+            "packets: {}/{}, equal: {}, deleted: {}, inserted: {}",
+            self.packets.0,
+            self.packets.1,
+            self.equal,
+            deleted,
+            self.entries.len() - deleted
+        )
+    }
+}
+
+impl PacketSummary {
+    pub fn new(pkt: &PcapPacket, nanosec: bool) -> Self {
+        let payload = pkt.payload();
+        let mut hasher = DefaultHasher::new();
+        payload.hash(&mut hasher);
+        Self {
+            ts_ns: pkt.get_timestamp_ns(nanosec),
+            caplen: payload.len(),
+            digest: hasher.finish(),
+        }
+    }
+}
+
+/// Packets of a capture matched by the filters of only one of two
+/// scripts. The ones matched by the first script only are listed as
+/// deleted and the ones matched by the second script only as inserted.
+#[derive(Debug, Default)]
+pub struct MatchReport {
+    pub packets: usize,
+    pub both: usize,
+    pub entries: Vec<DiffEntry>,
+}
+
+impl MatchReport {
+    /// Count the next packet given whether each script matched it
+    pub fn add(&mut self, pkt: PacketSummary, first: bool, second: bool) {
+        self.packets += 1;
+        match (first, second) {
+            (true, true) => self.both += 1,
+            (true, false) => self.entries.push(DiffEntry::Deleted(self.packets, pkt)),
+            (false, true) => self.entries.push(DiffEntry::Inserted(self.packets, pkt)),
+            (false, false) => {}
+        }
+    }
+
+    pub fn is_equal(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl fmt::Display for MatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        let first = self
+            .entries
+            .iter()
+            .filter(|e| matches!(e, DiffEntry::Deleted(..)))
+            .count();
+        writeln!(
+            f,
+            "packets: {}, matched by both: {}, first only: {}, second only: {}",
+            self.packets,
+            self.both,
+            first,
+            self.entries.len() - first
+        )
+    }
+}
+
+/// Read all the packets from a pcap stream as summaries
+pub fn summarize(pcap: &Pcap) -> io::Result<Vec<PacketSummary>> {
+    let mut packets = Vec::new();
+    loop {
+        match pcap.next_packet() {
+            Ok(pkt) => packets.push(PacketSummary::new(&pkt, pcap.is_nanosec())),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(packets),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Read all the packets from a pcap file as summaries
+pub fn summarize_file(path: &str) -> io::Result<Vec<PacketSummary>> {
+    let file = fs::File::open(path)?;
+    let handle = FileHandle::new_reader(io::BufReader::new(file));
+    let pcap = Pcap::from_file(Rc::new(handle))?;
+    summarize(&pcap)
+}
+
+impl DiffOptions {
+    fn matches(&self, a: &PacketSummary, b: &PacketSummary) -> bool {
+        a.caplen == b.caplen
+            && a.digest == b.digest
+            && (self.ignore_time || a.ts_ns.abs_diff(b.ts_ns) <= self.tolerance_ns)
+    }
+
+    /// Compare two sequences of packets. On a mismatch, look ahead up to
+    /// 'window' packets in either sequence for the nearest match to tell
+    /// the packets deleted from the first sequence from the ones inserted
+    /// into the second. If there is no match within the window, the packet
+    /// is considered to be replaced.
+    pub fn diff(&self, a: &[PacketSummary], b: &[PacketSummary]) -> DiffReport {
+        let mut report = DiffReport {
+            packets: (a.len(), b.len()),
+            ..Default::default()
+        };
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() && j < b.len() {
+            if self.matches(&a[i], &b[j]) {
+                report.equal += 1;
+                i += 1;
+                j += 1;
+                continue;
+            }
+            let ahead = |k: usize| {
+                if i + k < a.len() && self.matches(&a[i + k], &b[j]) {
+                    Some((k, 0))
+                } else if j + k < b.len() && self.matches(&a[i], &b[j + k]) {
+                    Some((0, k))
+                } else {
+                    None
+                }
+            };
+            let (deleted, inserted) = (1..=self.window).find_map(ahead).unwrap_or((1, 1));
+            report.deleted(a, i, deleted);
+            report.inserted(b, j, inserted);
+            i += deleted;
+            j += inserted;
+        }
+        report.deleted(a, i, a.len() - i);
+        report.inserted(b, j, b.len() - j);
+        report
+    }
+}
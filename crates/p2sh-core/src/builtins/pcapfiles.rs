@@ -0,0 +1,106 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::code::prop::glob_match;
+
+/// Order in which the files in a directory or matching a pattern are read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum ReadOrder {
+    /// By file name, with the numbers in the names compared by value
+    #[default]
+    Name,
+    /// By the time of the last modification, the oldest first
+    Mtime,
+}
+
+/// Expand a path given to read pcap files from. A directory stands for
+/// the files in it, and a path whose file name has a '*', which matches
+/// any sequence of characters, for the files in its directory that match
+/// it, e.g. 'captures/*.pcap'. Hidden files are left out. Any other path
+/// is returned as is.
+pub fn expand_path(path: &str, order: ReadOrder) -> io::Result<Vec<PathBuf>> {
+    let path_buf = PathBuf::from(path);
+    // The files of a pattern without a directory are in the current one
+    let (dir, pattern) = match path_buf.file_name().and_then(|name| name.to_str()) {
+        Some(name) if name.contains('*') => match path_buf.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => (dir.to_path_buf(), name),
+            _ => (PathBuf::new(), name),
+        },
+        _ if path_buf.is_dir() => (path_buf.clone(), "*"),
+        _ => return Ok(vec![path_buf]),
+    };
+    let read_dir = if dir.as_os_str().is_empty() {
+        fs::read_dir(".")?
+    } else {
+        fs::read_dir(&dir)?
+    };
+    let mut files = Vec::new();
+    for entry in read_dir {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        if name.starts_with('.') || !glob_match(pattern, name) || !entry.file_type()?.is_file() {
+            continue;
+        }
+        let mtime = match order {
+            ReadOrder::Name => SystemTime::UNIX_EPOCH,
+            ReadOrder::Mtime => entry.metadata()?.modified()?,
+        };
+        files.push((mtime, name.to_string()));
+    }
+    if files.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No files match '{}'", path),
+        ));
+    }
+    files.sort_by(|(t1, n1), (t2, n2)| t1.cmp(t2).then_with(|| natural_cmp(n1, n2)));
+    Ok(files.into_iter().map(|(_, name)| dir.join(name)).collect())
+}
+
+// Compare file names with the runs of digits in them compared by their
+// value, so that 'x2.pcap' comes before 'x10.pcap'. Names that are equal
+// by value, such as 'x02' and 'x2', are ordered as strings.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut x, mut y) = (a, b);
+    while let (Some(c), Some(d)) = (x.chars().next(), y.chars().next()) {
+        let ord = if c.is_ascii_digit() && d.is_ascii_digit() {
+            let (n, rest_x) = split_digits(x);
+            let (m, rest_y) = split_digits(y);
+            x = rest_x;
+            y = rest_y;
+            // Without leading zeros, the longer number is the larger one
+            let (n, m) = (n.trim_start_matches('0'), m.trim_start_matches('0'));
+            n.len().cmp(&m.len()).then_with(|| n.cmp(m))
+        } else {
+            x = &x[c.len_utf8()..];
+            y = &y[d.len_utf8()..];
+            c.cmp(&d)
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    x.len().cmp(&y.len()).then_with(|| a.cmp(b))
+}
+
+// Split a string into the run of digits it starts with and the rest
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// Expand each of the paths given to read pcap files from in turn
+pub fn expand_paths(paths: &[String], order: ReadOrder) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        files.extend(expand_path(path, order)?);
+    }
+    Ok(files)
+}
@@ -0,0 +1,534 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::Write;
+use std::io;
+use std::io::Write as _;
+use std::rc::Rc;
+
+use crate::object::float_to_string;
+use crate::object::Object;
+
+enum NumberFormat {
+    Boolean,
+    None,
+    Octal,
+    Hex,
+    HexaDecimal,
+    Exponent,
+}
+
+enum SpecJustify {
+    Default,
+    Left,
+    Right,
+}
+
+thread_local! {
+    // Output of the builtins that print to stdout held back rather than
+    // written, such as while replaying a REPL transcript
+    static CAPTURED: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Hold back the output of the builtins that print to stdout, such as
+/// 'print', until it is taken using 'take_captured', or write it to
+/// stdout again if 'capture' is false
+pub fn capture_stdout(capture: bool) {
+    CAPTURED.with(|c| *c.borrow_mut() = capture.then(String::new));
+}
+
+/// Take the output held back so far, which is empty unless captured
+pub fn take_captured() -> String {
+    CAPTURED.with(|c| {
+        c.borrow_mut()
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    })
+}
+
+/// Write the output of a builtin to stdout unless it is being captured
+pub fn write_stdout(s: &str) -> io::Result<()> {
+    CAPTURED.with(|c| match c.borrow_mut().as_mut() {
+        Some(captured) => {
+            captured.push_str(s);
+            Ok(())
+        }
+        None => io::stdout().lock().write_all(s.as_bytes()),
+    })
+}
+
+pub struct Collector(pub Vec<String>);
+
+impl fmt::Write for Collector {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.push(s.to_string());
+        Ok(())
+    }
+}
+
+fn format_obj(
+    collector: &mut Collector,
+    padding: &str,
+    justify: SpecJustify,
+    width_str: &str,
+    precision_str: &str,
+    num_fmt: NumberFormat,
+    obj: &Object,
+) -> Result<(), String> {
+    // Parse width
+    let width: usize = if width_str.is_empty() {
+        0
+    } else {
+        width_str
+            .parse()
+            .map_err(|_: std::num::ParseIntError| "Failed to parse width".to_string())?
+    };
+    // Parse precision
+    let precision: Option<usize> = if precision_str.is_empty() {
+        None
+    } else {
+        Some(
+            precision_str
+                .parse()
+                .map_err(|_: std::num::ParseIntError| "Failed to parse precision".to_string())?,
+        )
+    };
+
+    // Format based on NumberFormat
+    let formatted = match num_fmt {
+        NumberFormat::Boolean => {
+            if let Object::Integer(num) = obj {
+                format!("{:b}", *num as usize)
+            } else {
+                Err(String::from("Can't format non-number as binary"))?
+            }
+        }
+        NumberFormat::Octal => {
+            if let Object::Integer(num) = obj {
+                format!("{:o}", *num as usize)
+            } else {
+                Err(String::from("Can't format non-number as octal"))?
+            }
+        }
+        NumberFormat::Hex => {
+            if let Object::Integer(num) = obj {
+                format!("{:x}", *num as usize)
+            } else {
+                Err(String::from("Can't format non-number as hex"))?
+            }
+        }
+        NumberFormat::HexaDecimal => {
+            if let Object::Integer(num) = obj {
+                format!("{:X}", *num as usize)
+            } else {
+                Err(String::from("Can't format non-number as hex"))?
+            }
+        }
+        NumberFormat::Exponent => {
+            let num = match obj {
+                Object::Float(num) => *num,
+                Object::Integer(num) => *num as f64,
+                _ => Err(String::from(
+                    "Can't format non-number in scientific notation",
+                ))?,
+            };
+            match precision {
+                Some(p) => format!("{:.*e}", p, num),
+                None => format!("{:e}", num),
+            }
+        }
+        NumberFormat::None => {
+            match (obj, precision) {
+                (Object::Str(t), _) => {
+                    // Do not use display trait to print the string as
+                    // it wraps the string in quotes
+                    t.to_string()
+                }
+                (Object::Float(num), Some(p)) => format!("{:.*}", p, num),
+                (Object::Float(num), None) => float_to_string(*num),
+                (o, _) => {
+                    format!("{}", o)
+                }
+            }
+        }
+    };
+    // Use a default padding of spaces
+    let padding = if padding.is_empty() { " " } else { padding };
+    let width_pad = width.saturating_sub(formatted.len());
+    let padded: String = padding.repeat(width_pad);
+
+    // Use default justification as right for number and left for everything else
+    let justify = match justify {
+        SpecJustify::Default => {
+            if let Object::Integer(_) = obj {
+                SpecJustify::Right
+            } else {
+                SpecJustify::Left
+            }
+        }
+        _ => justify,
+    };
+
+    // Handle justification and padding
+    let justify = match justify {
+        SpecJustify::Default => {
+            if let Object::Integer(_) = obj {
+                SpecJustify::Right
+            } else {
+                SpecJustify::Left
+            }
+        }
+        _ => justify,
+    };
+
+    // Handle justification and padding
+    let formatted_output = match justify {
+        SpecJustify::Left => format!("{}{}", formatted, padded),
+        SpecJustify::Right => format!("{}{}", padded, formatted),
+        _ => formatted,
+    };
+
+    write!(collector, "{}", formatted_output).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// A printf style conversion specification such as %5d, %08x or %-20s
+struct PrintfSpec {
+    left: bool,
+    zero: bool,
+    plus: bool,
+    width: usize,
+    precision: Option<usize>,
+    conv: char,
+}
+
+// Parse the specification following a '%'. Returns the specification and
+// the number of characters it spans, or None if the characters do not form
+// a valid specification.
+fn parse_printf_spec(parts: &[char]) -> Option<(PrintfSpec, usize)> {
+    let mut spec = PrintfSpec {
+        left: false,
+        zero: false,
+        plus: false,
+        width: 0,
+        precision: None,
+        conv: '\0',
+    };
+    let mut idx = 0;
+    while idx < parts.len() {
+        match parts[idx] {
+            '-' => spec.left = true,
+            '0' => spec.zero = true,
+            '+' => spec.plus = true,
+            _ => break,
+        }
+        idx += 1;
+    }
+    while idx < parts.len() && parts[idx].is_ascii_digit() {
+        spec.width = spec
+            .width
+            .checked_mul(10)?
+            .checked_add(parts[idx].to_digit(10)? as usize)?;
+        idx += 1;
+    }
+    if idx < parts.len() && parts[idx] == '.' {
+        idx += 1;
+        let mut precision: usize = 0;
+        while idx < parts.len() && parts[idx].is_ascii_digit() {
+            precision = precision
+                .checked_mul(10)?
+                .checked_add(parts[idx].to_digit(10)? as usize)?;
+            idx += 1;
+        }
+        spec.precision = Some(precision);
+    }
+    match parts.get(idx) {
+        Some(c @ ('d' | 'i' | 'f' | 'e' | 's' | 'c' | 'x' | 'X' | 'o' | 'b')) => {
+            spec.conv = *c;
+            Some((spec, idx + 1))
+        }
+        _ => None,
+    }
+}
+
+fn format_printf(collector: &mut Collector, spec: &PrintfSpec, obj: &Object) -> Result<(), String> {
+    let numeric = !matches!(spec.conv, 's' | 'c');
+    let mut formatted = match spec.conv {
+        'd' | 'i' => match obj {
+            Object::Integer(num) => num.to_string(),
+            Object::Byte(b) => b.to_string(),
+            _ => Err(String::from("Can't format non-integer as decimal"))?,
+        },
+        'f' => {
+            let num = match obj {
+                Object::Float(num) => *num,
+                Object::Integer(num) => *num as f64,
+                _ => Err(String::from("Can't format non-number as float"))?,
+            };
+            format!("{:.*}", spec.precision.unwrap_or(6), num)
+        }
+        'e' => {
+            let num = match obj {
+                Object::Float(num) => *num,
+                Object::Integer(num) => *num as f64,
+                _ => Err(String::from(
+                    "Can't format non-number in scientific notation",
+                ))?,
+            };
+            match spec.precision {
+                Some(p) => format!("{:.*e}", p, num),
+                None => format!("{:e}", num),
+            }
+        }
+        'x' | 'X' | 'o' | 'b' => {
+            let base = match spec.conv {
+                'x' | 'X' => "hex",
+                'o' => "octal",
+                _ => "binary",
+            };
+            let num = match obj {
+                Object::Integer(num) => *num as usize,
+                Object::Byte(b) => *b as usize,
+                _ => Err(format!("Can't format non-number as {}", base))?,
+            };
+            match spec.conv {
+                'x' => format!("{:x}", num),
+                'X' => format!("{:X}", num),
+                'o' => format!("{:o}", num),
+                _ => format!("{:b}", num),
+            }
+        }
+        'c' => match obj {
+            Object::Char(c) => c.to_string(),
+            Object::Integer(num) => u32::try_from(*num)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| format!("Can't format {} as a character", num))?
+                .to_string(),
+            _ => Err(String::from("Can't format non-char as a character"))?,
+        },
+        _ => {
+            let s = match obj {
+                Object::Str(t) => t.to_string(),
+                Object::Float(num) => float_to_string(*num),
+                o => o.to_string(),
+            };
+            // A precision truncates the string as in %.3s
+            match spec.precision {
+                Some(p) => s.chars().take(p).collect(),
+                None => s,
+            }
+        }
+    };
+    if spec.plus && matches!(spec.conv, 'd' | 'i' | 'f' | 'e') && !formatted.starts_with('-') {
+        formatted.insert(0, '+');
+    }
+
+    let width_pad = spec.width.saturating_sub(formatted.chars().count());
+    let formatted_output = if spec.left {
+        format!("{}{}", formatted, " ".repeat(width_pad))
+    } else if spec.zero && numeric {
+        // Zeros go between the sign and the digits as in %05d
+        let sign_len = if formatted.starts_with(['-', '+']) {
+            1
+        } else {
+            0
+        };
+        let (sign, digits) = formatted.split_at(sign_len);
+        format!("{}{}{}", sign, "0".repeat(width_pad), digits)
+    } else {
+        format!("{}{}", " ".repeat(width_pad), formatted)
+    };
+
+    write!(collector, "{}", formatted_output).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn format_buf(args: Vec<Rc<Object>>) -> Result<Collector, String> {
+    if args.is_empty() {
+        return Err(String::from("takes a minimum of one argument"));
+    }
+    let parts: Vec<char> = if let Object::Str(fmt) = &*args[0] {
+        fmt.chars().collect()
+    } else {
+        return Err(String::from("Expected a string or format specifier"));
+    };
+    // Create a custom writer that collects the formatted output
+    let mut collector = Collector(Vec::new());
+    let mut idx_fmt = 0; // index to format specifier
+    let mut idx_arg = 1; // index to args skipping the format specifier
+    let mut in_spec = false; // inside '{}'
+    let mut in_spec_format = false; // format specifier available after colon ':'
+    let mut curr_spec_just = SpecJustify::Default;
+    let mut curr_spec_width = String::new(); // store format specifier
+    let mut curr_spec_precision = String::new(); // store precision specifier after '.'
+    let mut in_spec_precision = false; // precision specifier available after dot '.'
+    let mut curr_spec_padding = String::new(); // store padding specifier before '<' or '>'
+    let mut curr_spec_idx = String::new(); // store index specifier {0}, {1}, etc
+    let mut num_fmt: NumberFormat = NumberFormat::None;
+    while idx_fmt < parts.len() {
+        let curr = parts[idx_fmt];
+        let next = if idx_fmt < parts.len() - 1 {
+            parts[idx_fmt + 1]
+        } else {
+            '\0'
+        };
+        if curr == '{' {
+            if next == '{' {
+                write!(collector, "{{").map_err(|e| e.to_string())?;
+                idx_fmt += 2; // skip next brace as well
+            } else {
+                in_spec = true;
+                idx_fmt += 1;
+            }
+            continue;
+        } else if curr == '}' {
+            if next == '}' {
+                write!(collector, "}}").map_err(|e| e.to_string())?;
+                idx_fmt += 2; // skip next brace as well
+                continue;
+            }
+            // Now print the arguments based on the specifier
+            if idx_arg >= args.len() {
+                return Err(String::from("positional arguments exceeded the count"));
+            }
+
+            // If index specifier is empty, use positional index 'idx_arg'
+            // Otherwise, use the specified index into the arguments list
+            if curr_spec_idx.is_empty() {
+                // specifiers such as '{}', '{:10}', '{<5}', '{:0>5}' etc
+                format_obj(
+                    &mut collector,
+                    &curr_spec_padding,
+                    curr_spec_just,
+                    &curr_spec_width,
+                    &curr_spec_precision,
+                    num_fmt,
+                    &args[idx_arg],
+                )?;
+                idx_arg += 1;
+            } else {
+                // specifiers such as '{0}', '{1}', '{0:10}', '{0<5}', '{1:0>5}' etc
+                // 'args' also includes the format specifier. 'args.len()'
+                // So, 'idx_print' should be the next element in args vector.
+                let idx_print = curr_spec_idx.parse::<usize>().map_err(|e| e.to_string())? + 1;
+                if idx_print >= args.len() {
+                    return Err(String::from("positional argument index exceeded the count"));
+                }
+                format_obj(
+                    &mut collector,
+                    &curr_spec_padding,
+                    curr_spec_just,
+                    &curr_spec_width,
+                    &curr_spec_precision,
+                    num_fmt,
+                    &args[idx_print],
+                )?;
+            }
+
+            in_spec = false;
+            in_spec_format = false;
+            curr_spec_just = SpecJustify::Default;
+            curr_spec_width = String::new();
+            curr_spec_precision = String::new();
+            in_spec_precision = false;
+            curr_spec_padding = String::new();
+            curr_spec_idx = String::new();
+            num_fmt = NumberFormat::None;
+            idx_fmt += 1;
+            continue;
+        }
+        if in_spec {
+            if curr == ':' {
+                in_spec_format = true;
+                idx_fmt += 1;
+                continue;
+            }
+            if in_spec_format && (curr == '<' || curr == '>') {
+                // the specifier is actually a padding one
+                curr_spec_padding = curr_spec_width.clone();
+                curr_spec_width = String::new();
+                idx_fmt += 1;
+                curr_spec_just = if curr == '<' {
+                    SpecJustify::Left
+                } else {
+                    SpecJustify::Right
+                };
+                continue;
+            }
+            // A dot followed by a digit starts the precision specifier
+            // such as {:.2}, {:8.3} or {:.3e}
+            if in_spec_format && curr == '.' && next.is_ascii_digit() {
+                in_spec_precision = true;
+                idx_fmt += 1;
+                continue;
+            }
+
+            num_fmt = match curr {
+                'b' => NumberFormat::Boolean,
+                'o' => NumberFormat::Octal,
+                'x' => NumberFormat::Hex,
+                'X' => NumberFormat::HexaDecimal,
+                'e' => NumberFormat::Exponent,
+                _ => {
+                    // If none of the numberic specifiers, it must be an int
+                    if in_spec_precision {
+                        // Specifiers such as {:.2}
+                        curr_spec_precision.push(curr);
+                    } else if in_spec_format {
+                        // Specifiers such as {:10}, {:0<5}, {:0>5}
+                        curr_spec_width.push(curr);
+                    } else {
+                        // Specifiers such as {0}
+                        curr_spec_idx.push(curr);
+                    }
+                    NumberFormat::None
+                }
+            };
+        } else {
+            // Treat characters outside specifier as printable ones
+            write!(collector, "{}", curr).map_err(|e| e.to_string())?;
+        }
+        idx_fmt += 1;
+    }
+    Ok(collector)
+}
+
+/// Format the arguments using the printf style specifiers in the format
+/// string such as '%5d', '%08x', '%-20s' and '%.3f'. Braces are printed as
+/// they are, as is a '%' that does not start a valid specifier.
+pub fn sprintf_buf(args: Vec<Rc<Object>>) -> Result<Collector, String> {
+    if args.is_empty() {
+        return Err(String::from("takes a minimum of one argument"));
+    }
+    let parts: Vec<char> = if let Object::Str(fmt) = &*args[0] {
+        fmt.chars().collect()
+    } else {
+        return Err(String::from("Expected a string or format specifier"));
+    };
+    let mut collector = Collector(Vec::new());
+    let mut idx_fmt = 0; // index to format specifier
+    let mut idx_arg = 1; // index to args skipping the format specifier
+    while idx_fmt < parts.len() {
+        let curr = parts[idx_fmt];
+        if curr == '%' {
+            if parts.get(idx_fmt + 1) == Some(&'%') {
+                write!(collector, "%").map_err(|e| e.to_string())?;
+                idx_fmt += 2;
+                continue;
+            }
+            if let Some((spec, len)) = parse_printf_spec(&parts[idx_fmt + 1..]) {
+                if idx_arg >= args.len() {
+                    return Err(String::from("positional arguments exceeded the count"));
+                }
+                format_printf(&mut collector, &spec, &args[idx_arg])?;
+                idx_arg += 1;
+                idx_fmt += len + 1;
+                continue;
+            }
+        }
+        write!(collector, "{}", curr).map_err(|e| e.to_string())?;
+        idx_fmt += 1;
+    }
+    Ok(collector)
+}
@@ -0,0 +1,689 @@
+//! Regular expressions for matching strings and packet payloads.
+//!
+//! Patterns support literals, '.', character classes such as '[a-z]' and
+//! '\d', the anchors '^' and '$', word boundaries '\b' and '\B', groups
+//! '(...)' and '(?:...)', alternation '|', and the greedy and lazy
+//! repetitions '*', '+', '?' and '{m,n}'. A pattern is compiled to a
+//! program that is run as a Pike VM, which follows all the ways the
+//! pattern can match at once, so matching takes time linear in the length
+//! of the input regardless of the pattern. Strings are matched a character
+//! at a time and payloads a byte at a time, with '\xHH' matching a byte.
+
+use std::fmt;
+
+// Limits on the size of the compiled program
+const MAX_REPEAT: u32 = 1000;
+const MAX_PROGRAM: usize = 10000;
+// Limit on the nesting of groups and repetitions, which are parsed and
+// compiled recursively
+const MAX_NESTING: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Assert {
+    Start,
+    End,
+    WordBoundary,
+    NotWordBoundary,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Class {
+    ranges: Vec<(char, char)>,
+    negated: bool,
+}
+
+impl Class {
+    fn matches(&self, c: char) -> bool {
+        let found = self.ranges.iter().any(|(lo, hi)| *lo <= c && c <= *hi);
+        found != self.negated
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    Assert(Assert),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Assert(Assert),
+    // Try the first branch before the second
+    Split(usize, usize),
+    Jmp(usize),
+    Save(usize),
+    Match,
+}
+
+/// A compiled regular expression
+#[derive(Debug)]
+pub struct Regex {
+    pattern: String,
+    prog: Vec<Inst>,
+    groups: usize, // Number of capturing groups
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let mut parser = RegexParser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+            groups: 0,
+            depth: 0,
+        };
+        let node = parser.alternation()?;
+        if parser.pos < parser.chars.len() {
+            return Err(String::from("unmatched ')'"));
+        }
+        let mut compiler = RegexCompiler { prog: Vec::new() };
+        compiler.emit(Inst::Save(0))?;
+        compiler.node(&node)?;
+        compiler.emit(Inst::Save(1))?;
+        compiler.emit(Inst::Match)?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            prog: compiler.prog,
+            groups: parser.groups,
+        })
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Check if the pattern matches anywhere in a string
+    pub fn is_match_str(&self, s: &str) -> bool {
+        self.exec(&Input::Text(s)).is_some()
+    }
+
+    /// Check if the pattern matches anywhere in a payload
+    pub fn is_match_bytes(&self, bytes: &[u8]) -> bool {
+        self.exec(&Input::Bytes(bytes)).is_some()
+    }
+
+    /// Offsets of the leftmost match in a string followed by those of
+    /// each of the groups, which are None for the groups that did not
+    /// take part in the match
+    pub fn captures_str(&self, s: &str) -> Option<Vec<Option<(usize, usize)>>> {
+        self.exec(&Input::Text(s)).map(|slots| self.spans(&slots))
+    }
+
+    /// Offsets of the leftmost match in a payload and those of its groups
+    pub fn captures_bytes(&self, bytes: &[u8]) -> Option<Vec<Option<(usize, usize)>>> {
+        self.exec(&Input::Bytes(bytes))
+            .map(|slots| self.spans(&slots))
+    }
+
+    fn spans(&self, slots: &[Option<usize>]) -> Vec<Option<(usize, usize)>> {
+        (0..=self.groups)
+            .map(|g| match (slots[2 * g], slots[2 * g + 1]) {
+                (Some(start), Some(end)) => Some((start, end)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Run the program over the input and return the capture slots of the
+    // leftmost match, preferring the branches in the order of priority
+    fn exec(&self, input: &Input) -> Option<Vec<Option<usize>>> {
+        let nslots = 2 * (self.groups + 1);
+        let mut clist = Threads::new(self.prog.len());
+        let mut nlist = Threads::new(self.prog.len());
+        let mut matched = None;
+        let mut pos = 0;
+        loop {
+            // Start a new attempt at each position until a match is found,
+            // with a lower priority than the attempts started earlier
+            if matched.is_none() {
+                self.add_thread(&mut clist, 0, pos, vec![None; nslots], input);
+            }
+            if clist.threads.is_empty() && matched.is_some() {
+                break;
+            }
+            let unit = input.unit(pos);
+            for (pc, slots) in clist.threads.drain(..) {
+                let next = pos + unit.map_or(0, |(_, len)| len);
+                match &self.prog[pc] {
+                    Inst::Char(c) if unit.is_some_and(|(u, _)| u == *c) => {
+                        self.add_thread(&mut nlist, pc + 1, next, slots, input);
+                    }
+                    Inst::Any if unit.is_some_and(|(u, _)| u != '\n') => {
+                        self.add_thread(&mut nlist, pc + 1, next, slots, input);
+                    }
+                    Inst::Class(class) if unit.is_some_and(|(u, _)| class.matches(u)) => {
+                        self.add_thread(&mut nlist, pc + 1, next, slots, input);
+                    }
+                    Inst::Match => {
+                        // The threads of lower priority are cut off
+                        matched = Some(slots);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            std::mem::swap(&mut clist, &mut nlist);
+            nlist.clear();
+            match unit {
+                Some((_, len)) => pos += len,
+                None => break,
+            }
+        }
+        matched
+    }
+
+    // Add a thread at 'pc' along with the threads reachable from it
+    // without consuming any input, in the order of their priority
+    fn add_thread(
+        &self,
+        list: &mut Threads,
+        pc: usize,
+        pos: usize,
+        slots: Vec<Option<usize>>,
+        input: &Input,
+    ) {
+        let mut stack = vec![(pc, slots)];
+        while let Some((pc, mut slots)) = stack.pop() {
+            if !list.visit(pc) {
+                continue;
+            }
+            match &self.prog[pc] {
+                Inst::Jmp(target) => stack.push((*target, slots)),
+                Inst::Split(first, second) => {
+                    stack.push((*second, slots.clone()));
+                    stack.push((*first, slots));
+                }
+                Inst::Save(slot) => {
+                    slots[*slot] = Some(pos);
+                    stack.push((pc + 1, slots));
+                }
+                Inst::Assert(assert) => {
+                    if input.check(*assert, pos) {
+                        stack.push((pc + 1, slots));
+                    }
+                }
+                _ => list.threads.push((pc, slots)),
+            }
+        }
+    }
+}
+
+impl PartialEq for Regex {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "/{}/", self.pattern)
+    }
+}
+
+// The threads at a position of the input, with the instructions already
+// visited at that position
+struct Threads {
+    threads: Vec<(usize, Vec<Option<usize>>)>,
+    visited: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Self {
+            threads: Vec::new(),
+            visited: vec![false; len],
+        }
+    }
+
+    fn visit(&mut self, pc: usize) -> bool {
+        !std::mem::replace(&mut self.visited[pc], true)
+    }
+
+    fn clear(&mut self) {
+        self.threads.clear();
+        self.visited.iter_mut().for_each(|v| *v = false);
+    }
+}
+
+// A string is matched a character at a time, and bytes a byte at a time
+enum Input<'a> {
+    Text(&'a str),
+    Bytes(&'a [u8]),
+}
+
+impl Input<'_> {
+    // The character or byte at a position along with its length
+    fn unit(&self, pos: usize) -> Option<(char, usize)> {
+        match self {
+            Input::Text(s) => s[pos..].chars().next().map(|c| (c, c.len_utf8())),
+            Input::Bytes(b) => b.get(pos).map(|b| (*b as char, 1)),
+        }
+    }
+
+    fn prev(&self, pos: usize) -> Option<char> {
+        match self {
+            Input::Text(s) => s[..pos].chars().next_back(),
+            Input::Bytes(b) => pos.checked_sub(1).map(|p| b[p] as char),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Input::Text(s) => s.len(),
+            Input::Bytes(b) => b.len(),
+        }
+    }
+
+    fn check(&self, assert: Assert, pos: usize) -> bool {
+        let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let boundary = || is_word(self.prev(pos)) != is_word(self.unit(pos).map(|(c, _)| c));
+        match assert {
+            Assert::Start => pos == 0,
+            Assert::End => pos == self.len(),
+            Assert::WordBoundary => boundary(),
+            Assert::NotWordBoundary => !boundary(),
+        }
+    }
+}
+
+struct RegexParser {
+    chars: Vec<char>,
+    pos: usize,
+    groups: usize,
+    depth: usize, // Number of groups enclosing the position
+}
+
+impl RegexParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn alternation(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.concat()?];
+        while self.eat('|') {
+            branches.push(self.concat()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap_or(Node::Empty)
+        } else {
+            Node::Alt(branches)
+        })
+    }
+
+    fn concat(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            nodes.push(self.repeat()?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.pop().unwrap_or(Node::Empty),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    // Check that the nesting at the position with 'extra' more levels is
+    // within the limit
+    fn check_nesting(&self, extra: usize) -> Result<(), String> {
+        if self.depth + extra > MAX_NESTING {
+            return Err(format!(
+                "nesting is deeper than the limit of {}",
+                MAX_NESTING
+            ));
+        }
+        Ok(())
+    }
+
+    fn repeat(&mut self) -> Result<Node, String> {
+        let mut node = self.atom()?;
+        let mut repeats = 0;
+        loop {
+            let (min, max) = match self.peek() {
+                Some('{') => match self.counts()? {
+                    Some(counts) => counts,
+                    None => break,
+                },
+                Some(c @ ('*' | '+' | '?')) => {
+                    self.pos += 1;
+                    match c {
+                        '*' => (0, None),
+                        '+' => (1, None),
+                        _ => (0, Some(1)),
+                    }
+                }
+                _ => break,
+            };
+            if matches!(node, Node::Empty | Node::Assert(_)) {
+                return Err(String::from("nothing to repeat"));
+            }
+            // Each repetition of a repetition nests it one level deeper
+            repeats += 1;
+            self.check_nesting(repeats)?;
+            let greedy = !self.eat('?');
+            node = Node::Repeat {
+                node: Box::new(node),
+                min,
+                max,
+                greedy,
+            };
+        }
+        Ok(node)
+    }
+
+    // Parse a repetition operator and leave the position past it. The
+    // counts of '{m}', '{m,}' and '{m,n}' are checked, and a brace that
+    // does not begin one of these is a literal, for which None is returned.
+    fn counts(&mut self) -> Result<Option<(u32, Option<u32>)>, String> {
+        let start = self.pos;
+        self.pos += 1;
+        let min = self.number();
+        let max = if self.eat(',') { self.number() } else { min };
+        let Some(min) = min.filter(|_| self.eat('}')) else {
+            self.pos = start;
+            return Ok(None);
+        };
+        if min > MAX_REPEAT || max.is_some_and(|max| max > MAX_REPEAT) {
+            return Err(String::from("repetition count is too large"));
+        }
+        if max.is_some_and(|max| max < min) {
+            return Err(String::from("invalid repetition count"));
+        }
+        Ok(Some((min, max)))
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits.parse().ok()
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let Some(c) = self.next() else {
+            return Ok(Node::Empty);
+        };
+        match c {
+            '(' => {
+                let index = if self.eat('?') {
+                    if !self.eat(':') {
+                        return Err(String::from("unsupported group"));
+                    }
+                    None
+                } else {
+                    self.groups += 1;
+                    Some(self.groups)
+                };
+                self.depth += 1;
+                self.check_nesting(0)?;
+                let node = self.alternation()?;
+                self.depth -= 1;
+                if !self.eat(')') {
+                    return Err(String::from("unmatched '('"));
+                }
+                Ok(Node::Group(Box::new(node), index))
+            }
+            '[' => self.class(),
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Assert(Assert::Start)),
+            '$' => Ok(Node::Assert(Assert::End)),
+            '*' | '+' | '?' => Err(String::from("nothing to repeat")),
+            '\\' => self.escape(),
+            c => Ok(Node::Char(c)),
+        }
+    }
+
+    fn escape(&mut self) -> Result<Node, String> {
+        let Some(c) = self.next() else {
+            return Err(String::from("trailing backslash"));
+        };
+        let node = match c {
+            'b' => Node::Assert(Assert::WordBoundary),
+            'B' => Node::Assert(Assert::NotWordBoundary),
+            'd' | 'D' | 'w' | 'W' | 's' | 'S' => Node::Class(Class {
+                ranges: perl_class(c.to_ascii_lowercase()),
+                negated: c.is_ascii_uppercase(),
+            }),
+            c => Node::Char(self.escaped_char(c)?),
+        };
+        Ok(node)
+    }
+
+    // The character written as an escape sequence beginning with 'c'
+    fn escaped_char(&mut self, c: char) -> Result<char, String> {
+        match c {
+            'n' => Ok('\n'),
+            'r' => Ok('\r'),
+            't' => Ok('\t'),
+            'f' => Ok('\x0c'),
+            'v' => Ok('\x0b'),
+            '0' => Ok('\0'),
+            'x' => {
+                let hex: String = self.chars.iter().skip(self.pos).take(2).collect();
+                let byte = u8::from_str_radix(&hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == 2)
+                    .ok_or("invalid hex escape")?;
+                self.pos += 2;
+                Ok(byte as char)
+            }
+            c if c.is_ascii_alphanumeric() => Err(format!("invalid escape '\\{}'", c)),
+            c => Ok(c),
+        }
+    }
+
+    fn class(&mut self) -> Result<Node, String> {
+        let negated = self.eat('^');
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self
+                .next()
+                .ok_or_else(|| String::from("unterminated character class"))?;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let lo = if c == '\\' {
+                let e = self
+                    .next()
+                    .ok_or_else(|| String::from("unterminated character class"))?;
+                match e {
+                    'd' | 'w' | 's' => {
+                        ranges.extend(perl_class(e));
+                        continue;
+                    }
+                    'D' | 'W' | 'S' => {
+                        ranges.extend(complement(&perl_class(e.to_ascii_lowercase())));
+                        continue;
+                    }
+                    e => self.escaped_char(e)?,
+                }
+            } else {
+                c
+            };
+            // A '-' at the end of the class is a literal
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                let hi = match self.next() {
+                    Some('\\') => {
+                        let e = self
+                            .next()
+                            .ok_or_else(|| String::from("unterminated character class"))?;
+                        self.escaped_char(e)?
+                    }
+                    Some(hi) => hi,
+                    None => return Err(String::from("unterminated character class")),
+                };
+                if hi < lo {
+                    return Err(String::from("invalid range in character class"));
+                }
+                ranges.push((lo, hi));
+            } else {
+                ranges.push((lo, lo));
+            }
+        }
+        Ok(Node::Class(Class { ranges, negated }))
+    }
+}
+
+// Ranges of the classes '\d', '\w' and '\s'
+fn perl_class(c: char) -> Vec<(char, char)> {
+    match c {
+        'd' => vec![('0', '9')],
+        'w' => vec![('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')],
+        _ => vec![('\t', '\r'), (' ', ' ')],
+    }
+}
+
+// Ranges of the characters not in the sorted ranges given
+fn complement(ranges: &[(char, char)]) -> Vec<(char, char)> {
+    let mut out = Vec::new();
+    let mut next = 0u32;
+    for (lo, hi) in ranges {
+        if (*lo as u32) > next {
+            if let (Some(a), Some(b)) = (char::from_u32(next), char::from_u32(*lo as u32 - 1)) {
+                out.push((a, b));
+            }
+        }
+        next = *hi as u32 + 1;
+    }
+    if let Some(a) = char::from_u32(next) {
+        out.push((a, char::MAX));
+    }
+    out
+}
+
+struct RegexCompiler {
+    prog: Vec<Inst>,
+}
+
+impl RegexCompiler {
+    fn emit(&mut self, inst: Inst) -> Result<usize, String> {
+        if self.prog.len() >= MAX_PROGRAM {
+            return Err(String::from("pattern is too large"));
+        }
+        self.prog.push(inst);
+        Ok(self.prog.len() - 1)
+    }
+
+    fn node(&mut self, node: &Node) -> Result<(), String> {
+        match node {
+            Node::Empty => {}
+            Node::Char(c) => {
+                self.emit(Inst::Char(*c))?;
+            }
+            Node::Any => {
+                self.emit(Inst::Any)?;
+            }
+            Node::Class(class) => {
+                self.emit(Inst::Class(class.clone()))?;
+            }
+            Node::Assert(assert) => {
+                self.emit(Inst::Assert(*assert))?;
+            }
+            Node::Group(node, None) => self.node(node)?,
+            Node::Group(node, Some(index)) => {
+                self.emit(Inst::Save(2 * index))?;
+                self.node(node)?;
+                self.emit(Inst::Save(2 * index + 1))?;
+            }
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.node(node)?;
+                }
+            }
+            Node::Alt(branches) => {
+                // Each branch but the last is tried before the rest and
+                // jumps past them when it matches
+                let mut jumps = Vec::new();
+                for (i, branch) in branches.iter().enumerate() {
+                    if i + 1 < branches.len() {
+                        let split = self.emit(Inst::Split(0, 0))?;
+                        self.node(branch)?;
+                        jumps.push(self.emit(Inst::Jmp(0))?);
+                        self.prog[split] = Inst::Split(split + 1, self.prog.len());
+                    } else {
+                        self.node(branch)?;
+                    }
+                }
+                let end = self.prog.len();
+                for jump in jumps {
+                    self.prog[jump] = Inst::Jmp(end);
+                }
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => {
+                for _ in 0..*min {
+                    self.node(node)?;
+                }
+                match max {
+                    None => {
+                        let split = self.emit(Inst::Split(0, 0))?;
+                        self.node(node)?;
+                        self.emit(Inst::Jmp(split))?;
+                        let end = self.prog.len();
+                        self.prog[split] = self.split(split + 1, end, *greedy);
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.emit(Inst::Split(0, 0))?);
+                            self.node(node)?;
+                        }
+                        let end = self.prog.len();
+                        for split in splits {
+                            self.prog[split] = self.split(split + 1, end, *greedy);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn split(&self, body: usize, end: usize, greedy: bool) -> Inst {
+        if greedy {
+            Inst::Split(body, end)
+        } else {
+            Inst::Split(end, body)
+        }
+    }
+}
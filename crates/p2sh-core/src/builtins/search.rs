@@ -0,0 +1,113 @@
+//! Substring search over packet payloads.
+//!
+//! The search looks for the first byte of the pattern and verifies the
+//! rest of the pattern at each candidate. With the 'simd' feature on
+//! x86_64, the first byte is looked for 16 or 32 bytes at a time using
+//! SSE2 or AVX2 as detected at runtime.
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+use std::sync::OnceLock;
+
+/// Instruction set used to look for bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Accel {
+    Scalar,
+    Sse2,
+    Avx2,
+}
+
+/// Instruction set chosen for this machine
+pub fn accel() -> Accel {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    {
+        static ACCEL: OnceLock<Accel> = OnceLock::new();
+        *ACCEL.get_or_init(|| {
+            if is_x86_feature_detected!("avx2") {
+                Accel::Avx2
+            } else {
+                // SSE2 is part of the x86_64 baseline
+                Accel::Sse2
+            }
+        })
+    }
+    #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+    Accel::Scalar
+}
+
+/// Position of the first occurrence of 'needle' in 'haystack'. An empty
+/// needle is found at the start.
+pub fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    let Some((&first, rest)) = needle.split_first() else {
+        return Some(0);
+    };
+    if haystack.len() < needle.len() {
+        return None;
+    }
+    // The last position the needle can start at
+    let last = haystack.len() - needle.len();
+    let mut pos = 0;
+    while pos <= last {
+        let candidate = pos + find_byte(&haystack[pos..=last], first)?;
+        if haystack[candidate + 1..candidate + needle.len()] == *rest {
+            return Some(candidate);
+        }
+        pos = candidate + 1;
+    }
+    None
+}
+
+/// Position of the first occurrence of the byte 'b' in 'haystack'
+pub fn find_byte(haystack: &[u8], b: u8) -> Option<usize> {
+    match accel() {
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        // SAFETY: AVX2 is supported as detected at runtime
+        Accel::Avx2 => unsafe { x86::find_byte_avx2(haystack, b) },
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        // SAFETY: SSE2 is supported on all x86_64 processors
+        Accel::Sse2 => unsafe { x86::find_byte_sse2(haystack, b) },
+        _ => find_byte_scalar(haystack, b),
+    }
+}
+
+fn find_byte_scalar(haystack: &[u8], b: u8) -> Option<usize> {
+    haystack.iter().position(|&x| x == b)
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::find_byte_scalar;
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn find_byte_sse2(haystack: &[u8], b: u8) -> Option<usize> {
+        const LANES: usize = 16;
+        let target = _mm_set1_epi8(b as i8);
+        let mut pos = 0;
+        while pos + LANES <= haystack.len() {
+            let chunk = _mm_loadu_si128(haystack.as_ptr().add(pos) as *const __m128i);
+            let mask = _mm_movemask_epi8(_mm_cmpeq_epi8(chunk, target));
+            if mask != 0 {
+                return Some(pos + mask.trailing_zeros() as usize);
+            }
+            pos += LANES;
+        }
+        find_byte_scalar(&haystack[pos..], b).map(|i| pos + i)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn find_byte_avx2(haystack: &[u8], b: u8) -> Option<usize> {
+        const LANES: usize = 32;
+        let target = _mm256_set1_epi8(b as i8);
+        let mut pos = 0;
+        while pos + LANES <= haystack.len() {
+            let chunk = _mm256_loadu_si256(haystack.as_ptr().add(pos) as *const __m256i);
+            let mask = _mm256_movemask_epi8(_mm256_cmpeq_epi8(chunk, target));
+            if mask != 0 {
+                return Some(pos + mask.trailing_zeros() as usize);
+            }
+            pos += LANES;
+        }
+        find_byte_sse2(&haystack[pos..], b).map(|i| pos + i)
+    }
+}
@@ -0,0 +1,2576 @@
+#![allow(unused_imports)]
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process;
+use std::rc::Rc;
+
+#[cfg(feature = "pcap")]
+use super::bpf::BpfExpr;
+#[cfg(feature = "pcap")]
+use super::capture::Capture;
+use super::functions::{builtin_equals, builtin_format, builtin_sprintf};
+#[cfg(feature = "pcap")]
+use super::pcap::{CorruptPolicy, Pcap, PcapFormat, PcapPacketHeader, TsCorrection};
+#[cfg(feature = "pcap")]
+use super::pcapdiff::{summarize_file, DiffEntry, DiffOptions, MatchReport};
+#[cfg(feature = "pcap")]
+use super::pcapfiles::{expand_path, expand_paths, ReadOrder};
+use crate::code::prop::PacketPropType;
+use crate::compiler::Compiler;
+use crate::object::array::Array;
+use crate::object::file::FileHandle;
+use crate::object::float_to_string;
+#[cfg(feature = "pcap")]
+use crate::object::layers::Ipv4Packet;
+use crate::object::Object;
+use crate::vm::interpreter::VM;
+#[cfg(feature = "pcap")]
+use p2sh_dissect::dns::Dns;
+use std::collections::HashMap;
+
+#[test]
+fn test_builtin_function_format() {
+    struct FormatTest {
+        args: Vec<Rc<Object>>,
+        expected: &'static str,
+    }
+    let format_tests = vec![
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("{}".to_string())),
+                Rc::new(Object::Integer(69420)),
+            ],
+            expected: "69420",
+        },
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("{:b}".to_string())),
+                Rc::new(Object::Integer(69420)),
+            ],
+            expected: "10000111100101100",
+        },
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("{:o}".to_string())),
+                Rc::new(Object::Integer(69420)),
+            ],
+            expected: "207454",
+        },
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("{:x}".to_string())),
+                Rc::new(Object::Integer(69420)),
+            ],
+            expected: "10f2c",
+        },
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("{:X}".to_string())),
+                Rc::new(Object::Integer(69420)),
+            ],
+            expected: "10F2C",
+        },
+        // Default justify (numbers)
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("{:5}".to_string())),
+                Rc::new(Object::Integer(1)),
+            ],
+            expected: "    1",
+        },
+        // Right justify
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("{:>5}".to_string())),
+                Rc::new(Object::Integer(1)),
+            ],
+            expected: "    1",
+        },
+        // Left justify
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("{:<5}".to_string())),
+                Rc::new(Object::Integer(1)),
+            ],
+            expected: "1    ",
+        },
+        // Right justify with padding
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("{:0>5}".to_string())),
+                Rc::new(Object::Integer(1)),
+            ],
+            expected: "00001",
+        },
+        // Left justify with padding
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("{:0<5}".to_string())),
+                Rc::new(Object::Integer(1)),
+            ],
+            expected: "10000",
+        },
+        // Indexed arguments
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("My name is {1}, {0} {1}".to_string())),
+                Rc::new(Object::Str("James".to_string())),
+                Rc::new(Object::Str("Bond".to_string())),
+            ],
+            expected: "My name is Bond, James Bond",
+        },
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str(
+                    "{0:<10},{1:0<05},{2},{3:b},{3:o},{4:x},{4:X}".to_string(),
+                )),
+                Rc::new(Object::Str("Hello".to_string())),
+                Rc::new(Object::Integer(1)),
+                Rc::new(Object::Bool(true)),
+                Rc::new(Object::Integer(10)),
+                Rc::new(Object::Integer(65535)),
+            ],
+            expected: "Hello     ,10000,true,1010,12,ffff,FFFF",
+        },
+        // Floating point numbers
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("{} {} {} {}".to_string())),
+                Rc::new(Object::Float(0.1 + 0.2)),
+                Rc::new(Object::Float(2.)),
+                Rc::new(Object::Float(1e20)),
+                Rc::new(Object::Float(-1.5e-7)),
+            ],
+            expected: "0.30000000000000004 2.0 1e20 -1.5e-7",
+        },
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("{:.2},{:.0},{:8.3},{:0>8.3}".to_string())),
+                Rc::new(Object::Float(0.1 + 0.2)),
+                Rc::new(Object::Float(2.5)),
+                Rc::new(Object::Float(2.71235)),
+                Rc::new(Object::Float(2.71235)),
+            ],
+            expected: "0.30,2,2.712   ,0002.712",
+        },
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("{:e},{:.2e},{:e},{:.>6}".to_string())),
+                Rc::new(Object::Float(1234.5)),
+                Rc::new(Object::Float(1234.5)),
+                Rc::new(Object::Integer(1000)),
+                Rc::new(Object::Integer(1)),
+            ],
+            expected: "1.2345e3,1.23e3,1e3,.....1",
+        },
+        // '%' has no special meaning in the format string
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("%% 5%each 100%d {}%".to_string())),
+                Rc::new(Object::Integer(1)),
+            ],
+            expected: "%% 5%each 100%d 1%",
+        },
+    ];
+
+    let mut count: usize = 0;
+    for (i, t) in format_tests.iter().enumerate() {
+        let result = builtin_format(t.args.clone());
+        if let Ok(obj) = result {
+            if let Object::Str(s) = &*obj {
+                if s != t.expected {
+                    eprintln!("[{}] Incorrect result. want: {}, got: {}", i, t.expected, s);
+                    count += 1;
+                }
+            } else {
+                eprintln!("[{}] Test failed: Bad result", i);
+                count += 1;
+            }
+        } else {
+            eprintln!("[{}] Test failed: Bad result", i);
+            count += 1;
+        }
+    }
+    if count != 0 {
+        panic!("{} format tests failed", count);
+    }
+}
+
+#[test]
+#[allow(clippy::useless_vec)]
+fn test_builtin_function_sprintf() {
+    struct FormatTest {
+        args: Vec<Rc<Object>>,
+        expected: &'static str,
+    }
+    let format_tests = vec![
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("[%d|%5d|%-5d|%05d|%+d]".to_string())),
+                Rc::new(Object::Integer(42)),
+                Rc::new(Object::Integer(42)),
+                Rc::new(Object::Integer(42)),
+                Rc::new(Object::Integer(-42)),
+                Rc::new(Object::Integer(42)),
+            ],
+            expected: "[42|   42|42   |-0042|+42]",
+        },
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("%x %08x %X %o %b".to_string())),
+                Rc::new(Object::Integer(255)),
+                Rc::new(Object::Integer(3054)),
+                Rc::new(Object::Integer(255)),
+                Rc::new(Object::Integer(8)),
+                Rc::new(Object::Integer(5)),
+            ],
+            expected: "ff 00000bee FF 10 101",
+        },
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("[%s|%-8s|%8s|%.3s]".to_string())),
+                Rc::new(Object::Str("tcp".to_string())),
+                Rc::new(Object::Str("udp".to_string())),
+                Rc::new(Object::Str("icmp".to_string())),
+                Rc::new(Object::Str("abcdef".to_string())),
+            ],
+            expected: "[tcp|udp     |    icmp|abc]",
+        },
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("%f %.3f %8.2f %.2e %c".to_string())),
+                Rc::new(Object::Float(1.5)),
+                Rc::new(Object::Float(2.25175)),
+                Rc::new(Object::Integer(3)),
+                Rc::new(Object::Float(1234.5)),
+                Rc::new(Object::Char('x')),
+            ],
+            expected: "1.500000 2.252     3.00 1.23e3 x",
+        },
+        FormatTest {
+            args: vec![Rc::new(Object::Str("100%% %q % {} %".to_string()))],
+            expected: "100% %q % {} %",
+        },
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("%d %02x %o %b".to_string())),
+                Rc::new(Object::Byte(200)),
+                Rc::new(Object::Byte(10)),
+                Rc::new(Object::Byte(8)),
+                Rc::new(Object::Byte(5)),
+            ],
+            expected: "200 0a 10 101",
+        },
+    ];
+
+    for (i, t) in format_tests.iter().enumerate() {
+        let obj = builtin_sprintf(t.args.clone()).unwrap();
+        let Object::Str(s) = obj.as_ref() else {
+            panic!("[{}] Bad result", i);
+        };
+        assert_eq!(s, t.expected, "[{}]", i);
+    }
+
+    let failure_tests = vec![
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("%d %d".to_string())),
+                Rc::new(Object::Integer(1)),
+            ],
+            expected: "positional arguments exceeded the count",
+        },
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("%d".to_string())),
+                Rc::new(Object::Float(1.5)),
+            ],
+            expected: "Can't format non-integer as decimal",
+        },
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("%.2f".to_string())),
+                Rc::new(Object::Str("1.5".to_string())),
+            ],
+            expected: "Can't format non-number as float",
+        },
+    ];
+    for (i, t) in failure_tests.iter().enumerate() {
+        let err = builtin_sprintf(t.args.clone()).unwrap_err();
+        assert_eq!(err, t.expected, "[{}]", i);
+    }
+}
+
+#[test]
+#[allow(clippy::useless_vec)]
+fn test_builtin_function_format_failures() {
+    struct FormatTest {
+        args: Vec<Rc<Object>>,
+        expected: &'static str,
+    }
+    let format_tests = vec![
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Integer(69420)),
+                Rc::new(Object::Str("{:b}".to_string())),
+            ],
+            expected: "Expected a string or format specifier",
+        },
+        // Indexed arguments
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("{} {} {}".to_string())),
+                Rc::new(Object::Str("Hello".to_string())),
+                Rc::new(Object::Str("World".to_string())),
+            ],
+            expected: "positional arguments exceeded the count",
+        },
+        FormatTest {
+            args: vec![
+                Rc::new(Object::Str("{0} {2} {1}".to_string())),
+                Rc::new(Object::Str("Hello".to_string())),
+                Rc::new(Object::Str("World".to_string())),
+            ],
+            expected: "positional argument index exceeded the count",
+        },
+    ];
+
+    let mut count: usize = 0;
+    for (i, t) in format_tests.iter().enumerate() {
+        let result = builtin_format(t.args.clone());
+        if let Err(msg) = result {
+            if msg != t.expected {
+                eprintln!(
+                    "[{}] Incorrect result. want: {}, got: {}",
+                    i, t.expected, msg
+                );
+                count += 1;
+            }
+        } else {
+            eprintln!("[{}] Test failed: Expected error", i);
+            count += 1;
+        }
+    }
+    if count != 0 {
+        panic!("{} format tests failed", count);
+    }
+}
+
+#[test]
+fn test_float_display() {
+    let tests = vec![
+        (0.0, "0.0"),
+        (-0.0, "-0.0"),
+        (1.0, "1.0"),
+        (-42.0, "-42.0"),
+        (0.1 + 0.2, "0.30000000000000004"),
+        (1.0 / 3.0, "0.3333333333333333"),
+        (123456.789, "123456.789"),
+        (1e15, "1000000000000000.0"),
+        (1e16, "1e16"),
+        (1.5e300, "1.5e300"),
+        (0.00001, "0.00001"),
+        (0.000001, "1e-6"),
+        (-2.5e-10, "-2.5e-10"),
+        (f64::MAX, "1.7976931348623157e308"),
+        (f64::MIN_POSITIVE, "2.2250738585072014e-308"),
+        (f64::INFINITY, "inf"),
+        (f64::NEG_INFINITY, "-inf"),
+        (f64::NAN, "NaN"),
+    ];
+    for (i, (val, expected)) in tests.iter().enumerate() {
+        assert_eq!(float_to_string(*val), *expected, "[{}]", i);
+        // shortest representation must round-trip to the same value
+        if val.is_finite() {
+            assert_eq!(expected.parse::<f64>().unwrap(), *val, "[{}]", i);
+        }
+        assert_eq!(format!("{}", Object::Float(*val)), *expected, "[{}]", i);
+    }
+}
+
+// Write a little endian pcap file with the given snaplen and records.
+// Each record is a tuple of (caplen, wirelen, payload).
+#[cfg(feature = "pcap")]
+#[cfg(test)]
+fn write_test_pcap(name: &str, snaplen: u32, records: &[(u32, u32, Vec<u8>)]) -> PathBuf {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&0xA1B2C3D4u32.to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&4u16.to_le_bytes());
+    bytes.extend_from_slice(&0i32.to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&snaplen.to_le_bytes());
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    for (i, (caplen, wirelen, payload)) in records.iter().enumerate() {
+        bytes.extend_from_slice(&(i as u32).to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&caplen.to_le_bytes());
+        bytes.extend_from_slice(&wirelen.to_le_bytes());
+        bytes.extend_from_slice(payload);
+    }
+    let path = env::temp_dir().join(format!("p2sh-test-{}-{}.pcap", name, process::id()));
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+#[cfg(all(test, feature = "pcap"))]
+fn open_test_pcap(path: &PathBuf) -> Pcap {
+    let file = fs::File::open(path).unwrap();
+    let handle = FileHandle::new_reader(io::BufReader::new(file));
+    Pcap::from_file(Rc::new(handle)).unwrap()
+}
+
+// Read all the packets until an error; return the caplens and the error kind
+#[cfg(all(test, feature = "pcap"))]
+fn read_test_pcap(pcap: &Pcap) -> (Vec<i64>, io::ErrorKind) {
+    let mut caplens = Vec::new();
+    loop {
+        match pcap.next_packet() {
+            Ok(pkt) => match pkt.get_caplen().as_ref() {
+                Object::Integer(n) => caplens.push(*n),
+                _ => panic!("caplen is not an integer"),
+            },
+            Err(e) => return (caplens, e.kind()),
+        }
+    }
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_pcap_corrupt_records() {
+    let records = vec![
+        (4, 4, vec![1, 2, 3, 4]),
+        // caplen exceeds snaplen
+        (10, 10, vec![0; 10]),
+        // caplen exceeds wirelen
+        (6, 4, vec![0; 6]),
+        (2, 60, vec![5, 6]),
+    ];
+    let path = write_test_pcap("corrupt", 8, &records);
+
+    let pcap = open_test_pcap(&path);
+    let (caplens, kind) = read_test_pcap(&pcap);
+    assert_eq!(caplens, vec![4]);
+    assert_eq!(kind, io::ErrorKind::InvalidData);
+    assert_eq!(pcap.get_corrupt_count(), 1);
+
+    let pcap = open_test_pcap(&path);
+    pcap.set_on_corrupt(CorruptPolicy::Warn);
+    let (caplens, kind) = read_test_pcap(&pcap);
+    assert_eq!(caplens, vec![4, 10, 6, 2]);
+    assert_eq!(kind, io::ErrorKind::UnexpectedEof);
+    assert_eq!(pcap.get_corrupt_count(), 2);
+
+    let pcap = open_test_pcap(&path);
+    pcap.set_on_corrupt(CorruptPolicy::Clamp);
+    let (caplens, kind) = read_test_pcap(&pcap);
+    assert_eq!(caplens, vec![4, 8, 6, 2]);
+    assert_eq!(kind, io::ErrorKind::UnexpectedEof);
+    assert_eq!(pcap.get_corrupt_count(), 2);
+
+    let pcap = open_test_pcap(&path);
+    pcap.set_on_corrupt(CorruptPolicy::Skip);
+    let (caplens, kind) = read_test_pcap(&pcap);
+    assert_eq!(caplens, vec![4, 2]);
+    assert_eq!(kind, io::ErrorKind::UnexpectedEof);
+    assert_eq!(pcap.get_corrupt_count(), 2);
+
+    // A larger snaplen makes the second record valid
+    let pcap = open_test_pcap(&path);
+    pcap.set_on_corrupt(CorruptPolicy::Skip);
+    pcap.set_snaplen_raw(16);
+    let (caplens, _) = read_test_pcap(&pcap);
+    assert_eq!(caplens, vec![4, 10, 2]);
+    assert_eq!(pcap.get_corrupt_count(), 1);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_pcap_corrupt_record_truncated() {
+    // The caplen of the second record exceeds the snaplen and the rest of
+    // the file, but not the maximum packet size
+    let records = vec![
+        (4, 4, vec![1, 2, 3, 4]),
+        (100000, 100000, vec![0xff; 6]),
+        (3, 3, vec![7, 8, 9]),
+    ];
+    let path = write_test_pcap("corrupt-truncated", 65535, &records);
+
+    // The stream ending within the record is not the end of the packets
+    for policy in [
+        CorruptPolicy::Error,
+        CorruptPolicy::Warn,
+        CorruptPolicy::Clamp,
+        CorruptPolicy::Skip,
+    ] {
+        let pcap = open_test_pcap(&path);
+        pcap.set_on_corrupt(policy);
+        let (caplens, kind) = read_test_pcap(&pcap);
+        assert_eq!(caplens, vec![4], "{:?}", policy);
+        assert_eq!(kind, io::ErrorKind::InvalidData, "{:?}", policy);
+        assert_eq!(pcap.get_corrupt_count(), 1, "{:?}", policy);
+    }
+
+    // The record that follows is found by resyncing
+    let pcap = open_test_pcap(&path);
+    pcap.set_repair(true);
+    let (caplens, kind) = read_test_pcap(&pcap);
+    assert_eq!(caplens, vec![4, 3]);
+    assert_eq!(kind, io::ErrorKind::UnexpectedEof);
+    assert_eq!(pcap.get_corrupt_count(), 1);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_pcap_files_expand() {
+    let dir = env::temp_dir().join(format!("p2sh-test-pcapfiles-{}", process::id()));
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    // Written in the reverse order of their names
+    let files = [
+        "c.pcap1",
+        "b.pcap",
+        "a.pcap",
+        ".hidden.pcap",
+        "notes.txt",
+        "x10.pcap",
+        "x2.pcap",
+    ];
+    let start = std::time::SystemTime::now();
+    for (i, name) in files.iter().enumerate() {
+        let file = fs::File::create(dir.join(name)).unwrap();
+        file.set_modified(start + std::time::Duration::from_secs(i as u64))
+            .unwrap();
+    }
+    let names = |paths: Vec<PathBuf>| -> Vec<String> {
+        paths
+            .iter()
+            .map(|p| p.strip_prefix(&dir).unwrap().display().to_string())
+            .collect()
+    };
+    let pattern = dir.join("*.pcap*").display().to_string();
+    assert_eq!(
+        names(expand_path(&pattern, ReadOrder::Name).unwrap()),
+        vec!["a.pcap", "b.pcap", "c.pcap1", "x2.pcap", "x10.pcap"]
+    );
+    assert_eq!(
+        names(expand_path(&pattern, ReadOrder::Mtime).unwrap()),
+        vec!["c.pcap1", "b.pcap", "a.pcap", "x10.pcap", "x2.pcap"]
+    );
+    // A directory stands for the files in it
+    let path = dir.display().to_string();
+    assert_eq!(
+        names(expand_path(&path, ReadOrder::Name).unwrap()),
+        vec![
+            "a.pcap",
+            "b.pcap",
+            "c.pcap1",
+            "notes.txt",
+            "x2.pcap",
+            "x10.pcap"
+        ]
+    );
+    // Other paths are kept as they are, even if they do not exist
+    let paths = vec![
+        dir.join("b.pcap").display().to_string(),
+        "x.pcap".to_string(),
+    ];
+    assert_eq!(
+        expand_paths(&paths, ReadOrder::Name).unwrap(),
+        vec![dir.join("b.pcap"), PathBuf::from("x.pcap")]
+    );
+    let pattern = dir.join("*.pcapng").display().to_string();
+    let err = expand_path(&pattern, ReadOrder::Name).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert_eq!(err.to_string(), format!("No files match '{}'", pattern));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_pcap_max_packet_size() {
+    let records = vec![
+        (4, 4, vec![1, 2, 3, 4]),
+        (10, 10, (0..10).collect()),
+        (2, 60, vec![5, 6]),
+    ];
+    let path = write_test_pcap("maxpacket", 65535, &records);
+
+    let pcap = open_test_pcap(&path);
+    let (caplens, _) = read_test_pcap(&pcap);
+    assert_eq!(caplens, vec![4, 10, 2]);
+    assert_eq!(pcap.get_corrupt_count(), 0);
+
+    let pcap = open_test_pcap(&path);
+    pcap.set_max_packet_size(8);
+    let (caplens, kind) = read_test_pcap(&pcap);
+    assert_eq!(caplens, vec![4]);
+    assert_eq!(kind, io::ErrorKind::InvalidData);
+    assert_eq!(pcap.get_corrupt_count(), 1);
+
+    // The record is not read as is even with a warning
+    for policy in [CorruptPolicy::Warn, CorruptPolicy::Skip] {
+        let pcap = open_test_pcap(&path);
+        pcap.set_max_packet_size(8);
+        pcap.set_on_corrupt(policy);
+        let (caplens, kind) = read_test_pcap(&pcap);
+        assert_eq!(caplens, vec![4, 2]);
+        assert_eq!(kind, io::ErrorKind::UnexpectedEof);
+        assert_eq!(pcap.get_corrupt_count(), 1);
+    }
+
+    let pcap = open_test_pcap(&path);
+    pcap.set_max_packet_size(8);
+    pcap.set_on_corrupt(CorruptPolicy::Clamp);
+    pcap.next_packet().unwrap();
+    let pkt = pcap.next_packet().unwrap();
+    assert_eq!(pkt.payload(), (0..8).collect::<Vec<u8>>());
+    assert_eq!(pkt.get_wirelen(), Rc::new(Object::Integer(10)));
+    assert_eq!(pcap.next_packet().unwrap().payload(), vec![5, 6]);
+    fs::remove_file(&path).unwrap();
+
+    // A damaged length runs past the end of the stream
+    let records = vec![(4, 4, vec![1, 2, 3, 4]), (u32::MAX, u32::MAX, vec![0; 4])];
+    let path = write_test_pcap("maxpacket-damaged", 65535, &records);
+    let pcap = open_test_pcap(&path);
+    pcap.set_on_corrupt(CorruptPolicy::Skip);
+    let (caplens, kind) = read_test_pcap(&pcap);
+    assert_eq!(caplens, vec![4]);
+    assert_eq!(kind, io::ErrorKind::InvalidData);
+    assert_eq!(pcap.get_corrupt_count(), 1);
+    fs::remove_file(&path).unwrap();
+
+    let records = vec![(0, vec![0xaa; 5]), (0, vec![0xbb; 14])];
+    let path = write_test_pcapng("maxpacket", false, 6, &records);
+    let pcap = open_test_pcap(&path);
+    pcap.set_max_packet_size(8);
+    pcap.set_on_corrupt(CorruptPolicy::Clamp);
+    let (caplens, _) = read_test_pcap(&pcap);
+    assert_eq!(caplens, vec![5, 8, 3]);
+    assert_eq!(pcap.get_corrupt_count(), 1);
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_pcap_ts_correction() {
+    let records = vec![(1, 1, vec![0]), (1, 1, vec![0]), (1, 1, vec![0])];
+    let path = write_test_pcap("tscorrection", 8, &records);
+    let read_ts = |correction: Option<TsCorrection>| {
+        let pcap = open_test_pcap(&path);
+        if let Some(correction) = correction {
+            pcap.set_ts_correction(correction);
+        }
+        let mut ts = Vec::new();
+        while let Ok(pkt) = pcap.next_packet() {
+            ts.push(pkt.get_timestamp_ns(pcap.is_nanosec()));
+        }
+        ts
+    };
+    assert_eq!(read_ts(None), vec![0, 1_000_000_000, 2_000_000_000]);
+    // The skew is corrected from the first packet on
+    let correction = TsCorrection::new(2_500_000, 1000.0);
+    assert_eq!(
+        read_ts(Some(correction)),
+        vec![2_500_000, 1_003_500_000, 2_004_500_000]
+    );
+    // Timestamps before the epoch are clamped
+    let correction = TsCorrection::new(-1_500_000_000, 0.0);
+    assert_eq!(read_ts(Some(correction)), vec![0, 0, 500_000_000]);
+    fs::remove_file(&path).unwrap();
+
+    // Nanosecond timestamps keep their precision
+    let mut correction = TsCorrection::new(-7, -10.0);
+    let mut header = PcapPacketHeader {
+        ts_sec: 10,
+        ts_usec: 5,
+        caplen: 0,
+        wirelen: 0,
+    };
+    correction.apply(&mut header, true);
+    assert_eq!((header.ts_sec, header.ts_usec), (9, 999_999_998));
+    header.ts_sec = 110;
+    header.ts_usec = 5;
+    correction.apply(&mut header, true);
+    assert_eq!((header.ts_sec, header.ts_usec), (109, 999_000_000 - 2));
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_pcap_repair() {
+    // Write valid records with increasing timestamps and garbage in between
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&0xA1B2C3D4u32.to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&4u16.to_le_bytes());
+    bytes.extend_from_slice(&0i32.to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&65535u32.to_le_bytes());
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    let record = |ts_sec: u32, payload: &[u8]| {
+        let mut rec = Vec::new();
+        rec.extend_from_slice(&ts_sec.to_le_bytes());
+        rec.extend_from_slice(&500u32.to_le_bytes());
+        rec.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        rec.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        rec.extend_from_slice(payload);
+        rec
+    };
+    bytes.extend_from_slice(&record(1000, &[1; 10]));
+    // a record header with a garbage caplen followed by some bytes
+    bytes.extend_from_slice(&[0xff; 20]);
+    bytes.extend_from_slice(&record(1001, &[2; 20]));
+    bytes.extend_from_slice(&record(1002, &[3; 30]));
+    // truncated record header followed by garbage
+    bytes.extend_from_slice(&[0x01, 0x02, 0x03]);
+    bytes.extend_from_slice(&record(1003, &[4; 40]));
+    let path = env::temp_dir().join(format!("p2sh-test-repair-{}.pcap", process::id()));
+    fs::write(&path, bytes).unwrap();
+
+    let pcap = open_test_pcap(&path);
+    let (caplens, kind) = read_test_pcap(&pcap);
+    assert_eq!(caplens, vec![10]);
+    assert_eq!(kind, io::ErrorKind::InvalidData);
+
+    let pcap = open_test_pcap(&path);
+    pcap.set_repair(true);
+    let (caplens, kind) = read_test_pcap(&pcap);
+    assert_eq!(caplens, vec![10, 20, 30, 40]);
+    assert_eq!(kind, io::ErrorKind::UnexpectedEof);
+    assert_eq!(pcap.get_corrupt_count(), 2);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_pcap_big_endian() {
+    for magic in [0xA1B2C3D4u32, 0xA1B23C4D] {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&magic.to_be_bytes());
+        bytes.extend_from_slice(&2u16.to_be_bytes());
+        bytes.extend_from_slice(&4u16.to_be_bytes());
+        bytes.extend_from_slice(&0i32.to_be_bytes());
+        bytes.extend_from_slice(&0u32.to_be_bytes());
+        bytes.extend_from_slice(&65535u32.to_be_bytes());
+        bytes.extend_from_slice(&1u32.to_be_bytes());
+        for caplen in [3u32, 5] {
+            bytes.extend_from_slice(&1000u32.to_be_bytes());
+            bytes.extend_from_slice(&20u32.to_be_bytes());
+            bytes.extend_from_slice(&caplen.to_be_bytes());
+            bytes.extend_from_slice(&60u32.to_be_bytes());
+            bytes.extend_from_slice(&vec![0xaa; caplen as usize]);
+        }
+        let path = env::temp_dir().join(format!("p2sh-test-be-{:x}-{}.pcap", magic, process::id()));
+        fs::write(&path, bytes).unwrap();
+
+        let pcap = open_test_pcap(&path);
+        assert_eq!(pcap.get_magic_number_raw(), magic);
+        assert_eq!(pcap.get_snaplen(), Rc::new(Object::Integer(65535)));
+        assert_eq!(pcap.get_version_minor(), Rc::new(Object::Integer(4)));
+        let pkt = pcap.next_packet().unwrap();
+        assert_eq!(pkt.get_ts_sec(), Rc::new(Object::Integer(1000)));
+        assert_eq!(pkt.get_ts_usec(), Rc::new(Object::Integer(20)));
+        assert_eq!(pkt.get_wirelen(), Rc::new(Object::Integer(60)));
+        let (caplens, kind) = read_test_pcap(&pcap);
+        assert_eq!(caplens, vec![5]);
+        assert_eq!(kind, io::ErrorKind::UnexpectedEof);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_equals_packets() {
+    let records = vec![(4, 4, vec![1, 2, 3, 4]), (4, 4, vec![1, 2, 3, 4])];
+    let path = write_test_pcap("equals", 64, &records);
+    let read = |pcap: &Pcap| Rc::new(Object::Packet(pcap.next_packet().unwrap()));
+
+    let pcap1 = open_test_pcap(&path);
+    let pcap2 = open_test_pcap(&path);
+    let pkt1 = read(&pcap1);
+    let pkt2 = read(&pcap2);
+    // Packets are compared by their contents
+    let equal = builtin_equals(vec![pkt1.clone(), pkt2.clone()]).unwrap();
+    assert_eq!(*equal, Object::Bool(true));
+    let arr = |pkt: &Rc<Object>| Rc::new(Object::Arr(Rc::new(Array::new(vec![pkt.clone()]))));
+    let equal = builtin_equals(vec![arr(&pkt1), arr(&pkt2)]).unwrap();
+    assert_eq!(*equal, Object::Bool(true));
+
+    // The second record differs in the timestamp
+    let pkt3 = read(&pcap2);
+    let equal = builtin_equals(vec![pkt1, pkt3]).unwrap();
+    assert_eq!(*equal, Object::Bool(false));
+    fs::remove_file(&path).unwrap();
+}
+
+// Build a pcapng block in the given byte order with the body padded
+#[cfg(feature = "pcap")]
+#[cfg(test)]
+fn pcapng_block(block_type: u32, body: &[u8], big_endian: bool) -> Vec<u8> {
+    let u32_bytes = |n: u32| {
+        if big_endian {
+            n.to_be_bytes()
+        } else {
+            n.to_le_bytes()
+        }
+    };
+    let padded = (body.len() + 3) & !3;
+    let total_len = 12 + padded as u32;
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&u32_bytes(block_type));
+    bytes.extend_from_slice(&u32_bytes(total_len));
+    bytes.extend_from_slice(body);
+    bytes.resize(bytes.len() + padded - body.len(), 0);
+    bytes.extend_from_slice(&u32_bytes(total_len));
+    bytes
+}
+
+// Write a pcapng file with an interface with the given timestamp resolution
+// and enhanced packet blocks for each of the (timestamp, payload) records.
+// An unknown block and a simple packet block are added at the end.
+#[cfg(feature = "pcap")]
+#[cfg(test)]
+fn write_test_pcapng(
+    name: &str,
+    big_endian: bool,
+    tsresol: u8,
+    records: &[(u64, Vec<u8>)],
+) -> PathBuf {
+    let u16_bytes = |n: u16| {
+        if big_endian {
+            n.to_be_bytes()
+        } else {
+            n.to_le_bytes()
+        }
+    };
+    let u32_bytes = |n: u32| {
+        if big_endian {
+            n.to_be_bytes()
+        } else {
+            n.to_le_bytes()
+        }
+    };
+
+    let mut shb = Vec::new();
+    shb.extend_from_slice(&u32_bytes(0x1A2B3C4D));
+    shb.extend_from_slice(&u16_bytes(1));
+    shb.extend_from_slice(&u16_bytes(0));
+    shb.extend_from_slice(&[0xff; 8]);
+    let mut bytes = pcapng_block(0x0A0D0D0A, &shb, big_endian);
+
+    let mut idb = Vec::new();
+    idb.extend_from_slice(&u16_bytes(1));
+    idb.extend_from_slice(&u16_bytes(0));
+    idb.extend_from_slice(&u32_bytes(0));
+    idb.extend_from_slice(&u16_bytes(9));
+    idb.extend_from_slice(&u16_bytes(1));
+    idb.extend_from_slice(&[tsresol, 0, 0, 0]);
+    idb.extend_from_slice(&[0; 4]);
+    bytes.extend(pcapng_block(1, &idb, big_endian));
+
+    for (ts, payload) in records {
+        let mut epb = Vec::new();
+        epb.extend_from_slice(&u32_bytes(0));
+        epb.extend_from_slice(&u32_bytes((ts >> 32) as u32));
+        epb.extend_from_slice(&u32_bytes(*ts as u32));
+        epb.extend_from_slice(&u32_bytes(payload.len() as u32));
+        epb.extend_from_slice(&u32_bytes(payload.len() as u32 + 10));
+        epb.extend_from_slice(payload);
+        bytes.extend(pcapng_block(6, &epb, big_endian));
+    }
+    // Name resolution block to be skipped
+    bytes.extend(pcapng_block(4, &[0; 4], big_endian));
+    let mut spb = Vec::new();
+    spb.extend_from_slice(&u32_bytes(3));
+    spb.extend_from_slice(&[1, 2, 3]);
+    bytes.extend(pcapng_block(3, &spb, big_endian));
+
+    let path = env::temp_dir().join(format!("p2sh-test-{}-{}.pcapng", name, process::id()));
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_pcapng_read() {
+    // Timestamp resolutions: microseconds, nanoseconds and 2^-10 seconds
+    let tests = [
+        (6u8, 1_500_000u64, 500_000_000i64),
+        (9, 1_500_000_000, 500_000_000),
+        (0x8a, 1536, 500_000_000),
+    ];
+    for big_endian in [false, true] {
+        for (tsresol, ts, nsec) in tests {
+            let records = vec![(ts, vec![0xaa; 5]), (0, vec![0xbb; 14])];
+            let path = write_test_pcapng("read", big_endian, tsresol, &records);
+            let pcap = open_test_pcap(&path);
+            assert_eq!(pcap.format(), PcapFormat::PcapNg);
+            assert!(pcap.is_nanosec());
+            assert_eq!(pcap.get_linktype(), Rc::new(Object::Integer(1)));
+
+            let pkt = pcap.next_packet().unwrap();
+            assert_eq!(pkt.get_ts_sec(), Rc::new(Object::Integer(1)));
+            assert_eq!(pkt.get_ts_usec(), Rc::new(Object::Integer(nsec)));
+            assert_eq!(pkt.get_caplen(), Rc::new(Object::Integer(5)));
+            assert_eq!(pkt.get_wirelen(), Rc::new(Object::Integer(15)));
+            assert_eq!(pkt.payload(), vec![0xaa; 5]);
+            // The unknown block is skipped and the simple packet is read
+            let (caplens, kind) = read_test_pcap(&pcap);
+            assert_eq!(caplens, vec![14, 3]);
+            assert_eq!(kind, io::ErrorKind::UnexpectedEof);
+            fs::remove_file(&path).unwrap();
+        }
+    }
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_pcapng_write() {
+    let records = vec![(1_000_001, vec![1, 2, 3, 4, 5]), (2_000_000, vec![6; 60])];
+    let input = write_test_pcapng("write-in", false, 6, &records);
+    let output = env::temp_dir().join(format!("p2sh-test-write-out-{}.pcapng", process::id()));
+
+    let pcap_in = open_test_pcap(&input);
+    let file = fs::File::create(&output).unwrap();
+    let handle = Rc::new(FileHandle::new_writer(io::BufWriter::new(file)));
+    let pcap_out =
+        Pcap::new_with_format(handle, pcap_in.get_magic_number_raw(), pcap_in.format()).unwrap();
+    while let Ok(pkt) = pcap_in.next_packet() {
+        pcap_out.write_all(pkt).unwrap();
+    }
+    pcap_out.flush().unwrap();
+
+    // Read back the packets written
+    let pcap = open_test_pcap(&output);
+    assert_eq!(pcap.format(), PcapFormat::PcapNg);
+    let pkt = pcap.next_packet().unwrap();
+    assert_eq!(pkt.get_ts_sec(), Rc::new(Object::Integer(1)));
+    assert_eq!(pkt.get_ts_usec(), Rc::new(Object::Integer(1000)));
+    assert_eq!(pkt.payload(), vec![1, 2, 3, 4, 5]);
+    let pkt = pcap.next_packet().unwrap();
+    assert_eq!(pkt.get_ts_sec(), Rc::new(Object::Integer(2)));
+    assert_eq!(pkt.get_wirelen(), Rc::new(Object::Integer(70)));
+    // The simple packet block has no timestamp
+    let pkt = pcap.next_packet().unwrap();
+    assert_eq!(pkt.get_ts_sec(), Rc::new(Object::Integer(0)));
+    assert_eq!(pkt.payload(), vec![1, 2, 3]);
+    assert_eq!(
+        pcap.next_packet().unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+
+    fs::remove_file(&input).unwrap();
+    fs::remove_file(&output).unwrap();
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_pcapng_comments() {
+    use crate::embed::eval_on_packet;
+
+    let records = vec![(1_000_000, vec![1, 2, 3]), (2_000_000, vec![4; 10])];
+    let input = write_test_pcapng("comment-in", false, 6, &records);
+    let first = env::temp_dir().join(format!("p2sh-test-comment-1-{}.pcapng", process::id()));
+    let second = env::temp_dir().join(format!("p2sh-test-comment-2-{}.pcapng", process::id()));
+    let copy = |from: &PathBuf, to: &PathBuf, comment: Option<&str>| {
+        let pcap_in = open_test_pcap(from);
+        let file = fs::File::create(to).unwrap();
+        let handle = Rc::new(FileHandle::new_writer(io::BufWriter::new(file)));
+        let pcap_out =
+            Pcap::new_with_format(handle, pcap_in.get_magic_number_raw(), pcap_in.format())
+                .unwrap();
+        let mut i = 0;
+        while let Ok(pkt) = pcap_in.next_packet() {
+            if i == 0 {
+                if let Some(comment) = comment {
+                    let src = format!("set_comment($0, \"{}\")", comment);
+                    let result = eval_on_packet(&src, pkt.clone()).unwrap();
+                    assert_eq!(result.as_ref(), &Object::Null);
+                }
+            }
+            pcap_out.write_all(pkt).unwrap();
+            i += 1;
+        }
+        pcap_out.flush().unwrap();
+    };
+
+    // The comment is written as an option of the enhanced packet block
+    copy(&input, &first, Some("suspected exfil"));
+    let pcap = open_test_pcap(&first);
+    let pkt = pcap.next_packet().unwrap();
+    assert_eq!(pkt.comment().as_deref(), Some("suspected exfil"));
+    assert_eq!(pkt.payload(), vec![1, 2, 3]);
+    let pkt = pcap.next_packet().unwrap();
+    assert_eq!(pkt.comment(), None);
+    assert_eq!(pkt.payload(), vec![4; 10]);
+
+    // Comments read from a pcapng are kept when written again
+    copy(&first, &second, None);
+    let pkt = open_test_pcap(&second).next_packet().unwrap();
+    assert_eq!(pkt.comment().as_deref(), Some("suspected exfil"));
+    assert_eq!(pkt.get_ts_sec(), Rc::new(Object::Integer(1)));
+
+    let pkt = open_test_pcap(&input).next_packet().unwrap();
+    assert_eq!(
+        eval_on_packet("set_comment($1, \"x\")", pkt.clone()).unwrap_err(),
+        "[line 1] Runtime error: set_comment: first argument should be a packet"
+    );
+    assert_eq!(
+        eval_on_packet("set_comment($0, 1)", pkt.clone()).unwrap_err(),
+        "[line 1] Runtime error: set_comment: comment should be a string or null"
+    );
+    eval_on_packet("set_comment($0, \"x\")", pkt.clone()).unwrap();
+    eval_on_packet("set_comment($0, null)", pkt.clone()).unwrap();
+    assert_eq!(pkt.comment(), None);
+
+    for path in [input, first, second] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_pcapng_invalid() {
+    // A packet block before the interface description
+    let mut shb = Vec::new();
+    shb.extend_from_slice(&0x1A2B3C4Du32.to_le_bytes());
+    shb.extend_from_slice(&[1, 0, 0, 0]);
+    shb.extend_from_slice(&[0xff; 8]);
+    let mut bytes = pcapng_block(0x0A0D0D0A, &shb, false);
+    bytes.extend(pcapng_block(3, &[0; 8], false));
+    let path = env::temp_dir().join(format!("p2sh-test-ng-invalid-{}.pcapng", process::id()));
+    fs::write(&path, &bytes).unwrap();
+    let file = fs::File::open(&path).unwrap();
+    let handle = FileHandle::new_reader(io::BufReader::new(file));
+    let err = Pcap::from_file(Rc::new(handle)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    // Mismatched trailing block total length
+    let records = vec![(0, vec![1, 2, 3, 4])];
+    let path2 = write_test_pcapng("invalid", false, 6, &records);
+    let mut bytes = fs::read(&path2).unwrap();
+    let len = bytes.len();
+    // The trailing length of the last block (the simple packet block)
+    bytes[len - 4] = 0;
+    fs::write(&path2, &bytes).unwrap();
+    let pcap = open_test_pcap(&path2);
+    let (caplens, kind) = read_test_pcap(&pcap);
+    assert_eq!(caplens, vec![4]);
+    assert_eq!(kind, io::ErrorKind::InvalidData);
+
+    fs::remove_file(&path).unwrap();
+    fs::remove_file(&path2).unwrap();
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_pcap_diff() {
+    let packets: Vec<_> = (0..6u8).map(|i| (4, 4, vec![i; 4])).collect();
+    let first = write_test_pcap("diff-a", 65535, &packets);
+    // Drop the second packet, replace the fifth and append one more
+    let mut changed = packets.clone();
+    changed.remove(1);
+    changed[3] = (4, 4, vec![0xff; 4]);
+    changed.push((2, 2, vec![7; 2]));
+    let second = write_test_pcap("diff-b", 65535, &changed);
+
+    let a = summarize_file(first.to_str().unwrap()).unwrap();
+    let b = summarize_file(second.to_str().unwrap()).unwrap();
+    let options = DiffOptions {
+        ignore_time: true,
+        ..Default::default()
+    };
+    let report = options.diff(&a, &a);
+    assert!(report.is_equal());
+    assert_eq!(report.equal, 6);
+
+    let report = options.diff(&a, &b);
+    let entries: Vec<_> = report
+        .entries
+        .iter()
+        .map(|e| match e {
+            DiffEntry::Deleted(idx, _) => format!("-{}", idx),
+            DiffEntry::Inserted(idx, _) => format!("+{}", idx),
+        })
+        .collect();
+    assert_eq!(entries, vec!["-2", "-5", "+4", "+6"]);
+    assert_eq!(report.equal, 4);
+    assert!(report
+        .to_string()
+        .ends_with("packets: 6/6, equal: 4, deleted: 2, inserted: 2\n"));
+
+    // Timestamps shift after the dropped packet
+    let report = DiffOptions::default().diff(&a, &b);
+    assert_eq!(report.equal, 1);
+    let options = DiffOptions {
+        tolerance_ns: 1_000_000_000,
+        ..Default::default()
+    };
+    assert_eq!(options.diff(&a, &b).equal, 4);
+
+    // The same packets 5us later in a pcapng file
+    let records: Vec<_> = (0..6u8)
+        .map(|i| (i as u64 * 1_000_000 + 5, vec![i; 4]))
+        .collect();
+    let third = write_test_pcapng("diff-c", false, 6, &records);
+    let c = summarize_file(third.to_str().unwrap()).unwrap();
+    assert_eq!(DiffOptions::default().diff(&a, &c).equal, 0);
+    let options = DiffOptions {
+        tolerance_ns: 5000,
+        ..Default::default()
+    };
+    let report = options.diff(&a, &c);
+    assert_eq!(report.equal, 6);
+    // The simple packet block at the end of the pcapng file
+    assert_eq!(report.entries, vec![DiffEntry::Inserted(7, c[6])]);
+
+    fs::remove_file(&first).unwrap();
+    fs::remove_file(&second).unwrap();
+    fs::remove_file(&third).unwrap();
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_packet_timestamps() {
+    // Microseconds in a pcap, with the packets a second apart
+    let path = write_test_pcap("ts", 65535, &[(1, 1, vec![0]), (1, 1, vec![1])]);
+    let pcap = open_test_pcap(&path);
+    pcap.next_packet().unwrap();
+    let pkt = pcap.next_packet().unwrap();
+    assert_eq!(pkt.timestamp().nanos(), 1_000_000_000);
+    fs::remove_file(&path).unwrap();
+
+    // Nanoseconds in a pcapng
+    let records = [(1_700_000_000_123_456_789, vec![0u8; 4])];
+    let path = write_test_pcapng("ts", false, 9, &records);
+    let pcap = open_test_pcap(&path);
+    let pkt = pcap.next_packet().unwrap();
+    assert_eq!(
+        pkt.timestamp().to_string(),
+        "2023-11-14T22:13:20.123456789Z"
+    );
+    // A copy of the packet has the same timestamp
+    assert_eq!(pkt.duplicate().timestamp(), pkt.timestamp());
+
+    let ts = crate::embed::eval_on_packet("TS", pkt.clone()).unwrap();
+    assert_eq!(*ts, Object::Timestamp(pkt.timestamp()));
+    let elapsed = crate::embed::eval_on_packet("TS - timestamp(1700000000)", pkt).unwrap();
+    assert_eq!(elapsed.to_string(), "123456789ns");
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_match_report() {
+    let packets: Vec<_> = (0..4u8).map(|i| (4, 4, vec![i; 4])).collect();
+    let path = write_test_pcap("ab", 65535, &packets);
+    let summaries = summarize_file(path.to_str().unwrap()).unwrap();
+
+    let mut report = MatchReport::default();
+    let matched = [(true, true), (true, false), (false, false), (false, true)];
+    for (pkt, (first, second)) in summaries.iter().zip(matched) {
+        report.add(*pkt, first, second);
+    }
+    assert!(!report.is_equal());
+    assert_eq!(
+        report.entries,
+        vec![
+            DiffEntry::Deleted(2, summaries[1]),
+            DiffEntry::Inserted(4, summaries[3])
+        ]
+    );
+    assert!(report
+        .to_string()
+        .ends_with("packets: 4, matched by both: 1, first only: 1, second only: 1\n"));
+
+    // A copy of a packet is changed independently of the packet
+    let pcap = open_test_pcap(&path);
+    let pkt = pcap.next_packet().unwrap();
+    let copy = pkt.duplicate();
+    copy.rawdata.replace(Rc::new(vec![9; 4]));
+    assert_eq!(pkt.payload(), vec![0; 4]);
+    assert_eq!(copy.payload(), vec![9; 4]);
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(all(target_os = "linux", feature = "pcap"))]
+#[test]
+fn test_capture_loopback() {
+    let err = Capture::open("p2sh-no-such-if", 65535).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+    // Capturing needs privileges that may not be available
+    let capture = match Capture::open("lo", 64) {
+        Ok(capture) => capture,
+        Err(err) if err.kind() == io::ErrorKind::PermissionDenied => return,
+        Err(err) => panic!("{}", err),
+    };
+    let pcap = Pcap::from_capture(capture, 64);
+    assert!(pcap.is_nanosec());
+    assert_eq!(pcap.to_string(), "<capture: lo>");
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let payload = vec![0x5a; 100];
+    socket
+        .send_to(&payload, socket.local_addr().unwrap())
+        .unwrap();
+    // Look for the datagram among any other packets on the interface
+    loop {
+        let pkt = pcap.next_packet().unwrap();
+        if pkt.get_wirelen() == Rc::new(Object::Integer(14 + 20 + 8 + 100)) {
+            assert_eq!(pkt.get_caplen(), Rc::new(Object::Integer(64)));
+            assert_eq!(pkt.payload()[42..], payload[..22]);
+            break;
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "pcap"))]
+#[test]
+fn test_capture_duration() {
+    use std::time::{Duration, Instant};
+
+    let capture = match Capture::open("lo", 64) {
+        Ok(capture) => capture,
+        Err(err) if err.kind() == io::ErrorKind::PermissionDenied => return,
+        Err(err) => panic!("{}", err),
+    };
+    // The capture ends once the duration elapses even if no packets arrive
+    let start = Instant::now();
+    capture.set_duration(Duration::from_millis(200));
+    let pcap = Pcap::from_capture(capture, 64);
+    let err = loop {
+        if let Err(err) = pcap.next_packet() {
+            break err;
+        }
+    };
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(err.to_string(), "Capture duration elapsed");
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
+
+// Ethernet frame with an IPv4 header followed by the ports of a transport
+// header, optionally with a VLAN tag
+#[cfg(feature = "pcap")]
+#[cfg(test)]
+fn ipv4_frame(vlan: bool, proto: u8, src: [u8; 4], dst: [u8; 4], ports: (u16, u16)) -> Vec<u8> {
+    let mut frame = vec![0u8; 12];
+    if vlan {
+        frame.extend_from_slice(&[0x81, 0x00, 0x00, 0x0a]);
+    }
+    frame.extend_from_slice(&[0x08, 0x00, 0x45, 0, 0, 28, 0, 0, 0, 0, 64, proto, 0, 0]);
+    frame.extend_from_slice(&src);
+    frame.extend_from_slice(&dst);
+    frame.extend_from_slice(&ports.0.to_be_bytes());
+    frame.extend_from_slice(&ports.1.to_be_bytes());
+    frame.extend_from_slice(&[0; 4]);
+    frame
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_bpf_prefilter() {
+    let tcp = ipv4_frame(false, 6, [10, 0, 0, 1], [192, 168, 1, 2], (40000, 443));
+    let udp = ipv4_frame(true, 17, [192, 168, 1, 2], [8, 8, 8, 8], (5353, 53));
+    let mut ipv6 = vec![0u8; 12];
+    ipv6.extend_from_slice(&[0x86, 0xdd, 0x60, 0, 0, 0, 0, 8, 58, 64]);
+    ipv6.extend_from_slice(&[0; 15]);
+    ipv6.push(1);
+    ipv6.extend_from_slice(&[0xfe, 0x80]);
+    ipv6.extend_from_slice(&[0; 14]);
+    ipv6.extend_from_slice(&[128, 0, 0, 0, 0, 0, 0, 0]);
+    let records: Vec<_> = [tcp, udp, ipv6]
+        .into_iter()
+        .map(|p| (p.len() as u32, p.len() as u32, p))
+        .collect();
+    let path = write_test_pcap("bpf", 65535, &records);
+    let pcap = open_test_pcap(&path);
+    let packets: Vec<_> = (0..3).map(|_| pcap.next_packet().unwrap()).collect();
+
+    let tests = [
+        ("tcp", [true, false, false]),
+        ("udp port 53", [false, true, false]),
+        ("tcp port 80 or 443", [true, false, false]),
+        ("src port 53", [false, false, false]),
+        ("portrange 5000-6000", [false, true, false]),
+        ("host 192.168.1.2", [true, true, false]),
+        ("dst host 192.168.1.2 and not udp", [true, false, false]),
+        ("net 10.0.0.0/8 || dst net 8.8.0.0/16", [true, true, false]),
+        ("ip6 and icmp6 and host ::1", [false, false, true]),
+        ("src net fe80::/10", [false, false, false]),
+        ("vlan", [false, true, false]),
+        ("!(ip && tcp)", [false, true, true]),
+        ("len <= 42 and greater 42", [true, false, false]),
+        ("less 60 and ip", [true, true, false]),
+        ("arp or icmp", [false, false, false]),
+    ];
+    for (expr, expected) in tests {
+        let bpf = BpfExpr::compile(expr).unwrap();
+        let matched: Vec<_> = packets.iter().map(|p| bpf.matches(p, 1)).collect();
+        assert_eq!(matched, expected, "{}", expr);
+    }
+    fs::remove_file(&path).unwrap();
+
+    let errors = [
+        ("", "empty expression"),
+        ("tcp port", "unexpected end of expression"),
+        ("port 80 or", "unexpected end of expression"),
+        ("(tcp", "unexpected end of expression"),
+        ("tcp)", "unexpected ')'"),
+        ("host foo", "invalid host address 'foo'"),
+        ("net 10.0.0.0/33", "invalid network '10.0.0.0/33'"),
+        ("port 65536", "invalid port '65536'"),
+        ("portrange 90-80", "invalid port range '90-80'"),
+        ("len ~ 3", "unexpected '~'"),
+        (
+            "len in 3",
+            "expected a comparison operator after 'len', got 'in'",
+        ),
+        ("tcp & udp", "unexpected '&'"),
+    ];
+    for (expr, expected) in errors {
+        assert_eq!(BpfExpr::compile(expr).unwrap_err(), expected, "{}", expr);
+    }
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_dns_decode() {
+    let mut msg = vec![0x12, 0x34, 0x81, 0x80, 0, 1, 0, 2, 0, 0, 0, 0];
+    msg.extend_from_slice(b"\x07example\x03com\x00");
+    msg.extend_from_slice(&[0, 1, 0, 1]);
+    // CNAME with a compressed name in the data
+    msg.extend_from_slice(&[0xc0, 0x0c, 0, 5, 0, 1, 0, 0, 0, 60, 0, 6]);
+    msg.extend_from_slice(b"\x03www\xc0\x0c");
+    // A record for the name in the data of the CNAME
+    msg.extend_from_slice(&[0xc0, 0x29, 0, 1, 0, 1, 0, 0, 1, 0x2c, 0, 4]);
+    msg.extend_from_slice(&[93, 184, 216, 34]);
+    let mut frame = ipv4_frame(false, 17, [8, 8, 8, 8], [10, 0, 0, 1], (53, 40000));
+    frame.extend_from_slice(&msg);
+    let path = write_test_pcap(
+        "dns",
+        65535,
+        &[(frame.len() as u32, frame.len() as u32, frame)],
+    );
+    let pcap = open_test_pcap(&path);
+    let pkt = Rc::new(Object::Packet(pcap.next_packet().unwrap()));
+    fs::remove_file(&path).unwrap();
+
+    let vm = VM::new(Compiler::new().bytecode());
+    // The message is reachable at depth 4 as it is on port 53
+    let dns = vm.get_inner(&pkt, 4, 1).unwrap();
+    assert!(matches!(dns.as_ref(), Object::Dns(_)));
+    assert_eq!(
+        dns.to_string(),
+        "<response id:4660 qname:example.com answers:2>"
+    );
+    let prop = |p: PacketPropType| vm.exec_prop_expr(dns.clone(), p.into(), None, 1).unwrap();
+    assert_eq!(
+        *prop(PacketPropType::QName),
+        Object::Str("example.com".into())
+    );
+    assert_eq!(*prop(PacketPropType::QType), Object::Integer(1));
+    assert_eq!(*prop(PacketPropType::RCode), Object::Integer(0));
+    assert_eq!(*prop(PacketPropType::Opcode), Object::Integer(0));
+    match prop(PacketPropType::Queries).as_ref() {
+        Object::Arr(queries) => assert_eq!(queries.len(), 1),
+        _ => panic!("expected an array of queries"),
+    }
+    let answers = prop(PacketPropType::Answers);
+    let Object::Arr(answers) = answers.as_ref() else {
+        panic!("expected an array of answers");
+    };
+    let field = |i: usize, key: &str| match answers.get(i).as_ref() {
+        Object::Map(m) => m.get(&Rc::new(Object::Str(key.to_string()))),
+        _ => panic!("expected a map"),
+    };
+    assert_eq!(*field(0, "type"), Object::Integer(5));
+    assert_eq!(*field(0, "data"), Object::Str("www.example.com".into()));
+    assert_eq!(*field(1, "name"), Object::Str("www.example.com".into()));
+    assert_eq!(*field(1, "ttl"), Object::Integer(300));
+    assert_eq!(*field(1, "data"), Object::Str("93.184.216.34".into()));
+    // The message is read only
+    assert!(vm
+        .exec_prop_expr(
+            dns.clone(),
+            PacketPropType::Id.into(),
+            Some(prop(PacketPropType::Id)),
+            1
+        )
+        .is_err());
+
+    // Truncated messages and compression loops are errors
+    let truncated = Rc::new(msg[..20].to_vec());
+    assert!(Dns::from_bytes(truncated, 0).is_err());
+    let mut looped = msg[..12].to_vec();
+    looped.extend_from_slice(&[0xc0, 0x0c, 0, 1, 0, 1]);
+    assert_eq!(
+        Dns::from_bytes(Rc::new(looped), 0).unwrap_err().to_string(),
+        "malformed-dns-name"
+    );
+    // Over TCP, the message is preceded by its length
+    let mut tcp = (msg.len() as u16).to_be_bytes().to_vec();
+    tcp.extend_from_slice(&msg);
+    let dns = Dns::from_tcp_bytes(Rc::new(tcp), 0).unwrap();
+    assert_eq!(
+        *dns.get_qname::<Object>(),
+        Object::Str("example.com".into())
+    );
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_decode_as() {
+    use p2sh_dissect::ports::{parse_port_mapping, AppProto};
+
+    assert_eq!(
+        parse_port_mapping("5353=dns"),
+        Ok((5353, Some(AppProto::Dns)))
+    );
+    assert_eq!(parse_port_mapping("53=none"), Ok((53, None)));
+    assert!(parse_port_mapping("5353").is_err());
+    assert!(parse_port_mapping("dns=5353").is_err());
+    assert!(parse_port_mapping("5353=http").is_err());
+
+    let mut msg = vec![0, 1, 0x01, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+    msg.extend_from_slice(b"\x05local\x00");
+    msg.extend_from_slice(&[0, 1, 0, 1]);
+    let mut frame = ipv4_frame(false, 17, [10, 0, 0, 1], [224, 0, 0, 251], (5353, 5353));
+    frame.extend_from_slice(&msg);
+    let path = write_test_pcap(
+        "decode-as",
+        65535,
+        &[(frame.len() as u32, frame.len() as u32, frame)],
+    );
+    let pcap = open_test_pcap(&path);
+    let pkt = pcap.next_packet().unwrap();
+    fs::remove_file(&path).unwrap();
+
+    // The payload on a port that is not mapped is not decoded
+    let mut vm = VM::new(Compiler::new().bytecode());
+    let obj = Rc::new(Object::Packet(pkt.clone()));
+    assert_eq!(*vm.get_inner(&obj, 4, 1).unwrap(), Object::Null);
+
+    vm.decode_as(5353, Some(AppProto::Dns));
+    let obj = Rc::new(Object::Packet(pkt));
+    let dns = vm.get_inner(&obj, 4, 1).unwrap();
+    assert_eq!(dns.to_string(), "<query id:1 qname:local answers:0>");
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_detect_proto() {
+    use super::pcap::{PcapPacket, PcapPacketHeader};
+    use p2sh_dissect::detect::{detect_proto, Transport};
+    use p2sh_dissect::ports::AppProto;
+
+    let mut msg = vec![0x12, 0x34, 0x01, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+    msg.extend_from_slice(b"\x07example\x03com\x00");
+    msg.extend_from_slice(&[0, 1, 0, 1]);
+    assert_eq!(detect_proto(&msg, Transport::Udp), Some(AppProto::Dns));
+    let mut tcp_msg = (msg.len() as u16).to_be_bytes().to_vec();
+    tcp_msg.extend_from_slice(&msg);
+    assert_eq!(detect_proto(&tcp_msg, Transport::Tcp), Some(AppProto::Dns));
+    let record = [23, 3, 3, 0, 2, 0xde, 0xad];
+    assert_eq!(detect_proto(&record, Transport::Tcp), Some(AppProto::Tls));
+    assert_eq!(detect_proto(&record, Transport::Udp), None);
+    assert_eq!(detect_proto(b"GET / HTTP/1.1\r\n", Transport::Tcp), None);
+    assert_eq!(detect_proto(&[0; 4], Transport::Udp), None);
+
+    let mut frame = ipv4_frame(false, 17, [10, 0, 0, 1], [10, 0, 0, 2], (40000, 5300));
+    frame.extend_from_slice(&msg);
+    let header = PcapPacketHeader {
+        ts_sec: 0,
+        ts_usec: 0,
+        caplen: frame.len() as u32,
+        wirelen: frame.len() as u32,
+    };
+    let pkt = Rc::new(PcapPacket::new(header, frame));
+
+    // The payload on a port that is not mapped is detected only with heuristics
+    let mut vm = VM::new(Compiler::new().bytecode());
+    let udp = vm
+        .get_inner(&Rc::new(Object::Packet(pkt.clone())), 3, 1)
+        .unwrap();
+    let detected = |vm: &VM| {
+        vm.exec_prop_expr(udp.clone(), PacketPropType::DetectedProto.into(), None, 1)
+            .unwrap()
+    };
+    assert_eq!(*detected(&vm), Object::Null);
+    vm.set_heuristics(true);
+    assert_eq!(*detected(&vm), Object::Str("dns".into()));
+    let dns = vm.get_inner(&Rc::new(Object::Packet(pkt)), 4, 1).unwrap();
+    assert!(matches!(dns.as_ref(), Object::Dns(_)));
+
+    // Ports mapped to none are not decoded even with heuristics
+    vm.decode_as(5300, None);
+    assert_eq!(*detected(&vm), Object::Null);
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_truncated_packet() {
+    use super::pcap::{PcapPacket, PcapPacketHeader};
+    use super::variables::BuiltinVarType;
+
+    // The tcp header is cut short by the capture
+    let frame = ipv4_frame(false, 6, [10, 0, 0, 1], [10, 0, 0, 2], (40000, 80));
+    let make_packet = |wirelen: u32| {
+        let header = PcapPacketHeader {
+            ts_sec: 0,
+            ts_usec: 0,
+            caplen: frame.len() as u32,
+            wirelen,
+        };
+        Rc::new(PcapPacket::new(header, frame.clone()))
+    };
+    let vm = VM::new(Compiler::new().bytecode());
+    vm.init_builtin_vars(vec![]);
+    let nt = |vm: &VM| vm.builtinvars.borrow()[BuiltinVarType::NT as usize].clone();
+    let truncated = |pkt: &Rc<PcapPacket>| {
+        let obj = Rc::new(Object::Packet(pkt.clone()));
+        vm.exec_prop_expr(obj, PacketPropType::Truncated.into(), None, 1)
+            .unwrap()
+    };
+
+    let pkt = make_packet(80);
+    assert!(!pkt.is_truncated());
+    // Walking down to the ip layer does not reach the tcp header
+    let obj = Rc::new(Object::Packet(pkt.clone()));
+    assert!(matches!(
+        vm.get_inner(&obj, 2, 1).unwrap().as_ref(),
+        Object::Ipv4(_)
+    ));
+    assert!(!pkt.is_truncated());
+    assert_eq!(*nt(&vm), Object::Null);
+    let tcp = vm.get_inner(&obj, 3, 1).unwrap();
+    assert!(matches!(tcp.as_ref(), Object::Err(_)));
+    assert!(pkt.is_truncated());
+    assert_eq!(*nt(&vm), Object::Integer(1));
+    // A packet is counted once
+    assert_eq!(*truncated(&pkt), Object::Bool(true));
+    assert_eq!(*nt(&vm), Object::Integer(1));
+
+    // The property walks all the layers
+    let pkt = make_packet(80);
+    assert_eq!(*truncated(&pkt), Object::Bool(true));
+    assert_eq!(*nt(&vm), Object::Integer(2));
+    // A malformed packet that was captured in full is not truncated
+    let pkt = make_packet(frame.len() as u32);
+    assert_eq!(*truncated(&pkt), Object::Bool(false));
+    assert_eq!(*nt(&vm), Object::Integer(2));
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_tunnels() {
+    use super::pcap::{PcapPacket, PcapPacketHeader};
+
+    let make_packet = |frame: Vec<u8>| {
+        let header = PcapPacketHeader {
+            ts_sec: 0,
+            ts_usec: 0,
+            caplen: frame.len() as u32,
+            wirelen: frame.len() as u32,
+        };
+        Rc::new(Object::Packet(Rc::new(PcapPacket::new(header, frame))))
+    };
+    let inner = ipv4_frame(false, 17, [172, 16, 0, 1], [172, 16, 0, 2], (1000, 2000));
+    let vm = VM::new(Compiler::new().bytecode());
+    let prop = |obj: &Rc<Object>, prop: PacketPropType| {
+        vm.exec_prop_expr(obj.clone(), prop.into(), None, 1)
+            .unwrap()
+    };
+    let src = Object::Str("172.16.0.1".to_string());
+
+    // An ethernet frame in a VXLAN header with the VNI 5000
+    let mut frame = ipv4_frame(false, 17, [10, 0, 0, 1], [10, 0, 0, 2], (40000, 4789));
+    frame.extend_from_slice(&[0x08, 0, 0, 0, 0, 0x13, 0x88, 0]);
+    frame.extend_from_slice(&inner);
+    let pkt = make_packet(frame);
+    let vxlan = vm.get_inner(&pkt, 4, 1).unwrap();
+    assert!(matches!(vxlan.as_ref(), Object::Tunnel(_)));
+    assert_eq!(*prop(&vxlan, PacketPropType::Vni), Object::Integer(5000));
+    assert!(vm
+        .exec_prop_expr(vxlan.clone(), PacketPropType::Key.into(), None, 1)
+        .is_err());
+    let eth = prop(&vxlan, PacketPropType::Inner);
+    assert!(matches!(eth.as_ref(), Object::Eth(_)));
+    // The layers of the inner packet follow the tunnel
+    let ipv4 = vm.get_inner(&pkt, 6, 1).unwrap();
+    assert_eq!(*prop(&ipv4, PacketPropType::Src), src);
+    let udp = vm.get_inner(&pkt, 7, 1).unwrap();
+    assert_eq!(*prop(&udp, PacketPropType::DstPort), Object::Integer(2000));
+
+    // An ip packet in a GRE header with the key 42
+    let mut frame = ipv4_frame(false, 47, [10, 0, 0, 1], [10, 0, 0, 2], (0x2000, 0x0800));
+    frame.truncate(frame.len() - 4);
+    frame.extend_from_slice(&42u32.to_be_bytes());
+    frame.extend_from_slice(&inner[14..]);
+    let pkt = make_packet(frame);
+    let gre = vm.get_inner(&pkt, 3, 1).unwrap();
+    assert!(matches!(gre.as_ref(), Object::Tunnel(_)));
+    assert_eq!(*prop(&gre, PacketPropType::Key), Object::Integer(42));
+    assert_eq!(
+        *prop(&gre, PacketPropType::EtherType),
+        Object::Integer(0x0800)
+    );
+    let ipv4 = vm.get_inner(&pkt, 4, 1).unwrap();
+    assert_eq!(*prop(&ipv4, PacketPropType::Src), src);
+    // Tunnel headers are read only
+    let setval = Some(Rc::new(Object::Integer(1)));
+    assert!(vm
+        .exec_prop_expr(gre, PacketPropType::Key.into(), setval, 1)
+        .is_err());
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_dissect_profile() {
+    use super::pcap::{PcapPacket, PcapPacketHeader};
+
+    let mut frame = ipv4_frame(false, 6, [10, 0, 0, 1], [10, 0, 0, 2], (40000, 80));
+    frame.extend_from_slice(&[0, 0, 0, 0, 0x50, 0x18, 0xff, 0xff, 0, 0, 0, 0]);
+    let header = PcapPacketHeader {
+        ts_sec: 0,
+        ts_usec: 0,
+        caplen: frame.len() as u32,
+        wirelen: frame.len() as u32,
+    };
+    let pkt = Rc::new(Object::Packet(Rc::new(PcapPacket::new(header, frame))));
+    let mut vm = VM::new(Compiler::new().bytecode());
+    assert!(vm.dissect_profile().is_none());
+    vm.enable_dissect_profile();
+    assert!(matches!(
+        vm.get_inner(&pkt, 3, 1).unwrap().as_ref(),
+        Object::Tcp(_)
+    ));
+    // The layers already parsed are not parsed again
+    vm.get_inner(&pkt, 3, 1).unwrap();
+    let profile = vm.dissect_profile().unwrap();
+    let mut calls: Vec<_> = profile.stats().iter().map(|s| (s.name, s.calls)).collect();
+    calls.sort();
+    assert_eq!(calls, vec![("eth", 1), ("ipv4", 1), ("tcp", 1)]);
+    let report = profile.to_string();
+    assert!(report.starts_with("dissector"));
+    assert!(report.lines().last().unwrap().starts_with("total"));
+}
+
+#[test]
+fn test_payload_search() {
+    use super::search::{find, find_byte};
+
+    // Matches on either side of the 16 and 32 byte blocks
+    let payload: Vec<u8> = (0..100u8).map(|i| i % 50).collect();
+    for i in 0..50u8 {
+        assert_eq!(find_byte(&payload, i), Some(i as usize));
+        assert_eq!(find_byte(&payload[37..], i), Some((i as usize + 13) % 50));
+    }
+    assert_eq!(find_byte(&payload, 50), None);
+    assert_eq!(find(&payload, &[31, 32, 33]), Some(31));
+    assert_eq!(find(&payload[40..], &[49, 0, 1]), Some(9));
+    assert_eq!(find(&payload, &[49, 1]), None);
+    // The first byte recurs before the match
+    assert_eq!(find(b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaab", b"ab"), Some(32));
+    assert_eq!(find(b"ab", b"abc"), None);
+    assert_eq!(find(b"", b""), Some(0));
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_packet_rewrite() {
+    use super::pcap::{PcapPacket, PcapPacketHeader};
+    use p2sh_dissect::checksum::internet_checksum;
+
+    let make_packet = |frame: Vec<u8>| {
+        let header = PcapPacketHeader {
+            ts_sec: 0,
+            ts_usec: 0,
+            caplen: frame.len() as u32,
+            wirelen: frame.len() as u32,
+        };
+        Rc::new(PcapPacket::new(header, frame))
+    };
+    let vm = VM::new(Compiler::new().bytecode());
+    vm.init_builtin_vars(vec![]);
+    let auto = Rc::new(Object::Str("auto".to_string()));
+    let set = |obj: &Rc<Object>, prop: PacketPropType, val: Rc<Object>| {
+        vm.exec_prop_expr(obj.clone(), prop.into(), Some(val), 1)
+            .unwrap();
+    };
+
+    // An ipv4 header with options and a tcp header with the urgent pointer
+    let mut frame = vec![0u8; 12];
+    frame.extend_from_slice(&[0x08, 0x00, 0x46, 0, 0, 48, 0, 0, 0, 0, 64, 6, 0, 0]);
+    frame.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2, 1, 1, 1, 0]);
+    frame.extend_from_slice(&[0x9c, 0x40, 0, 80, 0, 0, 0, 1, 0, 0, 0, 0]);
+    frame.extend_from_slice(&[0x50, 0x38, 0xff, 0xff, 0, 0, 0, 7, 0xde, 0xad]);
+    let pkt = make_packet(frame.clone());
+    let obj = Rc::new(Object::Packet(pkt.clone()));
+    let ipv4 = vm.get_inner(&obj, 2, 1).unwrap();
+    let tcp = vm.get_inner(&obj, 3, 1).unwrap();
+    // The layers parsed are written back as they were
+    assert_eq!(pkt.serialize().1, frame);
+
+    set(
+        &ipv4,
+        PacketPropType::Src,
+        Rc::new(Object::Str("192.168.1.1".into())),
+    );
+    set(&ipv4, PacketPropType::TotalLength, auto.clone());
+    set(&ipv4, PacketPropType::Checksum, auto.clone());
+    set(&tcp, PacketPropType::Checksum, auto.clone());
+    let (header, data) = pkt.serialize();
+    assert_eq!(header.caplen as usize, frame.len());
+    assert_eq!(data[26..30], [192, 168, 1, 1]);
+    assert_eq!(u16::from_be_bytes([data[16], data[17]]), 46);
+    // The checksum of data that includes a valid checksum is zero
+    assert_eq!(internet_checksum(&[&data[14..38]]), 0);
+    let segment = &data[38..];
+    let pseudo = [
+        &data[26..34],
+        &(segment.len() as u32).to_be_bytes(),
+        &[0, 0, 0, 6],
+    ];
+    assert_eq!(
+        internet_checksum(&[pseudo[0], pseudo[1], pseudo[2], segment]),
+        0
+    );
+    // Setting a value stops computing it
+    set(
+        &ipv4,
+        PacketPropType::Checksum,
+        Rc::new(Object::Integer(0x1234)),
+    );
+    assert_eq!(pkt.serialize().1[24..26], [0x12, 0x34]);
+
+    // The udp length is computed from the payload
+    let mut frame = ipv4_frame(false, 17, [10, 0, 0, 1], [10, 0, 0, 2], (5000, 5001));
+    frame.extend_from_slice(b"hello");
+    let pkt = make_packet(frame);
+    let obj = Rc::new(Object::Packet(pkt.clone()));
+    let udp = vm.get_inner(&obj, 3, 1).unwrap();
+    set(&udp, PacketPropType::Length, auto.clone());
+    set(&udp, PacketPropType::Checksum, auto);
+    let data = pkt.serialize().1;
+    assert_eq!(u16::from_be_bytes([data[38], data[39]]), 13);
+    let segment = &data[34..];
+    let pseudo = [
+        &data[26..34],
+        &(segment.len() as u32).to_be_bytes(),
+        &[0, 0, 0, 17],
+    ];
+    assert_eq!(
+        internet_checksum(&[pseudo[0], pseudo[1], pseudo[2], segment]),
+        0
+    );
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_tls_client_hello() {
+    use p2sh_dissect::tls::Tls;
+
+    let mut hello = vec![0x03, 0x03];
+    hello.extend_from_slice(&[0; 32]);
+    hello.push(0);
+    // A GREASE cipher suite is left out of the fingerprint
+    hello.extend_from_slice(&[0, 6, 0x0a, 0x0a, 0x13, 0x01, 0xc0, 0x2f]);
+    hello.extend_from_slice(&[1, 0]);
+    let mut ext = vec![0x0a, 0x0a, 0, 0];
+    ext.extend_from_slice(&[0, 0, 0, 16, 0, 14, 0, 0, 11]);
+    ext.extend_from_slice(b"example.com");
+    ext.extend_from_slice(&[0, 10, 0, 6, 0, 4, 0, 0x1d, 0, 0x17]);
+    ext.extend_from_slice(&[0, 11, 0, 2, 1, 0]);
+    ext.extend_from_slice(&[0, 16, 0, 14, 0, 12, 2]);
+    ext.extend_from_slice(b"h2\x08http/1.1");
+    hello.extend_from_slice(&(ext.len() as u16).to_be_bytes());
+    hello.extend_from_slice(&ext);
+    let mut handshake = vec![1, 0];
+    handshake.extend_from_slice(&(hello.len() as u16).to_be_bytes());
+    handshake.extend_from_slice(&hello);
+    let mut record = vec![22, 3, 1];
+    record.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+    record.extend_from_slice(&handshake);
+
+    let mut frame = ipv4_frame(false, 6, [10, 0, 0, 1], [93, 184, 216, 34], (50000, 443));
+    frame.extend_from_slice(&[0, 0, 0, 0, 0x50, 0x18, 0xff, 0xff, 0, 0, 0, 0]);
+    frame.extend_from_slice(&record);
+    let path = write_test_pcap(
+        "tls",
+        65535,
+        &[(frame.len() as u32, frame.len() as u32, frame)],
+    );
+    let pcap = open_test_pcap(&path);
+    let pkt = Rc::new(Object::Packet(pcap.next_packet().unwrap()));
+    fs::remove_file(&path).unwrap();
+
+    let vm = VM::new(Compiler::new().bytecode());
+    // The record is reachable at depth 4 as it is on port 443
+    let tls = vm.get_inner(&pkt, 4, 1).unwrap();
+    assert_eq!(tls.to_string(), "<client-hello sni:example.com ciphers:3>");
+    let prop = |p: PacketPropType| vm.exec_prop_expr(tls.clone(), p.into(), None, 1).unwrap();
+    assert_eq!(*prop(PacketPropType::ContentType), Object::Integer(22));
+    assert_eq!(*prop(PacketPropType::Handshake), Object::Integer(1));
+    assert_eq!(*prop(PacketPropType::Version), Object::Integer(0x0303));
+    assert_eq!(
+        *prop(PacketPropType::Sni),
+        Object::Str("example.com".into())
+    );
+    assert_eq!(
+        prop(PacketPropType::Alpn).to_string(),
+        r#"["h2", "http/1.1"]"#
+    );
+    assert_eq!(
+        prop(PacketPropType::Ciphers).to_string(),
+        "[2570, 4865, 49199]"
+    );
+    assert_eq!(
+        *prop(PacketPropType::Ja3),
+        Object::Str("314abbbcca48548317336aed70894d82".into())
+    );
+
+    // Other records have no client hello fields
+    let tls = Tls::from_bytes(Rc::new(vec![23, 3, 3, 0, 2, 0xde, 0xad]), 0).unwrap();
+    assert_eq!(tls.to_string(), "<tls type:23 version:771>");
+    assert_eq!(*tls.get_sni::<Object>(), Object::Null);
+    assert_eq!(*tls.get_ja3::<Object>(), Object::Null);
+    // A truncated client hello is an error
+    assert!(Tls::from_bytes(Rc::new(record[..60].to_vec()), 0).is_err());
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_eval_on_packet() {
+    use super::pcap::{PcapPacket, PcapPacketHeader};
+    use crate::embed::{eval_on_packet, PacketExpr};
+
+    let make_packet = |frame: Vec<u8>| {
+        let header = PcapPacketHeader {
+            ts_sec: 1,
+            ts_usec: 2,
+            caplen: frame.len() as u32,
+            wirelen: frame.len() as u32,
+        };
+        Rc::new(PcapPacket::new(header, frame))
+    };
+    let quic = make_packet(ipv4_frame(
+        false,
+        17,
+        [10, 0, 0, 1],
+        [192, 168, 1, 2],
+        (40000, 443),
+    ));
+    let dns = make_packet(ipv4_frame(
+        false,
+        17,
+        [10, 0, 0, 3],
+        [8, 8, 8, 8],
+        (5353, 53),
+    ));
+
+    let result = eval_on_packet("($2).dst", quic.clone()).unwrap();
+    assert_eq!(*result, Object::Str("192.168.1.2".into()));
+    let result = eval_on_packet("PL + 1", quic.clone()).unwrap();
+    assert_eq!(*result, Object::Integer(43));
+
+    // Compile once and evaluate on many packets
+    let result = eval_on_packet("($3).detected_proto", dns.clone()).unwrap();
+    assert_eq!(*result, Object::Str("dns".into()));
+    let mut expr = PacketExpr::compile("($3).dstport").unwrap();
+    assert_eq!(*expr.eval(quic.clone()).unwrap(), Object::Integer(443));
+    assert_eq!(*expr.eval(dns.clone()).unwrap(), Object::Integer(53));
+
+    // A failed evaluation does not affect later ones
+    let mut expr = PacketExpr::compile("($2).src + 1").unwrap();
+    assert!(expr.eval(quic.clone()).is_err());
+    assert!(expr.eval(dns).is_err());
+
+    assert_eq!(
+        PacketExpr::compile("let a = 1;").err().unwrap(),
+        "expected a single expression"
+    );
+    assert!(PacketExpr::compile("($2).dst +").is_err());
+    assert!(eval_on_packet("undefined_name", quic).is_err());
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_packet_bytes() {
+    use super::pcap::{PcapPacket, PcapPacketHeader};
+    use crate::embed::eval_on_packet;
+
+    let frame = ipv4_frame(false, 17, [10, 0, 0, 1], [10, 0, 0, 2], (1024, 53));
+    let header = PcapPacketHeader {
+        ts_sec: 1,
+        ts_usec: 2,
+        caplen: frame.len() as u32,
+        wirelen: frame.len() as u32,
+    };
+    let pkt = Rc::new(PcapPacket::new(header, frame.clone()));
+
+    // Packets yield the frame without the record header
+    let result = eval_on_packet("bytes($1)", pkt.clone()).unwrap();
+    assert_eq!(*result, Object::Bytes(frame.clone()));
+    let result = eval_on_packet("slice($1, 26, 4)", pkt.clone()).unwrap();
+    assert_eq!(*result, Object::Bytes(vec![10, 0, 0, 1]));
+    let result = eval_on_packet("bytes(($3))", pkt.clone()).unwrap();
+    assert_eq!(*result, Object::Bytes(frame[34..].to_vec()));
+
+    // Packets are sequences of the bytes of the frame like bytes objects
+    let tests = [
+        ("len($0)", "42"),
+        (
+            "[first($0), last($0), $0[12], $0[13]]",
+            "[0x0, 0x0, 0x8, 0x0]",
+        ),
+        ("len(rest($0))", "41"),
+        ("slice($0, 12, 2)", r#"b"\x08\x00""#),
+    ];
+    for (src, expected) in tests {
+        let result = eval_on_packet(src, pkt.clone()).unwrap();
+        assert_eq!(result.to_string(), expected, "{}", src);
+    }
+    // A for loop goes over the bytes of the frame
+    let source = "let n = 0; for i, b in $0 { n = n + int(b); } n";
+    let mut parser = crate::parser::Parser::new(crate::scanner::Scanner::new(source));
+    let mut compiler = Compiler::new();
+    compiler.compile(parser.parse_program()).unwrap();
+    let mut vm = VM::new(compiler.bytecode());
+    vm.set_curr_pkt(pkt.clone());
+    vm.run().unwrap();
+    let total: i64 = frame.iter().map(|b| *b as i64).sum();
+    assert_eq!(*vm.last_popped(), Object::Integer(total));
+    let errors = [
+        ("$0[42]", "IndexError: packet index out of range."),
+        ("$0[0] = byte(1)", "IndexError: packet cannot be modified."),
+        ("push($0, byte(1))", "push: packet cannot be modified"),
+    ];
+    for (src, expected) in errors {
+        let err = eval_on_packet(src, pkt.clone()).unwrap_err();
+        assert!(err.contains(expected), "{}: {}", src, err);
+    }
+
+    let result = eval_on_packet("hexdump(($2))", pkt).unwrap();
+    let expected = "\
+0000  45 00 00 1c 00 00 00 00  40 11 00 00 0a 00 00 01   E.......@.......
+0010  0a 00 00 02 04 00 00 35  00 00 00 00               .......5....";
+    assert_eq!(*result, Object::Str(expected.into()));
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_pcap_demux() {
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    let tcp = ipv4_frame(false, 6, [10, 0, 0, 1], [10, 0, 0, 2], (40000, 80));
+    let udp = ipv4_frame(false, 17, [10, 0, 0, 1], [10, 0, 0, 2], (40000, 53));
+    let records: Vec<_> = [&tcp, &udp, &udp, &tcp, &udp]
+        .iter()
+        .map(|f| (f.len() as u32, f.len() as u32, f.to_vec()))
+        .collect();
+    let input = write_test_pcap("demux-in", 65535, &records);
+    let output = |name: &str| {
+        env::temp_dir().join(format!("p2sh-test-demux-{}-{}.pcap", name, process::id()))
+    };
+    let (out_tcp, out_udp) = (output("tcp"), output("udp"));
+
+    let source = format!(
+        r#"
+        let f = pcap_open("{}");
+        let out = map {{6: pcap_open("{}", 1), 17: pcap_open("{}", 101)}};
+        let pkt = null;
+        while pkt = pcap_read_next(f) {{
+            write(out[pkt.eth.ipv4.proto], pkt);
+        }}
+        flush(out[6]);
+        flush(out[17]);
+        "#,
+        input.display(),
+        out_tcp.display(),
+        out_udp.display()
+    );
+    let mut parser = Parser::new(Scanner::new(&source));
+    let program = parser.parse_program();
+    assert!(parser.parse_errors().is_empty());
+    let mut compiler = Compiler::new();
+    compiler.compile(program).unwrap();
+    let mut vm = VM::new(compiler.bytecode());
+    vm.run().unwrap();
+
+    // The packets are identified by their timestamps which are the indices
+    let read_ts = |path: &PathBuf, linktype: u32| {
+        let pcap = open_test_pcap(path);
+        assert_eq!(pcap.get_linktype_raw(), linktype);
+        let mut ts = Vec::new();
+        while let Ok(pkt) = pcap.next_packet() {
+            ts.push(pkt.get_ts_sec());
+        }
+        ts
+    };
+    let ts =
+        |secs: &[i64]| -> Vec<_> { secs.iter().map(|s| Rc::new(Object::Integer(*s))).collect() };
+    assert_eq!(read_ts(&out_tcp, 1), ts(&[0, 3]));
+    assert_eq!(read_ts(&out_udp, 101), ts(&[1, 2, 4]));
+
+    for path in [input, out_tcp, out_udp] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_typed_props() {
+    use super::pcap::{PcapPacket, PcapPacketHeader};
+    use crate::object::hmap::HMap;
+    use p2sh_dissect::addr::Addr;
+
+    let mut frame = ipv4_frame(false, 6, [10, 0, 0, 1], [10, 0, 0, 2], (40000, 80));
+    frame.extend_from_slice(&[0, 0, 0, 0, 0x50, 0x12, 0xff, 0xff, 0, 0, 0, 0]);
+    frame.extend_from_slice(b"hi");
+    let header = PcapPacketHeader {
+        ts_sec: 0,
+        ts_usec: 0,
+        caplen: frame.len() as u32,
+        wirelen: frame.len() as u32,
+    };
+    let pkt = Rc::new(Object::Packet(Rc::new(PcapPacket::new(header, frame))));
+    let mut vm = VM::new(Compiler::new().bytecode());
+    let ipv4 = vm.get_inner(&pkt, 2, 1).unwrap();
+    let tcp = vm.get_inner(&pkt, 3, 1).unwrap();
+    let prop = |vm: &VM, obj: &Rc<Object>, prop: PacketPropType| {
+        vm.exec_prop_expr(obj.clone(), prop.into(), None, 1)
+            .unwrap()
+    };
+
+    // Addresses compare equal to their textual form, also as map keys
+    let src = prop(&vm, &ipv4, PacketPropType::Src);
+    assert_eq!(*src, Object::Addr(Addr::parse("10.0.0.1").unwrap()));
+    assert_eq!(*src, Object::Str("10.0.0.1".into()));
+    assert_eq!(src.to_string(), "10.0.0.1");
+    let map = HMap::new(HashMap::new());
+    map.insert(
+        Rc::new(Object::Str("10.0.0.1".into())),
+        Rc::new(Object::Integer(1)),
+    );
+    assert_eq!(*map.get(&src), Object::Integer(1));
+    let mac = prop(&vm, &vm.get_inner(&pkt, 1, 1).unwrap(), PacketPropType::Src);
+    assert!(matches!(*mac, Object::Addr(Addr::Mac(_))));
+    // An address of another object can be assigned
+    let dst = prop(&vm, &ipv4, PacketPropType::Dst);
+    vm.exec_prop_expr(ipv4.clone(), PacketPropType::Src.into(), Some(dst), 1)
+        .unwrap();
+    assert_eq!(
+        *prop(&vm, &ipv4, PacketPropType::Src),
+        Object::Str("10.0.0.2".into())
+    );
+
+    // Flags are booleans that can be set
+    assert_eq!(*prop(&vm, &tcp, PacketPropType::Syn), Object::Bool(true));
+    assert_eq!(
+        *prop(&vm, &tcp, PacketPropType::AckFlag),
+        Object::Bool(true)
+    );
+    assert_eq!(*prop(&vm, &tcp, PacketPropType::Fin), Object::Bool(false));
+    let set_fin = Some(Rc::new(Object::Bool(true)));
+    vm.exec_prop_expr(tcp.clone(), PacketPropType::Fin.into(), set_fin, 1)
+        .unwrap();
+    assert_eq!(
+        *prop(&vm, &tcp, PacketPropType::Flags),
+        Object::Integer(0x5013)
+    );
+    assert_eq!(*prop(&vm, &ipv4, PacketPropType::Df), Object::Bool(false));
+
+    let payload = prop(&vm, &tcp, PacketPropType::Payload);
+    assert_eq!(*payload, Object::Bytes(b"hi".to_vec()));
+
+    // Plain values are returned in the legacy mode
+    vm.set_legacy_props(true);
+    let payload = prop(&vm, &tcp, PacketPropType::Payload);
+    assert!(matches!(*payload, Object::Arr(_)));
+    assert_eq!(*payload, Object::Bytes(b"hi".to_vec()));
+    assert!(matches!(
+        *prop(&vm, &ipv4, PacketPropType::Dst),
+        Object::Str(_)
+    ));
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_packet_props() {
+    use super::pcap::{PcapPacket, PcapPacketHeader};
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    let frame = ipv4_frame(false, 17, [10, 0, 0, 1], [10, 0, 0, 2], (40000, 9999));
+    let header = PcapPacketHeader {
+        ts_sec: 0,
+        ts_usec: 0,
+        caplen: frame.len() as u32,
+        wirelen: frame.len() as u32,
+    };
+    let pkt = Rc::new(Object::Packet(Rc::new(PcapPacket::new(
+        header,
+        frame.clone(),
+    ))));
+    let vm = VM::new(Compiler::new().bytecode());
+    let props = |pattern: &str| {
+        let pattern = Rc::new(Object::Str(pattern.to_string()));
+        match vm.packet_props(&pkt, &pattern, 1).unwrap().as_ref() {
+            Object::Map(map) => map.pairs.borrow().clone(),
+            obj => panic!("expected a map, got {}", obj),
+        }
+    };
+    let key = |path: &str| Rc::new(Object::Str(path.to_string()));
+
+    // All the properties of a layer, leaving out the inner layers
+    #[allow(clippy::mutable_key_type)]
+    let udp = props("udp.*");
+    assert_eq!(*udp[&key("udp.srcport")], Object::Integer(40000));
+    assert_eq!(*udp[&key("udp.dstport")], Object::Integer(9999));
+    assert!(udp.contains_key(&key("udp.payload")));
+    assert!(!udp.contains_key(&key("udp.dns")));
+    assert!(udp.keys().all(|k| k.to_string().starts_with("\"udp.")));
+
+    // Properties across the layers, skipping the absent ones
+    #[allow(clippy::mutable_key_type)]
+    let src = props("*.src");
+    assert_eq!(*src[&key("ipv4.src")], Object::Str("10.0.0.1".into()));
+    assert!(src.contains_key(&key("eth.src")));
+    assert!(!src.contains_key(&key("ipv6.src")));
+    assert!(props("tcp.*").is_empty());
+
+    // The builtin as called from a script
+    let len = frame.len() as u32;
+    let input = write_test_pcap("props", 65535, &[(len, len, frame)]);
+    let source = format!(
+        r#"
+        let f = pcap_open("{}");
+        let p = props(pcap_read_next(f), "ipv4.*");
+        assert(p["ipv4.dst"] == "10.0.0.2");
+        assert(p["ipv4.proto"] == 17);
+        "#,
+        input.display()
+    );
+    let mut parser = Parser::new(Scanner::new(&source));
+    let program = parser.parse_program();
+    assert!(parser.parse_errors().is_empty());
+    let mut compiler = Compiler::new();
+    compiler.compile(program).unwrap();
+    let mut vm = VM::new(compiler.bytecode());
+    vm.run().unwrap();
+    fs::remove_file(input).unwrap();
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_packet_to_map() {
+    use super::pcap::{PcapPacket, PcapPacketHeader};
+    use crate::builtins::json::to_json;
+
+    let frame = ipv4_frame(false, 17, [10, 0, 0, 1], [10, 0, 0, 2], (40000, 9999));
+    let header = PcapPacketHeader {
+        ts_sec: 7,
+        ts_usec: 0,
+        caplen: frame.len() as u32,
+        wirelen: frame.len() as u32,
+    };
+    let pkt = Rc::new(Object::Packet(Rc::new(PcapPacket::new(header, frame))));
+    let vm = VM::new(Compiler::new().bytecode());
+    let map = vm.packet_map(&pkt, 1).unwrap();
+    let get = |obj: &Rc<Object>, key: &str| match obj.as_ref() {
+        Object::Map(map) => map.get(&Rc::new(Object::Str(key.to_string()))),
+        obj => panic!("expected a map, got {}", obj),
+    };
+
+    // The layers are nested maps of their properties
+    assert_eq!(*get(&map, "sec"), Object::Integer(7));
+    let eth = get(&map, "eth");
+    let ipv4 = get(&eth, "ipv4");
+    assert_eq!(*get(&ipv4, "src"), Object::Str("10.0.0.1".into()));
+    let udp = get(&ipv4, "udp");
+    assert_eq!(*get(&udp, "dstport"), Object::Integer(9999));
+    // Layers missing from the packet are left out
+    assert_eq!(*get(&ipv4, "tcp"), Object::Null);
+    assert_eq!(*get(&eth, "vlan"), Object::Null);
+
+    let json = to_json(&udp).unwrap();
+    assert!(json.starts_with("{\"checksum\":0,\"dstport\":9999,"));
+    assert!(json.contains("\"payload\":\"\""));
+    assert!(to_json(&map).unwrap().contains("\"src\":\"10.0.0.1\""));
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_packet_decode() {
+    use super::pcap::{PcapPacket, PcapPacketHeader};
+
+    // An ip packet in a GRE header, so that ipv4 occurs twice
+    let inner = ipv4_frame(false, 17, [192, 168, 0, 1], [192, 168, 0, 2], (1000, 53));
+    let mut frame = ipv4_frame(false, 47, [10, 0, 0, 1], [10, 0, 0, 2], (0x2000, 0x0800));
+    frame.truncate(frame.len() - 4);
+    frame.extend_from_slice(&42u32.to_be_bytes());
+    frame.extend_from_slice(&inner[14..]);
+    let header = PcapPacketHeader {
+        ts_sec: 7,
+        ts_usec: 0,
+        caplen: frame.len() as u32,
+        wirelen: frame.len() as u32,
+    };
+    let pkt = Rc::new(PcapPacket::new(header, frame));
+
+    let layers = crate::embed::eval_on_packet(r#"decode($0)["layers"]"#, pkt.clone()).unwrap();
+    assert_eq!(
+        layers.to_string(),
+        r#"["packet", "eth", "ipv4", "gre", "ipv4_2", "udp"]"#
+    );
+    let tests = [
+        (r#"decode($0)["packet"]["sec"]"#, "7"),
+        (r#"decode($0)["ipv4"]["src"]"#, "10.0.0.1"),
+        (r#"decode($0)["ipv4_2"]["src"]"#, "192.168.0.1"),
+        (r#"decode($0)["gre"]["key"]"#, "42"),
+        (
+            r#"decode($1)["layers"]"#,
+            r#"["eth", "ipv4", "gre", "ipv4_2", "udp"]"#,
+        ),
+        // The layers are not nested
+        (r#"contains(decode($0)["eth"], "ipv4")"#, "false"),
+    ];
+    for (src, expected) in tests {
+        let obj = crate::embed::eval_on_packet(src, pkt.clone()).unwrap();
+        assert_eq!(obj.to_string(), expected, "{}", src);
+    }
+    let json = crate::embed::eval_on_packet(r#"decode($0, "json")"#, pkt.clone()).unwrap();
+    let Object::Str(json) = json.as_ref() else {
+        panic!("expected a string, got {}", json);
+    };
+    assert!(json.contains(r#""gre":{"#), "{}", json);
+    assert!(json.contains(r#""layers":["packet","eth","ipv4","gre","ipv4_2","udp"]"#));
+
+    let errors = [
+        ("decode(1)", "decode: unsupported argument"),
+        (
+            r#"decode($0, "xml")"#,
+            r#"decode: format must be "map" or "json""#,
+        ),
+    ];
+    for (src, expected) in errors {
+        let err = crate::embed::eval_on_packet(src, pkt.clone()).unwrap_err();
+        assert!(err.contains(expected), "{}", err);
+    }
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_flows() {
+    use super::pcap::{PcapPacket, PcapPacketHeader};
+    use super::variables::BuiltinVarType;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    let mut parser = Parser::new(Scanner::new("flow_timeout(5);"));
+    let program = parser.parse_program();
+    assert!(parser.parse_errors().is_empty());
+    let mut compiler = Compiler::new();
+    compiler.compile(program).unwrap();
+    let mut vm = VM::new(compiler.bytecode());
+    vm.run().unwrap();
+    // Not an ip packet until one is read
+    assert_eq!(*vm.flow(1).unwrap(), Object::Null);
+
+    let get = |obj: &Rc<Object>, key: &str| match obj.as_ref() {
+        Object::Map(map) => map.get(&Rc::new(Object::Str(key.to_string()))),
+        obj => panic!("expected a map, got {}", obj),
+    };
+    let count = |vm: &VM| match vm.flows().as_ref() {
+        Object::Arr(arr) => arr.len(),
+        obj => panic!("expected an array, got {}", obj),
+    };
+    let read = |vm: &mut VM, np: i64, time: f64, ports: (u16, u16)| {
+        let frame = ipv4_frame(false, 17, [10, 0, 0, 1], [10, 0, 0, 2], ports);
+        let header = PcapPacketHeader {
+            ts_sec: time as u32,
+            ts_usec: 0,
+            caplen: frame.len() as u32,
+            wirelen: frame.len() as u32,
+        };
+        vm.set_curr_pkt(Rc::new(PcapPacket::new(header, frame)));
+        vm.update_builtin_var(BuiltinVarType::NP, Rc::new(Object::Integer(np)));
+        vm.update_builtin_var(BuiltinVarType::Time, Rc::new(Object::Float(time)));
+        vm.flow(1).unwrap()
+    };
+
+    let flow = read(&mut vm, 1, 0.0, (40000, 53));
+    assert_eq!(*get(&flow, "proto"), Object::Integer(17));
+    assert_eq!(*get(&flow, "dst"), Object::Str("10.0.0.2".into()));
+    assert_eq!(*get(&flow, "dstport"), Object::Integer(53));
+    assert_eq!(*get(&flow, "packets"), Object::Integer(1));
+    assert_eq!(*get(&flow, "bytes"), Object::Integer(42));
+    // Looking up the flow again does not count the packet again
+    let flow = vm.flow(1).unwrap();
+    assert_eq!(*get(&flow, "packets"), Object::Integer(1));
+
+    let flow = read(&mut vm, 2, 1.0, (40000, 53));
+    assert_eq!(*get(&flow, "packets"), Object::Integer(2));
+    assert_eq!(*get(&flow, "last"), Object::Float(1.0));
+    // A different port is a different flow
+    let other = read(&mut vm, 3, 2.0, (40001, 53));
+    assert_eq!(*get(&other, "packets"), Object::Integer(1));
+    assert_eq!(count(&vm), 2);
+
+    // The first flow is idle for longer than the timeout
+    let other = read(&mut vm, 4, 6.5, (40001, 53));
+    assert_eq!(*get(&other, "packets"), Object::Integer(2));
+    assert_eq!(count(&vm), 1);
+    let flow = read(&mut vm, 5, 7.0, (40000, 53));
+    assert_eq!(*get(&flow, "packets"), Object::Integer(1));
+    assert_eq!(*get(&flow, "first"), Object::Float(7.0));
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_flow_seq_gaps() {
+    use super::pcap::{PcapPacket, PcapPacketHeader};
+    use super::variables::BuiltinVarType;
+
+    // An ipv4 frame holding a transport header and a payload, followed by
+    // the padding of a short frame
+    let frame = |proto: u8, transport: &[u8], payload: &[u8], padding: usize| {
+        let total = (20 + transport.len() + payload.len()) as u16;
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&[0x08, 0x00, 0x45, 0]);
+        frame.extend_from_slice(&total.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0, 0, 64, proto, 0, 0]);
+        frame.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        frame.extend_from_slice(transport);
+        frame.extend_from_slice(payload);
+        frame.extend_from_slice(&vec![0; padding]);
+        frame
+    };
+    let tcp = |seq: u32, flags: u8, len: usize, padding: usize| {
+        let mut hdr = vec![0x9c, 0x40, 0x00, 0x50];
+        hdr.extend_from_slice(&seq.to_be_bytes());
+        hdr.extend_from_slice(&[0, 0, 0, 0, 0x50, flags, 0xff, 0xff, 0, 0, 0, 0]);
+        frame(6, &hdr, &vec![b'x'; len], padding)
+    };
+    let rtp = |seq: u16| {
+        let hdr = [0x9c, 0x40, 0x13, 0x88, 0, 20, 0, 0];
+        let mut payload = vec![0x80, 0x00];
+        payload.extend_from_slice(&seq.to_be_bytes());
+        payload.extend_from_slice(&[0, 0, 0, 0, 0x12, 0x34, 0x56, 0x78]);
+        frame(17, &hdr, &payload, 0)
+    };
+
+    let mut vm = VM::new(Compiler::new().bytecode());
+    let mut np = 0;
+    let mut read = |frame: Vec<u8>| {
+        np += 1;
+        let header = PcapPacketHeader {
+            ts_sec: 0,
+            ts_usec: 0,
+            caplen: frame.len() as u32,
+            wirelen: frame.len() as u32,
+        };
+        vm.set_curr_pkt(Rc::new(PcapPacket::new(header, frame)));
+        vm.update_builtin_var(BuiltinVarType::NP, Rc::new(Object::Integer(np)));
+        vm.flow(1).unwrap()
+    };
+    let get = |obj: &Rc<Object>, key: &str| match obj.as_ref() {
+        Object::Map(map) => map.get(&Rc::new(Object::Str(key.to_string()))),
+        obj => panic!("expected a map, got {}", obj),
+    };
+
+    read(tcp(100, 0x02, 0, 0));
+    read(tcp(101, 0x10, 10, 0));
+    // The segment with the bytes 111 to 120 is missing
+    read(tcp(121, 0x10, 10, 0));
+    // and is sent again
+    read(tcp(111, 0x10, 10, 0));
+    // The padding of a short frame is not part of the payload
+    read(tcp(131, 0x10, 0, 6));
+    let flow = read(tcp(131, 0x10, 5, 0));
+    let analysis = get(&flow, "analysis");
+    assert_eq!(*get(&analysis, "gaps"), Object::Integer(1));
+    assert_eq!(*get(&analysis, "gap_bytes"), Object::Integer(10));
+    assert_eq!(*get(&analysis, "retransmissions"), Object::Integer(1));
+    assert_eq!(*get(&flow, "rtp"), Object::Null);
+
+    // A single udp packet is not taken to be an rtp stream
+    let flow = read(rtp(1));
+    assert_eq!(*get(&flow, "rtp"), Object::Null);
+    read(rtp(2));
+    // The packets 3 and 4 are lost, and then 4 arrives late
+    read(rtp(5));
+    read(rtp(4));
+    let flow = read(rtp(6));
+    let stats = get(&flow, "rtp");
+    assert_eq!(*get(&stats, "ssrc"), Object::Integer(0x12345678));
+    assert_eq!(*get(&stats, "packets"), Object::Integer(5));
+    assert_eq!(*get(&stats, "lost"), Object::Integer(1));
+    assert_eq!(*get(&stats, "late"), Object::Integer(1));
+    assert_eq!(*get(&flow, "analysis"), Object::Null);
+}
+
+#[test]
+fn test_ratelimit() {
+    use super::variables::BuiltinVarType;
+
+    let mut vm = VM::new(Compiler::new().bytecode());
+    let mut allow = |key: &str, time: f64| {
+        vm.update_builtin_var(BuiltinVarType::Time, Rc::new(Object::Float(time)));
+        let key = Rc::new(Object::Str(key.to_string()));
+        vm.ratelimit(&key, &Object::Integer(2), &Object::Integer(10))
+    };
+
+    // The bucket starts full
+    assert!(allow("a", 0.0));
+    assert!(allow("a", 0.5));
+    assert!(!allow("a", 1.0));
+    // Every key has a bucket of its own
+    assert!(allow("b", 1.0));
+    // A token is added every five seconds
+    assert!(allow("a", 5.0));
+    assert!(!allow("a", 6.0));
+    // The bucket holds at most two tokens
+    assert!(allow("a", 100.0));
+    assert!(allow("a", 100.0));
+    assert!(!allow("a", 100.0));
+}
+
+#[test]
+fn test_rate_limit() {
+    use super::variables::BuiltinVarType;
+
+    let mut vm = VM::new(Compiler::new().bytecode());
+    let mut allow = |key: &str, time: f64| {
+        vm.update_builtin_var(BuiltinVarType::Time, Rc::new(Object::Float(time)));
+        let key = Rc::new(Object::Str(key.to_string()));
+        vm.rate_limit(&key, &Object::Integer(2), &Object::Integer(10))
+    };
+
+    // Two calls are allowed in each window of ten seconds
+    assert!(allow("a", 0.0));
+    assert!(allow("a", 9.0));
+    assert!(!allow("a", 9.5));
+    // Every key has windows of its own
+    assert!(allow("b", 9.5));
+    // Unlike a bucket, a new window allows all of the calls at once
+    assert!(allow("a", 10.0));
+    assert!(allow("a", 10.0));
+    assert!(!allow("a", 19.9));
+    // Windows without calls are skipped over
+    assert!(allow("a", 45.0));
+    assert!(allow("a", 49.0));
+    assert!(!allow("a", 49.9));
+    assert!(allow("a", 50.0));
+}
+
+#[test]
+fn test_regex() {
+    use super::regex::Regex;
+
+    let spans = |pattern: &str, s: &str| {
+        let re = Regex::new(pattern).unwrap();
+        re.captures_str(s).map(|groups| {
+            groups
+                .iter()
+                .map(|g| g.map(|(start, end)| s[start..end].to_string()))
+                .collect::<Vec<_>>()
+        })
+    };
+    let some = |groups: &[&str]| Some(groups.iter().map(|g| Some(g.to_string())).collect());
+    assert_eq!(spans("b+", "abbbc"), some(&["bbb"]));
+    assert_eq!(spans("b+?", "abbbc"), some(&["b"]));
+    assert_eq!(spans("^a|c$", "abc"), some(&["a"]));
+    assert_eq!(spans("^b", "abc"), None);
+    assert_eq!(spans("x*", "abc"), some(&[""]));
+    assert_eq!(
+        spans("(\\w+)=(\\d+)", "k=v, port=443"),
+        some(&["port=443", "port", "443"])
+    );
+    assert_eq!(spans("(?:ab)+", "xababy"), some(&["abab"]));
+    assert_eq!(
+        spans("a(b)?c", "ac"),
+        Some(vec![Some("ac".to_string()), None])
+    );
+    assert_eq!(spans("cat|category", "category"), some(&["cat"]));
+    assert_eq!(spans("\\bport\\b", "sport port"), some(&["port"]));
+    assert_eq!(spans("[^a-c\\s]+", "abc def"), some(&["def"]));
+    assert_eq!(spans("[\\d.]{7,15}", "ip 10.0.0.1!"), some(&["10.0.0.1"]));
+    assert_eq!(spans("a{2}", "aaa"), some(&["aa"]));
+    assert_eq!(spans("a{2,}", "aaaa"), some(&["aaaa"]));
+    assert_eq!(spans("x{,2}", "x{,2}"), some(&["x{,2}"]));
+    assert_eq!(spans("h.llo", "héllo hello"), some(&["héllo"]));
+    assert_eq!(spans("(a*)*b", "aaab"), some(&["aaab", "aaa"]));
+
+    // Payloads are matched a byte at a time
+    let re = Regex::new("\\x16\\x03[\\x00-\\x03]").unwrap();
+    assert!(re.is_match_bytes(&[0x00, 0x16, 0x03, 0x01]));
+    assert!(!re.is_match_bytes(&[0x16, 0x03, 0x04]));
+    assert_eq!(
+        Regex::new("GET (\\S+)")
+            .unwrap()
+            .captures_bytes(b"GET /index.html HTTP/1.1"),
+        Some(vec![Some((0, 15)), Some((4, 15))])
+    );
+
+    // A pathological pattern takes linear time
+    let re = Regex::new("(a*)*c").unwrap();
+    assert!(!re.is_match_str(&"a".repeat(10000)));
+
+    for (pattern, err) in [
+        ("(a", "unmatched '('"),
+        ("a)", "unmatched ')'"),
+        ("*a", "nothing to repeat"),
+        ("[a-", "unterminated character class"),
+        ("[z-a]", "invalid range in character class"),
+        ("a{3,2}", "invalid repetition count"),
+        ("a{1001}", "repetition count is too large"),
+        ("\\q", "invalid escape '\\q'"),
+        ("\\x4", "invalid hex escape"),
+        ("(?=a)", "unsupported group"),
+    ] {
+        assert_eq!(Regex::new(pattern).unwrap_err(), err, "{}", pattern);
+    }
+
+    // Deeply nested groups and repetitions are an error, not a stack
+    // overflow
+    let nested = |n| format!("{}a{}", "(".repeat(n), ")".repeat(n));
+    assert!(Regex::new(&nested(256)).unwrap().is_match_str("a"));
+    assert!(Regex::new(&format!("a{}", "?".repeat(256))).is_ok());
+    for pattern in [
+        nested(257),
+        nested(10000),
+        "(".repeat(10000),
+        "(?:".repeat(10000),
+        format!("a{}", "+".repeat(10000)),
+    ] {
+        assert_eq!(
+            Regex::new(&pattern).unwrap_err(),
+            "nesting is deeper than the limit of 256"
+        );
+    }
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_ipv4_truncated_options() {
+    // The header length covers options beyond the end of the data
+    let mut header = vec![0x46, 0, 0, 24, 0, 0, 0, 0, 64, 17, 0, 0];
+    header.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+    assert_eq!(
+        Ipv4Packet::from_bytes(Rc::new(header.clone()), 0)
+            .unwrap_err()
+            .to_string(),
+        "invalid-length: 20"
+    );
+    header.extend_from_slice(&[1, 1, 1, 0]);
+    assert!(Ipv4Packet::from_bytes(Rc::new(header), 0).is_ok());
+}
@@ -0,0 +1,156 @@
+use crate::object::Object;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinVarType {
+    Argv,
+    NP,       // Number of packets processed so far
+    PL,       // Length of the current packet
+    WL,       // Length of the current packet on wire
+    Tss,      // Timestamp - seconds
+    Tsu,      // Timestamp - microseconds/nanoseconds
+    NC,       // Number of corrupt packet records found so far
+    Time,     // Time since the first packet in seconds
+    FI,       // Index of the filter statement being run
+    FL,       // Source line of the filter statement being run
+    NT,       // Number of packets found truncated so far
+    Auto,     // Value that has a length or a checksum computed on rewrite
+    Ts,       // Timestamp of the current packet
+    PsDrop,   // Packets dropped by the kernel during a live capture
+    PsIfDrop, // Packets dropped by the interface during a live capture
+    Filename, // Path of the pcap file being read
+    Max,
+}
+
+impl BuiltinVarType {
+    pub fn count() -> usize {
+        Self::Max as usize
+    }
+    pub fn range() -> std::ops::Range<usize> {
+        0..Self::count()
+    }
+
+    /// Types of the values the variable may hold
+    pub fn value_types(&self) -> &'static [BuiltinValueType] {
+        use BuiltinValueType::*;
+        match self {
+            Self::Argv => &[Arr],
+            Self::NP | Self::PL | Self::WL | Self::Tss | Self::Tsu => &[Integer, Null],
+            Self::NC | Self::FI | Self::FL | Self::NT => &[Integer, Null],
+            Self::PsDrop | Self::PsIfDrop => &[Integer, Null],
+            Self::Time => &[Float, Null],
+            Self::Auto => &[Str],
+            Self::Filename => &[Str, Null],
+            Self::Ts => &[Timestamp, Null],
+            Self::Max => &[],
+        }
+    }
+
+    /// Check that 'obj' may be assigned to the variable
+    pub fn check(&self, obj: &Object) -> Result<(), String> {
+        let types = self.value_types();
+        if types.is_empty() {
+            return Err(format!("invalid builtin variable index {}", *self as usize));
+        }
+        if types.iter().any(|t| t.matches(obj)) {
+            return Ok(());
+        }
+        let expected: Vec<&str> = types.iter().map(|t| t.name()).collect();
+        let name: &str = (*self).into();
+        Err(format!(
+            "builtin variable '{}' cannot hold {}, expected {}",
+            name,
+            obj,
+            expected.join(" or ")
+        ))
+    }
+}
+
+/// Types of the values held by the builtin variables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinValueType {
+    Null,
+    Integer,
+    Float,
+    Str,
+    Arr,
+    Timestamp,
+}
+
+impl BuiltinValueType {
+    pub fn matches(&self, obj: &Object) -> bool {
+        matches!(
+            (self, obj),
+            (Self::Null, Object::Null)
+                | (Self::Integer, Object::Integer(_))
+                | (Self::Float, Object::Float(_))
+                | (Self::Str, Object::Str(_))
+                | (Self::Arr, Object::Arr(_))
+                | (Self::Timestamp, Object::Timestamp(_))
+        )
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Integer => "integer",
+            Self::Float => "float",
+            Self::Str => "string",
+            Self::Arr => "array",
+            Self::Timestamp => "timestamp",
+        }
+    }
+}
+
+impl From<usize> for BuiltinVarType {
+    fn from(var: usize) -> Self {
+        match var {
+            0 => Self::Argv,
+            1 => Self::NP,
+            2 => Self::PL,
+            3 => Self::WL,
+            4 => Self::Tss,
+            5 => Self::Tsu,
+            6 => Self::NC,
+            7 => Self::Time,
+            8 => Self::FI,
+            9 => Self::FL,
+            10 => Self::NT,
+            11 => Self::Auto,
+            12 => Self::Ts,
+            13 => Self::PsDrop,
+            14 => Self::PsIfDrop,
+            15 => Self::Filename,
+            _ => Self::Max,
+        }
+    }
+}
+
+impl From<BuiltinVarType> for &'static str {
+    fn from(var: BuiltinVarType) -> Self {
+        match var {
+            BuiltinVarType::Argv => "argv",
+            BuiltinVarType::NP => "NP",
+            BuiltinVarType::PL => "PL",
+            BuiltinVarType::WL => "WL",
+            BuiltinVarType::Tss => "TSS",
+            BuiltinVarType::Tsu => "TSU",
+            BuiltinVarType::NC => "NC",
+            BuiltinVarType::Time => "TIME",
+            BuiltinVarType::FI => "FI",
+            BuiltinVarType::FL => "FL",
+            BuiltinVarType::NT => "NT",
+            BuiltinVarType::Auto => "auto",
+            BuiltinVarType::Ts => "TS",
+            BuiltinVarType::PsDrop => "ps_drop",
+            BuiltinVarType::PsIfDrop => "ps_ifdrop",
+            BuiltinVarType::Filename => "FILENAME",
+            BuiltinVarType::Max => "",
+        }
+    }
+}
+
+impl From<BuiltinVarType> for usize {
+    fn from(var: BuiltinVarType) -> Self {
+        var as usize
+    }
+}
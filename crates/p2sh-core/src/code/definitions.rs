@@ -0,0 +1,444 @@
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use super::opcode::*;
+use super::prop::PacketPropType;
+use crate::builtins::functions::BUILTINFNS;
+use crate::builtins::variables::BuiltinVarType;
+use crate::object::Object;
+use byteorder::{BigEndian, WriteBytesExt};
+
+// Longest constant, in characters, shown in a disassembly listing
+const MAX_LISTING_VALUE: usize = 40;
+
+#[derive(Debug, Clone)]
+pub struct Definition {
+    name: &'static str,
+    operand_widths: &'static [usize],
+}
+
+impl Definition {
+    fn new(name: &'static str, operand_widths: &'static [usize]) -> Definition {
+        Definition {
+            name,
+            operand_widths,
+        }
+    }
+
+    /// Number of bytes taken by the operands of the instruction
+    pub fn operands_len(&self) -> usize {
+        self.operand_widths.iter().sum()
+    }
+}
+
+lazy_static! {
+    static ref DEFINITIONS: Vec<Option<Definition>> = {
+        let mut map = HashMap::new();
+        map.insert(Opcode::Constant, Definition::new("OpConstant", &[2]));
+        map.insert(Opcode::Pop, Definition::new("OpPop", &[]));
+        map.insert(Opcode::Add, Definition::new("OpAdd", &[]));
+        map.insert(Opcode::Sub, Definition::new("OpSub", &[]));
+        map.insert(Opcode::Mul, Definition::new("OpMul", &[]));
+        map.insert(Opcode::Div, Definition::new("OpDiv", &[]));
+        map.insert(Opcode::Mod, Definition::new("OpMod", &[]));
+        map.insert(Opcode::True, Definition::new("OpTrue", &[]));
+        map.insert(Opcode::False, Definition::new("OpFalse", &[]));
+        map.insert(Opcode::Equal, Definition::new("OpEqual", &[]));
+        map.insert(Opcode::NotEqual, Definition::new("OpNotEqual", &[]));
+        map.insert(Opcode::Greater, Definition::new("OpGreater", &[]));
+        map.insert(Opcode::GreaterEq, Definition::new("OpGreaterEq", &[]));
+        map.insert(Opcode::Minus, Definition::new("OpMinus", &[]));
+        map.insert(Opcode::Bang, Definition::new("OpBang", &[]));
+        map.insert(Opcode::Jump, Definition::new("OpJump", &[2]));
+        map.insert(Opcode::JumpIfFalse, Definition::new("OpJumpIfFalse", &[2]));
+        map.insert(Opcode::JumpIfFalseNoPop, Definition::new("OpJumpIfFalseNoPop", &[2]));
+        map.insert(Opcode::Null, Definition::new("OpNull", &[]));
+        map.insert(Opcode::DefineGlobal, Definition::new("OpDefineGlobal", &[2]));
+        map.insert(Opcode::GetGlobal, Definition::new("OpGetGlobal", &[2]));
+        map.insert(Opcode::SetGlobal, Definition::new("OpSetGlobal", &[2]));
+        map.insert(Opcode::Array, Definition::new("OpArray", &[2]));
+        map.insert(Opcode::Map, Definition::new("OpMap", &[2]));
+        map.insert(Opcode::GetIndex, Definition::new("OpGetIndex", &[]));
+        map.insert(Opcode::SetIndex, Definition::new("OpSetIndex", &[]));
+        map.insert(Opcode::Call, Definition::new("OpCall", &[1]));
+        map.insert(Opcode::ReturnValue, Definition::new("OpReturnValue", &[]));
+        map.insert(Opcode::Return, Definition::new("OpReturn", &[]));
+        map.insert(Opcode::DefineLocal, Definition::new("OpDefineLocal", &[1]));
+        map.insert(Opcode::GetLocal, Definition::new("OpGetLocal", &[1]));
+        map.insert(Opcode::SetLocal, Definition::new("OpSetLocal", &[1]));
+        map.insert(Opcode::GetBuiltinFn, Definition::new("OpGetBuiltinFn", &[1]));
+        map.insert(Opcode::GetBuiltinVar, Definition::new("OpGetBuiltinVar", &[1]));
+        // 'OpClosure' has two operands - a 2-byte constant index and #free-variables
+        map.insert(Opcode::Closure, Definition::new("OpClosure", &[2, 1]));
+        map.insert(Opcode::GetFree, Definition::new("OpGetFree", &[1]));
+        map.insert(Opcode::SetFree, Definition::new("OpSetFree", &[1]));
+        map.insert(Opcode::CurrClosure, Definition::new("OpCurrClosure", &[]));
+        map.insert(Opcode::Not, Definition::new("OpNot", &[]));
+        map.insert(Opcode::And, Definition::new("OpAnd", &[]));
+        map.insert(Opcode::Or, Definition::new("OpOr", &[]));
+        map.insert(Opcode::Xor, Definition::new("OpXor", &[]));
+        map.insert(Opcode::ShiftLeft, Definition::new("OpShiftLeft", &[]));
+        map.insert(Opcode::ShiftRight, Definition::new("OpShiftRight", &[]));
+        map.insert(Opcode::Dup, Definition::new("OpDup", &[]));
+        map.insert(Opcode::GetProp, Definition::new("OpGetProp", &[1]));
+        map.insert(Opcode::SetProp, Definition::new("OpSetProp", &[1]));
+        map.insert(Opcode::Dollar, Definition::new("OpDollar", &[]));
+        // 'OpIter' has the number of loop variables as the operand
+        map.insert(Opcode::Iter, Definition::new("OpIter", &[1]));
+        // 'OpIterRange' has 1 as the operand if the range is inclusive
+        map.insert(Opcode::IterRange, Definition::new("OpIterRange", &[1]));
+        // 'OpIterNext' has the number of loop variables and the address to
+        // jump to when the iteration is done as the operands
+        map.insert(Opcode::IterNext, Definition::new("OpIterNext", &[1, 2]));
+        map.insert(Opcode::IterEnd, Definition::new("OpIterEnd", &[]));
+        map.insert(Opcode::RegexMatch, Definition::new("OpRegexMatch", &[]));
+        map.insert(Opcode::In, Definition::new("OpIn", &[]));
+        // 'OpTry' has the address of the catch block as the operand
+        map.insert(Opcode::Try, Definition::new("OpTry", &[2]));
+        map.insert(Opcode::EndTry, Definition::new("OpEndTry", &[]));
+        // The last operand of the increment instructions is 1 to decrement
+        map.insert(Opcode::IncGlobal, Definition::new("OpIncGlobal", &[2, 1]));
+        map.insert(Opcode::IncLocal, Definition::new("OpIncLocal", &[1, 1]));
+        map.insert(Opcode::IncFree, Definition::new("OpIncFree", &[1, 1]));
+        map.insert(Opcode::IncIndex, Definition::new("OpIncIndex", &[1]));
+
+        // Index the definitions by the opcode so that looking one up when
+        // decoding an instruction is an array access
+        let mut table = vec![None; u8::MAX as usize + 1];
+        for (op, def) in map {
+            table[u8::from(op) as usize] = Some(def);
+        }
+        table
+    };
+}
+
+pub fn lookup(op: u8) -> Result<&'static Definition, String> {
+    match DEFINITIONS[op as usize].as_ref() {
+        Some(def) => Ok(def),
+        None => Err(format!("opcode {} undefined", op)),
+    }
+}
+
+/*
+ * Helper function to build up bytecode instructions
+ * After calculating the final value of instruction_len, allocate the
+ * 'instruction' vector with a fixed capacity and add the opcode as the
+ * first byte. Then, iterate over the 'operand_widths', take the matching
+ * element from the operands and add it to the instruction. Make sure
+ * that the operands are encoded in BigEndian format. After encoding the
+ * operand, increment the offset by its width.
+ */
+pub fn make(op: Opcode, operands: &[usize], line: usize) -> Instructions {
+    if let Some(def) = DEFINITIONS[u8::from(op) as usize].as_ref() {
+        let mut instruction_len = 1;
+        for &w in def.operand_widths {
+            instruction_len += w;
+        }
+
+        let mut instruction = Vec::with_capacity(instruction_len);
+        instruction.push(op.into());
+
+        // Iterate through operands and its widths
+        for (&o, width) in operands.iter().zip(def.operand_widths) {
+            // Generate bytecode depending on the width of the operands
+            match width {
+                2 => {
+                    instruction.write_u16::<BigEndian>(o as u16).unwrap();
+                }
+                1 => {
+                    instruction.write_u8(o as u8).unwrap();
+                }
+                _ => panic!("Unsupported operand width: {}", width),
+            }
+        }
+
+        Instructions::new(instruction, vec![line; instruction_len])
+    } else {
+        Instructions::new(Vec::new(), Vec::new())
+    }
+}
+
+/*
+ * Helper function to decode the the operands of a bytecode instruction.
+ * It is a counterpart of 'make'
+ */
+pub fn read_operands(def: &Definition, ins: &[u8]) -> (Vec<usize>, usize) {
+    let mut operands = vec![0; def.operand_widths.len()];
+    let mut offset = 0;
+
+    for (i, &width) in def.operand_widths.iter().enumerate() {
+        operands[i] = match width {
+            2 => u16::from_be_bytes([ins[offset], ins[offset + 1]]) as usize,
+            1 => ins[offset] as usize,
+            _ => panic!("Unsupported operand width: {}", width),
+        };
+        offset += width;
+    }
+
+    (operands, offset)
+}
+
+/// Names and values that explain the operands of the instructions in a
+/// disassembly listing. Operands without a matching entry are shown as is.
+#[derive(Default)]
+pub struct Symbols<'a> {
+    pub constants: &'a [Rc<Object>],
+    /// Names of the global variables by their index
+    pub globals: &'a [String],
+}
+
+// A decoded instruction; its offset, opcode, definition and operands
+type Decoded = (usize, Opcode, &'static Definition, Vec<usize>);
+
+/// Describe a constant briefly, quoting strings and truncating long values
+pub fn describe_constant(obj: &Object) -> String {
+    let s = match obj {
+        Object::Str(s) => format!("{:?}", s),
+        Object::Func(func) if func.pattern.is_none() => format!("fn at line {}", func.line),
+        obj => obj.to_string(),
+    };
+    if s.chars().count() <= MAX_LISTING_VALUE {
+        s
+    } else {
+        let head: String = s.chars().take(MAX_LISTING_VALUE).collect();
+        format!("{}...", head)
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct Instructions {
+    pub code: Vec<u8>,
+    pub lines: Vec<usize>,
+}
+
+impl Instructions {
+    pub fn new(data: Vec<u8>, lines: Vec<usize>) -> Instructions {
+        Instructions { code: data, lines }
+    }
+    pub fn len(&self) -> usize {
+        self.code.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.code.is_empty()
+    }
+    #[allow(dead_code)]
+    pub fn get(&self, index: usize) -> u8 {
+        self.code[index]
+    }
+
+    /// The source line of the first definition of each global, keyed by
+    /// the index of the global
+    pub fn global_lines(&self) -> HashMap<usize, usize> {
+        let mut lines = HashMap::new();
+        for (pos, op, _, operands) in self.decode().into_iter().flatten() {
+            if op == Opcode::DefineGlobal {
+                lines.entry(operands[0]).or_insert(self.lines[pos]);
+            }
+        }
+        lines
+    }
+
+    fn fmt_instruction(&self, def: &Definition, operands: &[usize]) -> String {
+        let operand_count = def.operand_widths.len();
+        if operands.len() != operand_count {
+            return format!(
+                "ERROR: operand len {} does not match defined {}",
+                operands.len(),
+                operand_count
+            );
+        }
+
+        match operand_count {
+            0 => def.name.to_string(),
+            1 => format!("{} {}", def.name, operands[0]),
+            2 => format!("{} {} {}", def.name, operands[0], operands[1]),
+            _ => format!(
+                "ERROR: unhandled operand count for {} ['{}']",
+                def.name, operand_count
+            ),
+        }
+    }
+
+    // Decode the instructions, with an error for each undefined opcode
+    fn decode(&self) -> Vec<Result<Decoded, String>> {
+        let mut decoded = Vec::new();
+        let mut i = 0;
+        while i < self.code.len() {
+            match lookup(self.code[i]) {
+                Ok(def) => {
+                    let (operands, read) = read_operands(def, &self.code[i + 1..]);
+                    decoded.push(Ok((i, Opcode::from(self.code[i]), def, operands)));
+                    i += 1 + read;
+                }
+                Err(err) => {
+                    decoded.push(Err(err));
+                    i += 1;
+                }
+            }
+        }
+        decoded
+    }
+
+    /// List the instructions with the meaning of their operands; the value
+    /// of constants, the names of variables, builtins and properties, and
+    /// labels in place of the addresses that jumps go to, e.g.
+    ///
+    /// ```text
+    /// 0000 OpGetGlobal 0            ; x
+    /// 0003 OpJumpIfFalse 12         ; L0
+    /// 0006 OpConstant 1             ; "yes"
+    /// 0009 OpPop
+    /// L0:
+    /// 0012 OpNull
+    /// ```
+    pub fn listing(&self, symbols: &Symbols) -> String {
+        self.write_listing(symbols, false)
+    }
+
+    /// The listing with the source line of each instruction after its
+    /// offset, or '|' when it is on the same line as the one before, e.g.
+    ///
+    /// ```text
+    /// 0000    1 OpConstant 0             ; 1
+    /// 0003    | OpSetGlobal 0            ; x
+    /// 0006    2 OpGetGlobal 0            ; x
+    /// ```
+    pub fn listing_with_lines(&self, symbols: &Symbols) -> String {
+        self.write_listing(symbols, true)
+    }
+
+    fn write_listing(&self, symbols: &Symbols, with_lines: bool) -> String {
+        let decoded = self.decode();
+
+        // Number the labels in the order of the addresses
+        let mut targets: Vec<usize> = decoded
+            .iter()
+            .flatten()
+            .filter_map(|(_, op, _, operands)| Self::jump_target(*op, operands))
+            .collect();
+        targets.sort_unstable();
+        targets.dedup();
+        let label = |addr: usize| targets.binary_search(&addr).ok().map(|n| format!("L{}", n));
+
+        let mut out = String::new();
+        let mut prev_line = None;
+        for ins in decoded {
+            let (i, op, def, operands) = match ins {
+                Ok(ins) => ins,
+                Err(err) => {
+                    out.push_str(&format!("ERROR: {}\n", err));
+                    continue;
+                }
+            };
+            if let Some(l) = label(i) {
+                out.push_str(&format!("{}:\n", l));
+            }
+            let text = self.fmt_instruction(def, &operands);
+            let comment = match Self::jump_target(op, &operands) {
+                Some(addr) => label(addr),
+                None => Self::operand_comment(op, &operands, symbols),
+            };
+            out.push_str(&format!("{:04} ", i));
+            if with_lines {
+                let line = self.lines.get(i).copied();
+                match line {
+                    Some(line) if prev_line != Some(line) => out.push_str(&format!("{:>4} ", line)),
+                    _ => out.push_str("   | "),
+                }
+                prev_line = line;
+            }
+            match comment {
+                Some(c) => out.push_str(&format!("{:<24} ; {}\n", text, c)),
+                None => out.push_str(&format!("{}\n", text)),
+            }
+        }
+        // A jump past the last instruction
+        if let Some(l) = label(self.code.len()) {
+            out.push_str(&format!("{}:\n", l));
+        }
+        out
+    }
+
+    // Address that a jump instruction goes to
+    fn jump_target(op: Opcode, operands: &[usize]) -> Option<usize> {
+        match op {
+            Opcode::Jump | Opcode::JumpIfFalse | Opcode::JumpIfFalseNoPop | Opcode::Try => {
+                operands.first().copied()
+            }
+            Opcode::IterNext => operands.get(1).copied(),
+            _ => None,
+        }
+    }
+
+    fn operand_comment(op: Opcode, operands: &[usize], symbols: &Symbols) -> Option<String> {
+        let operand = *operands.first()?;
+        match op {
+            Opcode::Constant | Opcode::Closure => {
+                symbols.constants.get(operand).map(|c| describe_constant(c))
+            }
+            Opcode::DefineGlobal | Opcode::GetGlobal | Opcode::SetGlobal | Opcode::IncGlobal => {
+                symbols.globals.get(operand).cloned()
+            }
+            Opcode::GetBuiltinFn => BUILTINFNS.get(operand).map(|b| b.name.to_string()),
+            Opcode::GetBuiltinVar => {
+                let name: &str = BuiltinVarType::from(operand).into();
+                Some(name.to_string())
+            }
+            Opcode::GetProp | Opcode::SetProp => {
+                Some(PacketPropType::from(operand as u8).to_string())
+            }
+            _ => None,
+        }
+    }
+
+    // Disassemble instructions after compilation
+    #[allow(dead_code)]
+    pub fn disassemble(&self) {
+        eprintln!(
+            "--------- Instructions [len: {:<4}] -------------------",
+            self.len(),
+        );
+        eprint!("{}", self);
+        eprintln!("------------------------------------------------------");
+    }
+
+    // Print current instructions during execution
+    #[allow(dead_code)]
+    pub fn print(&self, ip: usize) {
+        eprintln!(
+            "--------- Instructions [len: {:<4}, ip: {:<4}] ---------",
+            self.len(),
+            ip
+        );
+        eprint!("{}", self);
+        eprintln!("------------------------------------------------------");
+    }
+}
+
+impl fmt::Display for Instructions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut out = String::new();
+        for ins in self.decode() {
+            match ins {
+                Ok((i, _, def, operands)) => out.push_str(&format!(
+                    "{:04} {}\n",
+                    i,
+                    self.fmt_instruction(def, &operands)
+                )),
+                Err(err) => out.push_str(&format!("ERROR: {}\n", err)),
+            }
+        }
+
+        write!(f, "{}", out)
+    }
+}
+
+impl PartialEq for Instructions {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code && self.lines == other.lines
+    }
+}
+
+impl Eq for Instructions {}
@@ -49,6 +49,18 @@ pub enum Opcode {
     GetProp,
     SetProp,
     Dollar,
+    Iter,
+    IterRange,
+    IterNext,
+    IterEnd,
+    RegexMatch,
+    In,
+    Try,
+    EndTry,
+    IncGlobal,
+    IncLocal,
+    IncFree,
+    IncIndex,
     #[default]
     Invalid,
 }
@@ -104,6 +116,18 @@ impl From<u8> for Opcode {
             45 => Opcode::GetProp,
             46 => Opcode::SetProp,
             47 => Opcode::Dollar,
+            48 => Opcode::Iter,
+            49 => Opcode::IterRange,
+            50 => Opcode::IterNext,
+            51 => Opcode::IterEnd,
+            52 => Opcode::RegexMatch,
+            53 => Opcode::In,
+            54 => Opcode::Try,
+            55 => Opcode::EndTry,
+            56 => Opcode::IncGlobal,
+            57 => Opcode::IncLocal,
+            58 => Opcode::IncFree,
+            59 => Opcode::IncIndex,
             _ => Opcode::Invalid,
         }
     }
@@ -0,0 +1,660 @@
+use std::fmt;
+
+/// Packet object property type
+/// To be kept in sync with 'PACKET_PROP_MAP'
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PacketPropType {
+    Magic,
+    Major,
+    Minor,
+    ThisZone,
+    SigFigs,
+    Snaplen,
+    LinkType,
+    Sec,
+    USec,
+    Caplen,
+    Wirelen,
+    Payload,
+    Eth,
+    Src,
+    Dst,
+    EtherType,
+    Vlan,
+    Id,
+    Priority,
+    Dei,
+    Ipv4,
+    Version,
+    Ihl,
+    TotalLength,
+    Dscp,
+    Ecn,
+    Flags,
+    FragmentOffset,
+    Ttl,
+    Protocol,
+    Checksum,
+    Udp,
+    SrcPort,
+    DstPort,
+    Length,
+    Tcp,
+    Sequence,
+    Ack,
+    DataOffset,
+    WindowSize,
+    Urgent,
+    Ipv6,
+    TrafficClass,
+    FlowLabel,
+    NextHeader,
+    HopLimit,
+    Dns,
+    QName,
+    QType,
+    Opcode,
+    RCode,
+    Queries,
+    Answers,
+    Tls,
+    ContentType,
+    Handshake,
+    Sni,
+    Alpn,
+    Ciphers,
+    Ja3,
+    DetectedProto,
+    Truncated,
+    Gre,
+    Vxlan,
+    Geneve,
+    Vni,
+    Key,
+    Inner,
+    Fin,
+    Syn,
+    Rst,
+    Psh,
+    AckFlag,
+    Urg,
+    Ece,
+    Cwr,
+    Df,
+    Mf,
+    #[default]
+    Invalid,
+}
+
+impl From<u8> for PacketPropType {
+    fn from(code: u8) -> Self {
+        match code {
+            0 => Self::Magic,
+            1 => Self::Major,
+            2 => Self::Minor,
+            3 => Self::ThisZone,
+            4 => Self::SigFigs,
+            5 => Self::Snaplen,
+            6 => Self::LinkType,
+            7 => Self::Sec,
+            8 => Self::USec,
+            9 => Self::Caplen,
+            10 => Self::Wirelen,
+            11 => Self::Payload,
+            12 => Self::Eth,
+            13 => Self::Src,
+            14 => Self::Dst,
+            15 => Self::EtherType,
+            16 => Self::Vlan,
+            17 => Self::Id,
+            18 => Self::Priority,
+            19 => Self::Dei,
+            20 => Self::Ipv4,
+            21 => Self::Version,
+            22 => Self::Ihl,
+            23 => Self::TotalLength,
+            24 => Self::Dscp,
+            25 => Self::Ecn,
+            26 => Self::Flags,
+            27 => Self::FragmentOffset,
+            28 => Self::Ttl,
+            29 => Self::Protocol,
+            30 => Self::Checksum,
+            31 => Self::Udp,
+            32 => Self::SrcPort,
+            33 => Self::DstPort,
+            34 => Self::Length,
+            35 => Self::Tcp,
+            36 => Self::Sequence,
+            37 => Self::Ack,
+            38 => Self::DataOffset,
+            39 => Self::WindowSize,
+            40 => Self::Urgent,
+            41 => Self::Ipv6,
+            42 => Self::TrafficClass,
+            43 => Self::FlowLabel,
+            44 => Self::NextHeader,
+            45 => Self::HopLimit,
+            46 => Self::Dns,
+            47 => Self::QName,
+            48 => Self::QType,
+            49 => Self::Opcode,
+            50 => Self::RCode,
+            51 => Self::Queries,
+            52 => Self::Answers,
+            53 => Self::Tls,
+            54 => Self::ContentType,
+            55 => Self::Handshake,
+            56 => Self::Sni,
+            57 => Self::Alpn,
+            58 => Self::Ciphers,
+            59 => Self::Ja3,
+            60 => Self::DetectedProto,
+            61 => Self::Truncated,
+            62 => Self::Gre,
+            63 => Self::Vxlan,
+            64 => Self::Geneve,
+            65 => Self::Vni,
+            66 => Self::Key,
+            67 => Self::Inner,
+            68 => Self::Fin,
+            69 => Self::Syn,
+            70 => Self::Rst,
+            71 => Self::Psh,
+            72 => Self::AckFlag,
+            73 => Self::Urg,
+            74 => Self::Ece,
+            75 => Self::Cwr,
+            76 => Self::Df,
+            77 => Self::Mf,
+            _ => Self::Invalid,
+        }
+    }
+}
+
+impl From<PacketPropType> for u8 {
+    fn from(code: PacketPropType) -> Self {
+        code as u8
+    }
+}
+
+impl fmt::Display for PacketPropType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let string_representation = match self {
+            PacketPropType::Magic => "magic",
+            PacketPropType::Major => "major",
+            PacketPropType::Minor => "minor",
+            PacketPropType::ThisZone => "thiszone",
+            PacketPropType::SigFigs => "sigfigs",
+            PacketPropType::Snaplen => "snaplen",
+            PacketPropType::LinkType => "linktype",
+            PacketPropType::Sec => "sec",
+            PacketPropType::USec => "usec",
+            PacketPropType::Caplen => "caplen",
+            PacketPropType::Wirelen => "wirelen",
+            PacketPropType::Payload => "payload",
+            PacketPropType::Eth => "eth",
+            PacketPropType::Src => "src",
+            PacketPropType::Dst => "dst",
+            PacketPropType::EtherType => "type",
+            PacketPropType::Vlan => "vlan",
+            PacketPropType::Id => "id",
+            PacketPropType::Priority => "priority",
+            PacketPropType::Dei => "dei",
+            PacketPropType::Ipv4 => "ipv4",
+            PacketPropType::Version => "version",
+            PacketPropType::Ihl => "ihl",
+            PacketPropType::TotalLength => "totlen",
+            PacketPropType::Dscp => "dscp",
+            PacketPropType::Ecn => "ecn",
+            PacketPropType::Flags => "flags",
+            PacketPropType::FragmentOffset => "fragoff",
+            PacketPropType::Ttl => "ttl",
+            PacketPropType::Protocol => "proto",
+            PacketPropType::Checksum => "checksum",
+            PacketPropType::Udp => "udp",
+            PacketPropType::SrcPort => "srcport",
+            PacketPropType::DstPort => "dstport",
+            PacketPropType::Length => "len",
+            PacketPropType::Tcp => "tcp",
+            PacketPropType::Sequence => "seq",
+            PacketPropType::Ack => "ack",
+            PacketPropType::DataOffset => "dataoff",
+            PacketPropType::WindowSize => "winsize",
+            PacketPropType::Urgent => "urgent",
+            PacketPropType::Ipv6 => "ipv6",
+            PacketPropType::TrafficClass => "trafficclass",
+            PacketPropType::FlowLabel => "flowlabel",
+            PacketPropType::NextHeader => "nextheader",
+            PacketPropType::HopLimit => "hoplimit",
+            PacketPropType::Dns => "dns",
+            PacketPropType::QName => "qname",
+            PacketPropType::QType => "qtype",
+            PacketPropType::Opcode => "opcode",
+            PacketPropType::RCode => "rcode",
+            PacketPropType::Queries => "queries",
+            PacketPropType::Answers => "answers",
+            PacketPropType::Tls => "tls",
+            PacketPropType::ContentType => "contenttype",
+            PacketPropType::Handshake => "handshake",
+            PacketPropType::Sni => "sni",
+            PacketPropType::Alpn => "alpn",
+            PacketPropType::Ciphers => "ciphers",
+            PacketPropType::Ja3 => "ja3",
+            PacketPropType::DetectedProto => "detected_proto",
+            PacketPropType::Truncated => "truncated",
+            PacketPropType::Gre => "gre",
+            PacketPropType::Vxlan => "vxlan",
+            PacketPropType::Geneve => "geneve",
+            PacketPropType::Vni => "vni",
+            PacketPropType::Key => "key",
+            PacketPropType::Inner => "inner",
+            PacketPropType::Fin => "fin",
+            PacketPropType::Syn => "syn",
+            PacketPropType::Rst => "rst",
+            PacketPropType::Psh => "psh",
+            PacketPropType::AckFlag => "ackflag",
+            PacketPropType::Urg => "urg",
+            PacketPropType::Ece => "ece",
+            PacketPropType::Cwr => "cwr",
+            PacketPropType::Df => "df",
+            PacketPropType::Mf => "mf",
+            PacketPropType::Invalid => "invalid",
+        };
+        write!(f, "{}", string_representation)
+    }
+}
+
+/// Metadata describing a property of a packet object
+pub struct PropInfo {
+    pub prop: PacketPropType,
+    pub kind: &'static str,
+    pub desc: &'static str,
+}
+
+impl PropInfo {
+    const fn new(prop: PacketPropType, kind: &'static str, desc: &'static str) -> Self {
+        Self { prop, kind, desc }
+    }
+
+    /// True if the property is a layer within the packet object. Such
+    /// properties decode the layer as the protocol asked for whether or
+    /// not the packet carries it.
+    pub fn is_layer(&self) -> bool {
+        self.kind == "object" || PACKET_PROTOS.iter().any(|p| p.name == self.kind)
+    }
+}
+
+/// Metadata describing a packet object and the properties it exposes
+pub struct ProtoInfo {
+    pub name: &'static str,
+    pub props: &'static [PropInfo],
+}
+
+/// Properties supported by each packet object.
+/// To be kept in sync with the 'exec_prop_*' handlers in the VM.
+pub const PACKET_PROTOS: &[ProtoInfo] = &[
+    ProtoInfo {
+        name: "pcap",
+        props: &[
+            PropInfo::new(PacketPropType::Magic, "integer", "pcap magic number"),
+            PropInfo::new(PacketPropType::Major, "integer", "pcap major version"),
+            PropInfo::new(PacketPropType::Minor, "integer", "pcap minor version"),
+            PropInfo::new(
+                PacketPropType::ThisZone,
+                "integer",
+                "GMT to local correction",
+            ),
+            PropInfo::new(PacketPropType::SigFigs, "integer", "accuracy of timestamps"),
+            PropInfo::new(
+                PacketPropType::Snaplen,
+                "integer",
+                "max length of captured packets",
+            ),
+            PropInfo::new(PacketPropType::LinkType, "integer", "data link type"),
+        ],
+    },
+    ProtoInfo {
+        name: "packet",
+        props: &[
+            PropInfo::new(PacketPropType::Sec, "integer", "timestamp in seconds"),
+            PropInfo::new(
+                PacketPropType::USec,
+                "integer",
+                "timestamp in microseconds or nanoseconds (alias: nsec)",
+            ),
+            PropInfo::new(PacketPropType::Caplen, "integer", "capture length"),
+            PropInfo::new(
+                PacketPropType::Wirelen,
+                "integer",
+                "length of packet on wire",
+            ),
+            PropInfo::new(PacketPropType::Eth, "eth", "ethernet packet"),
+            PropInfo::new(
+                PacketPropType::Truncated,
+                "boolean",
+                "a layer is cut short by the capture (read only)",
+            ),
+            PropInfo::new(PacketPropType::Payload, "bytes", "ethernet packet as bytes"),
+        ],
+    },
+    ProtoInfo {
+        name: "eth",
+        props: &[
+            PropInfo::new(PacketPropType::Src, "address", "source mac address"),
+            PropInfo::new(PacketPropType::Dst, "address", "destination mac address"),
+            PropInfo::new(PacketPropType::EtherType, "integer", "ethertype"),
+            PropInfo::new(
+                PacketPropType::Vlan,
+                "vlan",
+                "vlan packet if type is 0x8100",
+            ),
+            PropInfo::new(
+                PacketPropType::Ipv4,
+                "ipv4",
+                "ipv4 packet if type is 0x0800",
+            ),
+            PropInfo::new(
+                PacketPropType::Ipv6,
+                "ipv6",
+                "ipv6 packet if type is 0x86dd",
+            ),
+            PropInfo::new(PacketPropType::Payload, "bytes", "ethernet payload"),
+        ],
+    },
+    ProtoInfo {
+        name: "vlan",
+        props: &[
+            PropInfo::new(PacketPropType::Id, "integer", "vlan identifier"),
+            PropInfo::new(PacketPropType::Priority, "integer", "priority code point"),
+            PropInfo::new(PacketPropType::Dei, "boolean", "drop eligible indicator"),
+            PropInfo::new(PacketPropType::EtherType, "integer", "ethertype"),
+            PropInfo::new(
+                PacketPropType::Vlan,
+                "vlan",
+                "vlan packet if type is 0x8100",
+            ),
+            PropInfo::new(
+                PacketPropType::Ipv4,
+                "ipv4",
+                "ipv4 packet if type is 0x0800",
+            ),
+            PropInfo::new(PacketPropType::Payload, "bytes", "vlan payload"),
+        ],
+    },
+    ProtoInfo {
+        name: "ipv4",
+        props: &[
+            PropInfo::new(PacketPropType::Version, "integer", "ip version (read only)"),
+            PropInfo::new(PacketPropType::Ihl, "integer", "internet header length"),
+            PropInfo::new(PacketPropType::TotalLength, "integer", "total length"),
+            PropInfo::new(PacketPropType::Id, "integer", "identification"),
+            PropInfo::new(PacketPropType::Dscp, "integer", "differentiated services"),
+            PropInfo::new(
+                PacketPropType::Ecn,
+                "integer",
+                "explicit congestion notification",
+            ),
+            PropInfo::new(PacketPropType::Flags, "integer", "ip flags"),
+            PropInfo::new(PacketPropType::Df, "boolean", "don't fragment flag"),
+            PropInfo::new(PacketPropType::Mf, "boolean", "more fragments flag"),
+            PropInfo::new(PacketPropType::FragmentOffset, "integer", "fragment offset"),
+            PropInfo::new(PacketPropType::Ttl, "integer", "time to live"),
+            PropInfo::new(PacketPropType::Protocol, "integer", "protocol"),
+            PropInfo::new(PacketPropType::Checksum, "integer", "header checksum"),
+            PropInfo::new(PacketPropType::Src, "address", "source ip address"),
+            PropInfo::new(PacketPropType::Dst, "address", "destination ip address"),
+            PropInfo::new(PacketPropType::Udp, "udp", "udp packet if proto is 17"),
+            PropInfo::new(PacketPropType::Tcp, "tcp", "tcp packet if proto is 6"),
+            PropInfo::new(PacketPropType::Gre, "gre", "gre tunnel if proto is 47"),
+            PropInfo::new(PacketPropType::Payload, "bytes", "ipv4 payload"),
+        ],
+    },
+    ProtoInfo {
+        name: "ipv6",
+        props: &[
+            PropInfo::new(PacketPropType::Version, "integer", "ip version (read only)"),
+            PropInfo::new(PacketPropType::TrafficClass, "integer", "traffic class"),
+            PropInfo::new(PacketPropType::FlowLabel, "integer", "flow label"),
+            PropInfo::new(PacketPropType::Length, "integer", "payload length"),
+            PropInfo::new(PacketPropType::NextHeader, "integer", "next header"),
+            PropInfo::new(PacketPropType::HopLimit, "integer", "hop limit"),
+            PropInfo::new(PacketPropType::Src, "address", "source ip address"),
+            PropInfo::new(PacketPropType::Dst, "address", "destination ip address"),
+            PropInfo::new(
+                PacketPropType::Udp,
+                "udp",
+                "udp packet if next header is 17",
+            ),
+            PropInfo::new(PacketPropType::Tcp, "tcp", "tcp packet if next header is 6"),
+            PropInfo::new(
+                PacketPropType::Gre,
+                "gre",
+                "gre tunnel if next header is 47",
+            ),
+            PropInfo::new(PacketPropType::Payload, "bytes", "ipv6 payload"),
+        ],
+    },
+    ProtoInfo {
+        name: "udp",
+        props: &[
+            PropInfo::new(PacketPropType::SrcPort, "integer", "source port"),
+            PropInfo::new(PacketPropType::DstPort, "integer", "destination port"),
+            PropInfo::new(
+                PacketPropType::Length,
+                "integer",
+                "length of header and data",
+            ),
+            PropInfo::new(PacketPropType::Checksum, "integer", "checksum"),
+            PropInfo::new(PacketPropType::Dns, "dns", "dns message (read only)"),
+            PropInfo::new(PacketPropType::Vxlan, "vxlan", "vxlan tunnel (read only)"),
+            PropInfo::new(
+                PacketPropType::Geneve,
+                "geneve",
+                "geneve tunnel (read only)",
+            ),
+            PropInfo::new(
+                PacketPropType::DetectedProto,
+                "string",
+                "protocol of the payload (read only)",
+            ),
+            PropInfo::new(PacketPropType::Payload, "bytes", "udp payload"),
+        ],
+    },
+    ProtoInfo {
+        name: "tcp",
+        props: &[
+            PropInfo::new(PacketPropType::SrcPort, "integer", "source port"),
+            PropInfo::new(PacketPropType::DstPort, "integer", "destination port"),
+            PropInfo::new(PacketPropType::Sequence, "integer", "sequence number"),
+            PropInfo::new(PacketPropType::Ack, "integer", "acknowledgment number"),
+            PropInfo::new(
+                PacketPropType::DataOffset,
+                "integer",
+                "data offset in 32-bit words",
+            ),
+            PropInfo::new(PacketPropType::Length, "integer", "same as dataoff"),
+            PropInfo::new(PacketPropType::Flags, "integer", "tcp flags"),
+            PropInfo::new(PacketPropType::Fin, "boolean", "fin flag"),
+            PropInfo::new(PacketPropType::Syn, "boolean", "syn flag"),
+            PropInfo::new(PacketPropType::Rst, "boolean", "rst flag"),
+            PropInfo::new(PacketPropType::Psh, "boolean", "psh flag"),
+            PropInfo::new(PacketPropType::AckFlag, "boolean", "ack flag"),
+            PropInfo::new(PacketPropType::Urg, "boolean", "urg flag"),
+            PropInfo::new(PacketPropType::Ece, "boolean", "ece flag"),
+            PropInfo::new(PacketPropType::Cwr, "boolean", "cwr flag"),
+            PropInfo::new(PacketPropType::WindowSize, "integer", "receive window size"),
+            PropInfo::new(PacketPropType::Checksum, "integer", "checksum"),
+            PropInfo::new(PacketPropType::Urgent, "integer", "urgent pointer"),
+            PropInfo::new(PacketPropType::Dns, "dns", "dns message (read only)"),
+            PropInfo::new(PacketPropType::Tls, "tls", "tls record (read only)"),
+            PropInfo::new(
+                PacketPropType::DetectedProto,
+                "string",
+                "protocol of the payload (read only)",
+            ),
+            PropInfo::new(PacketPropType::Payload, "bytes", "tcp payload"),
+        ],
+    },
+    ProtoInfo {
+        name: "dns",
+        props: &[
+            PropInfo::new(PacketPropType::Id, "integer", "transaction id"),
+            PropInfo::new(PacketPropType::Flags, "integer", "flags"),
+            PropInfo::new(PacketPropType::Opcode, "integer", "kind of query"),
+            PropInfo::new(PacketPropType::RCode, "integer", "response code"),
+            PropInfo::new(
+                PacketPropType::QName,
+                "string",
+                "name in the first question",
+            ),
+            PropInfo::new(
+                PacketPropType::QType,
+                "integer",
+                "type in the first question",
+            ),
+            PropInfo::new(PacketPropType::Queries, "array", "questions"),
+            PropInfo::new(PacketPropType::Answers, "array", "answer records"),
+            PropInfo::new(PacketPropType::Payload, "bytes", "dns message"),
+        ],
+    },
+    ProtoInfo {
+        name: "tls",
+        props: &[
+            PropInfo::new(PacketPropType::ContentType, "integer", "record type"),
+            PropInfo::new(
+                PacketPropType::Version,
+                "integer",
+                "client hello or record version",
+            ),
+            PropInfo::new(
+                PacketPropType::Handshake,
+                "integer",
+                "handshake message type",
+            ),
+            PropInfo::new(PacketPropType::Sni, "string", "server name indication"),
+            PropInfo::new(PacketPropType::Alpn, "array", "protocols offered"),
+            PropInfo::new(PacketPropType::Ciphers, "array", "cipher suites offered"),
+            PropInfo::new(PacketPropType::Ja3, "string", "ja3 fingerprint"),
+            PropInfo::new(PacketPropType::Payload, "bytes", "tls record"),
+        ],
+    },
+    ProtoInfo {
+        name: "gre",
+        props: &[
+            PropInfo::new(PacketPropType::Key, "integer", "key or null if absent"),
+            PropInfo::new(
+                PacketPropType::EtherType,
+                "integer",
+                "ethertype of the inner packet",
+            ),
+            PropInfo::new(
+                PacketPropType::Inner,
+                "object",
+                "encapsulated ethernet or ip packet",
+            ),
+            PropInfo::new(PacketPropType::Payload, "bytes", "gre payload"),
+        ],
+    },
+    ProtoInfo {
+        name: "vxlan",
+        props: &[
+            PropInfo::new(PacketPropType::Vni, "integer", "virtual network identifier"),
+            PropInfo::new(
+                PacketPropType::EtherType,
+                "integer",
+                "ethertype of the inner packet",
+            ),
+            PropInfo::new(
+                PacketPropType::Inner,
+                "object",
+                "encapsulated ethernet or ip packet",
+            ),
+            PropInfo::new(PacketPropType::Payload, "bytes", "vxlan payload"),
+        ],
+    },
+    ProtoInfo {
+        name: "geneve",
+        props: &[
+            PropInfo::new(PacketPropType::Vni, "integer", "virtual network identifier"),
+            PropInfo::new(
+                PacketPropType::EtherType,
+                "integer",
+                "ethertype of the inner packet",
+            ),
+            PropInfo::new(
+                PacketPropType::Inner,
+                "object",
+                "encapsulated ethernet or ip packet",
+            ),
+            PropInfo::new(PacketPropType::Payload, "bytes", "geneve payload"),
+        ],
+    },
+];
+
+/// Return the property paths of the form '<object>.<property>' for
+/// all the packet objects
+pub fn property_paths() -> Vec<String> {
+    PACKET_PROTOS
+        .iter()
+        .flat_map(|p| {
+            p.props
+                .iter()
+                .map(move |i| format!("{}.{}", p.name, i.prop))
+        })
+        .collect()
+}
+
+/// Describe the properties of the given packet object, or of all the
+/// packet objects if none is specified, one property path per line
+pub fn describe_fields(proto: Option<&str>) -> Result<String, String> {
+    let protos: Vec<&ProtoInfo> = match proto {
+        Some(name) => match PACKET_PROTOS.iter().find(|p| p.name == name) {
+            Some(p) => vec![p],
+            None => {
+                let names: Vec<&str> = PACKET_PROTOS.iter().map(|p| p.name).collect();
+                return Err(format!(
+                    "unknown object '{}'. expected one of: {}",
+                    name,
+                    names.join(", ")
+                ));
+            }
+        },
+        None => PACKET_PROTOS.iter().collect(),
+    };
+    let mut out = String::new();
+    for p in protos {
+        for info in p.props {
+            let path = format!("{}.{}", p.name, info.prop);
+            out.push_str(&format!("{:<20} {:<8} {}\n", path, info.kind, info.desc));
+        }
+    }
+    Ok(out)
+}
+
+/// Return the properties whose paths of the form '<object>.<property>'
+/// match a pattern in which '*' matches any sequence of characters,
+/// e.g. 'tcp.*' for all the tcp properties or '*.src' for the sources.
+pub fn match_props(pattern: &str) -> Vec<(&'static str, &'static PropInfo)> {
+    PACKET_PROTOS
+        .iter()
+        .flat_map(|p| p.props.iter().map(move |i| (p.name, i)))
+        .filter(|(name, info)| glob_match(pattern, &format!("{}.{}", name, info.prop)))
+        .collect()
+}
+
+/// Match a text against a pattern in which '*' matches any sequence of
+/// characters
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((head, rest)) => {
+            let Some(text) = text.strip_prefix(head) else {
+                return false;
+            };
+            (0..=text.len())
+                .filter(|i| text.is_char_boundary(*i))
+                .any(|i| glob_match(rest, &text[i..]))
+        }
+    }
+}
@@ -0,0 +1,267 @@
+#![allow(unused_imports)]
+use super::opcode::*;
+use crate::code::definitions;
+use crate::code::definitions::Instructions;
+
+// Test the order of the Opcode enum variants
+#[test]
+fn test_opcode_order() {
+    for code in 0..(Opcode::Invalid as usize) {
+        let op = Opcode::from(code as u8);
+        assert_eq!(code, op as usize, "u8 to opcode conversion failed");
+    }
+}
+
+#[test]
+fn test_make() {
+    /*
+     * For opcode 'Constant', pass OpConstant and the operand 65534 to Make.
+     * Expect to get back a `Vec<u8>` holding three bytes. Of these three, the
+     * first one has to be the opcode, Constant, and the other two should be
+     * the big-endian encoding of 65534. That’s also why '65534' is used instead
+     * of the maximum value 65535. This way the endianness can be validated.
+     * 65534 is encoded in big endian as the byte sequence 0xFF 0xFE.
+     */
+    let tests: Vec<(Opcode, Vec<usize>, Vec<u8>)> = vec![
+        (
+            Opcode::Constant,
+            vec![65534],
+            vec![Opcode::Constant as u8, 255, 254],
+        ),
+        (
+            Opcode::GetLocal,
+            vec![255],
+            vec![Opcode::GetLocal as u8, 255],
+        ),
+        (Opcode::Add, vec![0], vec![Opcode::Add as u8]),
+        (
+            Opcode::Closure,
+            vec![65534, 255],
+            vec![Opcode::Closure as u8, 255, 254, 255],
+        ),
+    ];
+
+    for (op, operands, expected) in tests {
+        let instruction = definitions::make(op, &operands, 1);
+        assert_eq!(
+            instruction.len(),
+            expected.len(),
+            "instruction has wrong length. want={}, got={}",
+            expected.len(),
+            instruction.len()
+        );
+
+        for (i, &b) in expected.iter().enumerate() {
+            assert_eq!(
+                instruction.code[i], b,
+                "wrong byte at pos {}. want={}, got={}",
+                i, b, instruction.code[i]
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+fn concat_instructions(s: &[Instructions]) -> Instructions {
+    let mut out = Instructions::default();
+    for ins in s {
+        out.code.extend_from_slice(&ins.code);
+    }
+    out
+}
+
+#[test]
+fn test_instructions_string() {
+    let instructions = vec![
+        definitions::make(Opcode::Add, &[], 1),
+        definitions::make(Opcode::GetLocal, &[1], 1),
+        definitions::make(Opcode::Constant, &[2], 1),
+        definitions::make(Opcode::Constant, &[65535], 1),
+        definitions::make(Opcode::Closure, &[65535, 255], 1),
+    ];
+    // The '\' at the end of the lines escapes indentation in the next line
+    let expected = "\
+        0000 OpAdd\n\
+        0001 OpGetLocal 1\n\
+        0003 OpConstant 2\n\
+        0006 OpConstant 65535\n\
+        0009 OpClosure 65535 255\n";
+    let concatted = concat_instructions(&instructions);
+
+    assert_eq!(concatted.to_string(), expected);
+}
+
+#[test]
+fn test_instructions_listing() {
+    use crate::code::definitions::Symbols;
+    use crate::object::Object;
+    use std::rc::Rc;
+
+    let instructions = vec![
+        definitions::make(Opcode::GetGlobal, &[1], 1),
+        definitions::make(Opcode::JumpIfFalse, &[14], 1),
+        definitions::make(Opcode::Constant, &[0], 1),
+        definitions::make(Opcode::GetBuiltinFn, &[0], 1),
+        definitions::make(Opcode::Jump, &[19], 1),
+        definitions::make(Opcode::Constant, &[1], 1),
+        definitions::make(Opcode::GetProp, &[9], 1),
+        definitions::make(Opcode::GetGlobal, &[7], 1),
+        definitions::make(Opcode::Pop, &[], 1),
+    ];
+    let constants = vec![
+        Rc::new(Object::Str("a".repeat(50))),
+        Rc::new(Object::Integer(7)),
+    ];
+    let globals = vec!["x".to_string(), "y".to_string()];
+    let symbols = Symbols {
+        constants: &constants,
+        globals: &globals,
+    };
+    // Operands without a symbol, e.g. global 7, are shown as is
+    let expected = format!(
+        "\
+        0000 OpGetGlobal 1            ; y\n\
+        0003 OpJumpIfFalse 14         ; L0\n\
+        0006 OpConstant 0             ; \"{}...\n\
+        0009 OpGetBuiltinFn 0         ; len\n\
+        0011 OpJump 19                ; L1\n\
+        L0:\n\
+        0014 OpConstant 1             ; 7\n\
+        0017 OpGetProp 9              ; caplen\n\
+        L1:\n\
+        0019 OpGetGlobal 7\n\
+        0022 OpPop\n",
+        "a".repeat(39)
+    );
+    let concatted = concat_instructions(&instructions);
+
+    assert_eq!(concatted.listing(&symbols), expected);
+}
+
+#[test]
+fn test_instructions_listing_with_lines() {
+    use crate::code::definitions::Symbols;
+    use crate::object::Object;
+    use std::rc::Rc;
+
+    let instructions = vec![
+        definitions::make(Opcode::Constant, &[0], 1),
+        definitions::make(Opcode::SetGlobal, &[0], 1),
+        definitions::make(Opcode::GetGlobal, &[0], 12),
+        definitions::make(Opcode::JumpIfFalse, &[12], 12),
+        definitions::make(Opcode::Pop, &[], 1000),
+    ];
+    let constants = vec![Rc::new(Object::Integer(7))];
+    let globals = vec!["x".to_string()];
+    let symbols = Symbols {
+        constants: &constants,
+        globals: &globals,
+    };
+    let expected = "\
+        0000    1 OpConstant 0             ; 7\n\
+        0003    | OpSetGlobal 0            ; x\n\
+        0006   12 OpGetGlobal 0            ; x\n\
+        0009    | OpJumpIfFalse 12         ; L0\n\
+        L0:\n\
+        0012 1000 OpPop\n";
+    let mut concatted = Instructions::default();
+    for ins in instructions {
+        concatted.code.extend(ins.code);
+        concatted.lines.extend(ins.lines);
+    }
+
+    assert_eq!(concatted.listing_with_lines(&symbols), expected);
+}
+
+#[test]
+#[allow(clippy::unnecessary_cast)]
+fn test_read_operands() {
+    let tests = vec![
+        (Opcode::GetLocal, vec![255], 1),
+        (Opcode::Constant, vec![65535], 2),
+    ];
+
+    for (op, operands, bytes_read) in tests {
+        let instruction = definitions::make(op, &operands, 1);
+        let def = definitions::lookup(instruction.code[0] as u8).unwrap();
+        let (operands_read, n) = definitions::read_operands(def, &instruction.code[1..]);
+
+        assert_eq!(n, bytes_read, "n is wrong");
+
+        for (i, want) in operands.iter().enumerate() {
+            assert_eq!(operands_read[i], *want);
+        }
+    }
+}
+
+// Test that every packet property is described by at least one object
+#[test]
+fn test_packet_proto_metadata() {
+    use crate::code::prop::*;
+    use std::collections::HashSet;
+
+    let mut seen = HashSet::new();
+    for proto in PACKET_PROTOS {
+        let mut names = HashSet::new();
+        for info in proto.props {
+            assert_ne!(info.prop, PacketPropType::Invalid);
+            assert!(
+                names.insert(info.prop),
+                "duplicate property {}.{}",
+                proto.name,
+                info.prop
+            );
+            seen.insert(info.prop);
+        }
+    }
+    for p in 0..PacketPropType::Invalid as u8 {
+        let prop = PacketPropType::from(p);
+        assert!(seen.contains(&prop), "property '{}' not described", prop);
+    }
+
+    let fields = describe_fields(Some("udp")).unwrap();
+    assert_eq!(fields.lines().count(), 9);
+    assert!(fields.starts_with("udp.srcport"));
+    assert!(describe_fields(None).unwrap().contains("tcp.winsize"));
+    assert!(describe_fields(Some("foo")).is_err());
+    assert!(property_paths().contains(&"ipv6.hoplimit".to_string()));
+}
+
+#[test]
+fn test_match_props() {
+    use crate::code::prop::*;
+
+    let paths = |pattern: &str| -> Vec<(&str, PacketPropType)> {
+        match_props(pattern)
+            .into_iter()
+            .map(|(name, info)| (name, info.prop))
+            .collect()
+    };
+    let tcp = paths("tcp.*");
+    assert!(tcp.iter().all(|(name, _)| *name == "tcp"));
+    assert!(tcp.contains(&("tcp", PacketPropType::Syn)));
+    assert_eq!(paths("udp.*").len(), 9);
+    assert_eq!(paths("ipv4.src"), vec![("ipv4", PacketPropType::Src)]);
+    let src = paths("*.src");
+    assert!(src.contains(&("eth", PacketPropType::Src)));
+    assert!(src.contains(&("ipv6", PacketPropType::Src)));
+    assert!(src.iter().all(|(_, prop)| *prop == PacketPropType::Src));
+    assert_eq!(paths("*").len(), property_paths().len());
+    assert!(paths("tcp").is_empty());
+    assert!(paths("foo.*").is_empty());
+
+    // Layers within the packet objects
+    let layers: Vec<_> = match_props("ipv4.*")
+        .into_iter()
+        .filter(|(_, info)| info.is_layer())
+        .map(|(_, info)| info.prop)
+        .collect();
+    assert_eq!(
+        layers,
+        vec![
+            PacketPropType::Udp,
+            PacketPropType::Tcp,
+            PacketPropType::Gre
+        ]
+    );
+}
@@ -0,0 +1,382 @@
+//! On-disk cache of compiled scripts.
+//!
+//! Running a large rule file against many captures parses and compiles it
+//! every time. The bytecode of a script is saved under the cache directory,
+//! by default ~/.cache/p2sh, in a file named after the hash of the source
+//! and the options that change how it compiles. Later runs of the same
+//! script with the same version of the interpreter load the bytecode
+//! instead. A cache file that is missing, stale or corrupt is ignored and
+//! the script is compiled as usual.
+//!
+//! The file ends with a checksum of its contents. The instructions are
+//! checked when loading, so that a damaged or foreign file is rejected
+//! instead of crashing the VM; every opcode must be defined, and the
+//! operands must be complete and refer to existing constants, globals,
+//! builtins and addresses.
+//!
+//! The same format is used for the files written using '--compile' and
+//! run using '--exec'. Since the builtins are referred to by their index,
+//! a file is only run by the version of p2sh that compiled it.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use super::Bytecode;
+use crate::builtins::functions::{compile_regex, BUILTINFNS};
+use crate::code::definitions::{lookup, read_operands, Instructions};
+use crate::code::opcode::Opcode;
+use crate::object::file::FileHandle;
+use crate::object::func::CompiledFunction;
+use crate::object::serialize::{decode, encode};
+use crate::object::Object;
+use crate::vm::checkpoint::script_id;
+
+const MAGIC: &[u8; 4] = b"P2BC";
+// Bump when the encoding of the bytecode or the instruction set changes
+const VERSION: u8 = 7;
+const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Tags identifying the type of an encoded constant
+const CONST_VALUE: u8 = 0;
+const CONST_FUNC: u8 = 1;
+const CONST_STDIN: u8 = 2;
+const CONST_STDOUT: u8 = 3;
+const CONST_STDERR: u8 = 4;
+const CONST_REGEX: u8 = 5;
+
+/// Directory holding the compiled scripts; $XDG_CACHE_HOME/p2sh, or
+/// ~/.cache/p2sh if it is not set
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".cache"),
+    };
+    Some(base.join("p2sh"))
+}
+
+/// Path of the cache file for the script 'source' compiled with 'options',
+/// the command line options that change the bytecode generated
+pub fn cache_path(dir: &Path, source: &str, options: &[bool]) -> PathBuf {
+    let flags: String = options.iter().map(|o| if *o { '1' } else { '0' }).collect();
+    let key = format!("{}\0{}\0{}\0{}", PKG_VERSION, VERSION, flags, source);
+    dir.join(format!("{:016x}.p2c", script_id(&key)))
+}
+
+/// Load the bytecode saved to 'path'
+pub fn load(path: &Path) -> io::Result<Bytecode> {
+    let data = fs::read(path)?;
+    if data.len() < 5 || &data[..4] != MAGIC || data[4] != VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a compiled script",
+        ));
+    }
+    let mut reader = Reader { data, pos: 5 };
+    let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+    let version = reader.string().map_err(invalid)?.unwrap_or_default();
+    if version != PKG_VERSION {
+        return Err(invalid(format!(
+            "compiled by p2sh {}, not {}",
+            version, PKG_VERSION
+        )));
+    }
+    // The checksum follows the bytecode
+    let end = reader.data.len().saturating_sub(8).max(reader.pos);
+    let sum = reader.data.split_off(end);
+    if sum != checksum(&reader.data).to_be_bytes() {
+        return Err(invalid(String::from("checksum mismatch")));
+    }
+    let bytecode = reader.bytecode().map_err(invalid)?;
+    validate(&bytecode).map_err(invalid)?;
+    Ok(bytecode)
+}
+
+/// Save the bytecode to 'path', creating the cache directory if needed.
+/// The bytecode is written to a temporary file that is then renamed, so
+/// that concurrent runs never load a partially written file.
+pub fn save(path: &Path, bytecode: &Bytecode) -> io::Result<()> {
+    let mut data = Vec::new();
+    data.extend_from_slice(MAGIC);
+    data.push(VERSION);
+    encode_str(Some(PKG_VERSION), &mut data);
+    encode_bytecode(bytecode, &mut data)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let sum = checksum(&data);
+    data.extend_from_slice(&sum.to_be_bytes());
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.tmp", std::process::id()));
+    fs::write(&tmp, data)?;
+    fs::rename(&tmp, path)
+}
+
+// FNV-1a hash of the contents of a cache file, stored at the end of it
+fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// Check the instructions of the script and of each of its functions
+fn validate(bytecode: &Bytecode) -> Result<(), String> {
+    let funcs = bytecode
+        .constants
+        .iter()
+        .filter_map(|c| match c.as_ref() {
+            Object::Func(func) => Some(func),
+            _ => None,
+        })
+        .chain(&bytecode.filters)
+        .chain(&bytecode.filter_end)
+        .chain(bytecode.filter_intervals.iter().map(|(_, f)| f));
+    validate_instructions(&bytecode.instructions, bytecode)?;
+    for func in funcs {
+        validate_instructions(&func.instructions, bytecode)?;
+    }
+    Ok(())
+}
+
+// Check that each opcode is defined and that its operands are complete and
+// refer to constants, globals, builtins and addresses that exist
+fn validate_instructions(ins: &Instructions, bytecode: &Bytecode) -> Result<(), String> {
+    if ins.lines.len() != ins.code.len() {
+        return Err(String::from("line numbers do not match the instructions"));
+    }
+    let mut ip = 0;
+    while ip < ins.code.len() {
+        let def = lookup(ins.code[ip])?;
+        let next = ip + 1 + def.operands_len();
+        if next > ins.code.len() {
+            return Err(format!("truncated instruction at {:04}", ip));
+        }
+        let (operands, _) = read_operands(def, &ins.code[ip + 1..]);
+        let (limit, what) = match Opcode::from(ins.code[ip]) {
+            Opcode::Constant | Opcode::Closure => (bytecode.constants.len(), "constant"),
+            Opcode::DefineGlobal | Opcode::GetGlobal | Opcode::SetGlobal | Opcode::IncGlobal => {
+                (bytecode.globals.len(), "global")
+            }
+            Opcode::GetBuiltinFn => (BUILTINFNS.len(), "builtin"),
+            Opcode::Jump | Opcode::JumpIfFalse | Opcode::JumpIfFalseNoPop | Opcode::Try => {
+                (ins.code.len() + 1, "address")
+            }
+            _ => (usize::MAX, ""),
+        };
+        if operands.first().is_some_and(|&i| i >= limit) {
+            return Err(format!("invalid {} {} at {:04}", what, operands[0], ip));
+        }
+        if Opcode::from(ins.code[ip]) == Opcode::IterNext && operands[1] > ins.code.len() {
+            return Err(format!("invalid address {} at {:04}", operands[1], ip));
+        }
+        ip = next;
+    }
+    Ok(())
+}
+
+fn encode_bytecode(bytecode: &Bytecode, out: &mut Vec<u8>) -> Result<(), String> {
+    encode_instructions(&bytecode.instructions, out);
+    encode_len(bytecode.constants.len(), out);
+    for constant in &bytecode.constants {
+        match constant.as_ref() {
+            Object::Func(func) => {
+                out.push(CONST_FUNC);
+                encode_func(func, out);
+            }
+            Object::File(file) => match file.as_ref() {
+                FileHandle::Stdin => out.push(CONST_STDIN),
+                FileHandle::Stdout => out.push(CONST_STDOUT),
+                FileHandle::Stderr => out.push(CONST_STDERR),
+                _ => return Err(format!("cannot encode constant {}", constant)),
+            },
+            Object::Regex(re) => {
+                out.push(CONST_REGEX);
+                encode_len(re.pattern().len(), out);
+                out.extend_from_slice(re.pattern().as_bytes());
+            }
+            obj => {
+                out.push(CONST_VALUE);
+                encode(obj, out)?;
+            }
+        }
+    }
+    encode_len(bytecode.filters.len(), out);
+    for filter in &bytecode.filters {
+        encode_func(filter, out);
+    }
+    match &bytecode.filter_end {
+        Some(end) => {
+            out.push(1);
+            encode_func(end, out);
+        }
+        None => out.push(0),
+    }
+    encode_len(bytecode.filter_intervals.len(), out);
+    for (secs, filter) in &bytecode.filter_intervals {
+        out.extend_from_slice(&secs.to_bits().to_be_bytes());
+        encode_func(filter, out);
+    }
+    encode_len(bytecode.globals.len(), out);
+    for name in &bytecode.globals {
+        encode_str(Some(name), out);
+    }
+    Ok(())
+}
+
+fn encode_func(func: &CompiledFunction, out: &mut Vec<u8>) {
+    encode_instructions(&func.instructions, out);
+    encode_len(func.num_locals, out);
+    encode_len(func.num_params, out);
+    encode_len(func.line, out);
+    encode_str(func.name.as_deref(), out);
+    encode_str(func.pattern.as_deref(), out);
+    out.push(func.action as u8);
+    encode_str(func.module.as_deref(), out);
+}
+
+fn encode_str(s: Option<&str>, out: &mut Vec<u8>) {
+    match s {
+        Some(s) => {
+            out.push(1);
+            encode_len(s.len(), out);
+            out.extend_from_slice(s.as_bytes());
+        }
+        None => out.push(0),
+    }
+}
+
+fn encode_instructions(ins: &Instructions, out: &mut Vec<u8>) {
+    encode_len(ins.code.len(), out);
+    out.extend_from_slice(&ins.code);
+    encode_len(ins.lines.len(), out);
+    for line in &ins.lines {
+        encode_len(*line, out);
+    }
+}
+
+fn encode_len(len: usize, out: &mut Vec<u8>) {
+    out.extend_from_slice(&(len as u32).to_be_bytes());
+}
+
+struct Reader {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl Reader {
+    fn bytes(&mut self, len: usize) -> Result<&[u8], String> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or("unexpected end of data")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut buf = [0; N];
+        buf.copy_from_slice(self.bytes(N)?);
+        Ok(buf)
+    }
+
+    fn len(&mut self) -> Result<usize, String> {
+        Ok(u32::from_be_bytes(self.array()?) as usize)
+    }
+
+    fn flag(&mut self) -> Result<bool, String> {
+        Ok(self.array::<1>()?[0] != 0)
+    }
+
+    fn string(&mut self) -> Result<Option<String>, String> {
+        if !self.flag()? {
+            return Ok(None);
+        }
+        let len = self.len()?;
+        let s = self.bytes(len)?.to_vec();
+        String::from_utf8(s).map(Some).map_err(|e| e.to_string())
+    }
+
+    fn bytecode(&mut self) -> Result<Bytecode, String> {
+        let instructions = self.instructions()?;
+        let count = self.len()?;
+        let mut constants = Vec::new();
+        for _ in 0..count {
+            let [tag] = self.array()?;
+            let constant = match tag {
+                CONST_VALUE => {
+                    let (obj, len) = decode(&self.data[self.pos..])?;
+                    self.pos += len;
+                    obj
+                }
+                CONST_FUNC => Rc::new(Object::Func(self.func()?)),
+                CONST_STDIN => Rc::new(Object::File(Rc::new(FileHandle::Stdin))),
+                CONST_STDOUT => Rc::new(Object::File(Rc::new(FileHandle::Stdout))),
+                CONST_STDERR => Rc::new(Object::File(Rc::new(FileHandle::Stderr))),
+                CONST_REGEX => {
+                    let len = self.len()?;
+                    let pattern =
+                        String::from_utf8(self.bytes(len)?.to_vec()).map_err(|e| e.to_string())?;
+                    Rc::new(Object::Regex(compile_regex(&pattern)?))
+                }
+                _ => return Err(format!("invalid constant tag {}", tag)),
+            };
+            constants.push(constant);
+        }
+        let count = self.len()?;
+        let filters = (0..count)
+            .map(|_| self.func())
+            .collect::<Result<Vec<_>, _>>()?;
+        let filter_end = if self.flag()? {
+            Some(self.func()?)
+        } else {
+            None
+        };
+        let count = self.len()?;
+        let mut filter_intervals = Vec::new();
+        for _ in 0..count {
+            let secs = f64::from_bits(u64::from_be_bytes(self.array()?));
+            filter_intervals.push((secs, self.func()?));
+        }
+        let count = self.len()?;
+        let globals = (0..count)
+            .map(|_| self.string().map(Option::unwrap_or_default))
+            .collect::<Result<Vec<_>, _>>()?;
+        if self.pos != self.data.len() {
+            return Err(String::from("trailing data"));
+        }
+        Ok(Bytecode {
+            instructions,
+            constants,
+            filters,
+            filter_end,
+            filter_intervals,
+            globals,
+        })
+    }
+
+    fn func(&mut self) -> Result<Rc<CompiledFunction>, String> {
+        let instructions = self.instructions()?;
+        let num_locals = self.len()?;
+        let num_params = self.len()?;
+        let line = self.len()?;
+        let mut func = CompiledFunction::new(instructions, num_locals, num_params, line);
+        func.name = self.string()?;
+        func.pattern = self.string()?;
+        func.action = self.flag()?;
+        func.module = self.string()?;
+        Ok(Rc::new(func))
+    }
+
+    fn instructions(&mut self) -> Result<Instructions, String> {
+        let len = self.len()?;
+        let code = self.bytes(len)?.to_vec();
+        let count = self.len()?;
+        let lines = (0..count)
+            .map(|_| self.len())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Instructions::new(code, lines))
+    }
+}
@@ -1,23 +1,39 @@
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::fs;
+use std::mem;
 use std::rc::Rc;
 
 use self::symtab::Symbol;
 use self::symtab::SymbolScope;
+use crate::builtins::functions::compile_regex;
 use crate::builtins::functions::BUILTINFNS;
+use crate::builtins::functions::BUILTIN_ALIASES;
 use crate::builtins::variables::BuiltinVarType;
 use crate::code::definitions::{self, *};
 use crate::code::opcode::Opcode;
 use crate::compiler::error::CompileError;
 use crate::compiler::symtab::SymbolTable;
 use crate::object::file::FileHandle;
+use crate::object::func::BuiltinAlias;
 use crate::object::func::CompiledFunction;
 use crate::object::Object;
 use crate::parser::ast::expr::*;
 use crate::parser::ast::stmt::BlockStatement;
 use crate::parser::ast::stmt::FilterPattern;
 use crate::parser::ast::stmt::FilterStmt;
+use crate::parser::ast::stmt::ForStmt;
+use crate::parser::ast::stmt::ImportStmt;
+use crate::parser::ast::stmt::IncrStmt;
 use crate::parser::ast::stmt::Statement;
+use crate::parser::ast::stmt::TryStmt;
 use crate::parser::ast::*;
+use crate::parser::Parser;
+use crate::scanner::token::TokenType;
+use crate::scanner::Scanner;
+use crate::stdlib;
 
+pub mod cache;
 pub mod error;
 pub mod symtab;
 pub mod symtab_test;
@@ -28,6 +44,109 @@ pub struct Bytecode {
     pub constants: Vec<Rc<Object>>,
     pub filters: Vec<Rc<CompiledFunction>>,
     pub filter_end: Option<Rc<CompiledFunction>>,
+    // Filters run at intervals of the given number of seconds
+    pub filter_intervals: Vec<(f64, Rc<CompiledFunction>)>,
+    // Names of the global variables by index, used to disassemble and
+    // to report the objects held by each global
+    pub globals: Vec<String>,
+}
+
+impl Bytecode {
+    /// List the filter statements in the order in which they are run,
+    /// with the line, the kind, whether there is an action and the source
+    /// text of the pattern of each
+    pub fn list_filters(&self) -> String {
+        let mut rows = Vec::new();
+        let intervals = self.filter_intervals.iter().map(|(_, f)| ("interval", f));
+        let filters = self
+            .filters
+            .iter()
+            .map(|f| ("filter", f))
+            .chain(intervals)
+            .chain(self.filter_end.iter().map(|f| ("end", f)));
+        for (kind, func) in filters {
+            let location = match &func.module {
+                Some(module) => format!("{}:{}", module, func.line),
+                None => func.line.to_string(),
+            };
+            let pattern = match func.pattern.as_deref() {
+                Some("") | None => "(none)",
+                Some(pattern) => pattern,
+            };
+            let action = if func.action { "yes" } else { "no" };
+            rows.push((location, kind, action, pattern));
+        }
+        // The locations in modules are wider than the line numbers
+        let width = rows.iter().map(|r| r.0.len() + 2).max().unwrap_or(0).max(6);
+        let mut out = format!(
+            "{:<width$}{:<10}{:<8}{}\n",
+            "LINE", "KIND", "ACTION", "PATTERN"
+        );
+        for (location, kind, action, pattern) in rows {
+            out.push_str(&format!(
+                "{:<width$}{:<10}{:<8}{}\n",
+                location, kind, action, pattern
+            ));
+        }
+        out
+    }
+
+    /// Disassemble the main program followed by the functions among the
+    /// constants and the filter statements. Operands are shown with their
+    /// meaning, e.g. the value of a constant or the name of a global.
+    pub fn disassemble(&self) -> String {
+        self.disassemble_from(0)
+    }
+
+    /// Disassemble leaving out the functions among the constants before
+    /// 'first_constant', e.g. those compiled by earlier lines in the REPL
+    pub fn disassemble_from(&self, first_constant: usize) -> String {
+        self.write_disassembly(first_constant, false)
+    }
+
+    /// Disassemble the whole program as shown by '--disasm'; the listings
+    /// show the source line of each instruction and are preceded by the
+    /// pool of constants
+    pub fn disassemble_with_lines(&self) -> String {
+        self.write_disassembly(0, true)
+    }
+
+    fn write_disassembly(&self, first_constant: usize, with_lines: bool) -> String {
+        let symbols = Symbols {
+            constants: &self.constants,
+            globals: &self.globals,
+        };
+        let listing = |ins: &Instructions| match with_lines {
+            true => ins.listing_with_lines(&symbols),
+            false => ins.listing(&symbols),
+        };
+        let mut out = format!("== main ==\n{}", listing(&self.instructions));
+        if with_lines {
+            out.push_str("\n== constants ==\n");
+            for (i, obj) in self.constants.iter().enumerate() {
+                out.push_str(&format!("{:04} {}\n", i, describe_constant(obj)));
+            }
+        }
+        for (i, obj) in self.constants.iter().enumerate().skip(first_constant) {
+            if let Object::Func(func) = obj.as_ref() {
+                out.push_str(&format!(
+                    "\n== constant {}: fn at line {} ==\n{}",
+                    i,
+                    func.line,
+                    listing(&func.instructions)
+                ));
+            }
+        }
+        let intervals = self.filter_intervals.iter().map(|(_, f)| f);
+        for func in self.filters.iter().chain(intervals).chain(&self.filter_end) {
+            out.push_str(&format!(
+                "\n== {} ==\n{}",
+                func,
+                listing(&func.instructions)
+            ));
+        }
+        out
+    }
 }
 
 #[derive(Default, Clone)]
@@ -49,6 +168,10 @@ struct LoopContext {
     begin: usize,
     // positions of 'break' instructions in the current loop
     break_positions: Vec<usize>,
+    // 'for' loops keep the state of the iteration on the stack
+    iter: bool,
+    // number of 'try' blocks the loop is within
+    tries: usize,
 }
 
 impl LoopContext {
@@ -57,6 +180,8 @@ impl LoopContext {
             label,
             begin: position,
             break_positions: Vec::new(),
+            iter: false,
+            tries: 0,
         }
     }
 }
@@ -70,6 +195,7 @@ struct CompilationScope {
     prev_ins: EmittedInstruction, // instruction before the last
     loop_stack: Vec<LoopContext>, // stack of 'loop' instructions
     scope_depth: usize,           // depth within the current scope
+    tries: usize,                 // number of 'try' blocks being compiled
 }
 
 pub struct Compiler {
@@ -79,6 +205,18 @@ pub struct Compiler {
     scope_index: usize,
     pub filters: Vec<Rc<CompiledFunction>>,
     pub filter_end: Option<Rc<CompiledFunction>>,
+    pub filter_intervals: Vec<(f64, Rc<CompiledFunction>)>,
+    aliases: Vec<BuiltinAlias>,
+    deny_deprecated: bool,
+    imported: HashSet<String>, // paths of the modules imported
+    module: Option<String>,    // path of the module being compiled
+    warned: Cell<bool>,        // set if a warning was displayed
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Compiler {
@@ -104,9 +242,26 @@ impl Compiler {
             scope_index: 0,
             filters: Vec::new(),
             filter_end: None,
+            filter_intervals: Vec::new(),
+            aliases: BUILTIN_ALIASES.to_vec(),
+            deny_deprecated: false,
+            imported: HashSet::new(),
+            module: None,
+            warned: Cell::new(false),
         }
     }
 
+    /// Check if the bytecode can be cached, which is when it depends only on
+    /// the source of the script. Scripts that import modules from files, or
+    /// that produced warnings which would not be displayed again, are not.
+    pub fn cacheable(&self) -> bool {
+        !self.warned.get()
+            && self
+                .imported
+                .iter()
+                .all(|path| path.starts_with(stdlib::STD_PREFIX))
+    }
+
     pub fn new_with_state(symtab: SymbolTable, constants: Vec<Rc<Object>>) -> Compiler {
         let mut compiler = Self::new();
         compiler.constants = constants;
@@ -114,6 +269,11 @@ impl Compiler {
         compiler
     }
 
+    /// Treat the use of deprecated builtin names as errors
+    pub fn set_deny_deprecated(&mut self, deny: bool) {
+        self.deny_deprecated = deny;
+    }
+
     /// Enter a local scope. This is used when compiling a function body.
     pub fn enter_scope(&mut self) {
         let scope = CompilationScope::default();
@@ -141,62 +301,15 @@ impl Compiler {
         let constants = self.constants.clone();
         let filters = self.filters.clone();
         let filter_end = self.filter_end.clone();
-        #[cfg(feature = "debug_print_code")]
-        {
-            instructions.disassemble();
-            self.print_constants();
-            self.print_filters();
-        }
+        let filter_intervals = self.filter_intervals.clone();
+        let globals = self.symtab.global_names();
         Bytecode {
             instructions,
             constants,
             filters,
             filter_end,
-        }
-    }
-
-    #[allow(dead_code)]
-    fn print_constants(&self) {
-        let constants = self.constants.clone();
-        eprintln!(
-            "----------- Constants [len: {:<4}] --------------------",
-            constants.len(),
-        );
-
-        for (i, obj) in constants.iter().enumerate() {
-            eprintln!("[{}] {}", i, obj);
-            match obj.as_ref() {
-                Object::Clos(cl) => {
-                    let closure = cl.clone();
-                    closure.func.instructions.disassemble();
-                }
-                Object::Func(func) => {
-                    func.instructions.disassemble();
-                }
-                _ => {}
-            }
-        }
-        eprintln!("------------------------------------------------------");
-    }
-
-    #[allow(dead_code)]
-    fn print_filters(&self) {
-        let filters = self.filters.clone();
-        if filters.is_empty() && self.filter_end.is_none() {
-            return;
-        }
-        eprintln!(
-            "------------- Filters [len: {:<4}] --------------------",
-            filters.len(),
-        );
-
-        for (i, func) in filters.iter().enumerate() {
-            eprintln!("[{}] {}", i, func);
-            func.instructions.disassemble();
-        }
-        if let Some(end) = self.filter_end.as_ref() {
-            eprintln!("[end] {}", end);
-            end.instructions.disassemble();
+            filter_intervals,
+            globals,
         }
     }
 
@@ -347,6 +460,12 @@ impl Compiler {
     fn compile_statement(&mut self, stmt: Statement) -> Result<(), CompileError> {
         match stmt {
             Statement::Expr(stmt) => {
+                if let Expression::Function(_) = stmt.value {
+                    self.warn(
+                        "anonymous function as statement has no effect",
+                        stmt.token.line,
+                    );
+                }
                 self.compile_expression(stmt.value)?;
                 // Unlike the 'let' and the 'return' statments, expression
                 // statements do not consume the result of the expression
@@ -360,6 +479,7 @@ impl Compiler {
                 // Defining the symbol before the value allows compiling
                 // recursive functions that has reference to its own name.
                 let depth = self.scopes[self.scope_index].scope_depth;
+                self.check_shadowing(&stmt.name.value, stmt.name.token.line);
                 let symbol = self.symtab.define(&stmt.name.value, depth);
                 self.compile_let_stmt(stmt.value)?;
 
@@ -399,7 +519,7 @@ impl Compiler {
                 } else {
                     LoopContext::new(None, loop_begin)
                 };
-                self.scopes[self.scope_index].loop_stack.push(loop_label);
+                self.push_loop(loop_label);
                 // Compile the body of the loop
                 self.compile_block_statement(stmt.body)?;
                 // Instruction to jump to beginning of the loop
@@ -423,7 +543,7 @@ impl Compiler {
                 } else {
                     LoopContext::new(None, loop_begin)
                 };
-                self.scopes[self.scope_index].loop_stack.push(loop_label);
+                self.push_loop(loop_label);
 
                 // Compile the condition expression
                 self.compile_expression(stmt.condition)?;
@@ -446,6 +566,9 @@ impl Compiler {
                     }
                 }
             }
+            Statement::For(stmt) => {
+                self.compile_for_statement(stmt)?;
+            }
             Statement::Break(stmt) => {
                 // If loop stack is empty, the control is outside a loop
                 if self.scopes[self.scope_index].loop_stack.is_empty() {
@@ -454,27 +577,31 @@ impl Compiler {
                         stmt.token.line,
                     ));
                 } else {
-                    // Placeholder instruction to jump to end of the loop
-                    let pos = self.emit(Opcode::Jump, &[0xFFFF], stmt.token.line);
-
                     // Save the position of the 'break' instruction so it can be patched later
                     // Add the break position to the current inner most loop
                     if let Some(label) = stmt.label {
                         // Labeled break statements. Find the loop label and add the break position
-                        let loop_stack = &mut self.scopes[self.scope_index].loop_stack;
-                        for loop_label in loop_stack.iter_mut().rev() {
-                            if let Some(loop_label_name) = &loop_label.label {
-                                if loop_label_name == &label.literal {
-                                    loop_label.break_positions.push(pos);
-                                    return Ok(());
-                                }
-                            }
-                        }
-                        return Err(CompileError::new(
-                            &format!("unknown loop label '{}'", label.literal),
-                            stmt.token.line,
-                        ));
+                        let loop_stack = &self.scopes[self.scope_index].loop_stack;
+                        let target = loop_stack
+                            .iter()
+                            .rposition(|l| l.label.as_ref() == Some(&label.literal));
+                        let Some(target) = target else {
+                            return Err(CompileError::new(
+                                &format!("unknown loop label '{}'", label.literal),
+                                stmt.token.line,
+                            ));
+                        };
+                        self.emit_loop_exits(target, stmt.token.line);
+                        // Placeholder instruction to jump to end of the loop
+                        let pos = self.emit(Opcode::Jump, &[0xFFFF], stmt.token.line);
+                        self.scopes[self.scope_index].loop_stack[target]
+                            .break_positions
+                            .push(pos);
                     } else {
+                        let target = self.scopes[self.scope_index].loop_stack.len() - 1;
+                        self.emit_loop_exits(target, stmt.token.line);
+                        // Placeholder instruction to jump to end of the loop
+                        let pos = self.emit(Opcode::Jump, &[0xFFFF], stmt.token.line);
                         // Anonymous break statements
                         if let Some(last) = self.scopes[self.scope_index].loop_stack.last_mut() {
                             last.break_positions.push(pos);
@@ -495,20 +622,22 @@ impl Compiler {
                     if let Some(label) = stmt.label {
                         // Labeled continue statements. Find the loop label and add the break position
                         let loop_stack = &self.scopes[self.scope_index].loop_stack;
-                        for loop_label in loop_stack.iter().rev() {
-                            if let Some(loop_label_name) = &loop_label.label {
-                                if loop_label_name == &label.literal {
-                                    self.emit(Opcode::Jump, &[loop_label.begin], stmt.token.line);
-                                    return Ok(());
-                                }
-                            }
-                        }
-                        return Err(CompileError::new(
-                            &format!("unknown loop label '{}'", label.literal),
-                            stmt.token.line,
-                        ));
+                        let target = loop_stack
+                            .iter()
+                            .rposition(|l| l.label.as_ref() == Some(&label.literal));
+                        let Some(target) = target else {
+                            return Err(CompileError::new(
+                                &format!("unknown loop label '{}'", label.literal),
+                                stmt.token.line,
+                            ));
+                        };
+                        self.emit_loop_exits(target, stmt.token.line);
+                        let begin = self.scopes[self.scope_index].loop_stack[target].begin;
+                        self.emit(Opcode::Jump, &[begin], stmt.token.line);
                     } else {
                         // Anonymous continue statements.
+                        let target = self.scopes[self.scope_index].loop_stack.len() - 1;
+                        self.emit_loop_exits(target, stmt.token.line);
                         // Emit a 'Jump' instruction to the beginning of the current loop
                         let loop_stack = &self.scopes[self.scope_index].loop_stack;
                         if let Some(loop_label) = loop_stack.last() {
@@ -521,6 +650,7 @@ impl Compiler {
                 // Defining the symbol before the value allows compiling
                 // recursive functions that has reference to its own name.
                 let depth = self.scopes[self.scope_index].scope_depth;
+                self.check_shadowing(&func.name, func.token.line);
                 let symbol = self.symtab.define(&func.name, depth);
                 let line = func.token.line;
                 self.compile_function_literal(func)?;
@@ -535,6 +665,15 @@ impl Compiler {
             Statement::Filter(f) => {
                 self.compile_filter_statement(f)?;
             }
+            Statement::Import(stmt) => {
+                self.compile_import_statement(stmt)?;
+            }
+            Statement::Try(stmt) => {
+                self.compile_try_statement(stmt)?;
+            }
+            Statement::Incr(stmt) => {
+                self.compile_incr_statement(stmt)?;
+            }
             Statement::Invalid => {
                 panic!("Invalid statement encountered");
             }
@@ -542,6 +681,209 @@ impl Compiler {
         Ok(())
     }
 
+    // A 'for' loop keeps the state of the iteration on the stack while the
+    // body runs. 'OpIterNext' defines the loop variables from the state
+    // until the iteration is done and then jumps to 'OpIterEnd', which
+    // drops the state. 'break' statements also jump to 'OpIterEnd'.
+    fn compile_for_statement(&mut self, stmt: ForStmt) -> Result<(), CompileError> {
+        let line = stmt.token.line;
+        let num_vars = stmt.vars.len();
+        match stmt.iterable {
+            Expression::Range(range) => {
+                if num_vars != 1 {
+                    return Err(CompileError::new(
+                        "a range can only be iterated over using one variable",
+                        line,
+                    ));
+                }
+                self.compile_expression(*range.begin)?;
+                self.compile_expression(*range.end)?;
+                let inclusive = range.operator == "..=";
+                self.emit(Opcode::IterRange, &[inclusive as usize], line);
+            }
+            iterable => {
+                self.compile_expression(iterable)?;
+                self.emit(Opcode::Iter, &[num_vars], line);
+            }
+        }
+
+        let loop_begin = self.get_curr_instructions().len();
+        let mut loop_label = LoopContext::new(stmt.label.map(|l| l.literal), loop_begin);
+        loop_label.iter = true;
+        self.push_loop(loop_label);
+        let next_pos = self.emit(Opcode::IterNext, &[num_vars, 0xFFFF], line);
+
+        // The loop variables are only visible within the loop. The value
+        // is on top of the stack, above the key.
+        self.scopes[self.scope_index].scope_depth += 1;
+        let depth = self.scopes[self.scope_index].scope_depth;
+        for var in stmt.vars.iter().rev() {
+            self.check_shadowing(&var.literal, var.line);
+            let symbol = self.symtab.define(&var.literal, depth);
+            if symbol.scope == SymbolScope::Global {
+                self.emit(Opcode::DefineGlobal, &[symbol.index], line);
+            } else {
+                self.emit(Opcode::DefineLocal, &[symbol.index], line);
+            }
+        }
+        self.compile_block_statement(stmt.body)?;
+        self.scopes[self.scope_index].scope_depth -= 1;
+        self.emit(Opcode::Jump, &[loop_begin], line);
+
+        let end_pos = self.get_curr_instructions().len();
+        let next = definitions::make(Opcode::IterNext, &[num_vars, end_pos], line);
+        self.replace_instruction(next_pos, &next.code);
+        if let Some(loop_curr) = self.scopes[self.scope_index].loop_stack.pop() {
+            for pos in loop_curr.break_positions.iter() {
+                self.patch_jump(*pos);
+            }
+        }
+        self.emit(Opcode::IterEnd, &[], line);
+        Ok(())
+    }
+
+    // Push a loop onto the loop stack, recording the 'try' blocks it is in
+    fn push_loop(&mut self, mut loop_label: LoopContext) {
+        let scope = &mut self.scopes[self.scope_index];
+        loop_label.tries = scope.tries;
+        scope.loop_stack.push(loop_label);
+    }
+
+    // Leave the 'try' blocks entered within the loop at 'target' in the
+    // loop stack and drop the state of the 'for' loops nested within it
+    // before jumping out of them
+    fn emit_loop_exits(&mut self, target: usize, line: usize) {
+        let scope = &self.scopes[self.scope_index];
+        let num_tries = scope.tries - scope.loop_stack[target].tries;
+        let num_iters = scope.loop_stack[target + 1..]
+            .iter()
+            .filter(|l| l.iter)
+            .count();
+        for _ in 0..num_tries {
+            self.emit(Opcode::EndTry, &[], line);
+        }
+        for _ in 0..num_iters {
+            self.emit(Opcode::IterEnd, &[], line);
+        }
+    }
+
+    // 'x++' and 'x--' update the variable or the indexed element in place
+    // using a single instruction whose last operand is 1 to subtract one
+    // instead of adding it. Unlike an assignment, nothing is left on the
+    // stack. The container and the index of 'OpIncIndex' are evaluated once.
+    fn compile_incr_statement(&mut self, stmt: IncrStmt) -> Result<(), CompileError> {
+        let line = stmt.token.line;
+        let decrement = (stmt.token.ttype == TokenType::MinusMinus) as usize;
+        match stmt.target {
+            Expression::Ident(ident) => {
+                let depth = self.scopes[self.scope_index].scope_depth;
+                let Some(symbol) = self.symtab.resolve(&ident.value, depth) else {
+                    return Err(CompileError::new(
+                        &format!("undefined identifier '{}'", ident.value),
+                        line,
+                    ));
+                };
+                let op = match symbol.scope {
+                    SymbolScope::Global => Opcode::IncGlobal,
+                    SymbolScope::Local => Opcode::IncLocal,
+                    SymbolScope::Free => Opcode::IncFree,
+                    _ => return Err(CompileError::new("Invalid lvalue", line)),
+                };
+                self.emit(op, &[symbol.index, decrement], line);
+            }
+            Expression::Index(index) => {
+                self.compile_expression(*index.left)?;
+                self.compile_expression(*index.index)?;
+                self.emit(Opcode::IncIndex, &[decrement], line);
+            }
+            _ => return Err(CompileError::new("Invalid lvalue", line)),
+        }
+        Ok(())
+    }
+
+    // A 'try' statement registers the address of the catch block with
+    // 'OpTry' before running the body and unregisters it with 'OpEndTry'
+    // once the body is done. A runtime error raised in between unwinds
+    // the stack and the frames to where they were at 'OpTry' and jumps
+    // to the catch block with the error on top of the stack.
+    // The catch block is placed ahead of the body so that the statement
+    // ends with 'OpEndTry'. Otherwise a function or a block that ends with
+    // it would take the last 'OpPop' of the catch block as its value.
+    fn compile_try_statement(&mut self, stmt: TryStmt) -> Result<(), CompileError> {
+        let line = stmt.token.line;
+        let body_pos = self.emit(Opcode::Jump, &[0xFFFF], line);
+        let handler_pos = self.get_curr_instructions().len();
+
+        // The error is only visible within the catch block
+        self.scopes[self.scope_index].scope_depth += 1;
+        let depth = self.scopes[self.scope_index].scope_depth;
+        self.check_shadowing(&stmt.var.literal, stmt.var.line);
+        let symbol = self.symtab.define(&stmt.var.literal, depth);
+        if symbol.scope == SymbolScope::Global {
+            self.emit(Opcode::DefineGlobal, &[symbol.index], line);
+        } else {
+            self.emit(Opcode::DefineLocal, &[symbol.index], line);
+        }
+        self.compile_block_statement(stmt.handler)?;
+        self.scopes[self.scope_index].scope_depth -= 1;
+        let end_pos = self.emit(Opcode::Jump, &[0xFFFF], line);
+        self.patch_jump(body_pos);
+
+        self.emit(Opcode::Try, &[handler_pos], line);
+        self.scopes[self.scope_index].tries += 1;
+        self.compile_block_statement(stmt.body)?;
+        self.scopes[self.scope_index].tries -= 1;
+        self.emit(Opcode::EndTry, &[], line);
+        self.patch_jump(end_pos);
+        Ok(())
+    }
+
+    // Compile the statements of a module in place of the import statement.
+    // Modules whose paths begin with 'std/' are taken from the standard
+    // library embedded in the binary and the rest are read from files.
+    // A module is compiled only the first time it is imported.
+    fn compile_import_statement(&mut self, stmt: ImportStmt) -> Result<(), CompileError> {
+        let line = stmt.token.line;
+        if self.scope_index > 0 || self.scopes[self.scope_index].scope_depth > 0 {
+            return Err(CompileError::new(
+                "modules can only be imported at the top level",
+                line,
+            ));
+        }
+        if !self.imported.insert(stmt.path.clone()) {
+            return Ok(());
+        }
+        let source = if stmt.path.starts_with(stdlib::STD_PREFIX) {
+            match stdlib::module(&stmt.path) {
+                Some(source) => source.to_string(),
+                None => {
+                    let msg = format!("no module named '{}'", stmt.path);
+                    return Err(CompileError::new(&msg, line));
+                }
+            }
+        } else {
+            fs::read_to_string(&stmt.path).map_err(|e| {
+                let msg = format!("failed to import '{}': {}", stmt.path, e);
+                CompileError::new(&msg, line)
+            })?
+        };
+
+        let mut parser = Parser::new(Scanner::new(&source));
+        let program = parser.parse_program();
+        if let Some(err) = parser.parse_errors().first() {
+            let msg = format!("in module '{}': {}", stmt.path, err);
+            return Err(CompileError::new(&msg, line));
+        }
+        // Errors in the module refer to the lines of the module
+        let outer = self.module.replace(stmt.path.clone());
+        let result = self.compile_statements(program.statements);
+        self.module = outer;
+        result.map_err(|e| {
+            let msg = format!("in module '{}' at line {}: {}", stmt.path, e.line, e.msg);
+            CompileError::new(&msg, line)
+        })
+    }
+
     fn compile_expression(&mut self, expr: Expression) -> Result<(), CompileError> {
         match expr {
             Expression::Invalid => {}
@@ -608,43 +950,10 @@ impl Compiler {
                 self.emit(Opcode::Map, &[len], map.token.line);
             }
             Expression::Binary(binary) => {
-                match binary.operator.as_ref() {
-                    "&&" => {
-                        self.compile_logical_and(*binary.left, *binary.right, binary.token.line)?;
-                    }
-                    "||" => {
-                        self.compile_logical_or(*binary.left, *binary.right, binary.token.line)?;
-                    }
-                    "<" | "<=" => {
-                        // In case of '<' or '<=', re order the operands to reuse the '>' or '>='
-                        self.compile_expression(*binary.right)?;
-                        self.compile_expression(*binary.left)?;
-                        self.compile_infix_expr(&binary.operator, binary.token.line)?;
-                    }
-                    _ => {
-                        self.compile_expression(*binary.left)?;
-                        self.compile_expression(*binary.right)?;
-                        self.compile_infix_expr(&binary.operator, binary.token.line)?;
-                    }
-                }
+                self.compile_binary_expression(binary)?;
             }
             Expression::Unary(u) => {
-                self.compile_expression(*u.right)?;
-                match u.operator.as_ref() {
-                    "!" => {
-                        self.emit(Opcode::Bang, &[0], u.token.line);
-                    }
-                    "-" => {
-                        self.emit(Opcode::Minus, &[0], u.token.line);
-                    }
-                    "~" => {
-                        self.emit(Opcode::Not, &[0], u.token.line);
-                    }
-                    "$" => {
-                        self.emit(Opcode::Dollar, &[0], u.token.line);
-                    }
-                    _ => return Err(CompileError::new("invalid unary operator", u.token.line)),
-                }
+                self.compile_unary_expression(u)?;
             }
             Expression::Bool(b) => {
                 if b.value {
@@ -656,6 +965,9 @@ impl Compiler {
             Expression::If(expr) => {
                 self.compile_if_expression(expr)?;
             }
+            Expression::Ternary(expr) => {
+                self.compile_ternary_expression(expr)?;
+            }
             Expression::Match(expr) => {
                 self.compile_match_expression(expr)?;
             }
@@ -681,13 +993,7 @@ impl Compiler {
                 self.compile_function_literal(func)?;
             }
             Expression::Call(call) => {
-                self.compile_expression(*call.func)?;
-                let num_args = call.args.len();
-                for arg in call.args {
-                    self.compile_expression(arg)?;
-                }
-                // First operand to OpCall is the number of arguments
-                self.emit(Opcode::Call, &[num_args], call.token.line);
+                self.compile_call_expression(call)?;
             }
             Expression::Dot(expr) => {
                 self.compile_dot_expression(expr)?;
@@ -699,6 +1005,78 @@ impl Compiler {
         Ok(())
     }
 
+    // The arms of 'compile_expression' that recurse are compiled in
+    // functions of their own to keep its stack frame small, since it
+    // recurses once for each level of nesting of an expression.
+    fn compile_binary_expression(&mut self, binary: BinaryExpr) -> Result<(), CompileError> {
+        // A chain such as 'a + b + c' nests to the left. The operands to the
+        // left are walked down in a loop, and the operators are applied on
+        // the way back, so that long chains compile without recursing once
+        // for each operator.
+        let mut chain = Vec::new();
+        let mut left = Expression::Binary(binary);
+        while let Expression::Binary(mut binary) = left {
+            let (next, right) = binary.take_operands();
+            let operator = mem::take(&mut binary.operator);
+            if operator == "<" || operator == "<=" {
+                // In case of '<' or '<=', re order the operands to reuse the '>' or '>='
+                self.compile_expression(right)?;
+                chain.push((operator, binary.token.line, None));
+            } else {
+                chain.push((operator, binary.token.line, Some(right)));
+            }
+            left = next;
+        }
+        self.compile_expression(left)?;
+        for (operator, line, right) in chain.into_iter().rev() {
+            match (operator.as_ref(), right) {
+                ("&&", Some(right)) => self.compile_logical_and(right, line)?,
+                ("||", Some(right)) => self.compile_logical_or(right, line)?,
+                ("~" | "!~", Some(right)) => self.compile_regex_match(&operator, right, line)?,
+                (_, Some(right)) => {
+                    self.compile_expression(right)?;
+                    self.compile_infix_expr(&operator, line)?;
+                }
+                (_, None) => self.compile_infix_expr(&operator, line)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_unary_expression(&mut self, u: UnaryExpr) -> Result<(), CompileError> {
+        self.compile_expression(*u.right)?;
+        match u.operator.as_ref() {
+            "!" => {
+                self.emit(Opcode::Bang, &[0], u.token.line);
+            }
+            "-" => {
+                self.emit(Opcode::Minus, &[0], u.token.line);
+            }
+            "~" => {
+                self.emit(Opcode::Not, &[0], u.token.line);
+            }
+            "$" => {
+                self.emit(Opcode::Dollar, &[0], u.token.line);
+            }
+            _ => return Err(CompileError::new("invalid unary operator", u.token.line)),
+        }
+        Ok(())
+    }
+
+    fn compile_call_expression(&mut self, call: CallExpr) -> Result<(), CompileError> {
+        if self.compile_introspection_call(&call)? {
+            return Ok(());
+        }
+        self.compile_expression(*call.func)?;
+        let num_args = call.args.len();
+        for arg in call.args {
+            self.compile_expression(arg)?;
+        }
+        // First operand to OpCall is the number of arguments
+        self.emit(Opcode::Call, &[num_args], call.token.line);
+        Ok(())
+    }
+
     fn compile_infix_expr(&mut self, operator: &str, line: usize) -> Result<(), CompileError> {
         match operator {
             "+" => {
@@ -740,6 +1118,9 @@ impl Compiler {
             "<<" => {
                 self.emit(Opcode::ShiftLeft, &[0], line);
             }
+            "in" => {
+                self.emit(Opcode::In, &[], line);
+            }
             ">>" => {
                 self.emit(Opcode::ShiftRight, &[0], line);
             }
@@ -748,6 +1129,30 @@ impl Compiler {
         Ok(())
     }
 
+    // A string literal on the right of '~' or '!~' is compiled to a regex
+    // once here, instead of each time the expression is evaluated. The
+    // left-hand side is compiled first by the caller.
+    fn compile_regex_match(
+        &mut self,
+        operator: &str,
+        right: Expression,
+        line: usize,
+    ) -> Result<(), CompileError> {
+        match right {
+            Expression::Str(s) => {
+                let re = compile_regex(&s.value).map_err(|e| CompileError::new(&e, line))?;
+                let idx = self.add_constant(Object::Regex(re));
+                self.emit(Opcode::Constant, &[idx], line);
+            }
+            right => self.compile_expression(right)?,
+        }
+        self.emit(Opcode::RegexMatch, &[], line);
+        if operator == "!~" {
+            self.emit(Opcode::Bang, &[0], line);
+        }
+        Ok(())
+    }
+
     fn compile_let_stmt(&mut self, expr: Expression) -> Result<Object, CompileError> {
         self.compile_expression(expr)?;
         Ok(Object::Null)
@@ -824,6 +1229,19 @@ impl Compiler {
         Ok(())
     }
 
+    // Compiled like an 'if' expression whose branches are single
+    // expressions that always produce a value
+    fn compile_ternary_expression(&mut self, expr: TernaryExpr) -> Result<(), CompileError> {
+        self.compile_expression(*expr.condition)?;
+        let jump_if_false_pos = self.emit(Opcode::JumpIfFalse, &[0xFFFF], expr.token.line);
+        self.compile_expression(*expr.then_expr)?;
+        let jump_pos = self.emit(Opcode::Jump, &[0xFFFF], expr.token.line);
+        self.patch_jump(jump_if_false_pos);
+        self.compile_expression(*expr.else_expr)?;
+        self.patch_jump(jump_pos);
+        Ok(())
+    }
+
     fn compile_match_expression(&mut self, match_expr: MatchExpr) -> Result<(), CompileError> {
         // Jump vector to jump to the end of the match expression
         // for all patterns in all of the match arms
@@ -1029,6 +1447,15 @@ impl Compiler {
                     self.save_symbol(symbol, expr.token.line)?;
                 }
             }
+        } else if let Some(symbol) = self.resolve_alias(&expr.token.literal, expr.token.line)? {
+            match expr.context.access {
+                AccessType::Get => {
+                    self.load_symbol(symbol, expr.token.line);
+                }
+                AccessType::Set => {
+                    self.save_symbol(symbol, expr.token.line)?;
+                }
+            }
         } else {
             return Err(CompileError::new(
                 &format!("undefined identifier '{}'", expr.token.literal),
@@ -1038,6 +1465,84 @@ impl Compiler {
         Ok(())
     }
 
+    // Display a warning. Scripts that produced warnings are not cached so
+    // that the warnings are displayed every time the script is run.
+    fn warn(&self, msg: &str, line: usize) {
+        eprintln!("[line {}] warning: {}", line, msg);
+        self.warned.set(true);
+    }
+
+    // Warn about a definition that hides a builtin function, which is then
+    // no longer callable in the scope of the definition.
+    fn check_shadowing(&self, name: &str, line: usize) {
+        if BUILTINFNS.iter().any(|b| b.name == name) {
+            self.warn(&format!("shadowing builtin '{}'", name), line);
+        }
+    }
+
+    // Resolve the old name of a renamed builtin function to the builtin it
+    // was renamed to. A warning is displayed unless deprecated names are
+    // denied, in which case it is a compile error.
+    fn resolve_alias(&self, name: &str, line: usize) -> Result<Option<Rc<Symbol>>, CompileError> {
+        let alias = match self.aliases.iter().find(|a| a.name == name) {
+            Some(alias) => alias,
+            None => return Ok(None),
+        };
+        let msg = format!(
+            "'{}' is deprecated; use '{}' instead",
+            alias.name, alias.target
+        );
+        if self.deny_deprecated {
+            return Err(CompileError::new(&msg, line));
+        }
+        self.warn(&msg, line);
+        Ok(BUILTINFNS
+            .iter()
+            .position(|b| b.name == alias.target)
+            .map(|idx| Rc::new(Symbol::new(alias.target, SymbolScope::BuiltinFn, idx, 0))))
+    }
+
+    // The builtins 'globals()' and 'locals()' need access to the names of
+    // the variables which are only known at compile time. So, a call to
+    // either of them is compiled into a map literal of the variable names
+    // to their current values. Returns false if the call is not one of them.
+    fn compile_introspection_call(&mut self, call: &CallExpr) -> Result<bool, CompileError> {
+        let name = match call.func.as_ref() {
+            Expression::Ident(ident) => ident.token.literal.as_str(),
+            _ => return Ok(false),
+        };
+        if name != "globals" && name != "locals" {
+            return Ok(false);
+        }
+        let depth = self.scopes[self.scope_index].scope_depth;
+        // Make sure that the name is not shadowed by a user defined symbol
+        match self.symtab.resolve(name, depth) {
+            Some(sym) if sym.scope == SymbolScope::BuiltinFn => {}
+            _ => return Ok(false),
+        }
+        if !call.args.is_empty() {
+            return Err(CompileError::new(
+                &format!("{}: takes no arguments. got={}", name, call.args.len()),
+                call.token.line,
+            ));
+        }
+        // At the global scope, the local variables are the global variables
+        let symbols = if name == "globals" || self.scope_index == 0 {
+            let global_depth = self.scopes[0].scope_depth;
+            self.symtab.global_table().visible_symbols(global_depth)
+        } else {
+            self.symtab.visible_symbols(depth)
+        };
+        let len = symbols.len() * 2;
+        for sym in symbols {
+            let idx = self.add_constant(Object::Str(sym.name.clone()));
+            self.emit(Opcode::Constant, &[idx], call.token.line);
+            self.load_symbol(sym, call.token.line);
+        }
+        self.emit(Opcode::Map, &[len], call.token.line);
+        Ok(true)
+    }
+
     fn compile_index_expression(&mut self, expr: IndexExpr) -> Result<(), CompileError> {
         // Compile the expression being indexed
         self.compile_expression(*expr.left)?;
@@ -1062,6 +1567,7 @@ impl Compiler {
         if !func.name.is_empty() {
             self.symtab.define_function_name(&func.name);
         }
+        let name = Some(func.name.clone()).filter(|n| !n.is_empty());
 
         // Tell the compiler to turn the local references to the function
         // parameters into OpGetLocal instructions that load the arguments
@@ -1070,6 +1576,7 @@ impl Compiler {
         // variables (num_locals) of the function.
         let num_params = func.params.len();
         for p in func.params {
+            self.check_shadowing(&p.value, p.token.line);
             self.symtab.define(&p.value, 0);
         }
         self.compile_block_statement(func.body)?;
@@ -1098,12 +1605,10 @@ impl Compiler {
         for f in &free_symbols {
             self.load_symbol(f.clone(), func.token.line);
         }
-        let compiled_fn = Object::Func(Rc::new(CompiledFunction::new(
-            instructions,
-            num_locals,
-            num_params,
-            func.token.line,
-        )));
+        let compiled_fn = Object::Func(Rc::new(CompiledFunction {
+            name,
+            ..CompiledFunction::new(instructions, num_locals, num_params, func.token.line)
+        }));
         let idx = self.add_constant(compiled_fn);
         // emit closure instruction with the index to the compiled fn
         // and with number of free variables
@@ -1111,7 +1616,7 @@ impl Compiler {
         Ok(())
     }
 
-    // The the left-hand side expression is compiled first. That means, at
+    // The left-hand side expression is compiled first, by the caller. So, at
     // runtime, its value will be on top of the stack. If that value is falsey,
     // then the entire expression must be false and so the right-hand side is
     // not evaluated at all. Otherwise, if lhs is truthy, the discard the value
@@ -1125,13 +1630,7 @@ impl Compiler {
     // right operand expression   |
     // continue            <------+
     //
-    fn compile_logical_and(
-        &mut self,
-        left: Expression,
-        right: Expression,
-        line: usize,
-    ) -> Result<(), CompileError> {
-        self.compile_expression(left)?;
+    fn compile_logical_and(&mut self, right: Expression, line: usize) -> Result<(), CompileError> {
         // Emit an 'JumpIfFalseNoPop' with a placeholder. Save it's position so it can be altered later
         // Jump over the right hand side expression if the left hand side is false
         let jump_if_false_pos = self.emit(Opcode::JumpIfFalseNoPop, &[0xFFFF], line);
@@ -1148,7 +1647,7 @@ impl Compiler {
         Ok(())
     }
 
-    // The the left-hand side expression is compiled first. That means, at
+    // The left-hand side expression is compiled first, by the caller. So, at
     // runtime, its value will be on top of the stack. If that value is truthy,
     // then the entire expression must be true and so the right-hand side is
     // not evaluated at all. Otherwise, if lhs is falsey, the discard the value
@@ -1163,13 +1662,7 @@ impl Compiler {
     // right operand expression       |
     // continue           <-----------+
     //
-    fn compile_logical_or(
-        &mut self,
-        left: Expression,
-        right: Expression,
-        line: usize,
-    ) -> Result<(), CompileError> {
-        self.compile_expression(left)?;
+    fn compile_logical_or(&mut self, right: Expression, line: usize) -> Result<(), CompileError> {
         // If lhs is false, jump to the rhs expression to evaluate that
         let rhs_pos = self.emit(Opcode::JumpIfFalseNoPop, &[0xFFFF], line);
         // If true, then use the result on the stack as the value of the entire expression
@@ -1214,6 +1707,7 @@ impl Compiler {
     /// the bytecode for the filter statement is captured and stored separately.
     fn compile_filter_statement(&mut self, expr: FilterStmt) -> Result<(), CompileError> {
         self.enter_scope();
+        let has_action = expr.action.is_some();
 
         // If there is no filter pattern, and if it is not an 'end' pattern,
         // then the control flow executes the action statement unconditionally.
@@ -1227,9 +1721,10 @@ impl Compiler {
         // Do not pop the result of the filter since it is returned by the filter
         // statement when the action is 'None'. In this case the caller of the filter
         // statement is responsible for popping the result.
-        if expr.pattern.is_none() || expr.pattern.is_end() {
-            // Always execute the action if the filter pattern is 'end'
-            // or if there is no filter pattern that defaults to true
+        if expr.pattern.is_none() || expr.pattern.is_end() || expr.pattern.is_interval() {
+            // Always execute the action if the filter pattern is 'end' or
+            // an interval, or if there is no filter pattern that defaults
+            // to true
             // Since a pattern was not evaulated, do not pop the result of the
             // pattern expression. So, pass 'false'.
             self.emit_action_stmt(expr.action, false, expr.token.line)?;
@@ -1245,15 +1740,17 @@ impl Compiler {
         let instructions = self.leave_scope();
         // There are not free variables for the function wrapping a filter
         // The filter statements are compiled as closures that takes no parameters
-        let filter = Rc::new(CompiledFunction::new(
-            instructions,
-            num_locals,
-            0,
-            expr.token.line,
-        ));
-
-        // Add the filter to the list of filters except for the 'end' pattern
-        if expr.pattern.is_end() {
+        let filter = Rc::new(CompiledFunction {
+            action: has_action,
+            module: self.module.clone(),
+            ..CompiledFunction::new_filter(instructions, num_locals, expr.token.line, &expr.source)
+        });
+
+        // Add the filter to the list of filters except for the 'end' and
+        // the interval patterns
+        if let FilterPattern::Interval(secs) = expr.pattern {
+            self.filter_intervals.push((secs, filter));
+        } else if expr.pattern.is_end() {
             if self.filter_end.is_some() {
                 return Err(CompileError::new(
                     "multiple 'end' patterns in filter statement",
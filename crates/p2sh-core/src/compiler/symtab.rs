@@ -141,6 +141,53 @@ impl SymbolTable {
         None
     }
 
+    // Return the symbols visible at the given depth that hold a value, i.e.
+    // globals, locals and free variables. For names that are defined more
+    // than once, only the most recently defined symbol visible at the given
+    // depth is returned. The symbols are ordered by their names.
+    pub fn visible_symbols(&self, depth: usize) -> Vec<Rc<Symbol>> {
+        let mut symbols: Vec<Rc<Symbol>> = self
+            .store
+            .values()
+            .filter_map(|symbols| {
+                symbols
+                    .iter()
+                    .rev()
+                    .find(|symbol| symbol.depth <= depth)
+                    .cloned()
+            })
+            .filter(|symbol| {
+                matches!(
+                    symbol.scope,
+                    SymbolScope::Global | SymbolScope::Local | SymbolScope::Free
+                )
+            })
+            .collect();
+        symbols.sort_by(|a, b| a.name.cmp(&b.name));
+        symbols
+    }
+
+    // Return the outermost (global) symbol table
+    pub fn global_table(&self) -> &SymbolTable {
+        match &self.outer {
+            Some(outer) => outer.global_table(),
+            None => self,
+        }
+    }
+
+    // Return the names of the global variables indexed by their slot in the
+    // globals store. This is kept in the bytecode for the disassembler.
+    pub fn global_names(&self) -> Vec<String> {
+        let table = self.global_table();
+        let mut names = vec![String::new(); table.num_definitions];
+        for symbol in table.store.values().flatten() {
+            if symbol.scope == SymbolScope::Global {
+                names[symbol.index] = symbol.name.clone();
+            }
+        }
+        names
+    }
+
     pub fn define_builtin_fn(&mut self, index: usize, name: &str) -> Rc<Symbol> {
         let symbol = Rc::new(Symbol::new(name, SymbolScope::BuiltinFn, index, 0));
         self.store
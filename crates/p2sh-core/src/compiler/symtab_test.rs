@@ -39,6 +39,7 @@ fn test_define() {
 }
 
 #[test]
+#[allow(clippy::useless_vec)]
 fn test_resolve_global() {
     let mut global = SymbolTable::default();
     let _ = global.define("a", 0);
@@ -376,6 +377,7 @@ fn test_symbol_depth_within_current_scope() {
 }
 
 #[test]
+#[allow(clippy::explicit_counter_loop)]
 fn test_scope_depth_and_free_variables() {
     struct ResolveTest {
         table: SymbolTable,
@@ -35,6 +35,7 @@ fn parse_program(input: &str) -> Program {
 }
 
 #[cfg(test)]
+#[allow(clippy::clone_on_copy, clippy::needless_borrow, clippy::ptr_arg)]
 pub fn test_constants(expected: &Vec<Object>, actual: &Vec<Rc<Object>>) {
     assert_eq!(
         actual.len(),
@@ -52,6 +53,7 @@ pub fn test_constants(expected: &Vec<Object>, actual: &Vec<Rc<Object>>) {
             Object::Char(c) => test_char_object(got.clone(), c.clone()),
             Object::Byte(b) => test_byte_object(got.clone(), b.clone()),
             Object::Func(func) => test_function_object(&got.clone(), &func),
+            Object::Regex(_) => assert_eq!(got.as_ref(), exp, "wrong regex"),
             _ => {}
         }
     }
@@ -136,6 +138,7 @@ fn test_byte_object(actual: Rc<Object>, exp: u8) {
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow, clippy::useless_vec)]
 fn test_function_object(actual_obj: &Object, expected: &CompiledFunction) {
     if let Object::Func(actual) = actual_obj {
         test_instructions(
@@ -187,6 +190,7 @@ fn test_instructions(expected: &[Instructions], actual: &Instructions) {
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow)]
 fn run_compiler_tests(tests: &[CompilerTestCase]) {
     for (n, t) in tests.iter().enumerate() {
         println!("[{}] Compiler Test", n);
@@ -203,6 +207,7 @@ fn run_compiler_tests(tests: &[CompilerTestCase]) {
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow)]
 fn run_compiler_failed_tests(tests: &[CompilerTestCaseErrors]) {
     for (n, t) in tests.iter().enumerate() {
         println!("[{}] Compiler Test", n);
@@ -750,6 +755,38 @@ fn test_conditionals_else_if() {
     run_compiler_tests(&tests);
 }
 
+#[test]
+fn test_conditionals_ternary() {
+    let tests = vec![CompilerTestCase {
+        input: "true ? 10 : 20; 3333;",
+        expected_constants: vec![
+            Object::Integer(10),
+            Object::Integer(20),
+            Object::Integer(3333),
+        ],
+        expected_instructions: vec![
+            // 0000 : The condition
+            definitions::make(Opcode::True, &[], 1),
+            // 0001 : Jump to the expression following ':'
+            definitions::make(Opcode::JumpIfFalse, &[10], 1),
+            // 0004 : The expression following '?'
+            definitions::make(Opcode::Constant, &[0], 1),
+            // 0007 : Jump over the expression following ':'
+            definitions::make(Opcode::Jump, &[13], 1),
+            // 0010 : The expression following ':'
+            definitions::make(Opcode::Constant, &[1], 1),
+            // 0013 : [ Not part of the ternary expr - Pop its result ]
+            definitions::make(Opcode::Pop, &[], 1),
+            // 0014 : The instruction following the ternary expr
+            definitions::make(Opcode::Constant, &[2], 1),
+            // 0017 : Pop Constant '3333'
+            definitions::make(Opcode::Pop, &[], 1),
+        ],
+    }];
+
+    run_compiler_tests(&tests);
+}
+
 #[test]
 fn test_global_let_statements() {
     let tests = vec![
@@ -3399,3 +3436,421 @@ fn test_dot_expressions() {
 
     run_compiler_tests(&tests);
 }
+
+#[test]
+fn test_builtin_aliases() {
+    let program = parse_program("length([]);");
+    let mut compiler = Compiler::new();
+    compiler.aliases = vec![BuiltinAlias::new("length", "len")];
+    if let Err(err) = compiler.compile(program) {
+        panic!("{}", err);
+    }
+    let bytecode = compiler.bytecode();
+    test_instructions(
+        &[
+            // the deprecated name resolves to the built-in fn 'len'
+            definitions::make(Opcode::GetBuiltinFn, &[0], 1),
+            definitions::make(Opcode::Array, &[0], 1),
+            definitions::make(Opcode::Call, &[1], 1),
+            definitions::make(Opcode::Pop, &[], 1),
+        ],
+        &bytecode.instructions,
+    );
+
+    // A user defined symbol takes precedence over the alias
+    let program = parse_program("let length = 1; length");
+    let mut compiler = Compiler::new();
+    compiler.aliases = vec![BuiltinAlias::new("length", "len")];
+    compiler.set_deny_deprecated(true);
+    if let Err(err) = compiler.compile(program) {
+        panic!("{}", err);
+    }
+
+    let program = parse_program("length([]);");
+    let mut compiler = Compiler::new();
+    compiler.aliases = vec![BuiltinAlias::new("length", "len")];
+    compiler.set_deny_deprecated(true);
+    match compiler.compile(program) {
+        Ok(_) => panic!("expected deprecation error"),
+        Err(err) => assert_eq!(
+            format!("{}", err),
+            "[line 1] compile error: 'length' is deprecated; use 'len' instead"
+        ),
+    }
+}
+
+#[test]
+fn test_compile_warnings() {
+    // Scripts that produce warnings are not cacheable
+    let tests = [
+        ("let len = 5; len", true),
+        ("fn str(x) { x }", true),
+        ("fn f(print) { print }", true),
+        ("for split in [1] { split }", true),
+        ("fn (x) { x };", true),
+        ("let lens = 5; fn f(x) { x } f(lens)", false),
+        ("let f = fn (x) { x }; f", false),
+    ];
+    for (input, warned) in tests {
+        let program = parse_program(input);
+        let mut compiler = Compiler::new();
+        if let Err(err) = compiler.compile(program) {
+            panic!("{}", err);
+        }
+        assert_eq!(compiler.cacheable(), !warned, "{}", input);
+    }
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_import_statements() {
+    let tests = vec![
+        CompilerTestCaseErrors {
+            input: r#"import "std/none";"#,
+            error: "[line 1] compile error: no module named 'std/none'",
+        },
+        CompilerTestCaseErrors {
+            input: r#"fn f() { import "std/stats"; }"#,
+            error: "[line 1] compile error: modules can only be imported at the top level",
+        },
+        CompilerTestCaseErrors {
+            input: r#"{ import "std/stats"; }"#,
+            error: "[line 1] compile error: modules can only be imported at the top level",
+        },
+    ];
+    run_compiler_failed_tests(&tests);
+
+    // A module is compiled only once
+    let program = parse_program(r#"import "std/stats"; import "std/stats";"#);
+    let mut compiler = Compiler::new();
+    if let Err(err) = compiler.compile(program) {
+        panic!("{}", err);
+    }
+    assert_eq!(compiler.bytecode().filters.len(), 1);
+}
+
+#[test]
+fn test_for_statements() {
+    let tests = vec![
+        CompilerTestCase {
+            input: "for x in [] { x; }",
+            expected_constants: vec![],
+            expected_instructions: vec![
+                // 0000 : The array to iterate over
+                definitions::make(Opcode::Array, &[0], 1),
+                // 0003 : Start the iteration with one loop variable
+                definitions::make(Opcode::Iter, &[1], 1),
+                // 0005 : Get the next value or jump to the end of the loop
+                definitions::make(Opcode::IterNext, &[1, 19], 1),
+                // 0009 : Define the loop variable 'x'
+                definitions::make(Opcode::DefineGlobal, &[0], 1),
+                // 0012 : The body of the loop
+                definitions::make(Opcode::GetGlobal, &[0], 1),
+                definitions::make(Opcode::Pop, &[], 1),
+                // 0016 : Jump to the beginning of the loop
+                definitions::make(Opcode::Jump, &[5], 1),
+                // 0019 : Drop the state of the iteration
+                definitions::make(Opcode::IterEnd, &[], 1),
+            ],
+        },
+        CompilerTestCase {
+            input: "outer: for i in 0..2 { for k, v in map {} { break outer; } }",
+            expected_constants: vec![Object::Integer(0), Object::Integer(2)],
+            expected_instructions: vec![
+                // 0000 : The bounds of the range
+                definitions::make(Opcode::Constant, &[0], 1),
+                definitions::make(Opcode::Constant, &[1], 1),
+                // 0006 : Start the iteration over an exclusive range
+                definitions::make(Opcode::IterRange, &[0], 1),
+                // 0008 : Get the next value and define 'i'
+                definitions::make(Opcode::IterNext, &[1, 41], 1),
+                definitions::make(Opcode::DefineGlobal, &[0], 1),
+                // 0015 : Start the iteration over the map with two variables
+                definitions::make(Opcode::Map, &[0], 1),
+                definitions::make(Opcode::Iter, &[2], 1),
+                // 0020 : Get the next pair and define 'v' and 'k'
+                definitions::make(Opcode::IterNext, &[2, 37], 1),
+                definitions::make(Opcode::DefineGlobal, &[1], 1),
+                definitions::make(Opcode::DefineGlobal, &[2], 1),
+                // 0030 : Drop the state of the inner loop and break out
+                definitions::make(Opcode::IterEnd, &[], 1),
+                definitions::make(Opcode::Jump, &[41], 1),
+                // 0034 : The end of the inner loop
+                definitions::make(Opcode::Jump, &[20], 1),
+                definitions::make(Opcode::IterEnd, &[], 1),
+                // 0038 : The end of the outer loop
+                definitions::make(Opcode::Jump, &[8], 1),
+                definitions::make(Opcode::IterEnd, &[], 1),
+            ],
+        },
+    ];
+    run_compiler_tests(&tests);
+
+    let tests = vec![CompilerTestCaseErrors {
+        input: "for i, x in 0..2 { }",
+        error: "[line 1] compile error: a range can only be iterated over using one variable",
+    }];
+    run_compiler_failed_tests(&tests);
+}
+
+#[test]
+fn test_try_statements() {
+    let tests = vec![
+        CompilerTestCase {
+            input: "try { 1; } catch e { e; }",
+            expected_constants: vec![Object::Integer(1)],
+            expected_instructions: vec![
+                // 0000 : Skip the catch block
+                definitions::make(Opcode::Jump, &[13], 1),
+                // 0003 : Define the error 'e' and run the catch block
+                definitions::make(Opcode::DefineGlobal, &[0], 1),
+                definitions::make(Opcode::GetGlobal, &[0], 1),
+                definitions::make(Opcode::Pop, &[], 1),
+                definitions::make(Opcode::Jump, &[21], 1),
+                // 0013 : Register the catch block
+                definitions::make(Opcode::Try, &[3], 1),
+                // 0016 : The body of the try block
+                definitions::make(Opcode::Constant, &[0], 1),
+                definitions::make(Opcode::Pop, &[], 1),
+                // 0020 : Unregister the catch block
+                definitions::make(Opcode::EndTry, &[], 1),
+            ],
+        },
+        CompilerTestCase {
+            input: "loop { try { break; } catch e { } }",
+            expected_constants: vec![],
+            expected_instructions: vec![
+                // 0000 : Skip the catch block
+                definitions::make(Opcode::Jump, &[9], 1),
+                // 0003 : The catch block
+                definitions::make(Opcode::DefineGlobal, &[0], 1),
+                definitions::make(Opcode::Jump, &[17], 1),
+                // 0009 : Register the catch block
+                definitions::make(Opcode::Try, &[3], 1),
+                // 0012 : Leave the try block and break out
+                definitions::make(Opcode::EndTry, &[], 1),
+                definitions::make(Opcode::Jump, &[20], 1),
+                // 0016 : The end of the try block
+                definitions::make(Opcode::EndTry, &[], 1),
+                // 0017 : The end of the loop
+                definitions::make(Opcode::Jump, &[0], 1),
+            ],
+        },
+    ];
+    run_compiler_tests(&tests);
+}
+
+#[test]
+fn test_incr_statements() {
+    let tests = vec![
+        CompilerTestCase {
+            input: "let x = 1; x++; x--;",
+            expected_constants: vec![Object::Integer(1)],
+            expected_instructions: vec![
+                definitions::make(Opcode::Constant, &[0], 1),
+                definitions::make(Opcode::DefineGlobal, &[0], 1),
+                definitions::make(Opcode::IncGlobal, &[0, 0], 1),
+                definitions::make(Opcode::IncGlobal, &[0, 1], 1),
+            ],
+        },
+        CompilerTestCase {
+            input: "fn() { let x = 1; x++; fn() { x-- } }",
+            expected_constants: vec![
+                Object::Integer(1),
+                Object::Func(Rc::new(CompiledFunction::new(
+                    concat_instructions(&[
+                        // 'x' is a free variable of the inner function
+                        definitions::make(Opcode::IncFree, &[0, 1], 1),
+                        definitions::make(Opcode::Return, &[], 1),
+                    ]),
+                    0,
+                    0,
+                    1,
+                ))),
+                Object::Func(Rc::new(CompiledFunction::new(
+                    concat_instructions(&[
+                        definitions::make(Opcode::Constant, &[0], 1),
+                        definitions::make(Opcode::DefineLocal, &[0], 1),
+                        definitions::make(Opcode::IncLocal, &[0, 0], 1),
+                        definitions::make(Opcode::GetLocal, &[0], 1),
+                        definitions::make(Opcode::Closure, &[1, 1], 1),
+                        definitions::make(Opcode::ReturnValue, &[], 1),
+                    ]),
+                    1,
+                    0,
+                    1,
+                ))),
+            ],
+            expected_instructions: vec![
+                definitions::make(Opcode::Closure, &[2, 0], 1),
+                definitions::make(Opcode::Pop, &[], 1),
+            ],
+        },
+        CompilerTestCase {
+            input: "let m = map {}; m[1]++;",
+            expected_constants: vec![Object::Integer(1)],
+            expected_instructions: vec![
+                definitions::make(Opcode::Map, &[0], 1),
+                definitions::make(Opcode::DefineGlobal, &[0], 1),
+                definitions::make(Opcode::GetGlobal, &[0], 1),
+                definitions::make(Opcode::Constant, &[0], 1),
+                definitions::make(Opcode::IncIndex, &[0], 1),
+            ],
+        },
+    ];
+    run_compiler_tests(&tests);
+
+    let tests = vec![
+        CompilerTestCaseErrors {
+            input: "y++",
+            error: "[line 1] compile error: undefined identifier 'y'",
+        },
+        CompilerTestCaseErrors {
+            input: "len++",
+            error: "[line 1] compile error: Invalid lvalue",
+        },
+    ];
+    run_compiler_failed_tests(&tests);
+}
+
+#[test]
+fn test_regex_match() {
+    use crate::builtins::regex::Regex;
+
+    let regex = |pattern| Object::Regex(Rc::new(Regex::new(pattern).unwrap()));
+    let tests = vec![
+        CompilerTestCase {
+            // A string literal pattern is compiled to a regex constant
+            input: r#""abc" ~ "b+""#,
+            expected_constants: vec![Object::Str("abc".to_string()), regex("b+")],
+            expected_instructions: vec![
+                definitions::make(Opcode::Constant, &[0], 1),
+                definitions::make(Opcode::Constant, &[1], 1),
+                definitions::make(Opcode::RegexMatch, &[], 1),
+                definitions::make(Opcode::Pop, &[], 1),
+            ],
+        },
+        CompilerTestCase {
+            input: r#"let p = "b"; "abc" !~ p"#,
+            expected_constants: vec![Object::Str("b".to_string()), Object::Str("abc".to_string())],
+            expected_instructions: vec![
+                definitions::make(Opcode::Constant, &[0], 1),
+                definitions::make(Opcode::DefineGlobal, &[0], 1),
+                definitions::make(Opcode::Constant, &[1], 1),
+                definitions::make(Opcode::GetGlobal, &[0], 1),
+                definitions::make(Opcode::RegexMatch, &[], 1),
+                definitions::make(Opcode::Bang, &[], 1),
+                definitions::make(Opcode::Pop, &[], 1),
+            ],
+        },
+    ];
+    run_compiler_tests(&tests);
+
+    let tests = vec![CompilerTestCaseErrors {
+        input: r#""abc" ~ "a(""#,
+        error: "[line 1] compile error: invalid regex 'a(': unmatched '('",
+    }];
+    run_compiler_failed_tests(&tests);
+}
+
+#[test]
+fn test_in_operator() {
+    let tests = vec![CompilerTestCase {
+        input: "1 in [1, 2]",
+        expected_constants: vec![Object::Integer(1), Object::Integer(1), Object::Integer(2)],
+        expected_instructions: vec![
+            definitions::make(Opcode::Constant, &[0], 1),
+            definitions::make(Opcode::Constant, &[1], 1),
+            definitions::make(Opcode::Constant, &[2], 1),
+            definitions::make(Opcode::Array, &[2], 1),
+            definitions::make(Opcode::In, &[], 1),
+            definitions::make(Opcode::Pop, &[], 1),
+        ],
+    }];
+    run_compiler_tests(&tests);
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_list_filters() {
+    let program = parse_program(
+        r#"
+        let n = 0;
+        @ end { println("{}", n); }
+        @ ($3).dstport == 53 { n = n + 1; }
+        @ interval 10 { n = 0; }
+        @ ($3).syn &&
+          ($3).ack
+        @ { }
+        "#,
+    );
+    let mut compiler = Compiler::new();
+    if let Err(err) = compiler.compile(program) {
+        panic!("{}", err);
+    }
+    assert_eq!(
+        compiler.bytecode().list_filters(),
+        "\
+LINE  KIND      ACTION  PATTERN
+4     filter    yes     ($3).dstport == 53
+6     filter    no      ($3).syn && ($3).ack
+8     filter    yes     (none)
+5     interval  yes     interval 10
+3     end       yes     end
+"
+    );
+
+    // Filters imported from a module are listed along with its path
+    let program = parse_program(r#"import "std/stats";"#);
+    let mut compiler = Compiler::new();
+    if let Err(err) = compiler.compile(program) {
+        panic!("{}", err);
+    }
+    let listing = compiler.bytecode().list_filters();
+    assert!(listing.starts_with("LINE          KIND"), "{}", listing);
+    assert!(listing.contains("\nstd/stats:"), "{}", listing);
+}
+
+#[test]
+fn test_nesting_limit() {
+    use crate::parser::MAX_NESTING;
+
+    // Expressions nested up to the limit compile on a stack the size of
+    // that of the main thread
+    let n = MAX_NESTING / 2 - 1;
+    let inputs = [
+        format!("{}1", "-".repeat(MAX_NESTING - 2)),
+        vec!["1"; MAX_NESTING - 1].join(" < "),
+        format!("let a = 1; {}1{}", "if a { ".repeat(n), " }".repeat(n)),
+        format!("{}1{}", "fn() { ".repeat(n), " }".repeat(n)),
+    ];
+    let handle = std::thread::Builder::new()
+        .stack_size(8 << 20)
+        .spawn(move || {
+            for input in inputs {
+                let mut compiler = Compiler::new();
+                compiler.compile(parse_program(&input)).unwrap();
+            }
+        })
+        .unwrap();
+    handle.join().unwrap();
+}
+
+#[test]
+fn test_flat_chains() {
+    // Chains of binary operators compile without recursing once for each
+    // operator
+    let terms = |n: usize, op: &str| vec!["1"; n].join(op);
+    let inputs = [
+        terms(5_000, " + "),
+        terms(5_000, " < "),
+        terms(5_000, " || "),
+        terms(5_000, " && "),
+        format!("\"a\" ~ \"a\"{}", " ~ \"b\"".repeat(5_000)),
+        format!("match 1 {{ {} => 1, _ => 2 }}", terms(5_000, " | ")),
+    ];
+    for input in inputs {
+        let mut compiler = Compiler::new();
+        compiler.compile(parse_program(&input)).unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+//! Evaluate p2sh expressions on packets supplied by a host application.
+//!
+//! An expression is compiled once into a function that wraps it the same
+//! way a filter pattern is wrapped, and is then evaluated against any number
+//! of packets. For example, `($2).src` evaluates to the source address of
+//! the IPv4 packet in an ethernet frame.
+
+use std::rc::Rc;
+
+use crate::builtins::pcap::PcapPacket;
+use crate::code::definitions::Instructions;
+use crate::compiler::{Bytecode, Compiler};
+use crate::object::func::CompiledFunction;
+use crate::object::Object;
+use crate::parser::ast::stmt::{FilterPattern, FilterStmt, Statement};
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::vm::interpreter::VM;
+
+/// A compiled expression that can be evaluated on packets
+pub struct PacketExpr {
+    constants: Vec<Rc<Object>>,
+    func: Rc<CompiledFunction>,
+    vm: VM,
+}
+
+impl PacketExpr {
+    /// Compile the source of a single expression
+    pub fn compile(source: &str) -> Result<Self, String> {
+        let mut parser = Parser::new(Scanner::new(source));
+        let mut program = parser.parse_program();
+        let errors = parser.parse_errors();
+        if !errors.is_empty() {
+            return Err(errors.join("\n"));
+        }
+        let stmt = match program.statements.pop() {
+            Some(Statement::Expr(stmt)) if program.statements.is_empty() => stmt,
+            _ => return Err(String::from("expected a single expression")),
+        };
+        let filter = FilterStmt {
+            token: stmt.token,
+            pattern: FilterPattern::Expr(Box::new(stmt.value)),
+            source: source.trim().to_string(),
+            action: None,
+        };
+        program.statements.push(Statement::Filter(filter));
+
+        let mut compiler = Compiler::new();
+        compiler.compile(program).map_err(|e| e.to_string())?;
+        let bytecode = compiler.bytecode();
+        let func = bytecode.filters[0].clone();
+        let constants = bytecode.constants;
+        let vm = Self::new_vm(&constants);
+        Ok(Self {
+            constants,
+            func,
+            vm,
+        })
+    }
+
+    /// Evaluate the expression on a packet
+    pub fn eval(&mut self, pkt: Rc<PcapPacket>) -> Result<Rc<Object>, String> {
+        self.vm.set_curr_pkt(pkt);
+        let result = self
+            .vm
+            .push_filter_frame(&self.func)
+            .and_then(|_| self.vm.run())
+            .and_then(|_| self.vm.pop_filter_value());
+        // The frames of a failed evaluation are left on the stack,
+        // so start over with a fresh machine.
+        result.map_err(|e| {
+            self.vm = Self::new_vm(&self.constants);
+            e.to_string()
+        })
+    }
+
+    fn new_vm(constants: &[Rc<Object>]) -> VM {
+        let bytecode = Bytecode {
+            instructions: Instructions::default(),
+            constants: constants.to_vec(),
+            filters: Vec::new(),
+            filter_end: None,
+            filter_intervals: Vec::new(),
+            globals: Vec::new(),
+        };
+        let vm = VM::new(bytecode);
+        vm.init_builtin_vars(Vec::new());
+        vm
+    }
+}
+
+/// Compile an expression and evaluate it on a single packet. Use
+/// `PacketExpr` to evaluate the same expression on many packets.
+pub fn eval_on_packet(source: &str, pkt: Rc<PcapPacket>) -> Result<Rc<Object>, String> {
+    PacketExpr::compile(source)?.eval(pkt)
+}
@@ -0,0 +1,16 @@
+//! The p2sh programming language: the scanner, the parser, the compiler
+//! to bytecode, the virtual machine and the builtins, including the
+//! protocol layers of the packets that scripts process. The packets and
+//! the builtins that read, write and capture them are behind the `pcap`
+//! feature, without which the crate builds the language alone.
+
+pub mod builtins;
+pub mod code;
+pub mod compiler;
+#[cfg(feature = "pcap")]
+pub mod embed;
+pub mod object;
+pub mod parser;
+pub mod scanner;
+pub mod stdlib;
+pub mod vm;
@@ -53,7 +53,7 @@ impl fmt::Display for Array {
                 let _ = write!(&mut acc, "{}, ", p);
                 acc
             });
-        let elements_str = elements_str.trim_end_matches(|c| c == ' ' || c == ',');
+        let elements_str = elements_str.trim_end_matches([' ', ',']);
         write!(f, "[{}]", elements_str)
     }
 }
@@ -0,0 +1,46 @@
+use std::fmt;
+use std::io;
+
+#[cfg(feature = "pcap")]
+use p2sh_dissect::error::PacketError;
+
+/// The error object.
+/// It is different from runtime error in the sense that a runtime error
+/// is not interpreted by the programming language but will terminate the
+/// program. However, the error objects are used in the builtin functions
+/// for error handling.
+#[derive(Debug)]
+pub enum ErrorObj {
+    IO(io::Error),
+    Utf8(std::string::FromUtf8Error),
+    #[cfg(feature = "pcap")]
+    Packet(PacketError),
+    Runtime(String), // runtime error caught by a 'try' block
+    Value(String),   // value that could not be parsed or converted
+}
+
+impl fmt::Display for ErrorObj {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self {
+            Self::IO(e) => write!(f, "io-error: {e}"),
+            Self::Utf8(e) => write!(f, "utf8-error: {e}"),
+            #[cfg(feature = "pcap")]
+            Self::Packet(e) => write!(f, "packet-error: {}", e),
+            Self::Runtime(e) => write!(f, "runtime-error: {}", e),
+            Self::Value(e) => write!(f, "value-error: {}", e),
+        }
+    }
+}
+
+impl ErrorObj {
+    /// The message of the error without the kind of error
+    pub fn message(&self) -> String {
+        match &self {
+            Self::IO(e) => e.to_string(),
+            Self::Utf8(e) => e.to_string(),
+            #[cfg(feature = "pcap")]
+            Self::Packet(e) => e.to_string(),
+            Self::Runtime(e) | Self::Value(e) => e.clone(),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::cell::{RefCell, RefMut};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::{BufRead, Write};
+
+/// A file handle. The reader and the writer are None once the file is
+/// closed using 'close'.
+#[derive(Debug)]
+pub enum FileHandle {
+    Reader(RefCell<Option<io::BufReader<fs::File>>>),
+    Writer(RefCell<Option<io::BufWriter<fs::File>>>),
+    Stdin,
+    Stdout,
+    Stderr,
+}
+
+/// Borrow a file that has not been closed
+pub fn borrow_open<T>(file: &RefCell<Option<T>>) -> io::Result<RefMut<'_, T>> {
+    RefMut::filter_map(file.borrow_mut(), Option::as_mut)
+        .map_err(|_| io::Error::other("file is closed"))
+}
+
+impl FileHandle {
+    pub fn new_reader(reader: io::BufReader<fs::File>) -> Self {
+        Self::Reader(RefCell::new(Some(reader)))
+    }
+    pub fn new_writer(writer: io::BufWriter<fs::File>) -> Self {
+        Self::Writer(RefCell::new(Some(writer)))
+    }
+
+    /// Read a line from a reader or stdin without the line break at the
+    /// end. Returns None at the end of the file.
+    pub fn next_line(&self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        let len = match self {
+            Self::Reader(reader) => borrow_open(reader)?.read_line(&mut line)?,
+            Self::Stdin => io::stdin().read_line(&mut line)?,
+            _ => return Err(io::Error::other("cannot read lines from a writer")),
+        };
+        if len == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+
+    /// Close a file, flushing the data buffered for a writer. Closing a
+    /// file that is already closed has no effect.
+    pub fn close(&self) -> io::Result<()> {
+        match self {
+            Self::Reader(reader) => {
+                reader.borrow_mut().take();
+                Ok(())
+            }
+            Self::Writer(writer) => match writer.borrow_mut().take() {
+                Some(mut writer) => writer.flush(),
+                None => Ok(()),
+            },
+            Self::Stdin => Err(io::Error::other("cannot close stdin")),
+            Self::Stdout => Err(io::Error::other("cannot close stdout")),
+            Self::Stderr => Err(io::Error::other("cannot close stderr")),
+        }
+    }
+}
+
+impl fmt::Display for FileHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self {
+            Self::Reader(_) => write!(f, "<file: reader>"),
+            Self::Writer(_) => write!(f, "<file: writer>"),
+            Self::Stdin => write!(f, "<stdin>"),
+            Self::Stdout => write!(f, "<stdout>"),
+            Self::Stderr => write!(f, "<stderr>"),
+        }
+    }
+}
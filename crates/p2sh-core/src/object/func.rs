@@ -18,6 +18,14 @@ pub struct CompiledFunction {
     pub num_locals: usize,
     pub num_params: usize,
     pub line: usize,
+    /// Name of the function if it is bound to one
+    pub name: Option<String>,
+    /// Source text of the pattern if the function wraps a filter statement
+    pub pattern: Option<String>,
+    /// Set if the function wraps a filter statement that has an action
+    pub action: bool,
+    /// Path of the module the filter statement was imported from
+    pub module: Option<String>,
 }
 
 impl CompiledFunction {
@@ -32,13 +40,34 @@ impl CompiledFunction {
             num_locals,
             num_params,
             line,
+            name: None,
+            pattern: None,
+            action: false,
+            module: None,
+        }
+    }
+
+    /// Create the function that wraps a filter statement with the given pattern
+    pub fn new_filter(
+        instructions: Instructions,
+        num_locals: usize,
+        line: usize,
+        pattern: &str,
+    ) -> Self {
+        Self {
+            pattern: Some(pattern.to_string()),
+            ..Self::new(instructions, num_locals, 0, line)
         }
     }
 }
 
 impl fmt::Display for CompiledFunction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "<compiled function>")
+        match self.pattern.as_deref() {
+            Some("") => write!(f, "filter at line {}", self.line),
+            Some(pattern) => write!(f, "filter at line {}: {}", self.line, pattern),
+            None => write!(f, "<compiled function>"),
+        }
     }
 }
 
@@ -105,6 +134,20 @@ impl BuiltinFunction {
     }
 }
 
+// An alias for a builtin function that was renamed. The alias forwards
+// calls to the builtin function named 'target'.
+#[derive(Debug, Clone)]
+pub struct BuiltinAlias {
+    pub name: &'static str,
+    pub target: &'static str,
+}
+
+impl BuiltinAlias {
+    pub const fn new(name: &'static str, target: &'static str) -> BuiltinAlias {
+        BuiltinAlias { name, target }
+    }
+}
+
 impl PartialEq for BuiltinFunction {
     fn eq(&self, other: &Self) -> bool {
         self.name == other.name
@@ -9,28 +9,68 @@ use super::Object;
 #[derive(Debug, Default)]
 pub struct HMap {
     pub pairs: RefCell<HashMap<Rc<Object>, Rc<Object>>>,
+    // Value inserted when a key that is not present is indexed
+    pub default: RefCell<Option<Rc<Object>>>,
 }
 
 impl HMap {
+    #[allow(clippy::mutable_key_type)]
     pub fn new(pairs: HashMap<Rc<Object>, Rc<Object>>) -> Self {
         Self {
             pairs: RefCell::new(pairs),
+            default: RefCell::new(None),
         }
     }
     pub fn len(&self) -> usize {
         self.pairs.borrow().len()
     }
+    pub fn is_empty(&self) -> bool {
+        self.pairs.borrow().is_empty()
+    }
     pub fn get(&self, key: &Rc<Object>) -> Rc<Object> {
         match self.pairs.borrow().get(key) {
             Some(value) => value.clone(),
             None => Rc::new(Object::Null),
         }
     }
+    // Get the value for a key. If the key is not present and the map has
+    // a default value, a copy of the default is inserted and returned so
+    // that containers used as defaults are not shared between keys.
+    pub fn get_or_default(&self, key: &Rc<Object>) -> Rc<Object> {
+        if let Some(value) = self.pairs.borrow().get(key) {
+            return value.clone();
+        }
+        match self.default.borrow().as_ref() {
+            Some(default) => {
+                let value = default.deep_copy();
+                self.insert(key.clone(), value.clone());
+                value
+            }
+            None => Rc::new(Object::Null),
+        }
+    }
+    pub fn set_default(&self, default: Option<Rc<Object>>) {
+        *self.default.borrow_mut() = default;
+    }
     pub fn contains(&self, key: &Rc<Object>) -> bool {
         self.pairs.borrow().contains_key(key)
     }
+    // The key-value pairs ordered by the keys so that the output does not
+    // depend on the order of the hash table
+    pub fn sorted_pairs(&self) -> Vec<(Rc<Object>, Rc<Object>)> {
+        let mut pairs: Vec<(Rc<Object>, Rc<Object>)> = self
+            .pairs
+            .borrow()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+        pairs
+    }
+    // Arrays are mutable. So, the keys are copied on insertion so that
+    // changes to the original array do not affect the hash of the key.
     pub fn insert(&self, key: Rc<Object>, val: Rc<Object>) -> Rc<Object> {
-        match self.pairs.borrow_mut().insert(key, val) {
+        match self.pairs.borrow_mut().insert(key.deep_copy(), val) {
             Some(v) => v,
             None => Rc::new(Object::Null),
         }
@@ -40,14 +80,13 @@ impl HMap {
 impl fmt::Display for HMap {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let pairs_str = self
-            .pairs
-            .borrow()
+            .sorted_pairs()
             .iter()
             .fold(String::new(), |mut acc, (k, v)| {
                 let _ = write!(&mut acc, "{}: {}, ", k, v);
                 acc
             });
-        let pairs_str = pairs_str.trim_end_matches(|c| c == ' ' || c == ',');
+        let pairs_str = pairs_str.trim_end_matches([' ', ',']);
         write!(f, "map {{{}}}", pairs_str)
     }
 }
@@ -55,7 +94,7 @@ impl fmt::Display for HMap {
 impl From<&HMap> for Vec<u8> {
     fn from(obj: &HMap) -> Self {
         let mut bytes = Vec::new();
-        for (key, val) in obj.pairs.borrow().iter() {
+        for (key, val) in obj.sorted_pairs().iter() {
             let b: Vec<u8> = key.as_ref().into();
             bytes.extend_from_slice(&b);
             let b: Vec<u8> = val.as_ref().into();
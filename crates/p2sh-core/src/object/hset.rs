@@ -0,0 +1,99 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Write;
+use std::rc::Rc;
+
+use super::Object;
+
+#[derive(Debug, Default)]
+pub struct HSet {
+    pub elements: RefCell<HashSet<Rc<Object>>>,
+}
+
+impl HSet {
+    #[allow(clippy::mutable_key_type)]
+    pub fn new(elements: HashSet<Rc<Object>>) -> Self {
+        Self {
+            elements: RefCell::new(elements),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.elements.borrow().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.elements.borrow().is_empty()
+    }
+    pub fn contains(&self, elem: &Rc<Object>) -> bool {
+        self.elements.borrow().contains(elem)
+    }
+    // The elements in order so that the output does not depend on the
+    // order of the hash table
+    pub fn sorted_elements(&self) -> Vec<Rc<Object>> {
+        let mut elements: Vec<Rc<Object>> = self.elements.borrow().iter().cloned().collect();
+        elements.sort_by(|a, b| a.total_cmp(b));
+        elements
+    }
+    // Elements are copied on insertion for the same reason as map keys.
+    // Returns true if the element was not already present.
+    pub fn insert(&self, elem: Rc<Object>) -> bool {
+        self.elements.borrow_mut().insert(elem.deep_copy())
+    }
+    pub fn remove(&self, elem: &Rc<Object>) -> bool {
+        self.elements.borrow_mut().remove(elem)
+    }
+    #[allow(clippy::mutable_key_type)]
+    pub fn union(&self, other: &HSet) -> HSet {
+        let elements = self
+            .elements
+            .borrow()
+            .union(&other.elements.borrow())
+            .cloned()
+            .collect();
+        HSet::new(elements)
+    }
+    #[allow(clippy::mutable_key_type)]
+    pub fn intersection(&self, other: &HSet) -> HSet {
+        let elements = self
+            .elements
+            .borrow()
+            .intersection(&other.elements.borrow())
+            .cloned()
+            .collect();
+        HSet::new(elements)
+    }
+}
+
+impl fmt::Display for HSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let elements_str = self
+            .sorted_elements()
+            .iter()
+            .fold(String::new(), |mut acc, e| {
+                let _ = write!(&mut acc, "{}, ", e);
+                acc
+            });
+        let elements_str = elements_str.trim_end_matches([' ', ',']);
+        write!(f, "set {{{}}}", elements_str)
+    }
+}
+
+impl From<&HSet> for Vec<u8> {
+    fn from(obj: &HSet) -> Self {
+        let mut bytes = Vec::new();
+        for elem in obj.sorted_elements().iter() {
+            let b: Vec<u8> = elem.as_ref().into();
+            bytes.extend_from_slice(&b);
+        }
+        bytes
+    }
+}
+
+// compare HSet objects without considering the order of elements
+impl PartialEq for HSet {
+    fn eq(&self, other: &Self) -> bool {
+        *self.elements.borrow() == *other.elements.borrow()
+    }
+}
+
+impl Eq for HSet {}
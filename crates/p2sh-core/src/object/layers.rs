@@ -0,0 +1,103 @@
+use std::rc::Rc;
+
+use p2sh_dissect::addr::Addr;
+use p2sh_dissect::value::Value;
+use p2sh_dissect::{ethernet, ipv4, ipv6, tcp, tunnel, udp, vlan};
+
+use super::array::Array;
+use super::hmap::HMap;
+use super::Object;
+
+// The protocol layers of p2sh-dissect holding their inner packets as objects
+pub type Ethernet = ethernet::Ethernet<Object>;
+pub type Vlan = vlan::Vlan<Object>;
+pub type Ipv4Packet = ipv4::Ipv4Packet<Object>;
+pub type Ipv6Packet = ipv6::Ipv6Packet<Object>;
+pub type Tcp = tcp::Tcp<Object>;
+pub type Udp = udp::Udp<Object>;
+pub type Tunnel = tunnel::Tunnel<Object>;
+
+impl Value for Object {
+    fn null() -> Self {
+        Object::Null
+    }
+
+    fn integer(value: i64) -> Self {
+        Object::Integer(value)
+    }
+
+    fn boolean(value: bool) -> Self {
+        Object::Bool(value)
+    }
+
+    fn string(value: String) -> Self {
+        Object::Str(value)
+    }
+
+    fn bytes(value: Vec<u8>) -> Self {
+        Object::Bytes(value)
+    }
+
+    fn addr(value: Addr) -> Self {
+        Object::Addr(value)
+    }
+
+    fn array(elements: Vec<Rc<Self>>) -> Self {
+        Object::Arr(Rc::new(Array::new(elements)))
+    }
+
+    #[allow(clippy::mutable_key_type)]
+    fn record(fields: Vec<(&str, Self)>) -> Self {
+        let pairs = fields
+            .into_iter()
+            .map(|(k, v)| (Rc::new(Object::Str(k.to_string())), Rc::new(v)))
+            .collect();
+        Object::Map(Rc::new(HMap::new(pairs)))
+    }
+
+    fn as_integer(&self) -> Option<i64> {
+        match self {
+            Object::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            Object::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Object::Str(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn as_addr(&self) -> Option<&Addr> {
+        match self {
+            Object::Addr(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn as_tcp(&self) -> Option<&Tcp> {
+        match self {
+            Object::Tcp(tcp) => Some(tcp),
+            _ => None,
+        }
+    }
+
+    fn as_udp(&self) -> Option<&Udp> {
+        match self {
+            Object::Udp(udp) => Some(udp),
+            _ => None,
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.into()
+    }
+}
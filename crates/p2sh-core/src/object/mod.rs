@@ -0,0 +1,826 @@
+use std::cmp::Ordering;
+use std::convert::From;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops;
+use std::rc::Rc;
+
+#[cfg(feature = "pcap")]
+use crate::builtins::pcap::Pcap;
+#[cfg(feature = "pcap")]
+use crate::builtins::pcap::PcapPacket;
+use crate::builtins::regex::Regex;
+use crate::object::array::Array;
+use crate::object::error::ErrorObj;
+use crate::object::file::FileHandle;
+use crate::object::func::BuiltinFunction;
+use crate::object::func::Closure;
+use crate::object::func::CompiledFunction;
+use crate::object::hmap::HMap;
+use crate::object::hset::HSet;
+#[cfg(feature = "pcap")]
+use crate::object::layers::{Ethernet, Ipv4Packet, Ipv6Packet, Tcp, Tunnel, Udp, Vlan};
+use crate::object::timestamp::{Duration, Timestamp};
+#[cfg(feature = "pcap")]
+use p2sh_dissect::addr::{Addr, Cidr};
+#[cfg(feature = "pcap")]
+use p2sh_dissect::dns::Dns;
+#[cfg(feature = "pcap")]
+use p2sh_dissect::tls::Tls;
+
+pub mod array;
+pub mod error;
+pub mod file;
+pub mod func;
+pub mod hmap;
+pub mod hset;
+#[cfg(feature = "pcap")]
+pub mod layers;
+pub mod serialize;
+pub mod timestamp;
+
+#[derive(Debug)]
+pub enum Object {
+    Null,
+    Str(String),
+    Char(char),
+    Byte(u8),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Bytes(Vec<u8>),
+    #[cfg(feature = "pcap")]
+    Addr(Addr),
+    #[cfg(feature = "pcap")]
+    Cidr(Cidr),
+    Timestamp(Timestamp),
+    Duration(Duration),
+    Regex(Rc<Regex>),
+    Return(Rc<Object>),
+    Builtin(Rc<BuiltinFunction>),
+    Func(Rc<CompiledFunction>),
+    Arr(Rc<Array>),
+    Map(Rc<HMap>),
+    Set(Rc<HSet>),
+    Clos(Rc<Closure>),
+    File(Rc<FileHandle>),
+    Err(ErrorObj),
+    #[cfg(feature = "pcap")]
+    Pcap(Rc<Pcap>),
+    #[cfg(feature = "pcap")]
+    Packet(Rc<PcapPacket>),
+    #[cfg(feature = "pcap")]
+    Eth(Rc<Ethernet>),
+    #[cfg(feature = "pcap")]
+    Vlan(Rc<Vlan>),
+    #[cfg(feature = "pcap")]
+    Ipv4(Rc<Ipv4Packet>),
+    #[cfg(feature = "pcap")]
+    Ipv6(Rc<Ipv6Packet>),
+    #[cfg(feature = "pcap")]
+    Udp(Rc<Udp>),
+    #[cfg(feature = "pcap")]
+    Tcp(Rc<Tcp>),
+    #[cfg(feature = "pcap")]
+    Dns(Rc<Dns>),
+    #[cfg(feature = "pcap")]
+    Tls(Rc<Tls>),
+    #[cfg(feature = "pcap")]
+    Tunnel(Rc<Tunnel>),
+}
+
+impl From<&Object> for Vec<u8> {
+    fn from(obj: &Object) -> Self {
+        match obj {
+            Object::Null
+            | Object::Return(_)
+            | Object::Builtin(_)
+            | Object::Func(_)
+            | Object::Clos(_)
+            | Object::File(_)
+            | Object::Regex(_)
+            | Object::Err(_) => Vec::new(),
+            #[cfg(feature = "pcap")]
+            Object::Pcap(_) => Vec::new(),
+            Object::Str(v) => v.as_bytes().to_vec(),
+            Object::Char(v) => v.to_string().as_bytes().to_vec(),
+            Object::Byte(v) => vec![*v],
+            Object::Integer(v) => v.to_be_bytes().to_vec(),
+            Object::Float(v) => v.to_be_bytes().to_vec(),
+            Object::Bool(v) => vec![*v as u8],
+            Object::Bytes(v) => v.clone(),
+            #[cfg(feature = "pcap")]
+            Object::Addr(v) => v.into(),
+            #[cfg(feature = "pcap")]
+            Object::Cidr(v) => v.into(),
+            Object::Timestamp(v) => v.nanos().to_be_bytes().to_vec(),
+            Object::Duration(v) => v.nanos().to_be_bytes().to_vec(),
+            Object::Arr(v) => v.as_ref().into(),
+            Object::Map(v) => v.as_ref().into(),
+            Object::Set(v) => v.as_ref().into(),
+            #[cfg(feature = "pcap")]
+            Object::Packet(v) => v.as_ref().into(),
+            #[cfg(feature = "pcap")]
+            Object::Eth(v) => v.as_ref().into(),
+            #[cfg(feature = "pcap")]
+            Object::Vlan(v) => v.as_ref().into(),
+            #[cfg(feature = "pcap")]
+            Object::Ipv4(v) => v.as_ref().into(),
+            #[cfg(feature = "pcap")]
+            Object::Ipv6(v) => v.as_ref().into(),
+            #[cfg(feature = "pcap")]
+            Object::Udp(v) => v.as_ref().into(),
+            #[cfg(feature = "pcap")]
+            Object::Tcp(v) => v.as_ref().into(),
+            #[cfg(feature = "pcap")]
+            Object::Dns(v) => v.as_ref().into(),
+            #[cfg(feature = "pcap")]
+            Object::Tls(v) => v.as_ref().into(),
+            #[cfg(feature = "pcap")]
+            Object::Tunnel(v) => v.as_ref().into(),
+        }
+    }
+}
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Null, Object::Null) => true,
+            (Object::Str(a), Object::Str(b)) => a.eq(b),
+            (Object::Char(a), Object::Char(b)) => a.eq(b),
+            (Object::Byte(a), Object::Byte(b)) => a.eq(b),
+            (Object::Integer(a), Object::Integer(b)) => a.eq(b),
+            (Object::Integer(a), Object::Float(b)) => {
+                cmp_int_float(*a, *b) == Some(Ordering::Equal)
+            }
+            (Object::Float(a), Object::Integer(b)) => {
+                cmp_int_float(*b, *a) == Some(Ordering::Equal)
+            }
+            (Object::Float(a), Object::Float(b)) => a.eq(b),
+            (Object::Byte(a), Object::Integer(b)) => (*a as i64).eq(b),
+            (Object::Integer(a), Object::Byte(b)) => a.eq(&(*b as i64)),
+            (Object::Byte(a), Object::Float(b)) => (*a as f64).eq(b),
+            (Object::Float(a), Object::Byte(b)) => a.eq(&(*b as f64)),
+            (Object::Bool(a), Object::Bool(b)) => a.eq(b),
+            (Object::Bytes(a), Object::Bytes(b)) => a.eq(b),
+            // Bytes are equal to an array of the same bytes
+            (Object::Bytes(a), Object::Arr(b)) | (Object::Arr(b), Object::Bytes(a)) => {
+                let elements = b.elements.borrow();
+                a.len() == elements.len()
+                    && a.iter()
+                        .zip(elements.iter())
+                        .all(|(x, y)| **y == Object::Byte(*x))
+            }
+            #[cfg(feature = "pcap")]
+            (Object::Addr(a), Object::Addr(b)) => a.eq(b),
+            // Addresses are equal to strings in their textual form
+            #[cfg(feature = "pcap")]
+            (Object::Addr(a), Object::Str(b)) | (Object::Str(b), Object::Addr(a)) => {
+                a.to_string() == *b
+            }
+            #[cfg(feature = "pcap")]
+            (Object::Cidr(a), Object::Cidr(b)) => a.eq(b),
+            (Object::Timestamp(a), Object::Timestamp(b)) => a.eq(b),
+            (Object::Duration(a), Object::Duration(b)) => a.eq(b),
+            // Durations equal the numbers of seconds they are ordered with
+            (Object::Duration(a), Object::Integer(b))
+            | (Object::Integer(b), Object::Duration(a)) => {
+                cmp_int_float(*b, a.as_secs_f64()) == Some(Ordering::Equal)
+            }
+            (Object::Duration(a), Object::Float(b)) | (Object::Float(b), Object::Duration(a)) => {
+                a.as_secs_f64() == *b
+            }
+            #[cfg(feature = "pcap")]
+            (Object::Cidr(a), Object::Str(b)) | (Object::Str(b), Object::Cidr(a)) => {
+                a.to_string() == *b
+            }
+            (Object::Regex(a), Object::Regex(b)) => a.eq(b),
+            (Object::Arr(a), Object::Arr(b)) => a.eq(b),
+            (Object::Map(a), Object::Map(b)) => a.eq(b),
+            (Object::Set(a), Object::Set(b)) => a.eq(b),
+            (Object::Builtin(a), Object::Builtin(b)) => a.eq(b),
+            (Object::Func(a), Object::Func(b)) => a.eq(b),
+            (Object::Clos(a), Object::Clos(b)) => a.eq(b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Object {}
+
+#[allow(clippy::non_canonical_partial_ord_impl)]
+impl PartialOrd for Object {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Object::Null, Object::Null) => None,
+            (Object::Str(a), Object::Str(b)) => a.partial_cmp(b),
+            (Object::Char(a), Object::Char(b)) => a.partial_cmp(b),
+            (Object::Byte(a), Object::Byte(b)) => a.partial_cmp(b),
+            (Object::Integer(a), Object::Integer(b)) => a.partial_cmp(b),
+            (Object::Float(a), Object::Float(b)) => a.partial_cmp(b),
+            // Numbers of different types are compared by their values
+            (Object::Integer(a), Object::Float(b)) => cmp_int_float(*a, *b),
+            (Object::Float(a), Object::Integer(b)) => cmp_int_float(*b, *a).map(Ordering::reverse),
+            (Object::Byte(a), Object::Integer(b)) => (*a as i64).partial_cmp(b),
+            (Object::Integer(a), Object::Byte(b)) => a.partial_cmp(&(*b as i64)),
+            (Object::Byte(a), Object::Float(b)) => (*a as f64).partial_cmp(b),
+            (Object::Float(a), Object::Byte(b)) => a.partial_cmp(&(*b as f64)),
+            (Object::Bool(a), Object::Bool(b)) => a.partial_cmp(b),
+            (Object::Timestamp(a), Object::Timestamp(b)) => a.partial_cmp(b),
+            (Object::Duration(a), Object::Duration(b)) => a.partial_cmp(b),
+            // Durations are compared with numbers of seconds, such as the
+            // duration literals
+            (Object::Duration(a), Object::Integer(b)) => {
+                cmp_int_float(*b, a.as_secs_f64()).map(Ordering::reverse)
+            }
+            (Object::Integer(a), Object::Duration(b)) => cmp_int_float(*a, b.as_secs_f64()),
+            (Object::Duration(a), Object::Float(b)) => a.as_secs_f64().partial_cmp(b),
+            (Object::Float(a), Object::Duration(b)) => a.partial_cmp(&b.as_secs_f64()),
+            _ => None,
+        }
+    }
+}
+
+// Compare an integer with a float exactly. Converting the integer to a
+// float instead rounds the integers beyond 2^53, which would then equal
+// floats that other integers equal as well.
+fn cmp_int_float(i: i64, f: f64) -> Option<Ordering> {
+    // The floats from 2^63 onwards are beyond the range of the integers
+    const LIMIT: f64 = 9223372036854775808.;
+    if f.is_nan() {
+        None
+    } else if f >= LIMIT {
+        Some(Ordering::Less)
+    } else if f < -LIMIT {
+        Some(Ordering::Greater)
+    } else {
+        let whole = f.trunc();
+        match i.cmp(&(whole as i64)) {
+            Ordering::Equal => 0f64.partial_cmp(&(f - whole)),
+            ord => Some(ord),
+        }
+    }
+}
+
+impl Ord for Object {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // Handle the case where the objects are not comparable.
+        // For simplicity, we'll just return Ordering::Equal.
+        self.partial_cmp(other).unwrap_or(std::cmp::Ordering::Equal)
+    }
+}
+
+impl Object {
+    /// Name of the type of the object
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Object::Null => "null",
+            Object::Str(_) => "string",
+            Object::Char(_) => "char",
+            Object::Byte(_) => "byte",
+            Object::Integer(_) => "integer",
+            Object::Float(_) => "float",
+            Object::Bool(_) => "bool",
+            Object::Bytes(_) => "bytes",
+            #[cfg(feature = "pcap")]
+            Object::Addr(_) => "addr",
+            #[cfg(feature = "pcap")]
+            Object::Cidr(_) => "cidr",
+            Object::Timestamp(_) => "timestamp",
+            Object::Duration(_) => "duration",
+            Object::Regex(_) => "regex",
+            Object::Return(_) => "return",
+            Object::Builtin(_) => "builtin",
+            Object::Func(_) => "function",
+            Object::Arr(_) => "array",
+            Object::Map(_) => "map",
+            Object::Set(_) => "set",
+            Object::Clos(_) => "closure",
+            Object::File(_) => "file",
+            Object::Err(_) => "error",
+            #[cfg(feature = "pcap")]
+            Object::Pcap(_) => "pcap",
+            #[cfg(feature = "pcap")]
+            Object::Packet(_) => "packet",
+            #[cfg(feature = "pcap")]
+            Object::Eth(_) => "eth",
+            #[cfg(feature = "pcap")]
+            Object::Vlan(_) => "vlan",
+            #[cfg(feature = "pcap")]
+            Object::Ipv4(_) => "ipv4",
+            #[cfg(feature = "pcap")]
+            Object::Ipv6(_) => "ipv6",
+            #[cfg(feature = "pcap")]
+            Object::Udp(_) => "udp",
+            #[cfg(feature = "pcap")]
+            Object::Tcp(_) => "tcp",
+            #[cfg(feature = "pcap")]
+            Object::Dns(_) => "dns",
+            #[cfg(feature = "pcap")]
+            Object::Tls(_) => "tls",
+            #[cfg(feature = "pcap")]
+            Object::Tunnel(_) => "tunnel",
+        }
+    }
+
+    /// Compare objects of any types in a total order; by the type first,
+    /// then by the value. Numbers of different types are ordered by their
+    /// values. Used to list the elements of sets and the keys of maps in
+    /// an order that does not depend on the hash table.
+    pub fn total_cmp(&self, other: &Self) -> Ordering {
+        self.rank()
+            .cmp(&other.rank())
+            .then_with(|| match (self, other) {
+                (Object::Float(a), Object::Float(b)) => a.total_cmp(b),
+                (Object::Arr(a), Object::Arr(b)) => {
+                    let (a, b) = (a.elements.borrow(), b.elements.borrow());
+                    let ord = a.iter().zip(b.iter()).map(|(x, y)| x.total_cmp(y));
+                    ord.fold(Ordering::Equal, Ordering::then)
+                        .then(a.len().cmp(&b.len()))
+                }
+                _ => self.partial_cmp(other).unwrap_or(Ordering::Equal),
+            })
+            .then_with(|| self.kind().cmp(other.kind()))
+            .then_with(|| self.to_string().cmp(&other.to_string()))
+    }
+
+    // Position of the type of the object in the total order. Numbers that
+    // are not NaN share a rank so that they are ordered by their values.
+    fn rank(&self) -> u8 {
+        match self {
+            Object::Null => 0,
+            Object::Bool(_) => 1,
+            Object::Float(f) if f.is_nan() => 3,
+            Object::Byte(_) | Object::Integer(_) | Object::Float(_) => 2,
+            Object::Char(_) => 4,
+            Object::Str(_) => 5,
+            Object::Bytes(_) => 6,
+            Object::Timestamp(_) => 7,
+            Object::Duration(_) => 8,
+            Object::Arr(_) => 9,
+            _ => 10,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Object::Null)
+    }
+    pub fn is_string(&self) -> bool {
+        matches!(self, Object::Str(_))
+    }
+    pub fn is_timestamp(&self) -> bool {
+        matches!(self, Object::Timestamp(_))
+    }
+    pub fn is_duration(&self) -> bool {
+        matches!(self, Object::Duration(_))
+    }
+    // Number of seconds of a duration, or of a number given as seconds
+    pub fn as_secs(&self) -> Option<f64> {
+        match self {
+            Object::Integer(n) => Some(*n as f64),
+            Object::Float(f) => Some(*f),
+            Object::Duration(d) => Some(d.as_secs_f64()),
+            _ => None,
+        }
+    }
+    pub fn is_number(&self) -> bool {
+        matches!(self, Object::Integer(_) | Object::Float(_))
+    }
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Object::Integer(n) => *n == 0,
+            Object::Float(n) => *n == 0.,
+            Object::Byte(n) => *n == 0,
+            _ => false,
+        }
+    }
+    pub fn is_falsey(&self) -> bool {
+        match self {
+            Object::Bool(false) | Object::Integer(0) | Object::Null => true,
+            // floating point types cannot be used in patterns
+            Object::Float(v) => *v == 0.,
+            Object::Char(c) => *c == '\0',
+            Object::Byte(b) => *b == 0,
+            Object::Str(s) => s.is_empty(),
+            Object::Bytes(b) => b.is_empty(),
+            Object::Arr(a) => a.elements.borrow().is_empty(),
+            Object::Map(m) => m.pairs.borrow().is_empty(),
+            Object::Set(s) => s.elements.borrow().is_empty(),
+            _ => false,
+        }
+    }
+
+    // Arrays can be used as composite keys as long as all of their
+    // elements are valid keys themselves.
+    pub fn is_a_valid_key(&self) -> bool {
+        match self {
+            Object::Str(_)
+            | Object::Char(_)
+            | Object::Byte(_)
+            | Object::Integer(_)
+            | Object::Float(_)
+            | Object::Bool(_)
+            | Object::Bytes(_)
+            | Object::Timestamp(_)
+            | Object::Duration(_)
+            | Object::Null
+            | Object::Builtin(_) => true,
+            #[cfg(feature = "pcap")]
+            Object::Addr(_) | Object::Cidr(_) => true,
+            Object::Arr(arr) => arr.elements.borrow().iter().all(|e| e.is_a_valid_key()),
+            _ => false,
+        }
+    }
+
+    // Make a copy of the object including the elements of the containers
+    // within it. Objects that are not containers are shared as they are
+    // immutable.
+    #[allow(clippy::mutable_key_type)]
+    pub fn deep_copy(self: &Rc<Self>) -> Rc<Object> {
+        match self.as_ref() {
+            Object::Arr(arr) => {
+                let elements = arr
+                    .elements
+                    .borrow()
+                    .iter()
+                    .map(|e| e.deep_copy())
+                    .collect();
+                Rc::new(Object::Arr(Rc::new(Array::new(elements))))
+            }
+            Object::Map(map) => {
+                let pairs = map
+                    .pairs
+                    .borrow()
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.deep_copy()))
+                    .collect();
+                let copy = HMap::new(pairs);
+                copy.set_default(map.default.borrow().clone());
+                Rc::new(Object::Map(Rc::new(copy)))
+            }
+            Object::Set(set) => {
+                let elements = set.elements.borrow().clone();
+                Rc::new(Object::Set(Rc::new(HSet::new(elements))))
+            }
+            _ => self.clone(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, Object::Err(_))
+    }
+
+    // Strings, arrays, bytes and packets are sequences that can be indexed,
+    // sliced and iterated over alike. The elements of a string are its
+    // characters and those of a packet are the bytes of its frame. Returns
+    // the name of the sequence used in errors, or None for other objects.
+    pub fn seq_name(&self) -> Option<&'static str> {
+        match self {
+            Object::Str(_) => Some("string"),
+            Object::Arr(_) => Some("array"),
+            Object::Bytes(_) => Some("bytes"),
+            #[cfg(feature = "pcap")]
+            Object::Packet(_) => Some("packet"),
+            _ => None,
+        }
+    }
+
+    // Number of elements in a sequence
+    pub fn seq_len(&self) -> Option<usize> {
+        match self {
+            Object::Str(s) => Some(s.chars().count()),
+            Object::Arr(arr) => Some(arr.len()),
+            Object::Bytes(bytes) => Some(bytes.len()),
+            #[cfg(feature = "pcap")]
+            Object::Packet(pkt) => Some(pkt.payload().len()),
+            _ => None,
+        }
+    }
+
+    // Element of a sequence at an index, or None if it is out of range
+    pub fn seq_get(&self, idx: usize) -> Option<Rc<Object>> {
+        match self {
+            Object::Str(s) => s.chars().nth(idx).map(|c| Rc::new(Object::Char(c))),
+            Object::Arr(arr) => arr.elements.borrow().get(idx).cloned(),
+            Object::Bytes(bytes) => bytes.get(idx).map(|b| Rc::new(Object::Byte(*b))),
+            #[cfg(feature = "pcap")]
+            Object::Packet(pkt) => pkt.payload().get(idx).map(|b| Rc::new(Object::Byte(*b))),
+            _ => None,
+        }
+    }
+
+    // Up to 'len' elements of a sequence starting at 'start'. Slices of a
+    // packet are bytes while other sequences yield the same type.
+    pub fn seq_slice(&self, start: usize, len: usize) -> Option<Rc<Object>> {
+        let obj = match self {
+            Object::Str(s) => Object::Str(s.chars().skip(start).take(len).collect()),
+            Object::Arr(arr) => {
+                let elements = arr.elements.borrow();
+                let elements = elements.iter().skip(start).take(len).cloned().collect();
+                Object::Arr(Rc::new(Array::new(elements)))
+            }
+            Object::Bytes(bytes) => {
+                Object::Bytes(bytes.iter().skip(start).take(len).copied().collect())
+            }
+            #[cfg(feature = "pcap")]
+            Object::Packet(pkt) => {
+                Object::Bytes(pkt.payload().into_iter().skip(start).take(len).collect())
+            }
+            _ => return None,
+        };
+        Some(Rc::new(obj))
+    }
+
+    // All the elements of a sequence
+    pub fn seq_elements(&self) -> Option<Vec<Rc<Object>>> {
+        match self {
+            Object::Arr(arr) => Some(arr.elements.borrow().clone()),
+            Object::Str(s) => Some(s.chars().map(|c| Rc::new(Object::Char(c))).collect()),
+            Object::Bytes(bytes) => Some(bytes.iter().map(|b| Rc::new(Object::Byte(*b))).collect()),
+            #[cfg(feature = "pcap")]
+            Object::Packet(pkt) => Some(
+                pkt.payload()
+                    .into_iter()
+                    .map(|b| Rc::new(Object::Byte(b)))
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+// Display floating point numbers using the shortest representation that
+// round-trips to the same value. Very large and very small magnitudes use
+// the scientific notation, and integral values retain a fractional part so
+// that they are distinguishable from integers.
+pub fn float_to_string(val: f64) -> String {
+    if !val.is_finite() {
+        return format!("{}", val);
+    }
+    let abs = val.abs();
+    if abs != 0. && !(1e-5..1e16).contains(&abs) {
+        return format!("{:e}", val);
+    }
+    let s = format!("{}", val);
+    if s.contains('.') {
+        s
+    } else {
+        format!("{}.0", s)
+    }
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Self::Null => write!(f, "null"),
+            // Wrap the string in quotes while printing string expressions
+            // But do not use this in builtins "puts" and "print"
+            Self::Str(s) => write!(f, r#""{}""#, s),
+            Self::Char(c) => write!(f, "'{}'", c),
+            Self::Byte(b) => write!(f, "0x{:x}", b),
+            Self::Integer(val) => write!(f, "{}", val),
+            Self::Float(val) => write!(f, "{}", float_to_string(*val)),
+            Self::Bool(val) => write!(f, "{}", val),
+            Self::Bytes(val) => write!(f, "b\"{}\"", val.escape_ascii()),
+            #[cfg(feature = "pcap")]
+            Self::Addr(val) => write!(f, "{}", val),
+            #[cfg(feature = "pcap")]
+            Self::Cidr(val) => write!(f, "{}", val),
+            Self::Timestamp(val) => write!(f, "{}", val),
+            Self::Duration(val) => write!(f, "{}", val),
+            Self::Regex(val) => write!(f, "{}", val),
+            Self::Return(val) => write!(f, "{}", val),
+            Self::Builtin(val) => write!(f, "{}", val),
+            Self::Func(val) => write!(f, "{}", val),
+            Self::Arr(val) => write!(f, "{}", val),
+            Self::Map(val) => write!(f, "{}", val),
+            Self::Set(val) => write!(f, "{}", val),
+            Self::Clos(val) => write!(f, "{}", val),
+            Self::File(val) => write!(f, "{}", val),
+            Self::Err(val) => write!(f, "{}", val),
+            #[cfg(feature = "pcap")]
+            Self::Pcap(val) => write!(f, "{}", val),
+            #[cfg(feature = "pcap")]
+            Self::Packet(val) => write!(f, "{}", val),
+            #[cfg(feature = "pcap")]
+            Self::Eth(val) => write!(f, "{}", val),
+            #[cfg(feature = "pcap")]
+            Self::Vlan(val) => write!(f, "{}", val),
+            #[cfg(feature = "pcap")]
+            Self::Ipv4(val) => write!(f, "{}", val),
+            #[cfg(feature = "pcap")]
+            Self::Ipv6(val) => write!(f, "{}", val),
+            #[cfg(feature = "pcap")]
+            Self::Udp(val) => write!(f, "{}", val),
+            #[cfg(feature = "pcap")]
+            Self::Tcp(val) => write!(f, "{}", val),
+            #[cfg(feature = "pcap")]
+            Self::Dns(val) => write!(f, "{}", val),
+            #[cfg(feature = "pcap")]
+            Self::Tls(val) => write!(f, "{}", val),
+            #[cfg(feature = "pcap")]
+            Self::Tunnel(val) => write!(f, "{}", val),
+        }
+    }
+}
+
+impl ops::Add for &Object {
+    type Output = Object;
+
+    fn add(self, other: &Object) -> Object {
+        match (self, other) {
+            (&Object::Integer(a), &Object::Integer(b)) => Object::Integer(a.wrapping_add(b)),
+            (&Object::Float(a), &Object::Float(b)) => Object::Float(a + b),
+            (&Object::Integer(a), &Object::Float(b)) => Object::Float(a as f64 + b),
+            (&Object::Float(a), &Object::Integer(b)) => Object::Float(a + b as f64),
+            (&Object::Byte(a), &Object::Byte(b)) => Object::Byte(a.wrapping_add(b)),
+            (&Object::Integer(a), &Object::Byte(b)) => Object::Integer(a.wrapping_add(b as i64)),
+            (&Object::Byte(a), &Object::Integer(b)) => Object::Integer((a as i64).wrapping_add(b)),
+            (&Object::Float(a), &Object::Byte(b)) => Object::Float(a + b as f64),
+            (&Object::Byte(a), &Object::Float(b)) => Object::Float(a as f64 + b),
+            _ => panic!("Invalid binary operation"),
+        }
+    }
+}
+
+impl ops::Sub for &Object {
+    type Output = Object;
+    fn sub(self, other: &Object) -> Object {
+        match (self, other) {
+            (&Object::Integer(a), &Object::Integer(b)) => Object::Integer(a.wrapping_sub(b)),
+            (&Object::Float(a), &Object::Float(b)) => Object::Float(a - b),
+            (&Object::Integer(a), &Object::Float(b)) => Object::Float(a as f64 - b),
+            (&Object::Float(a), &Object::Integer(b)) => Object::Float(a - b as f64),
+            (&Object::Byte(a), &Object::Byte(b)) => Object::Byte(a.wrapping_sub(b)),
+            (&Object::Integer(a), &Object::Byte(b)) => Object::Integer(a.wrapping_sub(b as i64)),
+            (&Object::Byte(a), &Object::Integer(b)) => Object::Integer((a as i64).wrapping_sub(b)),
+            (&Object::Float(a), &Object::Byte(b)) => Object::Float(a - b as f64),
+            (&Object::Byte(a), &Object::Float(b)) => Object::Float(a as f64 - b),
+            _ => panic!("Invalid binary operation"),
+        }
+    }
+}
+
+impl ops::Mul for &Object {
+    type Output = Object;
+    fn mul(self, other: &Object) -> Object {
+        match (self, other) {
+            (&Object::Integer(a), &Object::Integer(b)) => Object::Integer(a.wrapping_mul(b)),
+            (&Object::Float(a), &Object::Float(b)) => Object::Float(a * b),
+            (&Object::Integer(a), &Object::Float(b)) => Object::Float(a as f64 * b),
+            (&Object::Float(a), &Object::Integer(b)) => Object::Float(a * b as f64),
+            (&Object::Byte(a), &Object::Byte(b)) => Object::Byte(a.wrapping_mul(b)),
+            (&Object::Integer(a), &Object::Byte(b)) => Object::Integer(a.wrapping_mul(b as i64)),
+            (&Object::Byte(a), &Object::Integer(b)) => Object::Integer((a as i64).wrapping_mul(b)),
+            (&Object::Float(a), &Object::Byte(b)) => Object::Float(a * b as f64),
+            (&Object::Byte(a), &Object::Float(b)) => Object::Float(a as f64 * b),
+            _ => panic!("Invalid binary operation"),
+        }
+    }
+}
+
+impl ops::Div for &Object {
+    type Output = Object;
+    fn div(self, other: &Object) -> Object {
+        match (self, other) {
+            // Only dividing the smallest integer by -1 wraps around
+            (&Object::Integer(a), &Object::Integer(b)) => Object::Integer(a.wrapping_div(b)),
+            (&Object::Float(a), &Object::Float(b)) => Object::Float(a / b),
+            (&Object::Integer(a), &Object::Float(b)) => Object::Float(a as f64 / b),
+            (&Object::Float(a), &Object::Integer(b)) => Object::Float(a / b as f64),
+            (&Object::Byte(a), &Object::Byte(b)) => Object::Byte(a / b),
+            (&Object::Integer(a), &Object::Byte(b)) => Object::Integer(a / b as i64),
+            (&Object::Byte(a), &Object::Integer(b)) => Object::Integer((a as i64).wrapping_div(b)),
+            (&Object::Float(a), &Object::Byte(b)) => Object::Float(a / b as f64),
+            (&Object::Byte(a), &Object::Float(b)) => Object::Float(a as f64 / b),
+            _ => panic!("Invalid binary operation"),
+        }
+    }
+}
+
+impl ops::Rem for &Object {
+    type Output = Object;
+    fn rem(self, other: &Object) -> Object {
+        match (self, other) {
+            (&Object::Integer(a), &Object::Integer(b)) => Object::Integer(a.wrapping_rem(b)),
+            (&Object::Float(a), &Object::Float(b)) => Object::Float(a % b),
+            (&Object::Integer(a), &Object::Float(b)) => Object::Float(a as f64 % b),
+            (&Object::Float(a), &Object::Integer(b)) => Object::Float(a % b as f64),
+            (&Object::Byte(a), &Object::Byte(b)) => Object::Byte(a % b),
+            (&Object::Integer(a), &Object::Byte(b)) => Object::Integer(a % b as i64),
+            (&Object::Byte(a), &Object::Integer(b)) => Object::Integer((a as i64).wrapping_rem(b)),
+            (&Object::Float(a), &Object::Byte(b)) => Object::Float(a % b as f64),
+            (&Object::Byte(a), &Object::Float(b)) => Object::Float(a as f64 % b),
+            _ => panic!("Invalid binary operation"),
+        }
+    }
+}
+
+impl ops::Neg for &Object {
+    type Output = Object;
+    fn neg(self) -> Object {
+        match *self {
+            Object::Integer(a) => Object::Integer(a.wrapping_neg()),
+            Object::Float(f) => Object::Float(-f),
+            _ => panic!("Invalid binary operation"),
+        }
+    }
+}
+
+impl ops::BitAnd for &Object {
+    type Output = Object;
+
+    fn bitand(self, other: &Object) -> Object {
+        match (self, other) {
+            (&Object::Integer(a), &Object::Integer(b)) => Object::Integer(a & b),
+            (&Object::Byte(a), &Object::Byte(b)) => Object::Byte(a & b),
+            _ => panic!("Invalid bitwise operation"),
+        }
+    }
+}
+
+impl ops::BitOr for &Object {
+    type Output = Object;
+
+    fn bitor(self, other: &Object) -> Object {
+        match (self, other) {
+            (&Object::Integer(a), &Object::Integer(b)) => Object::Integer(a | b),
+            (&Object::Byte(a), &Object::Byte(b)) => Object::Byte(a | b),
+            _ => panic!("Invalid bitwise operation"),
+        }
+    }
+}
+
+impl ops::BitXor for &Object {
+    type Output = Object;
+
+    fn bitxor(self, other: &Object) -> Object {
+        match (self, other) {
+            (&Object::Integer(a), &Object::Integer(b)) => Object::Integer(a ^ b),
+            (&Object::Byte(a), &Object::Byte(b)) => Object::Byte(a ^ b),
+            _ => panic!("Invalid bitwise operation"),
+        }
+    }
+}
+
+impl ops::Shl<&Object> for &Object {
+    type Output = Object;
+
+    fn shl(self, rhs: &Object) -> Object {
+        match (self, rhs) {
+            (&Object::Integer(a), Object::Integer(b)) => Object::Integer(a.wrapping_shl(*b as u32)),
+            (&Object::Byte(a), &Object::Byte(b)) => Object::Byte(a.wrapping_shl(b as u32)),
+            _ => panic!("Invalid bitwise operation"),
+        }
+    }
+}
+
+impl ops::Shr<&Object> for &Object {
+    type Output = Object;
+
+    fn shr(self, rhs: &Object) -> Object {
+        match (self, rhs) {
+            (&Object::Integer(a), Object::Integer(b)) => Object::Integer(a.wrapping_shr(*b as u32)),
+            (&Object::Byte(a), &Object::Byte(b)) => Object::Byte(a.wrapping_shr(b as u32)),
+            _ => panic!("Invalid bitwise operation"),
+        }
+    }
+}
+
+impl Hash for Object {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            // Numbers that compare equal must hash to the same value.
+            // So, bytes and integral floats are hashed as integers.
+            Object::Integer(ref n) => n.hash(state),
+            Object::Char(ref ch) => ch.hash(state),
+            Object::Byte(ref b) => (*b as i64).hash(state),
+            Object::Float(ref f) => hash_float(*f, state),
+            Object::Bool(ref b) => b.hash(state),
+            Object::Str(ref s) => s.hash(state),
+            // Hashed as the array of bytes and the string they equal
+            Object::Bytes(ref b) => b.iter().for_each(|b| (*b as i64).hash(state)),
+            #[cfg(feature = "pcap")]
+            Object::Addr(ref a) => a.to_string().hash(state),
+            #[cfg(feature = "pcap")]
+            Object::Cidr(ref c) => c.to_string().hash(state),
+            Object::Timestamp(ref t) => t.hash(state),
+            // Hashed as the number of seconds it equals
+            Object::Duration(ref d) => hash_float(d.as_secs_f64(), state),
+            Object::Builtin(f) => f.name.hash(state),
+            Object::Arr(ref a) => a.hash(state),
+            _ => "".hash(state),
+        }
+    }
+}
+
+// Hash a float such that it hashes to the same value as the integer
+// it is equal to, if any
+fn hash_float<H: Hasher>(f: f64, state: &mut H) {
+    if f.fract() == 0. && f >= i64::MIN as f64 && f < i64::MAX as f64 {
+        (f as i64).hash(state);
+    } else {
+        // Use the built-in hash function for f64
+        state.write_u64(f.to_bits());
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use super::array::Array;
+use super::hmap::HMap;
+use super::hset::HSet;
+use super::timestamp::{Duration, Timestamp};
+use super::Object;
+#[cfg(feature = "pcap")]
+use p2sh_dissect::addr::{Addr, Cidr};
+
+// Tags identifying the type of an encoded object
+const TAG_NULL: u8 = 0;
+const TAG_BOOL: u8 = 1;
+const TAG_INTEGER: u8 = 2;
+const TAG_FLOAT: u8 = 3;
+const TAG_STR: u8 = 4;
+const TAG_CHAR: u8 = 5;
+const TAG_BYTE: u8 = 6;
+const TAG_ARR: u8 = 7;
+const TAG_MAP: u8 = 8;
+const TAG_SET: u8 = 9;
+const TAG_BYTES: u8 = 10;
+#[cfg(feature = "pcap")]
+const TAG_ADDR: u8 = 11;
+#[cfg(feature = "pcap")]
+const TAG_CIDR: u8 = 12;
+const TAG_TIMESTAMP: u8 = 13;
+const TAG_DURATION: u8 = 14;
+
+/// Encode a value into a compact binary form. Only data values are
+/// supported; functions, file handles and packets are not. Values
+/// shared between containers are encoded once for each reference.
+pub fn encode(obj: &Object, out: &mut Vec<u8>) -> Result<(), String> {
+    match obj {
+        Object::Null => out.push(TAG_NULL),
+        Object::Bool(b) => out.extend_from_slice(&[TAG_BOOL, *b as u8]),
+        Object::Integer(i) => {
+            out.push(TAG_INTEGER);
+            out.extend_from_slice(&i.to_be_bytes());
+        }
+        Object::Float(f) => {
+            out.push(TAG_FLOAT);
+            out.extend_from_slice(&f.to_bits().to_be_bytes());
+        }
+        Object::Str(s) => {
+            out.push(TAG_STR);
+            encode_len(s.len(), out);
+            out.extend_from_slice(s.as_bytes());
+        }
+        Object::Char(c) => {
+            out.push(TAG_CHAR);
+            out.extend_from_slice(&(*c as u32).to_be_bytes());
+        }
+        Object::Byte(b) => out.extend_from_slice(&[TAG_BYTE, *b]),
+        Object::Arr(arr) => {
+            out.push(TAG_ARR);
+            let elements = arr.elements.borrow();
+            encode_len(elements.len(), out);
+            for elem in elements.iter() {
+                encode(elem, out)?;
+            }
+        }
+        Object::Map(map) => {
+            out.push(TAG_MAP);
+            match map.default.borrow().as_ref() {
+                Some(default) => {
+                    out.push(1);
+                    encode(default, out)?;
+                }
+                None => out.push(0),
+            }
+            let pairs = map.sorted_pairs();
+            encode_len(pairs.len(), out);
+            for (key, val) in pairs.iter() {
+                encode(key, out)?;
+                encode(val, out)?;
+            }
+        }
+        Object::Set(set) => {
+            out.push(TAG_SET);
+            let elements = set.sorted_elements();
+            encode_len(elements.len(), out);
+            for elem in elements.iter() {
+                encode(elem, out)?;
+            }
+        }
+        Object::Bytes(bytes) => {
+            out.push(TAG_BYTES);
+            encode_len(bytes.len(), out);
+            out.extend_from_slice(bytes);
+        }
+        #[cfg(feature = "pcap")]
+        Object::Addr(addr) => {
+            // Addresses are saved in their textual form
+            let s = addr.to_string();
+            out.push(TAG_ADDR);
+            encode_len(s.len(), out);
+            out.extend_from_slice(s.as_bytes());
+        }
+        #[cfg(feature = "pcap")]
+        Object::Cidr(cidr) => {
+            let s = cidr.to_string();
+            out.push(TAG_CIDR);
+            encode_len(s.len(), out);
+            out.extend_from_slice(s.as_bytes());
+        }
+        Object::Timestamp(ts) => {
+            out.push(TAG_TIMESTAMP);
+            out.extend_from_slice(&ts.nanos().to_be_bytes());
+        }
+        Object::Duration(d) => {
+            out.push(TAG_DURATION);
+            out.extend_from_slice(&d.nanos().to_be_bytes());
+        }
+        _ => return Err(format!("cannot encode {}", obj)),
+    }
+    Ok(())
+}
+
+/// Decode a value encoded using 'encode' from the start of 'data'.
+/// Returns the value along with the number of bytes consumed.
+pub fn decode(data: &[u8]) -> Result<(Rc<Object>, usize), String> {
+    let mut reader = Reader { data, pos: 0 };
+    let obj = reader.object()?;
+    Ok((obj, reader.pos))
+}
+
+fn encode_len(len: usize, out: &mut Vec<u8>) {
+    out.extend_from_slice(&(len as u32).to_be_bytes());
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or("unexpected end of data")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut buf = [0; N];
+        buf.copy_from_slice(self.bytes(N)?);
+        Ok(buf)
+    }
+
+    fn len(&mut self) -> Result<usize, String> {
+        Ok(u32::from_be_bytes(self.array()?) as usize)
+    }
+
+    #[allow(clippy::mutable_key_type)]
+    fn object(&mut self) -> Result<Rc<Object>, String> {
+        let [tag] = self.array()?;
+        let obj = match tag {
+            TAG_NULL => Object::Null,
+            TAG_BOOL => Object::Bool(self.array::<1>()?[0] != 0),
+            TAG_INTEGER => Object::Integer(i64::from_be_bytes(self.array()?)),
+            TAG_TIMESTAMP => {
+                Object::Timestamp(Timestamp::from_nanos(i64::from_be_bytes(self.array()?)))
+            }
+            TAG_DURATION => {
+                Object::Duration(Duration::from_nanos(i64::from_be_bytes(self.array()?)))
+            }
+            TAG_FLOAT => Object::Float(f64::from_bits(u64::from_be_bytes(self.array()?))),
+            TAG_STR => {
+                let len = self.len()?;
+                let s = std::str::from_utf8(self.bytes(len)?).map_err(|e| e.to_string())?;
+                Object::Str(s.to_string())
+            }
+            TAG_BYTES => {
+                let len = self.len()?;
+                Object::Bytes(self.bytes(len)?.to_vec())
+            }
+            #[cfg(feature = "pcap")]
+            TAG_ADDR => {
+                let len = self.len()?;
+                let s = std::str::from_utf8(self.bytes(len)?).map_err(|e| e.to_string())?;
+                Object::Addr(Addr::parse(s).ok_or("invalid address")?)
+            }
+            #[cfg(feature = "pcap")]
+            TAG_CIDR => {
+                let len = self.len()?;
+                let s = std::str::from_utf8(self.bytes(len)?).map_err(|e| e.to_string())?;
+                Object::Cidr(Cidr::parse(s)?)
+            }
+            TAG_CHAR => {
+                let c = u32::from_be_bytes(self.array()?);
+                Object::Char(char::from_u32(c).ok_or("invalid char")?)
+            }
+            TAG_BYTE => Object::Byte(self.array::<1>()?[0]),
+            TAG_ARR => {
+                let len = self.len()?;
+                let elements = (0..len)
+                    .map(|_| self.object())
+                    .collect::<Result<Vec<_>, _>>()?;
+                Object::Arr(Rc::new(Array::new(elements)))
+            }
+            TAG_MAP => {
+                let [has_default] = self.array()?;
+                let default = match has_default {
+                    0 => None,
+                    _ => Some(self.object()?),
+                };
+                let len = self.len()?;
+                let mut pairs = HashMap::new();
+                for _ in 0..len {
+                    let key = self.object()?;
+                    let val = self.object()?;
+                    pairs.insert(key, val);
+                }
+                let map = HMap::new(pairs);
+                map.set_default(default);
+                Object::Map(Rc::new(map))
+            }
+            TAG_SET => {
+                let len = self.len()?;
+                let elements = (0..len)
+                    .map(|_| self.object())
+                    .collect::<Result<HashSet<_>, _>>()?;
+                Object::Set(Rc::new(HSet::new(elements)))
+            }
+            _ => return Err(format!("invalid tag {}", tag)),
+        };
+        Ok(Rc::new(obj))
+    }
+}
@@ -0,0 +1,315 @@
+use std::fmt;
+
+const NANOS_PER_SEC: i64 = 1_000_000_000;
+const SECS_PER_DAY: i64 = 86400;
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// A point in time as the number of nanoseconds since the unix epoch,
+/// such as the timestamp of a packet. Timestamps are displayed and
+/// formatted in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    nanos: i64,
+}
+
+/// A length of time in nanoseconds, such as the time between two
+/// timestamps. Durations may be negative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Duration {
+    nanos: i64,
+}
+
+/// A timestamp broken down into the calendar date and the time of day
+struct DateTime {
+    year: i64,
+    month: u32, // 1 to 12
+    day: u32,   // 1 to 31
+    hour: u32,
+    minute: u32,
+    second: u32,
+    nanos: u32,
+    weekday: u32, // 0 for Sunday
+    yearday: u32, // 1 to 366
+}
+
+impl Timestamp {
+    pub fn from_nanos(nanos: i64) -> Self {
+        Self { nanos }
+    }
+
+    /// Timestamp from the seconds and the nanoseconds since the epoch,
+    /// or None if it is out of range
+    pub fn new(secs: i64, nanos: i64) -> Option<Self> {
+        let nanos = secs.checked_mul(NANOS_PER_SEC)?.checked_add(nanos)?;
+        Some(Self { nanos })
+    }
+
+    /// Timestamp from a number of seconds since the epoch with a fraction
+    pub fn from_secs_f64(secs: f64) -> Option<Self> {
+        Self::from_nanos(0).add_secs_f64(secs)
+    }
+
+    /// Number of nanoseconds since the epoch
+    pub fn nanos(&self) -> i64 {
+        self.nanos
+    }
+
+    /// Number of whole seconds since the epoch
+    pub fn secs(&self) -> i64 {
+        self.nanos.div_euclid(NANOS_PER_SEC)
+    }
+
+    /// Nanoseconds within the second
+    pub fn subsec_nanos(&self) -> i64 {
+        self.nanos.rem_euclid(NANOS_PER_SEC)
+    }
+
+    /// Number of seconds since the epoch with a fraction
+    pub fn as_secs_f64(&self) -> f64 {
+        self.secs() as f64 + self.subsec_nanos() as f64 / 1e9
+    }
+
+    /// Time elapsed since an earlier timestamp, which is negative if the
+    /// timestamp is later, or None if it is out of range
+    pub fn since(&self, earlier: &Timestamp) -> Option<Duration> {
+        let nanos = self.nanos.checked_sub(earlier.nanos)?;
+        Some(Duration { nanos })
+    }
+
+    /// The timestamp a duration later, or None if it is out of range
+    pub fn add(&self, d: &Duration) -> Option<Self> {
+        let nanos = self.nanos.checked_add(d.nanos)?;
+        Some(Self { nanos })
+    }
+
+    /// The timestamp a number of seconds later, or None if it is out of
+    /// range
+    pub fn add_secs(&self, secs: i64) -> Option<Self> {
+        let nanos = self.nanos.checked_add(secs.checked_mul(NANOS_PER_SEC)?)?;
+        Some(Self { nanos })
+    }
+
+    /// The timestamp a number of seconds with a fraction later
+    pub fn add_secs_f64(&self, secs: f64) -> Option<Self> {
+        let delta = (secs * 1e9).round();
+        if !delta.is_finite() || delta.abs() >= i64::MAX as f64 {
+            return None;
+        }
+        let nanos = self.nanos.checked_add(delta as i64)?;
+        Some(Self { nanos })
+    }
+
+    fn datetime(&self) -> DateTime {
+        let secs = self.secs();
+        let days = secs.div_euclid(SECS_PER_DAY);
+        let tod = secs.rem_euclid(SECS_PER_DAY) as u32;
+        let (year, month, day) = civil_from_days(days);
+        let yearday = (days - days_from_civil(year, 1, 1)) as u32 + 1;
+        DateTime {
+            year,
+            month,
+            day,
+            hour: tod / 3600,
+            minute: tod / 60 % 60,
+            second: tod % 60,
+            nanos: self.subsec_nanos() as u32,
+            // The epoch was on a Thursday
+            weekday: (days + 4).rem_euclid(7) as u32,
+            yearday,
+        }
+    }
+
+    /// Format the timestamp in UTC using the conversions of strftime(3),
+    /// and '%f' and '%N' for the microseconds and the nanoseconds
+    pub fn strftime(&self, format: &str) -> Result<String, String> {
+        let dt = self.datetime();
+        let hour12 = match dt.hour % 12 {
+            0 => 12,
+            h => h,
+        };
+        let mut out = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            let Some(conv) = chars.next() else {
+                return Err(String::from("format ends with '%'"));
+            };
+            let s = match conv {
+                'Y' => dt.year.to_string(),
+                'C' => format!("{:02}", dt.year.div_euclid(100)),
+                'y' => format!("{:02}", dt.year.rem_euclid(100)),
+                'm' => format!("{:02}", dt.month),
+                'd' => format!("{:02}", dt.day),
+                'e' => format!("{:2}", dt.day),
+                'j' => format!("{:03}", dt.yearday),
+                'H' => format!("{:02}", dt.hour),
+                'I' => format!("{:02}", hour12),
+                'M' => format!("{:02}", dt.minute),
+                'S' => format!("{:02}", dt.second),
+                'f' => format!("{:06}", dt.nanos / 1000),
+                'N' => format!("{:09}", dt.nanos),
+                'p' => String::from(if dt.hour < 12 { "AM" } else { "PM" }),
+                'a' => WEEKDAYS[dt.weekday as usize][..3].to_string(),
+                'A' => WEEKDAYS[dt.weekday as usize].to_string(),
+                'b' | 'h' => MONTHS[dt.month as usize - 1][..3].to_string(),
+                'B' => MONTHS[dt.month as usize - 1].to_string(),
+                'u' => (if dt.weekday == 0 { 7 } else { dt.weekday }).to_string(),
+                'w' => dt.weekday.to_string(),
+                's' => self.secs().to_string(),
+                'F' => format!("{}-{:02}-{:02}", dt.year, dt.month, dt.day),
+                'D' => format!(
+                    "{:02}/{:02}/{:02}",
+                    dt.month,
+                    dt.day,
+                    dt.year.rem_euclid(100)
+                ),
+                'T' => format!("{:02}:{:02}:{:02}", dt.hour, dt.minute, dt.second),
+                'R' => format!("{:02}:{:02}", dt.hour, dt.minute),
+                'z' => String::from("+0000"),
+                'Z' => String::from("UTC"),
+                'n' => String::from("\n"),
+                't' => String::from("\t"),
+                '%' => String::from("%"),
+                c => return Err(format!("unsupported conversion '%{}'", c)),
+            };
+            out.push_str(&s);
+        }
+        Ok(out)
+    }
+}
+
+impl Duration {
+    pub fn from_nanos(nanos: i64) -> Self {
+        Self { nanos }
+    }
+
+    /// Duration of a number of seconds with a fraction, or None if it is
+    /// out of range
+    pub fn from_secs_f64(secs: f64) -> Option<Self> {
+        let nanos = (secs * 1e9).round();
+        if !nanos.is_finite() || nanos.abs() >= i64::MAX as f64 {
+            return None;
+        }
+        Some(Self {
+            nanos: nanos as i64,
+        })
+    }
+
+    /// Duration of a number of milliseconds with a fraction
+    pub fn from_millis_f64(millis: f64) -> Option<Self> {
+        Self::from_secs_f64(millis / 1e3)
+    }
+
+    pub fn nanos(&self) -> i64 {
+        self.nanos
+    }
+
+    /// Number of whole seconds, rounded towards zero
+    pub fn secs(&self) -> i64 {
+        self.nanos / NANOS_PER_SEC
+    }
+
+    pub fn as_secs_f64(&self) -> f64 {
+        self.nanos as f64 / 1e9
+    }
+
+    pub fn checked_add(&self, other: &Duration) -> Option<Self> {
+        let nanos = self.nanos.checked_add(other.nanos)?;
+        Some(Self { nanos })
+    }
+
+    pub fn checked_sub(&self, other: &Duration) -> Option<Self> {
+        let nanos = self.nanos.checked_sub(other.nanos)?;
+        Some(Self { nanos })
+    }
+
+    pub fn checked_neg(&self) -> Option<Self> {
+        let nanos = self.nanos.checked_neg()?;
+        Some(Self { nanos })
+    }
+
+    /// The duration scaled by a factor, or None if it is out of range
+    pub fn mul_f64(&self, factor: f64) -> Option<Self> {
+        Self::from_secs_f64(self.as_secs_f64() * factor)
+    }
+}
+
+/// Displayed in the largest of the units of the duration literals that
+/// it is a whole number of, such as '1500ms' or '90s'
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (div, unit) = match self.nanos {
+            0 => (1, "s"),
+            n if n % NANOS_PER_SEC == 0 => (NANOS_PER_SEC, "s"),
+            n if n % 1_000_000 == 0 => (1_000_000, "ms"),
+            n if n % 1_000 == 0 => (1_000, "us"),
+            _ => (1, "ns"),
+        };
+        write!(f, "{}{}", self.nanos / div, unit)
+    }
+}
+
+/// Displayed in the format of RFC 3339 in UTC with nanoseconds
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let dt = self.datetime();
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}Z",
+            dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second, dt.nanos
+        )
+    }
+}
+
+// Year, month and day of a number of days since 1970-01-01 in the
+// proleptic gregorian calendar. The algorithm is from Howard Hinnant's
+// 'chrono-compatible low-level date algorithms'.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+// Number of days since 1970-01-01 of a date
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
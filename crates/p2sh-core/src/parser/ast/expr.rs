@@ -1,5 +1,6 @@
 use std::fmt;
 use std::fmt::Write;
+use std::mem;
 
 use super::stmt::*;
 use crate::code::prop::PacketPropType;
@@ -20,6 +21,7 @@ pub enum Expression {
     Binary(BinaryExpr),
     Bool(BooleanExpr),
     If(IfExpr),
+    Ternary(TernaryExpr),
     Match(MatchExpr),
     Function(FunctionLiteral),
     Call(CallExpr),
@@ -176,9 +178,43 @@ pub struct BinaryExpr {
     pub right: Box<Expression>,
 }
 
+impl BinaryExpr {
+    /// Take the left and the right operands out of the expression, leaving
+    /// invalid ones in their place
+    pub fn take_operands(&mut self) -> (Expression, Expression) {
+        (
+            mem::replace(&mut self.left, Expression::Invalid),
+            mem::replace(&mut self.right, Expression::Invalid),
+        )
+    }
+}
+
+// A chain such as 'a + b + c' nests to the left, and is dropped in a loop
+// so that dropping long chains does not recurse once for each operator
+impl Drop for BinaryExpr {
+    fn drop(&mut self) {
+        let mut left = mem::replace(&mut *self.left, Expression::Invalid);
+        while let Expression::Binary(mut binary) = left {
+            left = mem::replace(&mut *binary.left, Expression::Invalid);
+        }
+    }
+}
+
 impl fmt::Display for BinaryExpr {
+    // A chain such as 'a + b + c' nests to the left, and is written in a
+    // loop so that long chains do not recurse once for each operator
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "({} {} {})", self.left, self.token, self.right)
+        let mut chain = vec![self];
+        let mut left = self.left.as_ref();
+        while let Expression::Binary(binary) = left {
+            chain.push(binary);
+            left = binary.left.as_ref();
+        }
+        write!(f, "{}{}", "(".repeat(chain.len()), left)?;
+        for binary in chain.iter().rev() {
+            write!(f, " {} {})", binary.token, binary.right)?;
+        }
+        Ok(())
     }
 }
 
@@ -231,6 +267,24 @@ impl fmt::Display for IfExpr {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct TernaryExpr {
+    pub token: Token, // ? token
+    pub condition: Box<Expression>,
+    pub then_expr: Box<Expression>,
+    pub else_expr: Box<Expression>,
+}
+
+impl fmt::Display for TernaryExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "({} ? {} : {})",
+            self.condition, self.then_expr, self.else_expr
+        )
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum MatchPattern {
     Boolean(BooleanExpr),
@@ -340,7 +394,7 @@ impl fmt::Display for MatchArm {
             let _ = write!(&mut acc, "{} | ", p);
             acc
         });
-        let pat_str = pat_str.trim_end_matches(|c| c == ' ' || c == ',');
+        let pat_str = pat_str.trim_end_matches([' ', ',']);
         let body = format!("{}", self.body);
         write!(f, " {} => {{ {} }}", pat_str, body.trim())?;
         Ok(())
@@ -385,7 +439,7 @@ impl fmt::Display for FunctionLiteral {
             let _ = write!(&mut acc, "{}, ", p);
             acc
         });
-        let params_str = params_str.trim_end_matches(|c| c == ' ' || c == ',');
+        let params_str = params_str.trim_end_matches([' ', ',']);
         write!(f, "{} ({}) {}", self.token, params_str, self.body)
     }
 }
@@ -403,7 +457,7 @@ impl fmt::Display for CallExpr {
             let _ = write!(&mut acc, "{}, ", p);
             acc
         });
-        let args_str = args_str.trim_end_matches(|c| c == ' ' || c == ',');
+        let args_str = args_str.trim_end_matches([' ', ',']);
         write!(f, "{}({})", self.func, args_str)
     }
 }
@@ -420,7 +474,7 @@ impl fmt::Display for ArrayLiteral {
             let _ = write!(&mut acc, "{}, ", p);
             acc
         });
-        let elements_str = elements_str.trim_end_matches(|c| c == ' ' || c == ',');
+        let elements_str = elements_str.trim_end_matches([' ', ',']);
         write!(f, "[{}]", elements_str)
     }
 }
@@ -440,7 +494,7 @@ impl fmt::Display for HashLiteral {
             let _ = write!(&mut acc, "{}: {}, ", p.0, p.1);
             acc
         });
-        let pairs_str = pairs_str.trim_end_matches(|c| c == ' ' || c == ',');
+        let pairs_str = pairs_str.trim_end_matches([' ', ',']);
         write!(f, "{{{}}}", pairs_str)
     }
 }
@@ -531,6 +585,7 @@ impl Expression {
             Expression::Binary(binary) => binary.token.literal.clone(),
             Expression::Bool(b) => b.token.literal.clone(),
             Expression::If(i) => i.token.literal.clone(),
+            Expression::Ternary(t) => t.token.literal.clone(),
             Expression::Match(m) => m.token.literal.clone(),
             Expression::Function(f) => f.token.literal.clone(),
             Expression::Call(c) => c.token.literal.clone(),
@@ -562,6 +617,7 @@ impl fmt::Display for Expression {
             Expression::Binary(binary) => write!(f, "{}", binary),
             Expression::Bool(b) => write!(f, "{}", b),
             Expression::If(i) => write!(f, "{}", i),
+            Expression::Ternary(t) => write!(f, "{}", t),
             Expression::Match(m) => write!(f, "{}", m),
             Expression::Function(fun) => write!(f, "{}", fun),
             Expression::Call(c) => write!(f, "{}", c),
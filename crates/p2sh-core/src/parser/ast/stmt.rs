@@ -11,10 +11,14 @@ pub enum Statement {
     Block(BlockStatement),
     Loop(LoopStmt),
     While(WhileStmt),
+    For(ForStmt),
     Break(BreakStmt),
     Continue(ContinueStmt),
     Function(FunctionLiteral),
     Filter(FilterStmt),
+    Import(ImportStmt),
+    Try(TryStmt),
+    Incr(IncrStmt),
     Invalid,
 }
 
@@ -81,6 +85,58 @@ impl fmt::Display for WhileStmt {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct ForStmt {
+    pub token: Token, // for token
+    pub label: Option<Token>,
+    pub vars: Vec<Token>, // one or two loop variables
+    pub iterable: Expression,
+    pub body: BlockStatement,
+}
+
+impl fmt::Display for ForStmt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let vars: Vec<&str> = self.vars.iter().map(|v| v.literal.as_str()).collect();
+        write!(
+            f,
+            "for {} in {} {{ {} }}",
+            vars.join(", "),
+            self.iterable,
+            self.body
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TryStmt {
+    pub token: Token, // try token
+    pub body: BlockStatement,
+    pub var: Token, // the name of the error in the catch block
+    pub handler: BlockStatement,
+}
+
+impl fmt::Display for TryStmt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "try {{ {} }} catch {} {{ {} }}",
+            self.body, self.var.literal, self.handler
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IncrStmt {
+    pub token: Token, // '++' or '--' token
+    pub target: Expression,
+}
+
+impl fmt::Display for IncrStmt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.target, self.token.literal)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ExpressionStmt {
     pub token: Token,
@@ -103,10 +159,23 @@ impl fmt::Display for BlockStatement {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct ImportStmt {
+    pub token: Token, // import token
+    pub path: String,
+}
+
+impl fmt::Display for ImportStmt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "import \"{}\";", self.path)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum FilterPattern {
     Expr(Box<Expression>),
     End,
+    Interval(f64), // Seconds between the runs of the action
     None,
 }
 
@@ -117,12 +186,16 @@ impl FilterPattern {
     pub fn is_end(&self) -> bool {
         matches!(self, FilterPattern::End)
     }
+    pub fn is_interval(&self) -> bool {
+        matches!(self, FilterPattern::Interval(_))
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct FilterStmt {
     pub token: Token, // '@' token
     pub pattern: FilterPattern,
+    pub source: String, // source text of the pattern
     pub action: Option<BlockStatement>,
 }
 
@@ -132,6 +205,7 @@ impl fmt::Display for FilterStmt {
         match &self.pattern {
             FilterPattern::Expr(expr) => write!(f, "{}", expr)?,
             FilterPattern::End => write!(f, "end")?,
+            FilterPattern::Interval(secs) => write!(f, "interval {}s", secs)?,
             FilterPattern::None => write!(f, "")?,
         }
         if let Some(ref action) = self.action {
@@ -150,10 +224,14 @@ impl Statement {
             Statement::Block(stmt) => stmt.token.literal.clone(),
             Statement::Loop(stmt) => stmt.token.literal.clone(),
             Statement::While(stmt) => stmt.token.literal.clone(),
+            Statement::For(stmt) => stmt.token.literal.clone(),
             Statement::Break(brk) => brk.token.literal.clone(),
             Statement::Continue(con) => con.token.literal.clone(),
             Statement::Function(stmt) => stmt.token.literal.clone(),
             Statement::Filter(stmt) => stmt.token.literal.clone(),
+            Statement::Import(stmt) => stmt.token.literal.clone(),
+            Statement::Try(stmt) => stmt.token.literal.clone(),
+            Statement::Incr(stmt) => stmt.token.literal.clone(),
             Statement::Invalid => "null".to_string(),
         }
     }
@@ -171,10 +249,14 @@ impl fmt::Display for Statement {
             Statement::Block(b) => write!(f, "{}", b),
             Statement::Loop(l) => write!(f, "{}", l),
             Statement::While(w) => write!(f, "{}", w),
+            Statement::For(s) => write!(f, "{}", s),
             Statement::Break(_) => write!(f, "break"),
             Statement::Continue(_) => write!(f, "continue"),
             Statement::Function(fun) => write!(f, "{}", fun),
             Statement::Filter(s) => write!(f, "{}", s),
+            Statement::Import(i) => write!(f, "{}", i),
+            Statement::Try(t) => write!(f, "{}", t),
+            Statement::Incr(i) => write!(f, "{}", i),
             Statement::Invalid => write!(f, "invalid"),
         }
     }
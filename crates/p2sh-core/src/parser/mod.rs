@@ -10,18 +10,29 @@ use ast::stmt::*;
 use ast::*;
 
 use self::precedence::Precedence;
+use self::rules::parse_duration;
 
 type ParseError = String;
 type ParseErrors = Vec<ParseError>;
 
+// Deepest nesting of expressions and blocks. Parsing and compiling nested
+// expressions recurse, so deeper nesting could overflow the native stack.
+pub const MAX_NESTING: usize = 256;
+
 #[derive(Default)]
 pub struct Parser {
     scanner: Scanner,
     previous: Token,
     current: Token,
     peek_next: Token,
+    // Positions of the current and the next tokens in the source
+    curr_span: (usize, usize),
+    peek_span: (usize, usize),
     errors: ParseErrors,
     in_match_pattern: bool,
+    in_group: bool,      // within parentheses, brackets or a map literal
+    nesting: usize,      // depth of the expression or block being parsed
+    nesting_error: bool, // set once the nesting is too deep
 }
 
 impl Parser {
@@ -39,6 +50,8 @@ impl Parser {
         self.previous = self.current.clone();
         self.current = self.peek_next.clone();
         self.peek_next = self.scanner.next_token();
+        self.curr_span = self.peek_span;
+        self.peek_span = self.scanner.span();
     }
 
     fn prev_token_is(&self, ttype: &TokenType) -> bool {
@@ -63,15 +76,68 @@ impl Parser {
         }
     }
 
+    // Advance to the name being defined. A keyword in place of the name
+    // gets an error that tells that it is reserved rather than the generic
+    // 'expected token' error.
+    fn expect_name(&mut self, what: &str) -> bool {
+        if !self.peek_token_is(&TokenType::Identifier)
+            && Scanner::is_keyword(&self.peek_next.literal)
+        {
+            let msg = format!(
+                "'{}' is a reserved word and can't be used as a {} name",
+                self.peek_next.literal, what
+            );
+            // Skip the keyword so that it doesn't start a new statement
+            self.next_token();
+            self.push_error(&msg);
+            return false;
+        }
+        self.expect_peek(&TokenType::Identifier)
+    }
+
     // Report error at a specified line. This is used for reporting errors
     // in expressions that had an error in a prior sub expression which
     // causes the parser to synchronize and skip tokens until the next
     // statement. This helps report error at a line prior to synchronization.
     pub fn push_error_at(&mut self, err: &str, line: usize) {
+        // Parsing stops at a nesting that is too deep, so the errors of
+        // the enclosing expressions are not reported
+        if self.nesting_error {
+            return;
+        }
         self.errors.push(format!("[line {}] {}", line, err));
         self.synchronize();
     }
 
+    // Go one level deeper into nested expressions or blocks. Returns false
+    // after reporting an error and skipping the rest of the input if the
+    // nesting is too deep.
+    fn enter_nesting(&mut self) -> bool {
+        self.nesting += 1;
+        if self.nesting <= MAX_NESTING {
+            return true;
+        }
+        if !self.nesting_error {
+            let msg = format!("nesting is deeper than the limit of {}", MAX_NESTING);
+            self.push_error(&msg);
+            self.nesting_error = true;
+        }
+        while !self.curr_token_is(&TokenType::Eof) {
+            self.next_token();
+        }
+        false
+    }
+
+    // Parse within parentheses, brackets or the braces of a map, where a
+    // newline does not end the expression, or with 'in_group' false within
+    // a block, where it does again.
+    fn parse_in_group<T>(&mut self, in_group: bool, parse: impl FnOnce(&mut Self) -> T) -> T {
+        let outer = std::mem::replace(&mut self.in_group, in_group);
+        let result = parse(self);
+        self.in_group = outer;
+        result
+    }
+
     pub fn push_error(&mut self, err: &str) {
         self.push_error_at(err, self.scanner.get_line())
     }
@@ -81,10 +147,18 @@ impl Parser {
     }
 
     pub fn peek_error(&mut self, ttype: &TokenType) {
-        let msg = format!(
-            "expected token {}, got {} instead",
-            ttype, self.peek_next.ttype
-        );
+        // Show the offending text for illegal tokens
+        let msg = if self.peek_next.ttype == TokenType::Illegal {
+            format!(
+                "expected token {}, got {} '{}' instead",
+                ttype, self.peek_next.ttype, self.peek_next.literal
+            )
+        } else {
+            format!(
+                "expected token {}, got {} instead",
+                ttype, self.peek_next.ttype
+            )
+        };
         self.push_error(&msg);
     }
 
@@ -116,6 +190,11 @@ impl Parser {
         let mut program = Program::default();
 
         while self.current.ttype != TokenType::Eof {
+            // Skip empty statements
+            if self.curr_token_is(&TokenType::Semicolon) {
+                self.next_token();
+                continue;
+            }
             // TODO: Revisit error handling
             if let Ok(stmt) = self.parse_statement() {
                 program.statements.push(stmt)
@@ -132,18 +211,21 @@ impl Parser {
             TokenType::Return => self.parse_return_statement(),
             TokenType::Loop => self.parse_loop_statement(None),
             TokenType::While => self.parse_while_statement(None),
+            TokenType::For => self.parse_for_statement(None),
             TokenType::Break => self.parse_break_statement(),
             TokenType::Continue => self.parse_continue_statement(),
             TokenType::Function => self.parse_function_statement(),
             TokenType::LeftBrace => self.parse_block_begin(),
             TokenType::Filter => self.parse_filter_statement(),
+            TokenType::Import => self.parse_import_statement(),
+            TokenType::Try => self.parse_try_statement(),
             _ => self.parse_expr_statement(),
         }
     }
 
     fn parse_let_statement(&mut self) -> Result<Statement, ParseError> {
         let token_let = self.current.clone();
-        if !self.expect_peek(&TokenType::Identifier) {
+        if !self.expect_name("variable") {
             return Ok(Statement::Invalid);
         }
         let token_ident = self.current.clone();
@@ -237,6 +319,52 @@ impl Parser {
         }))
     }
 
+    // For statements are of the form 'for <var>[, <var>] in <expr> { <body> }'.
+    // The expression may also be a range of integers, whose bounds, unlike
+    // those of the ranges in match patterns, can be any expressions.
+    fn parse_for_statement(&mut self, label: Option<Token>) -> Result<Statement, ParseError> {
+        let token = self.current.clone();
+        let mut vars = Vec::new();
+        loop {
+            if !self.expect_peek(&TokenType::Identifier) {
+                return Ok(Statement::Invalid);
+            }
+            vars.push(self.current.clone());
+            if vars.len() == 2 || !self.peek_token_is(&TokenType::Comma) {
+                break;
+            }
+            self.next_token();
+        }
+        if !self.expect_peek(&TokenType::In) {
+            return Ok(Statement::Invalid);
+        }
+        self.next_token();
+        let mut iterable = self.parse_expression(Precedence::LogicalOr, false);
+        if self.peek_token_is(&TokenType::RangeEx) || self.peek_token_is(&TokenType::RangeInc) {
+            self.next_token();
+            let range_token = self.current.clone();
+            self.next_token();
+            let end = self.parse_expression(Precedence::LogicalOr, false);
+            iterable = Expression::Range(RangeExpr {
+                operator: range_token.literal.clone(),
+                token: range_token,
+                begin: Box::new(iterable),
+                end: Box::new(end),
+            });
+        }
+        if !self.expect_peek(&TokenType::LeftBrace) {
+            return Ok(Statement::Invalid);
+        }
+        let body = self.parse_block_statement();
+        Ok(Statement::For(ForStmt {
+            token,
+            label,
+            vars,
+            iterable,
+            body,
+        }))
+    }
+
     fn parse_break_statement(&mut self) -> Result<Statement, ParseError> {
         // The break token
         let token = self.current.clone();
@@ -271,14 +399,76 @@ impl Parser {
         Ok(Statement::Continue(con_stmt))
     }
 
+    // Import statements are of the form 'import "<path>";'
+    fn parse_import_statement(&mut self) -> Result<Statement, ParseError> {
+        let token = self.current.clone(); // import keyword
+        if !self.expect_peek(&TokenType::Str) {
+            return Ok(Statement::Invalid);
+        }
+        let path = self.current.literal.clone();
+        if self.peek_token_is(&TokenType::Semicolon) {
+            self.next_token();
+        }
+        Ok(Statement::Import(ImportStmt { token, path }))
+    }
+
+    // Try statements are of the form 'try { <body> } catch <var> { <handler> }'
+    fn parse_try_statement(&mut self) -> Result<Statement, ParseError> {
+        let token = self.current.clone(); // try keyword
+        if !self.expect_peek(&TokenType::LeftBrace) {
+            return Ok(Statement::Invalid);
+        }
+        let body = self.parse_block_statement();
+        if !self.expect_peek(&TokenType::Catch) {
+            return Ok(Statement::Invalid);
+        }
+        if !self.expect_name("variable") {
+            return Ok(Statement::Invalid);
+        }
+        let var = self.current.clone();
+        if !self.expect_peek(&TokenType::LeftBrace) {
+            return Ok(Statement::Invalid);
+        }
+        let handler = self.parse_block_statement();
+        Ok(Statement::Try(TryStmt {
+            token,
+            body,
+            var,
+            handler,
+        }))
+    }
+
+    // Increment statements are of the form '<target>++' or '<target>--'
+    // where the target is a variable or an indexed element
+    fn parse_incr_statement(&mut self, target: Expression) -> Result<Statement, ParseError> {
+        let token = self.current.clone(); // '++' or '--'
+        if !matches!(target, Expression::Ident(_) | Expression::Index(_)) {
+            self.push_error(&format!("Invalid operand of '{}'", token.literal));
+            return Ok(Statement::Invalid);
+        }
+        if self.peek_token_is(&TokenType::Semicolon) {
+            self.next_token();
+        }
+        Ok(Statement::Incr(IncrStmt { token, target }))
+    }
+
     // Function statements are of the form 'fn <name>(<params>) { <body> }'.
     // They differ from function expressions and are parsed differently.
     // However, the underlying implementations are the same.
     fn parse_function_statement(&mut self) -> Result<Statement, ParseError> {
         let token = self.current.clone(); // fn keyword
-        if !self.peek_token_is(&TokenType::Identifier) {
+        if self.peek_token_is(&TokenType::LeftParen) {
+            // An anonymous function
             return self.parse_expr_statement();
         }
+        if !self.peek_token_is(&TokenType::Identifier) {
+            if Scanner::is_keyword(&self.peek_next.literal) {
+                self.expect_name("function");
+            } else {
+                self.push_error("expected a function name or '(' after 'fn'");
+            }
+            return Ok(Statement::Invalid);
+        }
         // Advance to the function name
         self.next_token();
         let name = self.current.clone(); // fn name
@@ -305,6 +495,7 @@ impl Parser {
         let token: Token = self.current.clone();
         // advance to the filter pattern expression or to the action
         self.next_token();
+        let mut source = String::new();
 
         let pattern = if self.curr_token_is(&TokenType::LeftBrace) {
             FilterPattern::None
@@ -314,11 +505,35 @@ impl Parser {
                 self.push_error("expected '{' after 'end'");
                 return Ok(Statement::Invalid);
             }
+            source.push_str("end");
             FilterPattern::End
+        } else if self.curr_token_is_interval() {
+            // 'interval' is not a keyword so that it remains usable as a name
+            self.next_token();
+            let literal = self.current.literal.clone();
+            let secs = literal
+                .parse::<f64>()
+                .ok()
+                .or_else(|| parse_duration(&literal));
+            let Some(secs) = secs.filter(|s| *s > 0.0 && s.is_finite()) else {
+                self.push_error(&format!("invalid interval '{}'", literal));
+                return Ok(Statement::Invalid);
+            };
+            self.next_token();
+            if !self.curr_token_is(&TokenType::LeftBrace) {
+                self.push_error("expected '{' after the interval");
+                return Ok(Statement::Invalid);
+            }
+            source = format!("interval {}", literal);
+            FilterPattern::Interval(secs)
         } else {
+            let start = self.curr_span.0;
             let filter = FilterPattern::Expr(Box::new(
                 self.parse_expression(Precedence::Assignment, false),
             ));
+            // Join the lines of a pattern that spans multiple lines
+            let text = self.scanner.source(start, self.curr_span.1);
+            source = text.lines().map(str::trim).collect::<Vec<_>>().join(" ");
             // advance to the left brace
             if self.peek_token_is(&TokenType::LeftBrace) {
                 self.next_token();
@@ -339,10 +554,19 @@ impl Parser {
         Ok(Statement::Filter(FilterStmt {
             token,
             pattern,
+            source,
             action,
         }))
     }
 
+    // An 'interval' identifier followed by a number or a duration such as
+    // '1s' begins an interval filter statement
+    fn curr_token_is_interval(&self) -> bool {
+        self.curr_token_is(&TokenType::Identifier)
+            && self.current.literal == "interval"
+            && (self.peek_token_is(&TokenType::Decimal) || self.peek_token_is(&TokenType::Float))
+    }
+
     // Parse a statement as expression statement if it is none of the
     // other statement types. However, if the statement begins with
     // an idenifier and a colon, it is a label and should be followed
@@ -365,13 +589,24 @@ impl Parser {
                     // pass the label token to the while statement
                     return self.parse_while_statement(Some(token_expr));
                 }
+                TokenType::For => {
+                    self.next_token();
+                    // pass the label token to the for statement
+                    return self.parse_for_statement(Some(token_expr));
+                }
                 _ => {
                     // only loops support labels for now
+                    let msg = format!("label '{}' is not followed by a loop", token_expr.literal);
+                    self.push_error(&msg);
                     Ok(Statement::Invalid)
                 }
             };
         }
         let expr = self.parse_expression(Precedence::Assignment, false);
+        if self.peek_token_is(&TokenType::PlusPlus) || self.peek_token_is(&TokenType::MinusMinus) {
+            self.next_token();
+            return self.parse_incr_statement(expr);
+        }
         if self.peek_token_is(&TokenType::Semicolon) {
             self.next_token();
         }
@@ -421,6 +656,16 @@ impl Parser {
     /// 'a + b + c' -->> (a + (b + c)) when 'precedence <= self.peek_precedence()'
     ///
     fn parse_expression(&mut self, precedence: Precedence, property: bool) -> Expression {
+        let nesting = self.nesting;
+        let expr = self.parse_nested_expression(precedence, property);
+        self.nesting = nesting;
+        expr
+    }
+
+    fn parse_nested_expression(&mut self, precedence: Precedence, property: bool) -> Expression {
+        if !self.enter_nesting() {
+            return Expression::Invalid;
+        }
         let can_assign = precedence <= Precedence::Assignment;
         self.peek_invalid_assignment(can_assign);
 
@@ -434,6 +679,13 @@ impl Parser {
                 if let Some(infix) = &self.peek_infix() {
                     self.next_token();
                     left_expr = infix(self, left_expr);
+                    // A chain of binary operators such as 'a + b + c' is
+                    // compiled without recursing into the operands to its
+                    // left, so it does not count as nesting. Calls, indexes
+                    // and the like nest the expression to their left.
+                    if !matches!(left_expr, Expression::Binary(_)) && !self.enter_nesting() {
+                        return Expression::Invalid;
+                    }
                 }
             }
             left_expr
@@ -444,6 +696,13 @@ impl Parser {
     }
 
     fn no_prefix_parse_error(&mut self) {
+        // An unterminated block comment spans lines till the end of input.
+        // So, report the line where the comment starts.
+        if self.current.ttype == TokenType::Illegal && self.current.literal == "/*" {
+            let line = self.current.line;
+            self.push_error_at("unterminated block comment", line);
+            return;
+        }
         let msg = format!("failed to parse token '{}'", self.current);
         self.push_error(&msg);
     }
@@ -7,11 +7,12 @@ pub enum Precedence {
     #[default]
     Lowest = 0,
     Assignment, // =
+    Ternary,    // ? :
     MatchOr,    // | (in match pattern)
     Range,      // .. ..=
     LogicalOr,  // ||
     LogicalAnd, // &&
-    Relational, // == != < > <= >=
+    Relational, // == != < > <= >= ~ !~ in
     BitwiseOr,  // |
     BitwiseXor, // ^
     BitwiseAnd, // &
@@ -45,20 +46,21 @@ impl From<usize> for Precedence {
         match v {
             0 => Precedence::Lowest,
             1 => Precedence::Assignment,
-            2 => Precedence::MatchOr,
-            3 => Precedence::Range,
-            4 => Precedence::LogicalOr,
-            5 => Precedence::LogicalAnd,
-            6 => Precedence::Relational,
-            7 => Precedence::BitwiseOr,
-            8 => Precedence::BitwiseXor,
-            9 => Precedence::BitwiseAnd,
-            10 => Precedence::Shift,
-            11 => Precedence::Term,
-            12 => Precedence::Factor,
-            13 => Precedence::Unary,
-            14 => Precedence::Call,
-            15 => Precedence::Primary,
+            2 => Precedence::Ternary,
+            3 => Precedence::MatchOr,
+            4 => Precedence::Range,
+            5 => Precedence::LogicalOr,
+            6 => Precedence::LogicalAnd,
+            7 => Precedence::Relational,
+            8 => Precedence::BitwiseOr,
+            9 => Precedence::BitwiseXor,
+            10 => Precedence::BitwiseAnd,
+            11 => Precedence::Shift,
+            12 => Precedence::Term,
+            13 => Precedence::Factor,
+            14 => Precedence::Unary,
+            15 => Precedence::Call,
+            16 => Precedence::Primary,
             _ => panic!("Cannot convert {} into Precedence", v),
         }
     }
@@ -88,11 +88,11 @@ lazy_static! {
             None,
             Precedence::Lowest,
         );
-        // Unary - Bitwise '~'
+        // Unary - Bitwise '~', and binary - regex match '~'
         rules[TokenType::BitwiseNot as usize] = ParseRule::new(
             Some(Parser::parse_prefix_expression),
-            None,
-            Precedence::Lowest,
+            Some(Parser::parse_infix_expression),
+            Precedence::Relational,
         );
         // Unary - Dollar '$'
         rules[TokenType::Dollar as usize] = ParseRule::new(
@@ -111,6 +111,11 @@ lazy_static! {
             Some(Parser::parse_infix_expression),
             Precedence::Relational,
         );
+        rules[TokenType::BangTilde as usize] = ParseRule::new(
+            None,
+            Some(Parser::parse_infix_expression),
+            Precedence::Relational,
+        );
         rules[TokenType::Less as usize] = ParseRule::new(
             None,
             Some(Parser::parse_infix_expression),
@@ -131,6 +136,13 @@ lazy_static! {
             Some(Parser::parse_infix_expression),
             Precedence::Relational,
         );
+        // Binary - Containment 'in', which also separates the variables of
+        // a for loop from what it iterates over
+        rules[TokenType::In as usize] = ParseRule::new(
+            None,
+            Some(Parser::parse_infix_expression),
+            Precedence::Relational,
+        );
         // Binary - Arithmetic
         rules[TokenType::Plus as usize] =
             ParseRule::new(None, Some(Parser::parse_infix_expression), Precedence::Term);
@@ -203,6 +215,12 @@ lazy_static! {
         // Control flow
         rules[TokenType::If as usize] =
             ParseRule::new(Some(Parser::parse_if_expr), None, Precedence::Lowest);
+        rules[TokenType::Question as usize] = ParseRule::new_with_assoc(
+            None,
+            Some(Parser::parse_ternary_expression),
+            Precedence::Ternary,
+            Associativity::Right
+        );
         rules[TokenType::Match as usize] =
             ParseRule::new(Some(Parser::parse_match_expr), None, Precedence::Lowest);
         // Function
@@ -253,6 +271,21 @@ lazy_static! {
     };
 }
 
+/// Parse a duration literal such as '10s' or '1.5ms' into seconds
+pub fn parse_duration(literal: &str) -> Option<f64> {
+    let number = literal.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let n = number.parse::<f64>().ok()?;
+    match &literal[number.len()..] {
+        "ns" => Some(n / 1e9),
+        "us" => Some(n / 1e6),
+        "ms" => Some(n / 1e3),
+        "s" => Some(n),
+        "m" => Some(n * 60.0),
+        "h" => Some(n * 3600.0),
+        _ => None,
+    }
+}
+
 impl Parser {
     // Return the precedence of the current token. If the current token is a
     // bitwise OR operator and the parser is currently parsing a match pattern,
@@ -296,6 +329,23 @@ impl Parser {
         precedence_cond
             && !self.peek_token_is(&TokenType::Semicolon)
             && !self.peek_token_is(&TokenType::Eof)
+            && !self.peek_starts_statement()
+    }
+
+    /// A newline terminates an expression statement if the next line starts
+    /// with a token that can begin an expression as well as continue one,
+    /// i.e. '(', '[' or '-'. Otherwise, 'a' followed by '(b)' on the next
+    /// line would be parsed as a call. Operators that can only continue an
+    /// expression, e.g. '+' or '&&', may start the next line. Within
+    /// parentheses, brackets or a map literal no statement can start, so
+    /// the expression continues there.
+    fn peek_starts_statement(&self) -> bool {
+        !self.in_group
+            && self.peek_next.line > self.current.line
+            && matches!(
+                self.peek_next.ttype,
+                TokenType::LeftParen | TokenType::LeftBracket | TokenType::Minus
+            )
     }
 
     fn parse_null(&mut self, _: bool) -> Expression {
@@ -353,6 +403,11 @@ impl Parser {
                 token: self.current.clone(),
                 value,
             })
+        } else if let Some(value) = parse_duration(&self.current.literal) {
+            Expression::Float(FloatLiteral {
+                token: self.current.clone(),
+                value,
+            })
         } else {
             let msg = format!("could not parse '{}' as an integer", self.current.literal);
             self.push_error(&msg);
@@ -361,54 +416,27 @@ impl Parser {
     }
 
     fn parse_octal(&mut self, _: bool) -> Expression {
-        self.peek_invalid_assignment(false);
-        let str_value = &self.current.literal[2..];
-        if let Ok(value) = i64::from_str_radix(str_value, 8) {
-            Expression::Integer(IntegerLiteral {
-                token: self.current.clone(),
-                value,
-            })
-        } else {
-            let msg = format!(
-                "could not parse '{}' as an octal integer",
-                self.current.literal
-            );
-            self.push_error(&msg);
-            Expression::Invalid
-        }
+        self.parse_radix(8, "an octal")
     }
 
     fn parse_hexadecimal(&mut self, _: bool) -> Expression {
-        self.peek_invalid_assignment(false);
-        let str_value = &self.current.literal[2..];
-        if let Ok(value) = i64::from_str_radix(str_value, 16) {
-            Expression::Integer(IntegerLiteral {
-                token: self.current.clone(),
-                value,
-            })
-        } else {
-            let msg = format!(
-                "could not parse '{}' as a hexadecimal integer",
-                self.current.literal
-            );
-            self.push_error(&msg);
-            Expression::Invalid
-        }
+        self.parse_radix(16, "a hexadecimal")
     }
 
     fn parse_binary(&mut self, _: bool) -> Expression {
+        self.parse_radix(2, "a binary")
+    }
+
+    // Parse an integer literal with a two character prefix such as '0x'
+    fn parse_radix(&mut self, radix: u32, kind: &str) -> Expression {
+        // Reporting an invalid assignment moves past the literal
+        let token = self.current.clone();
         self.peek_invalid_assignment(false);
-        let str_value = &self.current.literal[2..];
-        if let Ok(value) = i64::from_str_radix(str_value, 2) {
-            Expression::Integer(IntegerLiteral {
-                token: self.current.clone(),
-                value,
-            })
+        let str_value = token.literal.get(2..).unwrap_or_default();
+        if let Ok(value) = i64::from_str_radix(str_value, radix) {
+            Expression::Integer(IntegerLiteral { token, value })
         } else {
-            let msg = format!(
-                "could not parse '{}' as a binary integer",
-                self.current.literal
-            );
+            let msg = format!("could not parse '{}' as {} integer", token.literal, kind);
             self.push_error(&msg);
             Expression::Invalid
         }
@@ -416,11 +444,17 @@ impl Parser {
 
     fn parse_float(&mut self, _: bool) -> Expression {
         self.peek_invalid_assignment(false);
-        if let Ok(value) = self.current.literal.parse() {
+        let literal = &self.current.literal;
+        let value = literal.parse().ok().or_else(|| parse_duration(literal));
+        if let Some(value) = value.filter(|v: &f64| v.is_finite()) {
             Expression::Float(FloatLiteral {
                 token: self.current.clone(),
                 value,
             })
+        } else if value.is_some() {
+            let msg = format!("float literal {} is out of range", self.current.literal);
+            self.push_error(&msg);
+            Expression::Invalid
         } else {
             let msg = format!("could not parse {} as a float", self.current.literal);
             self.push_error(&msg);
@@ -430,16 +464,10 @@ impl Parser {
 
     fn parse_string(&mut self, _: bool) -> Expression {
         self.peek_invalid_assignment(false);
-        if let Ok(value) = self.current.literal.parse() {
-            Expression::Str(StringLiteral {
-                token: self.current.clone(),
-                value,
-            })
-        } else {
-            let msg = format!("could not parse {} as a string", self.current.literal);
-            self.push_error(&msg);
-            Expression::Invalid
-        }
+        Expression::Str(StringLiteral {
+            token: self.current.clone(),
+            value: self.current.literal.clone(),
+        })
     }
 
     fn parse_char(&mut self, _: bool) -> Expression {
@@ -513,7 +541,7 @@ impl Parser {
     // Override operator precedence using grouped expression
     fn parse_grouped(&mut self, _: bool) -> Expression {
         self.next_token();
-        let expr = self.parse_expression(Precedence::Assignment, false);
+        let expr = self.parse_in_group(true, |p| p.parse_expression(Precedence::Assignment, false));
         if self.expect_peek(&TokenType::RightParen) {
             // check for cases such as '(a) = b'
             self.peek_invalid_assignment(false);
@@ -579,6 +607,11 @@ impl Parser {
         if !self.expect_peek(&TokenType::LeftBrace) {
             return Expression::Invalid;
         }
+        // Newlines end the bodies of the arms as they do in blocks
+        self.parse_in_group(false, |p| p.parse_match_arms(token, condition))
+    }
+
+    fn parse_match_arms(&mut self, token: Token, condition: Expression) -> Expression {
         let mut arms = Vec::new();
         let mut def_arm = false;
         while !self.peek_token_is(&TokenType::RightBrace) && !self.peek_token_is(&TokenType::Eof) {
@@ -725,21 +758,26 @@ impl Parser {
             Expression::Str(expr) => MatchPattern::Str(expr),
             Expression::Range(expr) => MatchPattern::Range(expr),
             Expression::Binary(expr) => {
-                // convert the bitwise or expression to patterns recursively
-                match expr.operator.as_ref() {
+                // convert the bitwise or expression to patterns. The
+                // operands of a chain such as 'a | b | c' nest to the left,
+                // so they are walked down in a loop.
+                let mut rights = Vec::new();
+                let mut left = Expression::Binary(expr);
+                while let Expression::Binary(mut expr) = left {
                     // bitwise OR operator
-                    "|" => {
-                        let mut left_patterns = Self::convert_to_pattern_list(*expr.left)?;
-                        patterns.append(&mut left_patterns);
-                        let mut right_patterns = Self::convert_to_pattern_list(*expr.right)?;
-                        patterns.append(&mut right_patterns);
-                    }
-                    _ => {
+                    if expr.operator != "|" {
                         return Err(format!(
                             "invalid operation in match pattern '{}'",
                             expr.operator
                         ));
                     }
+                    let (next, right) = expr.take_operands();
+                    rights.push(right);
+                    left = next;
+                }
+                patterns.append(&mut Self::convert_to_pattern_list(left)?);
+                for right in rights.into_iter().rev() {
+                    patterns.append(&mut Self::convert_to_pattern_list(right)?);
                 }
                 return Ok(patterns);
             }
@@ -754,14 +792,27 @@ impl Parser {
     pub fn parse_block_statement(&mut self) -> BlockStatement {
         let token = self.current.clone();
         let mut statements = Vec::new();
+        let nesting = self.nesting;
+        if !self.enter_nesting() {
+            self.nesting = nesting;
+            return BlockStatement { token, statements };
+        }
         self.next_token();
 
-        while !self.curr_token_is(&TokenType::RightBrace) && !self.curr_token_is(&TokenType::Eof) {
-            if let Ok(stmt) = self.parse_statement() {
-                statements.push(stmt);
+        self.parse_in_group(false, |p| {
+            while !p.curr_token_is(&TokenType::RightBrace) && !p.curr_token_is(&TokenType::Eof) {
+                // Skip empty statements
+                if p.curr_token_is(&TokenType::Semicolon) {
+                    p.next_token();
+                    continue;
+                }
+                if let Ok(stmt) = p.parse_statement() {
+                    statements.push(stmt);
+                }
+                p.next_token();
             }
-            self.next_token();
-        }
+        });
+        self.nesting = nesting;
         BlockStatement { token, statements }
     }
 
@@ -849,6 +900,10 @@ impl Parser {
     // of expressions. The only difference is the end token that is used to
     // indicate the end of the list. This token type is passed as an argument.
     fn parse_expression_list(&mut self, ttype_end: TokenType) -> Vec<Expression> {
+        self.parse_in_group(true, |p| p.parse_expressions_until(ttype_end))
+    }
+
+    fn parse_expressions_until(&mut self, ttype_end: TokenType) -> Vec<Expression> {
         let mut args = Vec::new();
 
         if self.peek_token_is(&ttype_end) {
@@ -887,7 +942,8 @@ impl Parser {
         let token = self.current.clone();
         // advance to the index token
         self.next_token();
-        let index = self.parse_expression(Precedence::Assignment, false);
+        let index =
+            self.parse_in_group(true, |p| p.parse_expression(Precedence::Assignment, false));
         if !self.expect_peek(&TokenType::RightBracket) {
             return Expression::Invalid;
         }
@@ -907,6 +963,10 @@ impl Parser {
     // Use the "map" keyword to create a hash map. Using a keyword helps
     // disambiguate between a hash map and a block statement.
     fn parse_hash_literal(&mut self, _: bool) -> Expression {
+        self.parse_in_group(true, Self::parse_hash_pairs)
+    }
+
+    fn parse_hash_pairs(&mut self) -> Expression {
         let mut pairs = Vec::new();
         // Consume the 'map' keyword
         self.next_token();
@@ -953,6 +1013,27 @@ impl Parser {
         })
     }
 
+    // Parse 'cond ? a : b'. Anything may come between '?' and ':', while
+    // the expression after ':' extends as far as another ternary would,
+    // so 'a ? b : c ? d : e' is 'a ? b : (c ? d : e)'.
+    fn parse_ternary_expression(&mut self, condition: Expression) -> Expression {
+        let token = self.current.clone();
+        let precedence = self.curr_precedence();
+        self.next_token();
+        let then_expr = self.parse_expression(Precedence::Lowest, false);
+        if !self.expect_peek(&TokenType::Colon) {
+            return Expression::Invalid;
+        }
+        self.next_token();
+        let else_expr = self.parse_expression(precedence, false);
+        Expression::Ternary(TernaryExpr {
+            token,
+            condition: Box::new(condition),
+            then_expr: Box::new(then_expr),
+            else_expr: Box::new(else_expr),
+        })
+    }
+
     fn parse_range_expression(&mut self, left: Expression) -> Expression {
         match self.parse_ranges(left) {
             Ok(expr) => Expression::Range(expr),
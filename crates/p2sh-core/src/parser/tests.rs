@@ -1,6 +1,7 @@
 #![allow(unused_imports)]
 use super::*;
 use crate::code::prop::PacketPropType;
+use crate::object::func::CompiledFunction;
 use std::collections::HashMap;
 
 #[cfg(test)]
@@ -187,6 +188,7 @@ fn test_literal(expression: &Expression, value: Literal) {
 
 // Generic prefix expression test helper that accepts a generic literal (number/string)
 #[cfg(test)]
+#[allow(clippy::explicit_auto_deref)]
 fn test_prefix_expression(expression: &Expression, operator: &str, right: Literal) {
     if let Expression::Unary(expr) = expression {
         if expr.operator != operator {
@@ -203,6 +205,7 @@ fn test_prefix_expression(expression: &Expression, operator: &str, right: Litera
 
 // Generic infix expression test helper that accepts a generic literal (number/string)
 #[cfg(test)]
+#[allow(clippy::explicit_auto_deref)]
 fn test_infix_expression(expression: &Expression, left: Literal, operator: &str, right: Literal) {
     if let Expression::Binary(expr) = expression {
         if expr.operator != operator {
@@ -220,6 +223,11 @@ fn test_infix_expression(expression: &Expression, left: Literal, operator: &str,
 
 // Generic dot expression test helper
 #[cfg(test)]
+#[allow(
+    clippy::borrow_deref_ref,
+    clippy::deref_addrof,
+    clippy::explicit_auto_deref
+)]
 fn test_dot_expression(expression: &Expression, left: Literal, right: Literal) {
     if let Expression::Dot(expr) = expression {
         test_literal(&*expr.left, left);
@@ -231,6 +239,7 @@ fn test_dot_expression(expression: &Expression, left: Literal, right: Literal) {
 
 // Assignment expression
 #[cfg(test)]
+#[allow(clippy::explicit_auto_deref)]
 fn test_assign_expression(expression: &Expression, left: Literal, right: Literal) {
     if let Expression::Assign(expr) = expression {
         test_literal(&*expr.left, left);
@@ -241,6 +250,7 @@ fn test_assign_expression(expression: &Expression, left: Literal, right: Literal
 }
 
 #[test]
+#[allow(clippy::char_lit_as_u8, clippy::needless_borrow)]
 fn test_let_statements() {
     struct TestLet {
         input: &'static str,
@@ -427,6 +437,26 @@ fn test_string_literal_expression() {
     }
 }
 
+// Any string the scanner returns is a string literal, whatever it holds
+#[test]
+fn test_string_literal_expressions_any_text() {
+    let tests = [
+        (r#""";"#, ""),
+        (r#""  ";"#, "  "),
+        (r#""{} {:x}";"#, "{} {:x}"),
+        (r#""10.0.0.1/8";"#, "10.0.0.1/8"),
+        (r#""héllo ✓";"#, "héllo ✓"),
+    ];
+    for (input, expected) in tests {
+        let program = parse_test_program(input, 1);
+        if let Statement::Expr(stmt) = &program.statements[0] {
+            test_string_literal(&stmt.value, expected);
+        } else {
+            panic!("not an expression statement. got={}", program.statements[0]);
+        }
+    }
+}
+
 #[test]
 fn test_parsing_constant_expressions() {
     struct ConstantTest {
@@ -458,6 +488,26 @@ fn test_parsing_constant_expressions() {
             input: "5e1",
             number: Literal::Float(50.),
         },
+        ConstantTest {
+            input: "10s",
+            number: Literal::Float(10.),
+        },
+        ConstantTest {
+            input: "1.5ms",
+            number: Literal::Float(0.0015),
+        },
+        ConstantTest {
+            input: "250us",
+            number: Literal::Float(0.00025),
+        },
+        ConstantTest {
+            input: "2m",
+            number: Literal::Float(120.),
+        },
+        ConstantTest {
+            input: "1h",
+            number: Literal::Float(3600.),
+        },
     ];
 
     for test in prefix_tests {
@@ -476,6 +526,7 @@ fn test_parsing_constant_expressions() {
 }
 
 #[test]
+#[allow(clippy::useless_vec)]
 fn test_parsing_constant_expressions_negative() {
     struct ConstantTest {
         input: &'static str,
@@ -498,6 +549,22 @@ fn test_parsing_constant_expressions_negative() {
             input: "0xFAN",
             errors: vec!["[line 1] could not parse '0xFAN' as a hexadecimal integer"],
         },
+        ConstantTest {
+            input: "10sec",
+            errors: vec!["[line 1] could not parse '10sec' as an integer"],
+        },
+        ConstantTest {
+            input: "1.5d",
+            errors: vec!["[line 1] could not parse 1.5d as a float"],
+        },
+        ConstantTest {
+            input: "1e309",
+            errors: vec!["[line 1] float literal 1e309 is out of range"],
+        },
+        ConstantTest {
+            input: "1 + /* comment\n\n",
+            errors: vec!["[line 1] unterminated block comment"],
+        },
     ];
 
     for (i, test) in tests.iter().enumerate() {
@@ -1022,6 +1089,21 @@ fn test_parsing_operator_precedence() {
             expected: "((a && (b == c)) || d)",
             num_stmts: 1,
         },
+        PrecedenceTest {
+            input: "a ~ b && c !~ d + e",
+            expected: "((a ~ b) && (c !~ (d + e)))",
+            num_stmts: 1,
+        },
+        PrecedenceTest {
+            input: "a in b && !(c in d | e)",
+            expected: "((a in b) && (!(c in (d | e))))",
+            num_stmts: 1,
+        },
+        PrecedenceTest {
+            input: "~a ~ b",
+            expected: "((~a) ~ b)",
+            num_stmts: 1,
+        },
         PrecedenceTest {
             input: "match x { 1..2 | 5..=6 => {} }",
             expected: "match x { (1..2) | (5..=6) | => {  } _ | => { null; }}",
@@ -1673,6 +1755,7 @@ fn test_parsing_assignment_expressions() {
 }
 
 #[test]
+#[allow(clippy::len_zero)]
 fn test_parsing_assignment_expressions_negative() {
     let mut count = 0;
     let error_str = "[line 1] Invalid assignment target";
@@ -1685,6 +1768,9 @@ fn test_parsing_assignment_expressions_negative() {
         "a + b = 1",
         "a * b = 1",
         "(a) = 1",
+        "0x1 = 1",
+        "0b1 = 0x",
+        "0o7 = x < 1",
     ];
 
     for (i, &test_input) in tests.iter().enumerate() {
@@ -1780,6 +1866,19 @@ fn test_loop_with_break_statement() {
     }
 }
 
+#[test]
+fn test_label_without_loop() {
+    for input in ["empty:", "empty: 1", "empty: if x { }"] {
+        let errors = parse_test_program_failures(input);
+        assert_eq!(
+            errors.first().map(String::as_str),
+            Some("[line 1] label 'empty' is not followed by a loop"),
+            "input: {}",
+            input
+        );
+    }
+}
+
 #[test]
 fn test_loop_with_label_statement() {
     let input = "empty: loop { }";
@@ -1957,6 +2056,7 @@ fn test_match_expression() {
 }
 
 #[test]
+#[allow(clippy::useless_vec)]
 fn test_match_expressions_negative() {
     struct MatchTest {
         input: &'static str,
@@ -2093,6 +2193,7 @@ fn test_parsing_dot_expressions() {
 }
 
 #[test]
+#[allow(clippy::useless_vec)]
 fn test_parsing_dot_expressions_negative() {
     struct DotExprTest {
         input: &'static str,
@@ -2154,3 +2255,401 @@ fn test_filter_statement() {
         );
     }
 }
+
+#[test]
+fn test_filter_statement_source() {
+    let input = r#"
+@ tcp.dstport == 53 { x }
+@ ($1).src == "a" &&
+    NP > 10
+@ { y }
+@ end { z }
+@ (x)
+"#;
+    let tests = [
+        ("tcp.dstport == 53", "filter at line 2: tcp.dstport == 53"),
+        (
+            r#"($1).src == "a" && NP > 10"#,
+            r#"filter at line 3: ($1).src == "a" && NP > 10"#,
+        ),
+        ("", "filter at line 5"),
+        ("end", "filter at line 6: end"),
+        ("(x)", "filter at line 7: (x)"),
+    ];
+    let program = parse_test_program(input, tests.len());
+    for (stmt, (source, display)) in program.statements.iter().zip(tests) {
+        let Statement::Filter(stmt) = stmt else {
+            panic!("not a filter statement. got={}", stmt);
+        };
+        assert_eq!(stmt.source, source);
+        let func = CompiledFunction::new_filter(Default::default(), 0, stmt.token.line, source);
+        assert_eq!(func.to_string(), display);
+    }
+}
+
+#[test]
+fn test_filter_statement_interval() {
+    let input = r#"
+@ interval 5 { x }
+@ interval 1.5s { y }
+@ interval > 2 { z }
+"#;
+    let program = parse_test_program(input, 3);
+    let patterns: Vec<_> = program
+        .statements
+        .iter()
+        .map(|stmt| match stmt {
+            Statement::Filter(stmt) => stmt.clone(),
+            stmt => panic!("not a filter statement. got={}", stmt),
+        })
+        .collect();
+    assert!(matches!(patterns[0].pattern, FilterPattern::Interval(s) if s == 5.0));
+    assert_eq!(patterns[0].source, "interval 5");
+    assert!(matches!(patterns[1].pattern, FilterPattern::Interval(s) if s == 1.5));
+    assert!(patterns[1].to_string().starts_with("@interval 1.5s {"));
+    // 'interval' is an ordinary name unless followed by a duration
+    assert!(matches!(patterns[2].pattern, FilterPattern::Expr(_)));
+
+    let tests = [
+        ("@ interval 0 { x }", "[line 1] invalid interval '0'"),
+        ("@ interval 5", "[line 1] expected '{' after the interval"),
+    ];
+    for (input, expected) in tests {
+        let errors = parse_test_program_failures(input);
+        assert_eq!(errors.first().map(String::as_str), Some(expected));
+    }
+}
+
+#[test]
+fn test_statement_termination() {
+    struct TerminationTest {
+        input: &'static str,
+        num_stmts: usize,
+        expected: &'static str,
+    }
+    let tests = vec![
+        // Semicolons are optional
+        TerminationTest {
+            input: "let a = 1; let b = 2;",
+            num_stmts: 2,
+            expected: "let a = 1;let b = 2;",
+        },
+        TerminationTest {
+            input: "let a = 1 let b = 2",
+            num_stmts: 2,
+            expected: "let a = 1;let b = 2;",
+        },
+        TerminationTest {
+            input: "a\nb\nc",
+            num_stmts: 3,
+            expected: "abc",
+        },
+        TerminationTest {
+            input: "a;;b",
+            num_stmts: 2,
+            expected: "ab",
+        },
+        // Operators that can only continue an expression may be on either line
+        TerminationTest {
+            input: "a +\nb",
+            num_stmts: 1,
+            expected: "(a + b)",
+        },
+        TerminationTest {
+            input: "a\n+ b",
+            num_stmts: 1,
+            expected: "(a + b)",
+        },
+        TerminationTest {
+            input: "a\n&& b\n|| c",
+            num_stmts: 1,
+            expected: "((a && b) || c)",
+        },
+        TerminationTest {
+            input: "a -\nb",
+            num_stmts: 1,
+            expected: "(a - b)",
+        },
+        // A newline followed by '(', '[' or '-' terminates the statement
+        TerminationTest {
+            input: "a\n-b",
+            num_stmts: 2,
+            expected: "a(-b)",
+        },
+        TerminationTest {
+            input: "puts(a)\n(b)",
+            num_stmts: 2,
+            expected: "puts(a)b",
+        },
+        TerminationTest {
+            input: "let a = b\n[1, 2]",
+            num_stmts: 2,
+            expected: "let a = b;[1, 2]",
+        },
+        TerminationTest {
+            input: "a(\nb,\n-c\n)[\n0\n]",
+            num_stmts: 1,
+            expected: "(a(b, (-c))[0])",
+        },
+        TerminationTest {
+            input: "return\n-1",
+            num_stmts: 1,
+            expected: "return (-1);",
+        },
+        // No statement starts within parentheses, brackets or a map literal
+        TerminationTest {
+            input: "(a\n - b)",
+            num_stmts: 1,
+            expected: "(a - b)",
+        },
+        TerminationTest {
+            input: "[a\n- b, c\n(d)]",
+            num_stmts: 1,
+            expected: "[(a - b), c(d)]",
+        },
+        TerminationTest {
+            input: "x[a\n- 1]",
+            num_stmts: 1,
+            expected: "(x[(a - 1)])",
+        },
+        TerminationTest {
+            input: "map {a: b\n[0]}",
+            num_stmts: 1,
+            expected: "{a: (b[0])}",
+        },
+        // but it does again within a block, and after the closing one
+        TerminationTest {
+            input: "f(fn() { a\n-b })",
+            num_stmts: 1,
+            expected: "f(fn () a; (-b);)",
+        },
+        TerminationTest {
+            input: "let x = (a)\n-1",
+            num_stmts: 2,
+            expected: "let x = a;(-1)",
+        },
+    ];
+
+    for (i, test) in tests.iter().enumerate() {
+        let program = parse_test_program(test.input, test.num_stmts);
+        assert_eq!(program.to_string(), test.expected, "test {}", i);
+    }
+}
+
+#[test]
+fn test_import_statement() {
+    let mut parser = Parser::new(Scanner::new(r#"import "std/stats"; import "lib.p2""#));
+    let program = parser.parse_program();
+    check_parse_errors(&parser);
+    assert_eq!(program.statements.len(), 2);
+    assert_eq!(
+        program.to_string(),
+        r#"import "std/stats";import "lib.p2";"#
+    );
+
+    let mut parser = Parser::new(Scanner::new("import stats;"));
+    parser.parse_program();
+    assert_eq!(
+        parser.parse_errors()[0],
+        "[line 1] expected token STRING, got IDENTIFIER instead"
+    );
+}
+
+#[test]
+fn test_for_statement() {
+    let tests = [
+        ("for x in a { x; }", "for x in a { x;  }"),
+        ("for k, v in map {} { }", "for k, v in {} {  }"),
+        ("for i in 0..len(a) { }", "for i in (0..len(a)) {  }"),
+        ("all: for i in n..=m + 1 { }", "for i in (n..=(m + 1)) {  }"),
+    ];
+    for (input, expected) in tests {
+        let program = parse_test_program(input, 1);
+        assert_eq!(program.statements[0].to_string(), expected);
+    }
+    let program = parse_test_program("outer: for x in a { }", 1);
+    if let Statement::For(stmt) = &program.statements[0] {
+        assert_eq!(stmt.label.as_ref().unwrap().literal, "outer");
+        assert_eq!(stmt.vars.len(), 1);
+    } else {
+        panic!("not a for statement");
+    }
+
+    let mut parser = Parser::new(Scanner::new("for x, y, z in a { }"));
+    parser.parse_program();
+    assert_eq!(
+        parser.parse_errors()[0],
+        "[line 1] expected token IN, got , instead"
+    );
+}
+
+#[test]
+fn test_try_statement() {
+    let program = parse_test_program("try { x / y; } catch e { puts(e); }", 1);
+    assert_eq!(
+        program.statements[0].to_string(),
+        "try { (x / y);  } catch e { puts(e);  }"
+    );
+    if let Statement::Try(stmt) = &program.statements[0] {
+        assert_eq!(stmt.var.literal, "e");
+        assert_eq!(stmt.body.statements.len(), 1);
+        assert_eq!(stmt.handler.statements.len(), 1);
+    } else {
+        panic!("not a try statement");
+    }
+
+    let tests = [
+        ("try x", "[line 1] expected token {, got IDENTIFIER instead"),
+        ("try { }", "[line 1] expected token CATCH, got EOF instead"),
+        (
+            "try { } catch { }",
+            "[line 1] expected token IDENTIFIER, got { instead",
+        ),
+        (
+            "try { } catch map { }",
+            "[line 1] 'map' is a reserved word and can't be used as a variable name",
+        ),
+    ];
+    for (input, expected) in tests {
+        let errors = parse_test_program_failures(input);
+        assert_eq!(
+            errors.first().map(String::as_str),
+            Some(expected),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_incr_statement() {
+    let program = parse_test_program("x++; m[k]--\ny--;", 3);
+    let expected = ["x++", "(m[k])--", "y--"];
+    for (stmt, expected) in program.statements.iter().zip(expected) {
+        assert!(matches!(stmt, Statement::Incr(_)), "{}", expected);
+        assert_eq!(stmt.to_string(), expected);
+    }
+    // Elsewhere the operators are two minus signs
+    let program = parse_test_program("a--1; --a", 2);
+    assert_eq!(program.statements[0].to_string(), "(a - (-1))");
+    assert_eq!(program.statements[1].to_string(), "(-(-a))");
+
+    let tests = [
+        ("1++", "[line 1] Invalid operand of '++'"),
+        ("f()--", "[line 1] Invalid operand of '--'"),
+        ("a + b++", "[line 1] Invalid operand of '++'"),
+    ];
+    for (input, expected) in tests {
+        let errors = parse_test_program_failures(input);
+        assert_eq!(
+            errors.first().map(String::as_str),
+            Some(expected),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_ternary_expression() {
+    let tests = [
+        ("a ? b : c", "(a ? b : c)"),
+        ("x > 0 ? 1 : -1", "((x > 0) ? 1 : (-1))"),
+        ("a || b ? c + 1 : d * 2", "((a || b) ? (c + 1) : (d * 2))"),
+        ("a ? b : c ? d : e", "(a ? b : (c ? d : e))"),
+        ("a ? b ? c : d : e", "(a ? (b ? c : d) : e)"),
+        ("x = a ? b : c", "(x = (a ? b : c))"),
+        ("f(a ? 1 : 2, 3)", "f((a ? 1 : 2), 3)"),
+        ("[a ? 1 : 2]", "[(a ? 1 : 2)]"),
+        ("(a ? b : c)[0]", "((a ? b : c)[0])"),
+    ];
+    for (input, expected) in tests {
+        let program = parse_test_program(input, 1);
+        assert_eq!(program.statements[0].to_string(), expected, "{}", input);
+    }
+    let errors = parse_test_program_failures("a ? b");
+    assert_eq!(
+        errors.first().map(String::as_str),
+        Some("[line 1] expected token :, got EOF instead")
+    );
+}
+
+#[test]
+fn test_reserved_words_as_names() {
+    let tests = [
+        (
+            "let map = 1;",
+            "[line 1] 'map' is a reserved word and can't be used as a variable name",
+        ),
+        (
+            "let x = 1;\nfn if(a) { a }",
+            "[line 2] 'if' is a reserved word and can't be used as a function name",
+        ),
+        (
+            "fn 1",
+            "[line 1] expected a function name or '(' after 'fn'",
+        ),
+    ];
+    for (input, expected) in tests {
+        let errors = parse_test_program_failures(input);
+        assert_eq!(errors, vec![expected.to_string()], "{}", input);
+    }
+}
+
+#[test]
+fn test_nesting_limit() {
+    use super::MAX_NESTING;
+
+    // Nesting up to the limit is allowed
+    let n = MAX_NESTING - 1;
+    let inputs = [
+        format!("{}1{}", "(".repeat(n), ")".repeat(n)),
+        format!("{}1", "-".repeat(n - 1)),
+        format!("{}{}", "{".repeat(MAX_NESTING), "}".repeat(MAX_NESTING)),
+    ];
+    for input in &inputs {
+        parse_test_program(input, 1);
+    }
+
+    // Chains of binary operators are not nested however long they are
+    let inputs = [
+        vec!["1"; 100_000].join(" + "),
+        (0..1000)
+            .map(|p| format!("p == {}", p))
+            .collect::<Vec<_>>()
+            .join(" || "),
+        format!("{}1{}", "(".repeat(n - 1), " + 1)".repeat(n - 1)),
+    ];
+    for input in &inputs {
+        parse_test_program(input, 1);
+    }
+
+    // A single error is reported for deeper nesting
+    let inputs = [
+        format!("{}1{}", "(".repeat(MAX_NESTING), ")".repeat(MAX_NESTING)),
+        format!("{}1", "!".repeat(100_000)),
+        format!(
+            "{}1{}",
+            "(".repeat(MAX_NESTING),
+            " + 1)".repeat(MAX_NESTING)
+        ),
+        format!("a{}", "[0]".repeat(100_000)),
+        format!("let a = 1;\n{}{}", "[".repeat(100_000), "]".repeat(100_000)),
+        format!("{}{}", "{".repeat(100_000), "}".repeat(100_000)),
+        format!("{}{}", "if a { ".repeat(200), " }".repeat(200)),
+    ];
+    for input in &inputs {
+        let errors = parse_test_program_failures(input);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(
+            errors[0].ends_with("nesting is deeper than the limit of 256"),
+            "{}",
+            errors[0]
+        );
+    }
+    let errors = parse_test_program_failures(&inputs[4]);
+    assert_eq!(
+        errors[0],
+        "[line 2] nesting is deeper than the limit of 256"
+    );
+}
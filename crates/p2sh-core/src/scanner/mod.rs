@@ -21,10 +21,15 @@ lazy_static! {
         m.insert("map".into(), TokenType::Map);
         m.insert("loop".into(), TokenType::Loop);
         m.insert("while".into(), TokenType::While);
+        m.insert("for".into(), TokenType::For);
+        m.insert("in".into(), TokenType::In);
         m.insert("break".into(), TokenType::Break);
         m.insert("continue".into(), TokenType::Continue);
         m.insert("match".into(), TokenType::Match);
         m.insert("struct".into(), TokenType::Struct);
+        m.insert("import".into(), TokenType::Import);
+        m.insert("try".into(), TokenType::Try);
+        m.insert("catch".into(), TokenType::Catch);
         m.insert("stdin".into(), TokenType::Stdin);
         m.insert("stdout".into(), TokenType::Stdout);
         m.insert("stderr".into(), TokenType::Stderr);
@@ -40,6 +45,9 @@ pub struct Scanner {
     read_position: usize,
     ch: char,
     line: usize,
+    ascii_ident: bool,
+    // Position in the input where the last token starts
+    token_start: usize,
 }
 
 impl Scanner {
@@ -50,11 +58,19 @@ impl Scanner {
             read_position: 0,
             ch: '\0',
             line: 1,
+            ascii_ident: false,
+            token_start: 0,
         };
         scanner.read_char();
         scanner
     }
 
+    /// Only allow ASCII characters in identifiers so that identifiers
+    /// with lookalike Unicode characters are rejected
+    pub fn set_ascii_ident(&mut self, ascii_ident: bool) {
+        self.ascii_ident = ascii_ident;
+    }
+
     /// Read the next character and advance the position in the input
     /// position points to the position where a character was last read from.
     /// read_position always points to the next position.
@@ -83,19 +99,29 @@ impl Scanner {
 
     pub fn next_token(&mut self) -> Token {
         self.skip_whitespace();
-        self.skip_comments();
+        if let Some(token) = self.skip_comments() {
+            return token;
+        }
 
+        self.token_start = self.position;
         let token = match self.ch {
             '\0' => self.make_token(TokenType::Eof, ""),
             ';' => self.make_token_ch(TokenType::Semicolon),
             ',' => self.make_token_ch(TokenType::Comma),
             ':' => self.make_token_ch(TokenType::Colon),
+            '?' => self.make_token_ch(TokenType::Question),
             '(' => self.make_token_ch(TokenType::LeftParen),
             ')' => self.make_token_ch(TokenType::RightParen),
             '{' => self.make_token_ch(TokenType::LeftBrace),
             '}' => self.make_token_ch(TokenType::RightBrace),
             '[' => self.make_token_ch(TokenType::LeftBracket),
             ']' => self.make_token_ch(TokenType::RightBracket),
+            '+' if self.ends_statement_twin() => {
+                self.make_token_twin(TokenType::Plus, &[('+', TokenType::PlusPlus)])
+            }
+            '-' if self.ends_statement_twin() => {
+                self.make_token_twin(TokenType::Minus, &[('-', TokenType::MinusMinus)])
+            }
             '+' => self.make_token_ch(TokenType::Plus),
             '-' => self.make_token_ch(TokenType::Minus),
             '*' => self.make_token_ch(TokenType::Asterisk),
@@ -105,7 +131,10 @@ impl Scanner {
             '~' => self.make_token_ch(TokenType::BitwiseNot),
             '$' => self.make_token_ch(TokenType::Dollar),
             '@' => self.make_token_ch(TokenType::Filter),
-            '!' => self.make_token_twin(TokenType::Bang, &[('=', TokenType::BangEqual)]),
+            '!' => self.make_token_twin(
+                TokenType::Bang,
+                &[('=', TokenType::BangEqual), ('~', TokenType::BangTilde)],
+            ),
             '&' => self.make_token_twin(TokenType::BitwiseAnd, &[('&', TokenType::LogicalAnd)]),
             '|' => self.make_token_twin(TokenType::BitwiseOr, &[('|', TokenType::LogicalOr)]),
             '=' => self.make_token_twin(
@@ -144,6 +173,17 @@ impl Scanner {
         token
     }
 
+    /// Start and end positions in the input of the last token returned
+    pub fn span(&self) -> (usize, usize) {
+        (self.token_start, self.position.max(self.token_start))
+    }
+
+    /// Source text between the given positions in the input
+    pub fn source(&self, start: usize, end: usize) -> String {
+        let end = end.min(self.input.len());
+        self.input[start.min(end)..end].iter().collect()
+    }
+
     fn make_token(&self, ttype: TokenType, literal: &str) -> Token {
         Token::new(ttype, literal, self.line)
     }
@@ -157,6 +197,21 @@ impl Scanner {
     // If the next character in the input matches the characters in 'next'
     // then make a token with the two characters (single, next[n].0), otherwise
     // make a token of type 'single' with the first character.
+    // '++' and '--' are single tokens only where they end a statement as
+    // in 'x++', i.e. when they are followed by a semicolon, a closing
+    // brace, a comment or the end of the line. Elsewhere, such as in
+    // '--a' and 'a--1', they are two operators as before.
+    fn ends_statement_twin(&self) -> bool {
+        if self.input.get(self.read_position) != Some(&self.ch) {
+            return false;
+        }
+        let rest = &self.input[self.read_position + 1..];
+        match rest.iter().find(|c| **c != ' ' && **c != '\t') {
+            Some(c) => matches!(c, ';' | '}' | '/' | '\n' | '\r'),
+            None => true,
+        }
+    }
+
     fn make_token_twin(&mut self, single: TokenType, next: &[(char, TokenType)]) -> Token {
         let curr = self.ch;
         if self.peek_char() == next[0].0 {
@@ -176,6 +231,9 @@ impl Scanner {
             self.read_char();
         }
         let identifier: String = self.input[position..self.position].iter().collect();
+        if self.ascii_ident && !identifier.is_ascii() {
+            return self.make_token(TokenType::Illegal, &identifier);
+        }
         // Check for a byte literal
         if self.ch == '\'' && identifier == "b" {
             self.read_char();
@@ -233,17 +291,13 @@ impl Scanner {
         // Read digits (decimal, octal, hexadecimal, or binary) but stop if the
         // is_hex is false and if encountered hexadecimal digits so we get
         // a chance to handle the exponent (scientific notation)
-        while self.ch.is_ascii_digit() || is_hex && self.ch.is_ascii_hexdigit() {
-            self.read_char();
-        }
+        self.read_digits(is_hex);
 
         // Check for a decimal point
         if self.ch == '.' && self.peek_char() != '.' {
             is_float = true;
             self.read_char(); // Consume the '.'
-            while self.ch.is_ascii_digit() {
-                self.read_char();
-            }
+            self.read_digits(false);
         }
 
         // Check for an exponent (scientific notation)
@@ -260,18 +314,29 @@ impl Scanner {
             if self.ch == '-' || self.ch == '+' {
                 self.read_char(); // Consume '-' or '+'
             }
-            while self.ch.is_ascii_digit() {
-                self.read_char();
+            // A sign without an exponent is illegal
+            if !self.ch.is_ascii_digit() {
+                let number: String = self.input[position..self.position].iter().collect();
+                return self.make_token(TokenType::Illegal, &number);
             }
+            self.read_digits(false);
         }
 
         // Read remaining digits if any so we can handle
         // bad cases such as '0o12FF' and '0b10FF', '0xFFX' etc.
+        let suffix = self.position;
         while Self::is_identifier_first(self.ch) {
             self.read_char();
         }
 
         let number: String = self.input[position..self.position].iter().collect();
+        // Separators that are not between two digits are illegal,
+        // e.g. '1_', '1__000', '0x_FF' and '1_.5'
+        if self.input[suffix..self.position].contains(&'_') {
+            return self.make_token(TokenType::Illegal, &number);
+        }
+        // Remove the digit separators, e.g. '1_000_000' is '1000000'
+        let number = number.replace('_', "");
         let token_type = if is_float {
             TokenType::Float
         } else if is_hex {
@@ -287,6 +352,18 @@ impl Scanner {
         self.make_token(token_type, &number)
     }
 
+    // Read digits allowing a single underscore as a separator between two
+    // digits. Include hexadecimal digits if 'is_hex' is set.
+    fn read_digits(&mut self, is_hex: bool) {
+        let is_digit = |ch: char| ch.is_ascii_digit() || is_hex && ch.is_ascii_hexdigit();
+        while is_digit(self.ch) {
+            self.read_char();
+            if self.ch == '_' && is_digit(self.peek_char()) {
+                self.read_char(); // Consume the separator
+            }
+        }
+    }
+
     fn read_string(&mut self) -> Token {
         // move past the opening quotes (") character
         let position = self.position + 1;
@@ -309,8 +386,10 @@ impl Scanner {
         let position = self.position;
         // move past the opening quote (') character
         self.read_char();
-        let the_char = self.input[self.position].to_string();
-        self.read_char();
+        let the_char = self.ch.to_string();
+        if self.ch != '\0' {
+            self.read_char();
+        }
         if self.ch == '\'' {
             return self.make_token(TokenType::Char, &the_char);
         }
@@ -353,6 +432,12 @@ impl Scanner {
         ch.is_alphanumeric() || ch == '_'
     }
 
+    /// Check if a word is reserved as a keyword and so can't be used as
+    /// the name of a variable or a function.
+    pub fn is_keyword(word: &str) -> bool {
+        word != "_" && KEYWORDS.contains_key(word)
+    }
+
     fn lookup_identifier(identifier: String) -> TokenType {
         match KEYWORDS.get(&identifier) {
             Some(kw_ttype) => *kw_ttype,
@@ -378,7 +463,9 @@ impl Scanner {
     }
 
     // skip single line comments
-    fn skip_comments(&mut self) {
+    // Skip line and block comments. Return an illegal token
+    // if a block comment is not terminated.
+    fn skip_comments(&mut self) -> Option<Token> {
         loop {
             if self.ch == '#' || self.ch == '/' && self.peek_char() == '/' {
                 loop {
@@ -388,11 +475,44 @@ impl Scanner {
                     }
                 }
                 self.skip_whitespace();
+            } else if self.ch == '/' && self.peek_char() == '*' {
+                if let Some(token) = self.skip_block_comment() {
+                    return Some(token);
+                }
+                self.skip_whitespace();
             } else {
-                break;
+                return None;
             }
         }
     }
+
+    // Skip a block comment that may contain nested block comments.
+    // The illegal token returned for an unterminated comment refers
+    // to the line where the comment starts.
+    fn skip_block_comment(&mut self) -> Option<Token> {
+        let line = self.line;
+        let mut depth = 0;
+        loop {
+            match (self.ch, self.peek_char()) {
+                ('/', '*') => {
+                    depth += 1;
+                    self.read_char();
+                }
+                ('*', '/') => {
+                    depth -= 1;
+                    self.read_char();
+                    if depth == 0 {
+                        self.read_char();
+                        return None;
+                    }
+                }
+                ('\n', _) => self.line += 1,
+                ('\0', _) => return Some(Token::new(TokenType::Illegal, "/*", line)),
+                _ => {}
+            }
+            self.read_char();
+        }
+    }
 }
 
 impl Iterator for Scanner {
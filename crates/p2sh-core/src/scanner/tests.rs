@@ -121,13 +121,13 @@ fn test_tokens_let() {
 #[test]
 fn test_tokens_arithmetic() {
     let input = r#"
-        !-/*5%2;
+        !-/ *5%2;
         5. + .1;
         10.0 - 30e1;
         40e+1 * 50e-1;
     "#;
     let tests = vec![
-        // !-/*5%2;
+        // !-/ *5%2;
         ExpectedToken(TokenType::Bang, "!"),
         ExpectedToken(TokenType::Minus, "-"),
         ExpectedToken(TokenType::Slash, "/"),
@@ -272,6 +272,7 @@ fn test_tokens_conditionals() {
             return false;
         }
         match x { 0 => 1, _ => 0, }
+        x?1:0
     "#;
     let tests = vec![
         // if (5 < 10) { return true; } else { return false; }
@@ -305,6 +306,12 @@ fn test_tokens_conditionals() {
         ExpectedToken(TokenType::Decimal, "0"),
         ExpectedToken(TokenType::Comma, ","),
         ExpectedToken(TokenType::RightBrace, "}"),
+        // x?1:0
+        ExpectedToken(TokenType::Identifier, "x"),
+        ExpectedToken(TokenType::Question, "?"),
+        ExpectedToken(TokenType::Decimal, "1"),
+        ExpectedToken(TokenType::Colon, ":"),
+        ExpectedToken(TokenType::Decimal, "0"),
         // EOF
         ExpectedToken(TokenType::Eof, ""),
     ];
@@ -374,6 +381,55 @@ fn test_tokens_loops() {
     run_scanner_tests(input, tests);
 }
 
+#[test]
+fn test_tokens_increment() {
+    let input = r#"
+        x++; y--
+        a--1 --a
+    "#;
+    let tests = vec![
+        // x++; y--
+        ExpectedToken(TokenType::Identifier, "x"),
+        ExpectedToken(TokenType::PlusPlus, "++"),
+        ExpectedToken(TokenType::Semicolon, ";"),
+        ExpectedToken(TokenType::Identifier, "y"),
+        ExpectedToken(TokenType::MinusMinus, "--"),
+        // a--1 --a : not at the end of a statement
+        ExpectedToken(TokenType::Identifier, "a"),
+        ExpectedToken(TokenType::Minus, "-"),
+        ExpectedToken(TokenType::Minus, "-"),
+        ExpectedToken(TokenType::Decimal, "1"),
+        ExpectedToken(TokenType::Minus, "-"),
+        ExpectedToken(TokenType::Minus, "-"),
+        ExpectedToken(TokenType::Identifier, "a"),
+        // EOF
+        ExpectedToken(TokenType::Eof, ""),
+    ];
+    run_scanner_tests(input, tests);
+}
+
+#[test]
+fn test_tokens_try_catch() {
+    let input = r#"
+        try { x; } catch e { }
+    "#;
+    let tests = vec![
+        // try { x; } catch e { }
+        ExpectedToken(TokenType::Try, "try"),
+        ExpectedToken(TokenType::LeftBrace, "{"),
+        ExpectedToken(TokenType::Identifier, "x"),
+        ExpectedToken(TokenType::Semicolon, ";"),
+        ExpectedToken(TokenType::RightBrace, "}"),
+        ExpectedToken(TokenType::Catch, "catch"),
+        ExpectedToken(TokenType::Identifier, "e"),
+        ExpectedToken(TokenType::LeftBrace, "{"),
+        ExpectedToken(TokenType::RightBrace, "}"),
+        // EOF
+        ExpectedToken(TokenType::Eof, ""),
+    ];
+    run_scanner_tests(input, tests);
+}
+
 #[test]
 fn test_tokens_struct() {
     let input = r#"
@@ -516,3 +572,123 @@ fn test_scanner_iterator() {
         assert_eq!(tt.literal, exp.1);
     }
 }
+
+#[test]
+fn test_tokens_ascii_identifiers() {
+    let input = "let café = 1; let caf = 2;";
+    let tests = vec![
+        ExpectedToken(TokenType::Let, "let"),
+        ExpectedToken(TokenType::Identifier, "café"),
+        ExpectedToken(TokenType::Assign, "="),
+    ];
+    run_scanner_tests(input, tests);
+
+    // Identifiers with non-ASCII characters are illegal in the strict mode
+    let mut scanner = Scanner::new(input);
+    scanner.set_ascii_ident(true);
+    let tests = [
+        ExpectedToken(TokenType::Let, "let"),
+        ExpectedToken(TokenType::Illegal, "café"),
+        ExpectedToken(TokenType::Assign, "="),
+        ExpectedToken(TokenType::Decimal, "1"),
+        ExpectedToken(TokenType::Semicolon, ";"),
+        ExpectedToken(TokenType::Let, "let"),
+        ExpectedToken(TokenType::Identifier, "caf"),
+    ];
+    for (n, tt) in tests.iter().enumerate() {
+        let token = scanner.next_token();
+        assert_eq!(token.ttype, tt.0, "tests[{}] - tokentype wrong", n);
+        assert_eq!(token.literal, tt.1, "tests[{}] - literal wrong", n);
+    }
+}
+
+#[test]
+fn test_tokens_number_forms() {
+    let input = r#"
+        .5e-3 1e+308 1E-5 1.
+        1_000_000 0xFF_FF 0b1010_1010 0o7_7 1_000.000_1 1e1_0
+        1_ 1__0 0x_FF 1_.5 1e+ 1e
+    "#;
+    let tests = vec![
+        // Leading dot and exponents
+        ExpectedToken(TokenType::Float, ".5e-3"),
+        ExpectedToken(TokenType::Float, "1e+308"),
+        ExpectedToken(TokenType::Float, "1E-5"),
+        ExpectedToken(TokenType::Float, "1."),
+        // Digit separators are removed
+        ExpectedToken(TokenType::Decimal, "1000000"),
+        ExpectedToken(TokenType::Hexadecimal, "0xFFFF"),
+        ExpectedToken(TokenType::Binary, "0b10101010"),
+        ExpectedToken(TokenType::Octal, "0o77"),
+        ExpectedToken(TokenType::Float, "1000.0001"),
+        ExpectedToken(TokenType::Float, "1e10"),
+        // Separators must be between two digits
+        ExpectedToken(TokenType::Illegal, "1_"),
+        ExpectedToken(TokenType::Illegal, "1__"),
+        ExpectedToken(TokenType::Decimal, "0"),
+        ExpectedToken(TokenType::Illegal, "0x_FF"),
+        ExpectedToken(TokenType::Illegal, "1_"),
+        ExpectedToken(TokenType::Float, ".5"),
+        // Exponents must have digits
+        ExpectedToken(TokenType::Illegal, "1e+"),
+        ExpectedToken(TokenType::Illegal, "1e"),
+        ExpectedToken(TokenType::Eof, ""),
+    ];
+    run_scanner_tests(input, tests);
+}
+
+#[test]
+fn test_tokens_comments() {
+    let input = r#"
+        # line comment
+        let a = 1; // line comment
+        /* block comment */ let b
+        /* block comment spanning
+           multiple lines /* with a nested
+           block comment */ @ { } */
+        = a /**/ / 2;
+        /* unterminated /* nested */
+    "#;
+    let tests = vec![
+        ExpectedToken(TokenType::Let, "let"),
+        ExpectedToken(TokenType::Identifier, "a"),
+        ExpectedToken(TokenType::Assign, "="),
+        ExpectedToken(TokenType::Decimal, "1"),
+        ExpectedToken(TokenType::Semicolon, ";"),
+        ExpectedToken(TokenType::Let, "let"),
+        ExpectedToken(TokenType::Identifier, "b"),
+        ExpectedToken(TokenType::Assign, "="),
+        ExpectedToken(TokenType::Identifier, "a"),
+        ExpectedToken(TokenType::Slash, "/"),
+        ExpectedToken(TokenType::Decimal, "2"),
+        ExpectedToken(TokenType::Semicolon, ";"),
+        ExpectedToken(TokenType::Illegal, "/*"),
+        ExpectedToken(TokenType::Eof, ""),
+    ];
+    run_scanner_tests(input, tests);
+
+    // Lines within block comments are counted
+    let mut scanner = Scanner::new("/* one\ntwo */\n/* three\n */ x /* four");
+    let token = scanner.next_token();
+    assert_eq!(token.literal, "x");
+    assert_eq!(token.line, 4);
+    let token = scanner.next_token();
+    assert_eq!(token.ttype, TokenType::Illegal);
+    assert_eq!(token.line, 4);
+}
+
+#[test]
+fn test_tokens_char_at_end() {
+    // A quote at the end of the input is an unterminated char
+    let tests = vec![
+        ExpectedToken(TokenType::Identifier, "x"),
+        ExpectedToken(TokenType::Illegal, "'"),
+        ExpectedToken(TokenType::Eof, ""),
+    ];
+    run_scanner_tests("x '", tests);
+    let tests = vec![
+        ExpectedToken(TokenType::Illegal, "'a"),
+        ExpectedToken(TokenType::Eof, ""),
+    ];
+    run_scanner_tests("'a", tests);
+}
@@ -58,6 +58,8 @@ pub enum TokenType {
     Assign,
     Plus,
     Minus,
+    PlusPlus,
+    MinusMinus,
     Asterisk,
     Slash,
     Modulo,
@@ -70,6 +72,7 @@ pub enum TokenType {
     GreaterEqual,
     Equal,
     BangEqual,
+    BangTilde,
     MatchArm,
     // Bitwise
     BitwiseAnd,
@@ -81,6 +84,7 @@ pub enum TokenType {
     // Delimiters
     Comma,
     Colon,
+    Question,
     Semicolon,
     LeftParen,
     RightParen,
@@ -101,6 +105,8 @@ pub enum TokenType {
     Map,
     Loop,
     While,
+    For,
+    In,
     Break,
     Continue,
     RangeEx,
@@ -110,6 +116,9 @@ pub enum TokenType {
     Filter,
     // Struct
     Struct,
+    Import,
+    Try,
+    Catch,
     Dot,
     // Packet tokens
     Src,
@@ -144,6 +153,8 @@ impl From<TokenType> for &'static str {
             TokenType::Assign => "=",
             TokenType::Plus => "+",
             TokenType::Minus => "-",
+            TokenType::PlusPlus => "++",
+            TokenType::MinusMinus => "--",
             TokenType::Asterisk => "*",
             TokenType::Slash => "/",
             TokenType::Modulo => "%",
@@ -156,6 +167,7 @@ impl From<TokenType> for &'static str {
             TokenType::GreaterEqual => ">=",
             TokenType::Equal => "==",
             TokenType::BangEqual => "!=",
+            TokenType::BangTilde => "!~",
             TokenType::MatchArm => "=>",
             TokenType::BitwiseAnd => "&",
             TokenType::BitwiseOr => "|",
@@ -165,6 +177,7 @@ impl From<TokenType> for &'static str {
             TokenType::RightShift => "<<",
             TokenType::Comma => ",",
             TokenType::Colon => ":",
+            TokenType::Question => "?",
             TokenType::Semicolon => ";",
             TokenType::LeftParen => "(",
             TokenType::RightParen => ")",
@@ -183,6 +196,8 @@ impl From<TokenType> for &'static str {
             TokenType::Map => "MAP",
             TokenType::Loop => "LOOP",
             TokenType::While => "WHILE",
+            TokenType::For => "FOR",
+            TokenType::In => "IN",
             TokenType::Break => "BREAK",
             TokenType::Continue => "CONTINUE",
             TokenType::RangeEx => "..",
@@ -190,6 +205,9 @@ impl From<TokenType> for &'static str {
             TokenType::Match => "MATCH",
             TokenType::Filter => "FILTER",
             TokenType::Struct => "STRUCT",
+            TokenType::Import => "IMPORT",
+            TokenType::Try => "TRY",
+            TokenType::Catch => "CATCH",
             TokenType::Dot => ".",
             TokenType::Src => "SRC",
             TokenType::Dest => "DEST",
@@ -0,0 +1,25 @@
+//! The standard library of p2sh modules.
+//!
+//! The modules are p2sh scripts embedded in the binary, which scripts load
+//! using 'import "std/<name>"' without having to know where they are
+//! installed.
+
+/// Prefix of the paths of the modules in the standard library
+pub const STD_PREFIX: &str = "std/";
+
+/// Paths and sources of the modules in the standard library
+pub const MODULES: &[(&str, &str)] = &[
+    ("std/math", include_str!("math.p2")),
+    ("std/net", include_str!("net.p2")),
+    #[cfg(feature = "pcap")]
+    ("std/stats", include_str!("stats.p2")),
+    ("std/strings", include_str!("strings.p2")),
+];
+
+/// Get the source of a module in the standard library
+pub fn module(path: &str) -> Option<&'static str> {
+    MODULES
+        .iter()
+        .find(|(name, _)| *name == path)
+        .map(|(_, source)| *source)
+}
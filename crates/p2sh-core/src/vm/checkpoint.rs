@@ -0,0 +1,140 @@
+//! Checkpoints of the global variables of a script.
+//!
+//! A long running filter, such as one on a live capture, periodically
+//! saves the globals holding data values so that a restarted run can
+//! resume with the statistics accumulated so far. Globals holding
+//! functions, file handles or packets are not saved and keep the values
+//! set by the script on restart.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::object::serialize::{decode, encode};
+use crate::object::Object;
+
+const MAGIC: &[u8; 4] = b"P2CK";
+const VERSION: u8 = 1;
+// Magic, version and the script id
+const HEADER_SIZE: usize = 4 + 1 + 8;
+
+/// Periodic checkpoints of the globals to a file
+#[derive(Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+    interval: Option<Duration>,
+    script_id: u64,
+    last: Instant,
+}
+
+impl Checkpoint {
+    /// Checkpoints of the globals of the script 'source', saved at the
+    /// interval if given
+    pub fn new(path: &Path, interval: Option<Duration>, source: &str) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            interval,
+            script_id: script_id(source),
+            last: Instant::now(),
+        }
+    }
+
+    /// Take the checkpoints of the globals of a script reloaded in place
+    /// of the one given before
+    pub fn set_source(&mut self, source: &str) {
+        self.script_id = script_id(source);
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Save the globals if the interval has elapsed since the last save
+    pub fn tick(&mut self, globals: &[Rc<Object>]) -> io::Result<()> {
+        match self.interval {
+            Some(interval) if self.last.elapsed() >= interval => {}
+            _ => return Ok(()),
+        }
+        self.last = Instant::now();
+        save(&self.path, self.script_id, globals).map(|_| ())
+    }
+
+    /// Restore the globals saved by an earlier run of the same script.
+    /// There is nothing to restore if the checkpoint does not exist yet.
+    pub fn resume(&self, globals: &mut [Rc<Object>]) -> io::Result<usize> {
+        match load(&self.path, self.script_id, globals) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(0),
+            result => result,
+        }
+    }
+}
+
+/// Identify the script the globals belong to, since globals are saved by
+/// their index which changes when the script does. This is the 64 bit
+/// FNV-1a hash of the source.
+pub fn script_id(source: &str) -> u64 {
+    source.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Save the globals that hold data values to 'path' and return their
+/// count. The checkpoint is written to a temporary file that is then
+/// renamed, so a crash while saving leaves the previous one intact.
+pub fn save(path: &Path, script_id: u64, globals: &[Rc<Object>]) -> io::Result<usize> {
+    let mut data = Vec::with_capacity(HEADER_SIZE);
+    data.extend_from_slice(MAGIC);
+    data.push(VERSION);
+    data.extend_from_slice(&script_id.to_be_bytes());
+    let mut count = 0;
+    for (idx, global) in globals.iter().enumerate() {
+        if matches!(global.as_ref(), Object::Null) {
+            continue;
+        }
+        let mut value = Vec::new();
+        if encode(global, &mut value).is_ok() {
+            data.extend_from_slice(&(idx as u32).to_be_bytes());
+            data.extend_from_slice(&value);
+            count += 1;
+        }
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, data)?;
+    fs::rename(&tmp, path)?;
+    Ok(count)
+}
+
+/// Restore the globals saved to 'path' and return their count. The
+/// checkpoint must have been taken from the script identified by
+/// 'script_id'.
+pub fn load(path: &Path, script_id: u64, globals: &mut [Rc<Object>]) -> io::Result<usize> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let data = fs::read(path)?;
+    if data.len() < HEADER_SIZE || &data[..4] != MAGIC || data[4] != VERSION {
+        return Err(invalid("not a checkpoint file"));
+    }
+    if data[5..HEADER_SIZE] != script_id.to_be_bytes() {
+        return Err(invalid("checkpoint was taken from a different script"));
+    }
+    // Decode all the globals before restoring any of them
+    let mut restored = Vec::new();
+    let mut pos = HEADER_SIZE;
+    while pos < data.len() {
+        let idx = data
+            .get(pos..pos + 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            .filter(|idx| *idx < globals.len())
+            .ok_or_else(|| invalid("invalid global index"))?;
+        let (value, len) = decode(&data[pos + 4..]).map_err(|e| invalid(&e))?;
+        restored.push((idx, value));
+        pos += 4 + len;
+    }
+    let count = restored.len();
+    for (idx, value) in restored {
+        globals[idx] = value;
+    }
+    Ok(count)
+}
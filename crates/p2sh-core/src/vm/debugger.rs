@@ -0,0 +1,220 @@
+//! Interactive debugger of the scripts run using '--debug'.
+//!
+//! The VM asks the debugger whether to pause before each instruction. It
+//! pauses at the first instruction of a line that has a breakpoint, or of
+//! any line while stepping, and reads commands until one resumes the run.
+//! A line is entered again when a loop jumps back to it, but not when a
+//! function called on it returns.
+
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use crate::object::Object;
+use crate::vm::error::RTError;
+
+/// Reads the next command; None at the end of the input
+pub type ReadCommand = Box<dyn FnMut() -> Option<String>>;
+
+const HELP: &str = "\
+break N, b N     pause at line N
+delete N, d N    remove the breakpoint at line N
+breakpoints      list the breakpoints
+step, s          run to the next line
+continue, c      run to the next breakpoint
+print NAME, p    show the value of a global variable
+globals          show the global variables
+locals           show the locals of the function running
+stack            show the values on the stack, the top last
+where, bt        show the lines of the functions being run
+list, l          show the source around the line
+quit, q          stop the script
+An empty command repeats the last one.";
+
+// Number of lines shown before and after the current one by 'list'
+const LIST_CONTEXT: usize = 3;
+
+/// State of the program paused, as seen by the debugger
+pub struct Paused<'a> {
+    pub line: usize,
+    pub globals: &'a [Rc<Object>],
+    /// Locals of the function running
+    pub locals: &'a [Rc<Object>],
+    pub stack: &'a [Rc<Object>],
+    /// Lines being run by each function, the innermost first
+    pub frames: Vec<usize>,
+}
+
+/// The outcome of a command
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    Show(String),
+    Resume,
+    Quit,
+}
+
+pub struct Debugger {
+    breakpoints: BTreeSet<usize>,
+    stepping: bool,
+    // Line and instruction last run by the function at each depth
+    last: Vec<Option<(usize, usize)>>,
+    globals: Vec<String>,
+    source: Vec<String>,
+    read: ReadCommand,
+    last_command: String,
+}
+
+impl Debugger {
+    /// A debugger that pauses at the first line. The names of the globals
+    /// and the source of the script are used to show them.
+    pub fn new(globals: Vec<String>, source: Option<&str>, read: ReadCommand) -> Self {
+        Self {
+            breakpoints: BTreeSet::new(),
+            stepping: true,
+            last: Vec::new(),
+            globals,
+            source: source.map_or(Vec::new(), |s| s.lines().map(String::from).collect()),
+            read,
+            last_command: String::new(),
+        }
+    }
+
+    /// Record the instruction at 'ip' on 'line' about to run in the
+    /// function at 'depth', and return true if the run is to pause there
+    pub fn should_pause(&mut self, line: usize, ip: usize, depth: usize) -> bool {
+        // Functions deeper than this one have returned
+        self.last.resize(depth, None);
+        let last = self.last[depth - 1].replace((line, ip));
+        let entered = match last {
+            Some((last_line, last_ip)) => line != last_line || ip <= last_ip,
+            None => true,
+        };
+        entered && (self.stepping || self.breakpoints.contains(&line))
+    }
+
+    /// Show where the run paused and read commands until one resumes it.
+    /// An error is returned to stop the script.
+    pub fn pause(&mut self, paused: &Paused) -> Result<(), RTError> {
+        println!("{}", self.location(paused.line));
+        loop {
+            let reply = match (self.read)() {
+                Some(input) => self.command(&input, paused),
+                None => Reply::Quit,
+            };
+            match reply {
+                Reply::Show(text) => println!("{}", text),
+                Reply::Resume => return Ok(()),
+                Reply::Quit => {
+                    // The exit handlers run to the end
+                    self.breakpoints.clear();
+                    self.stepping = false;
+                    return Err(RTError::exit(0, paused.line));
+                }
+            }
+        }
+    }
+
+    /// Run a command while the run is paused
+    pub fn command(&mut self, input: &str, paused: &Paused) -> Reply {
+        let input = match input.trim() {
+            "" => self.last_command.clone(),
+            input => input.to_string(),
+        };
+        self.last_command = input.clone();
+        let mut words = input.split_whitespace();
+        let (cmd, arg) = (words.next().unwrap_or(""), words.next());
+        match (cmd, arg) {
+            ("break" | "b", Some(arg)) => match arg.parse::<usize>() {
+                Ok(line) if line > 0 => {
+                    self.breakpoints.insert(line);
+                    Reply::Show(format!("Breakpoint at line {}", line))
+                }
+                _ => Reply::Show(format!("Invalid line '{}'", arg)),
+            },
+            ("delete" | "d", Some(arg)) => match arg.parse::<usize>() {
+                Ok(line) if self.breakpoints.remove(&line) => {
+                    Reply::Show(format!("Deleted the breakpoint at line {}", line))
+                }
+                _ => Reply::Show(format!("No breakpoint at line {}", arg)),
+            },
+            ("breakpoints", None) if self.breakpoints.is_empty() => {
+                Reply::Show("No breakpoints".to_string())
+            }
+            ("breakpoints", None) => {
+                let lines: Vec<String> = self.breakpoints.iter().map(|l| l.to_string()).collect();
+                Reply::Show(format!("Breakpoints at lines {}", lines.join(", ")))
+            }
+            ("step" | "s", None) => {
+                self.stepping = true;
+                Reply::Resume
+            }
+            ("continue" | "c", None) => {
+                self.stepping = false;
+                Reply::Resume
+            }
+            ("print" | "p", Some(name)) => {
+                let idx = self.globals.iter().position(|g| g == name);
+                match idx.and_then(|idx| paused.globals.get(idx)) {
+                    Some(value) => Reply::Show(format!("{} = {}", name, value)),
+                    None => Reply::Show(format!("No global named '{}'", name)),
+                }
+            }
+            ("globals", None) => {
+                let globals = self.globals.iter().zip(paused.globals);
+                let lines: Vec<String> = globals
+                    .map(|(name, value)| format!("{} = {}", name, value))
+                    .collect();
+                Reply::Show(lines.join("\n"))
+            }
+            ("locals", None) => Reply::Show(Self::slots(paused.locals)),
+            ("stack", None) => Reply::Show(Self::slots(paused.stack)),
+            ("where" | "bt", None) => {
+                let frames = paused.frames.iter().enumerate();
+                let lines: Vec<String> = frames
+                    .map(|(i, line)| format!("#{} {}", i, self.location(*line)))
+                    .collect();
+                Reply::Show(lines.join("\n"))
+            }
+            ("list" | "l", None) => Reply::Show(self.listing(paused.line)),
+            ("quit" | "q", None) => Reply::Quit,
+            ("help" | "h", None) => Reply::Show(HELP.to_string()),
+            _ => Reply::Show(format!("Unknown command '{}'; try 'help'", input)),
+        }
+    }
+
+    // The line number followed by its source if known
+    fn location(&self, line: usize) -> String {
+        match self.source.get(line.wrapping_sub(1)) {
+            Some(text) => format!("[line {}] {}", line, text.trim()),
+            None => format!("[line {}]", line),
+        }
+    }
+
+    // The source around 'line' with the line marked
+    fn listing(&self, line: usize) -> String {
+        if line == 0 || line > self.source.len() {
+            return format!("No source for line {}", line);
+        }
+        let first = line.saturating_sub(LIST_CONTEXT).max(1);
+        let last = (line + LIST_CONTEXT).min(self.source.len());
+        let lines: Vec<String> = (first..=last)
+            .map(|n| {
+                let mark = if n == line { ">" } else { " " };
+                format!("{} {:4} {}", mark, n, self.source[n - 1])
+            })
+            .collect();
+        lines.join("\n")
+    }
+
+    // Values in slots numbered from 0, or '(none)'
+    fn slots(values: &[Rc<Object>]) -> String {
+        if values.is_empty() {
+            return "(none)".to_string();
+        }
+        let lines: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| format!("[{}] {}", i, value))
+            .collect();
+        lines.join("\n")
+    }
+}
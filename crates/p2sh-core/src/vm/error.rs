@@ -0,0 +1,57 @@
+use std::fmt;
+
+/// Exit code of the program when the reader of its output has gone away,
+/// as if it were killed by SIGPIPE
+pub const EXIT_BROKEN_PIPE: i32 = 141;
+
+/// Runtime error. It is different from error objects used in the builtin
+/// functions in the sense that a runtime error is not interpreted by the
+/// programming language but will terminate the program. However, the error
+/// objects used in the builtin functions are returned to the program for
+/// further processing.
+#[derive(Debug)]
+pub struct RTError {
+    pub msg: String,
+    pub line: usize,
+    /// Exit code when the error is a request to exit the program
+    pub exit: Option<i32>,
+    /// Functions being run when the error was raised, the innermost first
+    pub backtrace: Vec<String>,
+}
+
+impl fmt::Display for RTError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[line {}] Runtime error: {}", self.line, self.msg)?;
+        for call in &self.backtrace {
+            write!(f, "\n  {}", call)?;
+        }
+        Ok(())
+    }
+}
+
+impl RTError {
+    pub fn new(msg: &str, line: usize) -> Self {
+        Self {
+            msg: msg.to_string(),
+            line,
+            exit: None,
+            backtrace: Vec::new(),
+        }
+    }
+
+    /// A request to exit the program with the given exit code
+    pub fn exit(code: i32, line: usize) -> Self {
+        Self {
+            msg: format!("exit({})", code),
+            line,
+            exit: Some(code),
+            backtrace: Vec::new(),
+        }
+    }
+
+    /// A request to exit the program quietly as stdout is a pipe whose
+    /// reader has gone away
+    pub fn broken_pipe(line: usize) -> Self {
+        Self::exit(EXIT_BROKEN_PIPE, line)
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::object::func::Closure;
+use crate::object::hmap::HMap;
+use crate::object::Object;
+
+/// Interval in seconds of packet time between the scans for idle flows
+const EXPIRY_SCAN_INTERVAL: f64 = 1.0;
+
+/// Largest jump in an rtp sequence number counted as lost packets rather
+/// than as a restart of the sequence (MAX_DROPOUT in RFC 3550)
+const RTP_MAX_DROPOUT: i16 = 3000;
+
+/// Largest step back in an rtp sequence number counted as a late packet
+/// (MAX_MISORDER in RFC 3550)
+const RTP_MAX_MISORDER: i16 = 100;
+
+/// Protocol, source and destination addresses and ports identifying a flow
+pub type FlowKey = Vec<Rc<Object>>;
+
+/// The parts of a packet used to look for the gaps in the sequence numbers
+/// of its flow
+pub enum Segment {
+    /// A tcp segment with the length of its payload
+    Tcp {
+        seq: u32,
+        len: u32,
+        syn: bool,
+        fin: bool,
+        rst: bool,
+    },
+    /// A udp datagram that looks like an rtp packet
+    Rtp { ssrc: u32, seq: u16 },
+}
+
+/// Sequence numbers of a flow seen so far
+enum SeqState {
+    None,
+    Tcp(Option<u32>), // Sequence number expected next
+    Rtp {
+        ssrc: u32,
+        next: u16,       // Sequence number expected next
+        confirmed: bool, // Two packets in a row were seen
+    },
+}
+
+/// A flow and the map holding its record, which the script may add to
+struct Flow {
+    seq: u64, // Order in which the flows were created
+    record: Rc<HMap>,
+    last: f64,   // Time of the last packet counted in the flow
+    packet: i64, // Number of the last packet counted in the flow
+    state: SeqState,
+}
+
+/// The flows of the packets seen by 'flow()' keyed by their 5-tuple.
+/// Flows that are idle for longer than the timeout are expired.
+#[derive(Default)]
+pub struct FlowTable {
+    flows: HashMap<FlowKey, Flow>,
+    next_seq: u64,
+    timeout: Option<f64>,
+    on_expire: Option<Rc<Closure>>,
+    last_scan: f64,
+}
+
+impl FlowTable {
+    /// Expire the flows idle for longer than 'timeout' seconds, passing
+    /// their records to 'on_expire' if given
+    pub fn set_timeout(&mut self, timeout: f64, on_expire: Option<Rc<Closure>>) {
+        self.timeout = Some(timeout);
+        self.on_expire = on_expire;
+    }
+
+    pub fn on_expire(&self) -> Option<Rc<Closure>> {
+        self.on_expire.clone()
+    }
+
+    /// Count a packet in its flow, creating the flow if it is new, and
+    /// return the record of the flow. A packet is counted once no matter
+    /// how many times it is looked up.
+    /// fields: the named parts of the key to initialise a new record with
+    /// time: the time of the packet relative to the first packet
+    /// packet: the number of the packet
+    /// bytes: the length of the packet on the wire
+    /// segment: the sequence number of the packet, if it has one
+    pub fn track(
+        &mut self,
+        key: FlowKey,
+        fields: &[(&str, Rc<Object>)],
+        time: f64,
+        packet: i64,
+        bytes: i64,
+        segment: Option<Segment>,
+    ) -> Rc<HMap> {
+        let next_seq = &mut self.next_seq;
+        let flow = self.flows.entry(key).or_insert_with(|| {
+            let record = HMap::default();
+            for (name, value) in fields {
+                record.insert(Rc::new(Object::Str(name.to_string())), value.clone());
+            }
+            set_field(&record, "first", Object::Float(time));
+            set_field(&record, "packets", Object::Integer(0));
+            set_field(&record, "bytes", Object::Integer(0));
+            *next_seq += 1;
+            Flow {
+                seq: *next_seq,
+                record: Rc::new(record),
+                last: time,
+                packet: 0,
+                state: SeqState::None,
+            }
+        });
+        if flow.packet != packet {
+            flow.packet = packet;
+            flow.last = time;
+            let record = &flow.record;
+            set_field(record, "last", Object::Float(time));
+            add_field(record, "packets", 1);
+            add_field(record, "bytes", bytes);
+            if let Some(segment) = segment {
+                flow.analyze(segment);
+            }
+        }
+        flow.record.clone()
+    }
+
+    /// Remove the flows that have been idle for longer than the timeout at
+    /// the time 'now' and return their records in the order in which the
+    /// flows were created. The flows are scanned at most once a second.
+    pub fn expire(&mut self, now: f64) -> Vec<Rc<HMap>> {
+        let Some(timeout) = self.timeout else {
+            return Vec::new();
+        };
+        if now - self.last_scan < EXPIRY_SCAN_INTERVAL {
+            return Vec::new();
+        }
+        self.last_scan = now;
+        let mut expired = Vec::new();
+        self.flows.retain(|_, flow| {
+            let idle = now - flow.last > timeout;
+            if idle {
+                expired.push((flow.seq, flow.record.clone()));
+            }
+            !idle
+        });
+        expired.sort_by_key(|(seq, _)| *seq);
+        expired.into_iter().map(|(_, record)| record).collect()
+    }
+
+    /// Records of the active flows in the order in which they were created
+    pub fn records(&self) -> Vec<Rc<HMap>> {
+        let mut flows: Vec<&Flow> = self.flows.values().collect();
+        flows.sort_by_key(|flow| flow.seq);
+        flows.iter().map(|flow| flow.record.clone()).collect()
+    }
+}
+
+impl Flow {
+    // Count the gaps in the sequence numbers of the flow
+    fn analyze(&mut self, segment: Segment) {
+        match segment {
+            Segment::Tcp {
+                seq,
+                len,
+                syn,
+                fin,
+                rst,
+            } => {
+                if let SeqState::None = self.state {
+                    self.state = SeqState::Tcp(None);
+                    let analysis = HMap::default();
+                    set_field(&analysis, "gaps", Object::Integer(0));
+                    set_field(&analysis, "gap_bytes", Object::Integer(0));
+                    set_field(&analysis, "retransmissions", Object::Integer(0));
+                    let key = Rc::new(Object::Str("analysis".to_string()));
+                    self.record
+                        .insert(key, Rc::new(Object::Map(Rc::new(analysis))));
+                }
+                let SeqState::Tcp(next) = &mut self.state else {
+                    return;
+                };
+                // The sequence number of a reset is not that of the data
+                if rst {
+                    return;
+                }
+                // The syn and the fin take up a sequence number each
+                let seglen = len + syn as u32 + fin as u32;
+                let end = seq.wrapping_add(seglen);
+                let Some(expected) = *next else {
+                    *next = Some(end);
+                    return;
+                };
+                let delta = seq.wrapping_sub(expected) as i32;
+                if delta > 0 {
+                    // The bytes in between were not captured
+                    let analysis = sub_record(&self.record, "analysis");
+                    add_field(&analysis, "gaps", 1);
+                    add_field(&analysis, "gap_bytes", delta as i64);
+                    *next = Some(end);
+                } else if delta < 0 {
+                    if seglen > 0 {
+                        let analysis = sub_record(&self.record, "analysis");
+                        add_field(&analysis, "retransmissions", 1);
+                    }
+                    if (end.wrapping_sub(expected) as i32) > 0 {
+                        *next = Some(end);
+                    }
+                } else {
+                    *next = Some(end);
+                }
+            }
+            Segment::Rtp { ssrc, seq } => {
+                let (last_ssrc, next, confirmed) = match &mut self.state {
+                    SeqState::None => {
+                        self.state = SeqState::Rtp {
+                            ssrc,
+                            next: seq.wrapping_add(1),
+                            confirmed: false,
+                        };
+                        return;
+                    }
+                    SeqState::Tcp(_) => return,
+                    SeqState::Rtp {
+                        ssrc,
+                        next,
+                        confirmed,
+                    } => (ssrc, next, confirmed),
+                };
+                let delta = seq.wrapping_sub(*next) as i16;
+                // A new source restarts the sequence
+                if *last_ssrc != ssrc {
+                    *last_ssrc = ssrc;
+                    *next = seq.wrapping_add(1);
+                    return;
+                }
+                if !*confirmed {
+                    // Two packets from the same source in a row make an
+                    // rtp stream rather than a udp flow that looks like one
+                    if delta != 0 {
+                        *next = seq.wrapping_add(1);
+                        return;
+                    }
+                    *confirmed = true;
+                    let rtp = HMap::default();
+                    set_field(&rtp, "ssrc", Object::Integer(ssrc as i64));
+                    set_field(&rtp, "packets", Object::Integer(1));
+                    set_field(&rtp, "lost", Object::Integer(0));
+                    set_field(&rtp, "late", Object::Integer(0));
+                    let key = Rc::new(Object::Str("rtp".to_string()));
+                    self.record.insert(key, Rc::new(Object::Map(Rc::new(rtp))));
+                }
+                let rtp = sub_record(&self.record, "rtp");
+                add_field(&rtp, "packets", 1);
+                if (0..RTP_MAX_DROPOUT).contains(&delta) {
+                    add_field(&rtp, "lost", delta as i64);
+                    *next = seq.wrapping_add(1);
+                } else if (-RTP_MAX_MISORDER..0).contains(&delta) {
+                    // A packet counted as lost arrived late
+                    add_field(&rtp, "lost", -1);
+                    add_field(&rtp, "late", 1);
+                } else {
+                    *next = seq.wrapping_add(1);
+                }
+            }
+        }
+    }
+}
+
+/// Source and sequence number of an rtp packet in a udp payload, if the
+/// payload looks like one: version 2, and not an rtcp packet
+pub fn rtp_header(payload: &[u8]) -> Option<(u32, u16)> {
+    if payload.len() < 12 || payload[0] >> 6 != 2 {
+        return None;
+    }
+    // The payload types 72 to 76 with the marker bit are rtcp packets
+    if (72..=76).contains(&(payload[1] & 0x7f)) {
+        return None;
+    }
+    let seq = u16::from_be_bytes([payload[2], payload[3]]);
+    let ssrc = u32::from_be_bytes([payload[8], payload[9], payload[10], payload[11]]);
+    Some((ssrc, seq))
+}
+
+// A map held in a field of the record, or an empty one if the script
+// replaced it
+fn sub_record(record: &HMap, name: &str) -> Rc<HMap> {
+    let key = Rc::new(Object::Str(name.to_string()));
+    match record.get(&key).as_ref() {
+        Object::Map(map) => map.clone(),
+        _ => Rc::new(HMap::default()),
+    }
+}
+
+fn set_field(record: &HMap, name: &str, value: Object) {
+    record.insert(Rc::new(Object::Str(name.to_string())), Rc::new(value));
+}
+
+// Add to a counter in the record unless the script replaced it
+fn add_field(record: &HMap, name: &str, n: i64) {
+    let key = Rc::new(Object::Str(name.to_string()));
+    if let Object::Integer(count) = record.get(&key).as_ref() {
+        set_field(record, name, Object::Integer(count + n));
+    }
+}
@@ -8,11 +8,26 @@ pub struct Frame {
     pub closure: Rc<Closure>,
     pub ip: usize, // instruction pointer
     pub bp: usize, // base pointer
+    /// Line of the call that pushed the frame, or 0 if not known
+    pub call_line: usize,
 }
 
 impl Frame {
     pub fn new(closure: Rc<Closure>, bp: usize) -> Frame {
-        Frame { closure, ip: 0, bp }
+        Frame {
+            closure,
+            ip: 0,
+            bp,
+            call_line: 0,
+        }
+    }
+
+    /// A frame to run a function called at 'line'
+    pub fn new_call(closure: Rc<Closure>, bp: usize, line: usize) -> Frame {
+        Frame {
+            call_line: line,
+            ..Frame::new(closure, bp)
+        }
     }
 
     pub fn instructions(&self) -> &Rc<Instructions> {
@@ -0,0 +1,2193 @@
+use byteorder::BigEndian;
+use byteorder::ByteOrder;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::rc::Rc;
+
+use crate::builtins::csv::{self, OutputFormat};
+use crate::builtins::functions::contains;
+use crate::builtins::functions::random;
+use crate::builtins::functions::regex_matches;
+use crate::builtins::functions::BUILTINFNS;
+#[cfg(feature = "pcap")]
+use crate::builtins::json;
+#[cfg(feature = "pcap")]
+use crate::builtins::pcap::PcapPacket;
+use crate::builtins::print::write_stdout;
+use crate::builtins::variables::BuiltinVarType;
+use crate::code::definitions::Instructions;
+use crate::code::opcode::Opcode;
+#[cfg(feature = "pcap")]
+use crate::code::prop::PacketPropType;
+use crate::compiler::Bytecode;
+use crate::object::array::Array;
+use crate::object::error::ErrorObj;
+use crate::object::file::FileHandle;
+use crate::object::func::BuiltinFunction;
+use crate::object::func::Closure;
+use crate::object::func::CompiledFunction;
+use crate::object::hmap::HMap;
+use crate::object::timestamp::Duration;
+use crate::object::Object;
+use crate::vm::debugger::{Debugger, Paused};
+use crate::vm::error::RTError;
+#[cfg(feature = "pcap")]
+use crate::vm::flows::{self, FlowTable, Segment};
+use crate::vm::frame::Frame;
+use crate::vm::objects::ObjectReport;
+#[cfg(feature = "pcap")]
+use crate::vm::pktprop::MAX_PROTO_DEPTH;
+#[cfg(feature = "pcap")]
+use crate::vm::profile::DissectProfile;
+use crate::vm::ratelimit::RateLimiter;
+use crate::vm::reload::carry_over_globals;
+use crate::vm::resolver::Resolver;
+use crate::vm::trace::{trace_builtin, TraceResult};
+#[cfg(feature = "pcap")]
+use p2sh_dissect::checksum::AUTO;
+#[cfg(feature = "pcap")]
+use p2sh_dissect::ports::{parse_app_proto, AppProto, PortMap};
+#[cfg(feature = "pcap")]
+use p2sh_dissect::tcp::{TCP_FIN, TCP_RST, TCP_SYN};
+
+const STACK_SIZE: usize = 4096;
+const MAX_FRAMES: usize = 4096;
+pub const GLOBALS_SIZE: usize = 65536;
+pub const BUILTINS_SIZE: usize = 256;
+// Longest string, in bytes, a string repetition may produce
+const MAX_STR_REPEAT: usize = 1 << 28;
+
+/*
+ * The virtual machine has the constants and instructions generated by the
+ * compiler and has a stack. The stack pointer always points to the next
+ * available free slot. So, the top of stack is stack[len - 1]. stack pointer
+ * is assumed to be '0' when stack is empt and stack_top() would return Null.
+ */
+pub struct VM {
+    constants: Vec<Rc<Object>>,
+    stack: Vec<Rc<Object>>,
+    sp: usize,
+    pub globals: Vec<Rc<Object>>,
+    pub builtinvars: RefCell<Vec<Rc<Object>>>,
+    frames: Vec<Frame>,
+    frames_index: usize,
+    #[cfg(feature = "pcap")]
+    curr_pkt: RefCell<Option<Rc<Object>>>,
+    exit_handlers: Vec<Rc<Closure>>,
+    registered_filters: Vec<RegisteredFilter>,
+    filters_sealed: bool,
+    testdata: Option<String>, // pcap declared using 'testdata'
+    #[cfg(feature = "pcap")]
+    port_map: PortMap, // protocols decoded from the payload on each port
+    #[cfg(feature = "pcap")]
+    dissect_profile: Option<DissectProfile>, // time spent by each dissector
+    legacy_props: bool,       // properties return plain values
+    #[cfg(feature = "pcap")]
+    flows: FlowTable, // flows of the packets seen by 'flow()'
+    trace_builtins: bool,     // log the calls to builtins to stderr
+    output_format: OutputFormat, // format of the output of 'puts'
+    resolver: Resolver,       // host and service names looked up
+    rate_limiter: RateLimiter, // token buckets of 'ratelimit()'
+    debugger: Option<Box<Debugger>>, // pauses the run with '--debug'
+    handlers: Vec<Handler>,   // catch blocks of the 'try' blocks being run
+    rng: Option<StdRng>,      // seeded generator of 'rand' if deterministic
+    global_names: Vec<String>, // names of the globals by index
+}
+
+// The catch block of a 'try' block being run, along with the frame and
+// the stack pointer to unwind to when a runtime error is raised.
+struct Handler {
+    frames_index: usize,
+    sp: usize,
+    ip: usize,
+}
+
+/// A filter registered using 'register_filter'. The pattern and the
+/// action are functions that take no arguments.
+pub struct RegisteredFilter {
+    pub pattern: Rc<Closure>,
+    pub action: Option<Rc<Closure>>,
+}
+
+enum BinaryOperation {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Relational,
+}
+
+impl VM {
+    pub fn new(bytecode: Bytecode) -> VM {
+        let data = Rc::new(Object::Null);
+        let fn_main = Rc::new(CompiledFunction::new(bytecode.instructions, 0, 0, 0));
+        let closure_m: Rc<Closure> = Rc::new(Closure::new(fn_main, Vec::new()));
+        let frame_e = Frame::default();
+        let frame_m = Frame::new(closure_m, 0);
+        let mut frames = vec![frame_e; MAX_FRAMES];
+        frames[0] = frame_m;
+
+        VM {
+            constants: bytecode.constants,
+            stack: vec![data.clone(); STACK_SIZE],
+            sp: 0,
+            globals: vec![data.clone(); GLOBALS_SIZE],
+            builtinvars: RefCell::new(vec![data; BUILTINS_SIZE]),
+            frames,
+            frames_index: 1,
+            #[cfg(feature = "pcap")]
+            curr_pkt: RefCell::new(None),
+            exit_handlers: Vec::new(),
+            registered_filters: Vec::new(),
+            filters_sealed: false,
+            testdata: None,
+            #[cfg(feature = "pcap")]
+            port_map: PortMap::default(),
+            #[cfg(feature = "pcap")]
+            dissect_profile: None,
+            legacy_props: false,
+            #[cfg(feature = "pcap")]
+            flows: FlowTable::default(),
+            trace_builtins: false,
+            output_format: OutputFormat::Text,
+            resolver: Resolver::default(),
+            rate_limiter: RateLimiter::default(),
+            debugger: None,
+            handlers: Vec::new(),
+            rng: None,
+            global_names: bytecode.globals,
+        }
+    }
+
+    pub fn new_with_global_store(bytecode: Bytecode, globals: Vec<Rc<Object>>) -> VM {
+        let mut vm = VM::new(bytecode);
+        vm.globals = globals;
+        vm
+    }
+
+    /// Replace the script run by the VM with a newly compiled one between
+    /// packets. The statements of the new script outside the filters are
+    /// run, and the globals whose names are in 'old_names', the globals of
+    /// the old script, get back their values except for functions. The
+    /// rest of the state of the VM, such as the flows, is kept. Filters
+    /// may be registered again. If the new script fails, the old one is
+    /// restored. Returns the number of globals that kept their values.
+    pub fn reload(&mut self, bytecode: Bytecode, old_names: &[String]) -> Result<usize, RTError> {
+        let new_names = bytecode.globals.clone();
+        let data = Rc::new(Object::Null);
+        let constants = std::mem::replace(&mut self.constants, bytecode.constants);
+        let old_main = self.frames[0].clone();
+        let globals = std::mem::replace(&mut self.globals, vec![data; GLOBALS_SIZE]);
+        let exit_handlers = std::mem::take(&mut self.exit_handlers);
+        self.set_main(bytecode.instructions);
+        self.registered_filters.clear();
+        self.filters_sealed = false;
+        let result = self.run();
+        self.filters_sealed = true;
+        if let Err(err) = result {
+            self.constants = constants;
+            self.frames[0] = old_main;
+            self.frames_index = 1;
+            self.sp = 0;
+            self.globals = globals;
+            self.exit_handlers = exit_handlers;
+            self.registered_filters.clear();
+            return Err(err);
+        }
+        let kept = carry_over_globals(old_names, &globals, &new_names, &mut self.globals);
+        self.global_names = new_names;
+        Ok(kept)
+    }
+
+    // Make the instructions the main function run from the start
+    fn set_main(&mut self, instructions: Instructions) {
+        let fn_main = Rc::new(CompiledFunction::new(instructions, 0, 0, 0));
+        let closure_m = Rc::new(Closure::new(fn_main, Vec::new()));
+        self.frames[0] = Frame::new(closure_m, 0);
+        self.frames_index = 1;
+        self.sp = 0;
+    }
+
+    // peek element from the top and return Null if underflow
+    pub fn peek(&self, distance: usize) -> Rc<Object> {
+        if self.sp <= distance {
+            Rc::new(Object::Null)
+        } else {
+            Rc::clone(&self.stack[self.sp - distance - 1])
+        }
+    }
+
+    // peek element from the top and return error if underflow
+    pub fn top(&self, distance: usize, line: usize) -> Result<Rc<Object>, RTError> {
+        if self.sp <= distance {
+            Err(RTError::new("Stack underflow!", line))
+        } else {
+            Ok(Rc::clone(&self.stack[self.sp - distance - 1]))
+        }
+    }
+
+    /*
+     * If there isn't enough space on stack then push elements on to it
+     * Otherwise, set the element on stack based on the stack pointer (sp).
+     * In either case, increment 'sp' to point to the newly available slot.
+     */
+    pub fn push(&mut self, obj: Rc<Object>, line: usize) -> Result<(), RTError> {
+        if self.sp >= self.stack.len() {
+            return Err(RTError::new("Stack overflow!", line));
+        } else {
+            self.stack[self.sp] = obj;
+        }
+        self.sp += 1;
+        Ok(())
+    }
+
+    pub fn pop(&mut self, line: usize) -> Result<Rc<Object>, RTError> {
+        if self.sp == 0 {
+            return Err(RTError::new("Stack underflow!", line));
+        }
+        let obj = self.stack[self.sp - 1].clone();
+        self.sp -= 1;
+        Ok(obj)
+    }
+
+    // Index of the bottom of the 'count' elements on top of the stack, or
+    // an error if there are fewer, as in corrupt bytecode
+    fn stack_base(&self, count: usize, line: usize) -> Result<usize, RTError> {
+        self.sp
+            .checked_sub(count)
+            .ok_or_else(|| RTError::new("Stack underflow!", line))
+    }
+
+    pub fn last_popped(&mut self) -> Rc<Object> {
+        self.stack[self.sp].clone()
+    }
+
+    pub fn current_frame(&mut self) -> &mut Frame {
+        &mut self.frames[self.frames_index - 1]
+    }
+
+    pub fn push_frame(&mut self, f: Frame) {
+        self.frames[self.frames_index] = f;
+        self.frames_index += 1;
+    }
+
+    pub fn pop_frame(&mut self) -> Frame {
+        self.frames_index -= 1;
+        self.frames[self.frames_index].clone()
+    }
+
+    #[allow(dead_code)]
+    pub fn print_stack(&self) {
+        eprintln!(
+            "------------ Stack [sp: {:<4}, bp:{:<4}] ---------------",
+            self.sp,
+            self.frames[self.frames_index - 1].bp,
+        );
+        if self.sp == 0 {
+            eprintln!("[<empty>]");
+            return;
+        }
+        for i in 0..self.sp {
+            eprint!("[ ");
+            eprint!("{}", self.stack[i]);
+            eprint!(" ]");
+        }
+        eprintln!();
+    }
+
+    /*
+     * The main run loop for the interpreter. Since this is the hot path,
+     * do not use functions such as lookup() or read_operands() for decoding
+     * instructions and operands.
+     */
+    /// Run the current frame to the end. A runtime error raised within a
+    /// 'try' block started by this run is passed to its catch block. Any
+    /// other runtime error raised within a function is returned with the
+    /// calls that led to it.
+    pub fn run(&mut self) -> Result<(), RTError> {
+        let base = self.frames_index;
+        loop {
+            let Err(err) = self.run_frames() else {
+                return Ok(());
+            };
+            if err.exit.is_none() {
+                if let Some(handler) = self.handlers.pop() {
+                    if handler.frames_index >= base {
+                        self.frames_index = handler.frames_index;
+                        self.sp = handler.sp;
+                        self.current_frame().ip = handler.ip;
+                        let obj = Object::Err(ErrorObj::Runtime(err.msg));
+                        self.push(Rc::new(obj), err.line)?;
+                        continue;
+                    }
+                    self.handlers.push(handler);
+                }
+            }
+            // The 'try' blocks of this run are abandoned along with its frames
+            self.handlers.retain(|h| h.frames_index < base);
+            return Err(self.add_backtrace(err));
+        }
+    }
+
+    // Record the functions being run, from the innermost one out, each
+    // with the line it was at and the line it was called from. Errors of
+    // nested runs already have it.
+    fn add_backtrace(&self, mut err: RTError) -> RTError {
+        if err.exit.is_some() || !err.backtrace.is_empty() {
+            return err;
+        }
+        let mut line = err.line;
+        for frame in self.frames[1..self.frames_index].iter().rev() {
+            let func = &frame.closure.func;
+            // Skip the filters and the empty frames of the calls made by builtins
+            if func.pattern.is_some() || frame.instructions().is_empty() {
+                continue;
+            }
+            let name = func.name.as_deref().unwrap_or("<anonymous>");
+            let mut call = format!("in fn {} at line {}", name, line);
+            if frame.call_line > 0 {
+                call.push_str(&format!(", called from line {}", frame.call_line));
+                line = frame.call_line;
+            }
+            err.backtrace.push(call);
+        }
+        err
+    }
+
+    fn run_frames(&mut self) -> Result<(), RTError> {
+        while self.current_frame().ip < self.current_frame().instructions().len() {
+            // Helpers
+            let ip = self.current_frame().ip;
+            let instructions = self.current_frame().instructions().clone();
+
+            #[cfg(feature = "debug_trace_execution")]
+            {
+                self.print_stack();
+                instructions.print(ip);
+            }
+
+            let op = Opcode::from(instructions.code[ip]);
+            let line = instructions.lines[ip];
+            if self.debugger.is_some() {
+                self.debug(line, ip)?;
+            }
+            match op {
+                Opcode::Constant => {
+                    let const_index =
+                        BigEndian::read_u16(&instructions.code[ip + 1..ip + 3]) as usize;
+                    let constant = self.constants.get(const_index).ok_or_else(|| {
+                        RTError::new(&format!("constant not found [idx: {}]", const_index), line)
+                    })?;
+                    self.push(constant.clone(), line)?;
+                    // skip over the two bytes of the operand in the next cycle
+                    self.current_frame().ip += 2;
+                }
+                Opcode::Pop => {
+                    self.pop(line)?;
+                }
+                Opcode::Add => {
+                    self.binary_op(BinaryOperation::Add, |a, b| a + b, line)?;
+                }
+                Opcode::Sub => {
+                    self.binary_op(BinaryOperation::Sub, |a, b| a - b, line)?;
+                }
+                Opcode::Mul => {
+                    self.binary_op(BinaryOperation::Mul, |a, b| a * b, line)?;
+                }
+                Opcode::Div => {
+                    self.binary_op(BinaryOperation::Div, |a, b| a / b, line)?;
+                }
+                Opcode::Mod => {
+                    self.binary_op(BinaryOperation::Mod, |a, b| a % b, line)?;
+                }
+                Opcode::True => self.push(Rc::new(Object::Bool(true)), line)?,
+                Opcode::False => self.push(Rc::new(Object::Bool(false)), line)?,
+                Opcode::Equal => {
+                    let b = self.pop(line)?;
+                    let a = self.pop(line)?;
+                    self.push(Rc::new(Object::Bool(a.as_ref() == b.as_ref())), line)?;
+                }
+                Opcode::NotEqual => {
+                    let b = self.pop(line)?;
+                    let a = self.pop(line)?;
+                    self.push(Rc::new(Object::Bool(a != b)), line)?;
+                }
+                Opcode::Greater => {
+                    self.binary_op(
+                        BinaryOperation::Relational,
+                        |a, b| Object::Bool(a > b),
+                        line,
+                    )?;
+                }
+                Opcode::GreaterEq => {
+                    self.binary_op(
+                        BinaryOperation::Relational,
+                        |a, b| Object::Bool(a >= b),
+                        line,
+                    )?;
+                }
+                Opcode::Minus => {
+                    if !self.peek(0).is_number() {
+                        return Err(RTError::new("bad operand type for unary '-'", line));
+                    }
+                    let obj = self.pop(line)?.clone();
+                    let val = -&*obj;
+                    self.push(Rc::new(val), line)?;
+                }
+                Opcode::RegexMatch => {
+                    let re = self.pop(line)?;
+                    let subject = self.pop(line)?;
+                    let matched = regex_matches(&subject, &re)
+                        .map_err(|e| RTError::new(&format!("Regex match failed: {}.", e), line))?;
+                    self.push(Rc::new(Object::Bool(matched)), line)?;
+                }
+                Opcode::In => {
+                    let container = self.pop(line)?;
+                    let elem = self.pop(line)?;
+                    let found = contains(&container, &elem).map_err(|e| {
+                        RTError::new(&format!("Operator 'in' failed: {}.", e), line)
+                    })?;
+                    self.push(Rc::new(Object::Bool(found)), line)?;
+                }
+                Opcode::Bang => {
+                    // Logical not (!)
+                    let obj = self.pop(line)?;
+                    self.push(Rc::new(Object::Bool(obj.is_falsey())), line)?;
+                }
+                Opcode::Jump => {
+                    let bytes = &instructions.code[ip + 1..ip + 3];
+                    // decode the operand (jump address) right after the opcode
+                    self.current_frame().ip = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+                    // Do not increment ip at the end of the loop since the
+                    // control is transferred to a jump statement. This allows
+                    // us to have statements such as 'loop {}' as the only
+                    // statement in a program.
+                    continue;
+                }
+                Opcode::JumpIfFalse => {
+                    let bytes = &instructions.code[ip + 1..ip + 3];
+                    // decode the operand (jump address) right after the opcode
+                    let pos: usize = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+                    // skip over the two bytes of the operand in the next cycle
+                    self.current_frame().ip += 2;
+                    // Pop the condition off the stack as it is not used in if-else
+                    let condition = self.pop(line)?;
+                    // Jump if the condition is false but continue program
+                    // execution otherwise.
+                    if condition.is_falsey() {
+                        self.current_frame().ip = pos;
+                        // Do not increment ip since the control is being
+                        // transferred to the jump address.
+                        continue;
+                    }
+                }
+                Opcode::JumpIfFalseNoPop => {
+                    let bytes = &instructions.code[ip + 1..ip + 3];
+                    // decode the operand (jump address) right after the opcode
+                    let pos: usize = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+                    // skip over the two bytes of the operand in the next cycle
+                    self.current_frame().ip += 2;
+                    // Do not pop the condition off the stack as it is used
+                    // by the logical operators '&&' and '||'
+                    let condition = self.top(0, line)?;
+                    if condition.is_falsey() {
+                        self.current_frame().ip = pos;
+                        // Do not increment ip since the control is being
+                        // transferred to the jump address.
+                        continue;
+                    }
+                }
+                Opcode::Null => {
+                    self.push(Rc::new(Object::Null), line)?;
+                }
+                Opcode::DefineGlobal => {
+                    let bytes = &instructions.code[ip + 1..ip + 3];
+                    // decode the operand (index to globals)
+                    let globals_index: usize = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+                    self.current_frame().ip += 2;
+                    self.globals[globals_index] = self.pop(line)?;
+                }
+                Opcode::GetGlobal => {
+                    let bytes = &instructions.code[ip + 1..ip + 3];
+                    // decode the operand (index to globals)
+                    let globals_index: usize = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+                    self.current_frame().ip += 2;
+                    self.push(self.globals[globals_index].clone(), line)?;
+                }
+                Opcode::SetGlobal => {
+                    let bytes = &instructions.code[ip + 1..ip + 3];
+                    // decode the operand (index to globals)
+                    let globals_index: usize = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+                    self.current_frame().ip += 2;
+                    // Use the element on top of the stack for the assignment
+                    // but do not pop the element off the stack since the assigment
+                    // expression also evaluates to the value that is assigned
+                    self.globals[globals_index] = self.top(0, line)?;
+                }
+                Opcode::Array => {
+                    // Read the first operand i.e. the number of array elements
+                    let num_elements = BigEndian::read_u16(&instructions.code[ip + 1..]) as usize;
+                    let base = self.stack_base(num_elements, line)?;
+                    let elements = self.build_array(base, self.sp);
+                    // pop 'num_elements' off the stack
+                    self.sp -= num_elements;
+                    // Push the array back onto the stack as an object
+                    self.push(Rc::new(Object::Arr(Rc::new(Array::new(elements)))), line)?;
+                    // skip over the two bytes of the operand in the next cycle
+                    self.current_frame().ip += 2;
+                }
+                Opcode::Map => {
+                    // Read the first operand i.e. the number of pairs
+                    let num_elements =
+                        BigEndian::read_u16(&instructions.code[ip + 1..ip + 3]) as usize;
+                    let base = self.stack_base(num_elements, line)?;
+                    let map = self.build_map(base, self.sp, line)?;
+                    // pop 'num_elements' off the stack
+                    self.sp -= num_elements;
+                    // Push the array back onto the stack as an object
+                    self.push(Rc::new(Object::Map(Rc::new(map))), line)?;
+                    // skip over the two bytes of the operand in the next cycle
+                    self.current_frame().ip += 2;
+                }
+                Opcode::Call => {
+                    let num_args = instructions.code[ip + 1] as usize;
+                    self.exec_call(num_args, line)?;
+                    // Do not increment ip here since the vm is using a new frame
+                    // and 'ip' should point to the first instruction in that frame
+                    continue;
+                }
+                Opcode::ReturnValue => {
+                    let ret_val = self.pop(line)?;
+                    let frame = self.pop_frame();
+                    self.drop_handlers();
+                    // Reset stack frame by popping the local bindings and the
+                    // the compiled function (the '-1' is for the compled function)
+                    // Since the callee's frame has already been popped, the 'ip' used here
+                    // refers to the caller's frame. So, do not increment that at the
+                    // end of this loop and 'continue' immediately.
+                    self.sp = frame.bp - 1;
+                    self.push(ret_val, line)?;
+                    continue;
+                }
+                Opcode::Return => {
+                    // There is no return value to pop
+                    let frame = self.pop_frame();
+                    self.drop_handlers();
+                    // Reset stack frame by popping the local bindings and the
+                    // the compiled function (the '-1' is for the compled function)
+                    self.sp = frame.bp - 1;
+                    self.push(Rc::new(Object::Null), line)?;
+                    // continue for the same reason as that of 'OpReturnValue'
+                    continue;
+                }
+                Opcode::GetIndex => {
+                    // The top most element on the stack is the index expression
+                    // The next element is the expression itself.
+                    let index = self.pop(line)?;
+                    let left = self.pop(line)?;
+                    self.exec_index_expr(left, index, None, line)?;
+                }
+                Opcode::SetIndex => {
+                    // The top most element on the stack is the index expression
+                    // The next element is the expression itself. The value to be
+                    // set is further down the stack.
+                    let index = self.pop(line)?;
+                    let left = self.pop(line)?;
+                    let setval = Some(self.pop(line)?);
+                    self.exec_index_expr(left, index, setval, line)?;
+                }
+                Opcode::DefineLocal => {
+                    // decode the operand (index to locals)
+                    let locals_index = instructions.code[ip + 1] as usize;
+                    self.current_frame().ip += 1;
+                    let bp = self.current_frame().bp;
+                    // Create the local binding
+                    self.stack[bp + locals_index] = self.pop(line)?;
+                }
+                Opcode::GetLocal => {
+                    // decode the operand (index to locals)
+                    let locals_index = instructions.code[ip + 1] as usize;
+                    self.current_frame().ip += 1;
+                    let bp = self.current_frame().bp;
+                    let obj = self.stack[bp + locals_index].clone();
+                    self.push(obj, line)?;
+                }
+                Opcode::SetLocal => {
+                    // decode the operand (index to locals)
+                    let locals_index = instructions.code[ip + 1] as usize;
+                    self.current_frame().ip += 1;
+                    let bp = self.current_frame().bp;
+                    // Use the element on top of the stack for the assignment
+                    // but do not pop the element off the stack since the assigment
+                    // expression also evaluates to the value that is assigned
+                    self.stack[bp + locals_index] = self.top(0, line)?;
+                }
+                Opcode::GetBuiltinFn => {
+                    // decode the operand (index to built-in functions)
+                    let builtin_index = instructions.code[ip + 1] as usize;
+                    self.current_frame().ip += 1;
+                    if let Some(bt) = BUILTINFNS.get(builtin_index) {
+                        // let builtin_func = bt.func;
+                        self.push(Rc::new(Object::Builtin(Rc::new(bt.clone()))), line)?;
+                    }
+                }
+                Opcode::GetBuiltinVar => {
+                    let builtin_index = instructions.code[ip + 1] as usize;
+                    self.current_frame().ip += 1;
+                    let obj = self.builtinvars.borrow()[builtin_index].clone();
+                    self.push(obj, line)?;
+                }
+                Opcode::Closure => {
+                    // Decode first operand (index to closure in the constant pool)
+                    let const_idx =
+                        BigEndian::read_u16(&instructions.code[ip + 1..ip + 3]) as usize;
+                    // Decode second operand (number of free varaibles)
+                    let num_free = instructions.code[ip + 3] as usize;
+                    // push the compiled function as a closure on stack
+                    self.push_closure(const_idx, num_free, line)?;
+                    self.current_frame().ip += 3;
+                }
+                Opcode::GetFree => {
+                    let free_idx = instructions.code[ip + 1] as usize;
+                    self.current_frame().ip += 1;
+                    let curr_closure = self.current_frame().closure.clone();
+                    self.push(curr_closure.free.borrow()[free_idx].clone(), line)?;
+                }
+                Opcode::SetFree => {
+                    let free_idx = instructions.code[ip + 1] as usize;
+                    self.current_frame().ip += 1;
+                    let curr_closure = self.current_frame().closure.clone();
+                    // Use the element on top of the stack for the assignment
+                    // but do not pop the element off the stack since the assigment
+                    // expression also evaluates to the value that is assigned
+                    curr_closure.free.borrow_mut()[free_idx] = self.top(0, line)?;
+                }
+                Opcode::CurrClosure => {
+                    let curr_closure = self.current_frame().closure.clone();
+                    // push the current closure on stack
+                    self.push(Rc::new(Object::Clos(curr_closure)), line)?;
+                }
+                Opcode::Not => {
+                    // Bitwise not (~)
+                    let obj = self.pop(line)?.clone();
+                    if let Object::Integer(n) = &*obj {
+                        self.push(Rc::new(Object::Integer(!n)), line)?;
+                    } else {
+                        return Err(RTError::new("bad operand type for unary '~'", line));
+                    }
+                }
+                Opcode::And => {
+                    self.bitwise_op(|a, b| a & b, line)?;
+                }
+                Opcode::Or => {
+                    self.bitwise_op(|a, b| a | b, line)?;
+                }
+                Opcode::Xor => {
+                    self.bitwise_op(|a, b| a ^ b, line)?;
+                }
+                Opcode::ShiftLeft => {
+                    self.bitwise_op(|a, b| a << b, line)?;
+                }
+                Opcode::ShiftRight => {
+                    self.bitwise_op(|a, b| a >> b, line)?;
+                }
+                Opcode::Dup => {
+                    // Duplicate the top most element on the stack
+                    let obj = self.peek(0);
+                    self.push(obj, line)?;
+                }
+                #[cfg(feature = "pcap")]
+                Opcode::GetProp => {
+                    // The property expression is the operand to this opcode.
+                    // The stack contains the packet expression
+                    let prop = instructions.code[ip + 1];
+                    let left = self.pop(line)?;
+                    let obj = self.exec_prop_expr(left, prop, None, line)?;
+                    self.push(obj, line)?;
+                    self.current_frame().ip += 1;
+                }
+                #[cfg(feature = "pcap")]
+                Opcode::SetProp => {
+                    let prop = instructions.code[ip + 1];
+                    let obj = self.pop(line)?.clone();
+                    let left = self.pop(line)?.clone();
+                    let obj = self.exec_prop_expr(left, prop, Some(obj), line)?;
+                    self.push(obj, line)?;
+                    self.current_frame().ip += 1;
+                }
+                // Properties belong to packets, which are not built without pcap
+                #[cfg(not(feature = "pcap"))]
+                Opcode::GetProp | Opcode::SetProp => {
+                    return Err(RTError::new(
+                        "packet properties need p2sh-core built with the 'pcap' feature",
+                        line,
+                    ));
+                }
+                Opcode::Dollar => {
+                    self.exec_dollar_expr(line)?;
+                }
+                Opcode::Iter => {
+                    let num_vars = instructions.code[ip + 1] as usize;
+                    self.current_frame().ip += 1;
+                    let iterable = self.pop(line)?;
+                    let items = self.iter_items(&iterable, num_vars, line)?;
+                    let state = vec![items, Rc::new(Object::Integer(0))];
+                    self.push(Rc::new(Object::Arr(Rc::new(Array::new(state)))), line)?;
+                }
+                Opcode::IterRange => {
+                    let inclusive = instructions.code[ip + 1] == 1;
+                    self.current_frame().ip += 1;
+                    let end = self.pop(line)?;
+                    let begin = self.pop(line)?;
+                    let (Object::Integer(begin), Object::Integer(end)) = (&*begin, &*end) else {
+                        return Err(RTError::new("Range bounds must be integers.", line));
+                    };
+                    // The state of a range is the end and the next value
+                    let end = if inclusive {
+                        end.saturating_add(1)
+                    } else {
+                        *end
+                    };
+                    let state = vec![
+                        Rc::new(Object::Integer(end)),
+                        Rc::new(Object::Integer(*begin)),
+                    ];
+                    self.push(Rc::new(Object::Arr(Rc::new(Array::new(state)))), line)?;
+                }
+                Opcode::IterNext => {
+                    let num_vars = instructions.code[ip + 1] as usize;
+                    let pos = BigEndian::read_u16(&instructions.code[ip + 2..ip + 4]) as usize;
+                    self.current_frame().ip += 3;
+                    if !self.iter_next(num_vars, line)? {
+                        self.current_frame().ip = pos;
+                        continue;
+                    }
+                }
+                Opcode::IterEnd => {
+                    self.pop(line)?;
+                }
+                Opcode::Try => {
+                    let pos = BigEndian::read_u16(&instructions.code[ip + 1..ip + 3]) as usize;
+                    self.current_frame().ip += 2;
+                    self.handlers.push(Handler {
+                        frames_index: self.frames_index,
+                        sp: self.sp,
+                        ip: pos,
+                    });
+                }
+                Opcode::EndTry => {
+                    self.handlers.pop();
+                }
+                Opcode::IncGlobal => {
+                    let globals_index = BigEndian::read_u16(&instructions.code[ip + 1..]) as usize;
+                    let decrement = instructions.code[ip + 3] == 1;
+                    self.current_frame().ip += 3;
+                    let value = self.globals[globals_index].clone();
+                    self.globals[globals_index] = self.increment(value, decrement, line)?;
+                }
+                Opcode::IncLocal => {
+                    let locals_index = instructions.code[ip + 1] as usize;
+                    let decrement = instructions.code[ip + 2] == 1;
+                    self.current_frame().ip += 2;
+                    let slot = self.current_frame().bp + locals_index;
+                    let value = self.stack[slot].clone();
+                    self.stack[slot] = self.increment(value, decrement, line)?;
+                }
+                Opcode::IncFree => {
+                    let free_idx = instructions.code[ip + 1] as usize;
+                    let decrement = instructions.code[ip + 2] == 1;
+                    self.current_frame().ip += 2;
+                    let curr_closure = self.current_frame().closure.clone();
+                    let value = curr_closure.free.borrow()[free_idx].clone();
+                    curr_closure.free.borrow_mut()[free_idx] =
+                        self.increment(value, decrement, line)?;
+                }
+                Opcode::IncIndex => {
+                    let decrement = instructions.code[ip + 1] == 1;
+                    self.current_frame().ip += 1;
+                    let index = self.pop(line)?;
+                    let left = self.pop(line)?;
+                    self.exec_index_expr(left.clone(), index.clone(), None, line)?;
+                    let value = self.pop(line)?;
+                    let value = self.increment(value, decrement, line)?;
+                    self.exec_index_expr(left, index, Some(value), line)?;
+                    self.pop(line)?;
+                }
+                Opcode::Invalid => {
+                    return Err(RTError::new(
+                        &format!("opcode {} undefined", op as u8),
+                        line,
+                    ))
+                }
+            }
+            self.current_frame().ip += 1;
+        }
+
+        Ok(())
+    }
+
+    // Drop the handlers of the 'try' blocks left by returning from a function
+    fn drop_handlers(&mut self) {
+        while matches!(self.handlers.last(), Some(h) if h.frames_index > self.frames_index) {
+            self.handlers.pop();
+        }
+    }
+
+    // Add one to a value, or subtract one from it, for 'x++' and 'x--'.
+    // Integers take a fast path while the other values are added to as
+    // 'x = x + 1' would, with the same errors.
+    fn increment(
+        &mut self,
+        value: Rc<Object>,
+        decrement: bool,
+        line: usize,
+    ) -> Result<Rc<Object>, RTError> {
+        if let Object::Integer(n) = *value {
+            let n = if decrement {
+                n.wrapping_sub(1)
+            } else {
+                n.wrapping_add(1)
+            };
+            return Ok(Rc::new(Object::Integer(n)));
+        }
+        self.push(value, line)?;
+        self.push(Rc::new(Object::Integer(1)), line)?;
+        if decrement {
+            self.binary_op(BinaryOperation::Sub, |a, b| a - b, line)?;
+        } else {
+            self.binary_op(BinaryOperation::Add, |a, b| a + b, line)?;
+        }
+        self.pop(line)
+    }
+
+    fn binary_op(
+        &mut self,
+        optype: BinaryOperation,
+        op: fn(a: &Object, b: &Object) -> Object,
+        line: usize,
+    ) -> Result<(), RTError> {
+        // pop right before left
+        let right = self.pop(line)?;
+        let left = self.pop(line)?;
+
+        match (&*left, &*right) {
+            (
+                Object::Integer(_) | Object::Float(_) | Object::Byte(_),
+                Object::Integer(_) | Object::Float(_) | Object::Byte(_),
+            ) => {
+                if matches!(optype, BinaryOperation::Div) && right.is_zero() {
+                    return Err(RTError::new("Division by zero.", line));
+                }
+                if matches!(optype, BinaryOperation::Mod) && right.is_zero() {
+                    return Err(RTError::new("Modulo by zero.", line));
+                }
+                self.push(Rc::new(op(&left, &right)), line)
+            }
+            (Object::Str(s1), Object::Str(s2)) => match optype {
+                BinaryOperation::Add => {
+                    self.push(Rc::new(Object::Str(format!("{}{}", s1, s2))), line)
+                }
+                BinaryOperation::Relational => self.push(Rc::new(op(&left, &right)), line),
+                _ => Err(RTError::new("Invalid operation on strings.", line)),
+            },
+            (Object::Char(c1), Object::Char(c2)) => match optype {
+                BinaryOperation::Add => {
+                    self.push(Rc::new(Object::Str(format!("{}{}", c1, c2))), line)
+                }
+                BinaryOperation::Relational => self.push(Rc::new(op(&left, &right)), line),
+                _ => Err(RTError::new("Invalid operation on chars.", line)),
+            },
+            (Object::Str(s), Object::Integer(n)) | (Object::Integer(n), Object::Str(s)) => {
+                if !matches!(optype, BinaryOperation::Mul) {
+                    return Err(RTError::new("Invalid operation on strings.", line));
+                }
+                // Repeating a string a negative number of times gives an
+                // empty string, as in python
+                let count = (*n).max(0) as usize;
+                if s.len()
+                    .checked_mul(count)
+                    .is_none_or(|n| n > MAX_STR_REPEAT)
+                {
+                    return Err(RTError::new("String repetition is too large.", line));
+                }
+                self.push(Rc::new(Object::Str(s.repeat(count))), line)
+            }
+            (Object::Arr(a), Object::Arr(b)) => {
+                if !matches!(optype, BinaryOperation::Add) {
+                    return Err(RTError::new("Invalid operation on arrays.", line));
+                }
+                let mut e1 = a.elements.borrow().clone();
+                let e2 = b.elements.borrow().clone();
+                e1.extend_from_slice(&e2);
+                self.push(Rc::new(Object::Arr(Rc::new(Array::new(e1)))), line)
+            }
+            // The difference between timestamps is a duration
+            (Object::Timestamp(a), Object::Timestamp(b)) => match optype {
+                BinaryOperation::Sub => match a.since(b) {
+                    Some(d) => self.push(Rc::new(Object::Duration(d)), line),
+                    None => Err(RTError::new("Duration out of range.", line)),
+                },
+                BinaryOperation::Relational => self.push(Rc::new(op(&left, &right)), line),
+                _ => Err(RTError::new("Invalid operation on timestamps.", line)),
+            },
+            // Adding a duration to a timestamp or subtracting it from it
+            (Object::Timestamp(ts), Object::Duration(d))
+            | (Object::Duration(d), Object::Timestamp(ts)) => {
+                let later = match optype {
+                    BinaryOperation::Add => ts.add(d),
+                    BinaryOperation::Sub if left.is_timestamp() => {
+                        d.checked_neg().and_then(|d| ts.add(&d))
+                    }
+                    _ => return Err(RTError::new("Invalid operation on timestamps.", line)),
+                };
+                match later {
+                    Some(ts) => self.push(Rc::new(Object::Timestamp(ts)), line),
+                    None => Err(RTError::new("Timestamp out of range.", line)),
+                }
+            }
+            // Adding seconds to a timestamp or subtracting them from it
+            (Object::Timestamp(ts), secs @ (Object::Integer(_) | Object::Float(_)))
+            | (secs @ (Object::Integer(_) | Object::Float(_)), Object::Timestamp(ts)) => {
+                let later = match (optype, secs) {
+                    (BinaryOperation::Add, Object::Integer(n)) => ts.add_secs(*n),
+                    (BinaryOperation::Add, Object::Float(f)) => ts.add_secs_f64(*f),
+                    (BinaryOperation::Sub, Object::Integer(n)) if left.is_timestamp() => {
+                        n.checked_neg().and_then(|n| ts.add_secs(n))
+                    }
+                    (BinaryOperation::Sub, Object::Float(f)) if left.is_timestamp() => {
+                        ts.add_secs_f64(-f)
+                    }
+                    _ => return Err(RTError::new("Invalid operation on timestamps.", line)),
+                };
+                match later {
+                    Some(ts) => self.push(Rc::new(Object::Timestamp(ts)), line),
+                    None => Err(RTError::new("Timestamp out of range.", line)),
+                }
+            }
+            (Object::Duration(a), Object::Duration(b)) => {
+                let d = match optype {
+                    BinaryOperation::Add => a.checked_add(b),
+                    BinaryOperation::Sub => a.checked_sub(b),
+                    // The ratio of two durations is a number
+                    BinaryOperation::Div if b.nanos() == 0 => {
+                        return Err(RTError::new("Division by zero.", line));
+                    }
+                    BinaryOperation::Div => {
+                        let ratio = a.nanos() as f64 / b.nanos() as f64;
+                        return self.push(Rc::new(Object::Float(ratio)), line);
+                    }
+                    BinaryOperation::Relational => {
+                        return self.push(Rc::new(op(&left, &right)), line);
+                    }
+                    _ => return Err(RTError::new("Invalid operation on durations.", line)),
+                };
+                match d {
+                    Some(d) => self.push(Rc::new(Object::Duration(d)), line),
+                    None => Err(RTError::new("Duration out of range.", line)),
+                }
+            }
+            // Numbers are seconds when added to or subtracted from durations,
+            // and factors when durations are scaled by them
+            (Object::Duration(d), n @ (Object::Integer(_) | Object::Float(_)))
+            | (n @ (Object::Integer(_) | Object::Float(_)), Object::Duration(d)) => {
+                let secs = match n {
+                    Object::Integer(n) => *n as f64,
+                    Object::Float(f) => *f,
+                    _ => unreachable!(),
+                };
+                let d = match optype {
+                    BinaryOperation::Relational => {
+                        return self.push(Rc::new(op(&left, &right)), line);
+                    }
+                    BinaryOperation::Add => {
+                        Duration::from_secs_f64(secs).and_then(|s| d.checked_add(&s))
+                    }
+                    BinaryOperation::Sub if left.is_duration() => {
+                        Duration::from_secs_f64(secs).and_then(|s| d.checked_sub(&s))
+                    }
+                    BinaryOperation::Sub => {
+                        Duration::from_secs_f64(secs).and_then(|s| s.checked_sub(d))
+                    }
+                    BinaryOperation::Mul => match n {
+                        Object::Integer(n) => d.nanos().checked_mul(*n).map(Duration::from_nanos),
+                        _ => d.mul_f64(secs),
+                    },
+                    BinaryOperation::Div if left.is_duration() => {
+                        if right.is_zero() {
+                            return Err(RTError::new("Division by zero.", line));
+                        }
+                        match n {
+                            Object::Integer(n) => {
+                                d.nanos().checked_div(*n).map(Duration::from_nanos)
+                            }
+                            _ => Duration::from_secs_f64(d.as_secs_f64() / secs),
+                        }
+                    }
+                    _ => return Err(RTError::new("Invalid operation on durations.", line)),
+                };
+                match d {
+                    Some(d) => self.push(Rc::new(Object::Duration(d)), line),
+                    None => Err(RTError::new("Duration out of range.", line)),
+                }
+            }
+            _ => Err(RTError::new("Invalid binary operation.", line)),
+        }
+    }
+
+    // Items that a 'for' loop iterates over. With two loop variables, each
+    // item is a pair of the index or the key, and the value. Maps are
+    // iterated over in the order of their keys. Files are iterated over
+    // a line at a time.
+    fn iter_items(
+        &self,
+        iterable: &Rc<Object>,
+        num_vars: usize,
+        line: usize,
+    ) -> Result<Rc<Object>, RTError> {
+        let values: Vec<Rc<Object>> = match iterable.as_ref() {
+            Object::Arr(_) | Object::Str(_) | Object::Bytes(_) => {
+                iterable.seq_elements().unwrap_or_default()
+            }
+            #[cfg(feature = "pcap")]
+            Object::Packet(_) => iterable.seq_elements().unwrap_or_default(),
+            // The elements and keys are copied as they are for a map key,
+            // so that changing them does not change the hash of the key
+            Object::Set(set) => set
+                .sorted_elements()
+                .iter()
+                .map(|e| e.deep_copy())
+                .collect(),
+            // Lines are read from a file as the iteration advances
+            Object::File(f) if matches!(f.as_ref(), FileHandle::Reader(_) | FileHandle::Stdin) => {
+                return Ok(iterable.clone());
+            }
+            Object::Map(map) => {
+                let items = map
+                    .sorted_pairs()
+                    .into_iter()
+                    .map(|(k, v)| {
+                        let k = k.deep_copy();
+                        if num_vars == 1 {
+                            k
+                        } else {
+                            Rc::new(Object::Arr(Rc::new(Array::new(vec![k, v]))))
+                        }
+                    })
+                    .collect();
+                return Ok(Rc::new(Object::Arr(Rc::new(Array::new(items)))));
+            }
+            _ => {
+                return Err(RTError::new(
+                    &format!("Cannot iterate over {}.", iterable),
+                    line,
+                ))
+            }
+        };
+        let items = if num_vars == 1 {
+            values
+        } else {
+            values
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    let pair = vec![Rc::new(Object::Integer(i as i64)), v];
+                    Rc::new(Object::Arr(Rc::new(Array::new(pair))))
+                })
+                .collect()
+        };
+        Ok(Rc::new(Object::Arr(Rc::new(Array::new(items)))))
+    }
+
+    // Advance the iteration whose state is on top of the stack and push the
+    // values of the loop variables. Returns false when the iteration is done.
+    fn iter_next(&mut self, num_vars: usize, line: usize) -> Result<bool, RTError> {
+        let state = self.peek(0);
+        let Object::Arr(state) = state.as_ref() else {
+            return Err(RTError::new("Invalid iteration state.", line));
+        };
+        let (items, next) = (state.get(0), state.get(1));
+        let Object::Integer(next) = *next else {
+            return Err(RTError::new("Invalid iteration state.", line));
+        };
+        match items.as_ref() {
+            // A range
+            Object::Integer(end) => {
+                if next >= *end {
+                    return Ok(false);
+                }
+                state.set(1, Rc::new(Object::Integer(next + 1)));
+                self.push(Rc::new(Object::Integer(next)), line)?;
+            }
+            Object::File(f) => {
+                let Some(text) = f
+                    .next_line()
+                    .map_err(|e| RTError::new(&format!("Failed to read line: {}.", e), line))?
+                else {
+                    return Ok(false);
+                };
+                state.set(1, Rc::new(Object::Integer(next + 1)));
+                if num_vars == 2 {
+                    self.push(Rc::new(Object::Integer(next)), line)?;
+                }
+                self.push(Rc::new(Object::Str(text)), line)?;
+            }
+            Object::Arr(items) => {
+                if next as usize >= items.len() {
+                    return Ok(false);
+                }
+                state.set(1, Rc::new(Object::Integer(next + 1)));
+                let item = items.get(next as usize);
+                match (num_vars, item.as_ref()) {
+                    (2, Object::Arr(pair)) => {
+                        self.push(pair.get(0), line)?;
+                        self.push(pair.get(1), line)?;
+                    }
+                    _ => self.push(item, line)?,
+                }
+            }
+            _ => return Err(RTError::new("Invalid iteration state.", line)),
+        }
+        Ok(true)
+    }
+
+    fn bitwise_op(
+        &mut self,
+        op: fn(a: &Object, b: &Object) -> Object,
+        line: usize,
+    ) -> Result<(), RTError> {
+        // pop right before left
+        let right = self.pop(line)?;
+        let left = self.pop(line)?;
+
+        match (&*left, &*right) {
+            (Object::Integer(_), Object::Integer(_)) => {
+                self.push(Rc::new(op(&left, &right)), line)?;
+                Ok(())
+            }
+            _ => Err(RTError::new("Invalid bitwise operation.", line)),
+        }
+    }
+
+    // Build array from elements on stack
+    fn build_array(&self, start_index: usize, end_index: usize) -> Vec<Rc<Object>> {
+        let mut elements = Vec::with_capacity(end_index - start_index);
+        for i in start_index..end_index {
+            elements.push(self.stack[i].clone());
+        }
+        elements
+    }
+
+    // Build map from objects on stack
+    #[allow(clippy::mutable_key_type)]
+    fn build_map(
+        &self,
+        start_index: usize,
+        end_index: usize,
+        line: usize,
+    ) -> Result<HMap, RTError> {
+        let mut elements = HashMap::with_capacity(end_index - start_index);
+        for i in (start_index..end_index).step_by(2) {
+            let key = self.stack[i].clone();
+            if !key.is_a_valid_key() {
+                return Err(RTError::new(
+                    &format!("KeyError: not a valid key: {}.", key),
+                    line,
+                ));
+            }
+            let val = self.stack[i + 1].clone();
+            // Copy the array keys so that they cannot be modified later
+            elements.insert(key.deep_copy(), val);
+        }
+        Ok(HMap::new(elements))
+    }
+
+    // Common code for indexing into sequences and maps
+    fn exec_index_expr(
+        &mut self,
+        left: Rc<Object>,
+        index: Rc<Object>,
+        setval: Option<Rc<Object>>,
+        line: usize,
+    ) -> Result<(), RTError> {
+        let obj = match (&*left, &*index) {
+            (Object::Map(map), _) => self.exec_hash_index(map, &index, setval, line),
+            (seq, Object::Integer(idx)) => match seq.seq_name() {
+                Some(name) => self.exec_seq_index(seq, name, *idx, setval, line),
+                None => Err(RTError::new("IndexError: unsupported operation.", line)),
+            },
+            _ => Err(RTError::new("IndexError: unsupported operation.", line)),
+        };
+        // Push the value onto the stack so it is available to
+        // the expression statement that uses the index expression
+        self.push(obj?, line)
+    }
+
+    // Index into an array, a string, bytes or a packet. Only the elements
+    // of arrays can be modified; bytes are shared with the packets.
+    fn exec_seq_index(
+        &mut self,
+        seq: &Object,
+        name: &str,
+        idx: i64,
+        setval: Option<Rc<Object>>,
+        line: usize,
+    ) -> Result<Rc<Object>, RTError> {
+        let out_of_range =
+            || RTError::new(&format!("IndexError: {} index out of range.", name), line);
+        if let Some(obj) = setval {
+            let Object::Arr(arr) = seq else {
+                let msg = format!("IndexError: {} cannot be modified.", name);
+                return Err(RTError::new(&msg, line));
+            };
+            if idx < 0 {
+                return Err(RTError::new("IndexError: index cannot be negative.", line));
+            } else if idx as usize >= arr.len() {
+                return Err(out_of_range());
+            }
+            // return the value being 'set'
+            arr.set(idx as usize, obj.clone());
+            return Ok(obj);
+        }
+        if idx < 0 {
+            return Err(RTError::new("IndexError: index cannot be negative.", line));
+        }
+        seq.seq_get(idx as usize).ok_or_else(out_of_range)
+    }
+
+    fn exec_hash_index(
+        &mut self,
+        map: &HMap,
+        key: &Rc<Object>,
+        setval: Option<Rc<Object>>,
+        line: usize,
+    ) -> Result<Rc<Object>, RTError> {
+        if !key.is_a_valid_key() {
+            return Err(RTError::new(
+                &format!("KeyError: not a valid key: {}.", key),
+                line,
+            ));
+        }
+        // If it is a SetIndex operation, then set the value at the index
+        // SetIndex operation for a map does ntot require that the key
+        // if present in the map already.
+        let obj = if let Some(obj) = setval {
+            map.insert(key.clone(), obj.clone());
+            obj
+        } else {
+            let obj = map.get_or_default(key);
+            if obj.is_null() {
+                return Err(RTError::new("KeyError: key not found.", line));
+            }
+            obj
+        };
+        // return the value being 'set' or 'get'
+        Ok(obj)
+    }
+
+    fn exec_call(&mut self, num_args: usize, line: usize) -> Result<(), RTError> {
+        // Calculate the location of the function on the stack by decoding
+        // the operand, 'num_args', and subtracting it from 'sp'. The additional
+        // '-1' is there because 'sp' points to the next free slot on the stack.
+        let callee = self.stack[self.stack_base(num_args + 1, line)?].clone();
+
+        match &*callee {
+            Object::Clos(closure) => {
+                self.call_func(closure, num_args, line)?;
+            }
+            Object::Builtin(builtin) => {
+                self.call_builtin(builtin, num_args, line)?;
+            }
+            _ => {
+                return Err(RTError::new("calling non-function", line));
+            }
+        }
+        Ok(())
+    }
+
+    // The stack during the execution of a function call
+    // looks like the following:
+    //                                  <<------ sp
+    //       <local var 2>              <<------ bp + 4
+    //       <local var 1>              <<------ bp + 3
+    //       <arg 2>                    <<------ bp + 2
+    //       <arg 1>                    <<------ bp + 1
+    //       <compiled-function>        <<------ bp
+    fn call_func(
+        &mut self,
+        closure: &Rc<Closure>,
+        num_args: usize,
+        line: usize,
+    ) -> Result<(), RTError> {
+        // Make sure that the right number of arguments is sitting on the stack
+        if num_args != closure.func.num_params {
+            return Err(RTError::new(
+                &format!(
+                    "wrong number of arguments: want={}, got={}",
+                    closure.func.num_params, num_args
+                ),
+                line,
+            ));
+        }
+
+        // Save the current stack pointer before calling a function
+        // The base pointer 'bp' is further down the stack and points to
+        // the first argument to the function.
+        let bp = self.sp - num_args;
+        let frame = Frame::new_call(closure.clone(), bp, line);
+
+        // Allocate space for local bindings on stack starting at the base
+        // pointer 'bp' with 'num_locals' slots on the stack. Note that the
+        // parameters to the function are also part of the local bindings,
+        // i.e. 'num_locals' is the sum of #locals and #arguments
+        // In the example above, num_locals = args(2) + locals(2) = 4.
+        self.sp = frame.bp + closure.func.num_locals;
+
+        // skip over the instruction and the 1-byte operand to OpCall 'before'
+        // pushing a new frame so that the callee's frame is not meddled with
+        self.current_frame().ip += 2;
+        self.push_frame(frame);
+        Ok(())
+    }
+
+    fn call_builtin(
+        &mut self,
+        builtin: &BuiltinFunction,
+        num_args: usize,
+        line: usize,
+    ) -> Result<(), RTError> {
+        // copy arguments from the stack into a vector
+        let args = self.stack[self.sp - num_args..self.sp].to_vec();
+        let traced = self.trace_builtins.then(|| args.clone());
+        let trace = |result| {
+            if let Some(args) = &traced {
+                eprintln!("{}", trace_builtin(builtin.name, args, result, line));
+            }
+        };
+        let builtin_func = builtin.func;
+        let result = if builtin.name == "puts" && self.output_format == OutputFormat::Csv {
+            match write_stdout(&csv::args_to_csv_row(&args)) {
+                Ok(()) => Ok(Rc::new(Object::Null)),
+                Err(e) => Ok(Rc::new(Object::Err(ErrorObj::IO(e)))),
+            }
+        } else if let Some(rng) = self.rng.as_mut().filter(|_| builtin.name == "rand") {
+            random(rng, &args)
+        } else if self.rng.is_some() && builtin.name == "time" {
+            // The clock stands still at the epoch if deterministic
+            builtin_func(args).map(|_| Rc::new(Object::Integer(0)))
+        } else {
+            builtin_func(args)
+        };
+        match result {
+            Ok(obj) => {
+                // pop the arguments and the function
+                self.sp = self.sp - num_args - 1;
+                // Builtins that act on the state of the VM
+                match (builtin.name, obj.as_ref()) {
+                    ("exit", Object::Integer(code)) => {
+                        trace(TraceResult::Exit);
+                        return Err(RTError::exit(*code as i32, line));
+                    }
+                    ("print" | "println" | "printf" | "puts", Object::Err(ErrorObj::IO(e)))
+                        if e.kind() == io::ErrorKind::BrokenPipe =>
+                    {
+                        trace(TraceResult::Exit);
+                        return Err(RTError::broken_pipe(line));
+                    }
+                    ("atexit", Object::Clos(closure)) => {
+                        self.exit_handlers.push(closure.clone());
+                        self.push(Rc::new(Object::Null), line)?;
+                    }
+                    ("register_filter", Object::Arr(arr)) => {
+                        self.register_filter(arr, line)?;
+                        self.push(Rc::new(Object::Null), line)?;
+                    }
+                    ("testdata", Object::Str(path)) => {
+                        self.testdata = Some(path.clone());
+                        self.push(Rc::new(Object::Null), line)?;
+                    }
+                    #[cfg(feature = "pcap")]
+                    ("decode_as", Object::Arr(arr)) => {
+                        self.apply_decode_as(arr, line)?;
+                        self.push(Rc::new(Object::Null), line)?;
+                    }
+                    ("group_by", Object::Arr(arr)) => {
+                        let groups = self.group_by(arr, line)?;
+                        self.push(groups, line)?;
+                    }
+                    #[cfg(feature = "pcap")]
+                    ("props", Object::Arr(arr)) => {
+                        let props = self.packet_props(&arr.get(0), &arr.get(1), line)?;
+                        self.push(props, line)?;
+                    }
+                    #[cfg(feature = "pcap")]
+                    ("to_map", Object::Arr(arr)) => {
+                        let map = self.packet_map(&arr.get(0), line)?;
+                        self.push(map, line)?;
+                    }
+                    #[cfg(feature = "pcap")]
+                    ("decode", Object::Arr(arr)) => {
+                        let mut layers = self.packet_layers(&arr.get(0), line)?;
+                        if let Object::Str(f) = arr.get(1).as_ref() {
+                            if f == "json" {
+                                let json = json::to_json(&layers)
+                                    .map_err(|e| RTError::new(&format!("decode: {}", e), line))?;
+                                layers = Rc::new(Object::Str(json));
+                            }
+                        }
+                        self.push(layers, line)?;
+                    }
+                    ("resolve", Object::Arr(arr)) => {
+                        let name = self.resolve(&arr.get(0));
+                        self.push(name, line)?;
+                    }
+                    ("service_name", Object::Arr(arr)) => {
+                        let name = self.service_name(&arr.get(0), &arr.get(1));
+                        self.push(name, line)?;
+                    }
+                    #[cfg(feature = "pcap")]
+                    ("flow", _) => {
+                        let flow = self.flow(line)?;
+                        self.push(flow, line)?;
+                    }
+                    #[cfg(feature = "pcap")]
+                    ("flows", _) => {
+                        let flows = self.flows();
+                        self.push(flows, line)?;
+                    }
+                    #[cfg(feature = "pcap")]
+                    ("flow_timeout", Object::Arr(arr)) => {
+                        self.set_flow_timeout(arr);
+                        self.push(Rc::new(Object::Null), line)?;
+                    }
+                    ("ratelimit", Object::Arr(arr)) => {
+                        let allowed = self.ratelimit(&arr.get(0), &arr.get(1), &arr.get(2));
+                        self.push(Rc::new(Object::Bool(allowed)), line)?;
+                    }
+                    ("rate_limit", Object::Arr(arr)) => {
+                        let allowed = self.rate_limit(&arr.get(0), &arr.get(1), &arr.get(2));
+                        self.push(Rc::new(Object::Bool(allowed)), line)?;
+                    }
+                    _ => self.push(obj, line)?,
+                }
+                // The value pushed is the result of the builtins that
+                // act on the state of the VM
+                trace(TraceResult::Value(&self.stack[self.sp - 1]));
+            }
+            Err(s) => {
+                trace(TraceResult::Error(&s));
+                // Prefix error messaage with the function name
+                let msg = format!("{}: {}", builtin.name, s);
+                return Err(RTError::new(&msg, 1));
+            }
+        }
+        self.current_frame().ip += 2;
+        Ok(())
+    }
+
+    // const_idx: Index of the compiled function in the constant pool
+    // num_free: number of free variables waiting on the stack
+    fn push_closure(
+        &mut self,
+        const_idx: usize,
+        num_free: usize,
+        line: usize,
+    ) -> Result<(), RTError> {
+        let constant = self.constants[const_idx].clone();
+        if let Object::Func(function) = constant.as_ref() {
+            let base = self.stack_base(num_free, line)?;
+            let mut free = Vec::with_capacity(num_free);
+
+            // Take each free variable from stack and copy it to 'free'
+            // copy in the same order they are referenced in GetFree
+            for i in 0..num_free {
+                let idx = base + i;
+                free.push(self.stack[idx].clone());
+            }
+            // cleanup stack of free variables
+            self.sp -= num_free;
+
+            let closure = Rc::new(Closure::new(function.clone(), free));
+            self.push(Rc::new(Object::Clos(closure)), line)
+        } else {
+            Err(RTError::new(
+                &format!("not a function: {:?}", constant),
+                line,
+            ))
+        }
+    }
+
+    /// Push a frame used to run the filter statement onto the stack.
+    /// The frame expects a closure, so convert the compiled function into one.
+    /// A closure here is simply a compiled function with a list of free variables.
+    /// There are no free variables in a function that wraps a filter statement.
+    /// num_locals represents the local variables used in the filter statement.
+    /// Save the stack pointer (sp) in the frame so that it can be restored later.
+    pub fn push_filter_frame(&mut self, filter: &Rc<CompiledFunction>) -> Result<(), RTError> {
+        let bp = self.sp;
+        let num_locals = filter.num_locals;
+        let closure: Rc<Closure> = Rc::new(Closure::new(filter.clone(), Vec::new()));
+        let frame = Frame::new(closure.clone(), bp);
+        self.sp = frame.bp + num_locals;
+        self.push_frame(frame);
+        Ok(())
+    }
+
+    /// Pop the frame used to run the filter statement from the stack.
+    /// This is done after the filter statement has been executed.
+    /// Also restore the stack by popping the local bindings.
+    pub fn pop_filter_frame(&mut self) -> Result<bool, RTError> {
+        let line = self.current_frame().closure.func.line;
+        let obj = self.pop_filter_value()?;
+        if let Object::Bool(b) = &*obj {
+            Ok(*b)
+        } else {
+            Err(RTError::new(
+                "filter expression must evaluate to a boolean",
+                line,
+            ))
+        }
+    }
+
+    /// Pop the frame used to run the filter statement and return its result.
+    /// The result is either pushed as the result of evaluating the pattern or
+    /// by the body of the custom action statement. The custom action statement
+    /// has a false statement at the end.
+    pub fn pop_filter_value(&mut self) -> Result<Rc<Object>, RTError> {
+        let frame = self.pop_frame();
+        let line = frame.closure.func.line;
+        let obj = self.pop(line)?;
+        // Reset stack frame by popping the local bindings
+        self.sp = frame.bp;
+        Ok(obj)
+    }
+
+    /// Set the current packet and the builtin variables
+    #[cfg(feature = "pcap")]
+    pub fn set_curr_pkt(&self, pkt: Rc<PcapPacket>) {
+        self.update_builtin_var(BuiltinVarType::PL, pkt.get_caplen());
+        self.update_builtin_var(BuiltinVarType::WL, pkt.get_wirelen());
+        self.update_builtin_var(BuiltinVarType::Tss, pkt.get_ts_sec());
+        self.update_builtin_var(BuiltinVarType::Tsu, pkt.get_ts_usec());
+        let ts = Object::Timestamp(pkt.timestamp());
+        self.update_builtin_var(BuiltinVarType::Ts, Rc::new(ts));
+        let obj = Object::Packet(pkt);
+        self.curr_pkt.borrow_mut().replace(Rc::new(obj));
+    }
+
+    /// Evaluate expressions such as $0, $n etc
+    /// The top of the stack contains the index of the dollar expression
+    /// The stack is popped and the result of the dollar expression is pushed
+    /// back onto the stack.
+    #[cfg(feature = "pcap")]
+    fn exec_dollar_expr(&mut self, line: usize) -> Result<(), RTError> {
+        // The stack contains the index of the dollar expression
+        let obj = self.pop(line)?;
+        let depth = match obj.as_ref() {
+            Object::Integer(n) => *n as usize,
+            _ => return Err(RTError::new("IndexError: invalid index.", line)),
+        };
+
+        let curr = self.curr_pkt.borrow().as_ref().cloned();
+        let obj = if let Some(obj) = curr {
+            if depth > MAX_PROTO_DEPTH {
+                return Err(RTError::new(
+                    &format!("IndexError: exceeded max protocol depth - '${}'", depth),
+                    line,
+                ));
+            }
+            self.get_inner(&obj, depth, line)?
+        } else {
+            Rc::new(Object::Null)
+        };
+        self.push(obj, line)?;
+        Ok(())
+    }
+
+    /// Take the functions registered using 'atexit' so that they
+    /// can be carried over to another VM instance
+    /// There is no current packet without pcap, so that the dollar
+    /// expressions are null
+    #[cfg(not(feature = "pcap"))]
+    fn exec_dollar_expr(&mut self, line: usize) -> Result<(), RTError> {
+        self.pop(line)?;
+        self.push(Rc::new(Object::Null), line)
+    }
+
+    pub fn take_exit_handlers(&mut self) -> Vec<Rc<Closure>> {
+        std::mem::take(&mut self.exit_handlers)
+    }
+
+    pub fn set_exit_handlers(&mut self, handlers: Vec<Rc<Closure>>) {
+        self.exit_handlers = handlers;
+    }
+
+    /// Run the functions registered using 'atexit' in the reverse order of
+    /// their registration. Errors are displayed and the remaining functions
+    /// still run. Return the exit code if any of the functions called exit.
+    pub fn run_exit_handlers(&mut self) -> Option<i32> {
+        let mut exit = None;
+        while let Some(closure) = self.exit_handlers.pop() {
+            if let Err(err) = self.call_closure(closure) {
+                match err.exit {
+                    Some(code) => exit = Some(code),
+                    None => eprintln!("{}", err),
+                }
+            }
+        }
+        exit
+    }
+
+    // The builtin returns the pattern and the action as an array
+    fn register_filter(&mut self, arr: &Array, line: usize) -> Result<(), RTError> {
+        if self.filters_sealed {
+            return Err(RTError::new(
+                "register_filter: filters cannot be registered while processing packets",
+                line,
+            ));
+        }
+        let elements = arr.elements.borrow();
+        let closure = |obj: Option<&Rc<Object>>| match obj.map(|o| o.as_ref()) {
+            Some(Object::Clos(c)) => Some(c.clone()),
+            _ => None,
+        };
+        if let Some(pattern) = closure(elements.first()) {
+            let action = closure(elements.get(1));
+            self.registered_filters
+                .push(RegisteredFilter { pattern, action });
+        }
+        Ok(())
+    }
+
+    /// Decode the payload on a port as a protocol. The builtin 'decode_as'
+    /// validates the arguments and returns them as an array.
+    #[cfg(feature = "pcap")]
+    fn apply_decode_as(&mut self, arr: &Array, line: usize) -> Result<(), RTError> {
+        match (arr.get(0).as_ref(), arr.get(1).as_ref()) {
+            (Object::Integer(port), Object::Str(proto)) => {
+                let proto = parse_app_proto(proto)
+                    .map_err(|e| RTError::new(&format!("decode_as: {}", e), line))?;
+                self.decode_as(*port as u16, proto);
+                Ok(())
+            }
+            _ => Err(RTError::new("decode_as: unsupported argument", line)),
+        }
+    }
+
+    /// Decode the payload on a port as a protocol, or stop decoding it
+    #[cfg(feature = "pcap")]
+    pub fn decode_as(&mut self, port: u16, proto: Option<AppProto>) {
+        self.port_map.decode_as(port, proto);
+    }
+
+    /// Detect the protocol of the payload on ports that are not mapped
+    #[cfg(feature = "pcap")]
+    pub fn set_heuristics(&mut self, enabled: bool) {
+        self.port_map.set_heuristics(enabled);
+    }
+
+    /// Protocols decoded from the payload on each port
+    #[cfg(feature = "pcap")]
+    pub fn port_map(&self) -> &PortMap {
+        &self.port_map
+    }
+
+    #[cfg(feature = "pcap")]
+    pub fn set_port_map(&mut self, port_map: PortMap) {
+        self.port_map = port_map;
+    }
+
+    /// Have the packet properties return addresses as strings and
+    /// payloads as arrays of bytes instead of the typed objects
+    pub fn set_legacy_props(&mut self, enabled: bool) {
+        self.legacy_props = enabled;
+    }
+
+    pub fn legacy_props(&self) -> bool {
+        self.legacy_props
+    }
+
+    /// Log every call to a builtin function with its arguments and its
+    /// result to stderr
+    pub fn set_trace_builtins(&mut self, enabled: bool) {
+        self.trace_builtins = enabled;
+    }
+
+    /// Pause the run at the breakpoints set using the debugger, starting
+    /// with the first line
+    pub fn set_debugger(&mut self, debugger: Debugger) {
+        self.debugger = Some(Box::new(debugger));
+    }
+
+    // Let the debugger pause before the instruction at 'ip' if it is the
+    // first one run on a line
+    fn debug(&mut self, line: usize, ip: usize) -> Result<(), RTError> {
+        let Some(mut debugger) = self.debugger.take() else {
+            return Ok(());
+        };
+        let mut result = Ok(());
+        if debugger.should_pause(line, ip, self.frames_index) {
+            let frame = &self.frames[self.frames_index - 1];
+            let num_locals = frame.closure.func.num_locals;
+            let locals_end = (frame.bp + num_locals).min(self.sp);
+            let frames = self.frames[..self.frames_index]
+                .iter()
+                .rev()
+                .map(|f| {
+                    let lines = &f.instructions().lines;
+                    lines.get(f.ip).or(lines.last()).copied().unwrap_or(0)
+                })
+                .collect();
+            let paused = Paused {
+                line,
+                globals: &self.globals,
+                locals: &self.stack[frame.bp.min(locals_end)..locals_end],
+                stack: &self.stack[..self.sp],
+                frames,
+            };
+            result = debugger.pause(&paused);
+        }
+        self.debugger = Some(debugger);
+        result
+    }
+
+    /// Print the arguments of 'puts' as a row of comma separated values
+    /// if the format is csv
+    pub fn set_output_format(&mut self, format: OutputFormat) {
+        self.output_format = format;
+    }
+
+    /// Make the output of the runs reproducible: 'rand' draws from a
+    /// generator seeded with 'seed' and 'time' always returns 0
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Some(StdRng::seed_from_u64(seed));
+    }
+
+    /// Look up the names of addresses in the hosts file only
+    pub fn set_no_dns(&mut self, no_dns: bool) {
+        self.resolver.set_no_dns(no_dns);
+    }
+
+    pub fn resolver_mut(&mut self) -> &mut Resolver {
+        &mut self.resolver
+    }
+
+    /// Time the dissectors parsing the layers of packets
+    #[cfg(feature = "pcap")]
+    pub fn enable_dissect_profile(&mut self) {
+        self.dissect_profile
+            .get_or_insert_with(DissectProfile::default);
+    }
+
+    /// Time spent by each dissector if profiling is enabled
+    #[cfg(feature = "pcap")]
+    pub fn dissect_profile(&self) -> Option<&DissectProfile> {
+        self.dissect_profile.as_ref()
+    }
+
+    /// Live objects reachable from the globals, with the largest
+    /// containers and the lines where their globals are defined
+    pub fn object_report(&self) -> ObjectReport {
+        let lines = self.frames[0].instructions().global_lines();
+        let globals: Vec<(String, Option<usize>, Rc<Object>)> = self
+            .globals
+            .iter()
+            .enumerate()
+            .filter(|(_, obj)| !matches!(obj.as_ref(), Object::Null))
+            .map(|(idx, obj)| {
+                let name = match self.global_names.get(idx) {
+                    Some(name) => name.clone(),
+                    None => format!("global{}", idx),
+                };
+                (name, lines.get(&idx).copied(), obj.clone())
+            })
+            .collect();
+        ObjectReport::new(&globals)
+    }
+
+    /// Run a dissector, timing it if profiling is enabled
+    #[cfg(feature = "pcap")]
+    pub(crate) fn dissect<T>(&self, name: &'static str, dissect: impl FnOnce() -> T) -> T {
+        match &self.dissect_profile {
+            Some(profile) => profile.time(name, dissect),
+            None => dissect(),
+        }
+    }
+
+    /// The pcap file declared using 'testdata' to read the packets from
+    pub fn testdata(&self) -> Option<String> {
+        self.testdata.clone()
+    }
+
+    pub fn has_registered_filters(&self) -> bool {
+        !self.registered_filters.is_empty()
+    }
+
+    /// Take the filters registered using 'register_filter' before processing
+    /// packets. Filters cannot be registered after this.
+    pub fn take_registered_filters(&mut self) -> Vec<RegisteredFilter> {
+        self.filters_sealed = true;
+        std::mem::take(&mut self.registered_filters)
+    }
+
+    /// Run a registered filter on the current packet. Return true if the
+    /// pattern matched and there is no action, i.e. the packet is to be
+    /// written to the output pcap stream.
+    pub fn run_registered_filter(&mut self, filter: &RegisteredFilter) -> Result<bool, RTError> {
+        let line = filter.pattern.func.line;
+        let matched = match self.call_closure(filter.pattern.clone())?.as_ref() {
+            Object::Bool(b) => *b,
+            _ => {
+                return Err(RTError::new(
+                    "filter pattern must evaluate to a boolean",
+                    line,
+                ))
+            }
+        };
+        match &filter.action {
+            Some(action) if matched => {
+                self.call_closure(action.clone())?;
+                Ok(false)
+            }
+            _ => Ok(matched),
+        }
+    }
+
+    // The builtin returns the array and the key function as an array.
+    // Group the elements of the array into a map keyed by the value
+    // returned by the function for each element.
+    fn group_by(&mut self, arr: &Array, line: usize) -> Result<Rc<Object>, RTError> {
+        let elements = arr.elements.borrow().clone();
+        let (items, func) = match (elements.first(), elements.get(1)) {
+            (Some(items), Some(func)) => (items.as_ref(), func.as_ref()),
+            _ => return Err(RTError::new("group_by: unsupported argument", line)),
+        };
+        let (Object::Arr(items), Object::Clos(func)) = (items, func) else {
+            return Err(RTError::new("group_by: unsupported argument", line));
+        };
+        let groups = HMap::default();
+        let items = items.elements.borrow().clone();
+        for item in items {
+            let key = self.call_function(func, std::slice::from_ref(&item), line)?;
+            if !key.is_a_valid_key() {
+                return Err(RTError::new(
+                    &format!("group_by: not a valid key: {}", key),
+                    line,
+                ));
+            }
+            let group = groups.pairs.borrow().get(&key).cloned();
+            match group.as_deref() {
+                Some(Object::Arr(group)) => group.push(item),
+                _ => {
+                    let group = Array::new(vec![item]);
+                    groups.insert(key, Rc::new(Object::Arr(Rc::new(group))));
+                }
+            }
+        }
+        Ok(Rc::new(Object::Map(Rc::new(groups))))
+    }
+
+    /// Return the record of the flow of the current packet, counting the
+    /// packet in it, or null if the packet is not an ip packet. The flow is
+    /// identified by the protocol and the addresses of the outermost ip
+    /// layer, and the ports of the udp or tcp layer within it, if any.
+    /// The idle flows are expired first.
+    #[cfg(feature = "pcap")]
+    pub fn flow(&mut self, line: usize) -> Result<Rc<Object>, RTError> {
+        let curr = self.curr_pkt.borrow().as_ref().cloned();
+        let Some(mut layer) = curr else {
+            return Ok(Rc::new(Object::Null));
+        };
+        // Find the outermost ip layer
+        let mut depth = 0;
+        while !matches!(layer.as_ref(), Object::Ipv4(_) | Object::Ipv6(_)) {
+            let inner = self.get_inner(&layer, 1, line)?;
+            depth += 1;
+            if Rc::ptr_eq(&inner, &layer) || depth > MAX_PROTO_DEPTH {
+                return Ok(Rc::new(Object::Null));
+            }
+            layer = inner;
+        }
+        let prop = |obj: &Rc<Object>, prop: PacketPropType| {
+            self.exec_prop_expr(obj.clone(), prop.into(), None, line)
+        };
+        let proto = match layer.as_ref() {
+            Object::Ipv4(_) => prop(&layer, PacketPropType::Protocol)?,
+            _ => prop(&layer, PacketPropType::NextHeader)?,
+        };
+        let src = prop(&layer, PacketPropType::Src)?;
+        let dst = prop(&layer, PacketPropType::Dst)?;
+        let inner = self.get_inner(&layer, 1, line)?;
+        let (srcport, dstport) = match inner.as_ref() {
+            Object::Udp(_) | Object::Tcp(_) => (
+                prop(&inner, PacketPropType::SrcPort)?,
+                prop(&inner, PacketPropType::DstPort)?,
+            ),
+            _ => (Rc::new(Object::Integer(0)), Rc::new(Object::Integer(0))),
+        };
+
+        let var = |var: BuiltinVarType| self.builtinvars.borrow()[var as usize].clone();
+        let time = match var(BuiltinVarType::Time).as_ref() {
+            Object::Float(t) => *t,
+            _ => 0.0,
+        };
+        let integer = |obj: Rc<Object>| match obj.as_ref() {
+            Object::Integer(n) => *n,
+            _ => 0,
+        };
+        let packet = integer(var(BuiltinVarType::NP));
+        let bytes = integer(var(BuiltinVarType::WL));
+        self.expire_flows(time, line)?;
+
+        let key = vec![
+            proto.clone(),
+            src.clone(),
+            srcport.clone(),
+            dst.clone(),
+            dstport.clone(),
+        ];
+        let fields = [
+            ("proto", proto),
+            ("src", src),
+            ("srcport", srcport),
+            ("dst", dst),
+            ("dstport", dstport),
+        ];
+        let segment = flow_segment(&layer, &inner);
+        let record = self.flows.track(key, &fields, time, packet, bytes, segment);
+        Ok(Rc::new(Object::Map(record)))
+    }
+
+    /// Remove the flows idle for longer than the timeout and pass their
+    /// records to the function given using 'flow_timeout', if any
+    #[cfg(feature = "pcap")]
+    fn expire_flows(&mut self, now: f64, line: usize) -> Result<(), RTError> {
+        let expired = self.flows.expire(now);
+        if let Some(func) = self.flows.on_expire() {
+            for record in expired {
+                self.call_function(&func, &[Rc::new(Object::Map(record))], line)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Records of the active flows in the order in which they were created
+    #[cfg(feature = "pcap")]
+    pub fn flows(&self) -> Rc<Object> {
+        let records = self
+            .flows
+            .records()
+            .into_iter()
+            .map(|record| Rc::new(Object::Map(record)))
+            .collect();
+        Rc::new(Object::Arr(Rc::new(Array::new(records))))
+    }
+
+    // The name of the host with an address validated by the builtin, or
+    // the address itself if it has no name
+    fn resolve(&mut self, addr: &Object) -> Rc<Object> {
+        let Object::Str(addr) = addr else {
+            return Rc::new(Object::Null);
+        };
+        let name = match addr.parse() {
+            Ok(ip) => self.resolver.host_name(ip),
+            Err(_) => None,
+        };
+        Rc::new(Object::Str(name.unwrap_or_else(|| addr.clone())))
+    }
+
+    // The name of the service on a port, or the port number as a string
+    // if it has no name
+    fn service_name(&mut self, port: &Object, proto: &Object) -> Rc<Object> {
+        let (Object::Integer(port), Object::Str(proto)) = (port, proto) else {
+            return Rc::new(Object::Null);
+        };
+        let name = self.resolver.service_name(*port as u16, proto);
+        Rc::new(Object::Str(name.unwrap_or_else(|| port.to_string())))
+    }
+
+    /// Check if the action keyed by a value may run given that it may run
+    /// 'max' times every 'per' seconds of packet time. The time is that of
+    /// the current packet relative to the first packet.
+    pub fn ratelimit(&mut self, key: &Rc<Object>, max: &Object, per: &Object) -> bool {
+        let max = match max {
+            Object::Integer(n) => *n,
+            _ => return false,
+        };
+        let Some(per) = per.as_secs() else {
+            return false;
+        };
+        let now = self.packet_time();
+        self.rate_limiter.allow(key.clone(), max, per, now)
+    }
+
+    /// Count a call of 'rate_limit' in the current window of time of the
+    /// key and return true if there were fewer than 'max' calls before it
+    pub fn rate_limit(&mut self, key: &Rc<Object>, max: &Object, per: &Object) -> bool {
+        let max = match max {
+            Object::Integer(n) => *n,
+            _ => return false,
+        };
+        let Some(per) = per.as_secs() else {
+            return false;
+        };
+        let now = self.packet_time();
+        self.rate_limiter.sample(key.clone(), max, per, now)
+    }
+
+    // Capture time of the current packet relative to the first one
+    fn packet_time(&self) -> f64 {
+        match self.builtinvars.borrow()[BuiltinVarType::Time as usize].as_ref() {
+            Object::Float(t) => *t,
+            _ => 0.0,
+        }
+    }
+
+    // The builtin returns the timeout and the function as an array
+    #[cfg(feature = "pcap")]
+    fn set_flow_timeout(&mut self, arr: &Array) {
+        let Some(timeout) = arr.get(0).as_secs() else {
+            return;
+        };
+        let on_expire = match arr.get(1).as_ref() {
+            Object::Clos(c) => Some(c.clone()),
+            _ => None,
+        };
+        self.flows.set_timeout(timeout, on_expire);
+    }
+
+    /// Call a closure with the given arguments from within a builtin while
+    /// the VM is running. An empty frame is pushed below the callee's frame
+    /// so that the VM stops running once the closure returns, leaving the
+    /// caller's frames and stack intact.
+    /// Return the value returned by the closure.
+    fn call_function(
+        &mut self,
+        closure: &Rc<Closure>,
+        args: &[Rc<Object>],
+        line: usize,
+    ) -> Result<Rc<Object>, RTError> {
+        let sp = self.sp;
+        self.push_frame(Frame::default());
+        self.push(Rc::new(Object::Clos(closure.clone())), line)?;
+        for arg in args {
+            self.push(arg.clone(), line)?;
+        }
+        let frame = Frame::new_call(closure.clone(), self.sp - args.len(), line);
+        self.sp = frame.bp + closure.func.num_locals;
+        self.push_frame(frame);
+        self.run()?;
+        let ret = self.pop(line)?;
+        self.pop_frame();
+        self.sp = sp;
+        Ok(ret)
+    }
+
+    /// Run a closure that takes no arguments to completion on a fresh stack.
+    /// The bottom frame is an empty one so that the VM stops running once
+    /// the closure returns.
+    /// Return the value returned by the closure.
+    fn call_closure(&mut self, closure: Rc<Closure>) -> Result<Rc<Object>, RTError> {
+        self.frames[0] = Frame::default();
+        self.frames_index = 1;
+        self.sp = 0;
+        let line = closure.func.line;
+        self.push(Rc::new(Object::Clos(closure.clone())), line)?;
+        let frame = Frame::new(closure.clone(), self.sp);
+        self.sp = frame.bp + closure.func.num_locals;
+        self.push_frame(frame);
+        self.run()?;
+        self.pop(line)
+    }
+
+    /// Update a builtin variable. A value of a type the variable does not
+    /// hold is not assigned, so that it cannot clobber the variable; a
+    /// warning is displayed and the run continues.
+    pub fn update_builtin_var(&self, vt: BuiltinVarType, obj: Rc<Object>) {
+        if let Err(err) = self.try_update_builtin_var(vt, obj) {
+            eprintln!("warning: {}", err);
+        }
+    }
+
+    /// Update a builtin variable after validating the value against the
+    /// types the variable may hold
+    pub fn try_update_builtin_var(
+        &self,
+        vt: BuiltinVarType,
+        obj: Rc<Object>,
+    ) -> Result<(), String> {
+        vt.check(&obj)?;
+        self.builtinvars.borrow_mut()[vt as usize] = obj;
+        Ok(())
+    }
+
+    /// Set 'argv' to the arguments and reset the variables of the packets
+    pub fn init_builtin_vars(&self, args: Vec<String>) {
+        let elements: Vec<Rc<Object>> = args.into_iter().map(|s| Rc::new(Object::Str(s))).collect();
+        let arr = Rc::new(Object::Arr(Rc::new(Array::new(elements))));
+        self.update_builtin_var(BuiltinVarType::Argv, arr);
+        self.update_builtin_var(BuiltinVarType::NP, Rc::new(Object::Null));
+        self.update_builtin_var(BuiltinVarType::PL, Rc::new(Object::Null));
+        self.update_builtin_var(BuiltinVarType::WL, Rc::new(Object::Null));
+        self.update_builtin_var(BuiltinVarType::Tss, Rc::new(Object::Null));
+        self.update_builtin_var(BuiltinVarType::Tsu, Rc::new(Object::Null));
+        self.update_builtin_var(BuiltinVarType::NC, Rc::new(Object::Null));
+        self.update_builtin_var(BuiltinVarType::Time, Rc::new(Object::Null));
+        self.update_builtin_var(BuiltinVarType::FI, Rc::new(Object::Null));
+        self.update_builtin_var(BuiltinVarType::FL, Rc::new(Object::Null));
+        self.update_builtin_var(BuiltinVarType::NT, Rc::new(Object::Null));
+        self.update_builtin_var(BuiltinVarType::Ts, Rc::new(Object::Null));
+        self.update_builtin_var(BuiltinVarType::PsDrop, Rc::new(Object::Null));
+        self.update_builtin_var(BuiltinVarType::PsIfDrop, Rc::new(Object::Null));
+        self.update_builtin_var(BuiltinVarType::Filename, Rc::new(Object::Null));
+        #[cfg(feature = "pcap")]
+        {
+            let auto = Object::Str(AUTO.to_string());
+            self.update_builtin_var(BuiltinVarType::Auto, Rc::new(auto));
+        }
+    }
+}
+
+// The sequence number of a tcp segment or of an rtp packet in a udp
+// datagram within an ip layer. The payload ends where the ip layer says
+// it does so that the padding of short frames is not counted. A length of
+// 0, as captured from a segmentation offload, extends to the end.
+#[cfg(feature = "pcap")]
+fn flow_segment(ip: &Object, inner: &Object) -> Option<Segment> {
+    let end = match ip {
+        Object::Ipv4(ip) => match ip.get_total_length_raw() as usize {
+            0 => usize::MAX,
+            len => (ip.offset + len).saturating_sub(ip.get_header_length_raw()),
+        },
+        Object::Ipv6(ip) => match ip.get_payload_length_raw() as usize {
+            0 => usize::MAX,
+            len => ip.offset + len,
+        },
+        _ => return None,
+    };
+    match inner {
+        Object::Tcp(tcp) => {
+            let start = tcp.get_payload_offset_raw();
+            let end = end.min(tcp.rawdata.borrow().len());
+            let flags = tcp.get_flags_raw();
+            Some(Segment::Tcp {
+                seq: tcp.get_sequence_raw(),
+                len: end.saturating_sub(start) as u32,
+                syn: flags & TCP_SYN != 0,
+                fin: flags & TCP_FIN != 0,
+                rst: flags & TCP_RST != 0,
+            })
+        }
+        Object::Udp(udp) => {
+            let data = udp.rawdata.borrow().clone();
+            let start = udp.offset.min(data.len());
+            let end = end.clamp(start, data.len());
+            let (ssrc, seq) = flows::rtp_header(&data[start..end])?;
+            Some(Segment::Rtp { ssrc, seq })
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,158 @@
+//! Metrics of the filters in the Prometheus text format.
+//!
+//! A monitor running on a live capture can serve its counters over HTTP
+//! using '--metrics-port', so that it can be scraped by Prometheus and
+//! alerted on like any other service. The counters are updated by the
+//! main thread as packets are processed and read by a thread that
+//! answers the requests.
+
+use std::fmt::Write;
+use std::io::{self, BufRead, BufReader, Read, Write as _};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// Time to wait for a client to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+// Number of bytes of the request and its headers read from a client
+const MAX_REQUEST_SIZE: u64 = 8192;
+
+/// Counters of the packets processed by the filters
+#[derive(Debug, Default)]
+pub struct Metrics {
+    packets: AtomicU64,
+    errors: AtomicU64,
+    corrupt: AtomicU64,
+    dropped: AtomicU64,
+    // Line of each filter and the number of packets it matched
+    filters: Mutex<Vec<(usize, u64)>>,
+}
+
+impl Metrics {
+    pub fn add_packet(&self) {
+        self.packets.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_corrupt(&self, count: u64) {
+        self.corrupt.store(count, Ordering::Relaxed);
+    }
+
+    pub fn set_dropped(&self, count: u64) {
+        self.dropped.store(count, Ordering::Relaxed);
+    }
+
+    /// Set the lines of the filters run on each packet, in order. The
+    /// counts of the filters start over, such as when the script is
+    /// reloaded.
+    pub fn set_filters(&self, lines: &[usize]) {
+        let mut filters = self.filters.lock().unwrap();
+        *filters = lines.iter().map(|line| (*line, 0)).collect();
+    }
+
+    /// Count a packet matched by the filter at 'index'
+    pub fn add_match(&self, index: usize) {
+        if let Some((_, count)) = self.filters.lock().unwrap().get_mut(index) {
+            *count += 1;
+        }
+    }
+
+    /// The metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let counters = [
+            (
+                "p2sh_packets_total",
+                "Packets processed by the filters",
+                &self.packets,
+            ),
+            (
+                "p2sh_errors_total",
+                "Runtime errors raised by the script",
+                &self.errors,
+            ),
+            (
+                "p2sh_corrupt_records_total",
+                "Corrupt packet records in the input",
+                &self.corrupt,
+            ),
+            (
+                "p2sh_packets_dropped_total",
+                "Packets dropped by the kernel before they were captured",
+                &self.dropped,
+            ),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, value.load(Ordering::Relaxed));
+        }
+        let name = "p2sh_filter_matches_total";
+        let _ = writeln!(out, "# HELP {} Packets matched by each filter", name);
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        for (i, (line, count)) in self.filters.lock().unwrap().iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "{}{{filter=\"{}\",line=\"{}\"}} {}",
+                name,
+                i + 1,
+                line,
+                count
+            );
+        }
+        out
+    }
+}
+
+/// Serve the metrics at '/metrics' on a thread of its own. Returns the
+/// address listened on.
+pub fn serve(metrics: Arc<Metrics>, addr: impl ToSocketAddrs) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(addr)?;
+    let local = listener.local_addr()?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // A client that goes away only affects its own request
+            let _ = respond(stream, &metrics);
+        }
+    });
+    Ok(local)
+}
+
+// Answer a single request and close the connection
+fn respond(stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new((&stream).take(MAX_REQUEST_SIZE));
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // Skip the headers up to the blank line
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+    let mut parts = request.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics.render()),
+        (Some("GET"), _) => ("404 Not Found", String::from("not found\n")),
+        _ => (
+            "405 Method Not Allowed",
+            String::from("method not allowed\n"),
+        ),
+    };
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
@@ -0,0 +1,20 @@
+pub mod checkpoint;
+pub mod debugger;
+pub mod error;
+#[cfg(feature = "pcap")]
+pub mod flows;
+pub mod frame;
+pub mod interpreter;
+pub mod metrics;
+pub mod objects;
+#[cfg(feature = "pcap")]
+pub mod pktprop;
+#[cfg(feature = "pcap")]
+pub mod profile;
+pub mod ratelimit;
+#[cfg(test)]
+pub mod reference;
+pub mod reload;
+pub mod resolver;
+pub mod tests;
+pub mod trace;
@@ -0,0 +1,167 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+use crate::object::Object;
+
+// Number of the largest containers listed in the report
+const MAX_LARGEST: usize = 10;
+// Longest key, in characters, shown in the path of a container
+const MAX_PATH_KEY: usize = 32;
+
+/// An array, a map or a set reachable from a global
+#[derive(Debug, Clone)]
+pub struct Container {
+    /// How the container is reached, e.g. 'flows["10.0.0.1"]'
+    pub path: String,
+    pub kind: &'static str,
+    pub len: usize,
+    /// Line where the global that holds the container is defined
+    pub line: Option<usize>,
+}
+
+/// Number of the live objects of each kind and the largest containers
+/// reachable from the globals of a script. Scripts that keep adding to
+/// the state they hold for each flow during a long capture show up as
+/// containers that keep growing.
+#[derive(Debug, Default)]
+pub struct ObjectReport {
+    counts: Vec<(&'static str, usize)>,
+    largest: Vec<Container>,
+}
+
+impl ObjectReport {
+    /// Walk the objects reachable from the globals, given by their names
+    /// and the lines where they are defined. An object shared by several
+    /// globals or containers is counted once.
+    pub fn new(globals: &[(String, Option<usize>, Rc<Object>)]) -> Self {
+        let mut seen = HashSet::new();
+        let mut counts: HashMap<&'static str, usize> = HashMap::new();
+        let mut containers = Vec::new();
+        // A stack instead of recursion so that deeply nested containers
+        // do not overflow the native stack
+        let mut pending: Vec<(String, Option<usize>, Rc<Object>)> =
+            globals.iter().rev().cloned().collect();
+        while let Some((path, line, obj)) = pending.pop() {
+            if !seen.insert(identity(&obj)) {
+                continue;
+            }
+            *counts.entry(obj.kind()).or_insert(0) += 1;
+            let mut children = Vec::new();
+            match obj.as_ref() {
+                Object::Arr(arr) => {
+                    let elements = arr.elements.borrow();
+                    for (i, elem) in elements.iter().enumerate() {
+                        children.push((format!("{}[{}]", path, i), elem.clone()));
+                    }
+                }
+                Object::Map(map) => {
+                    for (key, value) in map.sorted_pairs() {
+                        let child = format!("{}[{}]", path, path_key(&key));
+                        children.push((format!("{}<key>", child), key));
+                        children.push((child, value));
+                    }
+                }
+                Object::Set(set) => {
+                    for elem in set.sorted_elements() {
+                        children.push((format!("{}[{}]", path, path_key(&elem)), elem));
+                    }
+                }
+                Object::Clos(closure) => {
+                    let free = closure.free.borrow();
+                    for (i, value) in free.iter().enumerate() {
+                        children.push((format!("{}.free[{}]", path, i), value.clone()));
+                    }
+                }
+                _ => {}
+            }
+            if let Some(len) = container_len(&obj) {
+                containers.push(Container {
+                    path: path.clone(),
+                    kind: obj.kind(),
+                    len,
+                    line,
+                });
+            }
+            for (path, child) in children.into_iter().rev() {
+                pending.push((path, line, child));
+            }
+        }
+        let mut counts: Vec<(&'static str, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        // The largest first, and those found first among equals
+        containers.sort_by_key(|c| std::cmp::Reverse(c.len));
+        containers.truncate(MAX_LARGEST);
+        Self {
+            counts,
+            largest: containers,
+        }
+    }
+
+    /// Number of objects of each kind, the most common first
+    pub fn counts(&self) -> &[(&'static str, usize)] {
+        &self.counts
+    }
+
+    /// The largest arrays, maps and sets, the largest first
+    pub fn largest(&self) -> &[Container] {
+        &self.largest
+    }
+}
+
+// Containers and closures are identified by what they share so that
+// the copies of an object that refer to the same container are counted
+// once. Other objects are identified by their allocation.
+fn identity(obj: &Rc<Object>) -> *const () {
+    match obj.as_ref() {
+        Object::Arr(arr) => Rc::as_ptr(arr) as *const (),
+        Object::Map(map) => Rc::as_ptr(map) as *const (),
+        Object::Set(set) => Rc::as_ptr(set) as *const (),
+        Object::Clos(closure) => Rc::as_ptr(closure) as *const (),
+        _ => Rc::as_ptr(obj) as *const (),
+    }
+}
+
+fn container_len(obj: &Object) -> Option<usize> {
+    match obj {
+        Object::Arr(arr) => Some(arr.len()),
+        Object::Map(map) => Some(map.len()),
+        Object::Set(set) => Some(set.len()),
+        _ => None,
+    }
+}
+
+// Show a key in a path, truncating it to keep the path readable
+fn path_key(key: &Object) -> String {
+    let s = key.to_string();
+    if s.chars().count() <= MAX_PATH_KEY {
+        s
+    } else {
+        let head: String = s.chars().take(MAX_PATH_KEY).collect();
+        format!("{}...", head)
+    }
+}
+
+impl fmt::Display for ObjectReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<10} {:>10}", "object", "live")?;
+        for (kind, count) in &self.counts {
+            writeln!(f, "{:<10} {:>10}", kind, count)?;
+        }
+        let total: usize = self.counts.iter().map(|(_, count)| count).sum();
+        write!(f, "{:<10} {:>10}", "total", total)?;
+        if self.largest.is_empty() {
+            return Ok(());
+        }
+        write!(
+            f,
+            "\n\n{:<10} {:>10} {:>6}  path",
+            "container", "len", "line"
+        )?;
+        for c in &self.largest {
+            let line = c.line.map(|l| l.to_string()).unwrap_or_default();
+            write!(f, "\n{:<10} {:>10} {:>6}  {}", c.kind, c.len, line, c.path)?;
+        }
+        Ok(())
+    }
+}
@@ -4,19 +4,24 @@ use super::error::RTError;
 use super::interpreter::VM;
 use crate::builtins::pcap::Pcap;
 use crate::builtins::pcap::PcapPacket;
-use crate::builtins::protocols::ethernet::EtherTypes;
-use crate::builtins::protocols::ethernet::Ethernet;
-use crate::builtins::protocols::ipv4::Ipv4Packet;
-use crate::builtins::protocols::ipv4::Protocols;
-use crate::builtins::protocols::ipv6::Ipv6Packet;
-use crate::builtins::protocols::ipv6::NextHeaders;
-use crate::builtins::protocols::tcp::Tcp;
-use crate::builtins::protocols::udp::Udp;
-use crate::builtins::protocols::vlan::Vlan;
-use crate::code::prop::PacketPropType;
+use crate::builtins::variables::BuiltinVarType;
+use crate::code::prop::{match_props, PacketPropType, PACKET_PROTOS};
 use crate::object::array::Array;
 use crate::object::error::ErrorObj;
+use crate::object::hmap::HMap;
+use crate::object::layers::{Ethernet, Ipv4Packet, Ipv6Packet, Tcp, Tunnel, Udp, Vlan};
 use crate::object::Object;
+use p2sh_dissect::detect::Transport;
+use p2sh_dissect::dns::Dns;
+use p2sh_dissect::error::PacketError;
+use p2sh_dissect::ethernet::{EtherType, EtherTypes};
+use p2sh_dissect::ipv4::Protocols;
+use p2sh_dissect::ipv4::{IPV4_DF, IPV4_MF};
+use p2sh_dissect::ipv6::NextHeaders;
+use p2sh_dissect::ports::AppProto;
+use p2sh_dissect::tcp::{TCP_ACK, TCP_CWR, TCP_ECE, TCP_FIN, TCP_PSH, TCP_RST, TCP_SYN, TCP_URG};
+use p2sh_dissect::tls::Tls;
+use p2sh_dissect::tunnel::{TunnelKind, TRANSPARENT_ETHERNET};
 
 pub const MAX_PROTO_DEPTH: usize = 10;
 
@@ -34,14 +39,16 @@ impl VM {
             Object::Packet(pkt) => {
                 // Clone the wrapped Rc object so we do not get BorrowMutError
                 let wrapped = pkt.inner.borrow().clone();
-                if let Some(inner) = wrapped.as_ref() {
+                let obj = if let Some(inner) = wrapped.as_ref() {
                     self.get_inner(inner, depth - 1, line)?
                 } else {
                     // Parse inner packet from bytes
                     let obj =
                         self.exec_prop_packet(pkt.clone(), PacketPropType::Eth, None, line)?;
                     self.get_inner(&obj, depth - 1, line)?
-                }
+                };
+                self.check_truncated(pkt, &obj);
+                obj
             }
             Object::Eth(eth) => {
                 // Clone the wrapped Rc object so we do not get BorrowMutError
@@ -135,6 +142,11 @@ impl VM {
                             )?;
                             self.get_inner(&obj, depth - 1, line)?
                         }
+                        Protocols::Gre => {
+                            let obj =
+                                self.exec_prop_ipv4(ipv4.clone(), PacketPropType::Gre, None, line)?;
+                            self.get_inner(&obj, depth - 1, line)?
+                        }
                         _ => Rc::new(Object::Null),
                     }
                 }
@@ -157,6 +169,11 @@ impl VM {
                                 self.exec_prop_ipv6(ipv6.clone(), PacketPropType::Tcp, None, line)?;
                             self.get_inner(&obj, depth - 1, line)?
                         }
+                        NextHeaders::Gre => {
+                            let obj =
+                                self.exec_prop_ipv6(ipv6.clone(), PacketPropType::Gre, None, line)?;
+                            self.get_inner(&obj, depth - 1, line)?
+                        }
                         _ => Rc::new(Object::Null),
                     }
                 }
@@ -166,7 +183,29 @@ impl VM {
                 if let Some(inner) = wrapped.as_ref() {
                     self.get_inner(inner, depth - 1, line)?
                 } else {
-                    Rc::new(Object::Null)
+                    match self.udp_app_proto(udp) {
+                        Some(AppProto::Dns) => {
+                            let obj =
+                                self.exec_prop_udp(udp.clone(), PacketPropType::Dns, None, line)?;
+                            self.get_inner(&obj, depth - 1, line)?
+                        }
+                        Some(AppProto::Vxlan) => {
+                            let obj =
+                                self.exec_prop_udp(udp.clone(), PacketPropType::Vxlan, None, line)?;
+                            self.get_inner(&obj, depth - 1, line)?
+                        }
+                        Some(AppProto::Geneve) => {
+                            let obj = self.exec_prop_udp(
+                                udp.clone(),
+                                PacketPropType::Geneve,
+                                None,
+                                line,
+                            )?;
+                            self.get_inner(&obj, depth - 1, line)?
+                        }
+                        // TLS over udp (DTLS) is not decoded
+                        Some(AppProto::Tls) | None => Rc::new(Object::Null),
+                    }
                 }
             }
             Object::Tcp(tcp) => {
@@ -174,7 +213,30 @@ impl VM {
                 if let Some(inner) = wrapped.as_ref() {
                     self.get_inner(inner, depth - 1, line)?
                 } else {
-                    Rc::new(Object::Null)
+                    match self.tcp_app_proto(tcp) {
+                        Some(AppProto::Dns) => {
+                            let obj =
+                                self.exec_prop_tcp(tcp.clone(), PacketPropType::Dns, None, line)?;
+                            self.get_inner(&obj, depth - 1, line)?
+                        }
+                        Some(AppProto::Tls) => {
+                            let obj =
+                                self.exec_prop_tcp(tcp.clone(), PacketPropType::Tls, None, line)?;
+                            self.get_inner(&obj, depth - 1, line)?
+                        }
+                        // Tunnels over tcp are not decoded
+                        Some(AppProto::Vxlan | AppProto::Geneve) | None => Rc::new(Object::Null),
+                    }
+                }
+            }
+            Object::Tunnel(tunnel) => {
+                let wrapped = tunnel.inner.borrow().clone();
+                if let Some(inner) = wrapped.as_ref() {
+                    self.get_inner(inner, depth - 1, line)?
+                } else {
+                    let obj =
+                        self.exec_prop_tunnel(tunnel.clone(), PacketPropType::Inner, None, line)?;
+                    self.get_inner(&obj, depth - 1, line)?
                 }
             }
             _ => obj.clone(),
@@ -182,6 +244,41 @@ impl VM {
         Ok(obj)
     }
 
+    /// Flag the packet as truncated if a layer reached from it could not be
+    /// parsed because the capture is shorter than the packet on the wire.
+    /// Such packets are counted in 'NT'.
+    fn check_truncated(&self, pkt: &PcapPacket, obj: &Object) {
+        if let Object::Err(ErrorObj::Packet(PacketError::InvalidLength(_))) = obj {
+            if pkt.set_truncated() {
+                let count = match self.builtinvars.borrow()[BuiltinVarType::NT as usize].as_ref() {
+                    Object::Integer(n) => n + 1,
+                    _ => 1,
+                };
+                self.update_builtin_var(BuiltinVarType::NT, Rc::new(Object::Integer(count)));
+            }
+        }
+    }
+
+    /// Protocol that the payload of a udp packet is decoded as
+    fn udp_app_proto(&self, udp: &Udp) -> Option<AppProto> {
+        let src = udp.get_source_port_raw();
+        let dst = udp.get_destination_port_raw();
+        let rawdata = udp.rawdata.borrow();
+        let payload = rawdata.get(udp.offset..).unwrap_or_default();
+        self.port_map().resolve(src, dst, payload, Transport::Udp)
+    }
+
+    /// Protocol that the payload of a tcp packet is decoded as
+    fn tcp_app_proto(&self, tcp: &Tcp) -> Option<AppProto> {
+        let src = tcp.get_source_port_raw();
+        let dst = tcp.get_destination_port_raw();
+        let rawdata = tcp.rawdata.borrow();
+        let payload = rawdata
+            .get(tcp.get_payload_offset_raw()..)
+            .unwrap_or_default();
+        self.port_map().resolve(src, dst, payload, Transport::Tcp)
+    }
+
     /// Execute a property expression
     /// left: The object on which the property is being accessed
     /// prop: The property being accessed
@@ -205,14 +302,148 @@ impl VM {
             Object::Ipv6(ipv6) => self.exec_prop_ipv6(ipv6.clone(), prop, setval, line)?,
             Object::Udp(udp) => self.exec_prop_udp(udp.clone(), prop, setval, line)?,
             Object::Tcp(tcp) => self.exec_prop_tcp(tcp.clone(), prop, setval, line)?,
+            Object::Dns(dns) => self.exec_prop_dns(dns.clone(), prop, setval, line)?,
+            Object::Tls(tls) => self.exec_prop_tls(tls.clone(), prop, setval, line)?,
+            Object::Tunnel(tunnel) => self.exec_prop_tunnel(tunnel.clone(), prop, setval, line)?,
             _ => {
                 let msg = format!("{}: Object does not have any property", left);
                 return Err(RTError::new(&msg, line));
             }
         };
+        if self.legacy_props() {
+            return Ok(legacy_value(obj));
+        }
         Ok(obj)
     }
 
+    /// Collect the properties of a packet object and of the layers within
+    /// it whose paths match a pattern such as 'tcp.*' into a map of the
+    /// paths to their values. Where a protocol occurs more than once, such
+    /// as in a tunnel, the outermost layer is used. Properties that are
+    /// layers themselves or that have no value are left out.
+    pub fn packet_props(
+        &self,
+        obj: &Rc<Object>,
+        pattern: &Rc<Object>,
+        line: usize,
+    ) -> Result<Rc<Object>, RTError> {
+        let Object::Str(pattern) = pattern.as_ref() else {
+            return Err(RTError::new("props: unsupported argument", line));
+        };
+        let mut layers: Vec<(String, Rc<Object>)> = Vec::new();
+        let mut layer = obj.clone();
+        for depth in 0..MAX_PROTO_DEPTH {
+            if depth > 0 {
+                let inner = self.get_inner(&layer, 1, line)?;
+                if Rc::ptr_eq(&inner, &layer) {
+                    break;
+                }
+                layer = inner;
+            }
+            let Some(name) = layer_name(&layer) else {
+                break;
+            };
+            if !layers.iter().any(|(n, _)| *n == name) {
+                layers.push((name, layer.clone()));
+            }
+        }
+        let map = HMap::default();
+        for (name, info) in match_props(pattern) {
+            if info.is_layer() {
+                continue;
+            }
+            let Some((_, layer)) = layers.iter().find(|(n, _)| n == name) else {
+                continue;
+            };
+            let value = self.exec_prop_expr(layer.clone(), info.prop.into(), None, line)?;
+            if value.is_null() || matches!(value.as_ref(), Object::Err(_)) {
+                continue;
+            }
+            let key = Rc::new(Object::Str(format!("{}.{}", name, info.prop)));
+            map.insert(key, value);
+        }
+        Ok(Rc::new(Object::Map(Rc::new(map))))
+    }
+
+    /// Convert a packet object into a map of its properties to their
+    /// values in which the layer within it is a nested map named after its
+    /// protocol, e.g. {"sec": 0, "eth": {"src": ..., "ipv4": {...}}}.
+    /// Properties that have no value are left out.
+    pub fn packet_map(&self, obj: &Rc<Object>, line: usize) -> Result<Rc<Object>, RTError> {
+        let map = self.layer_fields(obj, line)?;
+        // The layer actually carried by the packet, which ends with a
+        // layer that has none
+        let inner = self.get_inner(obj, 1, line)?;
+        if !Rc::ptr_eq(&inner, obj) {
+            if let Some(name) = layer_name(&inner) {
+                let inner = self.packet_map(&inner, line)?;
+                map.insert(Rc::new(Object::Str(name)), inner);
+            }
+        }
+        Ok(Rc::new(Object::Map(Rc::new(map))))
+    }
+
+    /// Decode a packet, or any of its layers, into a map of each layer
+    /// down to the innermost one to a map of its properties, e.g.
+    /// {"layers": ["packet", "eth", ...], "packet": {...}, "eth": {...}}.
+    /// Unlike 'packet_map', the layers are not nested. 'layers' lists the
+    /// keys of the layers in the order in which they are decoded. A layer
+    /// that occurs more than once, such as in a tunnel, is keyed by its
+    /// name followed by the count of its occurrences, e.g. "ipv4_2".
+    pub fn packet_layers(&self, obj: &Rc<Object>, line: usize) -> Result<Rc<Object>, RTError> {
+        let map = HMap::default();
+        let mut layers: Vec<Rc<Object>> = Vec::new();
+        let mut names: Vec<String> = Vec::new();
+        let mut layer = obj.clone();
+        for _ in 0..=MAX_PROTO_DEPTH {
+            let Some(name) = layer_name(&layer) else {
+                break;
+            };
+            let count = names.iter().filter(|n| **n == name).count();
+            let key = match count {
+                0 => name.clone(),
+                n => format!("{}_{}", name, n + 1),
+            };
+            names.push(name);
+            let fields = self.layer_fields(&layer, line)?;
+            map.insert(
+                Rc::new(Object::Str(key.clone())),
+                Rc::new(Object::Map(Rc::new(fields))),
+            );
+            layers.push(Rc::new(Object::Str(key)));
+            let inner = self.get_inner(&layer, 1, line)?;
+            if Rc::ptr_eq(&inner, &layer) {
+                break;
+            }
+            layer = inner;
+        }
+        map.insert(
+            Rc::new(Object::Str(String::from("layers"))),
+            Rc::new(Object::Arr(Rc::new(Array::new(layers)))),
+        );
+        Ok(Rc::new(Object::Map(Rc::new(map))))
+    }
+
+    // Map the properties of a layer that are not layers themselves to their
+    // values, leaving out the ones without a value
+    fn layer_fields(&self, obj: &Rc<Object>, line: usize) -> Result<HMap, RTError> {
+        let map = HMap::default();
+        let Some(name) = layer_name(obj) else {
+            return Ok(map);
+        };
+        let Some(proto) = PACKET_PROTOS.iter().find(|p| p.name == name) else {
+            return Ok(map);
+        };
+        for info in proto.props.iter().filter(|info| !info.is_layer()) {
+            let value = self.exec_prop_expr(obj.clone(), info.prop.into(), None, line)?;
+            if value.is_null() || matches!(value.as_ref(), Object::Err(_)) {
+                continue;
+            }
+            map.insert(Rc::new(Object::Str(info.prop.to_string())), value);
+        }
+        Ok(map)
+    }
+
     /// Execute a pcap property expression
     /// pcap: The pcap on which the property is being accessed
     /// prop: The property being accessed
@@ -372,7 +603,9 @@ impl VM {
                     if let Some(inner) = pkt.inner.borrow().as_ref() {
                         return Ok(inner.clone());
                     }
-                    let obj = match Ethernet::from_bytes(Rc::clone(&pkt.rawdata.borrow()), 0) {
+                    let obj = match self.dissect("eth", || {
+                        Ethernet::from_bytes(Rc::clone(&pkt.rawdata.borrow()), 0)
+                    }) {
                         Ok(ethernet) => Rc::new(Object::Eth(Rc::new(ethernet))),
                         Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
                     };
@@ -381,15 +614,19 @@ impl VM {
                     obj
                 }
             }
+            PacketPropType::Truncated => {
+                if setval.is_some() {
+                    return Err(RTError::new("Cannot set packet property truncated", line));
+                }
+                // Walk all the layers so that the flag does not depend on
+                // the layers accessed so far
+                let obj = Rc::new(Object::Packet(pkt.clone()));
+                self.get_inner(&obj, MAX_PROTO_DEPTH, line)?;
+                Rc::new(Object::Bool(pkt.is_truncated()))
+            }
             PacketPropType::Payload => {
                 // return the payload as bytes
-                let payload = pkt.rawdata.borrow().clone();
-                let mut elements = Vec::new();
-                for byte in payload.iter() {
-                    elements.push(Rc::new(Object::Byte(*byte)));
-                }
-                let arr = Array::new(elements);
-                Rc::new(Object::Arr(Rc::new(arr)))
+                Rc::new(Object::Bytes(pkt.rawdata.borrow().to_vec()))
             }
             _ => {
                 return Err(RTError::new(
@@ -458,7 +695,9 @@ impl VM {
                     if let Some(inner) = eth.inner.borrow().as_ref() {
                         return Ok(inner.clone());
                     }
-                    let obj = match Vlan::from_bytes(Rc::clone(&eth.rawdata.borrow()), eth.offset) {
+                    let obj = match self.dissect("vlan", || {
+                        Vlan::from_bytes(Rc::clone(&eth.rawdata.borrow()), eth.offset)
+                    }) {
                         Ok(vlan) => Rc::new(Object::Vlan(Rc::new(vlan))),
                         Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
                     };
@@ -475,10 +714,9 @@ impl VM {
                     if let Some(inner) = eth.inner.borrow().as_ref() {
                         return Ok(inner.clone());
                     }
-                    let obj = match Ipv4Packet::from_bytes(
-                        Rc::clone(&eth.rawdata.borrow()),
-                        eth.offset,
-                    ) {
+                    let obj = match self.dissect("ipv4", || {
+                        Ipv4Packet::from_bytes(Rc::clone(&eth.rawdata.borrow()), eth.offset)
+                    }) {
                         Ok(ipv4) => Rc::new(Object::Ipv4(Rc::new(ipv4))),
                         Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
                     };
@@ -495,10 +733,9 @@ impl VM {
                     if let Some(inner) = eth.inner.borrow().as_ref() {
                         return Ok(inner.clone());
                     }
-                    let obj = match Ipv6Packet::from_bytes(
-                        Rc::clone(&eth.rawdata.borrow()),
-                        eth.offset,
-                    ) {
+                    let obj = match self.dissect("ipv6", || {
+                        Ipv6Packet::from_bytes(Rc::clone(&eth.rawdata.borrow()), eth.offset)
+                    }) {
                         Ok(ipv6) => Rc::new(Object::Ipv6(Rc::new(ipv6))),
                         Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
                     };
@@ -508,13 +745,9 @@ impl VM {
             }
             PacketPropType::Payload => {
                 let payload = eth.rawdata.borrow().clone();
-                let mut elements = Vec::new();
                 // start at offset 'offset' to skip the ethernet header
-                for byte in payload.iter().skip(eth.offset) {
-                    elements.push(Rc::new(Object::Byte(*byte)));
-                }
-                let arr = Array::new(elements);
-                Rc::new(Object::Arr(Rc::new(arr)))
+                let bytes = payload.iter().skip(eth.offset).copied().collect();
+                Rc::new(Object::Bytes(bytes))
             }
             _ => {
                 return Err(RTError::new(
@@ -587,8 +820,9 @@ impl VM {
                     if let Some(inner) = vlan.inner.borrow().as_ref() {
                         return Ok(inner.clone());
                     }
-                    let obj = match Vlan::from_bytes(Rc::clone(&vlan.rawdata.borrow()), vlan.offset)
-                    {
+                    let obj = match self.dissect("vlan", || {
+                        Vlan::from_bytes(Rc::clone(&vlan.rawdata.borrow()), vlan.offset)
+                    }) {
                         Ok(vlan) => Rc::new(Object::Vlan(Rc::new(vlan))),
                         Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
                     };
@@ -605,10 +839,9 @@ impl VM {
                     if let Some(inner) = vlan.inner.borrow().as_ref() {
                         return Ok(inner.clone());
                     }
-                    let obj = match Ipv4Packet::from_bytes(
-                        Rc::clone(&vlan.rawdata.borrow()),
-                        vlan.offset,
-                    ) {
+                    let obj = match self.dissect("ipv4", || {
+                        Ipv4Packet::from_bytes(Rc::clone(&vlan.rawdata.borrow()), vlan.offset)
+                    }) {
                         Ok(ipv4) => Rc::new(Object::Ipv4(Rc::new(ipv4))),
                         Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
                     };
@@ -619,13 +852,9 @@ impl VM {
             }
             PacketPropType::Payload => {
                 let payload = vlan.rawdata.borrow().clone();
-                let mut elements = Vec::new();
                 // start at offset 'offset' to skip the vlan header
-                for byte in payload.iter().skip(vlan.offset) {
-                    elements.push(Rc::new(Object::Byte(*byte)));
-                }
-                let arr = Array::new(elements);
-                Rc::new(Object::Arr(Rc::new(arr)))
+                let bytes = payload.iter().skip(vlan.offset).copied().collect();
+                Rc::new(Object::Bytes(bytes))
             }
             _ => {
                 return Err(RTError::new(
@@ -712,6 +941,20 @@ impl VM {
                     ipv4.get_flags()
                 }
             }
+            PacketPropType::Df | PacketPropType::Mf => {
+                let bit = match prop {
+                    PacketPropType::Df => IPV4_DF,
+                    _ => IPV4_MF,
+                };
+                if let Some(val) = setval {
+                    if let Err(e) = ipv4.set_flag(bit, val.clone()) {
+                        return Err(RTError::new(&e, line));
+                    }
+                    val
+                } else {
+                    ipv4.get_flag(bit)
+                }
+            }
             PacketPropType::FragmentOffset => {
                 if let Some(val) = setval {
                     if let Err(e) = ipv4.set_fragment_offset(val.clone()) {
@@ -780,8 +1023,9 @@ impl VM {
                     if let Some(inner) = ipv4.inner.borrow().as_ref() {
                         return Ok(inner.clone());
                     }
-                    let obj = match Udp::from_bytes(Rc::clone(&ipv4.rawdata.borrow()), ipv4.offset)
-                    {
+                    let obj = match self.dissect("udp", || {
+                        Udp::from_bytes(Rc::clone(&ipv4.rawdata.borrow()), ipv4.offset)
+                    }) {
                         Ok(udp) => Rc::new(Object::Udp(Rc::new(udp))),
                         Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
                     };
@@ -798,8 +1042,9 @@ impl VM {
                     if let Some(inner) = ipv4.inner.borrow().as_ref() {
                         return Ok(inner.clone());
                     }
-                    let obj = match Tcp::from_bytes(Rc::clone(&ipv4.rawdata.borrow()), ipv4.offset)
-                    {
+                    let obj = match self.dissect("tcp", || {
+                        Tcp::from_bytes(Rc::clone(&ipv4.rawdata.borrow()), ipv4.offset)
+                    }) {
                         Ok(tcp) => Rc::new(Object::Tcp(Rc::new(tcp))),
                         Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
                     };
@@ -808,6 +1053,26 @@ impl VM {
                     obj
                 }
             }
+            PacketPropType::Gre => {
+                if let Some(val) = setval {
+                    ipv4.inner.replace(Some(val.clone()));
+                    val
+                } else {
+                    if let Some(inner) = ipv4.inner.borrow().as_ref() {
+                        return Ok(inner.clone());
+                    }
+                    let rawdata = Rc::clone(&ipv4.rawdata.borrow());
+                    let obj = match self
+                        .dissect("gre", || Tunnel::gre_from_bytes(rawdata, ipv4.offset))
+                    {
+                        Ok(gre) => Rc::new(Object::Tunnel(Rc::new(gre))),
+                        Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
+                    };
+                    // Borrow the inner object again and replace its content
+                    ipv4.inner.replace(Some(obj.clone()));
+                    obj
+                }
+            }
             PacketPropType::Ipv6 => {
                 if let Some(val) = setval {
                     ipv4.inner.replace(Some(val.clone()));
@@ -816,10 +1081,9 @@ impl VM {
                     if let Some(inner) = ipv4.inner.borrow().as_ref() {
                         return Ok(inner.clone());
                     }
-                    let obj = match Ipv6Packet::from_bytes(
-                        Rc::clone(&ipv4.rawdata.borrow()),
-                        ipv4.offset,
-                    ) {
+                    let obj = match self.dissect("ipv6", || {
+                        Ipv6Packet::from_bytes(Rc::clone(&ipv4.rawdata.borrow()), ipv4.offset)
+                    }) {
                         Ok(ipv6) => Rc::new(Object::Ipv6(Rc::new(ipv6))),
                         Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
                     };
@@ -829,13 +1093,9 @@ impl VM {
             }
             PacketPropType::Payload => {
                 let payload = ipv4.rawdata.borrow().clone();
-                let mut elements = Vec::new();
                 // start at offset 'offset' to skip the ipv4 header
-                for byte in payload.iter().skip(ipv4.offset) {
-                    elements.push(Rc::new(Object::Byte(*byte)));
-                }
-                let arr = Array::new(elements);
-                Rc::new(Object::Arr(Rc::new(arr)))
+                let bytes = payload.iter().skip(ipv4.offset).copied().collect();
+                Rc::new(Object::Bytes(bytes))
             }
             _ => {
                 return Err(RTError::new(
@@ -941,8 +1201,9 @@ impl VM {
                     if let Some(inner) = ipv6.inner.borrow().as_ref() {
                         return Ok(inner.clone());
                     }
-                    let obj = match Udp::from_bytes(Rc::clone(&ipv6.rawdata.borrow()), ipv6.offset)
-                    {
+                    let obj = match self.dissect("udp", || {
+                        Udp::from_bytes(Rc::clone(&ipv6.rawdata.borrow()), ipv6.offset)
+                    }) {
                         Ok(udp) => Rc::new(Object::Udp(Rc::new(udp))),
                         Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
                     };
@@ -959,8 +1220,9 @@ impl VM {
                     if let Some(inner) = ipv6.inner.borrow().as_ref() {
                         return Ok(inner.clone());
                     }
-                    let obj = match Tcp::from_bytes(Rc::clone(&ipv6.rawdata.borrow()), ipv6.offset)
-                    {
+                    let obj = match self.dissect("tcp", || {
+                        Tcp::from_bytes(Rc::clone(&ipv6.rawdata.borrow()), ipv6.offset)
+                    }) {
                         Ok(tcp) => Rc::new(Object::Tcp(Rc::new(tcp))),
                         Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
                     };
@@ -969,15 +1231,31 @@ impl VM {
                     obj
                 }
             }
+            PacketPropType::Gre => {
+                if let Some(val) = setval {
+                    ipv6.inner.replace(Some(val.clone()));
+                    val
+                } else {
+                    if let Some(inner) = ipv6.inner.borrow().as_ref() {
+                        return Ok(inner.clone());
+                    }
+                    let rawdata = Rc::clone(&ipv6.rawdata.borrow());
+                    let obj = match self
+                        .dissect("gre", || Tunnel::gre_from_bytes(rawdata, ipv6.offset))
+                    {
+                        Ok(gre) => Rc::new(Object::Tunnel(Rc::new(gre))),
+                        Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
+                    };
+                    // Borrow the inner object again and replace its content
+                    ipv6.inner.replace(Some(obj.clone()));
+                    obj
+                }
+            }
             PacketPropType::Payload => {
                 let payload = ipv6.rawdata.borrow().clone();
-                let mut elements = Vec::new();
                 // start at offset 'offset' to skip the ipv4 header
-                for byte in payload.iter().skip(ipv6.offset) {
-                    elements.push(Rc::new(Object::Byte(*byte)));
-                }
-                let arr = Array::new(elements);
-                Rc::new(Object::Arr(Rc::new(arr)))
+                let bytes = payload.iter().skip(ipv6.offset).copied().collect();
+                Rc::new(Object::Bytes(bytes))
             }
             _ => {
                 return Err(RTError::new(
@@ -1037,15 +1315,72 @@ impl VM {
                     udp.get_checksum()
                 }
             }
+            PacketPropType::Dns => {
+                if setval.is_some() {
+                    return Err(RTError::new("Cannot set udp property dns", line));
+                }
+                let cached = udp.inner.borrow().clone();
+                if let Some(inner) = cached.filter(|o| matches!(o.as_ref(), Object::Dns(_))) {
+                    return Ok(inner);
+                }
+                let obj = match self.dissect("dns", || {
+                    Dns::from_bytes(Rc::clone(&udp.rawdata.borrow()), udp.offset)
+                }) {
+                    Ok(dns) => Rc::new(Object::Dns(Rc::new(dns))),
+                    Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
+                };
+                udp.inner.replace(Some(obj.clone()));
+                obj
+            }
+            PacketPropType::Vxlan => {
+                if setval.is_some() {
+                    return Err(RTError::new("Cannot set udp property vxlan", line));
+                }
+                let cached = udp.inner.borrow().clone();
+                if let Some(inner) = cached.filter(|o| matches!(o.as_ref(), Object::Tunnel(_))) {
+                    return Ok(inner);
+                }
+                let rawdata = Rc::clone(&udp.rawdata.borrow());
+                let obj =
+                    match self.dissect("vxlan", || Tunnel::vxlan_from_bytes(rawdata, udp.offset)) {
+                        Ok(tunnel) => Rc::new(Object::Tunnel(Rc::new(tunnel))),
+                        Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
+                    };
+                udp.inner.replace(Some(obj.clone()));
+                obj
+            }
+            PacketPropType::Geneve => {
+                if setval.is_some() {
+                    return Err(RTError::new("Cannot set udp property geneve", line));
+                }
+                let cached = udp.inner.borrow().clone();
+                if let Some(inner) = cached.filter(|o| matches!(o.as_ref(), Object::Tunnel(_))) {
+                    return Ok(inner);
+                }
+                let rawdata = Rc::clone(&udp.rawdata.borrow());
+                let obj = match self
+                    .dissect("geneve", || Tunnel::geneve_from_bytes(rawdata, udp.offset))
+                {
+                    Ok(tunnel) => Rc::new(Object::Tunnel(Rc::new(tunnel))),
+                    Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
+                };
+                udp.inner.replace(Some(obj.clone()));
+                obj
+            }
+            PacketPropType::DetectedProto => {
+                if setval.is_some() {
+                    return Err(RTError::new("Cannot set udp property detected_proto", line));
+                }
+                match self.udp_app_proto(&udp) {
+                    Some(proto) => Rc::new(Object::Str(proto.to_string())),
+                    None => Rc::new(Object::Null),
+                }
+            }
             PacketPropType::Payload => {
                 let payload = udp.rawdata.borrow().clone();
-                let mut elements = Vec::new();
                 // start at offset 'offset' to skip the udp header
-                for byte in payload.iter().skip(udp.offset) {
-                    elements.push(Rc::new(Object::Byte(*byte)));
-                }
-                let arr = Array::new(elements);
-                Rc::new(Object::Arr(Rc::new(arr)))
+                let bytes = payload.iter().skip(udp.offset).copied().collect();
+                Rc::new(Object::Bytes(bytes))
             }
             _ => {
                 return Err(RTError::new(
@@ -1125,6 +1460,33 @@ impl VM {
                     tcp.get_flags()
                 }
             }
+            PacketPropType::Fin
+            | PacketPropType::Syn
+            | PacketPropType::Rst
+            | PacketPropType::Psh
+            | PacketPropType::AckFlag
+            | PacketPropType::Urg
+            | PacketPropType::Ece
+            | PacketPropType::Cwr => {
+                let bit = match prop {
+                    PacketPropType::Fin => TCP_FIN,
+                    PacketPropType::Syn => TCP_SYN,
+                    PacketPropType::Rst => TCP_RST,
+                    PacketPropType::Psh => TCP_PSH,
+                    PacketPropType::AckFlag => TCP_ACK,
+                    PacketPropType::Urg => TCP_URG,
+                    PacketPropType::Ece => TCP_ECE,
+                    _ => TCP_CWR,
+                };
+                if let Some(val) = setval {
+                    if let Err(e) = tcp.set_flag(bit, val.clone()) {
+                        return Err(RTError::new(&e, line));
+                    }
+                    val
+                } else {
+                    tcp.get_flag(bit)
+                }
+            }
             PacketPropType::WindowSize => {
                 if let Some(val) = setval {
                     if let Err(e) = tcp.set_window_size(val.clone()) {
@@ -1155,15 +1517,54 @@ impl VM {
                     tcp.get_urgent()
                 }
             }
+            PacketPropType::Dns => {
+                if setval.is_some() {
+                    return Err(RTError::new("Cannot set tcp property dns", line));
+                }
+                let cached = tcp.inner.borrow().clone();
+                if let Some(inner) = cached.filter(|o| matches!(o.as_ref(), Object::Dns(_))) {
+                    return Ok(inner);
+                }
+                let rawdata = Rc::clone(&tcp.rawdata.borrow());
+                let obj = match Dns::from_tcp_bytes(rawdata, tcp.get_payload_offset_raw()) {
+                    Ok(dns) => Rc::new(Object::Dns(Rc::new(dns))),
+                    Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
+                };
+                tcp.inner.replace(Some(obj.clone()));
+                obj
+            }
+            PacketPropType::Tls => {
+                if setval.is_some() {
+                    return Err(RTError::new("Cannot set tcp property tls", line));
+                }
+                let cached = tcp.inner.borrow().clone();
+                if let Some(inner) = cached.filter(|o| matches!(o.as_ref(), Object::Tls(_))) {
+                    return Ok(inner);
+                }
+                let rawdata = Rc::clone(&tcp.rawdata.borrow());
+                let obj = match self.dissect("tls", || {
+                    Tls::from_bytes(rawdata, tcp.get_payload_offset_raw())
+                }) {
+                    Ok(tls) => Rc::new(Object::Tls(Rc::new(tls))),
+                    Err(e) => Rc::new(Object::Err(ErrorObj::Packet(e))),
+                };
+                tcp.inner.replace(Some(obj.clone()));
+                obj
+            }
+            PacketPropType::DetectedProto => {
+                if setval.is_some() {
+                    return Err(RTError::new("Cannot set tcp property detected_proto", line));
+                }
+                match self.tcp_app_proto(&tcp) {
+                    Some(proto) => Rc::new(Object::Str(proto.to_string())),
+                    None => Rc::new(Object::Null),
+                }
+            }
             PacketPropType::Payload => {
                 let payload = tcp.rawdata.borrow().clone();
-                let mut elements = Vec::new();
                 // start at offset 'offset' to skip the tcp header
-                for byte in payload.iter().skip(tcp.offset) {
-                    elements.push(Rc::new(Object::Byte(*byte)));
-                }
-                let arr = Array::new(elements);
-                Rc::new(Object::Arr(Rc::new(arr)))
+                let bytes = payload.iter().skip(tcp.offset).copied().collect();
+                Rc::new(Object::Bytes(bytes))
             }
             _ => {
                 return Err(RTError::new(
@@ -1174,4 +1575,167 @@ impl VM {
         };
         Ok(obj)
     }
+
+    fn exec_prop_dns(
+        &self,
+        dns: Rc<Dns>,
+        prop: PacketPropType,
+        setval: Option<Rc<Object>>,
+        line: usize,
+    ) -> Result<Rc<Object>, RTError> {
+        if setval.is_some() {
+            return Err(RTError::new(
+                &format!("Cannot set dns property {}", prop),
+                line,
+            ));
+        }
+        let obj = match prop {
+            PacketPropType::Id => dns.get_id(),
+            PacketPropType::Flags => dns.get_flags(),
+            PacketPropType::Opcode => dns.get_opcode(),
+            PacketPropType::RCode => dns.get_rcode(),
+            PacketPropType::QName => dns.get_qname(),
+            PacketPropType::QType => dns.get_qtype(),
+            PacketPropType::Queries => dns.get_queries(),
+            PacketPropType::Answers => dns.get_answers(),
+            PacketPropType::Payload => Rc::new(Object::Bytes(dns.rawdata[dns.offset..].to_vec())),
+            _ => {
+                return Err(RTError::new(
+                    &format!("Invalid dns property '{}'", prop),
+                    line,
+                ));
+            }
+        };
+        Ok(obj)
+    }
+
+    fn exec_prop_tls(
+        &self,
+        tls: Rc<Tls>,
+        prop: PacketPropType,
+        setval: Option<Rc<Object>>,
+        line: usize,
+    ) -> Result<Rc<Object>, RTError> {
+        if setval.is_some() {
+            return Err(RTError::new(
+                &format!("Cannot set tls property {}", prop),
+                line,
+            ));
+        }
+        let obj = match prop {
+            PacketPropType::ContentType => tls.get_content_type(),
+            PacketPropType::Version => tls.get_version(),
+            PacketPropType::Handshake => tls.get_handshake(),
+            PacketPropType::Sni => tls.get_sni(),
+            PacketPropType::Alpn => tls.get_alpn(),
+            PacketPropType::Ciphers => tls.get_ciphers(),
+            PacketPropType::Ja3 => tls.get_ja3(),
+            PacketPropType::Payload => Rc::new(Object::Bytes(tls.rawdata[tls.offset..].to_vec())),
+            _ => {
+                return Err(RTError::new(
+                    &format!("Invalid tls property '{}'", prop),
+                    line,
+                ));
+            }
+        };
+        Ok(obj)
+    }
+
+    fn exec_prop_tunnel(
+        &self,
+        tunnel: Rc<Tunnel>,
+        prop: PacketPropType,
+        setval: Option<Rc<Object>>,
+        line: usize,
+    ) -> Result<Rc<Object>, RTError> {
+        let kind = tunnel.get_kind();
+        if setval.is_some() {
+            return Err(RTError::new(
+                &format!("Cannot set {} property {}", kind, prop),
+                line,
+            ));
+        }
+        let obj = match prop {
+            PacketPropType::Vni if kind != TunnelKind::Gre => tunnel.get_vni(),
+            PacketPropType::Key if kind == TunnelKind::Gre => tunnel.get_key(),
+            PacketPropType::EtherType => tunnel.get_protocol(),
+            PacketPropType::Inner => {
+                if let Some(inner) = tunnel.inner.borrow().as_ref() {
+                    return Ok(inner.clone());
+                }
+                let rawdata = Rc::clone(&tunnel.rawdata.borrow());
+                let inner = match EtherType(tunnel.get_protocol_raw()) {
+                    EtherType(TRANSPARENT_ETHERNET) => self
+                        .dissect("eth", || Ethernet::from_bytes(rawdata, tunnel.offset))
+                        .map(|eth| Object::Eth(Rc::new(eth))),
+                    EtherTypes::Ipv4 => self
+                        .dissect("ipv4", || Ipv4Packet::from_bytes(rawdata, tunnel.offset))
+                        .map(|ipv4| Object::Ipv4(Rc::new(ipv4))),
+                    EtherTypes::Ipv6 => self
+                        .dissect("ipv6", || Ipv6Packet::from_bytes(rawdata, tunnel.offset))
+                        .map(|ipv6| Object::Ipv6(Rc::new(ipv6))),
+                    _ => return Ok(Rc::new(Object::Null)),
+                };
+                let obj = Rc::new(inner.unwrap_or_else(|e| Object::Err(ErrorObj::Packet(e))));
+                tunnel.inner.replace(Some(obj.clone()));
+                obj
+            }
+            PacketPropType::Payload => Rc::new(Object::Bytes(
+                tunnel.rawdata.borrow()[tunnel.offset..].to_vec(),
+            )),
+            _ => {
+                return Err(RTError::new(
+                    &format!("Invalid {} property '{}'", kind, prop),
+                    line,
+                ));
+            }
+        };
+        Ok(obj)
+    }
+}
+
+// Values of properties as returned before addresses and payloads had
+// types of their own
+fn legacy_value(obj: Rc<Object>) -> Rc<Object> {
+    match obj.as_ref() {
+        Object::Addr(addr) => Rc::new(Object::Str(addr.to_string())),
+        Object::Bytes(bytes) => {
+            let elements = bytes.iter().map(|b| Rc::new(Object::Byte(*b))).collect();
+            Rc::new(Object::Arr(Rc::new(Array::new(elements))))
+        }
+        // The dns records are maps within arrays
+        Object::Arr(arr) => {
+            let elements = arr.elements.borrow();
+            let elements = elements.iter().cloned().map(legacy_value).collect();
+            Rc::new(Object::Arr(Rc::new(Array::new(elements))))
+        }
+        Object::Map(map) => {
+            let pairs = map.pairs.borrow();
+            let pairs = pairs
+                .iter()
+                .map(|(k, v)| (k.clone(), legacy_value(v.clone())));
+            Rc::new(Object::Map(Rc::new(HMap::new(pairs.collect()))))
+        }
+        _ => obj,
+    }
+}
+
+/// Name of the packet object as used in the property paths, or None if the
+/// object is not a packet object
+fn layer_name(obj: &Object) -> Option<String> {
+    let name = match obj {
+        Object::Pcap(_) => "pcap",
+        Object::Packet(_) => "packet",
+        Object::Eth(_) => "eth",
+        Object::Vlan(_) => "vlan",
+        Object::Ipv4(_) => "ipv4",
+        Object::Ipv6(_) => "ipv6",
+        Object::Udp(_) => "udp",
+        Object::Tcp(_) => "tcp",
+        Object::Dns(_) => "dns",
+        Object::Tls(_) => "tls",
+        Object::Tunnel(tunnel) => return Some(tunnel.get_kind().to_string()),
+        _ => return None,
+    };
+    Some(name.to_string())
 }
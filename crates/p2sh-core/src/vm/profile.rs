@@ -0,0 +1,77 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Number of calls to a dissector and the time spent in them
+#[derive(Debug, Clone)]
+pub struct DissectStats {
+    pub name: &'static str,
+    pub calls: u64,
+    pub elapsed: Duration,
+}
+
+/// Time spent by each dissector parsing the layers of packets. The
+/// dissectors are timed only when the profile is enabled on the VM.
+#[derive(Debug, Default)]
+pub struct DissectProfile {
+    stats: RefCell<Vec<DissectStats>>,
+}
+
+impl DissectProfile {
+    /// Run the dissector 'name' and add the time it took to its stats
+    pub fn time<T>(&self, name: &'static str, dissect: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = dissect();
+        let elapsed = start.elapsed();
+        let mut stats = self.stats.borrow_mut();
+        match stats.iter_mut().find(|s| s.name == name) {
+            Some(entry) => {
+                entry.calls += 1;
+                entry.elapsed += elapsed;
+            }
+            None => stats.push(DissectStats {
+                name,
+                calls: 1,
+                elapsed,
+            }),
+        }
+        result
+    }
+
+    /// Stats of the dissectors that were run, the slowest first
+    pub fn stats(&self) -> Vec<DissectStats> {
+        let mut stats = self.stats.borrow().clone();
+        stats.sort_by_key(|s| std::cmp::Reverse(s.elapsed));
+        stats
+    }
+}
+
+impl fmt::Display for DissectProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let stats = self.stats();
+        let total: Duration = stats.iter().map(|s| s.elapsed).sum();
+        writeln!(
+            f,
+            "{:<10} {:>10} {:>12} {:>10} {:>6}",
+            "dissector", "calls", "total(us)", "avg(ns)", "%"
+        )?;
+        for s in &stats {
+            let avg = s.elapsed.as_nanos() / s.calls as u128;
+            let percent = if total.is_zero() {
+                0.0
+            } else {
+                s.elapsed.as_secs_f64() * 100.0 / total.as_secs_f64()
+            };
+            writeln!(
+                f,
+                "{:<10} {:>10} {:>12} {:>10} {:>6.1}",
+                s.name,
+                s.calls,
+                s.elapsed.as_micros(),
+                avg,
+                percent
+            )?;
+        }
+        write!(f, "{:<10} {:>10} {:>12}", "total", "", total.as_micros())
+    }
+}
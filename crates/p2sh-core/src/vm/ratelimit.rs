@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::object::Object;
+
+/// Interval in seconds of packet time between the scans for full buckets
+const PRUNE_SCAN_INTERVAL: f64 = 60.0;
+
+/// A bucket of tokens that refills at 'max' tokens every 'per' seconds
+struct Bucket {
+    tokens: f64,
+    last: f64, // Time at which the tokens were last counted
+    per: f64,
+}
+
+/// A window of time in which calls of 'rate_limit' are counted
+struct Window {
+    start: f64,
+    count: i64,
+    per: f64,
+}
+
+/// Token buckets of 'ratelimit' keyed by the value given by the script,
+/// such as a flow or the name of a rule. The buckets are filled using the
+/// capture time of the packets so that a capture is throttled the same
+/// way when it is read from a file as when it was captured live. The
+/// windows of 'rate_limit' are kept the same way.
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: HashMap<Rc<Object>, Bucket>,
+    windows: HashMap<Rc<Object>, Window>,
+    last_scan: f64,
+}
+
+impl RateLimiter {
+    /// Take a token from the bucket of the key at the time 'now' and
+    /// return true, or return false if the bucket is empty. A new bucket
+    /// starts full, so that the first 'max' calls are allowed.
+    pub fn allow(&mut self, key: Rc<Object>, max: i64, per: f64, now: f64) -> bool {
+        self.prune(now);
+        let max = max as f64;
+        let bucket = self.buckets.entry(key).or_insert(Bucket {
+            tokens: max,
+            last: now,
+            per,
+        });
+        // Packets out of order do not take tokens back
+        let elapsed = (now - bucket.last).max(0.0);
+        bucket.tokens = (bucket.tokens + elapsed * max / per).min(max);
+        bucket.last = bucket.last.max(now);
+        bucket.per = per;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Count a call in the window of the key at the time 'now' and return
+    /// true if it is one of the first 'max' calls in the window. Windows
+    /// are 'per' seconds long and start at the first call of the key.
+    pub fn sample(&mut self, key: Rc<Object>, max: i64, per: f64, now: f64) -> bool {
+        self.prune(now);
+        let window = self.windows.entry(key).or_insert(Window {
+            start: now,
+            count: 0,
+            per,
+        });
+        if now - window.start >= per {
+            // Skip over the windows in which there were no calls
+            window.start += ((now - window.start) / per).floor() * per;
+            window.count = 0;
+        }
+        window.per = per;
+        window.count += 1;
+        window.count <= max
+    }
+
+    // Remove the buckets that have had the time to fill up again and the
+    // windows that have ended, which are the same as new ones, so that the
+    // keys seen once do not pile up
+    fn prune(&mut self, now: f64) {
+        if now - self.last_scan < PRUNE_SCAN_INTERVAL {
+            return;
+        }
+        self.last_scan = now;
+        self.buckets
+            .retain(|_, bucket| now - bucket.last < bucket.per);
+        self.windows
+            .retain(|_, window| now - window.start < window.per);
+    }
+}
@@ -0,0 +1,858 @@
+//! A reference interpreter for the differential tests.
+//!
+//! It walks the syntax tree of a program instead of compiling it, and is
+//! kept as plain as possible so that it is easy to check by reading it.
+//! Running the same program through it and through the compiler and the
+//! VM, and comparing the results, catches the changes to the compiler or
+//! the VM that alter what a program computes. It covers the core of the
+//! language: values, operators, variables, control flow, functions and
+//! closures, and the builtins that do not depend on the state of the VM.
+//! Programs that use anything else are reported as unsupported.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::builtins::functions::{contains, BUILTINFNS};
+use crate::object::array::Array;
+use crate::object::error::ErrorObj;
+use crate::object::hmap::HMap;
+use crate::object::Object;
+use crate::parser::ast::expr::*;
+use crate::parser::ast::stmt::*;
+use crate::parser::ast::Program;
+use crate::scanner::token::{Token, TokenType};
+
+// Loop iterations and calls a program may make before it is given up on
+const MAX_STEPS: usize = 200_000;
+// Depth of the calls a program may make
+const MAX_DEPTH: usize = 100;
+
+// Builtins whose results depend on their arguments only
+const PURE_BUILTINS: &[&str] = &[
+    "len",
+    "first",
+    "last",
+    "rest",
+    "push",
+    "pop",
+    "get",
+    "contains",
+    "insert",
+    "str",
+    "int",
+    "float",
+    "char",
+    "byte",
+    "round",
+    "tolower",
+    "toupper",
+    "is_err",
+    "err_msg",
+    "sort",
+    "chars",
+    "join",
+    "set",
+    "remove",
+    "sum",
+    "min",
+    "max",
+    "split",
+    "trim",
+    "starts_with",
+    "ends_with",
+    "replace",
+    "slice",
+];
+
+/// The reason a program did not produce a value
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    /// A runtime error with the message the VM would report
+    Runtime(String),
+    /// The program uses a part of the language that is not covered
+    Unsupported(String),
+}
+
+// Functions are kept apart from the objects since the closures of the VM
+// hold compiled functions. They may be bound to names, passed to and
+// returned from functions, and called.
+#[derive(Clone)]
+enum Value {
+    Obj(Rc<Object>),
+    Func(Rc<Function>),
+}
+
+struct Function {
+    literal: FunctionLiteral,
+    env: Env,
+}
+
+type Env = Rc<RefCell<Scope>>;
+
+// Variables outside functions are globals of the VM and are shared by the
+// functions that refer to them. The variables of a function that a closure
+// refers to are copied into the closure when it is created.
+struct Scope {
+    vars: HashMap<String, Value>,
+    parent: Option<Env>,
+    local: bool, // within a function
+}
+
+// The ways the evaluation of a statement or an expression ends early
+enum Signal {
+    Error(EvalError),
+    Break(Option<String>),
+    Continue(Option<String>),
+    Return(Value),
+}
+
+type Eval<T> = Result<T, Signal>;
+
+fn runtime<T>(msg: &str) -> Eval<T> {
+    Err(Signal::Error(EvalError::Runtime(msg.to_string())))
+}
+
+fn unsupported<T>(what: &str) -> Eval<T> {
+    Err(Signal::Error(EvalError::Unsupported(what.to_string())))
+}
+
+fn new_env(parent: Option<Env>, local: bool) -> Env {
+    Rc::new(RefCell::new(Scope {
+        vars: HashMap::new(),
+        parent,
+        local,
+    }))
+}
+
+fn define(env: &Env, name: &str, value: Value) {
+    env.borrow_mut().vars.insert(name.to_string(), value);
+}
+
+fn lookup(env: &Env, name: &str) -> Option<Value> {
+    let scope = env.borrow();
+    match scope.vars.get(name) {
+        Some(value) => Some(value.clone()),
+        None => scope.parent.as_ref().and_then(|p| lookup(p, name)),
+    }
+}
+
+fn assign(env: &Env, name: &str, value: Value) -> bool {
+    let mut scope = env.borrow_mut();
+    if let Some(var) = scope.vars.get_mut(name) {
+        *var = value;
+        return true;
+    }
+    match &scope.parent {
+        Some(parent) => assign(parent, name, value),
+        None => false,
+    }
+}
+
+// The variables of the functions a closure is created in, with the
+// innermost definition of each name, above the globals
+fn capture(env: &Env) -> Env {
+    let mut vars = HashMap::new();
+    let mut curr = Some(env.clone());
+    while let Some(scope) = curr {
+        let scope = scope.borrow();
+        if !scope.local {
+            let captured = new_env(Some(env_of(&scope, env)), true);
+            captured.borrow_mut().vars = vars;
+            return captured;
+        }
+        for (name, value) in scope.vars.iter() {
+            vars.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+        curr = scope.parent.clone();
+    }
+    let captured = new_env(None, true);
+    captured.borrow_mut().vars = vars;
+    captured
+}
+
+// The environment whose scope is 'scope', found from 'env' outwards
+fn env_of(scope: &Scope, env: &Env) -> Env {
+    let mut curr = env.clone();
+    loop {
+        if std::ptr::eq(&*curr.borrow(), scope) {
+            return curr;
+        }
+        let parent = curr.borrow().parent.clone();
+        match parent {
+            Some(parent) => curr = parent,
+            None => return curr,
+        }
+    }
+}
+
+fn obj(object: Object) -> Value {
+    Value::Obj(Rc::new(object))
+}
+
+fn null() -> Value {
+    obj(Object::Null)
+}
+
+impl Value {
+    fn object(&self) -> Eval<Rc<Object>> {
+        match self {
+            Value::Obj(o) => Ok(o.clone()),
+            Value::Func(_) => unsupported("functions used as values"),
+        }
+    }
+}
+
+pub struct Evaluator {
+    globals: Env,
+    steps: usize,
+    depth: usize,
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self {
+            globals: new_env(None, false),
+            steps: 0,
+            depth: 0,
+        }
+    }
+}
+
+impl Evaluator {
+    /// Evaluate a program that ends with an expression statement and
+    /// return the value of that expression, as the VM leaves it as the
+    /// last value popped.
+    pub fn eval_program(&mut self, program: &Program) -> Result<Rc<Object>, EvalError> {
+        let env = self.globals.clone();
+        let mut last = None;
+        for stmt in &program.statements {
+            match self.exec(stmt, &env) {
+                Ok(value) => last = value,
+                Err(Signal::Error(e)) => return Err(e),
+                Err(_) => {
+                    let msg = "break, continue or return outside of a function or loop";
+                    return Err(EvalError::Unsupported(msg.to_string()));
+                }
+            }
+        }
+        match (program.statements.last(), last) {
+            (Some(Statement::Expr(_)), Some(Value::Obj(o))) => Ok(o),
+            _ => Err(EvalError::Unsupported(
+                "program does not end with an expression".to_string(),
+            )),
+        }
+    }
+
+    fn step(&mut self) -> Eval<()> {
+        self.steps += 1;
+        if self.steps > MAX_STEPS {
+            return unsupported("too many steps");
+        }
+        Ok(())
+    }
+
+    // Run a statement and return the value of an expression statement
+    fn exec(&mut self, stmt: &Statement, env: &Env) -> Eval<Option<Value>> {
+        match stmt {
+            Statement::Expr(e) => return self.eval(&e.value, env).map(Some),
+            Statement::Let(stmt) => {
+                let value = self.eval(&stmt.value, env)?;
+                define(env, &stmt.name.value, value);
+            }
+            Statement::Return(stmt) => {
+                let value = match &stmt.value {
+                    Some(e) => self.eval(e, env)?,
+                    None => null(),
+                };
+                return Err(Signal::Return(value));
+            }
+            Statement::Block(block) => {
+                self.eval_block(block, env)?;
+            }
+            Statement::Loop(stmt) => {
+                self.run_loop(&stmt.label, env, |ev, env| {
+                    ev.eval_block(&stmt.body, env)?;
+                    Ok(true)
+                })?;
+            }
+            Statement::While(stmt) => {
+                self.run_loop(&stmt.label, env, |ev, env| {
+                    if ev.eval(&stmt.condition, env)?.object()?.is_falsey() {
+                        return Ok(false);
+                    }
+                    ev.eval_block(&stmt.body, env)?;
+                    Ok(true)
+                })?;
+            }
+            Statement::For(stmt) => self.exec_for(stmt, env)?,
+            Statement::Break(stmt) => {
+                return Err(Signal::Break(
+                    stmt.label.as_ref().map(|l| l.literal.clone()),
+                ));
+            }
+            Statement::Continue(stmt) => {
+                let label = stmt.label.as_ref().map(|l| l.literal.clone());
+                return Err(Signal::Continue(label));
+            }
+            Statement::Function(func) => {
+                let value = self.closure(func, env);
+                define(env, &func.name, value);
+            }
+            Statement::Try(stmt) => match self.eval_block(&stmt.body, env) {
+                Err(Signal::Error(EvalError::Runtime(msg))) => {
+                    let scope = new_env(Some(env.clone()), env.borrow().local);
+                    let err = obj(Object::Err(ErrorObj::Runtime(msg)));
+                    define(&scope, &stmt.var.literal, err);
+                    self.eval_block(&stmt.handler, &scope)?;
+                }
+                result => {
+                    result?;
+                }
+            },
+            Statement::Incr(stmt) => self.exec_incr(stmt, env)?,
+            Statement::Filter(_) => return unsupported("filter statements"),
+            Statement::Import(_) => return unsupported("import statements"),
+            Statement::Invalid => return unsupported("invalid statements"),
+        }
+        Ok(None)
+    }
+
+    // Run the statements of a block in a scope of their own. The value of
+    // a block is that of its last statement if it is an expression.
+    fn eval_block(&mut self, block: &BlockStatement, env: &Env) -> Eval<Value> {
+        let scope = new_env(Some(env.clone()), env.borrow().local);
+        let mut last = None;
+        for stmt in &block.statements {
+            last = self.exec(stmt, &scope)?;
+        }
+        match (block.statements.last(), last) {
+            (Some(Statement::Expr(_)), Some(value)) => Ok(value),
+            _ => Ok(null()),
+        }
+    }
+
+    // Run the iterations of a loop until 'iteration' returns false, and
+    // handle the 'break' and 'continue' statements meant for the loop
+    fn run_loop(
+        &mut self,
+        label: &Option<Token>,
+        env: &Env,
+        mut iteration: impl FnMut(&mut Self, &Env) -> Eval<bool>,
+    ) -> Eval<()> {
+        let label = label.as_ref().map(|l| l.literal.clone());
+        loop {
+            self.step()?;
+            match iteration(self, env) {
+                Ok(true) => {}
+                Ok(false) => return Ok(()),
+                Err(Signal::Break(None)) => return Ok(()),
+                Err(Signal::Break(Some(l))) if Some(&l) == label.as_ref() => return Ok(()),
+                Err(Signal::Continue(None)) => {}
+                Err(Signal::Continue(Some(l))) if Some(&l) == label.as_ref() => {}
+                Err(signal) => return Err(signal),
+            }
+        }
+    }
+
+    fn exec_for(&mut self, stmt: &ForStmt, env: &Env) -> Eval<()> {
+        let items: Vec<Vec<Value>> = match &stmt.iterable {
+            Expression::Range(range) => {
+                let begin = self.eval(&range.begin, env)?.object()?;
+                let end = self.eval(&range.end, env)?.object()?;
+                let (Object::Integer(begin), Object::Integer(end)) = (&*begin, &*end) else {
+                    return runtime("Range bounds must be integers.");
+                };
+                let end = if range.operator == "..=" {
+                    end.saturating_add(1)
+                } else {
+                    *end
+                };
+                // Ranges are not collected since they may be long
+                let mut next = *begin;
+                let var = &stmt.vars[0].literal;
+                return self.run_loop(&stmt.label, env, |ev, env| {
+                    if next >= end {
+                        return Ok(false);
+                    }
+                    let scope = new_env(Some(env.clone()), env.borrow().local);
+                    define(&scope, var, obj(Object::Integer(next)));
+                    next += 1;
+                    ev.eval_block(&stmt.body, &scope)?;
+                    Ok(true)
+                });
+            }
+            iterable => {
+                let iterable = self.eval(iterable, env)?.object()?;
+                self.iter_items(&iterable, stmt.vars.len())?
+            }
+        };
+        let mut items = items.into_iter();
+        self.run_loop(&stmt.label, env, |ev, env| {
+            let Some(values) = items.next() else {
+                return Ok(false);
+            };
+            let scope = new_env(Some(env.clone()), env.borrow().local);
+            for (var, value) in stmt.vars.iter().zip(values) {
+                define(&scope, &var.literal, value);
+            }
+            ev.eval_block(&stmt.body, &scope)?;
+            Ok(true)
+        })
+    }
+
+    // The values of the loop variables in each iteration
+    fn iter_items(&mut self, iterable: &Rc<Object>, num_vars: usize) -> Eval<Vec<Vec<Value>>> {
+        let values: Vec<Rc<Object>> = match iterable.as_ref() {
+            Object::Arr(_) | Object::Str(_) | Object::Bytes(_) => {
+                iterable.seq_elements().unwrap_or_default()
+            }
+            Object::Set(set) => {
+                let mut elements: Vec<Rc<Object>> = set.elements.borrow().iter().cloned().collect();
+                elements.sort_by(|a, b| a.total_cmp(b));
+                elements
+            }
+            Object::Map(map) => {
+                let mut pairs: Vec<(Rc<Object>, Rc<Object>)> = map
+                    .pairs
+                    .borrow()
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+                return Ok(pairs
+                    .into_iter()
+                    .map(|(k, v)| match num_vars {
+                        1 => vec![Value::Obj(k)],
+                        _ => vec![Value::Obj(k), Value::Obj(v)],
+                    })
+                    .collect());
+            }
+            Object::File(_) => return unsupported("iterating over files"),
+            #[cfg(feature = "pcap")]
+            Object::Packet(_) => return unsupported("iterating over files"),
+            _ => return runtime(&format!("Cannot iterate over {}.", iterable)),
+        };
+        Ok(values
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| match num_vars {
+                1 => vec![Value::Obj(v)],
+                _ => vec![obj(Object::Integer(i as i64)), Value::Obj(v)],
+            })
+            .collect())
+    }
+
+    fn closure(&mut self, literal: &FunctionLiteral, env: &Env) -> Value {
+        Value::Func(Rc::new(Function {
+            literal: literal.clone(),
+            env: capture(env),
+        }))
+    }
+
+    fn eval(&mut self, expr: &Expression, env: &Env) -> Eval<Value> {
+        match expr {
+            Expression::Null(_) => Ok(null()),
+            Expression::Integer(n) => Ok(obj(Object::Integer(n.value))),
+            Expression::Float(f) => Ok(obj(Object::Float(f.value))),
+            Expression::Str(s) => Ok(obj(Object::Str(s.value.clone()))),
+            Expression::Char(c) => Ok(obj(Object::Char(c.value))),
+            Expression::Byte(b) => Ok(obj(Object::Byte(b.value))),
+            Expression::Bool(b) => Ok(obj(Object::Bool(b.value))),
+            Expression::Ident(ident) => self.eval_ident(ident, env),
+            Expression::Unary(u) => self.eval_unary(u, env),
+            Expression::Binary(b) => self.eval_binary(b, env),
+            Expression::If(e) => self.eval_if(e, env),
+            Expression::Ternary(e) => {
+                if !self.eval(&e.condition, env)?.object()?.is_falsey() {
+                    self.eval(&e.then_expr, env)
+                } else {
+                    self.eval(&e.else_expr, env)
+                }
+            }
+            Expression::Match(e) => self.eval_match(e, env),
+            Expression::Function(func) => Ok(self.closure(func, env)),
+            Expression::Call(call) => self.eval_call(call, env),
+            Expression::Array(arr) => {
+                let mut elements = Vec::with_capacity(arr.elements.len());
+                for e in &arr.elements {
+                    elements.push(self.eval(e, env)?.object()?);
+                }
+                Ok(obj(Object::Arr(Rc::new(Array::new(elements)))))
+            }
+            Expression::Hash(map) => {
+                #[allow(clippy::mutable_key_type)]
+                let mut pairs = HashMap::new();
+                for (k, v) in &map.pairs {
+                    let key = self.eval(k, env)?.object()?;
+                    let value = self.eval(v, env)?.object()?;
+                    if !key.is_a_valid_key() {
+                        return runtime(&format!("KeyError: not a valid key: {}.", key));
+                    }
+                    pairs.insert(key.deep_copy(), value);
+                }
+                Ok(obj(Object::Map(Rc::new(HMap::new(pairs)))))
+            }
+            Expression::Index(e) => {
+                let left = self.eval(&e.left, env)?.object()?;
+                let index = self.eval(&e.index, env)?.object()?;
+                index_get(&left, &index).map(Value::Obj)
+            }
+            Expression::Assign(e) => self.eval_assign(e, env),
+            Expression::Range(_) => unsupported("ranges outside of for loops"),
+            Expression::Score(_)
+            | Expression::Builtin(_)
+            | Expression::Dot(_)
+            | Expression::Prop(_) => unsupported("packets and builtin variables"),
+            Expression::Invalid => unsupported("invalid expressions"),
+        }
+    }
+
+    fn eval_ident(&mut self, ident: &Identifier, env: &Env) -> Eval<Value> {
+        if let Some(value) = lookup(env, &ident.value) {
+            return Ok(value);
+        }
+        match BUILTINFNS.iter().find(|b| b.name == ident.value) {
+            Some(bt) => Ok(obj(Object::Builtin(Rc::new(bt.clone())))),
+            None => unsupported(&format!("undefined identifier '{}'", ident.value)),
+        }
+    }
+
+    fn eval_unary(&mut self, u: &UnaryExpr, env: &Env) -> Eval<Value> {
+        let right = self.eval(&u.right, env)?.object()?;
+        match u.operator.as_str() {
+            "!" => Ok(obj(Object::Bool(right.is_falsey()))),
+            "-" if right.is_number() => Ok(obj(-&*right)),
+            "-" => runtime("bad operand type for unary '-'"),
+            "~" => match &*right {
+                Object::Integer(n) => Ok(obj(Object::Integer(!n))),
+                _ => runtime("bad operand type for unary '~'"),
+            },
+            _ => unsupported(&format!("unary operator '{}'", u.operator)),
+        }
+    }
+
+    fn eval_binary(&mut self, b: &BinaryExpr, env: &Env) -> Eval<Value> {
+        let op = b.operator.as_str();
+        match op {
+            // The right operand is only evaluated if the left one does not
+            // decide the result, which is the operand that decided it
+            "&&" | "||" => {
+                let left = self.eval(&b.left, env)?;
+                if left.object()?.is_falsey() == (op == "&&") {
+                    return Ok(left);
+                }
+                return self.eval(&b.right, env);
+            }
+            "~" | "!~" => return unsupported("regular expressions"),
+            _ => {}
+        }
+        // The VM evaluates the right operand of '<' and '<=' first as it
+        // swaps the operands to compare them using '>' and '>='
+        let (left, right) = if op == "<" || op == "<=" {
+            let right = self.eval(&b.right, env)?.object()?;
+            (self.eval(&b.left, env)?.object()?, right)
+        } else {
+            let left = self.eval(&b.left, env)?.object()?;
+            (left, self.eval(&b.right, env)?.object()?)
+        };
+        let result = match op {
+            "==" => Object::Bool(left == right),
+            "!=" => Object::Bool(left != right),
+            "<" => arith(">", &right, &left)?,
+            "<=" => arith(">=", &right, &left)?,
+            "&" | "|" | "^" | "<<" | ">>" => {
+                let (Object::Integer(_), Object::Integer(_)) = (&*left, &*right) else {
+                    return runtime("Invalid bitwise operation.");
+                };
+                match op {
+                    "&" => &*left & &*right,
+                    "|" => &*left | &*right,
+                    "^" => &*left ^ &*right,
+                    "<<" => &*left << &*right,
+                    _ => &*left >> &*right,
+                }
+            }
+            "in" => match contains(&right, &left) {
+                Ok(found) => Object::Bool(found),
+                Err(e) => return runtime(&format!("Operator 'in' failed: {}.", e)),
+            },
+            _ => arith(op, &left, &right)?,
+        };
+        Ok(obj(result))
+    }
+
+    fn eval_if(&mut self, e: &IfExpr, env: &Env) -> Eval<Value> {
+        if !self.eval(&e.condition, env)?.object()?.is_falsey() {
+            return self.eval_block(&e.then_stmt, env);
+        }
+        match &e.else_if {
+            ElseIfExpr::Empty => Ok(null()),
+            ElseIfExpr::Else(block) => self.eval_block(block, env),
+            ElseIfExpr::ElseIf(expr) => self.eval(expr, env),
+        }
+    }
+
+    // The arms are tried in order. If none of them matches, the value of
+    // the match expression is the value matched against.
+    fn eval_match(&mut self, e: &MatchExpr, env: &Env) -> Eval<Value> {
+        let value = self.eval(&e.expr, env)?.object()?;
+        for arm in &e.arms {
+            for pattern in &arm.patterns {
+                let matched = match pattern {
+                    MatchPattern::Boolean(b) => *value == Object::Bool(b.value),
+                    MatchPattern::Integer(n) => *value == Object::Integer(n.value),
+                    MatchPattern::Str(s) => *value == Object::Str(s.value.clone()),
+                    MatchPattern::Char(c) => *value == Object::Char(c.value),
+                    MatchPattern::Byte(b) => *value == Object::Byte(b.value),
+                    MatchPattern::Range(r) => {
+                        let begin = self.eval(&r.begin, env)?.object()?;
+                        let end = self.eval(&r.end, env)?.object()?;
+                        let cmp = if r.operator == ".." { ">=" } else { ">" };
+                        !arith(">=", &value, &begin)?.is_falsey()
+                            && arith(cmp, &value, &end)?.is_falsey()
+                    }
+                    MatchPattern::Default(_) => true,
+                };
+                if matched {
+                    return self.eval_block(&arm.body, env);
+                }
+            }
+        }
+        Ok(Value::Obj(value))
+    }
+
+    fn eval_call(&mut self, call: &CallExpr, env: &Env) -> Eval<Value> {
+        let callee = self.eval(&call.func, env)?;
+        let mut args = Vec::with_capacity(call.args.len());
+        for arg in &call.args {
+            args.push(self.eval(arg, env)?);
+        }
+        self.step()?;
+        match callee {
+            Value::Func(func) => self.call_function(&func, args),
+            Value::Obj(o) => match &*o {
+                Object::Builtin(bt) => {
+                    if bt.name == "globals" || bt.name == "locals" {
+                        return unsupported("introspection");
+                    }
+                    if !PURE_BUILTINS.contains(&bt.name) {
+                        return unsupported(&format!("builtin '{}'", bt.name));
+                    }
+                    let args = args
+                        .iter()
+                        .map(|a| a.object())
+                        .collect::<Eval<Vec<Rc<Object>>>>()?;
+                    match (bt.func)(args) {
+                        Ok(result) => Ok(Value::Obj(result)),
+                        Err(e) => runtime(&format!("{}: {}", bt.name, e)),
+                    }
+                }
+                Object::Clos(_) => unsupported("compiled closures"),
+                _ => runtime("calling non-function"),
+            },
+        }
+    }
+
+    // A function runs in a scope of its own with its name bound to itself,
+    // so that it can call itself, followed by the parameters
+    fn call_function(&mut self, func: &Rc<Function>, args: Vec<Value>) -> Eval<Value> {
+        let literal = &func.literal;
+        if args.len() != literal.params.len() {
+            return runtime(&format!(
+                "wrong number of arguments: want={}, got={}",
+                literal.params.len(),
+                args.len()
+            ));
+        }
+        if self.depth >= MAX_DEPTH {
+            return unsupported("deep recursion");
+        }
+        let scope = new_env(Some(func.env.clone()), true);
+        if !literal.name.is_empty() {
+            define(&scope, &literal.name, Value::Func(func.clone()));
+        }
+        for (param, arg) in literal.params.iter().zip(args) {
+            define(&scope, &param.value, arg);
+        }
+        self.depth += 1;
+        let result = self.eval_body(&literal.body, &scope);
+        self.depth -= 1;
+        match result {
+            Ok(value) | Err(Signal::Return(value)) => Ok(value),
+            Err(signal) => Err(signal),
+        }
+    }
+
+    // Functions return the value of their last statement if it is an
+    // expression, including one at the end of a block
+    fn eval_body(&mut self, body: &BlockStatement, env: &Env) -> Eval<Value> {
+        let mut last = None;
+        for stmt in &body.statements {
+            last = match stmt {
+                Statement::Block(block) => Some(self.eval_body(block, env)?),
+                stmt => self.exec(stmt, env)?,
+            };
+        }
+        match (body.statements.last(), last) {
+            (Some(Statement::Expr(_) | Statement::Block(_)), Some(value)) => Ok(value),
+            _ => Ok(null()),
+        }
+    }
+
+    // The value is evaluated before the variable or the element assigned
+    // 'x++' and 'x--' evaluate the container and the index of an element
+    // once, unlike the equivalent assignment
+    fn exec_incr(&mut self, stmt: &IncrStmt, env: &Env) -> Eval<()> {
+        let op = match stmt.token.ttype {
+            TokenType::MinusMinus => "-",
+            _ => "+",
+        };
+        let one = Rc::new(Object::Integer(1));
+        match &stmt.target {
+            Expression::Ident(ident) => {
+                let value = self.eval_ident(ident, env)?.object()?;
+                let value = obj(arith(op, &value, &one)?);
+                if !assign(env, &ident.value, value) {
+                    return unsupported(&format!("undefined identifier '{}'", ident.value));
+                }
+            }
+            Expression::Index(ie) => {
+                let left = self.eval(&ie.left, env)?.object()?;
+                let index = self.eval(&ie.index, env)?.object()?;
+                let value = index_get(&left, &index)?;
+                index_set(&left, &index, Rc::new(arith(op, &value, &one)?))?;
+            }
+            _ => return unsupported("increment of properties"),
+        }
+        Ok(())
+    }
+
+    fn eval_assign(&mut self, e: &AssignExpr, env: &Env) -> Eval<Value> {
+        let value = self.eval(&e.right, env)?;
+        match &*e.left {
+            Expression::Ident(ident) => {
+                if !assign(env, &ident.value, value.clone()) {
+                    return unsupported(&format!("undefined identifier '{}'", ident.value));
+                }
+                Ok(value)
+            }
+            Expression::Index(ie) => {
+                let left = self.eval(&ie.left, env)?.object()?;
+                let index = self.eval(&ie.index, env)?.object()?;
+                index_set(&left, &index, value.object()?).map(Value::Obj)
+            }
+            _ => unsupported("assignment to properties"),
+        }
+    }
+}
+
+fn index_get(left: &Rc<Object>, index: &Rc<Object>) -> Eval<Rc<Object>> {
+    match (&**left, &**index) {
+        (Object::Map(map), _) => {
+            if !index.is_a_valid_key() {
+                return runtime(&format!("KeyError: not a valid key: {}.", index));
+            }
+            let value = map.get_or_default(index);
+            if value.is_null() {
+                return runtime("KeyError: key not found.");
+            }
+            Ok(value)
+        }
+        (seq, Object::Integer(idx)) => {
+            let Some(name) = seq.seq_name() else {
+                return runtime("IndexError: unsupported operation.");
+            };
+            if *idx < 0 {
+                return runtime("IndexError: index cannot be negative.");
+            }
+            match seq.seq_get(*idx as usize) {
+                Some(value) => Ok(value),
+                None => runtime(&format!("IndexError: {} index out of range.", name)),
+            }
+        }
+        _ => runtime("IndexError: unsupported operation."),
+    }
+}
+
+fn index_set(left: &Rc<Object>, index: &Rc<Object>, value: Rc<Object>) -> Eval<Rc<Object>> {
+    match (&**left, &**index) {
+        (Object::Map(map), _) => {
+            if !index.is_a_valid_key() {
+                return runtime(&format!("KeyError: not a valid key: {}.", index));
+            }
+            map.insert(index.clone(), value.clone());
+            Ok(value)
+        }
+        (seq, Object::Integer(idx)) => {
+            let Some(name) = seq.seq_name() else {
+                return runtime("IndexError: unsupported operation.");
+            };
+            let Object::Arr(arr) = seq else {
+                return runtime(&format!("IndexError: {} cannot be modified.", name));
+            };
+            if *idx < 0 {
+                return runtime("IndexError: index cannot be negative.");
+            } else if *idx as usize >= arr.len() {
+                return runtime(&format!("IndexError: {} index out of range.", name));
+            }
+            arr.set(*idx as usize, value.clone());
+            Ok(value)
+        }
+        _ => runtime("IndexError: unsupported operation."),
+    }
+}
+
+// The arithmetic and the relational operators
+fn arith(op: &str, left: &Rc<Object>, right: &Rc<Object>) -> Eval<Object> {
+    let relational = op == ">" || op == ">=";
+    let compare = |a: &Object, b: &Object| match op {
+        ">" => Object::Bool(a > b),
+        _ => Object::Bool(a >= b),
+    };
+    match (&**left, &**right) {
+        (
+            Object::Integer(_) | Object::Float(_) | Object::Byte(_),
+            Object::Integer(_) | Object::Float(_) | Object::Byte(_),
+        ) => match op {
+            "+" => Ok(&**left + &**right),
+            "-" => Ok(&**left - &**right),
+            "*" => Ok(&**left * &**right),
+            "/" if right.is_zero() => runtime("Division by zero."),
+            "/" => Ok(&**left / &**right),
+            "%" if right.is_zero() => runtime("Modulo by zero."),
+            "%" => Ok(&**left % &**right),
+            _ => Ok(compare(left, right)),
+        },
+        (Object::Str(a), Object::Str(b)) => match op {
+            "+" => Ok(Object::Str(format!("{}{}", a, b))),
+            _ if relational => Ok(compare(left, right)),
+            _ => runtime("Invalid operation on strings."),
+        },
+        (Object::Char(a), Object::Char(b)) => match op {
+            "+" => Ok(Object::Str(format!("{}{}", a, b))),
+            _ if relational => Ok(compare(left, right)),
+            _ => runtime("Invalid operation on chars."),
+        },
+        (Object::Str(s), Object::Integer(n)) | (Object::Integer(n), Object::Str(s)) => {
+            if op != "*" {
+                return runtime("Invalid operation on strings.");
+            }
+            if *n > 1 << 16 {
+                return unsupported("long strings");
+            }
+            Ok(Object::Str(s.repeat((*n).max(0) as usize)))
+        }
+        (Object::Arr(a), Object::Arr(b)) => {
+            if op != "+" {
+                return runtime("Invalid operation on arrays.");
+            }
+            let mut elements = a.elements.borrow().clone();
+            elements.extend_from_slice(&b.elements.borrow());
+            Ok(Object::Arr(Rc::new(Array::new(elements))))
+        }
+        (Object::Timestamp(_) | Object::Duration(_), _)
+        | (_, Object::Timestamp(_) | Object::Duration(_)) => unsupported("timestamps"),
+        _ => runtime("Invalid binary operation."),
+    }
+}
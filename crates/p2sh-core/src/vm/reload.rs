@@ -0,0 +1,106 @@
+//! Reloading the script of a live capture.
+//!
+//! A long running monitor may be updated by editing its script, or by
+//! sending it SIGHUP, without stopping the capture. The script is compiled
+//! again between packets and the globals whose names the new script still
+//! declares keep their values, so the statistics gathered so far survive
+//! the reload.
+
+#[cfg(feature = "pcap")]
+use std::fs;
+#[cfg(feature = "pcap")]
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+#[cfg(feature = "pcap")]
+use std::time::{Duration, Instant, SystemTime};
+
+#[cfg(feature = "pcap")]
+use crate::builtins::capture;
+use crate::object::Object;
+
+/// Interval between the checks for changes to the script file
+#[cfg(feature = "pcap")]
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Watches a script file for changes and for SIGHUP
+#[cfg(feature = "pcap")]
+#[derive(Debug)]
+pub struct ScriptWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+#[cfg(feature = "pcap")]
+impl ScriptWatcher {
+    pub fn new(path: &Path) -> Self {
+        capture::watch_hangup();
+        Self {
+            path: path.to_path_buf(),
+            modified: modified(path),
+            last_check: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Return true if the script is to be reloaded, which is on SIGHUP or
+    /// once the modification time of the file changes. The file is
+    /// looked at no more than once a second.
+    pub fn changed(&mut self) -> bool {
+        if capture::take_hangup() {
+            self.modified = modified(&self.path);
+            return true;
+        }
+        if self.last_check.elapsed() < CHECK_INTERVAL {
+            return false;
+        }
+        self.last_check = Instant::now();
+        let modified = modified(&self.path);
+        // The file may be missing for a moment while an editor saves it
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+#[cfg(feature = "pcap")]
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Copy the values of the globals of the old script to the globals of the
+/// new one with the same names, and return their count. Globals holding
+/// functions in either script keep the values set by the new script, so
+/// that the functions take their new definitions.
+pub fn carry_over_globals(
+    old_names: &[String],
+    old: &[Rc<Object>],
+    new_names: &[String],
+    new: &mut [Rc<Object>],
+) -> usize {
+    let is_function =
+        |obj: &Object| matches!(obj, Object::Func(_) | Object::Clos(_) | Object::Builtin(_));
+    let mut count = 0;
+    for (old_idx, name) in old_names.iter().enumerate() {
+        if name.is_empty() {
+            continue;
+        }
+        let Some(new_idx) = new_names.iter().position(|n| n == name) else {
+            continue;
+        };
+        let (Some(value), Some(slot)) = (old.get(old_idx), new.get_mut(new_idx)) else {
+            continue;
+        };
+        if is_function(value) || is_function(slot) {
+            continue;
+        }
+        *slot = value.clone();
+        count += 1;
+    }
+    count
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+
+const HOSTS_PATH: &str = "/etc/hosts";
+const SERVICES_PATH: &str = "/etc/services";
+
+/// Resolution of addresses to host names and of ports to service names
+/// for 'resolve' and 'service_name'. The hosts and services files are
+/// read on first use and the names found, or not found, are cached so
+/// that every address is looked up once.
+#[derive(Default)]
+pub struct Resolver {
+    no_dns: bool, // look up the hosts file only
+    hosts: Option<HashMap<IpAddr, String>>,
+    services: Option<HashMap<(u16, String), String>>,
+    names: HashMap<IpAddr, Option<String>>,
+}
+
+impl Resolver {
+    /// Do not query the name servers for the addresses that are not in
+    /// the hosts file
+    pub fn set_no_dns(&mut self, no_dns: bool) {
+        self.no_dns = no_dns;
+    }
+
+    /// Use the entries in the text instead of the hosts file
+    pub fn set_hosts(&mut self, text: &str) {
+        self.hosts = Some(parse_hosts(text));
+        self.names.clear();
+    }
+
+    /// Use the entries in the text instead of the services file
+    pub fn set_services(&mut self, text: &str) {
+        self.services = Some(parse_services(text));
+    }
+
+    /// Name of the host with the address from the hosts file, else from
+    /// a reverse DNS lookup
+    pub fn host_name(&mut self, ip: IpAddr) -> Option<String> {
+        if let Some(name) = self.names.get(&ip) {
+            return name.clone();
+        }
+        let hosts = self.hosts.get_or_insert_with(|| {
+            parse_hosts(&fs::read_to_string(HOSTS_PATH).unwrap_or_default())
+        });
+        let name = match hosts.get(&ip) {
+            Some(name) => Some(name.clone()),
+            None if !self.no_dns => reverse_dns(ip),
+            None => None,
+        };
+        self.names.insert(ip, name.clone());
+        name
+    }
+
+    /// Name of the service on a port of a transport protocol such as tcp
+    pub fn service_name(&mut self, port: u16, proto: &str) -> Option<String> {
+        let services = self.services.get_or_insert_with(|| {
+            parse_services(&fs::read_to_string(SERVICES_PATH).unwrap_or_default())
+        });
+        services.get(&(port, proto.to_ascii_lowercase())).cloned()
+    }
+}
+
+// Parse the lines of a hosts file, e.g. '127.0.0.1 localhost'. The first
+// name given to an address is its canonical name.
+fn parse_hosts(text: &str) -> HashMap<IpAddr, String> {
+    let mut hosts = HashMap::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let (Some(addr), Some(name)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        if let Ok(ip) = addr.parse::<IpAddr>() {
+            hosts.entry(ip).or_insert_with(|| name.to_string());
+        }
+    }
+    hosts
+}
+
+// Parse the lines of a services file, e.g. 'http 80/tcp www'
+fn parse_services(text: &str) -> HashMap<(u16, String), String> {
+    let mut services = HashMap::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let (Some(name), Some(port_proto)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let Some((port, proto)) = port_proto.split_once('/') else {
+            continue;
+        };
+        if let Ok(port) = port.parse::<u16>() {
+            services
+                .entry((port, proto.to_ascii_lowercase()))
+                .or_insert_with(|| name.to_string());
+        }
+    }
+    services
+}
+
+// Look up the name of an address using the resolver of the system
+#[cfg(target_os = "linux")]
+fn reverse_dns(ip: IpAddr) -> Option<String> {
+    use std::ffi::CStr;
+    use std::mem;
+
+    // Size of the buffer for the host name, NI_MAXHOST in glibc
+    const MAX_HOST: usize = 1025;
+
+    let mut host = [0 as libc::c_char; MAX_HOST];
+    let ret = match ip {
+        IpAddr::V4(ip) => {
+            let mut addr: libc::sockaddr_in = unsafe { mem::zeroed() };
+            addr.sin_family = libc::AF_INET as libc::sa_family_t;
+            addr.sin_addr.s_addr = u32::from_ne_bytes(ip.octets());
+            unsafe {
+                libc::getnameinfo(
+                    &addr as *const libc::sockaddr_in as *const libc::sockaddr,
+                    mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+                    host.as_mut_ptr(),
+                    MAX_HOST as libc::socklen_t,
+                    std::ptr::null_mut(),
+                    0,
+                    libc::NI_NAMEREQD,
+                )
+            }
+        }
+        IpAddr::V6(ip) => {
+            let mut addr: libc::sockaddr_in6 = unsafe { mem::zeroed() };
+            addr.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            addr.sin6_addr.s6_addr = ip.octets();
+            unsafe {
+                libc::getnameinfo(
+                    &addr as *const libc::sockaddr_in6 as *const libc::sockaddr,
+                    mem::size_of::<libc::sockaddr_in6>() as libc::socklen_t,
+                    host.as_mut_ptr(),
+                    MAX_HOST as libc::socklen_t,
+                    std::ptr::null_mut(),
+                    0,
+                    libc::NI_NAMEREQD,
+                )
+            }
+        }
+    };
+    if ret != 0 {
+        return None;
+    }
+    let name = unsafe { CStr::from_ptr(host.as_ptr()) };
+    name.to_str().ok().map(String::from)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reverse_dns(_ip: IpAddr) -> Option<String> {
+    None
+}
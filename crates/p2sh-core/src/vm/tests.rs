@@ -0,0 +1,6698 @@
+#![allow(unused_imports)]
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::*;
+use crate::builtins::variables::BuiltinVarType;
+use crate::code::definitions::{make, Instructions};
+use crate::code::opcode::Opcode;
+use crate::compiler::*;
+use crate::object::array::Array;
+use crate::object::func::CompiledFunction;
+use crate::object::hmap::HMap;
+use crate::object::hset::HSet;
+use crate::object::Object;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::vm::error::RTError;
+use crate::vm::interpreter::VM;
+use rand::Rng;
+
+#[cfg(test)]
+fn check_parse_errors(parser: &Parser) {
+    if parser.print_errors() {
+        panic!("{} parse errors", parser.parse_errors().len());
+    }
+}
+
+#[cfg(test)]
+fn test_expected_object(evaluated: Rc<Object>, expected: &Object) {
+    match (evaluated.as_ref(), expected) {
+        (Object::Integer(eval), Object::Integer(exp)) => {
+            assert_eq!(
+                eval, exp,
+                "object has wrong integer value. got={}, want={}",
+                eval, exp
+            );
+        }
+        (Object::Float(eval), Object::Float(exp)) => {
+            assert_eq!(
+                eval, exp,
+                "object has wrong floating value. got={}, want={}",
+                eval, exp
+            );
+        }
+        (Object::Str(eval), Object::Str(exp)) => {
+            assert_eq!(
+                eval, exp,
+                "object has wrong string value. got={}, want={}",
+                eval, exp
+            );
+        }
+        (Object::Char(eval), Object::Char(exp)) => {
+            assert_eq!(
+                eval, exp,
+                "object has wrong char value. got={}, want={}",
+                eval, exp
+            );
+        }
+        (Object::Byte(eval), Object::Byte(exp)) => {
+            assert_eq!(
+                eval, exp,
+                "object has wrong byte value. got={}, want={}",
+                eval, exp
+            );
+        }
+        (Object::Bool(eval), Object::Bool(exp)) => {
+            assert_eq!(
+                eval, exp,
+                "object has wrong boolean value. got={}, want={}",
+                eval, exp
+            );
+        }
+        (Object::Arr(eval), Object::Arr(exp)) => {
+            assert_eq!(
+                eval.len(),
+                exp.len(),
+                "array object has wrong length. got={}, want={}",
+                eval.len(),
+                exp.len()
+            );
+            for (ex, ev) in exp
+                .elements
+                .borrow()
+                .iter()
+                .zip(eval.elements.borrow().iter())
+            {
+                assert_eq!(ex, ev);
+            }
+        }
+        (Object::Map(eval), Object::Map(exp)) => {
+            assert_eq!(
+                eval.len(),
+                exp.len(),
+                "map object has wrong length. got={}, want={}",
+                eval.len(),
+                exp.len()
+            );
+            assert_eq!(eval, exp);
+        }
+        (Object::Set(eval), Object::Set(exp)) => {
+            assert_eq!(eval, exp, "set object has wrong elements");
+        }
+        (Object::Bytes(eval), Object::Bytes(exp)) => {
+            assert_eq!(eval, exp, "bytes object has wrong bytes");
+        }
+        (_, Object::Null) => {
+            assert_eq!(
+                evaluated,
+                Rc::new(Object::Null),
+                "object is not Null. got={:?}",
+                evaluated
+            );
+        }
+        _ => {
+            panic!(
+                "invalid object types. got={:?}, want={:?}",
+                evaluated, expected
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+struct VmTestCase {
+    input: &'static str,
+    expected: Object,
+}
+
+#[cfg(test)]
+struct VmTestCaseErr {
+    input: &'static str,
+    expected: &'static str,
+}
+
+#[cfg(test)]
+fn test_compile(input: &str) -> Bytecode {
+    use crate::compiler::{Bytecode, Compiler};
+
+    let scanner = Scanner::new(input);
+    let mut parser = Parser::new(scanner);
+    let program = parser.parse_program();
+    check_parse_errors(&parser);
+    let mut compiler = Compiler::new();
+    if let Err(e) = compiler.compile(program) {
+        panic!("Compilation error: {}", e);
+    }
+    compiler.bytecode()
+}
+
+#[cfg(test)]
+fn run_vm_tests(tests: &[VmTestCase]) {
+    for (i, t) in tests.iter().enumerate() {
+        println!("[{}] VM Test", i);
+        let bytecode = test_compile(t.input);
+        let mut vm = VM::new(bytecode);
+        let arr = Rc::new(Object::Arr(Rc::new(Array::new(Vec::new()))));
+        vm.update_builtin_var(BuiltinVarType::Argv, arr);
+        let err = vm.run();
+        if let Err(err) = err {
+            panic!("Test [{}] vm error: {}", i, err);
+        }
+        // Get the object at the top of the VM's stack
+        let stack_elem = vm.last_popped();
+        test_expected_object(Rc::clone(&stack_elem), &t.expected);
+    }
+}
+
+#[cfg(test)]
+fn run_vm_negative_tests(tests: &[VmTestCaseErr]) {
+    for (i, t) in tests.iter().enumerate() {
+        let bytecode = test_compile(t.input);
+        let mut vm = VM::new(bytecode);
+        let err = vm.run();
+        if let Err(err) = err {
+            assert_eq!(err.msg, t.expected, "Test {}", i);
+        }
+    }
+}
+
+#[test]
+fn test_integer_arithmetic() {
+    let tests = vec![
+        VmTestCase {
+            input: "1",
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: "2",
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: "1 + 2",
+            expected: Object::Integer(3),
+        },
+        VmTestCase {
+            input: "1 - 2",
+            expected: Object::Integer(-1),
+        },
+        VmTestCase {
+            input: "1 * 2",
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: "4 / 2",
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: "10 % 3",
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: "50 / 2 * 2 + 10 - 5",
+            expected: Object::Integer(55),
+        },
+        VmTestCase {
+            input: "5 + 5 + 5 + 5 - 10",
+            expected: Object::Integer(10),
+        },
+        VmTestCase {
+            input: "2 * 2 * 2 * 2 * 2",
+            expected: Object::Integer(32),
+        },
+        VmTestCase {
+            input: "5 * 2 + 10",
+            expected: Object::Integer(20),
+        },
+        VmTestCase {
+            input: "5 + 2 * 10",
+            expected: Object::Integer(25),
+        },
+        VmTestCase {
+            input: "5 * (2 + 10)",
+            expected: Object::Integer(60),
+        },
+        VmTestCase {
+            input: "-5",
+            expected: Object::Integer(-5),
+        },
+        VmTestCase {
+            input: "-10",
+            expected: Object::Integer(-10),
+        },
+        VmTestCase {
+            input: "-50 + 100 + -50",
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            input: "(5 + 10 * 2 + 15 / 3) * 2 + -10",
+            expected: Object::Integer(50),
+        },
+        // Integer arithmetic wraps around
+        VmTestCase {
+            input: "9223372036854775807 + 1",
+            expected: Object::Integer(i64::MIN),
+        },
+        VmTestCase {
+            input: "-(-9223372036854775807 - 1)",
+            expected: Object::Integer(i64::MIN),
+        },
+        VmTestCase {
+            input: "1 << 65",
+            expected: Object::Integer(2),
+        },
+        // Integers are compared with floats exactly
+        VmTestCase {
+            input: "9007199254740993 == 9007199254740992.0",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "9007199254740993 > 9007199254740992.0",
+            expected: Object::Bool(true),
+        },
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_mixed_arithmetic() {
+    let tests = vec![
+        VmTestCase {
+            input: "1. + 2",
+            expected: Object::Float(3.),
+        },
+        VmTestCase {
+            input: "1 + 2.",
+            expected: Object::Float(3.),
+        },
+        VmTestCase {
+            input: "1 + b'2'",
+            expected: Object::Integer(51),
+        },
+        VmTestCase {
+            input: "b'1' - 2",
+            expected: Object::Integer(47),
+        },
+        VmTestCase {
+            input: "1. + b'2'",
+            expected: Object::Float(51.),
+        },
+        VmTestCase {
+            input: "b'1' - 2.",
+            expected: Object::Float(47.),
+        },
+        VmTestCase {
+            input: "1. + 2.",
+            expected: Object::Float(3.),
+        },
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_modulo_bucketing() {
+    let tests = vec![
+        VmTestCase {
+            input: "let ts = 1700000123; ts - ts % 60",
+            expected: Object::Integer(1700000100),
+        },
+        VmTestCase {
+            input: "let t = 12.5; t - t % 10s",
+            expected: Object::Float(10.0),
+        },
+        VmTestCase {
+            input: "1700000123 + 2m",
+            expected: Object::Float(1700000243.0),
+        },
+        VmTestCase {
+            input: "(-9223372036854775807 - 1) % -1",
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            input: "(-9223372036854775807 - 1) / -1",
+            expected: Object::Integer(i64::MIN),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_division_by_zero() {
+    let tests = vec![
+        VmTestCaseErr {
+            input: "1 / 0",
+            expected: "Division by zero.",
+        },
+        VmTestCaseErr {
+            input: "1. / 0",
+            expected: "Division by zero.",
+        },
+        VmTestCaseErr {
+            input: "1 % 0",
+            expected: "Modulo by zero.",
+        },
+        VmTestCaseErr {
+            input: "1.5 % 0s",
+            expected: "Modulo by zero.",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_unary_expressions() {
+    let tests = vec![
+        VmTestCase {
+            input: "-10",
+            expected: Object::Integer(-10),
+        },
+        VmTestCase {
+            input: "-10.2",
+            expected: Object::Float(-10.2),
+        },
+        VmTestCase {
+            input: "!2",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "~10",
+            expected: Object::Integer(-11),
+        },
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_unary_expressions_negative() {
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#"-"a""#,
+            expected: "bad operand type for unary '-'",
+        },
+        VmTestCaseErr {
+            input: r#"~"a""#,
+            expected: "bad operand type for unary '~'",
+        },
+        VmTestCaseErr {
+            input: r#"-'a'"#,
+            expected: "bad operand type for unary '-'",
+        },
+        VmTestCaseErr {
+            input: r#"-b'a'"#,
+            expected: "bad operand type for unary '-'",
+        },
+        VmTestCaseErr {
+            input: r#"~'a'"#,
+            expected: "bad operand type for unary '~'",
+        },
+        VmTestCaseErr {
+            input: r#"~b'a'"#,
+            expected: "bad operand type for unary '~'",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_bitwise_operators() {
+    let tests = vec![
+        VmTestCase {
+            input: "10 & 12",
+            expected: Object::Integer(8),
+        },
+        VmTestCase {
+            input: "10 | 12",
+            expected: Object::Integer(14),
+        },
+        VmTestCase {
+            input: "10 ^ 12",
+            expected: Object::Integer(6),
+        },
+        VmTestCase {
+            input: "10 << 2",
+            expected: Object::Integer(40),
+        },
+        VmTestCase {
+            input: "10 >> 2",
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: "((7 | (12 ^ 5)) & (9 << 2)) >> 2",
+            expected: Object::Integer(1),
+        },
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_bitwise_operators_negative() {
+    let tests = vec![
+        VmTestCaseErr {
+            input: "1.1 & 2.1",
+            expected: "Invalid bitwise operation.",
+        },
+        VmTestCaseErr {
+            input: "10 << 2.",
+            expected: "Invalid bitwise operation.",
+        },
+        VmTestCaseErr {
+            input: "10. >> 2",
+            expected: "Invalid bitwise operation.",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_floating_arithmetic() {
+    let tests = vec![
+        VmTestCase {
+            input: "1.",
+            expected: Object::Float(1.),
+        },
+        VmTestCase {
+            input: "int(1.5 + 2.5)",
+            expected: Object::Integer(4),
+        },
+        VmTestCase {
+            input: "int(1.5 - 2.5)",
+            expected: Object::Integer(-1),
+        },
+        VmTestCase {
+            input: "int(1.5 * 2.5)",
+            expected: Object::Integer(3),
+        },
+        VmTestCase {
+            input: "int(1.5 / 2.5)",
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            input: "int(50. / 2. * 2. + 10. - 5.)",
+            expected: Object::Integer(55),
+        },
+        VmTestCase {
+            input: "int(5. + 5. + 5. + 5. - 10.)",
+            expected: Object::Integer(10),
+        },
+        VmTestCase {
+            input: "int(2. * 2. * 2. * 2. * 2.)",
+            expected: Object::Integer(32),
+        },
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_boolean_expressions() {
+    let tests = vec![
+        VmTestCase {
+            input: "true",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "false",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "1 < 2",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "1 <= 2",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "1 > 2",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "1 >= 2",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "1 < 1",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "1 <= 1",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "1 > 1",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "1 >= 1",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "1.1 < 2.2",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "1.1 > 2.2",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "1.1 < 1.1",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "1.1 > 1.1",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "1 == 1",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "1 != 1",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "1 == 2",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "1 != 2",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "true == true",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "false == false",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "true == false",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "true != false",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "false != true",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "!null == true",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "(1 < 2) == true",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "(1 < 2) == false",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "(1 > 2) == true",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "(1 > 2) == false",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "!true",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "!false",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "!5",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "!!true",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "!!false",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "!!null",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "!!5",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "!!'a'",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "!b'a'",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: r#"!!"""#,
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "!![]",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "!!map {}",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "(10 + 50 + -5 - 5 * 2 / 2) < (100 - 35)",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "!(if false { 5; })",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: r#""a" < "a""#,
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: r#""a" > "a""#,
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: r#""a" <= "a""#,
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: r#""a" >= "a""#,
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: r#"'a' <= 'a'"#,
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: r#"b'a' < b'a'"#,
+            expected: Object::Bool(false),
+        },
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_conditionals() {
+    let tests = vec![
+        VmTestCase {
+            input: "if true { 10 }",
+            expected: Object::Integer(10),
+        },
+        // condition enclosed in parentheses
+        VmTestCase {
+            input: "if (true) { 10 }",
+            expected: Object::Integer(10),
+        },
+        VmTestCase {
+            input: "if true { 10 } else { 20 }",
+            expected: Object::Integer(10),
+        },
+        VmTestCase {
+            input: "if false { 10 } else { 20 } ",
+            expected: Object::Integer(20),
+        },
+        VmTestCase {
+            input: "if 1 { 10 }",
+            expected: Object::Integer(10),
+        },
+        VmTestCase {
+            input: "if 1 < 2 { 10 }",
+            expected: Object::Integer(10),
+        },
+        VmTestCase {
+            input: "if 1 < 2 { 10 } else { 20 }",
+            expected: Object::Integer(10),
+        },
+        VmTestCase {
+            input: "if 1 > 2 { 10 } else { 20 }",
+            expected: Object::Integer(20),
+        },
+        VmTestCase {
+            input: "if 1 > 2 { 10 }",
+            expected: Object::Null,
+        },
+        // force return a null for then and else branches that do not return a value
+        VmTestCase {
+            input: "if true { }",
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: "if true { let a = 1; }",
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: "if false { 10 }",
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: "if false { 10 } else { let a = 1; }",
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: "if false { } else { }",
+            expected: Object::Null,
+        },
+        // nested conditionals
+        VmTestCase {
+            input: "if (if false { 10 }) { 10 } else { 20 }",
+            expected: Object::Integer(20),
+        },
+        VmTestCase {
+            input: "if 1 > 2 { 10 } else if 2 > 3 { 20 } else { 30 }",
+            expected: Object::Integer(30),
+        },
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_conditionals_ternary() {
+    let tests = vec![
+        VmTestCase {
+            input: "true ? 10 : 20",
+            expected: Object::Integer(10),
+        },
+        VmTestCase {
+            input: "null ? 10 : 20",
+            expected: Object::Integer(20),
+        },
+        VmTestCase {
+            input: r#"let x = -3; x > 0 ? "up" : "down""#,
+            expected: Object::Str("down".to_string()),
+        },
+        VmTestCase {
+            input: r#"let x = 0; x < 0 ? "neg" : x == 0 ? "zero" : "pos""#,
+            expected: Object::Str("zero".to_string()),
+        },
+        VmTestCase {
+            input: "let x = 2; let y = x > 1 ? x * 10 : x; y + 1",
+            expected: Object::Integer(21),
+        },
+        // Only the chosen expression is evaluated
+        VmTestCase {
+            input: "let n = 0; true ? 1 : (n = 5); n",
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            input: "fn f(n) { n <= 1 ? 1 : n * f(n - 1) } f(5)",
+            expected: Object::Integer(120),
+        },
+        VmTestCase {
+            input: "len([1 > 2 ? 1 : 2, 3])",
+            expected: Object::Integer(2),
+        },
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_global_let_statements() {
+    let tests = vec![
+        VmTestCase {
+            input: "let one = 1; one",
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: "let one = 1; let two = 2; one + two",
+            expected: Object::Integer(3),
+        },
+        VmTestCase {
+            input: "let one = 1; let two = one + one; one + two",
+            expected: Object::Integer(3),
+        },
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_string_expressions() {
+    let tests = vec![
+        VmTestCase {
+            input: r#""p2sh""#,
+            expected: Object::Str("p2sh".to_string()),
+        },
+        VmTestCase {
+            input: r#""p2" + "sh""#,
+            expected: Object::Str("p2sh".to_string()),
+        },
+        VmTestCase {
+            input: r#""p2" + "sh" + "banana""#,
+            expected: Object::Str("p2shbanana".to_string()),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_char_and_byte_expressions() {
+    let tests = vec![
+        VmTestCase {
+            input: "'c'",
+            expected: Object::Char('c'),
+        },
+        VmTestCase {
+            input: "b'c'",
+            expected: Object::Byte(b'c'),
+        },
+        VmTestCase {
+            input: "'1' + '2'",
+            expected: Object::Str("12".to_string()),
+        },
+        VmTestCase {
+            input: "b'1' + b'2'",
+            expected: Object::Byte(99),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_array_literals() {
+    let tests = vec![
+        VmTestCase {
+            input: "[]",
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(Vec::new()),
+            })),
+        },
+        VmTestCase {
+            input: "[1, 2, 3]",
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![
+                    Rc::new(Object::Integer(1)),
+                    Rc::new(Object::Integer(2)),
+                    Rc::new(Object::Integer(3)),
+                ]),
+            })),
+        },
+        VmTestCase {
+            input: "[1 + 2, 3 * 4, 5 + 6]",
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![
+                    Rc::new(Object::Integer(3)),
+                    Rc::new(Object::Integer(12)),
+                    Rc::new(Object::Integer(11)),
+                ]),
+            })),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_array_operations() {
+    let tests = vec![
+        VmTestCase {
+            input: "[] + []",
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(Vec::new()),
+            })),
+        },
+        VmTestCase {
+            input: "[] + [1]",
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![Rc::new(Object::Integer(1))]),
+            })),
+        },
+        VmTestCase {
+            input: "[1] + [2]",
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![
+                    Rc::new(Object::Integer(1)),
+                    Rc::new(Object::Integer(2)),
+                ]),
+            })),
+        },
+        VmTestCase {
+            input: "let a = [1, 2]; let b = a + [3]; len(a) * 10 + len(b)",
+            expected: Object::Integer(23),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: "[1] - [2]",
+            expected: "Invalid operation on arrays.",
+        },
+        VmTestCaseErr {
+            input: "[1] < [2]",
+            expected: "Invalid operation on arrays.",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_string_repetition() {
+    let tests = vec![
+        VmTestCase {
+            input: r#""-" * 5"#,
+            expected: Object::Str("-----".into()),
+        },
+        VmTestCase {
+            input: r#"2 * "ab""#,
+            expected: Object::Str("abab".into()),
+        },
+        VmTestCase {
+            input: r#""ab" * 0"#,
+            expected: Object::Str("".into()),
+        },
+        VmTestCase {
+            input: r#""ab" * -1"#,
+            expected: Object::Str("".into()),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#""ab" * 9223372036854775807"#,
+            expected: "String repetition is too large.",
+        },
+        VmTestCaseErr {
+            input: r#""ab" - 2"#,
+            expected: "Invalid operation on strings.",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_hash_literals() {
+    let tests = vec![
+        VmTestCase {
+            input: "map {}",
+            expected: Object::Map(Rc::new(HMap::default())),
+        },
+        VmTestCase {
+            input: "map {1: 2, 2: 3}",
+            expected: Object::Map({
+                let map = HMap::default();
+                map.insert(Rc::new(Object::Integer(1)), Rc::new(Object::Integer(2)));
+                map.insert(Rc::new(Object::Integer(2)), Rc::new(Object::Integer(3)));
+                Rc::new(map)
+            }),
+        },
+        VmTestCase {
+            input: "map {1 + 1: 2 * 2, 3 + 3: 4 * 4}",
+            expected: Object::Map({
+                let map = HMap::default();
+                map.insert(
+                    Rc::new(Object::Integer(2.into())),
+                    Rc::new(Object::Integer(4.into())),
+                );
+                map.pairs.borrow_mut().insert(
+                    Rc::new(Object::Integer(6.into())),
+                    Rc::new(Object::Integer(16.into())),
+                );
+                Rc::new(map)
+            }),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_hash_literals_expressions_negative() {
+    let tests = vec![
+        VmTestCaseErr {
+            input: "map {fn() {}: 2}",
+            expected: "KeyError: not a valid key: <closure>.",
+        },
+        VmTestCaseErr {
+            input: "map {fn() {}: fn() {}}",
+            expected: "KeyError: not a valid key: <closure>.",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_index_expressions() {
+    let tests = vec![
+        VmTestCase {
+            input: "map {1: 1, 2: 2}[1]",
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: "map {1: 1, 2: 2}[2]",
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: "map {1.1: 1, 2.2: 2}[2.2]",
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: r#"map {"one": 1, "two": 2, "three": 3}["o" + "ne"]"#,
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: "map {'a': 1, 'b': 2, 'c': 3}['b']",
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: "map {b'a': 1, b'b': 2, b'c': 3}[b'b']",
+            expected: Object::Integer(2),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_index_expressions_negative() {
+    let tests = vec![
+        VmTestCaseErr {
+            input: "[][0]",
+            expected: "IndexError: array index out of range.",
+        },
+        VmTestCaseErr {
+            input: "map {1: 1}[0]",
+            expected: "KeyError: key not found.",
+        },
+        VmTestCaseErr {
+            input: "map {}[fn(){}]",
+            expected: "KeyError: not a valid key: <closure>.",
+        },
+        VmTestCaseErr {
+            input: "map {}[0]",
+            expected: "KeyError: key not found.",
+        },
+        VmTestCaseErr {
+            input: "[1, 2, 3][99]",
+            expected: "IndexError: array index out of range.",
+        },
+        VmTestCaseErr {
+            input: "[1, 2, 3][1]",
+            expected: "IndexError: array index out of range.",
+        },
+        VmTestCaseErr {
+            input: "[1][-1]",
+            expected: "IndexError: index cannot be negative.",
+        },
+        VmTestCaseErr {
+            input: "[[1, 1, 1]][0][0]",
+            expected: "IndexError: array index out of range.",
+        },
+        VmTestCaseErr {
+            input: "[1, 2, 3][0 + 2]",
+            expected: "IndexError: array index out of range.",
+        },
+        VmTestCaseErr {
+            input: "[][fn(){}]",
+            expected: "IndexError: unsupported operation.",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_calling_functions_without_args() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+            let fivePlusTen = fn() { 5 + 10; };
+            fivePlusTen();
+            "#,
+            expected: Object::Integer(15),
+        },
+        VmTestCase {
+            input: r#"
+            let f1 = fn() { 1 };
+            let f2 = fn() { 2 };
+            f1() + f2()
+            "#,
+            expected: Object::Integer(3),
+        },
+        VmTestCase {
+            input: r#"
+            let f1 = fn() { 1 };
+            let f2 = fn() { f1() + 1 };
+            let f3 = fn() { f2() + 1 };
+            f3()
+            "#,
+            expected: Object::Integer(3),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_calling_functions_with_return() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+            let earlyExit = fn() { return; 100; };
+            earlyExit();
+            "#,
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: r#"
+            let earlyExit = fn() { return 99; 100; };
+            earlyExit();
+            "#,
+            expected: Object::Integer(99),
+        },
+        VmTestCase {
+            input: r#"
+            let earlyExit = fn() { return 99; return 100; };
+            earlyExit();
+            "#,
+            expected: Object::Integer(99),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_calling_functions_without_return() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+            let noReturn = fn() { };
+            noReturn();
+            "#,
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: r#"
+            let noReturn1 = fn() { };
+            let noReturn2 = fn() { noReturn1(); };
+            noReturn1();
+            noReturn2();
+            "#,
+            expected: Object::Null,
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_first_class_functions_() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+                let returnsOne = fn() { 1; };
+                let returnsOneReturner = fn() { returnsOne; };
+                returnsOneReturner()();
+            "#,
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: r#"
+                let returnsOneReturner = fn() {
+                let returnsOne = fn() { 1; };
+                returnsOne;
+                };
+                returnsOneReturner()();
+            "#,
+            expected: Object::Integer(1),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_calling_functions_with_bindings() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+                let one = fn() { let one = 1; one };
+                one();
+            "#,
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: r#"
+                let one_and_two = fn() { let one = 1; let two = 2; one + two; };
+                one_and_two();
+            "#,
+            expected: Object::Integer(3),
+        },
+        VmTestCase {
+            input: r#"
+                let one_and_two = fn() { let one = 1; let two = 2; one + two; };
+                let three_and_four = fn() { let three = 3; let four = 4; three + four; };
+                one_and_two() + three_and_four();
+            "#,
+            expected: Object::Integer(10),
+        },
+        VmTestCase {
+            input: r#"
+                let first_foobar = fn() { let foobar = 50; foobar; };
+                let second_foobar = fn() { let foobar = 100; foobar; };
+                first_foobar() + second_foobar();
+            "#,
+            expected: Object::Integer(150),
+        },
+        VmTestCase {
+            input: r#"
+                let global_seed = 50;
+                let minus_one = fn() {
+                    let num = 1;
+                    global_seed - num;
+                };
+                let minus_two = fn() {
+                    let num = 2;
+                    global_seed - num;
+                };
+                minus_one() + minus_two();
+            "#,
+            expected: Object::Integer(97),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_calling_functions_with_args_and_bindings() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+                let identity = fn(a) { a; };
+                identity(4);
+            "#,
+            expected: Object::Integer(4),
+        },
+        VmTestCase {
+            input: r#"
+                let sum = fn(a, b) { a + b; };
+                sum(1, 2);
+            "#,
+            expected: Object::Integer(3),
+        },
+        VmTestCase {
+            input: r#"
+                let sum = fn(a, b) { let c = a + b; c; };
+                sum(1, 2);
+            "#,
+            expected: Object::Integer(3),
+        },
+        VmTestCase {
+            input: r#"
+                let sum = fn(a, b) { let c = a + b; c; };
+                sum(1, 2) + sum(3, 4);
+            "#,
+            expected: Object::Integer(10),
+        },
+        VmTestCase {
+            input: r#"
+                let sum = fn(a, b) { let c = a + b; c; };
+                let outer = fn() { sum(1, 2) + sum(3, 4); };
+                outer();
+            "#,
+            expected: Object::Integer(10),
+        },
+        VmTestCase {
+            input: r#"
+                let globalNum = 10;
+                let sum = fn(a, b) {
+                    let c = a + b;
+                    c + globalNum;
+                };
+                let outer = fn() {
+                    sum(1, 2) + sum(3, 4) + globalNum;
+                };
+                outer() + globalNum;
+            "#,
+            expected: Object::Integer(50),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_wrong_number_of_arguments() {
+    let tests: Vec<VmTestCaseErr> = vec![
+        VmTestCaseErr {
+            input: "fn() { 1; }(1);",
+            expected: "wrong number of arguments: want=0, got=1",
+        },
+        VmTestCaseErr {
+            input: "fn(a) { a; }();",
+            expected: "wrong number of arguments: want=1, got=0",
+        },
+        VmTestCaseErr {
+            input: "fn(a, b) { a + b; }(1);",
+            expected: "wrong number of arguments: want=2, got=1",
+        },
+    ];
+
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_builtin_function_len() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"len("")"#,
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            input: r#"len("four")"#,
+            expected: Object::Integer(4),
+        },
+        VmTestCase {
+            input: r#"len("hello world")"#,
+            expected: Object::Integer(11),
+        },
+        VmTestCase {
+            input: r#"len([1, 2, 3])"#,
+            expected: Object::Integer(3),
+        },
+        VmTestCase {
+            input: r#"len([])"#,
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            input: r#"len(map {})"#,
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            input: r#"len(map {"a": 1, "b": 2})"#,
+            expected: Object::Integer(2),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_builtin_functions_errors() {
+    let run = |input: &str| {
+        let mut vm = VM::new(test_compile(input));
+        vm.run()
+            .map(|_| vm.last_popped().to_string())
+            .map_err(|e| e.msg)
+    };
+    let tests = [
+        // Values that do not parse or convert are error values
+        (r#"is_err(int("12x"))"#, "true"),
+        (r#"err_msg(int("12x"))"#, r#""invalid integer '12x'""#),
+        (r#"str(float("x"))"#, r#""value-error: invalid float 'x'""#),
+        (
+            "err_msg(char(1114112))",
+            r#""invalid character code 1114112""#,
+        ),
+        ("is_err(byte(-1))", "true"),
+        (r#"[is_err(int("12")), int("12")]"#, "[false, 12]"),
+        // Files that cannot be opened are error values
+        (r#"is_err(open("/nonexistent/p2sh"))"#, "true"),
+        #[cfg(feature = "pcap")]
+        (r#"is_err(pcap_open("/nonexistent/p2sh.pcap"))"#, "true"),
+        #[cfg(feature = "pcap")]
+        (
+            r#"err_msg(open("/nonexistent/p2sh")) == err_msg(pcap_open("/nonexistent/p2sh"))"#,
+            "true",
+        ),
+        // Errors caught by 'try' blocks
+        (
+            "let m = 0; try { 1 / 0; } catch e { m = err_msg(e); } m",
+            r#""Division by zero.""#,
+        ),
+        // Other values are not errors
+        (
+            "[is_err(null), is_err(0), err_msg(5)]",
+            "[false, false, null]",
+        ),
+        // The deprecated name still works
+        ("is_error(int(\"x\"))", "true"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run(input).unwrap(), expected, "{}", input);
+    }
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: "is_err()",
+            expected: "is_err: takes one argument. got=0",
+        },
+        VmTestCaseErr {
+            input: "err_msg(1, 2)",
+            expected: "err_msg: takes one argument. got=2",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_builtin_functions_arrays() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"first([1, 2, 3])"#,
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: r#"first([])"#,
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: r#"last([1, 2, 3])"#,
+            expected: Object::Integer(3),
+        },
+        VmTestCase {
+            input: r#"last([])"#,
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: r#"rest([1, 2, 3])"#,
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![
+                    Rc::new(Object::Integer(2)),
+                    Rc::new(Object::Integer(3)),
+                ]),
+            })),
+        },
+        VmTestCase {
+            input: r#"rest([])"#,
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: r#"let a = [1]; push(a, 2); pop(a); a"#,
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![Rc::new(Object::Integer(1))]),
+            })),
+        },
+        VmTestCase {
+            input: r#"
+                let array = [1, 2, 3];
+                push(array, 4);
+                first(rest(array));
+            "#,
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: r#"sort([3, 1, 2])"#,
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![
+                    Rc::new(Object::Integer(1)),
+                    Rc::new(Object::Integer(2)),
+                    Rc::new(Object::Integer(3)),
+                ]),
+            })),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_builtin_functions_maps() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"contains(map {}, "k")"#,
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: r#"let m = map {"k1": 1, "k": 0, "k2": 2}; contains(m, "k")"#,
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: r#"get(map {}, "k")"#,
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: r#"let m = map {"k1": 999, "k2": 888, "k3": 777}; get(m, "k2")"#,
+            expected: Object::Integer(888),
+        },
+        VmTestCase {
+            input: r#"let m = map {"k1": 999, "k2": 888, "k3": 777}; get(m, "k")"#,
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: r#"let m = map {}; insert(m, "k", 1)"#,
+            // insert returns Null if key was not found already
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: r#"let m = map {"k1": 999, "k2": 888}; insert(m, "k3", 777)"#,
+            // insert returns Null if key was not found already
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: r#"let m = map {"k1": 999, "k2": 888}; insert(m, "k2", 777)"#,
+            // insert returns the previous value if key was found
+            expected: Object::Integer(888),
+        },
+        VmTestCase {
+            input: r#"let m = map {}; insert(m, "k", 1); m"#,
+            expected: Object::Map({
+                let map = HMap::default();
+                map.insert(
+                    Rc::new(Object::Str("k".to_string())),
+                    Rc::new(Object::Integer(1)),
+                );
+                Rc::new(map)
+            }),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_builtin_functions_sets() {
+    let set_of = |elements: &[i64]| {
+        let set = HSet::default();
+        for e in elements {
+            set.insert(Rc::new(Object::Integer(*e)));
+        }
+        Object::Set(Rc::new(set))
+    };
+    let tests = vec![
+        VmTestCase {
+            input: "len(set())",
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            input: "len(set([1, 2, 2, 3, 1]))",
+            expected: Object::Integer(3),
+        },
+        VmTestCase {
+            input: "set([3, 1, 2, 1])",
+            expected: set_of(&[1, 2, 3]),
+        },
+        VmTestCase {
+            input: "let s = set(); insert(s, 1)",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "let s = set([1]); insert(s, 1)",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            // Numbers that compare equal are the same element
+            input: "let s = set([1]); insert(s, 1.0); insert(s, 0x01); len(s)",
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: r#"let s = set(["10.0.0.1", "10.0.0.2"]); contains(s, "10.0.0.2")"#,
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: r#"let s = set(["10.0.0.1"]); contains(s, "10.0.0.2")"#,
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            // Array elements are copied on insertion
+            input: "let a = [1, 2]; let s = set(); insert(s, a); push(a, 3); contains(s, [1, 2])",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "let s = set([1, 2]); remove(s, 1)",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "let s = set([1, 2]); remove(s, 3); remove(s, 1); s",
+            expected: set_of(&[2]),
+        },
+        VmTestCase {
+            input: r#"let m = map {"k": 1}; remove(m, "k")"#,
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: r#"let m = map {"k": 1}; remove(m, "x"); len(m)"#,
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: "union(set([1, 2]), set([2, 3]))",
+            expected: set_of(&[1, 2, 3]),
+        },
+        VmTestCase {
+            input: "intersection(set([1, 2]), set([2, 3]))",
+            expected: set_of(&[2]),
+        },
+        VmTestCase {
+            // set() copies another set
+            input: "let a = set([1]); let b = set(a); insert(b, 2); a",
+            expected: set_of(&[1]),
+        },
+        VmTestCase {
+            input: "set([2, 1]) == set([1, 2])",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "if (set()) { 1 } else { 0 }",
+            expected: Object::Integer(0),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: "set(1)",
+            expected: "set: unsupported argument",
+        },
+        VmTestCaseErr {
+            input: "set([fn() {}])",
+            expected: "set: not a valid set element: <closure>",
+        },
+        VmTestCaseErr {
+            input: "insert(set(), 1, 2)",
+            expected: "insert: takes two arguments. got=3",
+        },
+        VmTestCaseErr {
+            input: "union(set(), [1])",
+            expected: "union: unsupported argument",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_builtin_functions_mapdefault() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"let m = mapdefault(map {}, 0); m["a"] = m["a"] + 1; m["a"]"#,
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: r#"
+            let counts = mapdefault(map {}, 0);
+            let i = 0;
+            while i < 5 {
+                counts[i % 2] = counts[i % 2] + 1;
+                i = i + 1;
+            }
+            [counts[0], counts[1]]
+            "#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Integer(3)),
+                Rc::new(Object::Integer(2)),
+            ]))),
+        },
+        VmTestCase {
+            // Indexing a missing key inserts the default
+            input: r#"let m = mapdefault(map {}, 0); m["a"]; contains(m, "a")"#,
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            // get() and contains() do not insert the default
+            input: r#"let m = mapdefault(map {}, 0); get(m, "a"); contains(m, "a")"#,
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            // Each key gets its own copy of a container default
+            input: r#"
+            let m = mapdefault(map {}, []);
+            push(m["a"], 1);
+            push(m["a"], 2);
+            push(m["b"], 3);
+            [len(m["a"]), len(m["b"])]
+            "#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Integer(2)),
+                Rc::new(Object::Integer(1)),
+            ]))),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#"let m = mapdefault(map {}, 0); mapdefault(m, null); m["a"]"#,
+            expected: "KeyError: key not found.",
+        },
+        VmTestCaseErr {
+            input: "mapdefault([], 0)",
+            expected: "mapdefault: unsupported argument",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_builtin_functions_aggregates() {
+    let tests = vec![
+        VmTestCase {
+            input: "sum([1, 2, 3])",
+            expected: Object::Integer(6),
+        },
+        VmTestCase {
+            input: "sum([])",
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            // Bytes are added as integers
+            input: "sum([1, 0x02, 3])",
+            expected: Object::Integer(6),
+        },
+        VmTestCase {
+            input: "sum([1, 2.5])",
+            expected: Object::Float(3.5),
+        },
+        VmTestCase {
+            input: r#"sum(map {"a": 1, "b": 2})"#,
+            expected: Object::Integer(3),
+        },
+        VmTestCase {
+            input: "sum(set([1, 2, 2]))",
+            expected: Object::Integer(3),
+        },
+        VmTestCase {
+            input: "avg([1, 2])",
+            expected: Object::Float(1.5),
+        },
+        VmTestCase {
+            input: "avg([])",
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: "min([3, 1.5, 2])",
+            expected: Object::Float(1.5),
+        },
+        VmTestCase {
+            input: "max([3, 1.5, 2])",
+            expected: Object::Integer(3),
+        },
+        VmTestCase {
+            input: "max([])",
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: r#"max(map {"a": 10, "b": 20})"#,
+            expected: Object::Integer(20),
+        },
+        VmTestCase {
+            input: r#"min(["b", "a", "c"])"#,
+            expected: Object::Str("a".to_string()),
+        },
+        VmTestCase {
+            input: "min(4, 2, 3)",
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: "max(2.5, 3)",
+            expected: Object::Integer(3),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#"sum([1, "2"])"#,
+            expected: "sum: not a number: \"2\"",
+        },
+        VmTestCaseErr {
+            input: "sum([9223372036854775807, 1])",
+            expected: "sum: integer overflow",
+        },
+        VmTestCaseErr {
+            input: r#"max([1, "a"])"#,
+            expected: "max: cannot compare \"a\" and 1",
+        },
+        VmTestCaseErr {
+            input: "min()",
+            expected: "min: takes one or more arguments. got=0",
+        },
+        VmTestCaseErr {
+            input: "avg(1)",
+            expected: "avg: unsupported argument",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_builtin_function_group_by() {
+    let group = |key: Object, elements: &[i64]| {
+        (
+            Rc::new(key),
+            Rc::new(Object::Arr(Rc::new(Array::new(
+                elements
+                    .iter()
+                    .map(|e| Rc::new(Object::Integer(*e)))
+                    .collect(),
+            )))),
+        )
+    };
+    let tests = vec![
+        VmTestCase {
+            input: "group_by([1, 2, 3, 4, 5], fn(n) { n % 2 == 0 })",
+            expected: Object::Map(Rc::new(HMap::new(HashMap::from([
+                group(Object::Bool(false), &[1, 3, 5]),
+                group(Object::Bool(true), &[2, 4]),
+            ])))),
+        },
+        VmTestCase {
+            input: "group_by([], fn(n) { n })",
+            expected: Object::Map(Rc::new(HMap::default())),
+        },
+        VmTestCase {
+            // Called from within a function in the middle of an expression
+            input: r#"
+                fn f(x) {
+                    let g = group_by([1, 2, 3], fn(n) { n + x });
+                    return len(g) + x;
+                }
+                [f(1), f(2)]
+            "#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Integer(4)),
+                Rc::new(Object::Integer(5)),
+            ]))),
+        },
+        VmTestCase {
+            // Nested calls and closures over free variables
+            input: r#"
+                let base = 10;
+                let g = group_by([1, 2, 3], fn(n) {
+                    if n > len(group_by([1, 2], fn(m) { m })) { return base; }
+                    base + 1
+                });
+                g[11]
+            "#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Integer(1)),
+                Rc::new(Object::Integer(2)),
+            ]))),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: "group_by([1], fn(a, b) { a })",
+            expected: "group_by: function must take one argument",
+        },
+        VmTestCaseErr {
+            input: "group_by(1, fn(a) { a })",
+            expected: "group_by: unsupported argument",
+        },
+        VmTestCaseErr {
+            input: "group_by([1], fn(a) { map {} })",
+            expected: "group_by: not a valid key: map {}",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_builtin_functions_equals_diff() {
+    let diff_entry = |path: &str, a: Object, b: Object| {
+        (
+            Rc::new(Object::Str(path.to_string())),
+            Rc::new(Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(a),
+                Rc::new(b),
+            ])))),
+        )
+    };
+    let tests = vec![
+        VmTestCase {
+            input: r#"equals([1, [2, map {"a": [3]}]], [1, [2, map {"a": [3]}]])"#,
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: r#"equals(map {"a": [1, 2]}, map {"a": [1, 3]})"#,
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            // Numbers are compared by their values as with '=='
+            input: "equals([1, 2.0], [1.0, 2])",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: r#"equals(1, "1")"#,
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: r#"diff(map {"a": [1, 2]}, map {"a": [1, 2]})"#,
+            expected: Object::Map(Rc::new(HMap::default())),
+        },
+        VmTestCase {
+            input: r#"diff([1, [2, 3]], [1, [2, 4, 5]])"#,
+            expected: Object::Map(Rc::new(HMap::new(HashMap::from([
+                diff_entry("[1][1]", Object::Integer(3), Object::Integer(4)),
+                diff_entry("[1][2]", Object::Null, Object::Integer(5)),
+            ])))),
+        },
+        VmTestCase {
+            input: r#"diff(map {"a": 1, "b": map {"c": true}}, map {"a": 1, "b": map {}, "d": 'x'})"#,
+            expected: Object::Map(Rc::new(HMap::new(HashMap::from([
+                diff_entry(r#"["b"]["c"]"#, Object::Bool(true), Object::Null),
+                diff_entry(r#"["d"]"#, Object::Null, Object::Char('x')),
+            ])))),
+        },
+        VmTestCase {
+            input: r#"diff(1, "1")"#,
+            expected: Object::Map(Rc::new(HMap::new(HashMap::from([diff_entry(
+                "",
+                Object::Integer(1),
+                Object::Str("1".to_string()),
+            )])))),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_builtin_functions_assert_testdata() {
+    let tests = vec![
+        VmTestCase {
+            input: "assert(1 < 2)",
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: r#"assert([1], "not empty")"#,
+            expected: Object::Null,
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: "assert(1 > 2)",
+            expected: "assert: assertion failed",
+        },
+        VmTestCaseErr {
+            input: r#"assert("", format("{} items", 0))"#,
+            expected: "assert: assertion failed: 0 items",
+        },
+        VmTestCaseErr {
+            input: "assert(false, [1, 2])",
+            expected: "assert: assertion failed: [1, 2]",
+        },
+        VmTestCaseErr {
+            input: "assert()",
+            expected: "assert: takes one or two arguments. got=0",
+        },
+        VmTestCaseErr {
+            input: "testdata(1)",
+            expected: "testdata: unsupported argument",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+
+    // The pcap file declared is recorded by the VM
+    let bytecode = test_compile(r#"testdata("a.pcap"); testdata("fixtures/b.pcap")"#);
+    let mut vm = VM::new(bytecode);
+    assert_eq!(vm.testdata(), None);
+    vm.run().unwrap();
+    assert_eq!(vm.testdata(), Some(String::from("fixtures/b.pcap")));
+    test_expected_object(vm.last_popped(), &Object::Null);
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_builtin_functions_decode_as() {
+    use p2sh_dissect::ports::AppProto;
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#"decode_as(5353, "http")"#,
+            expected: "decode_as: unknown protocol 'http'",
+        },
+        VmTestCaseErr {
+            input: r#"decode_as(70000, "dns")"#,
+            expected: "decode_as: invalid port 70000",
+        },
+        VmTestCaseErr {
+            input: r#"decode_as("53", "dns")"#,
+            expected: "decode_as: unsupported argument",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+
+    // The ports mapped are recorded by the VM
+    let bytecode = test_compile(r#"decode_as(5353, "dns"); decode_as(53, "none")"#);
+    let mut vm = VM::new(bytecode);
+    assert_eq!(vm.port_map().lookup(40000, 53), Some(AppProto::Dns));
+    vm.run().unwrap();
+    assert_eq!(vm.port_map().lookup(40000, 5353), Some(AppProto::Dns));
+    assert_eq!(vm.port_map().lookup(40000, 53), None);
+    test_expected_object(vm.last_popped(), &Object::Null);
+}
+
+#[test]
+fn test_builtin_functions_find() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"find("GET /index.html", "/index")"#,
+            expected: Object::Integer(4),
+        },
+        VmTestCase {
+            input: r#"find("GET /index.html", "POST")"#,
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: r#"find([b'a', b'b', b'a', b'b'], [b'a', b'b'], 1)"#,
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: r#"find([b'a', b'b', b'c'], b'c')"#,
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: r#"find("abc", "")"#,
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            input: r#"find("abc", "c", 10)"#,
+            expected: Object::Null,
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#"find("abc", 1)"#,
+            expected: "find: unsupported argument",
+        },
+        VmTestCaseErr {
+            input: r#"find("abc", "a", -1)"#,
+            expected: "find: start must be a non-negative integer",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_builtin_functions_json() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"to_json(map {"b": [1, 2.5, true], "a": null})"#,
+            expected: Object::Str(r#"{"a":null,"b":[1,2.5,true]}"#.into()),
+        },
+        VmTestCase {
+            input: r#"to_json(str('"') + str(char(92)) + str(char(9)))"#,
+            expected: Object::Str(r#""\"\\\t""#.into()),
+        },
+        VmTestCase {
+            input: r#"to_json(map {1: bytes("AB"), 'c': set([1])})"#,
+            expected: Object::Str(r#"{"'c'":[1],"1":"4142"}"#.into()),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#"to_json(len)"#,
+            expected: "to_json: cannot encode <built-in function len> as json",
+        },
+        #[cfg(feature = "pcap")]
+        VmTestCaseErr {
+            input: r#"to_map(map {})"#,
+            expected: "to_map: unsupported argument",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_builtin_functions_props() {
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#"props(1, "tcp.*")"#,
+            expected: "props: unsupported argument",
+        },
+        VmTestCaseErr {
+            input: r#"props(1, "foo.*")"#,
+            expected: "props: no property matches 'foo.*'",
+        },
+        VmTestCaseErr {
+            input: r#"props(1, 2)"#,
+            expected: "props: second argument should be a pattern string",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_sequence_consistency() {
+    let run = |input: &str| {
+        let mut vm = VM::new(test_compile(input));
+        vm.run()
+            .map(|_| vm.last_popped().to_string())
+            .map_err(|e| e.msg)
+    };
+
+    // The builtins, index expressions and for loops work alike on strings,
+    // arrays and bytes. The elements of strings are characters.
+    let tests = [
+        (
+            r#""héllo""#,
+            r#"[5, 'h', 'o', "éllo", "él", 'é', [[0, 'h'], [1, 'é'], [2, 'l'], [3, 'l'], [4, 'o']]]"#,
+        ),
+        (
+            r#"[1, "a", 3.5]"#,
+            r#"[3, 1, 3.5, ["a", 3.5], ["a", 3.5], "a", [[0, 1], [1, "a"], [2, 3.5]]]"#,
+        ),
+        (
+            r#"bytes("abc")"#,
+            r#"[3, 0x61, 0x63, b"bc", b"bc", 0x62, [[0, 0x61], [1, 0x62], [2, 0x63]]]"#,
+        ),
+    ];
+    for (seq, expected) in tests {
+        let input = format!(
+            "let s = {}; let items = []; for i, e in s {{ push(items, [i, e]); }}
+            [len(s), first(s), last(s), rest(s), slice(s, 1, 2), s[1], items]",
+            seq
+        );
+        assert_eq!(run(&input).unwrap(), expected, "{}", seq);
+        let input = format!(
+            "let s = {}; [get(s, 1) == s[1], get(s, 9), get(s, -1)]",
+            seq
+        );
+        assert_eq!(run(&input).unwrap(), "[true, null, null]", "{}", seq);
+    }
+    for seq in [r#""""#, "[]", r#"bytes("")"#] {
+        let input = format!("let s = {}; [len(s), first(s), last(s), rest(s)]", seq);
+        assert_eq!(run(&input).unwrap(), "[0, null, null, null]", "{}", seq);
+    }
+
+    // The errors name the type of the sequence
+    for (seq, name) in [
+        (r#""ab""#, "string"),
+        ("[1, 2]", "array"),
+        (r#"bytes("ab")"#, "bytes"),
+    ] {
+        let expected = format!("IndexError: {} index out of range.", name);
+        assert_eq!(run(&format!("{}[2]", seq)), Err(expected));
+        let expected = String::from("IndexError: index cannot be negative.");
+        assert_eq!(run(&format!("{}[-1]", seq)), Err(expected));
+    }
+    let tests = [
+        (
+            r#"let s = "ab"; s[0] = 'c';"#,
+            "IndexError: string cannot be modified.",
+        ),
+        (r#"push("ab", 'c')"#, "push: string cannot be modified"),
+        (r#"pop(bytes("ab"))"#, "pop: bytes cannot be modified"),
+        ("first(map {})", "first: unsupported argument"),
+        ("rest(1)", "rest: unsupported argument"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run(input), Err(String::from(expected)), "{}", input);
+    }
+}
+
+#[test]
+fn test_builtin_functions_bytes() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"bytes("GET")"#,
+            expected: Object::Bytes(b"GET".to_vec()),
+        },
+        VmTestCase {
+            input: r#"bytes([b'a', b'b'])[1]"#,
+            expected: Object::Byte(b'b'),
+        },
+        VmTestCase {
+            input: r#"len(bytes("abc"))"#,
+            expected: Object::Integer(3),
+        },
+        VmTestCase {
+            input: r#"bytes("ab") == [b'a', b'b']"#,
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: r#"ntohs(bytes("abc"), 1)"#,
+            expected: Object::Integer(0x6263),
+        },
+        VmTestCase {
+            input: r#"decode_utf8(bytes("hi"))"#,
+            expected: Object::Str(String::from("hi")),
+        },
+        VmTestCase {
+            input: r#"str(bytes([b'a', byte(10)]))"#,
+            expected: Object::Str(String::from(r#"b"a\n""#)),
+        },
+        VmTestCase {
+            input: r#"hexdump("GET / HTTP/1.1")"#,
+            expected: Object::Str(String::from(
+                "0000  47 45 54 20 2f 20 48 54  54 50 2f 31 2e 31         GET / HTTP/1.1",
+            )),
+        },
+        VmTestCase {
+            // Two full lines and a line with a single byte
+            input: r#"len(hexdump("0123456789abcdef0123456789abcdef!"))"#,
+            expected: Object::Integer(73 * 2 + 58 + 2),
+        },
+        VmTestCase {
+            input: r#"hexdump("")"#,
+            expected: Object::Str(String::new()),
+        },
+        VmTestCase {
+            input: r#"[hex(255), hex(255, 4), hex(byte(10), 2), hex(-1)]"#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Str(String::from("ff"))),
+                Rc::new(Object::Str(String::from("00ff"))),
+                Rc::new(Object::Str(String::from("0a"))),
+                Rc::new(Object::Str(String::from("ffffffffffffffff"))),
+            ]))),
+        },
+        VmTestCase {
+            input: r#"slice(bytes("GET /"), 1, 2)"#,
+            expected: Object::Bytes(b"ET".to_vec()),
+        },
+        VmTestCase {
+            input: r#"slice("héllo", 1, 3)"#,
+            expected: Object::Str(String::from("éll")),
+        },
+        VmTestCase {
+            input: r#"slice([1, 2, 3], 1)"#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Integer(2)),
+                Rc::new(Object::Integer(3)),
+            ]))),
+        },
+        VmTestCase {
+            input: r#"slice("abc", 2, 10) + slice("abc", 5)"#,
+            expected: Object::Str(String::from("c")),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#"let b = bytes("ab"); b[0] = b'c';"#,
+            expected: "IndexError: bytes cannot be modified.",
+        },
+        VmTestCaseErr {
+            input: r#"bytes("ab")[2]"#,
+            expected: "IndexError: bytes index out of range.",
+        },
+        VmTestCaseErr {
+            input: r#"bytes([1, "a"])"#,
+            expected: "bytes: array should contain only bytes",
+        },
+        VmTestCaseErr {
+            input: "hexdump(1)",
+            expected: "hexdump: unsupported argument",
+        },
+        VmTestCaseErr {
+            input: "hex(1, 65)",
+            expected: "hex: width must be an integer from 0 to 64",
+        },
+        VmTestCaseErr {
+            input: r#"slice("abc", -1)"#,
+            expected: "slice: start must be a non-negative integer",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_builtin_functions_conversions() {
+    let tests = vec![
+        VmTestCase {
+            // rest([]) is Null
+            input: "str(rest([]))",
+            expected: Object::Str(String::from("null")),
+        },
+        VmTestCase {
+            input: r#"str("hello")"#,
+            expected: Object::Str(String::from("hello")),
+        },
+        VmTestCase {
+            input: "str(999)",
+            expected: Object::Str(String::from("999")),
+        },
+        VmTestCase {
+            input: "str(str(999))",
+            expected: Object::Str(String::from("999")),
+        },
+        VmTestCase {
+            input: "str(true)",
+            expected: Object::Str(String::from("true")),
+        },
+        VmTestCase {
+            input: "str([1, 2, 3333, 4, 5])",
+            expected: Object::Str(String::from("[1, 2, 3333, 4, 5]")),
+        },
+        VmTestCase {
+            // maps are unordered so use only a single item
+            input: r#"str(map {"a": 1})"#,
+            expected: Object::Str(String::from(r#"map {"a": 1}"#)),
+        },
+        VmTestCase {
+            input: "str(map {})",
+            expected: Object::Str(String::from("map {}")),
+        },
+        VmTestCase {
+            input: r#"int("999")"#,
+            expected: Object::Integer(999),
+        },
+        VmTestCase {
+            input: r#"int(999.99)"#,
+            expected: Object::Integer(999),
+        },
+        VmTestCase {
+            input: "int(str(999))",
+            expected: Object::Integer(999),
+        },
+        VmTestCase {
+            input: "int(true)",
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: "int(false)",
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            input: "int(b'1')",
+            expected: Object::Integer(49),
+        },
+        VmTestCase {
+            input: "int('1')",
+            expected: Object::Integer(49),
+        },
+        VmTestCase {
+            input: "float(b'1')",
+            expected: Object::Float(49.),
+        },
+        VmTestCase {
+            input: "float('1')",
+            expected: Object::Float(49.),
+        },
+        VmTestCase {
+            input: r#"str(b'1')"#,
+            expected: Object::Str(String::from("49")),
+        },
+        VmTestCase {
+            input: r#"str('1')"#,
+            expected: Object::Str(String::from("1")),
+        },
+        VmTestCase {
+            input: "char(b'1')",
+            expected: Object::Char('1'),
+        },
+        VmTestCase {
+            input: "byte('1')",
+            expected: Object::Byte(b'1'),
+        },
+        VmTestCase {
+            input: r#"chars("hello")"#,
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![
+                    Rc::new(Object::Char('h')),
+                    Rc::new(Object::Char('e')),
+                    Rc::new(Object::Char('l')),
+                    Rc::new(Object::Char('l')),
+                    Rc::new(Object::Char('o')),
+                ]),
+            })),
+        },
+        VmTestCase {
+            input: r#"join(['h', 'e', 'l', 'l', 'o'])"#,
+            expected: Object::Str(String::from("hello")),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_builtin_functions_strings() {
+    let str_arr = |parts: &[&str]| {
+        Object::Arr(Rc::new(Array::new(
+            parts
+                .iter()
+                .map(|p| Rc::new(Object::Str(p.to_string())))
+                .collect(),
+        )))
+    };
+    let tests = vec![
+        VmTestCase {
+            input: r#"split("a,b,,c", ",")"#,
+            expected: str_arr(&["a", "b", "", "c"]),
+        },
+        VmTestCase {
+            input: r#"split("  GET   /index.html HTTP/1.1 ")"#,
+            expected: str_arr(&["GET", "/index.html", "HTTP/1.1"]),
+        },
+        VmTestCase {
+            input: r#"split("k=v", '=')"#,
+            expected: str_arr(&["k", "v"]),
+        },
+        VmTestCase {
+            input: r#"join(split("a b c"), ",")"#,
+            expected: Object::Str(String::from("a,b,c")),
+        },
+        VmTestCase {
+            input: "trim(\" \tHost: x\r\n\")",
+            expected: Object::Str(String::from("Host: x")),
+        },
+        VmTestCase {
+            input: r#"[starts_with("GET /", "GET"), starts_with("GET /", "POST")]"#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Bool(true)),
+                Rc::new(Object::Bool(false)),
+            ]))),
+        },
+        VmTestCase {
+            input: r#"[ends_with("index.html", ".html"), ends_with("a", 'b')]"#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Bool(true)),
+                Rc::new(Object::Bool(false)),
+            ]))),
+        },
+        VmTestCase {
+            input: r#"replace("a-b-c", "-", "::")"#,
+            expected: Object::Str(String::from("a::b::c")),
+        },
+        VmTestCase {
+            input: r#"[contains("user-agent", "agent"), contains("abc", 'd')]"#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Bool(true)),
+                Rc::new(Object::Bool(false)),
+            ]))),
+        },
+        VmTestCase {
+            input: r#"find("key=value", '=')"#,
+            expected: Object::Integer(3),
+        },
+        VmTestCase {
+            input: r#"toupper(trim(" get "))"#,
+            expected: Object::Str(String::from("GET")),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#"split("abc", "")"#,
+            expected: "split: separator should not be empty",
+        },
+        VmTestCaseErr {
+            input: r#"split(1, ",")"#,
+            expected: "split: first argument should be a string",
+        },
+        VmTestCaseErr {
+            input: r#"replace("abc", "", "x")"#,
+            expected: "replace: string to replace should not be empty",
+        },
+        VmTestCaseErr {
+            input: r#"starts_with("abc", 1)"#,
+            expected: "starts_with: argument should be a string or a char",
+        },
+        VmTestCaseErr {
+            input: "join([1, 2])",
+            expected: "join: array should contain only chars or strings",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_regex_match() {
+    let bools = |values: &[bool]| {
+        Object::Arr(Rc::new(Array::new(
+            values.iter().map(|b| Rc::new(Object::Bool(*b))).collect(),
+        )))
+    };
+    let tests = vec![
+        VmTestCase {
+            input: r#"["GET /index.html" ~ "^(GET|POST) ", "PUT /" ~ "^(GET|POST) "]"#,
+            expected: bools(&[true, false]),
+        },
+        VmTestCase {
+            input: r#"["abc" !~ "d", "abc" !~ "b"]"#,
+            expected: bools(&[true, false]),
+        },
+        VmTestCase {
+            // The pattern may be computed at runtime or be a regex object
+            input: r#"let p = "^" + "a"; let re = regex("c$"); ["abc" ~ p, "abc" ~ re]"#,
+            expected: bools(&[true, true]),
+        },
+        VmTestCase {
+            input: r#"[bytes("abc") ~ "^\x61b", [b'a', b'c'] ~ "c\x00"]"#,
+            expected: bools(&[true, false]),
+        },
+        VmTestCase {
+            input: r#"[matches("port=443", "\d+"), matches([b'a'], regex("b"))]"#,
+            expected: bools(&[true, false]),
+        },
+        VmTestCase {
+            input: r#"captures("src=10.0.0.1 port=53", "(\w+)=(\d+)$")"#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Str("port=53".to_string())),
+                Rc::new(Object::Str("port".to_string())),
+                Rc::new(Object::Str("53".to_string())),
+            ]))),
+        },
+        VmTestCase {
+            input: r#"captures("ac", "a(b)?c")"#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Str("ac".to_string())),
+                Rc::new(Object::Null),
+            ]))),
+        },
+        VmTestCase {
+            input: r#"captures(bytes("GET /"), "GET (\S+)")"#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Bytes(b"GET /".to_vec())),
+                Rc::new(Object::Bytes(b"/".to_vec())),
+            ]))),
+        },
+        VmTestCase {
+            input: r#"captures("abc", "d")"#,
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: r#"str(regex("a+b"))"#,
+            expected: Object::Str("a+b".to_string()),
+        },
+        VmTestCase {
+            input: r#"regex("a") == regex("a") && regex("a") != regex("b")"#,
+            expected: Object::Bool(true),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#"regex("(a")"#,
+            expected: "regex: invalid regex '(a': unmatched '('",
+        },
+        VmTestCaseErr {
+            input: r#"let p = "[a"; "abc" ~ p"#,
+            expected: "Regex match failed: invalid regex '[a': unterminated character class.",
+        },
+        VmTestCaseErr {
+            input: r#"1 ~ "a""#,
+            expected: "Regex match failed: unsupported argument.",
+        },
+        VmTestCaseErr {
+            input: r#"matches("a", 1)"#,
+            expected: "matches: pattern should be a regex or a string",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+#[allow(clippy::approx_constant)]
+fn test_builtin_functions_math() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"round(3.141592653589793238, 2)"#,
+            expected: Object::Float(3.14),
+        },
+        VmTestCase {
+            input: r#"
+                let n = rand(100);
+                let f = rand(100.);
+                n >= 0 && n < 100 && f >= 0. && f < 100.
+            "#,
+            expected: Object::Bool(true),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_builtin_functions_display() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"puts("hello", "world!")"#,
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: r#"print("Hello, World!")"#,
+            expected: Object::Integer(13),
+        },
+        VmTestCase {
+            input: r#"println("Hello, World!")"#,
+            expected: Object::Integer(14),
+        },
+        VmTestCase {
+            input: r#"
+                format("{0:<10},{1:0>5},{2},{3:b},{3:o},{4:x},{4:X}",
+                "Hello", 1, true, 10, 65535
+            )"#,
+            expected: Object::Str("Hello     ,00001,true,1010,12,ffff,FFFF".to_string()),
+        },
+        VmTestCase {
+            input: r#"
+                print("{0:<10},{1:0>5},{2},{3:b},{3:o},{4:x},{4:X}",
+                "Hello", 1, true, 10, 65535
+            )"#,
+            expected: Object::Integer(39),
+        },
+        VmTestCase {
+            input: r#"
+                println("{0:<10},{1:0>5},{2},{3:b},{3:o},{4:x},{4:X}",
+                "Hello", 1, true, 10, 65535
+            )"#,
+            expected: Object::Integer(40),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_file_lines_and_close() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+                let f = open("/tmp/__p2sh_test3.txt", "w");
+                write(f, "10.0.0.1");
+                write(f, byte(10));
+                close(f);
+                let f = open("/tmp/__p2sh_test3.txt", "a");
+                write(f, "10.0.0.2");
+                write(f, byte(13));
+                write(f, byte(10));
+                write(f, "10.0.0.3");
+                close(f);
+                close(f);
+                let blocked = set();
+                for line in open("/tmp/__p2sh_test3.txt") {
+                    insert(blocked, line);
+                }
+                blocked
+            "#,
+            expected: Object::Set(Rc::new(HSet::new(
+                ["10.0.0.1", "10.0.0.2", "10.0.0.3"]
+                    .iter()
+                    .map(|s| Rc::new(Object::Str(s.to_string())))
+                    .collect(),
+            ))),
+        },
+        VmTestCase {
+            input: r#"
+                let n = 0;
+                for i, line in open("/tmp/__p2sh_test3.txt") {
+                    if line == "10.0.0.2" { n = i; break; }
+                }
+                n
+            "#,
+            expected: Object::Integer(1),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#"
+                let f = open("/tmp/__p2sh_test3.txt");
+                close(f);
+                read_line(f)
+            "#,
+            expected: "read_line: file is closed",
+        },
+        VmTestCaseErr {
+            input: r#"
+                let f = open("/tmp/__p2sh_test3.txt");
+                close(f);
+                for line in f { }
+            "#,
+            expected: "Failed to read line: file is closed.",
+        },
+        VmTestCaseErr {
+            input: r#"for line in open("/tmp/__p2sh_test3.txt", "a") { }"#,
+            expected: "Cannot iterate over <file: writer>.",
+        },
+        VmTestCaseErr {
+            input: "close(stdout)",
+            expected: "close: cannot close <stdout>",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_csv_write() {
+    use crate::builtins::csv::{args_to_csv_row, to_csv_row};
+
+    let fields = vec![
+        Rc::new(Object::Integer(80)),
+        Rc::new(Object::Str(String::from("plain"))),
+        Rc::new(Object::Str(String::from("a,b"))),
+        Rc::new(Object::Str(String::from("say \"hi\""))),
+        Rc::new(Object::Str(String::from("two\nlines"))),
+        Rc::new(Object::Null),
+        Rc::new(Object::Char('"')),
+    ];
+    assert_eq!(
+        to_csv_row(&fields),
+        "80,plain,\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\",,\"\"\"\"\n"
+    );
+    let arr = Rc::new(Object::Arr(Rc::new(Array::new(fields[..2].to_vec()))));
+    assert_eq!(args_to_csv_row(&[arr]), "80,plain\n");
+    assert_eq!(to_csv_row(&[]), "\n");
+
+    let path = "/tmp/__p2sh_test_csv.csv";
+    let bytecode = test_compile(
+        r#"
+            let f = open("/tmp/__p2sh_test_csv.csv", "w");
+            let n = csv_write(f, ["src", "dst", "len"]);
+            csv_write(f, ["10.0.0.1", "10.0.0.2", 60]);
+            csv_write(f, ["x,y", 1.5, true]);
+            close(f);
+            n
+        "#,
+    );
+    let mut vm = VM::new(bytecode);
+    vm.run().unwrap();
+    assert_eq!(vm.last_popped().to_string(), "12");
+    let written = std::fs::read_to_string(path).unwrap();
+    assert_eq!(
+        written,
+        "src,dst,len\n10.0.0.1,10.0.0.2,60\n\"x,y\",1.5,true\n"
+    );
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: "csv_write(stdout)",
+            expected: "csv_write: takes two arguments. got=1",
+        },
+        VmTestCaseErr {
+            input: r#"csv_write("out.csv", [1])"#,
+            expected: "csv_write: first argument should be a file handle",
+        },
+        VmTestCaseErr {
+            input: "csv_write(stdout, 1)",
+            expected: "csv_write: second argument should be an array",
+        },
+        VmTestCaseErr {
+            input: "csv_write(stdin, [1])",
+            expected: "csv_write: cannot write to stdin",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_deterministic_mode() {
+    let run = |input: &str, seed: u64| {
+        let mut vm = VM::new(test_compile(input));
+        vm.set_seed(seed);
+        vm.run()
+            .map(|_| vm.last_popped().to_string())
+            .map_err(|e| e.msg)
+    };
+    let input = "[rand(), rand(1000), rand(1.0)]";
+    let first = run(input, 42).unwrap();
+    assert_eq!(run(input, 42).unwrap(), first);
+    assert_ne!(run(input, 7).unwrap(), first);
+    assert_eq!(run("time()", 42).unwrap(), "0");
+    assert_eq!(
+        run("time(1)", 42).unwrap_err(),
+        "time: takes no argument(s). got=1"
+    );
+    assert_eq!(
+        run("rand(1, 2)", 42).unwrap_err(),
+        "rand: takes one or no arguments. got=2"
+    );
+
+    // Maps and sets are printed in order in all modes
+    let tests = [
+        (
+            r#"map {"b": 2, "c": 3, "a": 1}"#,
+            r#"map {"a": 1, "b": 2, "c": 3}"#,
+        ),
+        ("set([3, 1, 2])", "set {1, 2, 3}"),
+        (
+            "map {2: set([5, 4]), 1: map {}}",
+            "map {1: map {}, 2: set {4, 5}}",
+        ),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run(input, 0).unwrap(), expected, "{}", input);
+    }
+
+    // Elements and keys of mixed types are ordered by their type, then by
+    // their value, whatever the order of the hash table
+    let input = r#"
+        let s = set(["b", 2.5, 'c', true, [2], [1, "x"], 1, "a", b'z', false, null, 3]);
+        let m = map {};
+        for e in s { m[e] = 0; }
+        [s, m]
+    "#;
+    let expected = concat!(
+        r#"[set {null, false, true, 1, 2.5, 3, 0x7a, 'c', "a", "b", [1, "x"], [2]}, "#,
+        r#"map {null: 0, false: 0, true: 0, 1: 0, 2.5: 0, 3: 0, 0x7a: 0, 'c': 0, "a": 0, "b": 0, [1, "x"]: 0, [2]: 0}]"#,
+    );
+    for seed in 0..20 {
+        assert_eq!(run(input, seed).unwrap(), expected, "seed {}", seed);
+    }
+}
+
+#[test]
+fn test_object_report() {
+    let input = r#"
+        let seen = map {};
+        for i in 0..20 { seen["k" + str(i)] = [i]; }
+
+        let ports = set([53, 443]);
+        let alias = seen;
+        let nested = [[1, 2, 3]];
+        fn count() { len(seen) }
+    "#;
+    let mut vm = VM::new(test_compile(input));
+    vm.run().unwrap();
+    let report = vm.object_report();
+    let count = |kind: &str| {
+        report
+            .counts()
+            .iter()
+            .find(|(k, _)| *k == kind)
+            .map_or(0, |(_, n)| *n)
+    };
+    // The map held by both 'seen' and 'alias' is counted once
+    assert_eq!(count("map"), 1);
+    assert_eq!(count("array"), 22);
+    assert_eq!(count("set"), 1);
+    assert_eq!(count("closure"), 1);
+    assert_eq!(count("string"), 20);
+
+    let largest = &report.largest()[0];
+    assert_eq!(
+        (
+            largest.kind,
+            largest.len,
+            largest.path.as_str(),
+            largest.line
+        ),
+        ("map", 20, "seen", Some(2))
+    );
+    let nested = report
+        .largest()
+        .iter()
+        .find(|c| c.path == "nested[0]")
+        .unwrap();
+    assert_eq!(
+        (nested.kind, nested.len, nested.line),
+        ("array", 3, Some(7))
+    );
+    assert!(report.largest().len() <= 10);
+    assert!(report.to_string().starts_with("object"));
+}
+
+#[test]
+fn test_name_resolution() {
+    let run = |input: &str| {
+        let mut vm = VM::new(test_compile(input));
+        vm.set_no_dns(true);
+        vm.resolver_mut().set_hosts(
+            "# comment\n127.0.0.1 localhost loopback\n10.0.0.1\tgateway # router\n\
+             ::1 ip6-localhost\n10.0.0.1 other\n",
+        );
+        vm.resolver_mut()
+            .set_services("http\t80/tcp www\ndomain 53/udp\ndomain 53/tcp\nbad x/tcp\n");
+        vm.run()
+            .map(|_| vm.last_popped().to_string())
+            .map_err(|e| e.msg)
+    };
+
+    let tests = [
+        (r#"resolve("10.0.0.1")"#, r#""gateway""#),
+        (r#"resolve("127.0.0.1")"#, r#""localhost""#),
+        (r#"resolve("::1")"#, r#""ip6-localhost""#),
+        // Addresses without a name are returned as they are
+        (r#"resolve("192.0.2.1")"#, r#""192.0.2.1""#),
+        (
+            r#"[resolve("192.0.2.1"), resolve("10.0.0.1")]"#,
+            r#"["192.0.2.1", "gateway"]"#,
+        ),
+        (r#"service_name(80, "tcp")"#, r#""http""#),
+        (r#"service_name(53, "UDP")"#, r#""domain""#),
+        (r#"service_name(80, "udp")"#, r#""80""#),
+        (r#"service_name(65535, "tcp")"#, r#""65535""#),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run(input).unwrap(), expected, "{}", input);
+    }
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: "resolve(1)",
+            expected: "resolve: argument should be an ip address",
+        },
+        VmTestCaseErr {
+            input: r#"resolve("10.0.0")"#,
+            expected: "resolve: invalid ip address '10.0.0'",
+        },
+        VmTestCaseErr {
+            input: r#"service_name(70000, "tcp")"#,
+            expected: "service_name: invalid port 70000",
+        },
+        VmTestCaseErr {
+            input: "service_name(80)",
+            expected: "service_name: takes two arguments. got=1",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_ip_and_cidr() {
+    let run = |input: &str| {
+        let mut vm = VM::new(test_compile(input));
+        vm.run()
+            .map(|_| vm.last_popped().to_string())
+            .map_err(|e| e.msg)
+    };
+    let tests = [
+        (r#"ip("10.0.0.1")"#, "10.0.0.1"),
+        (r#"ip("::1")"#, "0:0:0:0:0:0:0:1"),
+        (r#"ip("10.0.0.1") == "10.0.0.1""#, "true"),
+        (r#"cidr("10.1.2.3/16")"#, "10.1.0.0/16"),
+        (r#"cidr("10.1.2.3")"#, "10.1.2.3/32"),
+        (r#"cidr("fe80::1/10")"#, "fe80:0:0:0:0:0:0:0/10"),
+        (r#"cidr("10.1.2.3/16") == cidr("10.1.0.0/16")"#, "true"),
+        (r#"str(cidr("192.168.7.1/23"))"#, r#""192.168.6.0/23""#),
+        (r#"ip("10.0.0.1") in cidr("10.0.0.0/8")"#, "true"),
+        (r#"ip("11.0.0.1") in cidr("10.0.0.0/8")"#, "false"),
+        (r#""192.168.1.9" in cidr("192.168.0.0/16")"#, "true"),
+        (r#"ip("1.2.3.4") in cidr("0.0.0.0/0")"#, "true"),
+        (r#"ip("10.0.0.1") in cidr("::/0")"#, "false"),
+        (r#"ip("fe80::1:2") in cidr("fe80::/10")"#, "true"),
+        (r#"cidr("10.1.0.0/16") in cidr("10.0.0.0/8")"#, "true"),
+        (r#"cidr("10.0.0.0/8") in cidr("10.1.0.0/16")"#, "false"),
+        (r#"contains(cidr("10.0.0.0/8"), "10.2.3.4")"#, "true"),
+        (
+            r#"let nets = set(); insert(nets, cidr("10.0.0.0/8")); cidr("10.0.0.0/8") in nets"#,
+            "true",
+        ),
+        (r#"to_json([cidr("10.0.0.0/8")])"#, r#""["10.0.0.0/8"]""#),
+        // Addresses and networks that do not parse are error values
+        (
+            r#"str(ip("10.0.0"))"#,
+            r#""value-error: invalid ip address '10.0.0'""#,
+        ),
+        (
+            r#"err_msg(ip("00:11:22:33:44:55"))"#,
+            r#""invalid ip address '00:11:22:33:44:55'""#,
+        ),
+        (
+            r#"err_msg(cidr("10.0.0.0/33"))"#,
+            r#""invalid prefix length '33'""#,
+        ),
+        (
+            r#"err_msg(cidr("10.0.0/8"))"#,
+            r#""invalid network address '10.0.0'""#,
+        ),
+        // 'in' on the other containers
+        ("2 in [1, 2, 3]", "true"),
+        ("4 in [1, 2, 3]", "false"),
+        (r#""a" in map {"a": 1}"#, "true"),
+        (r#""ell" in "hello""#, "true"),
+        ("'z' in \"hello\"", "false"),
+        (
+            "let s = set(); insert(s, 5); [5 in s, 6 in s]",
+            "[true, false]",
+        ),
+        ("!(3 in [1]) && 1 in [1]", "true"),
+        (
+            "let n = 0; for x in [1, 2, 3] { if x in [2, 3] { n = n + x; } } n",
+            "5",
+        ),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run(input).unwrap(), expected, "{}", input);
+    }
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: "ip(1)",
+            expected: "ip: argument should be a string",
+        },
+        VmTestCaseErr {
+            input: r#""10.0.0" in cidr("10.0.0.0/8")"#,
+            expected: "Operator 'in' failed: invalid ip address '10.0.0'.",
+        },
+        VmTestCaseErr {
+            input: r#"1 in cidr("10.0.0.0/8")"#,
+            expected: "Operator 'in' failed: element should be an ip address or a network.",
+        },
+        VmTestCaseErr {
+            input: "1 in 2",
+            expected: "Operator 'in' failed: unsupported argument.",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_timestamps() {
+    let run = |input: &str| {
+        let mut vm = VM::new(test_compile(input));
+        vm.run()
+            .map(|_| vm.last_popped().to_string())
+            .map_err(|e| e.msg)
+    };
+    let tests = [
+        ("timestamp(0)", "1970-01-01T00:00:00.000000000Z"),
+        ("timestamp(1700000000, 5)", "2023-11-14T22:13:20.000000005Z"),
+        ("timestamp(1.25)", "1970-01-01T00:00:01.250000000Z"),
+        ("timestamp(-1)", "1969-12-31T23:59:59.000000000Z"),
+        ("timestamp(951782400)", "2000-02-29T00:00:00.000000000Z"),
+        // Arithmetic with seconds and the difference as a duration
+        ("timestamp(10) + 5", "1970-01-01T00:00:15.000000000Z"),
+        ("1.5 + timestamp(10)", "1970-01-01T00:00:11.500000000Z"),
+        ("timestamp(10) - 20", "1969-12-31T23:59:50.000000000Z"),
+        ("timestamp(10) - 1ms", "1970-01-01T00:00:09.999000000Z"),
+        ("timestamp(12, 500000000) - timestamp(10)", "2500ms"),
+        ("timestamp(10) - timestamp(12)", "-2s"),
+        (
+            "timestamp(10) + duration(1500)",
+            "1970-01-01T00:00:11.500000000Z",
+        ),
+        (
+            "timestamp(10) - duration(1)",
+            "1970-01-01T00:00:09.999000000Z",
+        ),
+        ("timestamp(12) - timestamp(10) > 1s", "true"),
+        // Comparisons
+        ("timestamp(10) < timestamp(11)", "true"),
+        ("timestamp(10) >= timestamp(10, 1)", "false"),
+        ("timestamp(10) == timestamp(9) + 1", "true"),
+        ("timestamp(10) != timestamp(10)", "false"),
+        (
+            "sort([timestamp(3), timestamp(1), timestamp(2)])[0] == timestamp(1)",
+            "true",
+        ),
+        // Conversions
+        ("int(timestamp(10, 999999999))", "10"),
+        ("float(timestamp(10, 500000000))", "10.5"),
+        ("str(timestamp(0))", r#""1970-01-01T00:00:00.000000000Z""#),
+        (
+            "to_json([timestamp(0)])",
+            r#""["1970-01-01T00:00:00.000000000Z"]""#,
+        ),
+        ("let m = map {}; m[timestamp(1)] = 1; m[timestamp(1)]", "1"),
+        // Formatting
+        (
+            r#"strftime(timestamp(1700000000, 123456789), "%Y-%m-%d %H:%M:%S.%f")"#,
+            r#""2023-11-14 22:13:20.123456""#,
+        ),
+        (
+            r#"strftime(timestamp(1700000000), "%a %A %b %B %e %j %u %w %y %C")"#,
+            r#""Tue Tuesday Nov November 14 318 2 2 23 20""#,
+        ),
+        (
+            r#"strftime(timestamp(1700000000, 7), "%F %T %R %D %I%p %s.%N %z %Z %%")"#,
+            r#""2023-11-14 22:13:20 22:13 11/14/23 10PM 1700000000.000000007 +0000 UTC %""#,
+        ),
+        (r#"strftime(86399, "%T")"#, r#""23:59:59""#),
+        (r#"strftime(0.5, "%S.%f")"#, r#""00.500000""#),
+        // Not set outside filters
+        ("TS", "null"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run(input).unwrap(), expected, "{}", input);
+    }
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#"timestamp("x")"#,
+            expected: "timestamp: unsupported argument",
+        },
+        VmTestCaseErr {
+            input: "timestamp()",
+            expected: "timestamp: takes one or two arguments. got=0",
+        },
+        VmTestCaseErr {
+            input: "timestamp(9223372036854775807)",
+            expected: "timestamp: timestamp out of range",
+        },
+        VmTestCaseErr {
+            input: r#"strftime("x", "%Y")"#,
+            expected: "strftime: first argument should be a timestamp",
+        },
+        VmTestCaseErr {
+            input: "strftime(0, 1)",
+            expected: "strftime: format should be a string",
+        },
+        VmTestCaseErr {
+            input: r#"strftime(0, "%Q")"#,
+            expected: "strftime: unsupported conversion '%Q'",
+        },
+        VmTestCaseErr {
+            input: r#"strftime(0, "%")"#,
+            expected: "strftime: format ends with '%'",
+        },
+        VmTestCaseErr {
+            input: "timestamp(1) + timestamp(2)",
+            expected: "Invalid operation on timestamps.",
+        },
+        VmTestCaseErr {
+            input: "1 - timestamp(2)",
+            expected: "Invalid operation on timestamps.",
+        },
+        VmTestCaseErr {
+            input: "timestamp(9223372036) + 1",
+            expected: "Timestamp out of range.",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_builtin_functions_file_io() {
+    // Preserve the sequence of tests here
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+               let f = open("/tmp/__p2sh_test1.txt", "w");
+               write(f, "Hello")
+            "#,
+            expected: Object::Integer(5),
+        },
+        VmTestCase {
+            input: r#"
+                let f = open("/tmp/__p2sh_test1.txt", "a");
+                let n1 = write(f, [b' ', b'W', b'o', b'r', b'l', b'd', b'!']);
+                let n2 = write(f, byte(10));
+                n1 + n2
+            "#,
+            expected: Object::Integer(8),
+        },
+        VmTestCase {
+            input: r#"
+                let f = open("/tmp/__p2sh_test1.txt");
+                let a1 = read(f, 5);
+                read(f, 1);
+                let a2 = read(f, 5);
+                let s1 = decode_utf8(a1);
+                let s2 = decode_utf8(a2);
+                s1 + s2
+            "#,
+            expected: Object::Str(String::from("HelloWorld")),
+        },
+        VmTestCase {
+            input: r#"
+                let f = open("/tmp/__p2sh_test1.txt");
+                let a = read(f);
+                decode_utf8(a)
+            "#,
+            expected: Object::Str(String::from("Hello World!\n")),
+        },
+        VmTestCase {
+            input: r#"
+                let n = 0;
+                let a = null;
+                let f = open("/tmp/__p2sh_test1.txt");
+                while a = read(f, 1) {
+                  n = n + 1;
+                }
+                n
+            "#,
+            expected: Object::Integer(13),
+        },
+        VmTestCase {
+            input: r#"
+                let f = open("/tmp/__p2sh_test1.txt", "a");
+                let n1 = write(f, "EOF");
+                let n2 = write(f, byte(10));
+                n1 + n2
+            "#,
+            expected: Object::Integer(4),
+        },
+        VmTestCase {
+            input: r#"
+                let s = null;
+                let all = "";
+                let f = open("/tmp/__p2sh_test1.txt");
+                while s = read_line(f) {
+                    all = all + s;
+                }
+                all
+            "#,
+            expected: Object::Str(String::from("Hello World!\nEOF\n")),
+        },
+        VmTestCase {
+            input: r#"decode_utf8([b'H', b'e', b'l', b'l', b'o'])"#,
+            expected: Object::Str(String::from("Hello")),
+        },
+        VmTestCase {
+            input: r#"encode_utf8("Hello")"#,
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![
+                    Rc::new(Object::Byte(b'H')),
+                    Rc::new(Object::Byte(b'e')),
+                    Rc::new(Object::Byte(b'l')),
+                    Rc::new(Object::Byte(b'l')),
+                    Rc::new(Object::Byte(b'o')),
+                ]),
+            })),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_builtin_function_failures() {
+    let tests: Vec<VmTestCaseErr> = vec![
+        VmTestCaseErr {
+            input: r#"len(1)"#,
+            expected: "len: unsupported argument",
+        },
+        VmTestCaseErr {
+            input: r#"len("one", "two")"#,
+            expected: "len: takes one argument. got=2",
+        },
+        VmTestCaseErr {
+            input: r#"first(1)"#,
+            expected: "first: unsupported argument",
+        },
+        VmTestCaseErr {
+            input: r#"last(1)"#,
+            expected: "last: unsupported argument",
+        },
+        VmTestCaseErr {
+            input: r#"push(1, 1)"#,
+            expected: "push: unsupported argument",
+        },
+        VmTestCaseErr {
+            input: "str(fn() {})",
+            expected: "str: unsupported argument",
+        },
+        VmTestCaseErr {
+            input: "int([])",
+            expected: "int: unsupported argument",
+        },
+        VmTestCaseErr {
+            input: "int(map {})",
+            expected: "int: unsupported argument",
+        },
+        VmTestCaseErr {
+            input: r#"int("1.11")"#,
+            expected: "int: failed to parse string into an int",
+        },
+        VmTestCaseErr {
+            input: r#"float("a")"#,
+            expected: "float: failed to parse string into a float",
+        },
+    ];
+
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_builtin_function_failures_file_io() {
+    let tests: Vec<VmTestCaseErr> = vec![
+        VmTestCaseErr {
+            input: r#"open()"#,
+            expected: "open: takes one or two arguments. got=0",
+        },
+        VmTestCaseErr {
+            input: r#"read()"#,
+            expected: "read: takes one or two arguments. got=0",
+        },
+        VmTestCaseErr {
+            input: r#"read_line()"#,
+            expected: "read_line: takes one argument. got=0",
+        },
+        VmTestCaseErr {
+            input: "flush(stdin)",
+            expected: "flush: cannot flush stdin",
+        },
+        VmTestCaseErr {
+            input: r#"write(stdin, "test")"#,
+            expected: "write: cannot write to stdin",
+        },
+        VmTestCaseErr {
+            input: "read(stdout)",
+            expected: "read: cannot read from stdout",
+        },
+        VmTestCaseErr {
+            input: "read(stderr)",
+            expected: "read: cannot read from stderr",
+        },
+        VmTestCaseErr {
+            input: r#"
+                let f = open("/tmp/__p2sh_test2.txt", "w");
+                read(f)
+            "#,
+            expected: "read: cannot read from a writer",
+        },
+        VmTestCaseErr {
+            input: r#"
+                let f = open("/tmp/__p2sh_test2.txt", "a");
+                read(f)
+            "#,
+            expected: "read: cannot read from a writer",
+        },
+        VmTestCaseErr {
+            input: r#"
+                let f = open("/tmp/__p2sh_test2.txt", "r");
+                write(f, "test")
+            "#,
+            expected: "write: cannot write to a reader",
+        },
+        VmTestCaseErr {
+            input: r#"
+                let f = open("/tmp/__p2sh_test2.txt", "r");
+                flush(f)
+            "#,
+            expected: "flush: cannot flush a reader",
+        },
+    ];
+
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_builtin_variables() {
+    // run_vm_tests passes empty argv to the vm
+    let tests = vec![
+        VmTestCase {
+            input: r#"argv"#,
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(Vec::new()),
+            })),
+        },
+        VmTestCase {
+            input: r#"len(argv)"#,
+            expected: Object::Integer(0),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_closures() {
+    let tests: Vec<VmTestCase> = vec![
+        // newClosure returns a closure that closes over a free variable,
+        // the parameter 'a' of newClosure
+        VmTestCase {
+            input: r#"
+                let newClosure = fn(a) {
+                    fn() { a; }
+                }
+                let closure = newClosure(99);
+                closure();
+                "#,
+            expected: Object::Integer(99),
+        },
+        VmTestCase {
+            input: r#"
+                let newAdder = fn(a, b) {
+                    fn(c) { a + b + c; }
+                }
+                let adder = newAdder(1, 2);
+                adder(8);
+                "#,
+            expected: Object::Integer(11),
+        },
+        VmTestCase {
+            input: r#"
+                let newAdder = fn(a, b) {
+                    let c = a + b;
+                    fn(d) { c + d; }
+                }
+                let adder = newAdder(1, 2);
+                adder(8);
+                "#,
+            expected: Object::Integer(11),
+        },
+        VmTestCase {
+            input: r#"
+                let newAdderOuter = fn(a, b) {
+                    let c = a + b;
+                    fn(d) {
+                        let e = d + c;
+                        fn(f) { e + f; };
+                    };
+                };
+                let newAdderInner = newAdderOuter(1, 2);
+                let adder = newAdderInner(3);
+                adder(8);
+                "#,
+            expected: Object::Integer(14),
+        },
+        VmTestCase {
+            input: r#"
+                let a = 1;
+                let newAdderOuter = fn(b) {
+                    fn(c) {
+                        fn(d) { a + b + c + d };
+                    };
+                };
+                let newAdderInner = newAdderOuter(2);
+                let adder = newAdderInner(3);
+                adder(8);
+                "#,
+            expected: Object::Integer(14),
+        },
+        VmTestCase {
+            input: r#"
+                let newClosure = fn(a, b) {
+                    let one = fn() { a; };
+                    let two = fn() { b; };
+                    fn() { one() + two(); };
+                };
+                let closure = newClosure(9, 90);
+                closure();
+                "#,
+            expected: Object::Integer(99),
+        },
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_recursive_functions() {
+    let tests: Vec<VmTestCase> = vec![
+        VmTestCase {
+            input: r#"
+                let countDown = fn(x) {
+                    if x == 0 {
+                        return 0;
+                    } else {
+                        countDown(x - 1);
+                    }
+                };
+                countDown(1);
+                "#,
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            input: r#"
+                let countDown = fn(x) {
+                    if x == 0 {
+                        return 0;
+                    } else {
+                        countDown(x - 1);
+                    }
+                };
+                let wrapper = fn() {
+                    countDown(1);
+                };
+                wrapper();
+            "#,
+            expected: Object::Integer(0),
+        },
+        // define a recursive function inside another function and also
+        // call it inside this other function.
+        VmTestCase {
+            input: r#"
+                let wrapper = fn() {
+                    let countDown = fn(x) {
+                        if x == 0 {
+                            return 0;
+                        } else {
+                            countDown(x - 1);
+                        }
+                    };
+                    countDown(1);
+                };
+                wrapper();
+            "#,
+            expected: Object::Integer(0),
+        },
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_recursive_fibonacci() {
+    let tests: Vec<VmTestCase> = vec![VmTestCase {
+        input: r#"
+                let fibonacci = fn(x) {
+                    if x == 0 {
+                        return 0;
+                    } else {
+                        if x == 1 {
+                            return 1;
+                        } else {
+                            fibonacci(x - 1) + fibonacci(x - 2);
+                        }
+                    }
+                };
+                fibonacci(15);
+                "#,
+        expected: Object::Integer(610),
+    }];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_iterative_fibonacci_loop() {
+    let tests: Vec<VmTestCase> = vec![VmTestCase {
+        input: r#"
+            fn fibonacci(n) {
+                if n == 0 {
+                    return 0;
+                } else {
+                    if n == 1 {
+                        return 1;
+                    }
+                }
+
+                let a = 0;
+                let b = 1;
+                let result = 0;
+                let i = 2;
+
+                loop {
+                    if i > n {
+                        break;
+                    }
+
+                    result = a + b;
+                    a = b;
+                    b = result;
+                    i = i + 1;
+                }
+
+                return result;
+            }
+
+            fibonacci(15);
+        "#,
+        expected: Object::Integer(610),
+    }];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_iterative_fibonacci_while() {
+    let tests: Vec<VmTestCase> = vec![VmTestCase {
+        input: r#"
+            fn fibonacci(n) {
+                if n == 0 {
+                    return 0;
+                } else {
+                    if n == 1 {
+                        return 1;
+                    }
+                }
+
+                let a = 0;
+                let b = 1;
+                let result = 0;
+                let i = 2;
+
+                while i <= n {
+                    result = a + b;
+                    a = b;
+                    b = result;
+                    i = i + 1;
+                }
+
+                return result;
+            }
+
+            fibonacci(15);
+        "#,
+        expected: Object::Integer(610),
+    }];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_closures_with_depth() {
+    let tests: Vec<VmTestCase> = vec![
+        VmTestCase {
+            input: r#"
+                let newClosure = fn(a) {
+                    fn() { a; }
+                }
+                let closure = newClosure(99);
+                closure();
+                "#,
+            expected: Object::Integer(99),
+        },
+        VmTestCase {
+            input: r#"
+                fn(a) {
+                    {
+                        let a = 10;
+                    }
+                    fn(b) {
+                        a + b
+                    }
+                }(1)(2)
+                "#,
+            expected: Object::Integer(3),
+        },
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_assignment_expressions() {
+    let tests: Vec<VmTestCase> = vec![
+        VmTestCase {
+            input: "let a = 1; a = 2;",
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: "let a = 1.; a = 2.; a",
+            expected: Object::Float(2.),
+        },
+        VmTestCase {
+            input: "let a = true; a = false; a",
+            expected: Object::Bool(false),
+        },
+        // Assignment expressions evaluate to the value assigned
+        // Use assignment expression enclosed in parantheses to avoid ambiguity
+        VmTestCase {
+            input: r#" let a = 0; if (a = 1) { "then" } else { "else" } "#,
+            expected: Object::Str("then".to_string()),
+        },
+        // If expression evaluates to falsey, since 0 is falsey.
+        VmTestCase {
+            input: r#" let a = 1; if (a = 0) { "then" } else { "else" } "#,
+            expected: Object::Str("else".to_string()),
+        },
+        VmTestCase {
+            input: "let a = [1]; a = [2]; a",
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![Rc::new(Object::Integer(2))]),
+            })),
+        },
+        VmTestCase {
+            input: r#"let m = map {"a": 1}; m = map {"a": 222}; m"#,
+            expected: Object::Map({
+                let map = HMap::default();
+                map.insert(
+                    Rc::new(Object::Str("a".into())),
+                    Rc::new(Object::Integer(222)),
+                );
+                Rc::new(map)
+            }),
+        },
+        VmTestCase {
+            input: "let a = 0; let b = 0; a = b = 222; [a, b]",
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![
+                    Rc::new(Object::Integer(222)),
+                    Rc::new(Object::Integer(222)),
+                ]),
+            })),
+        },
+        VmTestCase {
+            input: "let a = [0]; let b = [0]; a[0] = b[0] = 222; [a[0], b[0]]",
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![
+                    Rc::new(Object::Integer(222)),
+                    Rc::new(Object::Integer(222)),
+                ]),
+            })),
+        },
+        VmTestCase {
+            input: "let a = [0, 0]; a[0] = a[1] = 222; [a[0], a[0]]",
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![
+                    Rc::new(Object::Integer(222)),
+                    Rc::new(Object::Integer(222)),
+                ]),
+            })),
+        },
+        VmTestCase {
+            input: r#"let m = map {}; m[0] = m[1] = 222; [m[0], m[1]]"#,
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![
+                    Rc::new(Object::Integer(222)),
+                    Rc::new(Object::Integer(222)),
+                ]),
+            })),
+        },
+        VmTestCase {
+            input: "let a = [1]; let b = [a]; a[0] = 2; [a[0], b[0]]",
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![
+                    Rc::new(Object::Integer(2)),
+                    Rc::new(Object::Arr(Rc::new(Array {
+                        elements: RefCell::new(vec![Rc::new(Object::Integer(2))]),
+                    }))),
+                ]),
+            })),
+        },
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_set_index_assignment_expressions() {
+    let tests: Vec<VmTestCase> = vec![
+        VmTestCase {
+            input: "let a = [1, 2, 3]; a[0] = 111; a[1] = 222; a[2] = 333; a",
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![
+                    Rc::new(Object::Integer(111)),
+                    Rc::new(Object::Integer(222)),
+                    Rc::new(Object::Integer(333)),
+                ]),
+            })),
+        },
+        VmTestCase {
+            input: r#"
+                let mul = fn(a, idx, val) {
+                    a[idx] = a[idx] * val
+                }
+                let a = [1, 2, 3];
+                mul(a, 0, 10);
+                mul(a, 1, 100);
+                mul(a, 2, 1000);
+                a
+            "#,
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![
+                    Rc::new(Object::Integer(10)),
+                    Rc::new(Object::Integer(200)),
+                    Rc::new(Object::Integer(3000)),
+                ]),
+            })),
+        },
+        VmTestCase {
+            input: r#"let m = map {}; m["a"] = 1; m"#,
+            expected: Object::Map({
+                let map = HMap::default();
+                map.insert(
+                    Rc::new(Object::Str("a".into())),
+                    Rc::new(Object::Integer(1)),
+                );
+                Rc::new(map)
+            }),
+        },
+        VmTestCase {
+            input: r#"let m = map {"a": 1}; m["a"] = 111; m"#,
+            expected: Object::Map({
+                let map = HMap::default();
+                map.insert(
+                    Rc::new(Object::Str("a".into())),
+                    Rc::new(Object::Integer(111)),
+                );
+                Rc::new(map)
+            }),
+        },
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_set_index_assignment_expressions_negative() {
+    let tests: Vec<VmTestCaseErr> = vec![
+        VmTestCaseErr {
+            input: "let a = []; a[0] = 1;",
+            expected: "IndexError: array index out of range.",
+        },
+        VmTestCaseErr {
+            input: "let a = [0]; a[-1] = 1;",
+            expected: "IndexError: index cannot be negative.",
+        },
+        VmTestCaseErr {
+            input: "let a = [1, 2, 3]; a[4] = 4;",
+            expected: "IndexError: array index out of range.",
+        },
+    ];
+
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_set_index_assignment_expressions_free_variables() {
+    let tests: Vec<VmTestCase> = vec![
+        // newClosure returns a closure that closes over a free variable,
+        // the parameter 'a' of newClosure
+        VmTestCase {
+            input: r#"
+                let newClosure = fn(a) {
+                    fn() { a = 99; a; }
+                }
+                let closure = newClosure(88);
+                closure();
+                "#,
+            expected: Object::Integer(99),
+        },
+        VmTestCase {
+            input: r#"
+                let newAdder = fn(a, b) {
+                    fn(c) {
+                        a = a + 10; b = b + 20;
+                        a + b + c;
+                    }
+                }
+                let adder = newAdder(1, 2);
+                adder(7);
+                "#,
+            expected: Object::Integer(40),
+        },
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_logical_and_expressions() {
+    let tests = vec![
+        VmTestCase {
+            input: "true && true",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "true && false",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "false && true",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "false && false",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "true && 1",
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: r#"true && "hello""#,
+            expected: Object::Str("hello".to_string()),
+        },
+        VmTestCase {
+            input: r#"false && "hello""#,
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: r#""hello" && false"#,
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "1 < 2 && 3 < 4",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "1 < 2 && 3 > 4",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "1 > 2 && 3 < 4",
+            expected: Object::Bool(false),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_logical_or_expressions() {
+    let tests = vec![
+        VmTestCase {
+            input: "true || true",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "true || false",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "false || true",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "false || false",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "true || 1",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: r#"true || "hello""#,
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: r#"false || "hello""#,
+            expected: Object::Str("hello".to_string()),
+        },
+        VmTestCase {
+            input: r#""hello" || false"#,
+            expected: Object::Str("hello".to_string()),
+        },
+        VmTestCase {
+            input: "1 < 2 || 3 < 4",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "1 < 2 || 3 > 4",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "1 > 2 || 3 < 4",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "1 > 2 || 3 > 4",
+            expected: Object::Bool(false),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_logical_expressions_combined() {
+    let tests = vec![
+        VmTestCase {
+            input: "true && true || false",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "true && false || true",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "true || false && true",
+            expected: Object::Bool(true),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_block_statements() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+            {
+                100;
+                200
+            }
+            "#,
+            expected: Object::Integer(200),
+        },
+        VmTestCase {
+            input: "
+            let v = [];
+            let a = 1;
+            push(v, a);
+
+            fn f() {
+                let a = 2;
+                push(v, a);
+                {
+                    let a = 3;
+                    push(v, a);
+                }
+                push(v, a);
+            }
+
+            f();
+            push(v, a);
+            v
+            ",
+            expected: Object::Arr(Rc::new(Array {
+                elements: RefCell::new(vec![
+                    Rc::new(Object::Integer(1)),
+                    Rc::new(Object::Integer(2)),
+                    Rc::new(Object::Integer(3)),
+                    Rc::new(Object::Integer(2)),
+                    Rc::new(Object::Integer(1)),
+                ]),
+            })),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_loop_with_break() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+            let a = 1;
+            loop {
+                if a == 10 {
+                    break;
+                }
+                a = a + 1;
+            }
+            a;
+            "#,
+            expected: Object::Integer(10),
+        },
+        VmTestCase {
+            input: r#"
+            let a = 1;
+            loop {
+                if a == 10 {
+                    break;
+                }
+                a = a + 1;
+            }
+
+            loop {
+                if a <= 0 {
+                    break;
+                }
+                a = a - 1;
+            }
+            a;
+            "#,
+            expected: Object::Integer(0),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_while_loop() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+            let a = 1;
+            while a < 10 {
+                a = a + 1;
+            }
+            a;
+            "#,
+            expected: Object::Integer(10),
+        },
+        VmTestCase {
+            input: r#"
+            let a = 1;
+            while a < 10 {
+                a = a + 1;
+            }
+
+            while a > 0 {
+                a = a - 1;
+            }
+            a;
+            "#,
+            expected: Object::Integer(0),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_scoped_global_variables() {
+    let tests: Vec<VmTestCase> = vec![
+        VmTestCase {
+            input: r#"
+                let a = 1;
+                {
+                    let a = 10;
+                    a
+                }
+                a
+                "#,
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: r#"
+                let a = 1;
+                {
+                    let a = 10;
+                    a;
+                }
+                "#,
+            expected: Object::Integer(10),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_scoped_local_variables() {
+    let tests: Vec<VmTestCase> = vec![
+        VmTestCase {
+            input: r#"
+                let f = fn() {
+                    let a = 1;
+                    {
+                        let a = 10;
+                        a
+                    }
+                    a
+                }()
+                "#,
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: r#"
+                let f = fn() {
+                    let a = 1;
+                    {
+                        let a = 10;
+                        a
+                    }
+                }()
+                "#,
+            expected: Object::Integer(10),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_loop_with_break_and_continue() {
+    let tests = vec![VmTestCase {
+        input: r#"
+            let a = 0;
+            let s = 0;
+            loop {
+                if a % 2 == 0 {
+                    a = a + 1;
+                    continue;
+                }
+                if a > 10 {
+                    puts("break: a = ", a);
+                    break;
+                }
+                a = a + 1;
+                s = s + a;
+            }
+            s;
+            "#,
+        expected: Object::Integer(30),
+    }];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_while_with_continue() {
+    let tests = vec![VmTestCase {
+        input: r#"
+            let a = 0;
+            let s = 0;
+            while a < 10 {
+                if a % 2 == 0 {
+                    a = a + 1;
+                    continue;
+                }
+                a = a + 1;
+                s = s + a;
+            }
+            s;
+            "#,
+        expected: Object::Integer(30),
+    }];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_nested_loop_with_break_and_continue() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+                let a = 1;
+                let b = 0;
+                let s = 0;
+                a: loop {
+                    if a > 3 {
+                        break;
+                    }
+                    // skip 2
+                    if a == 2 {
+                        a = a + 1;
+                        continue a;
+                    }
+                    b = 1;
+                    b: loop {
+                        if b > 3 {
+                            break;
+                        }
+                        // skip a == b
+                        if a == b {
+                            b = b + 1;
+                            continue b;
+                        }
+                        // count
+                        s = s + 1;
+                        b = b + 1;
+                    }
+                    a = a + 1;
+                }
+                s
+            "#,
+            // Combinations: (1,2), (1,3), (3,1), (3,2) = 4
+            expected: Object::Integer(4),
+        },
+        VmTestCase {
+            input: r#"
+                let total = 0;
+                let i = 1;
+                let j = 0;
+
+                loop {
+                    if i > 3 {
+                        break;
+                    }
+            
+                    if i % 2 == 0 {
+                        i = i + 1;
+                        continue; // Skip even values of i
+                    }
+            
+                    j = 1;
+                    loop {
+                        if j > 3 {
+                            break;
+                        }
+            
+                        if j % 2 == 0 {
+                            j = j + 1;
+                            continue; // Skip even values of j
+                        }
+            
+                        total = total + i * j;
+                        j = j + 1;
+                    }
+            
+                    i = i + 1;
+                }
+                total
+            "#,
+            //  1*1 + 1*3 + 3*1 + 3*3 = 16
+            expected: Object::Integer(16),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_nested_while_with_continue() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+                let a = 1;
+                let b = 0;
+                let s = 0;
+                a: while a <= 3 {
+                    // skip 2
+                    if a == 2 {
+                        a = a + 1;
+                        continue a;
+                    }
+                    b = 1;
+                    b: while b <= 3 {
+                        // skip a == b
+                        if a == b {
+                            b = b + 1;
+                            continue b;
+                        }
+                        // count
+                        s = s + 1;
+                        b = b + 1;
+                    }
+                    a = a + 1;
+                }
+                s
+            "#,
+            // Combinations: (1,2), (1,3), (3,1), (3,2) = 4
+            expected: Object::Integer(4),
+        },
+        VmTestCase {
+            input: r#"
+                let total = 0;
+                let i = 1;
+                let j = 0;
+
+                while i <= 3 {
+                    if i % 2 == 0 {
+                        i = i + 1;
+                        continue; // Skip even values of i
+                    }
+
+                    j = 1;
+                    while j <= 3 {
+                        if j % 2 == 0 {
+                            j = j + 1;
+                            continue; // Skip even values of j
+                        }
+
+                        total = total + i * j;
+                        j = j + 1;
+                    }
+
+                    i = i + 1;
+                }
+                total
+            "#,
+            //  1*1 + 1*3 + 3*1 + 3*3 = 16
+            expected: Object::Integer(16),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_match_expressions_integers() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+                fn to_str(p) {
+                    match p {
+                        90..101 => "A",
+                        80..90  => "B",
+                        70..80  => "C",
+                        60..70  => "D",
+                        50..60  => "E",
+                        _ => "F",
+                    }
+                }
+                let s1 = to_str(100) + "," + to_str(80) + "," + to_str(70) + "," + to_str(60) + "," + to_str(50) + "," + to_str(40);
+                let s2 = to_str(99) + "," + to_str(89) + "," + to_str(79) + "," + to_str(69) + "," + to_str(59) + "," + to_str(49);
+                s1 + " " + s2
+            "#,
+            expected: Object::Str("A,B,C,D,E,F A,B,C,D,E,F".to_string()),
+        },
+        VmTestCase {
+            input: r#"
+                fn to_str(p) {
+                    match p {
+                        90..=100 => "A",
+                        80..=89  => "B",
+                        70..=79  => "C",
+                        60..=69  => "D",
+                        50..=59  => "E",
+                        _ => "F",
+                    }
+                }
+                let s1 = to_str(100) + "," + to_str(80) + "," + to_str(70) + "," + to_str(60) + "," + to_str(50) + "," + to_str(40);
+                let s2 = to_str(99) + "," + to_str(89) + "," + to_str(79) + "," + to_str(69) + "," + to_str(59) + "," + to_str(49);
+                s1 + " " + s2
+            "#,
+            expected: Object::Str("A,B,C,D,E,F A,B,C,D,E,F".to_string()),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_match_expressions_strings() {
+    let tests = vec![VmTestCase {
+        input: r#"
+                fn lang(p) {
+                    match tolower(p) {
+                        "a" | "e" | "i" | "o" | "u" => {  "vowel" }
+                        "b"..="z" => "consonant",
+                        _ => {  "others" }
+                      }
+                }
+                [
+                    lang("a"), lang("E"), lang("i"), lang("O"), lang("u"),
+                    lang("b"), lang("C"), lang("d"), lang("F"), lang("z"),
+                    lang("1"), lang("3"), lang("5"), lang("9"), lang("0"),
+                    lang(" "), lang(","), lang(";"), lang("'"), lang("+")
+                ]
+            "#,
+        expected: Object::Arr(Rc::new(Array {
+            elements: RefCell::new(vec![
+                Rc::new(Object::Str("vowel".to_string())),
+                Rc::new(Object::Str("vowel".to_string())),
+                Rc::new(Object::Str("vowel".to_string())),
+                Rc::new(Object::Str("vowel".to_string())),
+                Rc::new(Object::Str("vowel".to_string())),
+                Rc::new(Object::Str("consonant".to_string())),
+                Rc::new(Object::Str("consonant".to_string())),
+                Rc::new(Object::Str("consonant".to_string())),
+                Rc::new(Object::Str("consonant".to_string())),
+                Rc::new(Object::Str("consonant".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+            ]),
+        })),
+    }];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_match_expressions_char() {
+    let tests = vec![VmTestCase {
+        // Test match expressions with char patterns and a mix of range expressions
+        input: r#"
+                fn lang(p) {
+                    match tolower(p) {
+                        'a' | 'e' | 'i' | 'o' | 'u' => {  "vowel" }
+                        'b' | 'c'..='y' | 'z' => "consonant",
+                        _ => {  "others" }
+                      }
+                }
+                [
+                    lang('a'), lang('E'), lang('i'), lang('O'), lang('u'),
+                    lang('b'), lang('C'), lang('d'), lang('F'), lang('z'),
+                    lang('1'), lang('3'), lang('5'), lang('9'), lang('0'),
+                    lang(' '), lang(','), lang(';'), lang('-'), lang('+')
+                ]
+            "#,
+        expected: Object::Arr(Rc::new(Array {
+            elements: RefCell::new(vec![
+                Rc::new(Object::Str("vowel".to_string())),
+                Rc::new(Object::Str("vowel".to_string())),
+                Rc::new(Object::Str("vowel".to_string())),
+                Rc::new(Object::Str("vowel".to_string())),
+                Rc::new(Object::Str("vowel".to_string())),
+                Rc::new(Object::Str("consonant".to_string())),
+                Rc::new(Object::Str("consonant".to_string())),
+                Rc::new(Object::Str("consonant".to_string())),
+                Rc::new(Object::Str("consonant".to_string())),
+                Rc::new(Object::Str("consonant".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+            ]),
+        })),
+    }];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_match_expressions_byte() {
+    let tests = vec![VmTestCase {
+        // Test match expressions with byte patterns and a mix of range expressions
+        input: r#"
+                fn lang(p) {
+                    match toupper(p) {
+                        b'A' | b'E' | b'I' | b'O' | b'U' => {  "vowel" }
+                        b'B' | b'C'..=b'Y' | b'Z' => "consonant",
+                        _ => {  "others" }
+                      }
+                }
+                [
+                    lang(b'a'), lang(b'E'), lang(b'i'), lang(b'O'), lang(b'u'),
+                    lang(b'b'), lang(b'C'), lang(b'd'), lang(b'F'), lang(b'z'),
+                    lang(b'1'), lang(b'3'), lang(b'5'), lang(b'9'), lang(b'0'),
+                    lang(b' '), lang(b','), lang(b';'), lang(b'-'), lang(b'+')
+                ]
+            "#,
+        expected: Object::Arr(Rc::new(Array {
+            elements: RefCell::new(vec![
+                Rc::new(Object::Str("vowel".to_string())),
+                Rc::new(Object::Str("vowel".to_string())),
+                Rc::new(Object::Str("vowel".to_string())),
+                Rc::new(Object::Str("vowel".to_string())),
+                Rc::new(Object::Str("vowel".to_string())),
+                Rc::new(Object::Str("consonant".to_string())),
+                Rc::new(Object::Str("consonant".to_string())),
+                Rc::new(Object::Str("consonant".to_string())),
+                Rc::new(Object::Str("consonant".to_string())),
+                Rc::new(Object::Str("consonant".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+                Rc::new(Object::Str("others".to_string())),
+            ]),
+        })),
+    }];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_match_expressions_null() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+                let x = 1;
+                match 1 {
+                    1 => { let p = 1; }
+                    _ => { 999 }
+                };
+            "#,
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: r#"
+                let x = 0;
+                match x {
+                    1 => { 111 }
+                    _ => { let p = 1; }
+                };
+            "#,
+            expected: Object::Null,
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_match_expressions_boolean() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+                let b = true;
+                match b {
+                    true  => { 1 }
+                    false => { 0 }
+                }
+            "#,
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: r#"
+                let b = false;
+                match b {
+                    true  => { 1 }
+                    false => { 0 }
+                }
+            "#,
+            expected: Object::Integer(0),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_globals_and_locals() {
+    let tests = vec![
+        VmTestCase {
+            input: "globals()",
+            expected: Object::Map(Rc::new(HMap::default())),
+        },
+        VmTestCase {
+            input: r#"
+                let a = 1;
+                let b = "two";
+                globals()
+            "#,
+            expected: Object::Map({
+                let map = HMap::default();
+                map.insert(
+                    Rc::new(Object::Str("a".to_string())),
+                    Rc::new(Object::Integer(1)),
+                );
+                map.insert(
+                    Rc::new(Object::Str("b".to_string())),
+                    Rc::new(Object::Str("two".to_string())),
+                );
+                Rc::new(map)
+            }),
+        },
+        VmTestCase {
+            input: r#"
+                let a = 1;
+                let f = fn(x) { let y = x * 2; locals() };
+                f(5)
+            "#,
+            expected: Object::Map({
+                let map = HMap::default();
+                map.insert(
+                    Rc::new(Object::Str("x".to_string())),
+                    Rc::new(Object::Integer(5)),
+                );
+                map.insert(
+                    Rc::new(Object::Str("y".to_string())),
+                    Rc::new(Object::Integer(10)),
+                );
+                Rc::new(map)
+            }),
+        },
+        VmTestCase {
+            input: r#"
+                let a = 1;
+                let f = fn() { let y = 2; len(globals()) };
+                f()
+            "#,
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: r#"
+                let a = 1;
+                len(locals())
+            "#,
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: r#"
+                let globals = fn() { 42 };
+                globals()
+            "#,
+            expected: Object::Integer(42),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_mixed_numeric_comparisons() {
+    let tests = vec![
+        ("1 < 1.5", true),
+        ("1.5 < 1", false),
+        ("1 > 0.5", true),
+        ("2.0 >= 2", true),
+        ("2 <= 2.0", true),
+        ("2 == 2.0", true),
+        ("2 != 2.5", true),
+        ("b'a' > 96", true),
+        ("b'a' < 97", false),
+        ("b'a' >= 97", true),
+        ("97 == b'a'", true),
+        ("97.5 > b'a'", true),
+        ("b'a' <= 97.0", true),
+        ("0x100 > byte(255)", true),
+        ("-1 < byte(0)", true),
+    ];
+    let tests: Vec<VmTestCase> = tests
+        .into_iter()
+        .map(|(input, expected)| VmTestCase {
+            input,
+            expected: Object::Bool(expected),
+        })
+        .collect();
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_mixed_numeric_map_keys() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+                let m = map { 1: "one", 2.5: "two and a half" };
+                [m[1.0], m[byte(1)], m[2.5]]
+            "#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Str("one".to_string())),
+                Rc::new(Object::Str("one".to_string())),
+                Rc::new(Object::Str("two and a half".to_string())),
+            ]))),
+        },
+        VmTestCase {
+            input: r#"
+                let m = map {};
+                m[1] = "a";
+                m[1.0] = "b";
+                len(m)
+            "#,
+            expected: Object::Integer(1),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_array_map_keys() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"
+                let a = [1, 2];
+                let m = map { a: "one-two" };
+                push(a, 3);
+                [m[[1, 2]], contains(m, a), len(a)]
+            "#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Str("one-two".to_string())),
+                Rc::new(Object::Bool(false)),
+                Rc::new(Object::Integer(3)),
+            ]))),
+        },
+        VmTestCase {
+            input: r#"
+                let a = [1, [2]];
+                let m = map {};
+                m[a] = "x";
+                a[1][0] = 3;
+                [m[[1, [2]]], contains(m, a)]
+            "#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Str("x".to_string())),
+                Rc::new(Object::Bool(false)),
+            ]))),
+        },
+        VmTestCase {
+            input: r#"
+                let a = [1];
+                let m = map {};
+                insert(m, a, "y");
+                a[0] = 2;
+                m[[1]]
+            "#,
+            expected: Object::Str("y".to_string()),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: "let m = map {}; insert(m, map {}, 1)",
+            expected: "insert: not a valid key: map {}",
+        },
+        VmTestCaseErr {
+            input: "let m = map {}; insert(m, [1, map {}], 1)",
+            expected: "insert: not a valid key: [1, map {}]",
+        },
+        VmTestCaseErr {
+            input: "let m = map {}; m[[1, [set()]]] = 1",
+            expected: "KeyError: not a valid key: [1, [set {}]].",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_composite_map_keys() {
+    let tests = vec![
+        VmTestCase {
+            // Flows keyed by (src, dst, dport)
+            input: r#"
+                let flows = map {};
+                let src = "10.0.0.1";
+                let dst = "10.0.0.2";
+                flows[[src, dst, 53]] = 1;
+                flows[[src, dst, 53]] = flows[[src, dst, 53]] + 1;
+                flows[[dst, src, 53]] = 1;
+                [len(flows), flows[["10.0.0.1", "10.0.0.2", 53]]]
+            "#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Integer(2)),
+                Rc::new(Object::Integer(2)),
+            ]))),
+        },
+        VmTestCase {
+            // Elements are compared structurally, including numbers of
+            // different types that compare equal
+            input: "let m = map {[1, 'a', true]: 1}; m[[1.0, 'a', true]]",
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: "let m = map {[1, 2]: 1}; contains(m, [2, 1])",
+            expected: Object::Bool(false),
+        },
+        VmTestCase {
+            input: "let m = map {[[1, 2], 3]: 1}; contains(m, [1, [2, 3]])",
+            expected: Object::Bool(false),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_byte_order_builtins() {
+    let tests = vec![
+        VmTestCase {
+            input: "ntohs(htons(0x1234))",
+            expected: Object::Integer(0x1234),
+        },
+        VmTestCase {
+            input: "ntohl(htonl(0x12345678))",
+            expected: Object::Integer(0x12345678),
+        },
+        VmTestCase {
+            input: "htons(0x1234) == 0x1234",
+            expected: Object::Bool(cfg!(target_endian = "big")),
+        },
+        VmTestCase {
+            input: "[ntohs([byte(0x12), byte(0x34)]), ntohs([byte(0), byte(0x12), byte(0x34)], 1)]",
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Integer(0x1234)),
+                Rc::new(Object::Integer(0x1234)),
+            ]))),
+        },
+        VmTestCase {
+            input: "ntohl([byte(0xc0), byte(0xa8), byte(1), byte(2)])",
+            expected: Object::Integer(0xc0a80102),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: "ntohl([byte(1), byte(2)])",
+            expected: "ntohl: array too short to read 4 bytes at offset 0",
+        },
+        VmTestCaseErr {
+            input: "ntohs([1, 2])",
+            expected: "ntohs: array should contain only bytes",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_atexit() {
+    let input = r#"
+        let log = [];
+        atexit(fn() { push(log, 1); });
+        atexit(fn() { push(log, 2); });
+        exit(3);
+        push(log, 0);
+    "#;
+    let bytecode = test_compile(input);
+    let mut vm = VM::new(bytecode);
+    let err = vm.run().expect_err("expected a request to exit");
+    assert_eq!(err.exit, Some(3));
+    // The handlers run in the reverse order of registration
+    assert_eq!(vm.run_exit_handlers(), None);
+    assert_eq!(vm.globals[0].to_string(), "[2, 1]");
+    // The handlers are run only once
+    assert_eq!(vm.run_exit_handlers(), None);
+    assert_eq!(vm.globals[0].to_string(), "[2, 1]");
+
+    // An exit from a handler overrides the exit code
+    let bytecode = test_compile("atexit(fn() { exit(7); }); atexit(fn() { [][1]; });");
+    let mut vm = VM::new(bytecode);
+    vm.run().unwrap();
+    assert_eq!(vm.run_exit_handlers(), Some(7));
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: "atexit(fn(x) { x });",
+            expected: "atexit: function must not take any arguments",
+        },
+        VmTestCaseErr {
+            input: "atexit(1);",
+            expected: "atexit: unsupported argument",
+        },
+        VmTestCaseErr {
+            input: "exit(\"1\");",
+            expected: "exit: unsupported argument",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_register_filter() {
+    let input = r#"
+        let n = 0;
+        register_filter(fn() { n == 0 }, fn() { n = n + 1; });
+        register_filter(fn() { n > 0 }, null);
+        register_filter(fn() { 1 }, null);
+    "#;
+    let bytecode = test_compile(input);
+    let mut vm = VM::new(bytecode);
+    vm.run().unwrap();
+    assert!(vm.has_registered_filters());
+    let filters = vm.take_registered_filters();
+    assert_eq!(filters.len(), 3);
+    assert!(!vm.has_registered_filters());
+
+    // A filter with an action does not write the packet
+    assert!(!vm.run_registered_filter(&filters[0]).unwrap());
+    assert_eq!(vm.globals[0].to_string(), "1");
+    assert!(!vm.run_registered_filter(&filters[0]).unwrap());
+    assert_eq!(vm.globals[0].to_string(), "1");
+    // A filter without an action writes the packet if the pattern matches
+    assert!(vm.run_registered_filter(&filters[1]).unwrap());
+    let err = vm.run_registered_filter(&filters[2]).unwrap_err();
+    assert_eq!(err.msg, "filter pattern must evaluate to a boolean");
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: "register_filter(fn() { true });",
+            expected: "register_filter: takes two arguments. got=1",
+        },
+        VmTestCaseErr {
+            input: "register_filter(null, fn() { true });",
+            expected: "register_filter: unsupported argument",
+        },
+        VmTestCaseErr {
+            input: "register_filter(fn(x) { true }, null);",
+            expected: "register_filter: function must not take any arguments",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_checkpoint_globals() {
+    use crate::vm::checkpoint::Checkpoint;
+    use std::time::Duration;
+
+    let source = r#"
+        let counts = map {"10.0.0.1": [1, 2.5, "x", 'c', b'b']};
+        mapdefault(counts, 0);
+        let seen = set([true, null, cidr("10.0.0.0/8"), timestamp(1700000000, 5)]);
+        let count = 42;
+        let f = fn() { count };
+    "#;
+    let run = || {
+        let mut vm = VM::new(test_compile(source));
+        vm.run().unwrap();
+        vm
+    };
+    let path = std::env::temp_dir().join(format!("p2sh-test-{}.checkpoint", std::process::id()));
+    let mut checkpoint = Checkpoint::new(&path, Some(Duration::ZERO), source);
+    let vm = run();
+    checkpoint.tick(&vm.globals).unwrap();
+
+    // A restarted run resumes with the values saved
+    let mut resumed = run();
+    resumed.globals[2] = Rc::new(Object::Integer(0));
+    let counts = match resumed.globals[0].as_ref() {
+        Object::Map(map) => map.clone(),
+        _ => panic!("expected a map"),
+    };
+    counts.insert(
+        Rc::new(Object::Str("10.0.0.2".to_string())),
+        Rc::new(Object::Null),
+    );
+    assert_eq!(checkpoint.resume(&mut resumed.globals).unwrap(), 3);
+    for idx in 0..3 {
+        assert_eq!(resumed.globals[idx], vm.globals[idx]);
+    }
+    // The default value of the map is restored too
+    match resumed.globals[0].as_ref() {
+        Object::Map(map) => {
+            assert_eq!(*map.get_or_default(&resumed.globals[2]), Object::Integer(0))
+        }
+        _ => panic!("expected a map"),
+    }
+    // Functions are not saved
+    assert!(matches!(resumed.globals[3].as_ref(), Object::Clos(_)));
+
+    // The checkpoint of a different script is not restored
+    let other = Checkpoint::new(&path, None, "let count = 0;");
+    let err = other.resume(&mut resumed.globals).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "checkpoint was taken from a different script"
+    );
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(other.resume(&mut resumed.globals).unwrap(), 0);
+}
+
+#[test]
+fn test_reload_script() {
+    let old = r#"
+        let counts = map {};
+        let total = 0;
+        let label = fn(n) { "old " + str(n) };
+        let unused = 1;
+    "#;
+    let new = r#"
+        let scale = 10;
+        let label = fn(n) { "new " + str(n * scale) };
+        let total = 0;
+        let counts = map {};
+        register_filter(fn() { true }, null);
+    "#;
+    let bytecode = test_compile(old);
+    let old_names = bytecode.globals.clone();
+    let mut vm = VM::new(bytecode);
+    vm.run().unwrap();
+    vm.take_registered_filters();
+    #[allow(clippy::mutable_key_type)]
+    let mut counts = HashMap::new();
+    counts.insert(
+        Rc::new(Object::Str("dns".to_string())),
+        Rc::new(Object::Integer(3)),
+    );
+    vm.globals[0] = Rc::new(Object::Map(Rc::new(HMap::new(counts))));
+    vm.globals[1] = Rc::new(Object::Integer(7));
+    let old_label = vm.globals[2].clone();
+
+    // The data values are kept under their new indices, and the functions
+    // take the new definitions
+    let bytecode = test_compile(new);
+    let new_names = bytecode.globals.clone();
+    assert_eq!(vm.reload(bytecode, &old_names).unwrap(), 2);
+    assert_eq!(*vm.globals[2], Object::Integer(7));
+    assert_eq!(vm.globals[3].to_string(), r#"map {"dns": 3}"#);
+    assert_eq!(*vm.globals[0], Object::Integer(10));
+    assert!(vm.has_registered_filters());
+    assert_eq!(vm.take_registered_filters().len(), 1);
+    assert!(matches!(vm.globals[1].as_ref(), Object::Clos(_)));
+    assert_ne!(vm.globals[1], old_label);
+
+    // A script that fails leaves the one running in place
+    let err = vm.reload(test_compile("let total = 1 / 0;"), &new_names);
+    assert_eq!(err.unwrap_err().msg, "Division by zero.");
+    assert_eq!(*vm.globals[2], Object::Integer(7));
+    assert!(!vm.has_registered_filters());
+}
+
+#[test]
+fn test_metrics() {
+    use crate::vm::metrics::{serve, Metrics};
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::Arc;
+
+    let metrics = Arc::new(Metrics::default());
+    metrics.set_filters(&[3, 5]);
+    metrics.add_packet();
+    metrics.add_packet();
+    metrics.add_match(1);
+    metrics.add_match(2);
+    metrics.add_error();
+    metrics.set_dropped(4);
+    let text = metrics.render();
+    assert!(text.contains("# TYPE p2sh_packets_total counter\np2sh_packets_total 2\n"));
+    assert!(text.contains("p2sh_errors_total 1\n"));
+    assert!(text.contains("p2sh_packets_dropped_total 4\n"));
+    assert!(text.contains("p2sh_filter_matches_total{filter=\"1\",line=\"3\"} 0\n"));
+    assert!(text.contains("p2sh_filter_matches_total{filter=\"2\",line=\"5\"} 1\n"));
+
+    // Reloading the script starts the counts of the filters over
+    metrics.set_filters(&[4]);
+    assert!(metrics
+        .render()
+        .contains("p2sh_filter_matches_total{filter=\"1\",line=\"4\"} 0\n"));
+
+    let addr = serve(metrics, "127.0.0.1:0").unwrap();
+    let get = |path: &str| {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    let response = get("/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("p2sh_filter_matches_total{filter=\"1\",line=\"4\"} 0\n"));
+    assert!(get("/").starts_with("HTTP/1.1 404 Not Found\r\n"));
+}
+
+#[test]
+fn test_runtime_error_backtrace() {
+    let run = |input: &str| {
+        let mut vm = VM::new(test_compile(input));
+        vm.run().unwrap_err().to_string()
+    };
+    let input = "fn inner(x) {\n    x / 0\n}\nfn outer(x) {\n    inner(x + 1)\n}\nlet f = fn(a) { outer(a) };\nf(1)";
+    assert_eq!(
+        run(input),
+        "[line 2] Runtime error: Division by zero.\n  \
+         in fn inner at line 2, called from line 5\n  \
+         in fn outer at line 5, called from line 7\n  \
+         in fn f at line 7, called from line 8"
+    );
+    // Functions called by builtins are in the backtrace
+    let input = "fn key(x) {\n  x / 0\n}\ngroup_by([1], fn(x) { key(x) })";
+    assert_eq!(
+        run(input),
+        "[line 2] Runtime error: Division by zero.\n  \
+         in fn key at line 2, called from line 4\n  \
+         in fn <anonymous> at line 4, called from line 4"
+    );
+    // Errors outside functions and requests to exit have none
+    assert_eq!(run("1 / 0"), "[line 1] Runtime error: Division by zero.");
+    let mut vm = VM::new(test_compile("fn f() { exit(2) } f()"));
+    assert!(vm.run().unwrap_err().backtrace.is_empty());
+}
+
+#[test]
+fn test_incr_statements() {
+    let tests = vec![
+        VmTestCase {
+            input: "let x = 1; x++; x++; x--; x",
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: "let x = 9223372036854775807; x++; x",
+            expected: Object::Integer(i64::MIN),
+        },
+        VmTestCase {
+            input: "let x = 1.5; x--; x",
+            expected: Object::Float(0.5),
+        },
+        VmTestCase {
+            input: "let x = 0x01; x++; x",
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: "fn f() { let n = 0; for i in 0..10 { n++; } n } f()",
+            expected: Object::Integer(10),
+        },
+        VmTestCase {
+            input: "fn f() { let n = 0; let g = fn() { n++; n }; g(); g() } f()",
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: r#"let m = mapdefault(map {}, 0); m["a"]++; m["a"]++; m["b"]--; [m["a"], m["b"]]"#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Integer(2)),
+                Rc::new(Object::Integer(-1)),
+            ]))),
+        },
+        VmTestCase {
+            input: "let a = [1, 2]; let i = 0; a[i + 1]++; a",
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Integer(1)),
+                Rc::new(Object::Integer(3)),
+            ]))),
+        },
+        // The statements leave nothing on the stack
+        VmTestCase {
+            input: "let x = 1; let a = [0]; fn f() { x++ } if true { a[0]++ }; [f(), x, a[0]]",
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Null),
+                Rc::new(Object::Integer(2)),
+                Rc::new(Object::Integer(1)),
+            ]))),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#"let s = "a"; s++"#,
+            expected: "Invalid operation on strings.",
+        },
+        VmTestCaseErr {
+            input: "let x = null; x--",
+            expected: "Invalid binary operation.",
+        },
+        VmTestCaseErr {
+            input: "let a = [1]; a[1]++",
+            expected: "IndexError: array index out of range.",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_try_statements() {
+    let tests = vec![
+        VmTestCase {
+            input: "let r = 0; try { r = 1 / 0; } catch e { r = str(e); } r",
+            expected: Object::Str("runtime-error: Division by zero.".into()),
+        },
+        VmTestCase {
+            input: "let r = 0; try { r = 1; } catch e { r = 2; } r",
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: "let r = false; try { [1][5] + 1; } catch e { r = is_err(e); } r",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            // A function that ends with a try statement returns null
+            input: "fn f(d) { try { 1 / d; } catch e { 2; } } [f(0), f(1), 3]",
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Null),
+                Rc::new(Object::Null),
+                Rc::new(Object::Integer(3)),
+            ]))),
+        },
+        VmTestCase {
+            // The error unwinds the calls made within the try block
+            input: r#"
+            fn f(n) { if n == 0 { return 1 / 0; } f(n - 1) }
+            let r = 0;
+            try { f(5); } catch e { r = 1; }
+            r + 1
+            "#,
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            // Errors raised by functions called by builtins are caught too
+            input: "let r = 0; try { group_by([1], fn(x) { x / 0 }); } catch e { r = 1; } r",
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: r#"
+            let s = 0;
+            for i in 0..5 {
+                try {
+                    if i == 3 { break; }
+                    s = s + 10 / (i - 1);
+                } catch e {
+                    continue;
+                }
+            }
+            s
+            "#,
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            input: r#"
+            fn f(x) { try { return 10 / x; } catch e { return -1; } }
+            [f(2), f(0), f(5)]
+            "#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Integer(5)),
+                Rc::new(Object::Integer(-1)),
+                Rc::new(Object::Integer(2)),
+            ]))),
+        },
+        VmTestCase {
+            input: r#"
+            let r = "";
+            try {
+                try { 1 / 0; } catch e { r = r + "inner "; [][0]; }
+            } catch e {
+                r = r + "outer";
+            }
+            r
+            "#,
+            expected: Object::Str("inner outer".into()),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            // Errors raised in a catch block are not caught by it
+            input: "try { 1 / 0; } catch e { 2 / 0; }",
+            expected: "Division by zero.",
+        },
+        VmTestCaseErr {
+            // A try block that is done no longer catches errors
+            input: "fn f() { try { return 1; } catch e { } } f(); 1 / 0",
+            expected: "Division by zero.",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+
+    // Requests to exit are not errors that can be caught
+    let mut vm = VM::new(test_compile("try { exit(3); } catch e { }"));
+    assert_eq!(vm.run().unwrap_err().exit, Some(3));
+}
+
+#[test]
+fn test_debugger() {
+    use crate::vm::debugger::{Debugger, Paused, Reply};
+    use std::collections::VecDeque;
+
+    let source = "let total = 0;\nfn add(a, b) {\n    return a + b;\n}\nfor i in 0..3 {\n    total = add(total, i);\n}\ntotal";
+    let debug = |commands: &[&str]| {
+        let bytecode = test_compile(source);
+        let mut commands: VecDeque<String> = commands.iter().map(|c| c.to_string()).collect();
+        let debugger = Debugger::new(
+            bytecode.globals.clone(),
+            Some(source),
+            Box::new(move || commands.pop_front()),
+        );
+        let mut vm = VM::new(bytecode);
+        vm.set_debugger(debugger);
+        vm.run().map(|_| vm.last_popped().to_string())
+    };
+    // The run stops where the debugger quits, at the end of the commands
+    assert_eq!(debug(&[]).unwrap_err().line, 1);
+    assert_eq!(debug(&["b 3", "c", "q"]).unwrap_err().line, 3);
+    assert_eq!(debug(&["s", "s", "quit"]).unwrap_err().line, 5);
+    // A breakpoint is hit on each call, and not again on return
+    let err = debug(&["b 3", "b 6", "c", "c", "c", "c", "c", "q"]).unwrap_err();
+    assert_eq!((err.line, err.exit), (6, Some(0)));
+    assert_eq!(debug(&["b 3", "c", "d 3", "c"]).unwrap(), "3");
+
+    let mut debugger = Debugger::new(
+        vec!["total".to_string(), "add".to_string()],
+        Some(source),
+        Box::new(|| None),
+    );
+    assert!(debugger.should_pause(1, 0, 1));
+    assert!(!debugger.should_pause(1, 3, 1));
+    assert!(debugger.should_pause(3, 0, 2));
+    let globals = [Rc::new(Object::Integer(7)), Rc::new(Object::Null)];
+    let locals = [Rc::new(Object::Integer(1)), Rc::new(Object::Integer(2))];
+    let paused = Paused {
+        line: 3,
+        globals: &globals,
+        locals: &locals,
+        stack: &locals,
+        frames: vec![3, 6],
+    };
+    let mut run = |cmd: &str| debugger.command(cmd, &paused);
+    let show = |text: &str| Reply::Show(text.to_string());
+    assert_eq!(run("p total"), show("total = 7"));
+    assert_eq!(run("p nope"), show("No global named 'nope'"));
+    assert_eq!(run("locals"), show("[0] 1\n[1] 2"));
+    assert_eq!(
+        run("where"),
+        show("#0 [line 3] return a + b;\n#1 [line 6] total = add(total, i);")
+    );
+    assert_eq!(run("b 9"), show("Breakpoint at line 9"));
+    assert_eq!(run("b x"), show("Invalid line 'x'"));
+    assert_eq!(run("breakpoints"), show("Breakpoints at lines 9"));
+    assert_eq!(run("d 2"), show("No breakpoint at line 2"));
+    let listing = [
+        "     1 let total = 0;",
+        "     2 fn add(a, b) {",
+        ">    3     return a + b;",
+        "     4 }",
+        "     5 for i in 0..3 {",
+        "     6     total = add(total, i);",
+    ];
+    assert_eq!(run("list"), show(&listing.join("\n")));
+    assert_eq!(run("c"), Reply::Resume);
+    assert_eq!(run(""), Reply::Resume);
+    assert_eq!(run("jump"), show("Unknown command 'jump'; try 'help'"));
+    assert_eq!(run("q"), Reply::Quit);
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_compiled_script_cache() {
+    let source = r#"
+        import "std/stats";
+        let ports = map {"dns": 53, "https": 443};
+        fn total(m) { let t = 0; for _k, v in m { t = t + v; } t }
+        @ ($3).dstport == 53 { println("dns"); }
+        @ 10s { println("{}", TIME); }
+        @ end { write(stderr, "done"); }
+        [total(ports), 'x', b'y', 1.5, "s", stdout, "GET /" ~ "^GET"]
+    "#;
+    let dir = std::env::temp_dir().join(format!("p2sh-test-cache-{}", std::process::id()));
+    let path = cache::cache_path(&dir, source, &[false, false]);
+    assert_ne!(path, cache::cache_path(&dir, source, &[true, false]));
+    assert_ne!(path, cache::cache_path(&dir, "1", &[false, false]));
+    assert!(cache::load(&path).is_err());
+
+    let bytecode = test_compile(source);
+    cache::save(&path, &bytecode).unwrap();
+    let loaded = cache::load(&path).unwrap();
+    assert_eq!(loaded.instructions.code, bytecode.instructions.code);
+    assert_eq!(loaded.instructions.lines, bytecode.instructions.lines);
+    assert_eq!(loaded.constants.len(), bytecode.constants.len());
+    let patterns = |b: &Bytecode| {
+        let mut funcs: Vec<_> = b.filters.iter().map(|f| f.to_string()).collect();
+        funcs.extend(b.filter_end.iter().map(|f| f.to_string()));
+        for (secs, f) in &b.filter_intervals {
+            funcs.push(format!("{} {}", secs, f));
+        }
+        funcs
+    };
+    assert_eq!(patterns(&loaded), patterns(&bytecode));
+    assert_eq!(loaded.globals, bytecode.globals);
+    let names = |b: &Bytecode| -> Vec<Option<String>> {
+        let funcs = b.constants.iter().filter_map(|c| match c.as_ref() {
+            Object::Func(f) => Some(f.name.clone()),
+            _ => None,
+        });
+        funcs.collect()
+    };
+    assert!(names(&bytecode).contains(&Some("total".to_string())));
+    assert_eq!(names(&loaded), names(&bytecode));
+
+    // The loaded bytecode runs the same as the compiled one
+    let run = |bytecode| {
+        let mut vm = VM::new(bytecode);
+        vm.run().unwrap();
+        vm.last_popped().to_string()
+    };
+    let expected = run(bytecode);
+    assert_eq!(run(loaded), expected);
+
+    // A corrupt cache file is not loaded
+    let data = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &data[..data.len() - 1]).unwrap();
+    assert!(cache::load(&path).is_err());
+    let mut flipped = data.clone();
+    flipped[data.len() / 2] ^= 1;
+    std::fs::write(&path, &flipped).unwrap();
+    let Err(err) = cache::load(&path) else {
+        panic!("expected an error");
+    };
+    assert_eq!(err.to_string(), "checksum mismatch");
+
+    // Nor is the bytecode compiled by another version of p2sh
+    let version = env!("CARGO_PKG_VERSION");
+    let mut other = data.clone();
+    other[10..10 + version.len()].fill(b'9');
+    std::fs::write(&path, &other).unwrap();
+    let Err(err) = cache::load(&path) else {
+        panic!("expected an error");
+    };
+    let expected = format!(
+        "compiled by p2sh {}, not {}",
+        "9".repeat(version.len()),
+        version
+    );
+    assert_eq!(err.to_string(), expected);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_compiled_script_invalid_instructions() {
+    let dir = std::env::temp_dir().join(format!("p2sh-test-invalid-{}", std::process::id()));
+    let path = dir.join("invalid.p2c");
+    let tests = [
+        (vec![255], "opcode 255 undefined"),
+        (
+            vec![u8::from(Opcode::Constant), 0],
+            "truncated instruction at 0000",
+        ),
+        (
+            vec![u8::from(Opcode::Constant), 0, 1],
+            "invalid constant 1 at 0000",
+        ),
+        (
+            vec![u8::from(Opcode::GetGlobal), 0, 0],
+            "invalid global 0 at 0000",
+        ),
+        (
+            vec![u8::from(Opcode::GetBuiltinFn), 255],
+            "invalid builtin 255 at 0000",
+        ),
+        (
+            vec![u8::from(Opcode::Jump), 0, 4],
+            "invalid address 4 at 0000",
+        ),
+        (
+            vec![u8::from(Opcode::IterNext), 1, 0, 9],
+            "invalid address 9 at 0000",
+        ),
+    ];
+    for (code, expected) in tests {
+        let lines = vec![1; code.len()];
+        let bytecode = Bytecode {
+            instructions: Instructions::new(code, lines),
+            constants: vec![Rc::new(Object::Integer(1))],
+            filters: Vec::new(),
+            filter_end: None,
+            filter_intervals: Vec::new(),
+            globals: Vec::new(),
+        };
+        cache::save(&path, &bytecode).unwrap();
+        let Err(err) = cache::load(&path) else {
+            panic!("expected an error for {}", expected);
+        };
+        assert_eq!(err.to_string(), expected);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_trace_builtins() {
+    use crate::vm::trace::{trace_builtin, TraceResult};
+
+    let args = vec![
+        Rc::new(Object::Str(String::from("a b"))),
+        Rc::new(Object::Integer(1)),
+    ];
+    let result = Object::Arr(Rc::new(Array::new(vec![Rc::new(Object::Str(
+        String::from("a"),
+    ))])));
+    assert_eq!(
+        trace_builtin("split", &args, TraceResult::Value(&result), 3),
+        r#"[line 3] split("a b", 1) = ["a"]"#
+    );
+    assert_eq!(
+        trace_builtin("split", &args[..1], TraceResult::Error("failed"), 4),
+        r#"[line 4] split("a b") = error: failed"#
+    );
+    assert_eq!(
+        trace_builtin("exit", &[], TraceResult::Exit, 5),
+        "[line 5] exit() = ?"
+    );
+
+    // Long values are truncated
+    let long = Object::Str("x".repeat(100));
+    let line = trace_builtin("len", &[], TraceResult::Value(&long), 1);
+    assert_eq!(line, format!("[line 1] len() = \"{}...", "x".repeat(63)));
+
+    // Tracing does not change the result of the builtins
+    let bytecode = test_compile(r#"let f = flow_timeout(5); [len("abc"), f]"#);
+    let mut vm = VM::new(bytecode);
+    vm.set_trace_builtins(true);
+    vm.run().unwrap();
+    assert_eq!(vm.last_popped().to_string(), "[3, null]");
+}
+
+#[test]
+fn test_builtin_var_validation() {
+    use crate::builtins::variables::BuiltinValueType;
+
+    // Every builtin variable has the types of values it may hold
+    for idx in BuiltinVarType::range() {
+        let vt = BuiltinVarType::from(idx);
+        assert!(!vt.value_types().is_empty(), "no types for {:?}", vt);
+    }
+    assert!(BuiltinVarType::Max.value_types().is_empty());
+    assert_eq!(
+        BuiltinVarType::Time.value_types(),
+        &[BuiltinValueType::Float, BuiltinValueType::Null]
+    );
+    assert!(BuiltinVarType::NP.check(&Object::Integer(1)).is_ok());
+    assert!(BuiltinVarType::NP.check(&Object::Null).is_ok());
+    assert!(BuiltinVarType::Auto.check(&Object::Null).is_err());
+    assert_eq!(
+        BuiltinVarType::PL.check(&Object::Str("x".to_string())),
+        Err("builtin variable 'PL' cannot hold \"x\", expected integer or null".to_string())
+    );
+    assert_eq!(
+        BuiltinVarType::from(200).check(&Object::Null),
+        Err("invalid builtin variable index 16".to_string())
+    );
+    assert!(BuiltinVarType::PsDrop.check(&Object::Integer(3)).is_ok());
+    assert_eq!(
+        BuiltinVarType::PsIfDrop.check(&Object::Float(1.0)),
+        Err("builtin variable 'ps_ifdrop' cannot hold 1.0, expected integer or null".to_string())
+    );
+
+    // The drop counts are null unless the packets are captured live
+    let mut vm = VM::new(test_compile("[ps_drop, ps_ifdrop]"));
+    vm.init_builtin_vars(vec![]);
+    vm.run().unwrap();
+    assert_eq!(vm.last_popped().to_string(), "[null, null]");
+    let mut vm = VM::new(test_compile("ps_drop + ps_ifdrop"));
+    vm.init_builtin_vars(vec![]);
+    vm.update_builtin_var(BuiltinVarType::PsDrop, Rc::new(Object::Integer(5)));
+    vm.update_builtin_var(BuiltinVarType::PsIfDrop, Rc::new(Object::Integer(2)));
+    vm.run().unwrap();
+    test_expected_object(vm.last_popped(), &Object::Integer(7));
+
+    // The file name is null unless the packets are read from a file
+    let mut vm = VM::new(test_compile("[FILENAME, len(str(FILENAME))]"));
+    vm.init_builtin_vars(vec![]);
+    vm.run().unwrap();
+    assert_eq!(vm.last_popped().to_string(), "[null, 4]");
+    let mut vm = VM::new(test_compile("FILENAME"));
+    vm.init_builtin_vars(vec![]);
+    let name = Object::Str("captures/a.pcap".to_string());
+    assert!(BuiltinVarType::Filename.check(&name).is_ok());
+    vm.update_builtin_var(BuiltinVarType::Filename, Rc::new(name));
+    vm.run().unwrap();
+    assert_eq!(vm.last_popped().to_string(), "\"captures/a.pcap\"");
+
+    // A rejected value leaves the variable as it was
+    let vm = VM::new(test_compile("NP"));
+    vm.init_builtin_vars(vec![]);
+    vm.update_builtin_var(BuiltinVarType::NP, Rc::new(Object::Integer(7)));
+    let err = vm.try_update_builtin_var(BuiltinVarType::NP, Rc::new(Object::Float(1.5)));
+    assert!(err.is_err());
+    vm.update_builtin_var(BuiltinVarType::NP, Rc::new(Object::Bool(true)));
+    let mut vm = vm;
+    vm.run().unwrap();
+    test_expected_object(vm.last_popped(), &Object::Integer(7));
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_builtin_functions_flow() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"flow()"#,
+            expected: Object::Null,
+        },
+        VmTestCase {
+            input: r#"len(flows())"#,
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            input: r#"flow_timeout(30s, fn(f) { puts(f) })"#,
+            expected: Object::Null,
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#"flow(1)"#,
+            expected: "flow: takes no arguments. got=1",
+        },
+        VmTestCaseErr {
+            input: r#"flow_timeout(0)"#,
+            expected: "flow_timeout: timeout must be a positive number of seconds",
+        },
+        VmTestCaseErr {
+            input: r#"flow_timeout(5, fn(a, b) { a })"#,
+            expected: "flow_timeout: function must take one argument",
+        },
+        VmTestCaseErr {
+            input: r#"flow_timeout(5, 1)"#,
+            expected: "flow_timeout: unsupported argument",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_durations() {
+    let run = |input: &str| {
+        let mut vm = VM::new(test_compile(input));
+        vm.run()
+            .map(|_| vm.last_popped().to_string())
+            .map_err(|e| e.msg)
+    };
+    let tests = [
+        ("duration(1500)", "1500ms"),
+        ("duration(60000)", "60s"),
+        ("duration(0.25)", "250us"),
+        ("duration(0)", "0s"),
+        ("duration(duration(5))", "5ms"),
+        // Arithmetic with durations and numbers of seconds
+        ("duration(1000) + duration(500)", "1500ms"),
+        ("duration(1000) - duration(1500)", "-500ms"),
+        ("duration(1000) + 2", "3s"),
+        ("duration(1000) - 500ms", "500ms"),
+        ("3 - duration(1000)", "2s"),
+        ("duration(1000) * 3", "3s"),
+        ("0.5 * duration(1000)", "500ms"),
+        ("duration(1000) / 4", "250ms"),
+        ("duration(1000) / 0.5", "2s"),
+        ("duration(3000) / duration(1000)", "3.0"),
+        // Comparisons with durations and seconds
+        ("duration(1000) < duration(1001)", "true"),
+        ("duration(1000) == duration(1000)", "true"),
+        ("duration(1500) > 1", "true"),
+        ("duration(500) >= 500ms", "true"),
+        ("2s <= duration(1500)", "false"),
+        // Equality agrees with the ordering
+        ("duration(1500) == 1.5s", "true"),
+        ("duration(1500) != 1.5", "false"),
+        ("duration(1000) == 1", "true"),
+        ("1 == duration(1001)", "false"),
+        ("duration(1500) == 1", "false"),
+        // Conversions
+        ("int(duration(2500))", "2"),
+        ("float(duration(2500))", "2.5"),
+        ("str(duration(10))", r#""10ms""#),
+        ("to_json([duration(1500)])", r#""[1.5]""#),
+        ("let m = map {}; m[duration(1)] = 1; m[duration(1)]", "1"),
+        (
+            "let m = map {}; m[duration(1000)] = 1; [m[1], m[1.0], m[1s]]",
+            "[1, 1, 1]",
+        ),
+        ("let m = map {}; m[1.5] = 1; m[duration(1500)]", "1"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run(input).unwrap(), expected, "{}", input);
+    }
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#"duration("1s")"#,
+            expected: "duration: argument should be a number of milliseconds",
+        },
+        VmTestCaseErr {
+            input: "duration(9223372036854775807)",
+            expected: "duration: duration out of range",
+        },
+        VmTestCaseErr {
+            input: "duration(1) / 0",
+            expected: "Division by zero.",
+        },
+        VmTestCaseErr {
+            input: "duration(1) * duration(1)",
+            expected: "Invalid operation on durations.",
+        },
+        VmTestCaseErr {
+            input: "1 / duration(1)",
+            expected: "Invalid operation on durations.",
+        },
+        VmTestCaseErr {
+            input: "duration(1) - timestamp(1)",
+            expected: "Invalid operation on timestamps.",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[test]
+fn test_builtin_functions_ratelimit() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"[ratelimit("rule", 2, 1m), ratelimit("rule", 2, 1m), ratelimit("rule", 2, 1m)]"#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Bool(true)),
+                Rc::new(Object::Bool(true)),
+                Rc::new(Object::Bool(false)),
+            ]))),
+        },
+        VmTestCase {
+            input: r#"ratelimit(["10.0.0.1", 53], 1, 0.5)"#,
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: r#"[rate_limit("log", 1, duration(100)), rate_limit("log", 1, duration(100))]"#,
+            expected: Object::Arr(Rc::new(Array::new(vec![
+                Rc::new(Object::Bool(true)),
+                Rc::new(Object::Bool(false)),
+            ]))),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: r#"ratelimit("rule", 1)"#,
+            expected: "ratelimit: takes three arguments. got=2",
+        },
+        VmTestCaseErr {
+            input: r#"ratelimit(fn() {}, 1, 1)"#,
+            expected: "ratelimit: not a valid key: <closure>",
+        },
+        VmTestCaseErr {
+            input: r#"ratelimit("rule", 0, 1)"#,
+            expected: "ratelimit: maximum must be a positive integer",
+        },
+        VmTestCaseErr {
+            input: r#"ratelimit("rule", 1, -1s)"#,
+            expected: "ratelimit: period must be a positive number of seconds",
+        },
+        VmTestCaseErr {
+            input: r#"rate_limit("log", 1.5, 1)"#,
+            expected: "rate_limit: maximum must be a positive integer",
+        },
+        VmTestCaseErr {
+            input: r#"rate_limit("log", 1, duration(0))"#,
+            expected: "rate_limit: period must be a positive number of seconds",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_import_std_stats() {
+    let tests = vec![
+        VmTestCase {
+            input: r#"import "std/stats"; len(top_talkers(5))"#,
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            input: r#"import "std/stats"; retransmission_summary()["segments"]"#,
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            input: r#"import "std/stats"; stats_proto_name(17)"#,
+            expected: Object::Str("udp".into()),
+        },
+        VmTestCase {
+            input: r#"
+            import "std/stats";
+            let pairs = stats_sorted(["a", "b", "c"], map {"a": 1, "b": 3, "c": 2});
+            pairs[0][0] + pairs[1][0] + pairs[2][0]
+            "#,
+            expected: Object::Str("bca".into()),
+        },
+    ];
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_import_std_helpers() {
+    let run = |input: &str| {
+        let mut vm = VM::new(test_compile(input));
+        vm.run()
+            .map(|_| vm.last_popped().to_string())
+            .map_err(|e| e.msg)
+    };
+    let tests = [
+        (
+            r#"import "std/math"; [abs(-3), clamp(5, 0, 3), gcd(12, -18), lcm(4, 6), pow(2, 10)]"#,
+            "[3, 3, 6, 12, 1024]",
+        ),
+        (
+            r#"import "std/math"; [sqrt(16), sqrt(-1), stddev([2, 4, 4, 4, 5, 5, 7, 9])]"#,
+            "[4.0, null, 2.0]",
+        ),
+        (
+            r#"import "std/math"; let v = [15, 20, 35, 40, 50]; [median(v), median([1, 2]), percentile(v, 30), percentile([], 50)]"#,
+            "[35, 1.5, 20, null]",
+        ),
+        (
+            r#"import "std/net"; [ip_to_int("10.0.0.1"), ip_to_int("1.2.3.256"), int_to_ip(4294967295), netmask(8)]"#,
+            r#"[167772161, null, "255.255.255.255", 4278190080]"#,
+        ),
+        (
+            r#"import "std/net"; [in_cidr("10.1.2.3", "10.0.0.0/8"), in_cidr("11.1.2.3", "10.0.0.0/8"), in_cidr("10.0.0.1", "10.0.0.1")]"#,
+            "[true, false, true]",
+        ),
+        (
+            r#"import "std/net"; [is_private("172.20.1.1"), is_private("172.32.0.1"), is_loopback("127.0.0.1"), is_multicast("239.1.1.1")]"#,
+            "[true, false, true, true]",
+        ),
+        (
+            r#"import "std/strings"; [pad_left(42, 5, '0'), pad_right("ab", 4, "."), center("ab", 6), truncate("hello world", 8)]"#,
+            r#"["00042", "ab..", "  ab  ", "hello..."]"#,
+        ),
+        (
+            r#"import "std/strings"; [reverse("héllo"), count("a,b,,c", ","), is_digits("0123"), title("big world")]"#,
+            r#"["olléh", 3, true, "Big World"]"#,
+        ),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run(input).unwrap(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_for_statements() {
+    let tests = vec![
+        VmTestCase {
+            input: "let s = 0; for x in [1, 2, 3] { s = s + x; } s",
+            expected: Object::Integer(6),
+        },
+        VmTestCase {
+            input: "let s = 0; for i, x in [5, 6, 7] { s = s + i * x; } s",
+            expected: Object::Integer(20),
+        },
+        VmTestCase {
+            input: r#"let s = ""; for k, v in map {"b": 2, "a": 1} { s = s + k + str(v); } s"#,
+            expected: Object::Str("a1b2".into()),
+        },
+        VmTestCase {
+            input: r#"let s = ""; for k in map {"b": 2, "a": 1} { s = s + k; } s"#,
+            expected: Object::Str("ab".into()),
+        },
+        VmTestCase {
+            input: r#"let s = ""; for c in "abc" { s = str(c) + s; } s"#,
+            expected: Object::Str("cba".into()),
+        },
+        VmTestCase {
+            input: "let s = 0; for x in set([1, 2, 3]) { s = s * 10 + x; } s",
+            expected: Object::Integer(123),
+        },
+        // Changing the keys given to the loop does not change the map
+        VmTestCase {
+            input: "let m = map{}; m[[1]] = 1; for k, v in m { push(k, 9); } m[[1]]",
+            expected: Object::Integer(1),
+        },
+        VmTestCase {
+            input: "let m = map{}; m[[1]] = 1; for k in m { push(k, 9); } str(m)",
+            expected: Object::Str("map {[1]: 1}".into()),
+        },
+        VmTestCase {
+            input: "let s = set([[1]]); for x in s { push(x, 9); } contains(s, [1])",
+            expected: Object::Bool(true),
+        },
+        VmTestCase {
+            input: "let n = 4; let s = 0; for i in 1..n { s = s + i; } s",
+            expected: Object::Integer(6),
+        },
+        VmTestCase {
+            input: "let n = 4; let s = 0; for i in 1..=n { s = s + i; } s",
+            expected: Object::Integer(10),
+        },
+        VmTestCase {
+            input: "let s = 0; for i in 5..0 { s = s + 1; } s",
+            expected: Object::Integer(0),
+        },
+        VmTestCase {
+            input: r#"
+            fn f(a) {
+                let s = 0;
+                for x in a {
+                    if x == 2 { continue; }
+                    if x == 4 { break; }
+                    s = s + x;
+                }
+                s
+            }
+            f([1, 2, 3, 4, 5])
+            "#,
+            expected: Object::Integer(4),
+        },
+        VmTestCase {
+            input: r#"
+            let s = 0;
+            outer: for i in 0..3 {
+                for j in 0..3 {
+                    if j == 2 { continue outer; }
+                    if i == 2 { break outer; }
+                    s = s + 1;
+                }
+            }
+            s
+            "#,
+            expected: Object::Integer(4),
+        },
+        VmTestCase {
+            input: "fn f() { for x in [1, 2, 3] { if x == 2 { return x; } } 0 } f()",
+            expected: Object::Integer(2),
+        },
+        VmTestCase {
+            input: "fn f() { for x in [1] { } } f()",
+            expected: Object::Null,
+        },
+        VmTestCase {
+            // Adding to the array does not change the iteration
+            input: "let a = [1, 2]; for x in a { push(a, x); } len(a)",
+            expected: Object::Integer(4),
+        },
+    ];
+    run_vm_tests(&tests);
+
+    let tests = vec![
+        VmTestCaseErr {
+            input: "for x in 5 { }",
+            expected: "Cannot iterate over 5.",
+        },
+        VmTestCaseErr {
+            input: r#"for x in 0.."a" { }"#,
+            expected: "Range bounds must be integers.",
+        },
+    ];
+    run_vm_negative_tests(&tests);
+}
+
+// Property tests of the operators. Objects of every type that takes part
+// in the operators, including the edge values of the numbers, are drawn
+// from proptest strategies, which shrink a failing case down to a small
+// one and keep it to be tried again.
+
+#[cfg(test)]
+use proptest::prelude::*;
+
+#[cfg(test)]
+fn integer_strategy() -> impl Strategy<Value = i64> {
+    prop_oneof![
+        prop::sample::select(vec![
+            0,
+            1,
+            -1,
+            2,
+            i64::MAX,
+            i64::MIN,
+            1 << 53,
+            (1 << 53) + 1,
+            63
+        ]),
+        -100..100i64,
+        any::<i64>(),
+    ]
+}
+
+#[cfg(test)]
+fn float_strategy() -> impl Strategy<Value = f64> {
+    prop_oneof![
+        prop::sample::select(vec![
+            0.,
+            -0.,
+            0.5,
+            -1.,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::MAX,
+            9007199254740992.,
+            9223372036854775808.,
+            -9223372036854775808.,
+        ]),
+        (-100..100i64).prop_map(|n| n as f64),
+        -1e6..1e6f64,
+    ]
+}
+
+// Durations of up to about 48 days, whose numbers of seconds as floats are
+// exact to the nanosecond, so that their order agrees with that of the
+// numbers. Whole and half seconds make them equal to numbers at times.
+#[cfg(test)]
+fn duration_strategy() -> impl Strategy<Value = Object> {
+    use crate::object::timestamp::Duration;
+
+    const LIMIT: i64 = 1 << 52;
+    prop_oneof![(-100..100i64).prop_map(|n| n * 500_000_000), -LIMIT..LIMIT,]
+        .prop_map(|nanos| Object::Duration(Duration::from_nanos(nanos)))
+}
+
+// The textual form of an ipv4, ipv6 or mac address
+#[cfg(all(test, feature = "pcap"))]
+fn addr_text_strategy() -> impl Strategy<Value = String> {
+    use p2sh_dissect::addr::Addr;
+
+    prop_oneof![
+        (0..3u8, 0..3u8).prop_map(|(a, b)| format!("10.0.{}.{}", a, b)),
+        (0..3u16).prop_map(|a| format!("fe80::{:x}", a)),
+        (0..3u8).prop_map(|a| format!("00:00:5e:00:53:{:02x}", a)),
+    ]
+    .prop_map(|s| Addr::parse(&s).unwrap().to_string())
+}
+
+// The textual form of an ipv4 or ipv6 network
+#[cfg(all(test, feature = "pcap"))]
+fn cidr_text_strategy() -> impl Strategy<Value = String> {
+    use p2sh_dissect::addr::Cidr;
+
+    prop_oneof![
+        (0..3u8, prop::sample::select(vec![8u8, 16, 24, 32]))
+            .prop_map(|(a, len)| format!("10.{}.0.0/{}", a, len)),
+        (0..3u16, prop::sample::select(vec![16u8, 64, 128]))
+            .prop_map(|(a, len)| format!("fe80:{:x}::/{}", a, len)),
+    ]
+    .prop_map(|s| Cidr::parse(&s).unwrap().to_string())
+}
+
+// An address or a network, or the string they equal
+#[cfg(all(test, feature = "pcap"))]
+fn addr_strategy() -> impl Strategy<Value = Object> {
+    use p2sh_dissect::addr::{Addr, Cidr};
+
+    prop_oneof![
+        addr_text_strategy().prop_map(|s| Object::Addr(Addr::parse(&s).unwrap())),
+        cidr_text_strategy().prop_map(|s| Object::Cidr(Cidr::parse(&s).unwrap())),
+        prop_oneof![addr_text_strategy(), cidr_text_strategy()].prop_map(Object::Str),
+    ]
+}
+
+// Strings in place of the addresses and networks, which are not built
+// without pcap
+#[cfg(all(test, not(feature = "pcap")))]
+fn addr_strategy() -> impl Strategy<Value = Object> {
+    prop::sample::select(vec!["10.0.0.1", "fe80::1", "10.0.0.0/8"])
+        .prop_map(|s| Object::Str(s.to_string()))
+}
+
+// Two objects of different types, or of the same, that are meant to be
+// equal: the forms of a number, an address or a network and its string,
+// and bytes and the array of them
+#[cfg(all(test, feature = "pcap"))]
+fn alike_pair_strategy() -> impl Strategy<Value = (Rc<Object>, Rc<Object>)> {
+    use crate::object::timestamp::Duration;
+    use p2sh_dissect::addr::{Addr, Cidr};
+    use prop::sample::Index;
+
+    // Whole and half numbers; bytes are not compared with durations
+    let number = (-40..40i64, any::<bool>()).prop_map(|(halves, byte)| {
+        let mut forms = vec![Object::Float(halves as f64 / 2.)];
+        if halves % 2 == 0 {
+            forms.push(Object::Integer(halves / 2));
+        }
+        if byte && halves >= 0 && halves % 2 == 0 {
+            forms.push(Object::Byte((halves / 2) as u8));
+        } else {
+            forms.push(Object::Duration(Duration::from_nanos(halves * 500_000_000)));
+        }
+        forms
+    });
+    let addr = addr_text_strategy()
+        .prop_map(|s| vec![Object::Addr(Addr::parse(&s).unwrap()), Object::Str(s)]);
+    let cidr = cidr_text_strategy()
+        .prop_map(|s| vec![Object::Cidr(Cidr::parse(&s).unwrap()), Object::Str(s)]);
+    let bytes = prop::collection::vec(any::<u8>(), 0..3).prop_map(|b| {
+        let elements = b.iter().map(|b| Rc::new(Object::Byte(*b))).collect();
+        vec![Object::Bytes(b), Object::Arr(Rc::new(Array::new(elements)))]
+    });
+    (
+        prop_oneof![number, addr, cidr, bytes],
+        any::<Index>(),
+        any::<Index>(),
+    )
+        .prop_map(|(forms, i, j)| {
+            let forms: Vec<Rc<Object>> = forms.into_iter().map(Rc::new).collect();
+            (
+                forms[i.index(forms.len())].clone(),
+                forms[j.index(forms.len())].clone(),
+            )
+        })
+}
+
+// An object that may be used as a key of a map
+#[cfg(test)]
+fn key_strategy() -> impl Strategy<Value = Rc<Object>> {
+    use crate::object::timestamp::Timestamp;
+
+    let leaf = prop_oneof![
+        1 => Just(()).prop_map(|_| Object::Null),
+        1 => any::<bool>().prop_map(Object::Bool),
+        2 => integer_strategy().prop_map(Object::Integer),
+        2 => float_strategy().prop_map(Object::Float),
+        1 => any::<u8>().prop_map(Object::Byte),
+        1 => prop::sample::select(vec![0u8, 1, 2, 255]).prop_map(Object::Byte),
+        1 => prop::char::range('a', 'c').prop_map(Object::Char),
+        1 => prop::sample::select(vec!["", "a", "b", "ab"]).prop_map(|s| Object::Str(s.to_string())),
+        2 => duration_strategy(),
+        1 => integer_strategy().prop_map(|n| Object::Timestamp(Timestamp::from_nanos(n))),
+        2 => addr_strategy(),
+        1 => prop::collection::vec(0..3u8, 0..3).prop_map(Object::Bytes),
+    ]
+    .prop_map(Rc::new);
+    leaf.prop_recursive(2, 8, 3, |inner| {
+        prop::collection::vec(inner, 0..3)
+            .prop_map(|elements| Rc::new(Object::Arr(Rc::new(Array::new(elements)))))
+    })
+}
+
+// An object of any type the operators apply to
+#[cfg(test)]
+fn operand_strategy() -> impl Strategy<Value = Rc<Object>> {
+    prop_oneof![
+        1 => Just(Rc::new(Object::Float(f64::NAN))),
+        1 => prop::collection::vec((key_strategy(), key_strategy()), 0..3)
+            .prop_map(|pairs| Rc::new(Object::Map(Rc::new(HMap::new(pairs.into_iter().collect()))))),
+        1 => prop::collection::vec(key_strategy(), 0..3)
+            .prop_map(|elements| Rc::new(Object::Set(Rc::new(HSet::new(elements.into_iter().collect()))))),
+        9 => key_strategy(),
+    ]
+}
+
+// A VM holding the operands as its constants
+#[cfg(test)]
+fn operator_vm(operands: &[Rc<Object>]) -> VM {
+    VM::new(Bytecode {
+        instructions: Instructions::default(),
+        constants: operands.to_vec(),
+        filters: Vec::new(),
+        filter_end: None,
+        filter_intervals: Vec::new(),
+        globals: Vec::new(),
+    })
+}
+
+// Run an operator on the operands at the given indices of the constants
+// and return its result. The VM is left ready for the next one.
+#[cfg(test)]
+fn run_operator(vm: &mut VM, op: Opcode, operands: &[usize]) -> Result<Rc<Object>, RTError> {
+    let mut instructions = Instructions::default();
+    let mut code: Vec<Instructions> = operands
+        .iter()
+        .map(|i| make(Opcode::Constant, &[*i], 1))
+        .collect();
+    code.push(make(op, &[], 1));
+    for ins in code {
+        instructions.code.extend(ins.code);
+        instructions.lines.extend(ins.lines);
+    }
+    let func = Rc::new(CompiledFunction::new_filter(instructions, 0, 1, ""));
+    vm.push_filter_frame(&func)?;
+    match vm.run() {
+        Ok(()) => vm.pop_filter_value(),
+        Err(err) => {
+            vm.pop_frame();
+            while vm.pop(1).is_ok() {}
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+fn hash_of(obj: &Object) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    obj.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+fn is_nan(obj: &Object) -> bool {
+    matches!(obj, Object::Float(f) if f.is_nan())
+}
+
+#[cfg(test)]
+const BINARY_OPERATORS: [Opcode; 16] = [
+    Opcode::Add,
+    Opcode::Sub,
+    Opcode::Mul,
+    Opcode::Div,
+    Opcode::Mod,
+    Opcode::Equal,
+    Opcode::NotEqual,
+    Opcode::Greater,
+    Opcode::GreaterEq,
+    Opcode::And,
+    Opcode::Or,
+    Opcode::Xor,
+    Opcode::ShiftLeft,
+    Opcode::ShiftRight,
+    Opcode::In,
+    Opcode::GetIndex,
+];
+
+#[cfg(test)]
+fn number_strategy() -> impl Strategy<Value = Rc<Object>> {
+    prop_oneof![
+        1 => float_strategy().prop_map(Object::Float),
+        1 => any::<u8>().prop_map(Object::Byte),
+        2 => integer_strategy().prop_map(Object::Integer),
+    ]
+    .prop_map(Rc::new)
+}
+
+// Combine the results of an operator outside of the VM, as they are not
+// among its constants
+#[cfg(test)]
+fn combine(op: Opcode, l: &Object, r: &Object) -> Object {
+    match op {
+        Opcode::Add => l + r,
+        Opcode::Mul => l * r,
+        Opcode::And => l & r,
+        Opcode::Or => l | r,
+        _ => l ^ r,
+    }
+}
+
+// Sort the objects and check that the order of every pair agrees with the
+// sorted order, which it only does if the order is transitive
+#[cfg(test)]
+fn check_sorted_order(mut objs: Vec<Rc<Object>>) -> Result<(), TestCaseError> {
+    use std::cmp::Ordering;
+
+    objs.sort();
+    for (i, x) in objs.iter().enumerate() {
+        for y in &objs[i + 1..] {
+            prop_assert_ne!(x.cmp(y), Ordering::Greater, "{} > {} after sorting", x, y);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(500))]
+
+    #[test]
+    fn test_property_operators_do_not_panic(
+        operands in prop::collection::vec(operand_strategy(), 1..6)
+    ) {
+        const UNARY: [Opcode; 3] = [Opcode::Minus, Opcode::Bang, Opcode::Not];
+        let mut vm = operator_vm(&operands);
+        // Errors are fine; the operators must not panic on any pair
+        for a in 0..operands.len() {
+            for b in 0..operands.len() {
+                for op in BINARY_OPERATORS {
+                    let _ = run_operator(&mut vm, op, &[a, b]);
+                }
+            }
+            for op in UNARY {
+                let _ = run_operator(&mut vm, op, &[a]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_property_commutative(a in number_strategy(), b in number_strategy()) {
+        let mut vm = operator_vm(&[a.clone(), b.clone()]);
+        let same = |x: &Rc<Object>, y: &Rc<Object>| x == y || (is_nan(x) && is_nan(y));
+        for op in [Opcode::Add, Opcode::Mul, Opcode::Equal, Opcode::NotEqual] {
+            let ab = run_operator(&mut vm, op, &[0, 1]).unwrap();
+            let ba = run_operator(&mut vm, op, &[1, 0]).unwrap();
+            prop_assert!(same(&ab, &ba), "{} {:?} {} is not commutative", a, op, b);
+        }
+    }
+
+    // Integer arithmetic wraps around, so it is associative
+    #[test]
+    fn test_property_associative(
+        x in integer_strategy(),
+        y in integer_strategy(),
+        z in integer_strategy(),
+    ) {
+        let operands = [x, y, z].map(|n| Rc::new(Object::Integer(n)));
+        let mut vm = operator_vm(&operands);
+        for op in [Opcode::Add, Opcode::Mul, Opcode::And, Opcode::Or, Opcode::Xor] {
+            let xy = run_operator(&mut vm, op, &[0, 1]).unwrap();
+            let yz = run_operator(&mut vm, op, &[1, 2]).unwrap();
+            prop_assert_eq!(
+                combine(op, &xy, &operands[2]),
+                combine(op, &operands[0], &yz),
+                "{} {:?} {} {:?} {} is not associative",
+                x,
+                op,
+                y,
+                op,
+                z
+            );
+        }
+    }
+
+    #[cfg(feature = "pcap")]
+    #[test]
+    fn test_property_alike_keys((a, b) in alike_pair_strategy()) {
+        use std::cmp::Ordering;
+
+        prop_assert_eq!(&a, &b, "{} and {} are not equal", a, b);
+        prop_assert_eq!(hash_of(&a), hash_of(&b), "{} == {} hash differently", a, b);
+        if let Some(ord) = a.partial_cmp(&b) {
+            prop_assert_eq!(ord, Ordering::Equal, "{} == {} compare as {:?}", a, b, ord);
+        }
+    }
+
+    #[test]
+    fn test_property_key_order_and_hash(a in key_strategy(), b in key_strategy()) {
+        use std::cmp::Ordering;
+
+        // Keys are equal to themselves; NaN is not generated as a key
+        prop_assert_eq!(&a, &a, "{} is not equal to itself", a);
+        // Keys that are equal hash alike and compare as equal
+        if a == b {
+            prop_assert_eq!(hash_of(&a), hash_of(&b), "{} == {} hash differently", a, b);
+            if let Some(ord) = a.partial_cmp(&b) {
+                prop_assert_eq!(ord, Ordering::Equal, "{} == {} compare as {:?}", a, b, ord);
+            }
+        }
+        // The order is antisymmetric
+        if let Some(ord) = a.partial_cmp(&b) {
+            prop_assert_eq!(b.partial_cmp(&a), Some(ord.reverse()), "{} and {}", a, b);
+        }
+    }
+
+    // The order of the numbers is transitive, so that they sort alike
+    // whatever the order they start in. Bytes and durations are not
+    // ordered with each other, so they are sorted apart.
+    #[test]
+    fn test_property_number_order(
+        numbers in prop::collection::vec(number_strategy(), 0..12),
+        durations in prop::collection::vec(
+            prop_oneof![
+                duration_strategy().prop_map(Rc::new),
+                number_strategy().prop_filter("no bytes", |n| !matches!(**n, Object::Byte(_))),
+            ],
+            0..12,
+        ),
+    ) {
+        check_sorted_order(numbers)?;
+        check_sorted_order(durations)?;
+    }
+
+    // Timestamps are only ordered with each other
+    #[test]
+    fn test_property_timestamp_order(
+        a in integer_strategy(),
+        b in integer_strategy(),
+    ) {
+        use crate::object::timestamp::Timestamp;
+
+        let ta = Object::Timestamp(Timestamp::from_nanos(a));
+        let tb = Object::Timestamp(Timestamp::from_nanos(b));
+        prop_assert_eq!(ta.partial_cmp(&tb), a.partial_cmp(&b));
+        prop_assert_eq!(ta == tb, a == b);
+        prop_assert_eq!(ta.partial_cmp(&Object::Integer(a)), None);
+    }
+}
+
+// Differential tests. Programs are run through the compiler and the VM and
+// through the reference interpreter over the syntax tree, and the values
+// they end with, or the runtime errors they stop with, must be the same.
+
+#[cfg(test)]
+fn run_differential(input: &str) -> bool {
+    use crate::vm::reference::{EvalError, Evaluator};
+
+    let mut parser = Parser::new(Scanner::new(input));
+    let program = parser.parse_program();
+    if !parser.parse_errors().is_empty() {
+        panic!("parse errors in:\n{}", input);
+    }
+    let expected = match Evaluator::default().eval_program(&program) {
+        Ok(value) => Ok(value.to_string()),
+        Err(EvalError::Runtime(msg)) => Err(msg),
+        Err(EvalError::Unsupported(_)) => return false,
+    };
+    let mut compiler = Compiler::new();
+    if compiler.compile(program).is_err() {
+        return false;
+    }
+    let mut vm = VM::new(compiler.bytecode());
+    let arr = Rc::new(Object::Arr(Rc::new(Array::new(Vec::new()))));
+    vm.update_builtin_var(BuiltinVarType::Argv, arr);
+    let got = match vm.run() {
+        Ok(_) => Ok(vm.last_popped().to_string()),
+        Err(err) => Err(err.msg),
+    };
+    assert_eq!(
+        got, expected,
+        "the VM and the reference differ on:\n{}",
+        input
+    );
+    true
+}
+
+#[test]
+fn test_differential_corpus() {
+    let programs = [
+        "let a = 5; let b = a * 2 - 3; [a, b, a / b, a % b, -a]",
+        "let s = \"ab\"; [s + \"c\", s * 3, len(s), s < \"b\", 'x' + 'y']",
+        "let m = map {\"a\": 1, \"b\": 2}; m[\"c\"] = 3; [m[\"a\"], m[\"c\"], len(m), \"b\" in m]",
+        "let a = [1, 2, 3]; a[1] = a[0] + a[2]; [a, a + [4], 2 in a, rest(a)]",
+        "let f = fn(x) { fn(y) { x * y } }; let g = f(3); [g(4), f(5)(6)]",
+        "fn fib(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } } fib(15)",
+        "fn f(n) { let t = 0; for i in 0..=n { t = t + i; } t } [f(10), f(0)]",
+        "let x = 1; fn f() { x = x + 1; x } [f(), f(), x]",
+        "fn f(a) { let g = fn() { a }; a = 10; [a, g()] } f(1)",
+        "let c = 0; outer: for i in 0..5 { for j in 0..5 { if j > i { continue outer; } \
+         if i == 4 { break outer; } c = c + 1; } } c",
+        "let n = 0; let t = 0; while n < 10 { n = n + 1; if n % 2 == 0 { continue; } t = t + n; } t",
+        "let n = 0; loop { n = n + 3; if n > 20 { break; } } n",
+        "let r = []; for k, v in map {\"b\": 2, \"a\": 1} { r = push(r, [k, v]); } \
+         for i, c in \"xy\" { r = push(r, [i, c]); } r",
+        "let r = []; for x in set(3, 1, 2) { r = push(r, x); } r",
+        "fn f(x) { match x { 0 => { \"zero\" } 1 | 2 => { \"small\" } 3..10 => { \"medium\" } \
+         _ => { \"large\" } } } [f(0), f(2), f(5), f(10)]",
+        "match 7 { 1 => { 1 } }",
+        "fn f(d) { try { return 10 / d; } catch e { return str(e); } } [f(2), f(0)]",
+        "fn f(n) { if n == 0 { 1 / 0 } f(n - 1) } let r = 0; try { f(5); } catch e { r = str(e); } r",
+        "fn f() { try { 1; } catch e { 2; } } [f(), 1]",
+        "let x = 1; x++; x++; x--; let y = 2.5; y++; [x, y]",
+        "fn f() { let n = 0; for i in 0..5 { n++; } let g = fn() { n--; n }; [g(), n] } f()",
+        "let m = map {\"a\": 1}; m[\"a\"]++; let a = [1, 2]; a[1]--; [m[\"a\"], a]",
+        "let m = map {}; try { m[\"x\"]++; } catch e { m = str(e); } m",
+        "let s = \"a\"; try { s++; } catch e { s = str(e); } s",
+        "let x = 3; [x > 2 ? \"big\" : \"small\", x < 0 ? -x : x == 3 ? 30 : x, null ? 1 : 2]",
+        "fn f(n) { n < 2 ? n : f(n - 1) + f(n - 2) } let k = 0; false ? (k = 1) : 0; [f(12), k]",
+        "let r = 0; for i in 0..5 { try { if i == 3 { break; } r = r + 10 / (2 - i); } \
+         catch e { r = r + 100; } } r",
+        "let r = 0; try { try { 1 / 0; } catch e { r = 1; [][1]; } } catch e { r = [r, str(e)]; } r",
+        "[1 && 2, 0 || 3, null || false, !0, !null, 5 & 3, 5 | 3, 5 ^ 3, 1 << 4, ~5]",
+        "[sort([3, 1, 2]), join([\"a\", \"b\"], \"-\"), split(\"a,b\", \",\"), trim(\" a \")]",
+        "[int(\"42\"), float(3), str(4.5), toupper(\"ab\"), max([1, 9, 3]), min([4, 2])]",
+        "let a = [1]; let b = a; b[0] = 2; [a, b]",
+        "let x = 5; if x > 3 { let y = x * 2; y } else { 0 }",
+        "let f = fn(g, x) { g(g(x)) }; f(fn(x) { x * 3 }, 2)",
+        "[10 / 3, 10.0 / 4, 7 % -3, 1 + 2.5, 2 < 2.5, 0.1 + 0.2]",
+        "1 / 0",
+        "[1, 2][5]",
+        "map {\"a\": 1}[\"b\"]",
+        "len(1)",
+        "let f = fn(x) { x }; f(1, 2)",
+        "5()",
+        "-\"a\"",
+        "1 + \"a\"",
+        "for x in 5 { } 1",
+    ];
+    for input in programs {
+        assert!(run_differential(input), "unsupported:\n{}", input);
+    }
+}
+
+// Generates random programs out of integer globals, arithmetic, conditions,
+// bounded loops with 'break' and 'continue', try/catch, functions and
+// closures. Functions only call the ones defined before them so that the
+// programs end.
+#[cfg(test)]
+struct ProgramGen {
+    rng: rand::rngs::StdRng,
+    next_id: usize,
+    funcs: usize,
+}
+
+#[cfg(test)]
+impl ProgramGen {
+    const GLOBALS: usize = 4;
+
+    fn id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn program(&mut self) -> String {
+        self.funcs = 0;
+        let mut out = String::new();
+        for i in 0..Self::GLOBALS {
+            out += &format!("let g{} = {};\n", i, self.rng.gen_range(-5..20));
+        }
+        for _ in 0..self.rng.gen_range(0..4) {
+            out += &self.function();
+            self.funcs += 1;
+        }
+        out += &self.block(0, false, &[]);
+        out += "[g0, g1, g2, g3]\n";
+        out
+    }
+
+    fn function(&mut self) -> String {
+        let locals = vec!["a".to_string(), "b".to_string()];
+        let body = self.block(1, false, &locals);
+        let end = match self.rng.gen_range(0..5) {
+            0 => format!("return {};", self.expr(2, &locals)),
+            1 => self.expr(2, &locals),
+            2 => format!(
+                "if {} {{ {} }} else {{ {} }}",
+                self.cond(&locals),
+                self.expr(2, &locals),
+                self.expr(2, &locals)
+            ),
+            3 => {
+                let id = self.id();
+                format!(
+                    "let c{id} = fn(y) {{ {} }};\nreturn c{id}({});",
+                    self.expr(1, &["y".to_string(), "a".to_string()]),
+                    self.expr(1, &locals)
+                )
+            }
+            _ => {
+                // Ends with a statement, so the function returns null
+                let id = self.id();
+                format!(
+                    "try {{ {} }} catch e{id} {{ {}; }}",
+                    self.block(2, false, &locals),
+                    self.expr(1, &locals)
+                )
+            }
+        };
+        format!("fn f{}(a, b) {{\n{}{}\n}}\n", self.funcs, body, end)
+    }
+
+    fn block(&mut self, depth: usize, in_loop: bool, locals: &[String]) -> String {
+        let mut out = String::new();
+        for _ in 0..self.rng.gen_range(1..4) {
+            out += &self.stmt(depth, in_loop, locals);
+        }
+        out
+    }
+
+    fn stmt(&mut self, depth: usize, in_loop: bool, locals: &[String]) -> String {
+        let choice = if depth > 3 {
+            0
+        } else {
+            self.rng.gen_range(0..9)
+        };
+        match choice {
+            0 | 1 => {
+                let target = self.target(locals);
+                format!("{} = {};\n", target, self.expr(2, locals))
+            }
+            2 => format!(
+                "if {} {{\n{}}} else {{\n{}}}\n",
+                self.cond(locals),
+                self.block(depth + 1, in_loop, locals),
+                self.block(depth + 1, in_loop, locals)
+            ),
+            3 => {
+                let id = self.id();
+                let mut inner = locals.to_vec();
+                inner.push(format!("i{}", id));
+                let count = self.rng.gen_range(0..5);
+                let body = self.block(depth + 1, true, &inner);
+                format!("for i{id} in 0..{count} {{\n{body}}}\n")
+            }
+            4 => {
+                let id = self.id();
+                let mut inner = locals.to_vec();
+                inner.push(format!("w{}", id));
+                let count = self.rng.gen_range(0..5);
+                let body = self.block(depth + 1, true, &inner);
+                format!("let w{id} = 0;\nwhile w{id} < {count} {{\nw{id} = w{id} + 1;\n{body}}}\n")
+            }
+            5 if in_loop => {
+                let jump = if self.rng.gen() { "break" } else { "continue" };
+                format!("if {} {{ {}; }}\n", self.cond(locals), jump)
+            }
+            6 => {
+                let id = self.id();
+                let body = self.block(depth + 1, in_loop, locals);
+                let target = self.target(locals);
+                format!(
+                    "try {{\n{body}}} catch e{id} {{\n{target} = {target} + len(str(e{id}));\n}}\n"
+                )
+            }
+            7 if self.funcs > 0 => {
+                let f = self.rng.gen_range(0..self.funcs);
+                let target = self.target(locals);
+                let (a, b) = (self.expr(1, locals), self.expr(1, locals));
+                format!("{} = f{}({}, {});\n", target, f, a, b)
+            }
+            _ => {
+                let target = self.target(locals);
+                match self.rng.gen_range(0..3) {
+                    0 => format!("{}++;\n", target),
+                    1 => format!("{}--;\n", target),
+                    _ => format!("{} = {} + 1;\n", target, target),
+                }
+            }
+        }
+    }
+
+    // A global or a parameter of the function
+    fn target(&mut self, locals: &[String]) -> String {
+        let params: Vec<&String> = locals.iter().filter(|l| l.len() == 1).collect();
+        if !params.is_empty() && self.rng.gen_range(0..3) == 0 {
+            return params[self.rng.gen_range(0..params.len())].clone();
+        }
+        format!("g{}", self.rng.gen_range(0..Self::GLOBALS))
+    }
+
+    fn cond(&mut self, locals: &[String]) -> String {
+        const CMP: [&str; 6] = ["<", "<=", ">", ">=", "==", "!="];
+        let op = CMP[self.rng.gen_range(0..CMP.len())];
+        let cond = format!("({} {} {})", self.expr(1, locals), op, self.expr(1, locals));
+        match self.rng.gen_range(0..5) {
+            0 => format!("({} && {})", cond, self.cond(locals)),
+            1 => format!("({} || {})", cond, self.cond(locals)),
+            _ => cond,
+        }
+    }
+
+    fn expr(&mut self, depth: usize, locals: &[String]) -> String {
+        const OPS: [&str; 5] = ["+", "-", "*", "/", "%"];
+        let choice = if depth == 0 {
+            0
+        } else {
+            self.rng.gen_range(0..8)
+        };
+        match choice {
+            0 | 1 => match self.rng.gen_range(0..3) {
+                0 => self.rng.gen_range(-3..10).to_string(),
+                1 if !locals.is_empty() => locals[self.rng.gen_range(0..locals.len())].clone(),
+                _ => format!("g{}", self.rng.gen_range(0..Self::GLOBALS)),
+            },
+            2..=4 => {
+                let op = OPS[self.rng.gen_range(0..OPS.len())];
+                format!(
+                    "({} {} {})",
+                    self.expr(depth - 1, locals),
+                    op,
+                    self.expr(depth - 1, locals)
+                )
+            }
+            5 => format!("-{}", self.expr(depth - 1, locals)),
+            6 if self.rng.gen_bool(0.5) => format!(
+                "if {} {{ {} }} else {{ {} }}",
+                self.cond(locals),
+                self.expr(depth - 1, locals),
+                self.expr(depth - 1, locals)
+            ),
+            6 => format!(
+                "({} ? {} : {})",
+                self.cond(locals),
+                self.expr(depth - 1, locals),
+                self.expr(depth - 1, locals)
+            ),
+            _ => format!("len([{}, {}])", self.expr(0, locals), self.expr(0, locals)),
+        }
+    }
+}
+
+#[cfg(test)]
+const DIFFERENTIAL_CASES: usize = 500;
+
+#[test]
+fn test_differential_random() {
+    use rand::SeedableRng;
+
+    let mut gen = ProgramGen {
+        rng: rand::rngs::StdRng::seed_from_u64(0x7032_7368),
+        next_id: 0,
+        funcs: 0,
+    };
+    let mut supported = 0;
+    for _ in 0..DIFFERENTIAL_CASES {
+        let input = gen.program();
+        if run_differential(&input) {
+            supported += 1;
+        }
+    }
+    // The generator only uses what the reference interpreter covers
+    assert_eq!(supported, DIFFERENTIAL_CASES);
+}
@@ -0,0 +1,36 @@
+use std::rc::Rc;
+
+use crate::object::Object;
+
+// Longest argument or result, in characters, shown in a trace line
+const MAX_TRACE_VALUE: usize = 64;
+
+/// Outcome of a builtin call shown in the trace
+pub enum TraceResult<'a> {
+    Value(&'a Object),
+    Error(&'a str),
+    Exit,
+}
+
+// Display a value, truncating it to a length that keeps a line readable
+fn trace_value(obj: &Object) -> String {
+    let s = obj.to_string();
+    if s.chars().count() <= MAX_TRACE_VALUE {
+        s
+    } else {
+        let head: String = s.chars().take(MAX_TRACE_VALUE).collect();
+        format!("{}...", head)
+    }
+}
+
+/// Format a call to a builtin function in the manner of strace, e.g.
+/// `[line 3] split("a b") = ["a", "b"]`
+pub fn trace_builtin(name: &str, args: &[Rc<Object>], result: TraceResult, line: usize) -> String {
+    let args: Vec<String> = args.iter().map(|a| trace_value(a)).collect();
+    let result = match result {
+        TraceResult::Value(obj) => trace_value(obj),
+        TraceResult::Error(msg) => format!("error: {}", msg),
+        TraceResult::Exit => String::from("?"),
+    };
+    format!("[line {}] {}({}) = {}", line, name, args.join(", "), result)
+}
@@ -0,0 +1,132 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ipv4addr::Ipv4Address;
+use crate::ipv6addr::Ipv6Address;
+use crate::macaddress::MacAddress;
+
+/// Link or network layer address of a packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Addr {
+    Mac(MacAddress),
+    Ipv4(Ipv4Address),
+    Ipv6(Ipv6Address),
+}
+
+impl Addr {
+    /// Parse an ipv4, ipv6 or mac address
+    pub fn parse(s: &str) -> Option<Self> {
+        if let Ok(ip) = Ipv4Address::from_str(s) {
+            return Some(Self::Ipv4(ip));
+        }
+        if let Ok(mac) = MacAddress::from_str(s) {
+            return Some(Self::Mac(mac));
+        }
+        Ipv6Address::from_str(s).ok().map(Self::Ipv6)
+    }
+}
+
+impl fmt::Display for Addr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Mac(mac) => write!(f, "{}", mac),
+            Self::Ipv4(ip) => write!(f, "{}", ip),
+            Self::Ipv6(ip) => write!(f, "{}", ip),
+        }
+    }
+}
+
+impl From<&Addr> for Vec<u8> {
+    fn from(addr: &Addr) -> Self {
+        match addr {
+            Addr::Mac(mac) => mac.into(),
+            Addr::Ipv4(ip) => ip.into(),
+            Addr::Ipv6(ip) => ip.into(),
+        }
+    }
+}
+
+/// An ipv4 or ipv6 network given by an address and the length of its
+/// prefix, e.g. 10.0.0.0/8. The bits of the address beyond the prefix are
+/// cleared.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    network: Vec<u8>, // 4 bytes for ipv4 or 16 for ipv6
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Parse a network such as "192.168.0.0/16". An address without a
+    /// prefix length is a network of that address alone.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let network: Vec<u8> = match Addr::parse(addr) {
+            Some(addr @ (Addr::Ipv4(_) | Addr::Ipv6(_))) => (&addr).into(),
+            _ => return Err(format!("invalid network address '{}'", addr)),
+        };
+        let bits = network.len() * 8;
+        let prefix = match prefix {
+            None => bits,
+            Some(p) => match p.parse::<usize>() {
+                Ok(n) if n <= bits => n,
+                _ => return Err(format!("invalid prefix length '{}'", p)),
+            },
+        };
+        let mut cidr = Self {
+            network,
+            prefix: prefix as u8,
+        };
+        cidr.network = cidr.masked(&cidr.network);
+        Ok(cidr)
+    }
+
+    /// Check if an ip address is in the network
+    pub fn contains(&self, addr: &Addr) -> bool {
+        let bytes: Vec<u8> = addr.into();
+        match addr {
+            Addr::Ipv4(_) | Addr::Ipv6(_) if bytes.len() == self.network.len() => {
+                self.masked(&bytes) == self.network
+            }
+            _ => false,
+        }
+    }
+
+    /// Check if another network lies within the network
+    pub fn contains_cidr(&self, other: &Cidr) -> bool {
+        other.network.len() == self.network.len()
+            && other.prefix >= self.prefix
+            && self.masked(&other.network) == self.network
+    }
+
+    // The bytes of an address with the bits beyond the prefix cleared
+    fn masked(&self, bytes: &[u8]) -> Vec<u8> {
+        let prefix = self.prefix as usize;
+        bytes
+            .iter()
+            .enumerate()
+            .map(|(i, b)| match prefix.saturating_sub(i * 8) {
+                0 => 0,
+                n if n >= 8 => *b,
+                n => b & (0xffu8 << (8 - n)),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.network.as_slice() {
+            [a, b, c, d] => write!(f, "{}/{}", Ipv4Address(*a, *b, *c, *d), self.prefix),
+            v6 => write!(f, "{}/{}", Ipv6Address::from_bytes(v6), self.prefix),
+        }
+    }
+}
+
+impl From<&Cidr> for Vec<u8> {
+    fn from(cidr: &Cidr) -> Self {
+        cidr.network.clone()
+    }
+}
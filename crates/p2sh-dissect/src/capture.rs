@@ -0,0 +1,404 @@
+use std::fmt;
+use std::io;
+
+use crate::record::PcapPacketHeader;
+
+#[cfg(target_os = "linux")]
+pub use self::linux::{take_hangup, watch_hangup, Capture};
+
+/// Clock that stamps the packets of a live capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum TstampType {
+    /// The clock of the host when the kernel receives the packet
+    #[default]
+    Host,
+    /// The clock of the network adapter, which is not synchronized with
+    /// the clock of the host
+    Adapter,
+}
+
+/// Packets dropped since a capture was opened, named after the fields of
+/// the statistics of libpcap
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureStats {
+    /// Packets dropped by the kernel, such as when the socket buffer was full
+    pub drop: u64,
+    /// Packets dropped by the interface, such as when its ring was full
+    pub ifdrop: u64,
+}
+
+impl fmt::Display for Capture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<capture: {}>", self.interface)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::cell::{Cell, RefCell};
+    use std::ffi::CString;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+    use super::{io, CaptureStats, PcapPacketHeader, TstampType};
+
+    // Hardware types of the interfaces whose frames have an ethernet header
+    const ARPHRD_ETHER: u16 = 1;
+    const ARPHRD_LOOPBACK: u16 = 772;
+    // Time to wait for a packet before checking for an interrupt
+    const CAPTURE_POLL_USEC: i64 = 100_000;
+    // Room for the control messages received with a packet
+    const CONTROL_LEN: usize = 256;
+
+    // Set by the SIGINT handler to stop the capture
+    static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn on_interrupt(_: libc::c_int) {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+    }
+
+    // Set by the SIGHUP handler to reload the script
+    static HANGUP: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn on_hangup(_: libc::c_int) {
+        HANGUP.store(true, Ordering::SeqCst);
+    }
+
+    /// Handle SIGHUP as a request to reload the script instead of exiting
+    pub fn watch_hangup() {
+        HANGUP.store(false, Ordering::SeqCst);
+        unsafe {
+            libc::signal(libc::SIGHUP, on_hangup as *const () as libc::sighandler_t);
+        }
+    }
+
+    /// Return true if SIGHUP was received since the last call
+    pub fn take_hangup() -> bool {
+        HANGUP.swap(false, Ordering::SeqCst)
+    }
+
+    /// Live capture of the packets on a network interface using a packet socket.
+    /// The capture stops on Ctrl-C, or when the duration set has elapsed, which
+    /// is reported as the end of the stream.
+    #[derive(Debug)]
+    pub struct Capture {
+        pub(super) interface: String,
+        fd: OwnedFd,
+        loopback: bool,
+        buffer: RefCell<Vec<u8>>,
+        deadline: Cell<Option<Instant>>, // time to stop the capture at
+        dropped: Cell<u64>,              // packets dropped by the kernel
+        ifdropped: Option<u64>,          // drops of the interface when opened
+        tstamp: Cell<TstampType>,        // clock that stamps the packets
+    }
+
+    // Convert the return value of a libc call to an io::Result
+    fn check(ret: libc::c_int) -> io::Result<libc::c_int> {
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret)
+        }
+    }
+
+    // Number of packets dropped by the interface since it came up
+    fn interface_drops(interface: &str) -> Option<u64> {
+        let path = format!("/sys/class/net/{}/statistics/rx_dropped", interface);
+        std::fs::read_to_string(path).ok()?.trim().parse().ok()
+    }
+
+    impl Capture {
+        /// Open a capture on the interface with the given name. Packets larger
+        /// than 'snaplen' bytes are truncated. The interface must have ethernet
+        /// framing as the packets are written with that link type.
+        pub fn open(interface: &str, snaplen: u32) -> io::Result<Self> {
+            let name = CString::new(interface).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, "Invalid interface name")
+            })?;
+            let ifindex = unsafe { libc::if_nametoindex(name.as_ptr()) };
+            if ifindex == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No such interface '{}'", interface),
+                ));
+            }
+            let protocol = (libc::ETH_P_ALL as u16).to_be();
+            let fd =
+                check(unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, protocol as i32) })?;
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+            let mut addr: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+            addr.sll_family = libc::AF_PACKET as u16;
+            addr.sll_protocol = protocol;
+            addr.sll_ifindex = ifindex as i32;
+            let mut addr_len = std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+            let addr_ptr = &mut addr as *mut libc::sockaddr_ll as *mut libc::sockaddr;
+            check(unsafe { libc::bind(fd.as_raw_fd(), addr_ptr, addr_len) })?;
+            // The hardware type of the interface is known once bound
+            check(unsafe { libc::getsockname(fd.as_raw_fd(), addr_ptr, &mut addr_len) })?;
+            if addr.sll_hatype != ARPHRD_ETHER && addr.sll_hatype != ARPHRD_LOOPBACK {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!(
+                        "Unsupported hardware type {} of interface '{}'",
+                        addr.sll_hatype, interface
+                    ),
+                ));
+            }
+            // Time out on reads to check for an interrupt periodically
+            let timeout = libc::timeval {
+                tv_sec: 0,
+                tv_usec: CAPTURE_POLL_USEC,
+            };
+            check(unsafe {
+                libc::setsockopt(
+                    fd.as_raw_fd(),
+                    libc::SOL_SOCKET,
+                    libc::SO_RCVTIMEO,
+                    &timeout as *const libc::timeval as *const libc::c_void,
+                    std::mem::size_of::<libc::timeval>() as libc::socklen_t,
+                )
+            })?;
+            INTERRUPTED.store(false, Ordering::SeqCst);
+            unsafe {
+                libc::signal(
+                    libc::SIGINT,
+                    on_interrupt as *const () as libc::sighandler_t,
+                );
+            }
+            Ok(Self {
+                interface: interface.to_string(),
+                fd,
+                loopback: addr.sll_hatype == ARPHRD_LOOPBACK,
+                buffer: RefCell::new(vec![0u8; snaplen as usize]),
+                deadline: Cell::new(None),
+                dropped: Cell::new(0),
+                ifdropped: interface_drops(interface),
+                tstamp: Cell::new(TstampType::Host),
+            })
+            .and_then(|capture| {
+                capture.set_tstamp_type(TstampType::Host)?;
+                Ok(capture)
+            })
+        }
+
+        /// Stamp the packets using the given clock. The clock of the
+        /// adapter is only available on interfaces that support hardware
+        /// timestamps, and is enabled for all the packets they receive.
+        pub fn set_tstamp_type(&self, tstamp: TstampType) -> io::Result<()> {
+            let flags = match tstamp {
+                TstampType::Host => {
+                    libc::SOF_TIMESTAMPING_RX_SOFTWARE | libc::SOF_TIMESTAMPING_SOFTWARE
+                }
+                TstampType::Adapter => {
+                    self.enable_hw_timestamps()?;
+                    libc::SOF_TIMESTAMPING_RX_HARDWARE | libc::SOF_TIMESTAMPING_RAW_HARDWARE
+                }
+            };
+            check(unsafe {
+                libc::setsockopt(
+                    self.fd.as_raw_fd(),
+                    libc::SOL_SOCKET,
+                    libc::SO_TIMESTAMPING,
+                    &flags as *const libc::c_uint as *const libc::c_void,
+                    std::mem::size_of::<libc::c_uint>() as libc::socklen_t,
+                )
+            })?;
+            self.tstamp.set(tstamp);
+            Ok(())
+        }
+
+        // Ask the driver to stamp the packets received by the interface
+        fn enable_hw_timestamps(&self) -> io::Result<()> {
+            let mut config = libc::hwtstamp_config {
+                flags: 0,
+                tx_type: libc::HWTSTAMP_TX_OFF as libc::c_int,
+                rx_filter: libc::HWTSTAMP_FILTER_ALL as libc::c_int,
+            };
+            let mut ifr: libc::ifreq = unsafe { std::mem::zeroed() };
+            let name = self.interface.as_bytes();
+            for (dst, src) in ifr
+                .ifr_name
+                .iter_mut()
+                .zip(name.iter().take(libc::IFNAMSIZ - 1))
+            {
+                *dst = *src as libc::c_char;
+            }
+            ifr.ifr_ifru.ifru_data = &mut config as *mut libc::hwtstamp_config as *mut libc::c_char;
+            let ret =
+                unsafe { libc::ioctl(self.fd.as_raw_fd(), libc::SIOCSHWTSTAMP as _, &mut ifr) };
+            check(ret)
+                .map(|_| ())
+                .map_err(|err| match err.raw_os_error() {
+                    Some(libc::EOPNOTSUPP) | Some(libc::EINVAL) | Some(libc::ERANGE) => {
+                        io::Error::new(
+                            io::ErrorKind::Unsupported,
+                            format!(
+                                "Interface '{}' does not support adapter timestamps",
+                                self.interface
+                            ),
+                        )
+                    }
+                    _ => err,
+                })
+        }
+
+        /// Stop the capture once 'duration' has elapsed from now
+        pub fn set_duration(&self, duration: Duration) {
+            self.deadline.set(Instant::now().checked_add(duration));
+        }
+
+        /// Packets dropped by the kernel and by the interface since the
+        /// capture was opened. The drops of the interface are those of all
+        /// its traffic, and are 0 if the interface does not count them.
+        pub fn stats(&self) -> io::Result<CaptureStats> {
+            let mut stats: libc::tpacket_stats = unsafe { std::mem::zeroed() };
+            let mut len = std::mem::size_of::<libc::tpacket_stats>() as libc::socklen_t;
+            check(unsafe {
+                libc::getsockopt(
+                    self.fd.as_raw_fd(),
+                    libc::SOL_PACKET,
+                    libc::PACKET_STATISTICS,
+                    &mut stats as *mut libc::tpacket_stats as *mut libc::c_void,
+                    &mut len,
+                )
+            })?;
+            // The kernel resets the counters each time they are read
+            self.dropped.set(self.dropped.get() + stats.tp_drops as u64);
+            let ifdrop = match (self.ifdropped, interface_drops(&self.interface)) {
+                (Some(start), Some(now)) => now.saturating_sub(start),
+                _ => 0,
+            };
+            Ok(CaptureStats {
+                drop: self.dropped.get(),
+                ifdrop,
+            })
+        }
+
+        // The time stamped on a packet by the clock in use, found in the
+        // control messages received with it
+        fn timestamp(&self, msg: &libc::msghdr) -> Option<Duration> {
+            let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(msg) };
+            while !cmsg.is_null() {
+                let hdr = unsafe { &*cmsg };
+                if hdr.cmsg_level == libc::SOL_SOCKET && hdr.cmsg_type == libc::SCM_TIMESTAMPING {
+                    // The software, the deprecated and the raw hardware times
+                    let data = unsafe { libc::CMSG_DATA(cmsg) } as *const [libc::timespec; 3];
+                    let times = unsafe { std::ptr::read_unaligned(data) };
+                    let ts = match self.tstamp.get() {
+                        TstampType::Host => times[0],
+                        TstampType::Adapter => times[2],
+                    };
+                    if ts.tv_sec == 0 && ts.tv_nsec == 0 {
+                        return None;
+                    }
+                    return Some(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32));
+                }
+                cmsg = unsafe { libc::CMSG_NXTHDR(msg, cmsg) };
+            }
+            None
+        }
+
+        /// Wait for the next packet on the interface. The timestamp is in
+        /// nanoseconds. An error of kind UnexpectedEof is returned on Ctrl-C
+        /// or once the duration of the capture has elapsed.
+        pub fn next_packet(&self) -> io::Result<(PcapPacketHeader, Vec<u8>)> {
+            let mut buffer = self.buffer.borrow_mut();
+            loop {
+                if INTERRUPTED.load(Ordering::SeqCst) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Capture interrupted",
+                    ));
+                }
+                if self.deadline.get().is_some_and(|t| Instant::now() >= t) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Capture duration elapsed",
+                    ));
+                }
+                // The length of the packet on wire is returned if truncated
+                let mut addr: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+                let mut iov = libc::iovec {
+                    iov_base: buffer.as_mut_ptr() as *mut libc::c_void,
+                    iov_len: buffer.len(),
+                };
+                // Aligned for the headers of the control messages
+                let mut control = [0u64; CONTROL_LEN / 8];
+                let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+                msg.msg_name = &mut addr as *mut libc::sockaddr_ll as *mut libc::c_void;
+                msg.msg_namelen = std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+                msg.msg_iov = &mut iov;
+                msg.msg_iovlen = 1;
+                msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+                msg.msg_controllen = CONTROL_LEN as _;
+                let len = unsafe { libc::recvmsg(self.fd.as_raw_fd(), &mut msg, libc::MSG_TRUNC) };
+                if len < 0 {
+                    let err = io::Error::last_os_error();
+                    match err.kind() {
+                        io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted => continue,
+                        _ => return Err(err),
+                    }
+                }
+                // Packets sent on the loopback interface are also received
+                if self.loopback && addr.sll_pkttype == libc::PACKET_OUTGOING {
+                    continue;
+                }
+                let wirelen = len as usize;
+                let caplen = wirelen.min(buffer.len());
+                // Packets that the clock did not stamp, such as those sent
+                // by the host with the clock of the adapter, get the time
+                // they were read at
+                let now = self.timestamp(&msg).unwrap_or_else(|| {
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                });
+                let header = PcapPacketHeader {
+                    ts_sec: now.as_secs() as u32,
+                    ts_usec: now.subsec_nanos(),
+                    caplen: caplen as u32,
+                    wirelen: wirelen as u32,
+                };
+                return Ok((header, buffer[..caplen].to_vec()));
+            }
+        }
+    }
+}
+
+/// Live capture is only supported on Linux
+#[cfg(not(target_os = "linux"))]
+#[derive(Debug)]
+pub struct Capture {
+    interface: String,
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Capture {
+    pub fn open(_interface: &str, _snaplen: u32) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Live capture is not supported on this platform",
+        ))
+    }
+    pub fn set_duration(&self, _duration: std::time::Duration) {}
+    pub fn set_tstamp_type(&self, _tstamp: TstampType) -> io::Result<()> {
+        Ok(())
+    }
+    pub fn stats(&self) -> io::Result<CaptureStats> {
+        Ok(CaptureStats::default())
+    }
+    pub fn next_packet(&self) -> io::Result<(PcapPacketHeader, Vec<u8>)> {
+        Self::open(&self.interface, 0).map(|_| unreachable!())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn watch_hangup() {}
+
+#[cfg(not(target_os = "linux"))]
+pub fn take_hangup() -> bool {
+    false
+}
@@ -0,0 +1,54 @@
+use crate::value::Value;
+
+/// The internet checksum, i.e. the ones' complement of the ones'
+/// complement sum of the 16 bit words of the data
+pub fn internet_checksum(chunks: &[&[u8]]) -> u16 {
+    let mut sum: u32 = 0;
+    let mut odd = None;
+    for byte in chunks.iter().flat_map(|chunk| chunk.iter()) {
+        match odd.take() {
+            Some(hi) => sum += u16::from_be_bytes([hi, *byte]) as u32,
+            None => odd = Some(*byte),
+        }
+    }
+    if let Some(hi) = odd {
+        sum += u16::from_be_bytes([hi, 0]) as u32;
+    }
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Value assigned to a length or a checksum to have it computed when the
+/// packet is written. It is also available as the builtin variable 'auto'.
+pub const AUTO: &str = "auto";
+
+/// Check if a value assigned to a property asks for it to be computed
+pub fn is_auto<V: Value>(value: &V) -> bool {
+    value.as_str() == Some(AUTO)
+}
+
+/// Compute the checksum of the tcp or udp segment 'data' serialized from
+/// 'inner' if it was set to 'auto'. The pseudo header is made of the
+/// source and destination addresses 'addrs' of the ip header followed by
+/// the protocol and the length of the segment.
+pub fn fix_transport_checksum<V: Value>(inner: &V, addrs: &[u8], data: &mut [u8]) {
+    let (protocol, pos) = match (inner.as_tcp(), inner.as_udp()) {
+        (Some(tcp), _) if tcp.is_auto_checksum() => (6, 16),
+        (_, Some(udp)) if udp.is_auto_checksum() => (17, 6),
+        _ => return,
+    };
+    if data.len() < pos + 2 {
+        return;
+    }
+    data[pos..pos + 2].fill(0);
+    let len = (data.len() as u32).to_be_bytes();
+    let checksum = internet_checksum(&[addrs, &len, &[0, 0, 0, protocol], data]);
+    // A udp checksum of zero means that there is no checksum
+    let checksum = match (protocol, checksum) {
+        (17, 0) => 0xffff,
+        _ => checksum,
+    };
+    data[pos..pos + 2].copy_from_slice(&checksum.to_be_bytes());
+}
@@ -0,0 +1,98 @@
+use std::rc::Rc;
+
+use crate::dns::{Dns, DNS_HEADER_SIZE};
+use crate::ports::AppProto;
+use crate::tls::{ContentTypes, HandshakeTypes, TLS_RECORD_HEADER_SIZE};
+
+// Minimum score for a payload to be detected as a protocol
+const MIN_SCORE: u32 = 60;
+// Maximum length of a tls record including the expansion by encryption
+const MAX_TLS_RECORD: usize = 16384 + 2048;
+
+/// Transport protocol carrying the payload whose protocol is detected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Udp,
+    Tcp,
+}
+
+/// Detect the protocol of a payload on ports that are not mapped to one.
+/// Each candidate scores the payload on how well it matches the magic
+/// bytes and the structure the protocol expects, and the candidate with
+/// the highest score above a threshold is chosen.
+pub fn detect_proto(payload: &[u8], transport: Transport) -> Option<AppProto> {
+    [
+        (AppProto::Dns, score_dns(payload, transport)),
+        (AppProto::Tls, score_tls(payload, transport)),
+    ]
+    .into_iter()
+    .filter(|(_, score)| *score >= MIN_SCORE)
+    .max_by_key(|(_, score)| *score)
+    .map(|(proto, _)| proto)
+}
+
+fn score_dns(payload: &[u8], transport: Transport) -> u32 {
+    let mut score = 0;
+    // Over tcp, the message is preceded by its length
+    let msg = match transport {
+        Transport::Udp => payload,
+        Transport::Tcp if payload.len() >= 2 => {
+            let len = u16::from_be_bytes([payload[0], payload[1]]) as usize;
+            if len == payload.len() - 2 {
+                score += 10;
+            }
+            &payload[2..]
+        }
+        Transport::Tcp => return 0,
+    };
+    if msg.len() < DNS_HEADER_SIZE {
+        return 0;
+    }
+    let flags = u16::from_be_bytes([msg[2], msg[3]]);
+    let opcode = (flags >> 11) & 0xf;
+    // Unassigned opcodes or the reserved 'Z' bit rule out dns
+    if opcode == 3 || opcode > 6 || flags & 0x0040 != 0 {
+        return 0;
+    }
+    // Messages almost always carry exactly one question
+    if u16::from_be_bytes([msg[4], msg[5]]) != 1 {
+        return 0;
+    }
+    score += 20;
+    if Dns::from_bytes(Rc::new(msg.to_vec()), 0).is_ok() {
+        score += 50;
+    }
+    score
+}
+
+fn score_tls(payload: &[u8], transport: Transport) -> u32 {
+    if transport != Transport::Tcp || payload.len() < TLS_RECORD_HEADER_SIZE {
+        return 0;
+    }
+    let content_type = payload[0];
+    if !(ContentTypes::CHANGE_CIPHER_SPEC..=ContentTypes::APPLICATION_DATA).contains(&content_type)
+    {
+        return 0;
+    }
+    // Versions from SSL 3.0 (0x0300) to TLS 1.3 (0x0304)
+    if payload[1] != 3 || payload[2] > 4 {
+        return 0;
+    }
+    let length = u16::from_be_bytes([payload[3], payload[4]]) as usize;
+    if length == 0 || length > MAX_TLS_RECORD {
+        return 0;
+    }
+    let mut score = 50;
+    if payload.len() >= TLS_RECORD_HEADER_SIZE + length {
+        score += 20;
+    }
+    if content_type == ContentTypes::HANDSHAKE
+        && matches!(
+            payload.get(TLS_RECORD_HEADER_SIZE),
+            Some(&HandshakeTypes::CLIENT_HELLO | &HandshakeTypes::SERVER_HELLO)
+        )
+    {
+        score += 20;
+    }
+    score
+}
@@ -0,0 +1,308 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::addr::Addr;
+use crate::error::PacketError;
+use crate::ipv4addr::Ipv4Address;
+use crate::ipv6addr::Ipv6Address;
+use crate::value::Value;
+
+pub const DNS_HEADER_SIZE: usize = 12;
+pub const DNS_PORT: u16 = 53;
+
+// Maximum number of compression pointers followed while reading a name
+const MAX_NAME_POINTERS: usize = 64;
+
+#[allow(non_snake_case)]
+pub mod RecordTypes {
+    pub const A: u16 = 1;
+    pub const NS: u16 = 2;
+    pub const CNAME: u16 = 5;
+    pub const PTR: u16 = 12;
+    pub const MX: u16 = 15;
+    pub const TXT: u16 = 16;
+    pub const AAAA: u16 = 28;
+}
+
+/// An entry in the question section of a DNS message
+#[derive(Debug, Clone)]
+pub struct DnsQuestion {
+    pub name: String,
+    pub qtype: u16,
+    pub qclass: u16,
+}
+
+/// Data of a resource record decoded based on its type
+#[derive(Debug, Clone)]
+pub enum RecordData {
+    Addr(Addr),
+    Name(String),
+    Text(String),
+    Raw(Vec<u8>),
+}
+
+/// A resource record in the answer section of a DNS message
+#[derive(Debug, Clone)]
+pub struct DnsRecord {
+    pub name: String,
+    pub rtype: u16,
+    pub rclass: u16,
+    pub ttl: u32,
+    pub data: RecordData,
+}
+
+/// A DNS message carried in a UDP datagram or a TCP segment.
+/// The message is decoded when the object is created and is read only.
+#[derive(Debug)]
+pub struct Dns {
+    id: u16,
+    flags: u16,
+    queries: Vec<DnsQuestion>,
+    answers: Vec<DnsRecord>,
+    pub rawdata: Rc<Vec<u8>>, // Raw data of the entire packet
+    pub offset: usize,        // Offset of the DNS message
+}
+
+impl fmt::Display for Dns {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = if self.flags & 0x8000 != 0 {
+            "response"
+        } else {
+            "query"
+        };
+        let qname = self.queries.first().map_or("", |q| q.name.as_str());
+        write!(
+            f,
+            "<{} id:{} qname:{} answers:{}>",
+            kind,
+            self.id,
+            qname,
+            self.answers.len()
+        )
+    }
+}
+
+impl From<&Dns> for Vec<u8> {
+    fn from(dns: &Dns) -> Self {
+        dns.rawdata[dns.offset..].to_vec()
+    }
+}
+
+impl Dns {
+    /// Decode the DNS message at the offset 'off' of the raw data
+    pub fn from_bytes(rawdata: Rc<Vec<u8>>, off: usize) -> Result<Self, PacketError> {
+        if rawdata.len() < off + DNS_HEADER_SIZE {
+            return Err(PacketError::InvalidLength(rawdata.len()));
+        }
+        let msg = &rawdata[off..];
+        let read_u16 = |i: usize| u16::from_be_bytes([msg[i], msg[i + 1]]);
+        let id = read_u16(0);
+        let flags = read_u16(2);
+        let qdcount = read_u16(4);
+        let ancount = read_u16(6);
+
+        let mut pos = DNS_HEADER_SIZE;
+        let mut queries = Vec::new();
+        for _ in 0..qdcount {
+            let name = read_name(msg, &mut pos)?;
+            let fixed = read_bytes(msg, &mut pos, 4)?;
+            queries.push(DnsQuestion {
+                name,
+                qtype: u16::from_be_bytes([fixed[0], fixed[1]]),
+                qclass: u16::from_be_bytes([fixed[2], fixed[3]]),
+            });
+        }
+        let mut answers = Vec::new();
+        for _ in 0..ancount {
+            answers.push(read_record(msg, &mut pos)?);
+        }
+        Ok(Self {
+            id,
+            flags,
+            queries,
+            answers,
+            rawdata,
+            offset: off,
+        })
+    }
+
+    /// Decode a DNS message sent over TCP, which is preceded by its length
+    pub fn from_tcp_bytes(rawdata: Rc<Vec<u8>>, off: usize) -> Result<Self, PacketError> {
+        if rawdata.len() < off + 2 {
+            return Err(PacketError::InvalidLength(rawdata.len()));
+        }
+        Self::from_bytes(rawdata, off + 2)
+    }
+
+    pub fn get_id<V: Value>(&self) -> Rc<V> {
+        Rc::new(V::integer(self.id as i64))
+    }
+
+    pub fn get_flags<V: Value>(&self) -> Rc<V> {
+        Rc::new(V::integer(self.flags as i64))
+    }
+
+    pub fn get_opcode<V: Value>(&self) -> Rc<V> {
+        Rc::new(V::integer(((self.flags >> 11) & 0xf) as i64))
+    }
+
+    pub fn get_rcode<V: Value>(&self) -> Rc<V> {
+        Rc::new(V::integer((self.flags & 0xf) as i64))
+    }
+
+    /// Name in the first question, or null if there are no questions
+    pub fn get_qname<V: Value>(&self) -> Rc<V> {
+        match self.queries.first() {
+            Some(q) => Rc::new(V::string(q.name.clone())),
+            None => Rc::new(V::null()),
+        }
+    }
+
+    /// Type in the first question, or null if there are no questions
+    pub fn get_qtype<V: Value>(&self) -> Rc<V> {
+        match self.queries.first() {
+            Some(q) => Rc::new(V::integer(q.qtype as i64)),
+            None => Rc::new(V::null()),
+        }
+    }
+
+    /// Questions as an array of maps with the keys 'name', 'type' and 'class'
+    pub fn get_queries<V: Value>(&self) -> Rc<V> {
+        let elements = self
+            .queries
+            .iter()
+            .map(|q| {
+                Rc::new(V::record(vec![
+                    ("name", V::string(q.name.clone())),
+                    ("type", V::integer(q.qtype as i64)),
+                    ("class", V::integer(q.qclass as i64)),
+                ]))
+            })
+            .collect();
+        Rc::new(V::array(elements))
+    }
+
+    /// Answers as an array of maps with the keys 'name', 'type', 'class',
+    /// 'ttl' and 'data'
+    pub fn get_answers<V: Value>(&self) -> Rc<V> {
+        let elements = self
+            .answers
+            .iter()
+            .map(|r| {
+                let data = match &r.data {
+                    RecordData::Addr(addr) => V::addr(addr.clone()),
+                    RecordData::Name(s) | RecordData::Text(s) => V::string(s.clone()),
+                    RecordData::Raw(bytes) => V::bytes(bytes.clone()),
+                };
+                Rc::new(V::record(vec![
+                    ("name", V::string(r.name.clone())),
+                    ("type", V::integer(r.rtype as i64)),
+                    ("class", V::integer(r.rclass as i64)),
+                    ("ttl", V::integer(r.ttl as i64)),
+                    ("data", data),
+                ]))
+            })
+            .collect();
+        Rc::new(V::array(elements))
+    }
+}
+
+fn read_bytes<'a>(msg: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8], PacketError> {
+    if msg.len() < *pos + len {
+        return Err(PacketError::InvalidLength(msg.len()));
+    }
+    let bytes = &msg[*pos..*pos + len];
+    *pos += len;
+    Ok(bytes)
+}
+
+/// Read a possibly compressed domain name at 'pos' and advance past it.
+/// The root domain is returned as '.' and other names without the
+/// trailing dot.
+fn read_name(msg: &[u8], pos: &mut usize) -> Result<String, PacketError> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut cursor = *pos;
+    let mut pointers = 0;
+    loop {
+        let len = *msg
+            .get(cursor)
+            .ok_or(PacketError::InvalidLength(msg.len()))? as usize;
+        match len & 0xc0 {
+            0x00 if len == 0 => {
+                cursor += 1;
+                break;
+            }
+            0x00 => {
+                cursor += 1;
+                let label = read_bytes(msg, &mut cursor, len)?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+            }
+            0xc0 => {
+                let low = *msg
+                    .get(cursor + 1)
+                    .ok_or(PacketError::InvalidLength(msg.len()))?;
+                // The name continues after the first pointer
+                if pointers == 0 {
+                    *pos = cursor + 2;
+                }
+                pointers += 1;
+                if pointers > MAX_NAME_POINTERS {
+                    return Err(PacketError::MalformedDnsName);
+                }
+                cursor = ((len & 0x3f) << 8) | low as usize;
+            }
+            _ => return Err(PacketError::MalformedDnsName),
+        }
+    }
+    if pointers == 0 {
+        *pos = cursor;
+    }
+    if labels.is_empty() {
+        Ok(String::from("."))
+    } else {
+        Ok(labels.join("."))
+    }
+}
+
+fn read_record(msg: &[u8], pos: &mut usize) -> Result<DnsRecord, PacketError> {
+    let name = read_name(msg, pos)?;
+    let fixed = read_bytes(msg, pos, 10)?;
+    let rtype = u16::from_be_bytes([fixed[0], fixed[1]]);
+    let rclass = u16::from_be_bytes([fixed[2], fixed[3]]);
+    let ttl = u32::from_be_bytes([fixed[4], fixed[5], fixed[6], fixed[7]]);
+    let rdlength = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+    // Names in the data may be compressed, so they are read from the message
+    let mut start = *pos;
+    let rdata = read_bytes(msg, pos, rdlength)?;
+    let data = match (rtype, rdlength) {
+        (RecordTypes::A, 4) => RecordData::Addr(Addr::Ipv4(Ipv4Address::from_bytes(rdata))),
+        (RecordTypes::AAAA, 16) => RecordData::Addr(Addr::Ipv6(Ipv6Address::from_bytes(rdata))),
+        (RecordTypes::NS | RecordTypes::CNAME | RecordTypes::PTR, _) => {
+            RecordData::Name(read_name(msg, &mut start)?)
+        }
+        // The exchange follows a 16 bit preference
+        (RecordTypes::MX, 3..) => {
+            start += 2;
+            RecordData::Name(read_name(msg, &mut start)?)
+        }
+        (RecordTypes::TXT, _) => {
+            let mut text = String::new();
+            let mut i = 0;
+            while i < rdata.len() {
+                let len = rdata[i] as usize;
+                let end = (i + 1 + len).min(rdata.len());
+                text.push_str(&String::from_utf8_lossy(&rdata[i + 1..end]));
+                i = end;
+            }
+            RecordData::Text(text)
+        }
+        _ => RecordData::Raw(rdata.to_vec()),
+    };
+    Ok(DnsRecord {
+        name,
+        rtype,
+        rclass,
+        ttl,
+        data,
+    })
+}
@@ -4,6 +4,7 @@ use std::fmt;
 pub enum PacketError {
     InvalidLength(usize),
     InvalidMacAddress,
+    MalformedDnsName,
 }
 
 impl fmt::Display for PacketError {
@@ -11,6 +12,7 @@ impl fmt::Display for PacketError {
         match &self {
             Self::InvalidLength(len) => write!(f, "invalid-length: {len}"),
             Self::InvalidMacAddress => write!(f, "invalid-mac-address"),
+            Self::MalformedDnsName => write!(f, "malformed-dns-name"),
         }
     }
 }
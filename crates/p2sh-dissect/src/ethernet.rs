@@ -1,11 +1,13 @@
-use super::error::PacketError;
-use super::macaddress::MacAddress;
-use crate::object::Object;
+use crate::addr::Addr;
+use crate::error::PacketError;
+use crate::macaddress::MacAddress;
+use crate::value::Value;
 
 use std::cell::RefCell;
 use std::convert::From;
 use std::fmt;
 use std::rc::Rc;
+use std::str::FromStr;
 
 #[derive(Debug, Clone)]
 pub struct EthernetHeader {
@@ -27,16 +29,16 @@ impl From<&EthernetHeader> for Vec<u8> {
 }
 
 #[derive(Debug)]
-pub struct Ethernet {
+pub struct Ethernet<V> {
     header: RefCell<EthernetHeader>,   // Header of the ethernet packet
     pub rawdata: RefCell<Rc<Vec<u8>>>, // Raw data of the entire packet
     pub offset: usize,                 // Offset of the ethernet header
-    pub inner: RefCell<Option<Rc<Object>>>, // Inner packet
+    pub inner: RefCell<Option<Rc<V>>>, // Inner packet
 }
 
 pub const ETHERNET_HEADER_SIZE: usize = 14;
 
-impl fmt::Display for Ethernet {
+impl<V: Value> fmt::Display for Ethernet<V> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
@@ -53,12 +55,12 @@ impl fmt::Display for Ethernet {
     }
 }
 
-impl From<&Ethernet> for Vec<u8> {
-    fn from(eth: &Ethernet) -> Self {
+impl<V: Value> From<&Ethernet<V>> for Vec<u8> {
+    fn from(eth: &Ethernet<V>) -> Self {
         let header = eth.header.borrow().clone();
         let mut bytes: Vec<u8> = (&header).into();
         if let Some(inner) = eth.inner.borrow().clone() {
-            let data: Vec<u8> = inner.as_ref().into();
+            let data = inner.to_bytes();
             bytes.extend_from_slice(&data);
         } else {
             let data = eth.rawdata.borrow().clone();
@@ -68,7 +70,7 @@ impl From<&Ethernet> for Vec<u8> {
     }
 }
 
-impl Ethernet {
+impl<V: Value> Ethernet<V> {
     // off is the offset of the ethernet header when it is encapsulated in
     // another protocol. For example, if the ethernet header is encapsulated
     // in an 802.1Q VLAN header, then off is the offset of the VLAN header.
@@ -96,46 +98,54 @@ impl Ethernet {
     pub fn get_ethertype_raw(&self) -> EtherType {
         self.header.borrow().ethertype.clone()
     }
-    pub fn get_src(&self) -> Rc<Object> {
-        Rc::new(Object::Str(self.header.borrow().source.to_string()))
+    pub fn get_src(&self) -> Rc<V> {
+        Rc::new(V::addr(Addr::Mac(self.header.borrow().source.clone())))
     }
-    pub fn get_dst(&self) -> Rc<Object> {
-        Rc::new(Object::Str(self.header.borrow().dest.to_string()))
+    pub fn get_dst(&self) -> Rc<V> {
+        Rc::new(V::addr(Addr::Mac(self.header.borrow().dest.clone())))
     }
-    pub fn get_ethertype(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().ethertype.0 as i64))
+    pub fn get_ethertype(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().ethertype.0 as i64))
     }
-    pub fn set_src(&self, src: Rc<Object>) -> Result<(), String> {
-        match src.as_ref() {
-            Object::Str(src) => match MacAddress::from_str(src) {
+    pub fn set_src(&self, src: Rc<V>) -> Result<(), String> {
+        match (src.as_str(), src.as_addr()) {
+            (Some(src), _) => match MacAddress::from_str(src) {
                 Ok(mac) => {
                     self.header.borrow_mut().source = mac;
                     Ok(())
                 }
                 Err(e) => Err(e.to_string()),
             },
+            (_, Some(Addr::Mac(mac))) => {
+                self.header.borrow_mut().source = mac.clone();
+                Ok(())
+            }
             _ => Err("Invalid value for ethernet property src".to_string()),
         }
     }
-    pub fn set_dst(&self, src: Rc<Object>) -> Result<(), String> {
-        match src.as_ref() {
-            Object::Str(dst) => match MacAddress::from_str(dst) {
+    pub fn set_dst(&self, src: Rc<V>) -> Result<(), String> {
+        match (src.as_str(), src.as_addr()) {
+            (Some(dst), _) => match MacAddress::from_str(dst) {
                 Ok(mac) => {
                     self.header.borrow_mut().dest = mac;
                     Ok(())
                 }
                 Err(e) => Err(e.to_string()),
             },
+            (_, Some(Addr::Mac(mac))) => {
+                self.header.borrow_mut().dest = mac.clone();
+                Ok(())
+            }
             _ => Err("Invalid value for ethernet property dest".to_string()),
         }
     }
-    pub fn set_ethertype(&self, ethertype: Rc<Object>) -> Result<(), String> {
-        match ethertype.as_ref() {
-            Object::Integer(ethertype) => {
-                if *ethertype < 0 || *ethertype > 65535 {
+    pub fn set_ethertype(&self, ethertype: Rc<V>) -> Result<(), String> {
+        match ethertype.as_integer() {
+            Some(ethertype) => {
+                if !(0..=65535).contains(&ethertype) {
                     return Err("Invalid value for VLAN property ethertype".to_string());
                 }
-                self.header.borrow_mut().ethertype = EtherType(*ethertype as u16);
+                self.header.borrow_mut().ethertype = EtherType(ethertype as u16);
                 Ok(())
             }
             _ => Err("Invalid value for VLAN property ethertype".to_string()),
@@ -1,11 +1,18 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::convert::From;
 use std::fmt;
 use std::rc::Rc;
+use std::str::FromStr;
 
-use super::error::PacketError;
-use super::ipv4addr::Ipv4Address;
-use crate::object::Object;
+use crate::addr::Addr;
+use crate::checksum::{fix_transport_checksum, internet_checksum, is_auto};
+use crate::error::PacketError;
+use crate::ipv4addr::Ipv4Address;
+use crate::value::Value;
+
+// Bits of the ipv4 flags
+pub const IPV4_MF: u8 = 0x1;
+pub const IPV4_DF: u8 = 0x2;
 
 #[derive(Debug, Clone)]
 pub struct Ipv4Header {
@@ -22,19 +29,22 @@ pub struct Ipv4Header {
     checksum: u16,
     source: Ipv4Address,
     destination: Ipv4Address,
+    options: Vec<u8>,
 }
 
 #[derive(Debug)]
-pub struct Ipv4Packet {
+pub struct Ipv4Packet<V> {
     header: RefCell<Ipv4Header>,
     pub rawdata: RefCell<Rc<Vec<u8>>>,
     pub offset: usize,
-    pub inner: RefCell<Option<Rc<Object>>>,
+    pub inner: RefCell<Option<Rc<V>>>,
+    auto_length: Cell<bool>,   // compute the total length when serialized
+    auto_checksum: Cell<bool>, // compute the checksum when serialized
 }
 
 pub const IPV4_HEADER_SIZE: usize = 20;
 
-impl fmt::Display for Ipv4Packet {
+impl<V: Value> fmt::Display for Ipv4Packet<V> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
@@ -51,7 +61,7 @@ impl fmt::Display for Ipv4Packet {
     }
 }
 
-impl Ipv4Packet {
+impl<V: Value> Ipv4Packet<V> {
     pub fn from_bytes(rawdata: Rc<Vec<u8>>, off: usize) -> Result<Self, PacketError> {
         if rawdata.len() < off + IPV4_HEADER_SIZE {
             return Err(PacketError::InvalidLength(rawdata.len()));
@@ -59,6 +69,9 @@ impl Ipv4Packet {
         let version_ihl = rawdata[off] & 0xF;
         let version = (rawdata[off] >> 4) & 0xF;
         let ihl = version_ihl & 0xF;
+        if rawdata.len() < off + ihl as usize * 4 {
+            return Err(PacketError::InvalidLength(rawdata.len()));
+        }
         let dscp_ecn = rawdata[off + 1];
         let dscp = dscp_ecn >> 2;
         let ecn = dscp_ecn & 0x03;
@@ -98,173 +111,211 @@ impl Ipv4Packet {
             checksum,
             source,
             destination,
+            options,
         };
         Ok(Self {
             header: RefCell::new(header),
             rawdata: RefCell::new(rawdata),
             offset,
             inner: RefCell::new(None),
+            auto_length: Cell::new(false),
+            auto_checksum: Cell::new(false),
         })
     }
 
-    pub fn get_version(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().version as i64))
+    pub fn get_version(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().version as i64))
     }
-    pub fn get_ihl(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().ihl as i64))
+    pub fn get_ihl(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().ihl as i64))
     }
 
-    pub fn get_src(&self) -> Rc<Object> {
-        Rc::new(Object::Str(self.header.borrow().source.to_string()))
+    pub fn get_src(&self) -> Rc<V> {
+        Rc::new(V::addr(Addr::Ipv4(self.header.borrow().source.clone())))
     }
-    pub fn set_src(&self, source_address: Rc<Object>) -> Result<(), String> {
-        match source_address.as_ref() {
-            Object::Str(source_address) => match Ipv4Address::from_str(source_address) {
+    pub fn set_src(&self, source_address: Rc<V>) -> Result<(), String> {
+        match (source_address.as_str(), source_address.as_addr()) {
+            (Some(source_address), _) => match Ipv4Address::from_str(source_address) {
                 Ok(ipv4_addr) => {
                     self.header.borrow_mut().source = ipv4_addr;
                     Ok(())
                 }
                 Err(e) => Err(e.to_string()),
             },
+            (_, Some(Addr::Ipv4(ip))) => {
+                self.header.borrow_mut().source = ip.clone();
+                Ok(())
+            }
             _ => Err("Invalid value for Ipv4 property source_address".to_string()),
         }
     }
 
-    pub fn get_dst(&self) -> Rc<Object> {
-        Rc::new(Object::Str(self.header.borrow().destination.to_string()))
+    pub fn get_dst(&self) -> Rc<V> {
+        Rc::new(V::addr(Addr::Ipv4(
+            self.header.borrow().destination.clone(),
+        )))
     }
-    pub fn set_dst(&self, destination_address: Rc<Object>) -> Result<(), String> {
-        match destination_address.as_ref() {
-            Object::Str(destination_address) => match Ipv4Address::from_str(destination_address) {
+    pub fn set_dst(&self, destination_address: Rc<V>) -> Result<(), String> {
+        match (destination_address.as_str(), destination_address.as_addr()) {
+            (Some(destination_address), _) => match Ipv4Address::from_str(destination_address) {
                 Ok(ipv4_addr) => {
                     self.header.borrow_mut().destination = ipv4_addr;
                     Ok(())
                 }
                 Err(e) => Err(e.to_string()),
             },
+            (_, Some(Addr::Ipv4(ip))) => {
+                self.header.borrow_mut().destination = ip.clone();
+                Ok(())
+            }
             _ => Err("Invalid value for Ipv4 property destination_address".to_string()),
         }
     }
 
-    pub fn set_ihl(&self, ihl: Rc<Object>) -> Result<(), String> {
-        match ihl.as_ref() {
-            Object::Integer(ihl) => {
-                if *ihl < 0 || *ihl > 15 {
+    pub fn set_ihl(&self, ihl: Rc<V>) -> Result<(), String> {
+        match ihl.as_integer() {
+            Some(ihl) => {
+                if !(0..=15).contains(&ihl) {
                     return Err("Invalid value for Ipv4 property ihl".to_string());
                 }
-                self.header.borrow_mut().ihl = *ihl as u8;
+                self.header.borrow_mut().ihl = ihl as u8;
                 Ok(())
             }
             _ => Err("Invalid value for Ipv4 property ihl".to_string()),
         }
     }
 
-    pub fn get_dscp(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().dscp as i64))
+    pub fn get_dscp(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().dscp as i64))
     }
-    pub fn set_dscp(&self, dscp: Rc<Object>) -> Result<(), String> {
-        match dscp.as_ref() {
-            Object::Integer(dscp) => {
-                if *dscp < 0 || *dscp > 63 {
+    pub fn set_dscp(&self, dscp: Rc<V>) -> Result<(), String> {
+        match dscp.as_integer() {
+            Some(dscp) => {
+                if !(0..=63).contains(&dscp) {
                     return Err("Invalid value for Ipv4 property dscp".to_string());
                 }
-                self.header.borrow_mut().dscp = *dscp as u8;
+                self.header.borrow_mut().dscp = dscp as u8;
                 Ok(())
             }
             _ => Err("Invalid value for Ipv4 property dscp".to_string()),
         }
     }
 
-    pub fn get_ecn(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().ecn as i64))
+    pub fn get_ecn(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().ecn as i64))
     }
-    pub fn set_ecn(&self, ecn: Rc<Object>) -> Result<(), String> {
-        match ecn.as_ref() {
-            Object::Integer(ecn) => {
-                if *ecn < 0 || *ecn > 3 {
+    pub fn set_ecn(&self, ecn: Rc<V>) -> Result<(), String> {
+        match ecn.as_integer() {
+            Some(ecn) => {
+                if !(0..=3).contains(&ecn) {
                     return Err("Invalid value for Ipv4 property ecn".to_string());
                 }
-                self.header.borrow_mut().ecn = *ecn as u8;
+                self.header.borrow_mut().ecn = ecn as u8;
                 Ok(())
             }
             _ => Err("Invalid value for Ipv4 property ecn".to_string()),
         }
     }
 
-    pub fn get_total_length(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().total_length as i64))
+    pub fn get_total_length_raw(&self) -> u16 {
+        self.header.borrow().total_length
+    }
+
+    /// Length of the header including the options
+    pub fn get_header_length_raw(&self) -> usize {
+        self.header.borrow().ihl as usize * 4
+    }
+
+    pub fn get_total_length(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().total_length as i64))
     }
-    pub fn set_total_length(&self, total_length: Rc<Object>) -> Result<(), String> {
-        match total_length.as_ref() {
-            Object::Integer(total_length) => {
-                if *total_length < 0 || *total_length > 65535 {
+    pub fn set_total_length(&self, total_length: Rc<V>) -> Result<(), String> {
+        self.auto_length.set(is_auto(total_length.as_ref()));
+        match total_length.as_integer() {
+            _ if self.auto_length.get() => Ok(()),
+            Some(total_length) => {
+                if !(0..=65535).contains(&total_length) {
                     return Err("Invalid value for Ipv4 property total_length".to_string());
                 }
-                self.header.borrow_mut().total_length = *total_length as u16;
+                self.header.borrow_mut().total_length = total_length as u16;
                 Ok(())
             }
             _ => Err("Invalid value for Ipv4 property total_length".to_string()),
         }
     }
 
-    pub fn get_identification(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().identification as i64))
+    pub fn get_identification(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().identification as i64))
     }
-    pub fn set_identification(&self, identification: Rc<Object>) -> Result<(), String> {
-        match identification.as_ref() {
-            Object::Integer(identification) => {
-                if *identification < 0 || *identification > 65535 {
+    pub fn set_identification(&self, identification: Rc<V>) -> Result<(), String> {
+        match identification.as_integer() {
+            Some(identification) => {
+                if !(0..=65535).contains(&identification) {
                     return Err("Invalid value for Ipv4 property identification".to_string());
                 }
-                self.header.borrow_mut().identification = *identification as u16;
+                self.header.borrow_mut().identification = identification as u16;
                 Ok(())
             }
             _ => Err("Invalid value for Ipv4 property identification".to_string()),
         }
     }
 
-    pub fn get_flags(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().flags as i64))
+    pub fn get_flags(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().flags as i64))
+    }
+    /// Check if the flag 'bit' is set
+    pub fn get_flag(&self, bit: u8) -> Rc<V> {
+        Rc::new(V::boolean(self.header.borrow().flags & bit != 0))
     }
-    pub fn set_flags(&self, flags: Rc<Object>) -> Result<(), String> {
-        match flags.as_ref() {
-            Object::Integer(flags) => {
-                if *flags < 0 || *flags > 7 {
+    /// Set or clear the flag 'bit'
+    pub fn set_flag(&self, bit: u8, value: Rc<V>) -> Result<(), String> {
+        let mut header = self.header.borrow_mut();
+        match value.as_bool() {
+            Some(true) => header.flags |= bit,
+            Some(false) => header.flags &= !bit,
+            _ => return Err("Invalid value for Ipv4 flag".to_string()),
+        }
+        Ok(())
+    }
+    pub fn set_flags(&self, flags: Rc<V>) -> Result<(), String> {
+        match flags.as_integer() {
+            Some(flags) => {
+                if !(0..=7).contains(&flags) {
                     return Err("Invalid value for Ipv4 property flags".to_string());
                 }
-                self.header.borrow_mut().flags = *flags as u8;
+                self.header.borrow_mut().flags = flags as u8;
                 Ok(())
             }
             _ => Err("Invalid value for Ipv4 property flags".to_string()),
         }
     }
 
-    pub fn get_fragment_offset(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().fragment_offset as i64))
+    pub fn get_fragment_offset(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().fragment_offset as i64))
     }
-    pub fn set_fragment_offset(&self, fragment_offset: Rc<Object>) -> Result<(), String> {
-        match fragment_offset.as_ref() {
-            Object::Integer(fragment_offset) => {
-                if *fragment_offset < 0 || *fragment_offset > 8191 {
+    pub fn set_fragment_offset(&self, fragment_offset: Rc<V>) -> Result<(), String> {
+        match fragment_offset.as_integer() {
+            Some(fragment_offset) => {
+                if !(0..=8191).contains(&fragment_offset) {
                     return Err("Invalid value for Ipv4 property fragment_offset".to_string());
                 }
-                self.header.borrow_mut().fragment_offset = *fragment_offset as u16;
+                self.header.borrow_mut().fragment_offset = fragment_offset as u16;
                 Ok(())
             }
             _ => Err("Invalid value for Ipv4 property fragment_offset".to_string()),
         }
     }
 
-    pub fn get_ttl(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().ttl as i64))
+    pub fn get_ttl(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().ttl as i64))
     }
-    pub fn set_ttl(&self, ttl: Rc<Object>) -> Result<(), String> {
-        match ttl.as_ref() {
-            Object::Integer(ttl) => {
-                if *ttl < 0 || *ttl > 255 {
+    pub fn set_ttl(&self, ttl: Rc<V>) -> Result<(), String> {
+        match ttl.as_integer() {
+            Some(ttl) => {
+                if !(0..=255).contains(&ttl) {
                     return Err("Invalid value for Ipv4 property ttl".to_string());
                 }
-                self.header.borrow_mut().ttl = *ttl as u8;
+                self.header.borrow_mut().ttl = ttl as u8;
                 Ok(())
             }
             _ => Err("Invalid value for Ipv4 property ttl".to_string()),
@@ -274,32 +325,34 @@ impl Ipv4Packet {
     pub fn get_protocol_raw(&self) -> Protocol {
         self.header.borrow().protocol.clone()
     }
-    pub fn get_protocol(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().protocol.0 as i64))
+    pub fn get_protocol(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().protocol.0 as i64))
     }
-    pub fn set_protocol(&self, protocol: Rc<Object>) -> Result<(), String> {
-        match protocol.as_ref() {
-            Object::Integer(protocol) => {
-                if *protocol < 0 || *protocol > 255 {
+    pub fn set_protocol(&self, protocol: Rc<V>) -> Result<(), String> {
+        match protocol.as_integer() {
+            Some(protocol) => {
+                if !(0..=255).contains(&protocol) {
                     return Err("Invalid value for Ipv4 property protocol".to_string());
                 }
-                self.header.borrow_mut().protocol = Protocol(*protocol as u8);
+                self.header.borrow_mut().protocol = Protocol(protocol as u8);
                 Ok(())
             }
             _ => Err("Invalid value for Ipv4 property protocol".to_string()),
         }
     }
 
-    pub fn get_checksum(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().checksum as i64))
+    pub fn get_checksum(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().checksum as i64))
     }
-    pub fn set_checksum(&self, checksum: Rc<Object>) -> Result<(), String> {
-        match checksum.as_ref() {
-            Object::Integer(checksum) => {
-                if *checksum < 0 || *checksum > 65535 {
+    pub fn set_checksum(&self, checksum: Rc<V>) -> Result<(), String> {
+        self.auto_checksum.set(is_auto(checksum.as_ref()));
+        match checksum.as_integer() {
+            _ if self.auto_checksum.get() => Ok(()),
+            Some(checksum) => {
+                if !(0..=65535).contains(&checksum) {
                     return Err("Invalid value for Ipv4 property checksum".to_string());
                 }
-                self.header.borrow_mut().checksum = *checksum as u16;
+                self.header.borrow_mut().checksum = checksum as u16;
                 Ok(())
             }
             _ => Err("Invalid value for Ipv4 property checksum".to_string()),
@@ -325,21 +378,33 @@ impl From<&Ipv4Header> for Vec<u8> {
         bytes.extend_from_slice(&b);
         let b: Vec<u8> = (&hdr.destination).into();
         bytes.extend_from_slice(&b);
+        bytes.extend_from_slice(&hdr.options);
         bytes
     }
 }
 
-impl From<&Ipv4Packet> for Vec<u8> {
-    fn from(ipv4: &Ipv4Packet) -> Self {
+impl<V: Value> From<&Ipv4Packet<V>> for Vec<u8> {
+    fn from(ipv4: &Ipv4Packet<V>) -> Self {
         let header = ipv4.header.borrow().clone();
         let mut bytes: Vec<u8> = (&header).into();
+        let header_len = bytes.len();
         if let Some(inner) = ipv4.inner.borrow().clone() {
-            let data: Vec<u8> = inner.as_ref().into();
+            let mut data = inner.to_bytes();
+            fix_transport_checksum(inner.as_ref(), &bytes[12..20], &mut data);
             bytes.extend_from_slice(&data);
         } else {
             let data = ipv4.rawdata.borrow().clone();
             bytes.extend_from_slice(&data[ipv4.offset..]);
         }
+        if ipv4.auto_length.get() {
+            let total_length = bytes.len() as u16;
+            bytes[2..4].copy_from_slice(&total_length.to_be_bytes());
+        }
+        if ipv4.auto_checksum.get() {
+            bytes[10..12].fill(0);
+            let checksum = internet_checksum(&[&bytes[..header_len]]);
+            bytes[10..12].copy_from_slice(&checksum.to_be_bytes());
+        }
         bytes
     }
 }
@@ -1,7 +1,8 @@
 use std::convert::From;
 use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Ipv4Address(pub u8, pub u8, pub u8, pub u8);
 
 impl Ipv4Address {
@@ -11,8 +12,12 @@ impl Ipv4Address {
         }
         Self(bytes[0], bytes[1], bytes[2], bytes[3])
     }
+}
+
+impl FromStr for Ipv4Address {
+    type Err = &'static str;
 
-    pub fn from_str(s: &str) -> Result<Self, &'static str> {
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         let parts: Vec<&str> = s.split('.').collect();
 
         if parts.len() != 4 {
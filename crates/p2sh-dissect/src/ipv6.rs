@@ -1,10 +1,13 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::fmt;
 use std::rc::Rc;
+use std::str::FromStr;
 
-use super::error::PacketError;
-use super::ipv6addr::Ipv6Address;
-use crate::object::Object;
+use crate::addr::Addr;
+use crate::checksum::{fix_transport_checksum, is_auto};
+use crate::error::PacketError;
+use crate::ipv6addr::Ipv6Address;
+use crate::value::Value;
 
 pub const IPV6_HEADER_SIZE: usize = 40;
 
@@ -21,14 +24,15 @@ pub struct Ipv6Header {
 }
 
 #[derive(Debug)]
-pub struct Ipv6Packet {
+pub struct Ipv6Packet<V> {
     header: RefCell<Ipv6Header>,
     pub rawdata: RefCell<Rc<Vec<u8>>>,
     pub offset: usize,
-    pub inner: RefCell<Option<Rc<Object>>>,
+    pub inner: RefCell<Option<Rc<V>>>,
+    auto_length: Cell<bool>, // compute the payload length when serialized
 }
 
-impl fmt::Display for Ipv6Packet {
+impl<V: Value> fmt::Display for Ipv6Packet<V> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
@@ -62,22 +66,27 @@ impl From<&Ipv6Header> for Vec<u8> {
     }
 }
 
-impl From<&Ipv6Packet> for Vec<u8> {
-    fn from(ipv6: &Ipv6Packet) -> Self {
+impl<V: Value> From<&Ipv6Packet<V>> for Vec<u8> {
+    fn from(ipv6: &Ipv6Packet<V>) -> Self {
         let header = ipv6.header.borrow().clone();
         let mut bytes: Vec<u8> = (&header).into();
         if let Some(inner) = ipv6.inner.borrow().clone() {
-            let data: Vec<u8> = inner.as_ref().into();
+            let mut data = inner.to_bytes();
+            fix_transport_checksum(inner.as_ref(), &bytes[8..40], &mut data);
             bytes.extend_from_slice(&data);
         } else {
             let data = ipv6.rawdata.borrow().clone();
             bytes.extend_from_slice(&data[ipv6.offset..]);
         }
+        if ipv6.auto_length.get() {
+            let payload_length = (bytes.len() - IPV6_HEADER_SIZE) as u16;
+            bytes[4..6].copy_from_slice(&payload_length.to_be_bytes());
+        }
         bytes
     }
 }
 
-impl Ipv6Packet {
+impl<V: Value> Ipv6Packet<V> {
     pub fn from_bytes(rawdata: Rc<Vec<u8>>, off: usize) -> Result<Self, PacketError> {
         if rawdata.len() < off + IPV6_HEADER_SIZE {
             return Err(PacketError::InvalidLength(rawdata.len()));
@@ -111,116 +120,133 @@ impl Ipv6Packet {
             rawdata: RefCell::new(rawdata),
             offset,
             inner: RefCell::new(None),
+            auto_length: Cell::new(false),
         })
     }
 
-    pub fn get_version(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().version as i64))
+    pub fn get_version(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().version as i64))
+    }
+
+    pub fn get_traffic_class(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().traffic_class as i64))
     }
 
-    pub fn get_traffic_class(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().traffic_class as i64))
+    pub fn get_flow_label(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().flow_label as i64))
     }
 
-    pub fn get_flow_label(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().flow_label as i64))
+    pub fn get_payload_length_raw(&self) -> u16 {
+        self.header.borrow().payload_length
     }
 
-    pub fn get_payload_length(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().payload_length as i64))
+    pub fn get_payload_length(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().payload_length as i64))
     }
 
     pub fn get_next_header_raw(&self) -> NextHeader {
         self.header.borrow().next_header.clone()
     }
 
-    pub fn get_next_header(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().next_header.0 as i64))
+    pub fn get_next_header(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().next_header.0 as i64))
     }
 
-    pub fn get_hop_limit(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().hop_limit as i64))
+    pub fn get_hop_limit(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().hop_limit as i64))
     }
 
-    pub fn get_src(&self) -> Rc<Object> {
-        Rc::new(Object::Str(self.header.borrow().source.to_string()))
+    pub fn get_src(&self) -> Rc<V> {
+        Rc::new(V::addr(Addr::Ipv6(self.header.borrow().source.clone())))
     }
 
-    pub fn get_dst(&self) -> Rc<Object> {
-        Rc::new(Object::Str(self.header.borrow().destination.to_string()))
+    pub fn get_dst(&self) -> Rc<V> {
+        Rc::new(V::addr(Addr::Ipv6(
+            self.header.borrow().destination.clone(),
+        )))
     }
 
-    pub fn set_traffic_class(&self, tc: Rc<Object>) -> Result<(), String> {
-        match tc.as_ref() {
-            Object::Integer(tc) => {
-                self.header.borrow_mut().traffic_class = *tc as u8;
+    pub fn set_traffic_class(&self, tc: Rc<V>) -> Result<(), String> {
+        match tc.as_integer() {
+            Some(tc) => {
+                self.header.borrow_mut().traffic_class = tc as u8;
                 Ok(())
             }
             _ => Err("Invalid value for Ipv6 property traffic class".to_string()),
         }
     }
 
-    pub fn set_flow_label(&self, flow_label: Rc<Object>) -> Result<(), String> {
-        match flow_label.as_ref() {
-            Object::Integer(flow_label) => {
-                self.header.borrow_mut().flow_label = *flow_label as u32;
+    pub fn set_flow_label(&self, flow_label: Rc<V>) -> Result<(), String> {
+        match flow_label.as_integer() {
+            Some(flow_label) => {
+                self.header.borrow_mut().flow_label = flow_label as u32;
                 Ok(())
             }
             _ => Err("Invalid value for Ipv6 property flow label".to_string()),
         }
     }
 
-    pub fn set_payload_length(&self, payload_length: Rc<Object>) -> Result<(), String> {
-        match payload_length.as_ref() {
-            Object::Integer(payload_length) => {
-                self.header.borrow_mut().payload_length = *payload_length as u16;
+    pub fn set_payload_length(&self, payload_length: Rc<V>) -> Result<(), String> {
+        self.auto_length.set(is_auto(payload_length.as_ref()));
+        match payload_length.as_integer() {
+            _ if self.auto_length.get() => Ok(()),
+            Some(payload_length) => {
+                self.header.borrow_mut().payload_length = payload_length as u16;
                 Ok(())
             }
             _ => Err("Invalid value for Ipv6 property payload length".to_string()),
         }
     }
 
-    pub fn set_next_header(&self, next_header: Rc<Object>) -> Result<(), String> {
-        match next_header.as_ref() {
-            Object::Integer(next_header) => {
-                self.header.borrow_mut().next_header.0 = *next_header as u8;
+    pub fn set_next_header(&self, next_header: Rc<V>) -> Result<(), String> {
+        match next_header.as_integer() {
+            Some(next_header) => {
+                self.header.borrow_mut().next_header.0 = next_header as u8;
                 Ok(())
             }
             _ => Err("Invalid value for Ipv6 property next header".to_string()),
         }
     }
 
-    pub fn set_hop_limit(&self, hop_limit: Rc<Object>) -> Result<(), String> {
-        match hop_limit.as_ref() {
-            Object::Integer(hop_limit) => {
-                self.header.borrow_mut().hop_limit = *hop_limit as u8;
+    pub fn set_hop_limit(&self, hop_limit: Rc<V>) -> Result<(), String> {
+        match hop_limit.as_integer() {
+            Some(hop_limit) => {
+                self.header.borrow_mut().hop_limit = hop_limit as u8;
                 Ok(())
             }
             _ => Err("Invalid value for Ipv6 property hop limit".to_string()),
         }
     }
 
-    pub fn set_src(&self, source_address: Rc<Object>) -> Result<(), String> {
-        match source_address.as_ref() {
-            Object::Str(src) => match Ipv6Address::from_str(src) {
+    pub fn set_src(&self, source_address: Rc<V>) -> Result<(), String> {
+        match (source_address.as_str(), source_address.as_addr()) {
+            (Some(src), _) => match Ipv6Address::from_str(src) {
                 Ok(ipv4_addr) => {
                     self.header.borrow_mut().source = ipv4_addr;
                     Ok(())
                 }
                 Err(e) => Err(e.to_string()),
             },
+            (_, Some(Addr::Ipv6(ip))) => {
+                self.header.borrow_mut().source = ip.clone();
+                Ok(())
+            }
             _ => Err("Invalid value for Ipv6 property source address".to_string()),
         }
     }
-    pub fn set_dst(&self, destination_address: Rc<Object>) -> Result<(), String> {
-        match destination_address.as_ref() {
-            Object::Str(dst) => match Ipv6Address::from_str(dst) {
+    pub fn set_dst(&self, destination_address: Rc<V>) -> Result<(), String> {
+        match (destination_address.as_str(), destination_address.as_addr()) {
+            (Some(dst), _) => match Ipv6Address::from_str(dst) {
                 Ok(ipv4_addr) => {
                     self.header.borrow_mut().destination = ipv4_addr;
                     Ok(())
                 }
                 Err(e) => Err(e.to_string()),
             },
+            (_, Some(Addr::Ipv6(ip))) => {
+                self.header.borrow_mut().destination = ip.clone();
+                Ok(())
+            }
             _ => Err("Invalid value for Ipv6 property destination address".to_string()),
         }
     }
@@ -239,6 +265,7 @@ pub mod NextHeaders {
     pub const Ipv6Route: NextHeader = NextHeader(43);
     pub const Ipv6Frag: NextHeader = NextHeader(44);
     pub const Rsvp: NextHeader = NextHeader(46);
+    pub const Gre: NextHeader = NextHeader(47);
     pub const EncapSecPayload: NextHeader = NextHeader(50);
     pub const AuthHdr: NextHeader = NextHeader(51);
     pub const Icmpv6: NextHeader = NextHeader(58);
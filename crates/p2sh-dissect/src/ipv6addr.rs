@@ -0,0 +1,77 @@
+use std::fmt;
+use std::net::Ipv6Addr;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv6Address(
+    pub u16,
+    pub u16,
+    pub u16,
+    pub u16,
+    pub u16,
+    pub u16,
+    pub u16,
+    pub u16,
+);
+
+impl Ipv6Address {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        if bytes.len() != 16 {
+            panic!("Invalid byte length for IPv6 address");
+        }
+        Self(
+            ((bytes[0] as u16) << 8) | bytes[1] as u16,
+            ((bytes[2] as u16) << 8) | bytes[3] as u16,
+            ((bytes[4] as u16) << 8) | bytes[5] as u16,
+            ((bytes[6] as u16) << 8) | bytes[7] as u16,
+            ((bytes[8] as u16) << 8) | bytes[9] as u16,
+            ((bytes[10] as u16) << 8) | bytes[11] as u16,
+            ((bytes[12] as u16) << 8) | bytes[13] as u16,
+            ((bytes[14] as u16) << 8) | bytes[15] as u16,
+        )
+    }
+}
+
+impl FromStr for Ipv6Address {
+    type Err = &'static str;
+
+    /// Create an IPv6 address from a string that may use zero compression,
+    /// including at the start or the end of the address, e.g. "::1"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let addr: Ipv6Addr = s.parse().map_err(|_| "Invalid IPv6 address format")?;
+        Ok(Self::from_bytes(&addr.octets()))
+    }
+}
+
+impl fmt::Display for Ipv6Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:x}:{:x}:{:x}:{:x}:{:x}:{:x}:{:x}:{:x}",
+            self.0, self.1, self.2, self.3, self.4, self.5, self.6, self.7
+        )
+    }
+}
+
+impl From<&Ipv6Address> for Vec<u8> {
+    fn from(ip: &Ipv6Address) -> Self {
+        vec![
+            (ip.0 >> 8) as u8,
+            ip.0 as u8,
+            (ip.1 >> 8) as u8,
+            ip.1 as u8,
+            (ip.2 >> 8) as u8,
+            ip.2 as u8,
+            (ip.3 >> 8) as u8,
+            ip.3 as u8,
+            (ip.4 >> 8) as u8,
+            ip.4 as u8,
+            (ip.5 >> 8) as u8,
+            ip.5 as u8,
+            (ip.6 >> 8) as u8,
+            ip.6 as u8,
+            (ip.7 >> 8) as u8,
+            ip.7 as u8,
+        ]
+    }
+}
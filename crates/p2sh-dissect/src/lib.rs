@@ -0,0 +1,26 @@
+//! Packet dissection: addresses, the records of pcap and pcapng streams,
+//! live capture, checksums and the protocol layers that scripts access as
+//! objects. The layers are generic over the values of the language that
+//! embeds them, which p2sh-core provides.
+
+pub mod addr;
+pub mod capture;
+pub mod checksum;
+pub mod detect;
+pub mod dns;
+pub mod error;
+pub mod ethernet;
+pub mod ipv4;
+pub mod ipv4addr;
+pub mod ipv6;
+pub mod ipv6addr;
+pub mod macaddress;
+pub mod pcapng;
+pub mod ports;
+pub mod record;
+pub mod tcp;
+pub mod tls;
+pub mod tunnel;
+pub mod udp;
+pub mod value;
+pub mod vlan;
@@ -1,17 +1,22 @@
 use std::convert::From;
 use std::fmt;
+use std::str::FromStr;
 
-use super::error::PacketError;
+use crate::error::PacketError;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MacAddress(pub u8, pub u8, pub u8, pub u8, pub u8, pub u8);
 
 impl MacAddress {
     pub fn from_bytes(bytes: &[u8]) -> Self {
         Self(bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5])
     }
+}
+
+impl FromStr for MacAddress {
+    type Err = PacketError;
 
-    pub fn from_str(s: &str) -> Result<Self, PacketError> {
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         let parts: Vec<&str> = s.split(':').collect();
 
         if parts.len() != 6 {
@@ -0,0 +1,322 @@
+use std::io;
+
+use crate::record::{read_u16, read_u32, PcapPacketHeader};
+
+// Block types
+pub const PCAPNG_SHB: u32 = 0x0A0D0D0A; // Section Header Block
+pub const PCAPNG_IDB: u32 = 0x00000001; // Interface Description Block
+pub const PCAPNG_SPB: u32 = 0x00000003; // Simple Packet Block
+pub const PCAPNG_EPB: u32 = 0x00000006; // Enhanced Packet Block
+
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B3C4D;
+// Option code for the timestamp resolution of an interface
+const PCAPNG_IF_TSRESOL: u16 = 9;
+// Option holding a comment in any block
+const PCAPNG_OPT_COMMENT: u16 = 1;
+// Blocks larger than this are considered garbage
+const PCAPNG_MAX_BLOCK_LEN: u32 = 16 * 1024 * 1024;
+
+/// An interface described by an Interface Description Block.
+#[derive(Debug, Clone)]
+pub struct PcapNgInterface {
+    pub linktype: u16,
+    pub snaplen: u32,
+    // Number of timestamp units per second
+    pub ts_units: u64,
+}
+
+/// A block read from a pcapng stream. 'body' is the block without the
+/// block type and the leading and the trailing block total length.
+pub struct PcapNgBlock {
+    pub block_type: u32,
+    pub body: Vec<u8>,
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn padded_len(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// Determine the byte order of a section from the byte-order magic that
+/// follows the block total length in a Section Header Block.
+/// Returns true if the section is big endian.
+pub fn section_byte_order(data: &[u8]) -> io::Result<bool> {
+    match read_u32(data, false) {
+        PCAPNG_BYTE_ORDER_MAGIC => Ok(false),
+        m if m.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => Ok(true),
+        m => Err(invalid_data(format!(
+            "Invalid pcapng byte-order magic {:X}",
+            m
+        ))),
+    }
+}
+
+/// Read a block given its type using 'read' to read from the stream.
+/// The byte order of a Section Header Block is determined from the block
+/// itself, so 'big_endian' is updated when one is read.
+pub fn read_block<F>(block_type: u32, big_endian: &mut bool, mut read: F) -> io::Result<PcapNgBlock>
+where
+    F: FnMut(&mut [u8]) -> io::Result<()>,
+{
+    let mut len = [0u8; 4];
+    read(&mut len)?;
+    if block_type == PCAPNG_SHB {
+        let mut bom = [0u8; 4];
+        read(&mut bom)?;
+        *big_endian = section_byte_order(&bom)?;
+        let total_len = read_u32(&len, *big_endian);
+        let body = read_body(total_len, 4, &mut read, *big_endian)?;
+        let mut data = bom.to_vec();
+        data.extend_from_slice(&body);
+        return Ok(PcapNgBlock {
+            block_type,
+            body: data,
+        });
+    }
+    let total_len = read_u32(&len, *big_endian);
+    let body = read_body(total_len, 0, &mut read, *big_endian)?;
+    Ok(PcapNgBlock { block_type, body })
+}
+
+// Read the rest of a block after the first 8 bytes and 'consumed' bytes of
+// the body, and validate the trailing block total length.
+fn read_body<F>(
+    total_len: u32,
+    consumed: usize,
+    read: &mut F,
+    big_endian: bool,
+) -> io::Result<Vec<u8>>
+where
+    F: FnMut(&mut [u8]) -> io::Result<()>,
+{
+    if total_len < 12 + consumed as u32
+        || !total_len.is_multiple_of(4)
+        || total_len > PCAPNG_MAX_BLOCK_LEN
+    {
+        return Err(invalid_data(format!(
+            "Invalid pcapng block total length {}",
+            total_len
+        )));
+    }
+    let mut body = vec![0u8; total_len as usize - 12 - consumed];
+    read(&mut body)?;
+    let mut trailer = [0u8; 4];
+    read(&mut trailer)?;
+    if read_u32(&trailer, big_endian) != total_len {
+        return Err(invalid_data(format!(
+            "Mismatched pcapng block total length {}",
+            total_len
+        )));
+    }
+    Ok(body)
+}
+
+/// Parse the body of an Interface Description Block
+pub fn parse_interface(body: &[u8], big_endian: bool) -> io::Result<PcapNgInterface> {
+    if body.len() < 8 {
+        return Err(invalid_data(String::from(
+            "Invalid pcapng interface description block",
+        )));
+    }
+    let mut interface = PcapNgInterface {
+        linktype: read_u16(body, big_endian),
+        snaplen: read_u32(&body[4..], big_endian),
+        ts_units: 1_000_000,
+    };
+    // Look for the timestamp resolution among the options
+    let mut options = &body[8..];
+    while options.len() >= 4 {
+        let code = read_u16(options, big_endian);
+        let len = read_u16(&options[2..], big_endian) as usize;
+        if code == 0 || options.len() < 4 + len {
+            break;
+        }
+        if code == PCAPNG_IF_TSRESOL && len >= 1 {
+            // The most significant bit selects a power of 2 or of 10
+            let resol = options[4];
+            let exp = (resol & 0x7f) as u32;
+            interface.ts_units = if resol & 0x80 != 0 {
+                1u64.checked_shl(exp).unwrap_or(0)
+            } else {
+                10u64.checked_pow(exp).unwrap_or(0)
+            };
+            if interface.ts_units == 0 {
+                return Err(invalid_data(format!(
+                    "Unsupported pcapng timestamp resolution {:X}",
+                    resol
+                )));
+            }
+        }
+        options = &options[(4 + padded_len(len)).min(options.len())..];
+    }
+    Ok(interface)
+}
+
+/// Parse the body of an Enhanced Packet Block into a packet header with
+/// the timestamp in nanoseconds and the packet data.
+pub fn parse_enhanced_packet(
+    body: &[u8],
+    big_endian: bool,
+    interfaces: &[PcapNgInterface],
+) -> io::Result<(PcapPacketHeader, Vec<u8>)> {
+    if body.len() < 20 {
+        return Err(invalid_data(String::from(
+            "Invalid pcapng enhanced packet block",
+        )));
+    }
+    let id = read_u32(body, big_endian) as usize;
+    let interface = interfaces
+        .get(id)
+        .ok_or_else(|| invalid_data(format!("Invalid pcapng interface id {} in packet", id)))?;
+    let ts =
+        (read_u32(&body[4..], big_endian) as u64) << 32 | read_u32(&body[8..], big_endian) as u64;
+    let caplen = read_u32(&body[12..], big_endian);
+    let wirelen = read_u32(&body[16..], big_endian);
+    let data = &body[20..];
+    if caplen as usize > data.len() {
+        return Err(invalid_data(format!(
+            "Invalid caplen value {} exceeds the pcapng block",
+            caplen
+        )));
+    }
+    let (ts_sec, ts_nsec) = timestamp_ns(ts, interface.ts_units);
+    let header = PcapPacketHeader {
+        ts_sec,
+        ts_usec: ts_nsec,
+        caplen,
+        wirelen,
+    };
+    Ok((header, data[..caplen as usize].to_vec()))
+}
+
+/// The first comment among the options of an Enhanced Packet Block
+pub fn packet_comment(body: &[u8], big_endian: bool) -> Option<String> {
+    if body.len() < 20 {
+        return None;
+    }
+    let caplen = read_u32(&body[12..], big_endian) as usize;
+    let mut options = body.get(20 + padded_len(caplen)..)?;
+    while options.len() >= 4 {
+        let code = read_u16(options, big_endian);
+        let len = read_u16(&options[2..], big_endian) as usize;
+        if code == 0 || options.len() < 4 + len {
+            break;
+        }
+        if code == PCAPNG_OPT_COMMENT {
+            return Some(String::from_utf8_lossy(&options[4..4 + len]).into_owned());
+        }
+        options = &options[(4 + padded_len(len)).min(options.len())..];
+    }
+    None
+}
+
+/// Parse the body of a Simple Packet Block. It has no timestamp and
+/// belongs to the first interface.
+pub fn parse_simple_packet(
+    body: &[u8],
+    big_endian: bool,
+    interfaces: &[PcapNgInterface],
+) -> io::Result<(PcapPacketHeader, Vec<u8>)> {
+    if body.len() < 4 || interfaces.is_empty() {
+        return Err(invalid_data(String::from(
+            "Invalid pcapng simple packet block",
+        )));
+    }
+    let wirelen = read_u32(body, big_endian);
+    let data = &body[4..];
+    let mut caplen = wirelen.min(data.len() as u32);
+    if interfaces[0].snaplen != 0 {
+        caplen = caplen.min(interfaces[0].snaplen);
+    }
+    let header = PcapPacketHeader {
+        ts_sec: 0,
+        ts_usec: 0,
+        caplen,
+        wirelen,
+    };
+    Ok((header, data[..caplen as usize].to_vec()))
+}
+
+// Convert a timestamp in the given units per second to seconds and
+// nanoseconds
+fn timestamp_ns(ts: u64, units: u64) -> (u32, u32) {
+    let sec = ts / units;
+    let nsec = (ts % units) as u128 * 1_000_000_000 / units as u128;
+    (sec as u32, nsec as u32)
+}
+
+// Append a block with the given type and body padded to 32 bits
+fn write_block(bytes: &mut Vec<u8>, block_type: u32, body: &[u8]) {
+    let total_len = (12 + padded_len(body.len())) as u32;
+    bytes.extend_from_slice(&block_type.to_le_bytes());
+    bytes.extend_from_slice(&total_len.to_le_bytes());
+    bytes.extend_from_slice(body);
+    bytes.resize(bytes.len() + padded_len(body.len()) - body.len(), 0);
+    bytes.extend_from_slice(&total_len.to_le_bytes());
+}
+
+/// Serialize a Section Header Block followed by an Interface Description
+/// Block for a single interface. The timestamps of the interface are in
+/// nanoseconds if 'nanosec' is set, else in microseconds.
+pub fn section_header(linktype: u32, snaplen: u32, nanosec: bool) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut shb = Vec::new();
+    shb.extend_from_slice(&PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+    shb.extend_from_slice(&1u16.to_le_bytes()); // major version
+    shb.extend_from_slice(&0u16.to_le_bytes()); // minor version
+    shb.extend_from_slice(&(-1i64).to_le_bytes()); // section length unknown
+    write_block(&mut bytes, PCAPNG_SHB, &shb);
+
+    let mut idb = Vec::new();
+    idb.extend_from_slice(&(linktype as u16).to_le_bytes());
+    idb.extend_from_slice(&0u16.to_le_bytes());
+    idb.extend_from_slice(&snaplen.to_le_bytes());
+    // if_tsresol option followed by opt_endofopt
+    idb.extend_from_slice(&PCAPNG_IF_TSRESOL.to_le_bytes());
+    idb.extend_from_slice(&1u16.to_le_bytes());
+    idb.extend_from_slice(&[if nanosec { 9 } else { 6 }, 0, 0, 0]);
+    idb.extend_from_slice(&[0u8; 4]);
+    write_block(&mut bytes, PCAPNG_IDB, &idb);
+    bytes
+}
+
+/// Serialize a packet as an Enhanced Packet Block on the first interface.
+/// The sub-second part of the timestamp is in nanoseconds if 'nanosec'
+/// is set, else in microseconds. The comment is written as an option.
+pub fn enhanced_packet(
+    header: &PcapPacketHeader,
+    data: &[u8],
+    nanosec: bool,
+    comment: Option<&str>,
+) -> Vec<u8> {
+    let units: u64 = if nanosec { 1_000_000_000 } else { 1_000_000 };
+    let ts = header.ts_sec as u64 * units + header.ts_usec as u64;
+    let mut epb = Vec::with_capacity(20 + data.len());
+    epb.extend_from_slice(&0u32.to_le_bytes()); // interface id
+    epb.extend_from_slice(&((ts >> 32) as u32).to_le_bytes());
+    epb.extend_from_slice(&(ts as u32).to_le_bytes());
+    epb.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    epb.extend_from_slice(&header.wirelen.to_le_bytes());
+    epb.extend_from_slice(data);
+    if let Some(comment) = comment {
+        // opt_comment followed by opt_endofopt. The length of an option
+        // is 16 bits, so longer comments are truncated.
+        let mut end = comment.len().min(u16::MAX as usize);
+        while !comment.is_char_boundary(end) {
+            end -= 1;
+        }
+        let text = &comment.as_bytes()[..end];
+        epb.resize(padded_len(epb.len()), 0);
+        epb.extend_from_slice(&PCAPNG_OPT_COMMENT.to_le_bytes());
+        epb.extend_from_slice(&(text.len() as u16).to_le_bytes());
+        epb.extend_from_slice(text);
+        epb.resize(padded_len(epb.len()), 0);
+        epb.extend_from_slice(&[0u8; 4]);
+    }
+    let mut bytes = Vec::with_capacity(32 + data.len());
+    write_block(&mut bytes, PCAPNG_EPB, &epb);
+    bytes
+}
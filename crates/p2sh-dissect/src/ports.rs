@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::detect::{detect_proto, Transport};
+use crate::dns::DNS_PORT;
+use crate::tls::TLS_PORT;
+use crate::tunnel::{GENEVE_PORT, VXLAN_PORT};
+
+/// Application protocols decoded from the payload of udp and tcp packets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppProto {
+    Dns,
+    Tls,
+    Vxlan,
+    Geneve,
+}
+
+impl fmt::Display for AppProto {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AppProto::Dns => write!(f, "dns"),
+            AppProto::Tls => write!(f, "tls"),
+            AppProto::Vxlan => write!(f, "vxlan"),
+            AppProto::Geneve => write!(f, "geneve"),
+        }
+    }
+}
+
+impl FromStr for AppProto {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dns" => Ok(AppProto::Dns),
+            "tls" => Ok(AppProto::Tls),
+            "vxlan" => Ok(AppProto::Vxlan),
+            "geneve" => Ok(AppProto::Geneve),
+            _ => Err(format!("unknown protocol '{}'", s)),
+        }
+    }
+}
+
+/// Protocols that the payload on each port is decoded as when walking
+/// the layers of a packet. A payload on a port that is not in the map
+/// is not decoded unless heuristics are enabled, in which case its
+/// protocol is detected from its contents.
+#[derive(Debug, Clone)]
+pub struct PortMap {
+    // A port mapped to None is never decoded
+    ports: HashMap<u16, Option<AppProto>>,
+    heuristics: bool,
+}
+
+impl Default for PortMap {
+    fn default() -> Self {
+        let mut ports = HashMap::new();
+        ports.insert(DNS_PORT, Some(AppProto::Dns));
+        ports.insert(TLS_PORT, Some(AppProto::Tls));
+        ports.insert(VXLAN_PORT, Some(AppProto::Vxlan));
+        ports.insert(GENEVE_PORT, Some(AppProto::Geneve));
+        Self {
+            ports,
+            heuristics: false,
+        }
+    }
+}
+
+impl PortMap {
+    /// Decode the payload on 'port' as 'proto', or stop decoding it if
+    /// 'proto' is None
+    pub fn decode_as(&mut self, port: u16, proto: Option<AppProto>) {
+        self.ports.insert(port, proto);
+    }
+
+    /// Detect the protocol of the payload on ports that are not mapped
+    pub fn set_heuristics(&mut self, enabled: bool) {
+        self.heuristics = enabled;
+    }
+
+    /// Protocol of the payload between the ports. The destination port
+    /// takes precedence over the source port.
+    pub fn lookup(&self, src: u16, dst: u16) -> Option<AppProto> {
+        self.mapping(src, dst).flatten()
+    }
+
+    /// Protocol of the payload between the ports, detected from the
+    /// payload if neither port is mapped and heuristics are enabled
+    pub fn resolve(
+        &self,
+        src: u16,
+        dst: u16,
+        payload: &[u8],
+        transport: Transport,
+    ) -> Option<AppProto> {
+        match self.mapping(src, dst) {
+            Some(proto) => proto,
+            None if self.heuristics => detect_proto(payload, transport),
+            None => None,
+        }
+    }
+
+    fn mapping(&self, src: u16, dst: u16) -> Option<Option<AppProto>> {
+        self.ports
+            .get(&dst)
+            .or_else(|| self.ports.get(&src))
+            .copied()
+    }
+}
+
+/// Parse a protocol name given to 'decode_as'. The name 'none' stops
+/// decoding the payload on a port.
+pub fn parse_app_proto(s: &str) -> Result<Option<AppProto>, String> {
+    match s {
+        "none" => Ok(None),
+        _ => s.parse().map(Some),
+    }
+}
+
+/// Parse a port mapping such as '5353=dns'
+pub fn parse_port_mapping(s: &str) -> Result<(u16, Option<AppProto>), String> {
+    let (port, proto) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected PORT=PROTO, got '{}'", s))?;
+    let port = port
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid port '{}'", port))?;
+    Ok((port, parse_app_proto(proto.trim())?))
+}
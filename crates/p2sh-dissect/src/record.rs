@@ -0,0 +1,73 @@
+//! The headers of the packet records of pcap streams
+
+use std::fmt;
+use std::io;
+
+// Read an unsigned 32 bit integer in the given byte order
+pub fn read_u32(data: &[u8], big_endian: bool) -> u32 {
+    let bytes = [data[0], data[1], data[2], data[3]];
+    if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    }
+}
+
+// Read an unsigned 16 bit integer in the given byte order
+pub fn read_u16(data: &[u8], big_endian: bool) -> u16 {
+    let bytes = [data[0], data[1]];
+    if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PcapPacketHeader {
+    pub ts_sec: u32,  // Timestamp seconds
+    pub ts_usec: u32, // Timestamp in nanoseconds or microseconds
+    pub caplen: u32,  // Length of portion present
+    pub wirelen: u32, // Length of the packet (off wire)
+}
+
+impl fmt::Display for PcapPacketHeader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "<pcap-packet caplen: {}, wirelen: {}>",
+            self.caplen, self.wirelen
+        )
+    }
+}
+
+impl From<&PcapPacketHeader> for Vec<u8> {
+    fn from(header: &PcapPacketHeader) -> Self {
+        let mut bytes = Vec::new();
+        // Convert each field to its byte representation and append to the bytes Vec
+        bytes.extend_from_slice(&header.ts_sec.to_le_bytes());
+        bytes.extend_from_slice(&header.ts_usec.to_le_bytes());
+        bytes.extend_from_slice(&header.caplen.to_le_bytes());
+        bytes.extend_from_slice(&header.wirelen.to_le_bytes());
+        bytes
+    }
+}
+
+impl PcapPacketHeader {
+    // Create a new instance of PcapPacketHeader from a byte slice
+    // that is in the byte order of the pcap stream
+    pub fn from_bytes(data: &[u8], big_endian: bool) -> io::Result<Self> {
+        if data.len() < 16 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid pcap packet header size",
+            ));
+        }
+        Ok(Self {
+            ts_sec: read_u32(data, big_endian),
+            ts_usec: read_u32(&data[4..], big_endian),
+            caplen: read_u32(&data[8..], big_endian),
+            wirelen: read_u32(&data[12..], big_endian),
+        })
+    }
+}
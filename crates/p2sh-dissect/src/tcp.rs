@@ -0,0 +1,297 @@
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+
+use crate::checksum::is_auto;
+use crate::error::PacketError;
+use crate::value::Value;
+
+// Bits of the tcp flags
+pub const TCP_FIN: u16 = 0x01;
+pub const TCP_SYN: u16 = 0x02;
+pub const TCP_RST: u16 = 0x04;
+pub const TCP_PSH: u16 = 0x08;
+pub const TCP_ACK: u16 = 0x10;
+pub const TCP_URG: u16 = 0x20;
+pub const TCP_ECE: u16 = 0x40;
+pub const TCP_CWR: u16 = 0x80;
+
+#[derive(Debug, Clone)]
+pub struct TcpHeader {
+    srcport: u16,     // Source port number
+    dstport: u16,     // Destination port number
+    sequence: u32,    // Sequence number
+    ack: u32,         // Acknowledgment number
+    data_off: u8,     // Data offset
+    flags: u16,       // Flags for TCP
+    window_size: u16, // Window size
+    checksum: u16,    // Checksum for integrity
+    urgent: u16,      // Urgent pointer
+}
+
+impl From<&TcpHeader> for Vec<u8> {
+    fn from(hdr: &TcpHeader) -> Self {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&hdr.srcport.to_be_bytes());
+        bytes.extend_from_slice(&hdr.dstport.to_be_bytes());
+        bytes.extend_from_slice(&hdr.sequence.to_be_bytes());
+        bytes.extend_from_slice(&hdr.ack.to_be_bytes());
+        bytes.extend_from_slice(&hdr.flags.to_be_bytes());
+        bytes.extend_from_slice(&hdr.window_size.to_be_bytes());
+        bytes.extend_from_slice(&hdr.checksum.to_be_bytes());
+        bytes.extend_from_slice(&hdr.urgent.to_be_bytes());
+        bytes
+    }
+}
+
+#[derive(Debug)]
+pub struct Tcp<V> {
+    header: RefCell<TcpHeader>,        // Header of the TCP packet
+    pub rawdata: RefCell<Rc<Vec<u8>>>, // Raw data of the entire packet
+    pub offset: usize,                 // Offset of the TCP header
+    pub inner: RefCell<Option<Rc<V>>>, // Inner packet
+    auto_checksum: Cell<bool>,         // Compute the checksum when serialized
+}
+
+pub const TCP_HEADER_SIZE: usize = 20; // Basic TCP header size
+
+impl<V: Value> fmt::Display for Tcp<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "<port:{}:{} seq:{} ack:{}>",
+            self.header.borrow().srcport,
+            self.header.borrow().dstport,
+            self.header.borrow().sequence,
+            self.header.borrow().ack
+        )
+    }
+}
+
+impl<V: Value> From<&Tcp<V>> for Vec<u8> {
+    fn from(tcp: &Tcp<V>) -> Self {
+        let header = tcp.header.borrow().clone();
+        let mut bytes: Vec<u8> = (&header).into();
+        let data = tcp.rawdata.borrow().clone();
+        bytes.extend_from_slice(&data[tcp.offset..]);
+        bytes
+    }
+}
+
+impl<V: Value> Tcp<V> {
+    pub fn from_bytes(rawdata: Rc<Vec<u8>>, off: usize) -> Result<Self, PacketError> {
+        if rawdata.len() < off + TCP_HEADER_SIZE {
+            return Err(PacketError::InvalidLength(rawdata.len()));
+        }
+        let srcport = u16::from_be_bytes([rawdata[off], rawdata[off + 1]]);
+        let dstport = u16::from_be_bytes([rawdata[off + 2], rawdata[off + 3]]);
+        let sequence = u32::from_be_bytes([
+            rawdata[off + 4],
+            rawdata[off + 5],
+            rawdata[off + 6],
+            rawdata[off + 7],
+        ]);
+        let ack = u32::from_be_bytes([
+            rawdata[off + 8],
+            rawdata[off + 9],
+            rawdata[off + 10],
+            rawdata[off + 11],
+        ]);
+        let data_off = rawdata[off + 12] >> 4;
+        let flags = u16::from_be_bytes([rawdata[off + 12], rawdata[off + 13]]);
+        let window_size = u16::from_be_bytes([rawdata[off + 14], rawdata[off + 15]]);
+        let checksum = u16::from_be_bytes([rawdata[off + 16], rawdata[off + 17]]);
+        let urgent = u16::from_be_bytes([rawdata[off + 18], rawdata[off + 19]]);
+
+        let header = RefCell::new(TcpHeader {
+            srcport,
+            dstport,
+            sequence,
+            ack,
+            data_off,
+            flags,
+            window_size,
+            checksum,
+            urgent,
+        });
+
+        Ok(Self {
+            header,
+            rawdata: RefCell::new(rawdata),
+            offset: off + TCP_HEADER_SIZE,
+            inner: RefCell::new(None),
+            auto_checksum: Cell::new(false),
+        })
+    }
+
+    pub fn get_source_port_raw(&self) -> u16 {
+        self.header.borrow().srcport
+    }
+
+    pub fn get_destination_port_raw(&self) -> u16 {
+        self.header.borrow().dstport
+    }
+
+    /// Offset of the payload after the header including the options
+    pub fn get_payload_offset_raw(&self) -> usize {
+        let data_off = self.header.borrow().data_off as usize;
+        self.offset - TCP_HEADER_SIZE + (data_off * 4).max(TCP_HEADER_SIZE)
+    }
+
+    pub fn get_source_port(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().srcport as i64))
+    }
+
+    pub fn get_destination_port(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().dstport as i64))
+    }
+
+    pub fn get_sequence_raw(&self) -> u32 {
+        self.header.borrow().sequence
+    }
+
+    pub fn get_flags_raw(&self) -> u16 {
+        self.header.borrow().flags
+    }
+
+    pub fn get_sequence(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().sequence as i64))
+    }
+
+    pub fn get_ack(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().ack as i64))
+    }
+
+    pub fn get_data_off(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().data_off as i64))
+    }
+
+    pub fn get_flags(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().flags as i64))
+    }
+
+    /// Check if the flag 'bit' is set
+    pub fn get_flag(&self, bit: u16) -> Rc<V> {
+        Rc::new(V::boolean(self.header.borrow().flags & bit != 0))
+    }
+
+    pub fn get_window_size(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().window_size as i64))
+    }
+
+    pub fn get_checksum(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().checksum as i64))
+    }
+
+    pub fn get_urgent(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().urgent as i64))
+    }
+
+    pub fn set_source_port(&self, port: Rc<V>) -> Result<(), String> {
+        match port.as_integer() {
+            Some(port_value) => {
+                self.header.borrow_mut().srcport = port_value as u16;
+                Ok(())
+            }
+            _ => Err("Invalid value for source port".to_string()),
+        }
+    }
+
+    pub fn set_destination_port(&self, port: Rc<V>) -> Result<(), String> {
+        match port.as_integer() {
+            Some(port_value) => {
+                self.header.borrow_mut().dstport = port_value as u16;
+                Ok(())
+            }
+            _ => Err("Invalid value for destination port".to_string()),
+        }
+    }
+
+    pub fn set_sequence(&self, sequence: Rc<V>) -> Result<(), String> {
+        match sequence.as_integer() {
+            Some(seq) => {
+                self.header.borrow_mut().sequence = seq as u32;
+                Ok(())
+            }
+            _ => Err("Invalid value for sequence number".to_string()),
+        }
+    }
+
+    pub fn set_ack(&self, ack: Rc<V>) -> Result<(), String> {
+        match ack.as_integer() {
+            Some(ack) => {
+                self.header.borrow_mut().ack = ack as u32;
+                Ok(())
+            }
+            _ => Err("Invalid value for acknowledgment number".to_string()),
+        }
+    }
+
+    pub fn set_data_off(&self, data_off: Rc<V>) -> Result<(), String> {
+        match data_off.as_integer() {
+            Some(data_off_value) => {
+                self.header.borrow_mut().data_off = data_off_value as u8;
+                Ok(())
+            }
+            _ => Err("Invalid value for data offset".to_string()),
+        }
+    }
+
+    pub fn set_flags(&self, flags: Rc<V>) -> Result<(), String> {
+        match flags.as_integer() {
+            Some(flags_value) => {
+                self.header.borrow_mut().flags = flags_value as u16;
+                Ok(())
+            }
+            _ => Err("Invalid value for flags".to_string()),
+        }
+    }
+
+    /// Set or clear the flag 'bit'
+    pub fn set_flag(&self, bit: u16, value: Rc<V>) -> Result<(), String> {
+        let mut header = self.header.borrow_mut();
+        match value.as_bool() {
+            Some(true) => header.flags |= bit,
+            Some(false) => header.flags &= !bit,
+            _ => return Err("Invalid value for tcp flag".to_string()),
+        }
+        Ok(())
+    }
+
+    pub fn set_window_size(&self, window_size: Rc<V>) -> Result<(), String> {
+        match window_size.as_integer() {
+            Some(size) => {
+                self.header.borrow_mut().window_size = size as u16;
+                Ok(())
+            }
+            _ => Err("Invalid value for window size".to_string()),
+        }
+    }
+
+    /// Set if the checksum is computed by the ip layer when serialized
+    pub fn is_auto_checksum(&self) -> bool {
+        self.auto_checksum.get()
+    }
+
+    pub fn set_checksum(&self, checksum: Rc<V>) -> Result<(), String> {
+        self.auto_checksum.set(is_auto(checksum.as_ref()));
+        match checksum.as_integer() {
+            _ if self.auto_checksum.get() => Ok(()),
+            Some(checksum_value) => {
+                self.header.borrow_mut().checksum = checksum_value as u16;
+                Ok(())
+            }
+            _ => Err("Invalid value for checksum".to_string()),
+        }
+    }
+
+    pub fn set_urgent(&self, urgent: Rc<V>) -> Result<(), String> {
+        match urgent.as_integer() {
+            Some(urgent_value) => {
+                self.header.borrow_mut().urgent = urgent_value as u16;
+                Ok(())
+            }
+            _ => Err("Invalid value for urgent pointer".to_string()),
+        }
+    }
+}
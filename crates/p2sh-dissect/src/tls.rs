@@ -0,0 +1,354 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::error::PacketError;
+use crate::value::Value;
+
+pub const TLS_RECORD_HEADER_SIZE: usize = 5;
+pub const TLS_PORT: u16 = 443;
+
+// Size of the handshake message header preceding the client hello
+const HANDSHAKE_HEADER_SIZE: usize = 4;
+// Size of the random bytes in the client hello
+const RANDOM_SIZE: usize = 32;
+
+#[allow(non_snake_case)]
+pub mod ContentTypes {
+    pub const CHANGE_CIPHER_SPEC: u8 = 20;
+    pub const ALERT: u8 = 21;
+    pub const HANDSHAKE: u8 = 22;
+    pub const APPLICATION_DATA: u8 = 23;
+}
+
+#[allow(non_snake_case)]
+pub mod HandshakeTypes {
+    pub const CLIENT_HELLO: u8 = 1;
+    pub const SERVER_HELLO: u8 = 2;
+}
+
+#[allow(non_snake_case)]
+mod ExtensionTypes {
+    pub const SERVER_NAME: u16 = 0;
+    pub const SUPPORTED_GROUPS: u16 = 10;
+    pub const EC_POINT_FORMATS: u16 = 11;
+    pub const ALPN: u16 = 16;
+}
+
+/// Fields of a ClientHello message used to identify the server and
+/// to fingerprint the client
+#[derive(Debug, Clone, Default)]
+pub struct ClientHello {
+    pub version: u16,
+    pub ciphers: Vec<u16>,
+    pub extensions: Vec<u16>,
+    pub sni: Option<String>,
+    pub alpn: Vec<String>,
+    pub groups: Vec<u16>,
+    pub point_formats: Vec<u8>,
+}
+
+/// The first TLS record in a TCP segment. Only the ClientHello message is
+/// decoded from the handshake records. The record is decoded when the
+/// object is created and is read only.
+#[derive(Debug)]
+pub struct Tls {
+    content_type: u8,
+    version: u16,
+    handshake: Option<u8>,
+    hello: Option<ClientHello>,
+    pub rawdata: Rc<Vec<u8>>, // Raw data of the entire packet
+    pub offset: usize,        // Offset of the TLS record
+}
+
+impl fmt::Display for Tls {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.hello {
+            Some(hello) => write!(
+                f,
+                "<client-hello sni:{} ciphers:{}>",
+                hello.sni.as_deref().unwrap_or(""),
+                hello.ciphers.len()
+            ),
+            None => write!(
+                f,
+                "<tls type:{} version:{}>",
+                self.content_type, self.version
+            ),
+        }
+    }
+}
+
+impl From<&Tls> for Vec<u8> {
+    fn from(tls: &Tls) -> Self {
+        tls.rawdata[tls.offset..].to_vec()
+    }
+}
+
+impl Tls {
+    /// Decode the TLS record at the offset 'off' of the raw data
+    pub fn from_bytes(rawdata: Rc<Vec<u8>>, off: usize) -> Result<Self, PacketError> {
+        if rawdata.len() < off + TLS_RECORD_HEADER_SIZE {
+            return Err(PacketError::InvalidLength(rawdata.len()));
+        }
+        let msg = &rawdata[off..];
+        let content_type = msg[0];
+        let version = u16::from_be_bytes([msg[1], msg[2]]);
+        let length = u16::from_be_bytes([msg[3], msg[4]]) as usize;
+        // The record may continue in the next segments
+        let end = (TLS_RECORD_HEADER_SIZE + length).min(msg.len());
+        let body = &msg[TLS_RECORD_HEADER_SIZE..end];
+
+        let mut handshake = None;
+        let mut hello = None;
+        if content_type == ContentTypes::HANDSHAKE && !body.is_empty() {
+            handshake = Some(body[0]);
+            if body[0] == HandshakeTypes::CLIENT_HELLO {
+                let start = HANDSHAKE_HEADER_SIZE.min(body.len());
+                hello = Some(read_client_hello(&body[start..])?);
+            }
+        }
+        Ok(Self {
+            content_type,
+            version,
+            handshake,
+            hello,
+            rawdata,
+            offset: off,
+        })
+    }
+
+    pub fn get_content_type<V: Value>(&self) -> Rc<V> {
+        Rc::new(V::integer(self.content_type as i64))
+    }
+
+    /// Version in the client hello, or else the version of the record
+    pub fn get_version<V: Value>(&self) -> Rc<V> {
+        let version = self.hello.as_ref().map_or(self.version, |h| h.version);
+        Rc::new(V::integer(version as i64))
+    }
+
+    /// Type of the handshake message, or null for other records
+    pub fn get_handshake<V: Value>(&self) -> Rc<V> {
+        match self.handshake {
+            Some(t) => Rc::new(V::integer(t as i64)),
+            None => Rc::new(V::null()),
+        }
+    }
+
+    /// Server name indication, or null if absent
+    pub fn get_sni<V: Value>(&self) -> Rc<V> {
+        match self.hello.as_ref().and_then(|h| h.sni.as_ref()) {
+            Some(sni) => Rc::new(V::string(sni.clone())),
+            None => Rc::new(V::null()),
+        }
+    }
+
+    /// Protocols offered using ALPN as an array of strings
+    pub fn get_alpn<V: Value>(&self) -> Rc<V> {
+        match &self.hello {
+            Some(hello) => {
+                let elements = hello
+                    .alpn
+                    .iter()
+                    .map(|p| Rc::new(V::string(p.clone())))
+                    .collect();
+                Rc::new(V::array(elements))
+            }
+            None => Rc::new(V::null()),
+        }
+    }
+
+    /// Cipher suites offered as an array of integers
+    pub fn get_ciphers<V: Value>(&self) -> Rc<V> {
+        match &self.hello {
+            Some(hello) => {
+                let elements = hello
+                    .ciphers
+                    .iter()
+                    .map(|c| Rc::new(V::integer(*c as i64)))
+                    .collect();
+                Rc::new(V::array(elements))
+            }
+            None => Rc::new(V::null()),
+        }
+    }
+
+    /// JA3 fingerprint of the client hello as a hex string, or null for
+    /// other records
+    pub fn get_ja3<V: Value>(&self) -> Rc<V> {
+        match &self.hello {
+            Some(hello) => {
+                let digest = md5(ja3_string(hello).as_bytes());
+                let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+                Rc::new(V::string(hex))
+            }
+            None => Rc::new(V::null()),
+        }
+    }
+}
+
+/// The fields of the client hello that make up the JA3 fingerprint,
+/// each a list of decimal values separated by '-', and the fields
+/// separated by ','. GREASE values are left out.
+pub fn ja3_string(hello: &ClientHello) -> String {
+    let join = |values: &mut dyn Iterator<Item = u16>| {
+        values
+            .filter(|v| !is_grease(*v))
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join("-")
+    };
+    format!(
+        "{},{},{},{},{}",
+        hello.version,
+        join(&mut hello.ciphers.iter().copied()),
+        join(&mut hello.extensions.iter().copied()),
+        join(&mut hello.groups.iter().copied()),
+        join(&mut hello.point_formats.iter().map(|p| *p as u16)),
+    )
+}
+
+// GREASE values (RFC 8701) are of the form 0x?a?a with equal bytes
+fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
+fn read_bytes<'a>(msg: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8], PacketError> {
+    if msg.len() < *pos + len {
+        return Err(PacketError::InvalidLength(msg.len()));
+    }
+    let bytes = &msg[*pos..*pos + len];
+    *pos += len;
+    Ok(bytes)
+}
+
+fn read_u8(msg: &[u8], pos: &mut usize) -> Result<u8, PacketError> {
+    Ok(read_bytes(msg, pos, 1)?[0])
+}
+
+fn read_u16(msg: &[u8], pos: &mut usize) -> Result<u16, PacketError> {
+    let bytes = read_bytes(msg, pos, 2)?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u16_list(data: &[u8]) -> Vec<u16> {
+    data.chunks_exact(2)
+        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+        .collect()
+}
+
+fn read_client_hello(msg: &[u8]) -> Result<ClientHello, PacketError> {
+    let mut pos = 0;
+    let mut hello = ClientHello {
+        version: read_u16(msg, &mut pos)?,
+        ..Default::default()
+    };
+    read_bytes(msg, &mut pos, RANDOM_SIZE)?;
+    let len = read_u8(msg, &mut pos)? as usize;
+    read_bytes(msg, &mut pos, len)?;
+    let len = read_u16(msg, &mut pos)? as usize;
+    hello.ciphers = read_u16_list(read_bytes(msg, &mut pos, len)?);
+    let len = read_u8(msg, &mut pos)? as usize;
+    read_bytes(msg, &mut pos, len)?;
+    // Extensions are optional
+    if pos == msg.len() {
+        return Ok(hello);
+    }
+    let len = read_u16(msg, &mut pos)? as usize;
+    let extensions = read_bytes(msg, &mut pos, len)?;
+    let mut pos = 0;
+    while pos < extensions.len() {
+        let ext_type = read_u16(extensions, &mut pos)?;
+        let len = read_u16(extensions, &mut pos)? as usize;
+        let data = read_bytes(extensions, &mut pos, len)?;
+        hello.extensions.push(ext_type);
+        match ext_type {
+            ExtensionTypes::SERVER_NAME => hello.sni = read_server_name(data)?,
+            ExtensionTypes::ALPN => hello.alpn = read_alpn(data)?,
+            ExtensionTypes::SUPPORTED_GROUPS if data.len() >= 2 => {
+                hello.groups = read_u16_list(&data[2..]);
+            }
+            ExtensionTypes::EC_POINT_FORMATS if !data.is_empty() => {
+                hello.point_formats = data[1..].to_vec();
+            }
+            _ => {}
+        }
+    }
+    Ok(hello)
+}
+
+// The first host name in the server name list
+fn read_server_name(data: &[u8]) -> Result<Option<String>, PacketError> {
+    let mut pos = 2;
+    while pos < data.len() {
+        let name_type = read_u8(data, &mut pos)?;
+        let len = read_u16(data, &mut pos)? as usize;
+        let name = read_bytes(data, &mut pos, len)?;
+        if name_type == 0 {
+            return Ok(Some(String::from_utf8_lossy(name).into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+fn read_alpn(data: &[u8]) -> Result<Vec<String>, PacketError> {
+    let mut protocols = Vec::new();
+    let mut pos = 2;
+    while pos < data.len() {
+        let len = read_u8(data, &mut pos)? as usize;
+        let proto = read_bytes(data, &mut pos, len)?;
+        protocols.push(String::from_utf8_lossy(proto).into_owned());
+    }
+    Ok(protocols)
+}
+
+// MD5 digest (RFC 1321) used by the JA3 fingerprint
+fn md5(data: &[u8]) -> [u8; 16] {
+    const S: [u32; 64] = [
+        7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5,
+        9, 14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10,
+        15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+    ];
+    let k: Vec<u32> = (0..64)
+        .map(|i| ((i as f64 + 1.0).sin().abs() * 4294967296.0) as u32)
+        .collect();
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    for chunk in msg.chunks_exact(64) {
+        let m: Vec<u32> = chunk
+            .chunks_exact(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f.wrapping_add(a).wrapping_add(k[i]).wrapping_add(m[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(S[i]));
+        }
+        state[0] = state[0].wrapping_add(a);
+        state[1] = state[1].wrapping_add(b);
+        state[2] = state[2].wrapping_add(c);
+        state[3] = state[3].wrapping_add(d);
+    }
+
+    let mut digest = [0u8; 16];
+    for (i, word) in state.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
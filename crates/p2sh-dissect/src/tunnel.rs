@@ -0,0 +1,228 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::error::PacketError;
+use crate::value::Value;
+
+pub const VXLAN_PORT: u16 = 4789;
+pub const GENEVE_PORT: u16 = 6081;
+
+pub const GRE_HEADER_SIZE: usize = 4;
+pub const VXLAN_HEADER_SIZE: usize = 8;
+pub const GENEVE_HEADER_SIZE: usize = 8;
+
+/// Protocol type of the ethernet frames carried by GRE and GENEVE
+pub const TRANSPARENT_ETHERNET: u16 = 0x6558;
+
+// Flags of the GRE header indicating the optional fields present
+const GRE_CHECKSUM: u16 = 0x8000;
+const GRE_KEY: u16 = 0x2000;
+const GRE_SEQUENCE: u16 = 0x1000;
+// The VNI of a VXLAN header is valid only if this flag is set
+const VXLAN_VALID_VNI: u8 = 0x08;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TunnelKind {
+    Gre,
+    Vxlan,
+    Geneve,
+}
+
+impl fmt::Display for TunnelKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TunnelKind::Gre => write!(f, "gre"),
+            TunnelKind::Vxlan => write!(f, "vxlan"),
+            TunnelKind::Geneve => write!(f, "geneve"),
+        }
+    }
+}
+
+/// A tunnel header that encapsulates another packet. The encapsulated
+/// packet is either an ethernet frame or an ip packet as indicated by the
+/// protocol type, and is parsed only when referred to. The tunnel header
+/// is read only.
+#[derive(Debug)]
+pub struct Tunnel<V> {
+    kind: TunnelKind,
+    protocol: u16,                     // EtherType of the encapsulated packet
+    vni: Option<u32>,                  // Virtual network identifier
+    key: Option<u32>,                  // Key of a GRE header
+    start: usize,                      // Offset of the tunnel header
+    pub rawdata: RefCell<Rc<Vec<u8>>>, // Raw data of the entire packet
+    pub offset: usize,                 // Offset of the encapsulated packet
+    pub inner: RefCell<Option<Rc<V>>>, // Encapsulated packet
+}
+
+impl<V: Value> fmt::Display for Tunnel<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{}", self.kind)?;
+        if let Some(vni) = self.vni {
+            write!(f, " vni:{}", vni)?;
+        }
+        if let Some(key) = self.key {
+            write!(f, " key:{}", key)?;
+        }
+        write!(f, ">")?;
+        if let Some(inner) = self.inner.borrow().clone() {
+            write!(f, " {}", inner)
+        } else {
+            write!(f, " [len: {}]", self.rawdata.borrow().len() - self.offset)
+        }
+    }
+}
+
+impl<V: Value> From<&Tunnel<V>> for Vec<u8> {
+    fn from(tunnel: &Tunnel<V>) -> Self {
+        let data = tunnel.rawdata.borrow().clone();
+        let mut bytes = data[tunnel.start..tunnel.offset].to_vec();
+        if let Some(inner) = tunnel.inner.borrow().clone() {
+            let inner = inner.to_bytes();
+            bytes.extend_from_slice(&inner);
+        } else {
+            bytes.extend_from_slice(&data[tunnel.offset..]);
+        }
+        bytes
+    }
+}
+
+impl<V: Value> Tunnel<V> {
+    /// Decode the GRE header at the offset 'off' of the raw data
+    pub fn gre_from_bytes(rawdata: Rc<Vec<u8>>, off: usize) -> Result<Self, PacketError> {
+        if rawdata.len() < off + GRE_HEADER_SIZE {
+            return Err(PacketError::InvalidLength(rawdata.len()));
+        }
+        let flags = u16::from_be_bytes([rawdata[off], rawdata[off + 1]]);
+        let protocol = u16::from_be_bytes([rawdata[off + 2], rawdata[off + 3]]);
+        let mut offset = off + GRE_HEADER_SIZE;
+        // The checksum is followed by a reserved field of 16 bits
+        if flags & GRE_CHECKSUM != 0 {
+            offset += 4;
+        }
+        let mut key = None;
+        if flags & GRE_KEY != 0 {
+            if rawdata.len() < offset + 4 {
+                return Err(PacketError::InvalidLength(rawdata.len()));
+            }
+            key = Some(read_u32(&rawdata[offset..]));
+            offset += 4;
+        }
+        if flags & GRE_SEQUENCE != 0 {
+            offset += 4;
+        }
+        if rawdata.len() < offset {
+            return Err(PacketError::InvalidLength(rawdata.len()));
+        }
+        Ok(Self::new(
+            TunnelKind::Gre,
+            protocol,
+            None,
+            key,
+            rawdata,
+            off,
+            offset,
+        ))
+    }
+
+    /// Decode the VXLAN header at the offset 'off' of the raw data
+    pub fn vxlan_from_bytes(rawdata: Rc<Vec<u8>>, off: usize) -> Result<Self, PacketError> {
+        if rawdata.len() < off + VXLAN_HEADER_SIZE {
+            return Err(PacketError::InvalidLength(rawdata.len()));
+        }
+        let vni = if rawdata[off] & VXLAN_VALID_VNI != 0 {
+            Some(read_u32(&rawdata[off + 4..]) >> 8)
+        } else {
+            None
+        };
+        let offset = off + VXLAN_HEADER_SIZE;
+        Ok(Self::new(
+            TunnelKind::Vxlan,
+            TRANSPARENT_ETHERNET,
+            vni,
+            None,
+            rawdata,
+            off,
+            offset,
+        ))
+    }
+
+    /// Decode the GENEVE header at the offset 'off' of the raw data
+    pub fn geneve_from_bytes(rawdata: Rc<Vec<u8>>, off: usize) -> Result<Self, PacketError> {
+        if rawdata.len() < off + GENEVE_HEADER_SIZE {
+            return Err(PacketError::InvalidLength(rawdata.len()));
+        }
+        // The options follow the fixed header and their length is in
+        // multiples of 4 bytes
+        let options_len = (rawdata[off] & 0x3f) as usize * 4;
+        let protocol = u16::from_be_bytes([rawdata[off + 2], rawdata[off + 3]]);
+        let vni = read_u32(&rawdata[off + 4..]) >> 8;
+        let offset = off + GENEVE_HEADER_SIZE + options_len;
+        if rawdata.len() < offset {
+            return Err(PacketError::InvalidLength(rawdata.len()));
+        }
+        Ok(Self::new(
+            TunnelKind::Geneve,
+            protocol,
+            Some(vni),
+            None,
+            rawdata,
+            off,
+            offset,
+        ))
+    }
+
+    fn new(
+        kind: TunnelKind,
+        protocol: u16,
+        vni: Option<u32>,
+        key: Option<u32>,
+        rawdata: Rc<Vec<u8>>,
+        start: usize,
+        offset: usize,
+    ) -> Self {
+        // Do not parse the inner packet yet. Parse it only when referred to.
+        Self {
+            kind,
+            protocol,
+            vni,
+            key,
+            start,
+            rawdata: RefCell::new(rawdata),
+            offset,
+            inner: RefCell::new(None),
+        }
+    }
+
+    pub fn get_kind(&self) -> TunnelKind {
+        self.kind
+    }
+
+    pub fn get_protocol_raw(&self) -> u16 {
+        self.protocol
+    }
+
+    pub fn get_protocol(&self) -> Rc<V> {
+        Rc::new(V::integer(self.protocol as i64))
+    }
+
+    /// Virtual network identifier, or null if absent
+    pub fn get_vni(&self) -> Rc<V> {
+        match self.vni {
+            Some(vni) => Rc::new(V::integer(vni as i64)),
+            None => Rc::new(V::null()),
+        }
+    }
+
+    /// Key of a GRE header, or null if absent
+    pub fn get_key(&self) -> Rc<V> {
+        match self.key {
+            Some(key) => Rc::new(V::integer(key as i64)),
+            None => Rc::new(V::null()),
+        }
+    }
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+    u32::from_be_bytes([data[0], data[1], data[2], data[3]])
+}
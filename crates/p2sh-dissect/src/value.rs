@@ -0,0 +1,31 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::addr::Addr;
+use crate::tcp::Tcp;
+use crate::udp::Udp;
+
+/// Values of the language that embeds the protocol layers. A layer holds
+/// the packet it encapsulates as a value, returns its properties as values
+/// and reads the values assigned to them.
+pub trait Value: fmt::Display + Sized {
+    fn null() -> Self;
+    fn integer(value: i64) -> Self;
+    fn boolean(value: bool) -> Self;
+    fn string(value: String) -> Self;
+    fn bytes(value: Vec<u8>) -> Self;
+    fn addr(value: Addr) -> Self;
+    fn array(elements: Vec<Rc<Self>>) -> Self;
+    /// A map from the names of the fields to their values
+    fn record(fields: Vec<(&str, Self)>) -> Self;
+
+    fn as_integer(&self) -> Option<i64>;
+    fn as_bool(&self) -> Option<bool>;
+    fn as_str(&self) -> Option<&str>;
+    fn as_addr(&self) -> Option<&Addr>;
+    fn as_tcp(&self) -> Option<&Tcp<Self>>;
+    fn as_udp(&self) -> Option<&Udp<Self>>;
+
+    /// Serialize the value when it is written as the payload of a layer
+    fn to_bytes(&self) -> Vec<u8>;
+}
@@ -2,9 +2,9 @@ use std::cell::RefCell;
 use std::fmt;
 use std::rc::Rc;
 
-use super::error::PacketError;
-use super::ethernet::EtherType;
-use crate::object::Object;
+use crate::error::PacketError;
+use crate::ethernet::EtherType;
+use crate::value::Value;
 
 #[derive(Debug, Clone)]
 pub struct VlanHeader {
@@ -28,11 +28,11 @@ impl From<&VlanHeader> for Vec<u8> {
 }
 
 #[derive(Debug, Clone)]
-pub struct Vlan {
+pub struct Vlan<V> {
     header: RefCell<VlanHeader>,
     pub rawdata: RefCell<Rc<Vec<u8>>>, // Raw data of the entire packet
     pub offset: usize,                 // Offset of the VLAN header
-    pub inner: RefCell<Option<Rc<Object>>>, // Inner packet
+    pub inner: RefCell<Option<Rc<V>>>, // Inner packet
 }
 
 pub const VLAN_HEADER_SIZE: usize = 4;
@@ -66,7 +66,7 @@ impl From<ClassOfService> for u8 {
     }
 }
 
-impl fmt::Display for Vlan {
+impl<V: Value> fmt::Display for Vlan<V> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
@@ -82,12 +82,12 @@ impl fmt::Display for Vlan {
     }
 }
 
-impl From<&Vlan> for Vec<u8> {
-    fn from(vlan: &Vlan) -> Self {
+impl<V: Value> From<&Vlan<V>> for Vec<u8> {
+    fn from(vlan: &Vlan<V>) -> Self {
         let header = vlan.header.borrow().clone();
         let mut bytes: Vec<u8> = (&header).into();
         if let Some(inner) = vlan.inner.borrow().clone() {
-            let b: Vec<u8> = inner.as_ref().into();
+            let b = inner.to_bytes();
             bytes.extend_from_slice(&b);
         } else {
             let data = vlan.rawdata.borrow().clone();
@@ -97,7 +97,7 @@ impl From<&Vlan> for Vec<u8> {
     }
 }
 
-impl Vlan {
+impl<V: Value> Vlan<V> {
     // off is the offset of the VLAN header when it is encapsulated in
     // another protocol. For example, if the VLAN header is encapsulated
     // in an 802.1ad QinQ header, then off is the offset of the QinQ header.
@@ -126,59 +126,59 @@ impl Vlan {
     pub fn get_ethertype_raw(&self) -> EtherType {
         self.header.borrow().ethertype.clone()
     }
-    pub fn get_priority(&self) -> Rc<Object> {
+    pub fn get_priority(&self) -> Rc<V> {
         let priority: u8 = self.header.borrow().priority.clone().into();
-        Rc::new(Object::Integer(priority as i64))
+        Rc::new(V::integer(priority as i64))
     }
-    pub fn get_dei(&self) -> Rc<Object> {
-        Rc::new(Object::Bool(self.header.borrow().dei))
+    pub fn get_dei(&self) -> Rc<V> {
+        Rc::new(V::boolean(self.header.borrow().dei))
     }
-    pub fn get_vlan_id(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().vlan_id as i64))
+    pub fn get_vlan_id(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().vlan_id as i64))
     }
-    pub fn get_ethertype(&self) -> Rc<Object> {
-        Rc::new(Object::Integer(self.header.borrow().ethertype.0 as i64))
+    pub fn get_ethertype(&self) -> Rc<V> {
+        Rc::new(V::integer(self.header.borrow().ethertype.0 as i64))
     }
-    pub fn set_priority(&self, priority: Rc<Object>) -> Result<(), String> {
-        match priority.as_ref() {
-            Object::Integer(priority) => {
-                if *priority < 0 || *priority > 7 {
+    pub fn set_priority(&self, priority: Rc<V>) -> Result<(), String> {
+        match priority.as_integer() {
+            Some(priority) => {
+                if !(0..=7).contains(&priority) {
                     return Err("Invalid value for VLAN property priority".to_string());
                 }
-                self.header.borrow_mut().priority = ClassOfService(*priority as u8);
+                self.header.borrow_mut().priority = ClassOfService(priority as u8);
                 Ok(())
             }
             _ => Err("Invalid value for VLAN property priority".to_string()),
         }
     }
-    pub fn set_dei(&self, dei: Rc<Object>) -> Result<(), String> {
-        match dei.as_ref() {
-            Object::Bool(dei) => {
-                self.header.borrow_mut().dei = *dei;
+    pub fn set_dei(&self, dei: Rc<V>) -> Result<(), String> {
+        match dei.as_bool() {
+            Some(dei) => {
+                self.header.borrow_mut().dei = dei;
                 Ok(())
             }
             _ => Err("Invalid value for VLAN property dei".to_string()),
         }
     }
-    pub fn set_vlan_id(&self, vlan_id: Rc<Object>) -> Result<(), String> {
-        match vlan_id.as_ref() {
-            Object::Integer(vlan_id) => {
-                if *vlan_id < 0 || *vlan_id > 4095 {
+    pub fn set_vlan_id(&self, vlan_id: Rc<V>) -> Result<(), String> {
+        match vlan_id.as_integer() {
+            Some(vlan_id) => {
+                if !(0..=4095).contains(&vlan_id) {
                     return Err("Invalid value for VLAN property vlan_id".to_string());
                 }
-                self.header.borrow_mut().vlan_id = *vlan_id as u16;
+                self.header.borrow_mut().vlan_id = vlan_id as u16;
                 Ok(())
             }
             _ => Err("Invalid value for VLAN property vlan_id".to_string()),
         }
     }
-    pub fn set_ethertype(&self, ethertype: Rc<Object>) -> Result<(), String> {
-        match ethertype.as_ref() {
-            Object::Integer(ethertype) => {
-                if *ethertype < 0 || *ethertype > 65535 {
+    pub fn set_ethertype(&self, ethertype: Rc<V>) -> Result<(), String> {
+        match ethertype.as_integer() {
+            Some(ethertype) => {
+                if !(0..=65535).contains(&ethertype) {
                     return Err("Invalid value for VLAN property ethertype".to_string());
                 }
-                self.header.borrow_mut().ethertype = EtherType(*ethertype as u16);
+                self.header.borrow_mut().ethertype = EtherType(ethertype as u16);
                 Ok(())
             }
             _ => Err("Invalid value for VLAN property ethertype".to_string()),
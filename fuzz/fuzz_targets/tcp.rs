@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use p2sh_fuzz::Layer;
+
+fuzz_target!(|data: &[u8]| {
+    p2sh_fuzz::dissect(Layer::Tcp, data);
+});
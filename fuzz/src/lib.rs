@@ -0,0 +1,108 @@
+//! Harnesses of the fuzz targets. Each takes arbitrary bytes and must not
+//! panic; errors are expected and ignored.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use p2sh_core::builtins::pcap::{PcapPacket, PcapPacketHeader};
+use p2sh_core::compiler::Compiler;
+use p2sh_core::embed::PacketExpr;
+use p2sh_core::parser::Parser;
+use p2sh_core::scanner::token::TokenType;
+use p2sh_core::scanner::Scanner;
+
+/// The layer of a packet the fuzzed bytes start at. The layers below it
+/// are filled in so that the bytes are dissected as that layer.
+#[derive(Debug, Clone, Copy)]
+pub enum Layer {
+    Ethernet,
+    Ipv4,
+    Ipv6,
+    Tcp,
+    Udp,
+}
+
+/// Scan the bytes as the source of a script up to the end
+pub fn scan(data: &[u8]) {
+    let source = String::from_utf8_lossy(data);
+    let mut scanner = Scanner::new(&source);
+    while scanner.next_token().ttype != TokenType::Eof {}
+}
+
+/// Parse the bytes as a script and compile it if it parses
+pub fn parse(data: &[u8]) {
+    let source = String::from_utf8_lossy(data);
+    let mut parser = Parser::new(Scanner::new(&source));
+    let program = parser.parse_program();
+    if parser.parse_errors().is_empty() {
+        let _ = Compiler::new().compile(program);
+    }
+}
+
+// Expressions decoding every layer of a packet and reading their properties
+const PACKET_EXPRS: &[&str] = &[
+    "str($0)",
+    "str($1)",
+    "str($2)",
+    "str($3)",
+    "str($4)",
+    "str($5)",
+    "str($6)",
+    "str(props($0, \"*\"))",
+    "str(to_map($0))",
+    "decode($0, \"json\")",
+];
+
+thread_local! {
+    static EXPRS: RefCell<Vec<PacketExpr>> = RefCell::new(
+        PACKET_EXPRS
+            .iter()
+            .map(|source| PacketExpr::compile(source).expect("invalid fuzz expression"))
+            .collect(),
+    );
+}
+
+/// Dissect the bytes as the given layer of a packet, read all of its
+/// properties and write it back out
+pub fn dissect(layer: Layer, data: &[u8]) {
+    let mut bytes = headers(layer, data.len());
+    bytes.extend_from_slice(data);
+    let header = PcapPacketHeader {
+        ts_sec: 0,
+        ts_usec: 0,
+        caplen: bytes.len() as u32,
+        wirelen: bytes.len() as u32,
+    };
+    let pkt = Rc::new(PcapPacket::new(header, bytes));
+    EXPRS.with(|exprs| {
+        for expr in exprs.borrow_mut().iter_mut() {
+            let _ = expr.eval(pkt.clone());
+        }
+    });
+    let _: Vec<u8> = pkt.as_ref().into();
+}
+
+// Headers of the layers below 'layer' for a payload of 'len' bytes
+fn headers(layer: Layer, len: usize) -> Vec<u8> {
+    let ethernet = |ethertype: u16| {
+        let mut bytes = vec![0x02, 0, 0, 0, 0, 1, 0x02, 0, 0, 0, 0, 2];
+        bytes.extend_from_slice(&ethertype.to_be_bytes());
+        bytes
+    };
+    let ipv4 = |proto: u8| {
+        let mut bytes = ethernet(0x0800);
+        let totlen = (20 + len).min(u16::MAX as usize) as u16;
+        bytes.extend_from_slice(&[0x45, 0]);
+        bytes.extend_from_slice(&totlen.to_be_bytes());
+        bytes.extend_from_slice(&[0, 0, 0, 0, 64, proto, 0, 0]);
+        bytes.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        bytes
+    };
+    match layer {
+        Layer::Ethernet => Vec::new(),
+        Layer::Ipv4 => ethernet(0x0800),
+        Layer::Ipv6 => ethernet(0x86dd),
+        Layer::Tcp => ipv4(6),
+        Layer::Udp => ipv4(17),
+    }
+}
@@ -1,6 +0,0 @@
-pub mod functions;
-pub mod pcap;
-pub mod print;
-pub mod protocols;
-pub mod tests;
-pub mod variables;
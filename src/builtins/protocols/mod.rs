@@ -1,10 +0,0 @@
-pub mod error;
-pub mod ethernet;
-pub mod ipv4;
-pub mod ipv4addr;
-pub mod ipv6;
-pub mod ipv6addr;
-pub mod macaddress;
-pub mod tcp;
-pub mod udp;
-pub mod vlan;
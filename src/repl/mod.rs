@@ -1,2 +0,0 @@
-pub mod completion;
-pub mod prompt;
@@ -1,5 +0,0 @@
-pub mod error;
-pub mod frame;
-pub mod interpreter;
-pub mod pktprop;
-pub mod tests;